@@ -0,0 +1,74 @@
+//! Benchmarks of the queries behind the library views, run with `cargo bench`.
+//!
+//! Phonique has no library target, so the storage module is compiled in directly.
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+
+#[allow(dead_code)]
+#[path = "../src/storage/mod.rs"]
+mod storage;
+
+use storage::{
+    AudioFileDescriptor, AudioFileKind, Storage,
+    local::{LocalStorage, init_storage},
+};
+
+const LIBRARY_SIZE: usize = 5_000;
+
+fn library() -> LocalStorage {
+    let mut storage = init_storage(":memory:").unwrap();
+    let files: Vec<AudioFileDescriptor> = (0..LIBRARY_SIZE)
+        .map(|i| AudioFileDescriptor {
+            title: format!("Track {i}"),
+            artist: format!("Artist {}", i % 200),
+            album: format!("Album {}", i % 500),
+            year: 2000 + (i % 25) as u16,
+            genre: format!("Genre {}", i % 20),
+            duration: 180 + (i % 240) as u64,
+            bitrate: 1411,
+            sample_rate: 44_100,
+            lossless: Some(true),
+            release_date: String::new(),
+            path: format!("/music/{i}.flac"),
+            artwork: None,
+            kind: AudioFileKind::LocalFile,
+        })
+        .collect();
+    let ids = storage.bulk_append_to_library(&files).unwrap();
+    for id in ids.iter().step_by(10) {
+        storage.append_like(*id).unwrap();
+    }
+    let tag = storage.create_tag("mood/dark").unwrap();
+    for id in ids.iter().step_by(7) {
+        storage.append_to_tag(tag, *id).unwrap();
+    }
+    storage
+}
+
+fn queries(c: &mut Criterion) {
+    let storage = library();
+    let ids: Vec<i64> = (1..=500).collect();
+    let paths: Vec<String> = (0..500).map(|i| format!("/music/{i}.flac")).collect();
+
+    c.bench_function("read_library", |b| {
+        b.iter(|| storage.read_library().unwrap())
+    });
+    c.bench_function("read_library_from_ids", |b| {
+        b.iter(|| storage.read_library_from_ids(black_box(&ids)).unwrap())
+    });
+    c.bench_function("read_likes", |b| b.iter(|| storage.read_likes().unwrap()));
+    c.bench_function("is_liked", |b| {
+        b.iter(|| storage.is_liked(black_box(42)).unwrap())
+    });
+    c.bench_function("read_tag_prefix", |b| {
+        b.iter(|| storage.read_tag_prefix(black_box("mood")).unwrap())
+    });
+    c.bench_function("filter_library_by_paths", |b| {
+        b.iter(|| storage.filter_library_by_paths(black_box(&paths)).unwrap())
+    });
+}
+
+criterion_group!(benches, queries);
+criterion_main!(benches);
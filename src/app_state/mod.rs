@@ -3,7 +3,7 @@ use std::{
     io::{BufReader, Cursor, Read},
 };
 
-use crate::storage;
+use crate::{jellyfin, offline, podcasts, remote, storage, subsonic, webdav};
 
 pub mod state_impl;
 
@@ -16,8 +16,185 @@ pub enum Section {
     Library,
     Favorites,
     RecentlyPlayed,
+    // playables queued by hand to play next, only kept for the session
+    Queue,
     Playlist(i64),
     Tag(i64),
+    // tracks carrying all of these tags, always more than one
+    Tags(Vec<i64>),
+    // tracks of a tag group, e.g. `mood` for `mood/dark` and `mood/happy`
+    TagPrefix(String),
+    // the library searched for these words, best matches first
+    Search(String),
+    Podcast(i64),
+    ImportHistory,
+    // playables added by one import batch
+    Import(i64),
+    // the page of the album of this name
+    Album(String),
+    // the songs mirrored from the Subsonic or Jellyfin server at this address
+    Server(String),
+    // soft deleted playables, waiting to be restored or purged
+    RecentlyDeleted,
+    // saved states of the library and how it changed since
+    Snapshots,
+    // format conversion settings and the queue of conversions
+    Conversions,
+    // checks a playlist against what CDJs play and copies it onto a USB drive
+    CdjExport,
+    // splits a digitized record side into tracks
+    SplitRecording,
+    // records an input device into the library
+    Recorder,
+    // read-only playlists computed from the history and likes
+    Auto(storage::AutoPlaylist),
+    // never played and long forgotten tracks
+    Insights,
+    // genres suggested for the tracks without one, to accept or reject
+    GenreSuggestions,
+    // played before, but not in this many months
+    NotPlayedFor(u32),
+    ScannerSettings,
+    // what plays next and how
+    PlaybackSettings,
+    // history retention and incognito listening
+    PrivacySettings,
+    // custom fields of the playables
+    FieldSettings,
+    // how playlists are written out, e.g. the copied tracklist
+    PlaylistSettings,
+    // sharing likes, tags and playlists with other instances on the network
+    SyncSettings,
+    // servers whose music is indexed, e.g. the WebDAV share of a NAS
+    ServerSettings,
+    // database statistics and maintenance
+    AdvancedSettings,
+    // what the checks run on launch found, with what may fix it
+    Diagnostics,
+}
+
+impl Section {
+    pub fn has_tag(&self, tag_id: i64) -> bool {
+        match self {
+            Section::Tag(id) => *id == tag_id,
+            Section::Tags(ids) => ids.contains(&tag_id),
+            _ => false,
+        }
+    }
+
+    /// Adds `tag_id` to the selected tags or removes it when already selected.
+    /// The last selected tag can not be removed.
+    pub fn toggle_tag(&self, tag_id: i64) -> Section {
+        let mut ids = match self {
+            Section::Tag(id) => vec![*id],
+            Section::Tags(ids) => ids.clone(),
+            _ => Vec::new(),
+        };
+        if let Some(position) = ids.iter().position(|id| *id == tag_id) {
+            if ids.len() > 1 {
+                ids.remove(position);
+            }
+        } else {
+            ids.push(tag_id);
+        }
+        match ids.as_slice() {
+            [id] => Section::Tag(*id),
+            _ => Section::Tags(ids),
+        }
+    }
+
+    /// How the section is stored to be opened again on the next start, None for the tools which
+    /// start over each time.
+    pub fn to_setting(&self) -> Option<String> {
+        let value = match self {
+            Section::ListenNow => "listen_now".to_string(),
+            Section::Browse => "browse".to_string(),
+            Section::Library => "library".to_string(),
+            Section::Favorites => "favorites".to_string(),
+            Section::RecentlyPlayed => "recently_played".to_string(),
+            Section::Playlist(id) => format!("playlist:{id}"),
+            Section::Tag(id) => format!("tag:{id}"),
+            Section::Tags(ids) => format!(
+                "tags:{}",
+                ids.iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            ),
+            Section::TagPrefix(prefix) => format!("tag_prefix:{prefix}"),
+            Section::Podcast(id) => format!("podcast:{id}"),
+            Section::ImportHistory => "import_history".to_string(),
+            Section::Import(id) => format!("import:{id}"),
+            Section::Album(name) => format!("album:{name}"),
+            Section::Server(url) => format!("server:{url}"),
+            Section::RecentlyDeleted => "recently_deleted".to_string(),
+            Section::Auto(storage::AutoPlaylist::MostPlayed) => "auto:most_played".to_string(),
+            Section::Auto(storage::AutoPlaylist::RecentlyLiked) => {
+                "auto:recently_liked".to_string()
+            }
+            Section::Auto(storage::AutoPlaylist::NeverPlayed) => "auto:never_played".to_string(),
+            Section::Auto(storage::AutoPlaylist::TopRated) => "auto:top_rated".to_string(),
+            Section::NotPlayedFor(months) => format!("not_played_for:{months}"),
+            Section::PlaybackSettings => "playback_settings".to_string(),
+            Section::PrivacySettings => "privacy_settings".to_string(),
+            Section::FieldSettings => "field_settings".to_string(),
+            Section::PlaylistSettings => "playlist_settings".to_string(),
+            Section::AdvancedSettings => "advanced_settings".to_string(),
+            Section::Queue
+            | Section::Search(_)
+            | Section::Snapshots
+            | Section::Conversions
+            | Section::CdjExport
+            | Section::SplitRecording
+            | Section::Recorder
+            | Section::Insights
+            | Section::GenreSuggestions
+            | Section::ScannerSettings
+            | Section::SyncSettings
+            | Section::ServerSettings
+            | Section::Diagnostics => return None,
+        };
+        Some(value)
+    }
+
+    pub fn from_setting(value: &str) -> Option<Section> {
+        let section = match value.split_once(':') {
+            None => match value {
+                "listen_now" => Section::ListenNow,
+                "browse" => Section::Browse,
+                "library" => Section::Library,
+                "favorites" => Section::Favorites,
+                "recently_played" => Section::RecentlyPlayed,
+                "import_history" => Section::ImportHistory,
+                "recently_deleted" => Section::RecentlyDeleted,
+                "playback_settings" => Section::PlaybackSettings,
+                "privacy_settings" => Section::PrivacySettings,
+                "field_settings" => Section::FieldSettings,
+                "playlist_settings" => Section::PlaylistSettings,
+                "advanced_settings" => Section::AdvancedSettings,
+                _ => return None,
+            },
+            Some(("playlist", id)) => Section::Playlist(id.parse().ok()?),
+            Some(("tag", id)) => Section::Tag(id.parse().ok()?),
+            Some(("tags", ids)) => Section::Tags(
+                ids.split(',')
+                    .map(|id| id.parse().ok())
+                    .collect::<Option<Vec<_>>>()?,
+            ),
+            Some(("tag_prefix", prefix)) => Section::TagPrefix(prefix.to_string()),
+            Some(("podcast", id)) => Section::Podcast(id.parse().ok()?),
+            Some(("import", id)) => Section::Import(id.parse().ok()?),
+            Some(("album", name)) => Section::Album(name.to_string()),
+            Some(("server", url)) => Section::Server(url.to_string()),
+            Some(("auto", "most_played")) => Section::Auto(storage::AutoPlaylist::MostPlayed),
+            Some(("auto", "recently_liked")) => Section::Auto(storage::AutoPlaylist::RecentlyLiked),
+            Some(("auto", "never_played")) => Section::Auto(storage::AutoPlaylist::NeverPlayed),
+            Some(("auto", "top_rated")) => Section::Auto(storage::AutoPlaylist::TopRated),
+            Some(("not_played_for", months)) => Section::NotPlayedFor(months.parse().ok()?),
+            _ => return None,
+        };
+        Some(section)
+    }
 }
 
 impl Default for Section {
@@ -34,19 +211,52 @@ impl std::fmt::Display for Section {
             Section::Library => f.write_str("Library"),
             Section::Favorites => f.write_str("Favorites"),
             Section::RecentlyPlayed => f.write_str("Recently Played"),
+            Section::Queue => f.write_str("Queue"),
             Section::Playlist(id) => f.write_fmt(format_args!("Playlist {id}")),
             Section::Tag(id) => f.write_fmt(format_args!("Tag {id}")),
+            Section::Tags(ids) => f.write_fmt(format_args!("Tags {ids:?}")),
+            Section::TagPrefix(prefix) => f.write_fmt(format_args!("Tags under {prefix}")),
+            Section::Search(query) => f.write_fmt(format_args!("Search for {query}")),
+            Section::Podcast(id) => f.write_fmt(format_args!("Podcast {id}")),
+            Section::ImportHistory => f.write_str("Import History"),
+            Section::Import(id) => f.write_fmt(format_args!("Import {id}")),
+            Section::Album(name) => f.write_fmt(format_args!("Album {name}")),
+            Section::Server(url) => f.write_fmt(format_args!("Server {url}")),
+            Section::RecentlyDeleted => f.write_str("Recently Deleted"),
+            Section::Snapshots => f.write_str("Snapshots"),
+            Section::Conversions => f.write_str("Conversions"),
+            Section::CdjExport => f.write_str("Export for CDJ"),
+            Section::SplitRecording => f.write_str("Split Recording"),
+            Section::Recorder => f.write_str("Recorder"),
+            Section::Auto(playlist) => f.write_fmt(format_args!("{playlist}")),
+            Section::Insights => f.write_str("Insights"),
+            Section::GenreSuggestions => f.write_str("Genre Suggestions"),
+            Section::NotPlayedFor(months) => {
+                f.write_fmt(format_args!("Not played in {months} months"))
+            }
+            Section::ScannerSettings => f.write_str("Scanner Settings"),
+            Section::PlaybackSettings => f.write_str("Playback Settings"),
+            Section::PrivacySettings => f.write_str("Privacy Settings"),
+            Section::FieldSettings => f.write_str("Field Settings"),
+            Section::PlaylistSettings => f.write_str("Playlist Settings"),
+            Section::SyncSettings => f.write_str("Sync Settings"),
+            Section::ServerSettings => f.write_str("Server Settings"),
+            Section::AdvancedSettings => f.write_str("Advanced Settings"),
+            Section::Diagnostics => f.write_str("Diagnostics"),
         }
     }
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PlayableKind {
     LocalFile,
     GoogleDrive,
     Dropbox,
     Youtube,
     Stream,
+    WebDav,
+    Subsonic,
+    Jellyfin,
 }
 
 impl From<storage::AudioFileKind> for PlayableKind {
@@ -57,6 +267,9 @@ impl From<storage::AudioFileKind> for PlayableKind {
             storage::AudioFileKind::Dropbox => PlayableKind::Dropbox,
             storage::AudioFileKind::Youtube => PlayableKind::Youtube,
             storage::AudioFileKind::Stream => PlayableKind::Stream,
+            storage::AudioFileKind::WebDav => PlayableKind::WebDav,
+            storage::AudioFileKind::Subsonic => PlayableKind::Subsonic,
+            storage::AudioFileKind::Jellyfin => PlayableKind::Jellyfin,
         }
     }
 }
@@ -70,6 +283,7 @@ pub trait AudioPlayable: std::fmt::Debug + Send + Sync {
     fn get_artist(&self) -> &str;
     fn get_date_added(&self) -> &i64;
     fn get_genre(&self) -> &str;
+    fn get_label(&self) -> &str;
     fn get_duration(&self) -> u64;
     fn get_path(&self) -> &str;
     fn get_album_art(&self) -> &Option<Vec<u8>>;
@@ -102,12 +316,32 @@ impl AudioPlayable for storage::Playable {
     }
 
     fn stream(&self) -> Result<Cursor<Vec<u8>>, std::io::Error> {
-        let file = File::open(self.get_path())?;
-        let mut reader = BufReader::new(file);
-        let mut buffer = Vec::new();
-        reader.read_to_end(&mut buffer)?;
-
-        Ok(Cursor::new(buffer))
+        // made available offline, streamed again when the file is gone
+        if let Some(path) = offline::copy_of(self.id)
+            && let Ok(audio) = read_file(&path)
+        {
+            return Ok(audio);
+        }
+        // streams liked from outside the library are fetched whole, like podcast episodes
+        if self.type_id == storage::AudioFileKind::Stream && self.source_url.starts_with("http") {
+            return Ok(Cursor::new(podcasts::fetch_audio(&self.source_url)?));
+        }
+        // songs of a server whose album is in the local files too play from the local file
+        if remote::is_remote(self.type_id)
+            && let Some(path) = remote::local_copy(&self.source_url)
+        {
+            return read_file(&path);
+        }
+        if self.type_id == storage::AudioFileKind::WebDav {
+            return Ok(Cursor::new(webdav::fetch_audio(&self.source_url)?));
+        }
+        if self.type_id == storage::AudioFileKind::Subsonic {
+            return Ok(Cursor::new(subsonic::fetch_audio(&self.source_url)?));
+        }
+        if self.type_id == storage::AudioFileKind::Jellyfin {
+            return Ok(Cursor::new(jellyfin::fetch_audio(&self.source_url)?));
+        }
+        read_file(self.get_path())
     }
 
     fn get_date_added(&self) -> &i64 {
@@ -122,6 +356,10 @@ impl AudioPlayable for storage::Playable {
         }
     }
 
+    fn get_label(&self) -> &str {
+        self.label.as_deref().unwrap_or_default()
+    }
+
     fn get_path(&self) -> &str {
         &self.source_url
     }
@@ -142,6 +380,80 @@ impl AudioPlayable for storage::Playable {
             storage::AudioFileKind::Dropbox => PlayableKind::Dropbox,
             storage::AudioFileKind::Youtube => PlayableKind::Youtube,
             storage::AudioFileKind::Stream => PlayableKind::Stream,
+            storage::AudioFileKind::WebDav => PlayableKind::WebDav,
+            storage::AudioFileKind::Subsonic => PlayableKind::Subsonic,
+            storage::AudioFileKind::Jellyfin => PlayableKind::Jellyfin,
+        }
+    }
+}
+
+fn read_file(path: &str) -> Result<Cursor<Vec<u8>>, std::io::Error> {
+    let mut buffer = Vec::new();
+    BufReader::new(File::open(path)?).read_to_end(&mut buffer)?;
+    Ok(Cursor::new(buffer))
+}
+
+static NO_ARTWORK: Option<Vec<u8>> = None;
+
+impl AudioPlayable for storage::Episode {
+    // episodes are not part of the library
+    fn get_id(&self) -> PlayableId {
+        -1
+    }
+
+    fn get_title(&self) -> &str {
+        self.title.as_str()
+    }
+
+    fn get_album(&self) -> &str {
+        ""
+    }
+
+    fn get_artist(&self) -> &str {
+        ""
+    }
+
+    fn get_date_added(&self) -> &i64 {
+        &self.published
+    }
+
+    fn get_genre(&self) -> &str {
+        ""
+    }
+
+    fn get_label(&self) -> &str {
+        ""
+    }
+
+    fn get_duration(&self) -> u64 {
+        self.duration.try_into().unwrap_or(0)
+    }
+
+    fn get_path(&self) -> &str {
+        if let Some(path) = &self.local_path {
+            path.as_str()
+        } else {
+            self.audio_url.as_str()
+        }
+    }
+
+    fn get_album_art(&self) -> &Option<Vec<u8>> {
+        &NO_ARTWORK
+    }
+
+    fn get_kind(&self) -> PlayableKind {
+        if self.local_path.is_some() {
+            PlayableKind::LocalFile
+        } else {
+            PlayableKind::Stream
+        }
+    }
+
+    fn stream(&self) -> Result<Cursor<Vec<u8>>, std::io::Error> {
+        if let Some(path) = &self.local_path {
+            read_file(path)
+        } else {
+            Ok(Cursor::new(podcasts::fetch_audio(&self.audio_url)?))
         }
     }
 }
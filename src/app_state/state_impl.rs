@@ -1,23 +1,181 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
 use directories::UserDirs;
 use log::{error, info};
-use rand::Rng;
+use rand::{Rng, seq::SliceRandom};
 use thiserror::Error;
 
 use crate::{
     app_state::{AudioPlayable, PlayableKind, Section},
-    audio_scanner::{ScannedFile, ScannedKind},
+    artwork::{self, Lookup},
+    audio_scanner::{DEFAULT_ARTWORK_MAX_SIZE, ScanOptions, ScannedFile, ScannedKind},
+    chapters::{self, Chapter},
+    clipboard::{self, DEFAULT_TRACKLIST_TEMPLATE},
+    convert::{self, ConvertOptions, Format},
+    discogs::{Credentials, OAuthTokens, Release},
+    equalizer::{EqCurves, Preset},
+    genre_inference::{self, Suggestion},
+    jellyfin,
+    m3u::PlaylistFile,
+    offline,
+    podcasts::{Feed, FeedEpisode},
+    recorder::RecordFormat,
+    remote::{self, Mirror},
+    resampler::ResampleQuality,
+    schedule::{self, Alarm},
+    secrets,
+    shared_playlist::{self, SharedPlaylist, SharedTrack},
+    snapshots::{self, SnapshotDiff},
     storage::{
-        self, AudioFileDescriptor, AudioFileKind, DummyStorage, Playable, Playlist, Storage, Tag,
-        local::init_storage,
+        self, Album, AsyncStorage, AudioFileDescriptor, AudioFileKind, AutoPlaylist, Bookmark,
+        CustomField, DatabaseStats, DummyStorage, Episode, EpisodeDescriptor, ImportBatch,
+        LinkKind, MAX_RATING, NewImport, Playable, PlayableLink, Playlist, PlaylistPlayback,
+        PlaylistRule, Podcast, Snapshot, Storage, Tag, Watch, WatchKind, WatchRelease,
+        WatchReleaseDescriptor, local::init_storage, worker::Worker,
     },
+    subsonic,
+    sync::{self, Peer, SyncDocument, SyncServer, SyncTrack},
+    theme::Theme,
+    util::{date_to_timestamp, local_offset, timestamp_to_str, unix_now},
+    volume_trim::{TrimTarget, VolumeTrims},
+    webdav,
 };
 
 use super::PlayableId;
 
+const TRACKLIST_TEMPLATE_KEY: &str = "tracklist_template";
+// one glob per line
+const SCAN_EXCLUDE_KEY: &str = "scan_exclude";
+const SCAN_MIN_DURATION_KEY: &str = "scan_min_duration";
+const SCAN_FOLLOW_SYMLINKS_KEY: &str = "scan_follow_symlinks";
+const SCAN_SKIP_HIDDEN_KEY: &str = "scan_skip_hidden";
+// empty when there is no limit
+const SCAN_MAX_DEPTH_KEY: &str = "scan_max_depth";
+// empty to let the scanner pick
+const SCAN_THREADS_KEY: &str = "scan_threads";
+// empty to keep artwork at its original size, unset for the default
+const SCAN_ARTWORK_MAX_SIZE_KEY: &str = "scan_artwork_max_size";
+// folders whose new files are imported as they show up, one per line
+const WATCH_FOLDERS_KEY: &str = "watch_folders";
+// files and folders added from the File menu or dropped on the window, newest first, one per
+// line
+const RECENT_FILES_KEY: &str = "recent_files";
+const RECENT_FILES_LIMIT: usize = 8;
+// the WebDAV shares whose music is indexed, with their accounts, as JSON
+const WEBDAV_SERVERS_KEY: &str = "webdav_servers";
+// the Subsonic servers mirrored into the library, with their accounts, as JSON. The passwords
+// are kept in the keychain
+const SUBSONIC_SERVERS_KEY: &str = "subsonic_servers";
+// the Jellyfin servers signed in to, with their tokens, as JSON
+const JELLYFIN_SERVERS_KEY: &str = "jellyfin_servers";
+// what playback does after the last playable of the list, see `ContextEnd`
+const CONTEXT_END_KEY: &str = "context_end";
+// what closing the window does while playing, see `CloseBehavior`
+const CLOSE_BEHAVIOR_KEY: &str = "close_behavior";
+// how the track list is ordered as `column:asc` or `column:desc`, empty for the section's order
+const LIST_SORT_KEY: &str = "list_sort";
+// the optional columns shown in the track list, comma separated
+const LIST_COLUMNS_KEY: &str = "list_columns";
+// widths of the track list columns resized by hand, by view and column, as JSON
+const LIST_WIDTHS_KEY: &str = "list_widths";
+// track lists are shown as a grid of their albums while set to "true"
+const ALBUM_GRID_KEY: &str = "album_grid";
+// "light" or "dark"
+const THEME_KEY: &str = "theme";
+// length of the volume ramp on pause, resume and skip, in milliseconds, 0 to cut hard
+const FADE_KEY: &str = "fade_ms";
+const DEFAULT_FADE_MS: u64 = 150;
+// seconds the end of a track overlaps the start of the next, 0 follows it without a gap
+const CROSSFADE_KEY: &str = "crossfade_secs";
+pub const MAX_CROSSFADE_SECS: u64 = 12;
+// delay of the output device, e.g. Bluetooth headphones, taken off the shown position
+const OUTPUT_LATENCY_KEY: &str = "output_latency_ms";
+// opens the output device at the format of each track instead of the shared one
+const MATCH_SOURCE_RATE_KEY: &str = "match_source_rate";
+// hovering a track in Browse plays a snippet of it, on unless set to "false"
+const HOVER_PREVIEW_KEY: &str = "hover_preview";
+// how tracks are converted when the device runs at another sample rate, see `ResampleQuality`
+const RESAMPLE_QUALITY_KEY: &str = "resample_quality";
+// gain per kind of source and per station, see `VolumeTrims`
+const VOLUME_TRIMS_KEY: &str = "volume_trims";
+// pauses quieter than this many dB below full scale are cut short, unset plays them whole
+const SKIP_SILENCE_KEY: &str = "skip_silence_db";
+// the equalizer curve of each output device, see `EqCurves`
+const EQUALIZER_KEY: &str = "equalizer_curves";
+// tracks at least this long resume where they were left, in minutes, 0 turns it off
+const LONG_TRACK_KEY: &str = "long_track_minutes";
+pub const DEFAULT_LONG_TRACK_MINUTES: u32 = 20;
+// days a play stays in the history, empty or unset to keep it forever
+const HISTORY_RETENTION_KEY: &str = "history_retention_days";
+// days a deleted playable stays in Recently Deleted, empty to keep it until purged by hand,
+// unset for the default
+const DELETED_RETENTION_KEY: &str = "deleted_retention_days";
+const DEFAULT_DELETED_RETENTION_DAYS: u32 = 30;
+// months without a play before the insights count a track as forgotten
+const DEFAULT_INSIGHT_MONTHS: u32 = 12;
+const SECONDS_PER_MONTH: i64 = 30 * 86_400;
+// albums are in "this week" within this many days of their release date, either side
+const THIS_WEEK_DAYS: i64 = 3;
+// playables listed in Recently Played, each once at its latest play
+const RECENTLY_PLAYED_LIMIT: usize = 200;
+// playables read on the UI thread when the library is shown, the rest follows a page at a time
+const LIBRARY_PAGE: usize = 1000;
+// how `Convert…` encodes the selection, see `ConvertOptions`
+const CONVERT_FORMAT_KEY: &str = "convert_format";
+const CONVERT_BITRATE_KEY: &str = "convert_bitrate";
+// unset for a folder next to the music folder
+const CONVERT_FOLDER_KEY: &str = "convert_folder";
+const CONVERT_TEMPLATE_KEY: &str = "convert_template";
+const CONVERT_REPLACE_KEY: &str = "convert_replace";
+// where the track list was left, restored on the next start
+const SESSION_SECTION_KEY: &str = "session_section";
+const SESSION_SCROLL_KEY: &str = "session_scroll";
+const SESSION_SELECTION_KEY: &str = "session_selection";
+// geometry of the main window, see `WindowGeometry`
+const WINDOW_SIZE_KEY: &str = "window_size";
+const WINDOW_POSITION_KEY: &str = "window_position";
+const WINDOW_MAXIMIZED_KEY: &str = "window_maximized";
+const PANE_RATIO_KEY: &str = "pane_ratio";
+// the alarm clock, see `Alarm`
+const ALARM_ENABLED_KEY: &str = "alarm_enabled";
+const ALARM_TIME_KEY: &str = "alarm_time";
+const ALARM_PLAYLIST_KEY: &str = "alarm_playlist";
+const ALARM_RAMP_KEY: &str = "alarm_ramp";
+const ALARM_DAILY_KEY: &str = "alarm_daily";
+// name of the device the recorder last recorded from
+const RECORD_SOURCE_KEY: &str = "record_source";
+const RECORD_FORMAT_KEY: &str = "record_format";
+// shares the likes, tags and playlists with other instances on the network while set
+const SYNC_SHARING_KEY: &str = "sync_sharing";
+// the code other instances give to read the shared library, kept in the keychain
+const SYNC_PAIRING_KEY: &str = "sync_pairing_code";
+// the codes of the instances synced from as JSON, by the name they share under, kept in the
+// keychain
+const SYNC_PEER_CODES_KEY: &str = "sync_peer_codes";
+// personal access token for searching Discogs in Browse, kept in the keychain
+const DISCOGS_TOKEN_KEY: &str = "discogs_token";
+// the tokens of a Discogs connection made with OAuth as JSON, preferred over the personal token,
+// kept in the keychain
+const DISCOGS_OAUTH_KEY: &str = "discogs_oauth";
+// how far playback has to move before a resume position is written again, in seconds
+const POSITION_SAVE_STEP: u64 = 10;
+// how often a new Discogs watch is checked, in hours
+pub const DEFAULT_WATCH_INTERVAL: u32 = 24;
+
 #[derive(Debug, Error, PartialEq)]
 pub enum StateError {
     #[error("StorageError: {0}")]
     StorageError(#[from] storage::StorageError),
+    #[error("SyncError: {0}")]
+    SyncError(String),
+    #[error("SecretError: {0}")]
+    SecretError(#[from] secrets::SecretError),
+    #[error("JsonError: {0}")]
+    JsonError(#[from] serde_json::Error),
 }
 
 pub type Result<T> = std::result::Result<T, StateError>;
@@ -27,70 +185,829 @@ pub struct PlaylistNode {
     pub children: Vec<PlaylistNode>,
 }
 
+pub struct TagNode {
+    // last segment of the path
+    pub name: String,
+    pub path: String,
+    // None for groups which only exist through their nested tags
+    pub tag: Option<Tag>,
+    pub children: Vec<TagNode>,
+}
+
+// What a mutation did to the current section. Applied to the loaded playables in place so
+// that a like or an append does not read the whole section again.
+enum Change {
+    // playables which now belong to the section
+    Added(Vec<PlayableId>),
+    // playables gone from the section, or from the whole library
+    Removed(Vec<PlayableId>),
+    // the section changed in a way that can not be patched, e.g. a new playlist order
+    Refresh,
+}
+
+pub struct LibraryInsights {
+    pub months: u32,
+    pub never_played: Vec<Playable>,
+    // played before, but not in the last `months`
+    pub not_played: Vec<Playable>,
+    // tracks without a genre, see `genre_inference`
+    pub without_genre: usize,
+}
+
+impl Default for LibraryInsights {
+    fn default() -> Self {
+        Self {
+            months: DEFAULT_INSIGHT_MONTHS,
+            never_played: Vec::new(),
+            not_played: Vec::new(),
+            without_genre: 0,
+        }
+    }
+}
+
+/// Everything the song info panel shows about one playable.
+pub struct SongInfo {
+    // loaded with its artwork
+    pub playable: Playable,
+    pub tags: Vec<Tag>,
+    pub playlists: Vec<Playlist>,
+}
+
+/// A snapshot next to the library as it is now.
+pub struct SnapshotComparison {
+    pub snapshot: Snapshot,
+    // playables, tags and playlists in the library now
+    pub counts: (i64, i64, i64),
+    pub diff: SnapshotDiff,
+}
+
+/// The tracks of a list which share an album, one tile of the album grid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlbumGroup {
+    // empty for the tracks without an album
+    pub name: String,
+    // empty when the tracks are by more than one artist
+    pub artist: String,
+    pub tracks: usize,
+    // the track whose artwork stands for the album
+    pub cover: PlayableId,
+    // the earliest release date or year of its tracks
+    pub released: Option<String>,
+}
+
+/// The part of the library left to read after its first page is shown, a page at a time off
+/// the UI thread. The pages of a load overtaken by another load of the track list are dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LibraryLoad {
+    generation: u64,
+    // the id of the last playable read, the pages go by id
+    pub after: PlayableId,
+    pub limit: usize,
+}
+
+/// The scroll offset and selected rows of the track list when the app was closed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Session {
+    pub scroll_offset: f32,
+    // row indexes
+    pub selection: Vec<usize>,
+}
+
+/// Size and place of the main window and the share of its width the sidebar takes. None for
+/// what was never saved, the window opens with the defaults for those.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct WindowGeometry {
+    // width and height in logical pixels
+    pub size: Option<(f32, f32)>,
+    pub position: Option<(f32, f32)>,
+    pub maximized: bool,
+    pub pane_ratio: Option<f32>,
+}
+
+/// The playlist an imported shared playlist became and what of it is missing here.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedImport {
+    pub playlist_id: i64,
+    pub name: String,
+    pub matched: usize,
+    // artist and title of each track not found in the library
+    pub missing: Vec<String>,
+}
+
+/// What merging a peer's library changed, counted in tracks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncSummary {
+    pub liked: usize,
+    pub rated: usize,
+    pub tagged: usize,
+    pub added_to_playlists: usize,
+    // tracks of the peer found neither by path nor by content
+    pub unmatched: usize,
+}
+
+/// What happens when playback reaches the end of the list it walks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContextEnd {
+    Stop,
+    // starts the list over
+    #[default]
+    Repeat,
+    // keeps playing the library after the last played playable
+    Library,
+}
+
+impl ContextEnd {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ContextEnd::Stop => "stop",
+            ContextEnd::Repeat => "repeat",
+            ContextEnd::Library => "library",
+        }
+    }
+}
+
+impl From<&str> for ContextEnd {
+    fn from(s: &str) -> Self {
+        match s {
+            "stop" => ContextEnd::Stop,
+            "library" => ContextEnd::Library,
+            _ => ContextEnd::Repeat,
+        }
+    }
+}
+
+/// What closing the window does while something plays, it always quits otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CloseBehavior {
+    Quit,
+    // minimizes the window instead and playback carries on
+    KeepPlaying,
+    // asks whether to quit or keep playing
+    #[default]
+    Ask,
+}
+
+impl CloseBehavior {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CloseBehavior::Quit => "quit",
+            CloseBehavior::KeepPlaying => "keep_playing",
+            CloseBehavior::Ask => "ask",
+        }
+    }
+}
+
+impl From<&str> for CloseBehavior {
+    fn from(s: &str) -> Self {
+        match s {
+            "quit" => CloseBehavior::Quit,
+            "keep_playing" => CloseBehavior::KeepPlaying,
+            _ => CloseBehavior::Ask,
+        }
+    }
+}
+
+/// A column of the track list, by which it can be ordered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListColumn {
+    Title,
+    Album,
+    Genre,
+    Duration,
+    // optional, see `State::list_columns`
+    PlayCount,
+    LastPlayed,
+    Rating,
+    Released,
+    Year,
+    DateAdded,
+    // where the playable is played from, e.g. a local file or a stream
+    Kind,
+    // the value of a custom field, by its id
+    Field(i64),
+}
+
+// narrowest a column can be dragged to, in pixels
+pub const MIN_COLUMN_WIDTH: f32 = 60.0;
+
+impl ListColumn {
+    fn to_setting(self) -> String {
+        match self {
+            ListColumn::Title => "title".to_string(),
+            ListColumn::Album => "album".to_string(),
+            ListColumn::Genre => "genre".to_string(),
+            ListColumn::Duration => "duration".to_string(),
+            ListColumn::PlayCount => "plays".to_string(),
+            ListColumn::LastPlayed => "last_played".to_string(),
+            ListColumn::Rating => "rating".to_string(),
+            ListColumn::Released => "released".to_string(),
+            ListColumn::Year => "year".to_string(),
+            ListColumn::DateAdded => "date_added".to_string(),
+            ListColumn::Kind => "kind".to_string(),
+            ListColumn::Field(id) => format!("field_{id}"),
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        if let Some(id) = s.strip_prefix("field_") {
+            return id.parse().ok().map(ListColumn::Field);
+        }
+        match s {
+            "title" => Some(ListColumn::Title),
+            "album" => Some(ListColumn::Album),
+            "genre" => Some(ListColumn::Genre),
+            "duration" => Some(ListColumn::Duration),
+            "plays" => Some(ListColumn::PlayCount),
+            "last_played" => Some(ListColumn::LastPlayed),
+            "rating" => Some(ListColumn::Rating),
+            "released" => Some(ListColumn::Released),
+            "year" => Some(ListColumn::Year),
+            "date_added" => Some(ListColumn::DateAdded),
+            "kind" => Some(ListColumn::Kind),
+            _ => None,
+        }
+    }
+
+    /// The width of the column until it is resized, the title takes what is left.
+    pub fn default_width(self) -> f32 {
+        match self {
+            ListColumn::Album => 220.0,
+            ListColumn::Genre | ListColumn::LastPlayed | ListColumn::DateAdded => 150.0,
+            ListColumn::Year | ListColumn::PlayCount => 70.0,
+            _ => 110.0,
+        }
+    }
+}
+
+/// How the track list is ordered, ties keep the order of the section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortOrder {
+    pub column: ListColumn,
+    pub descending: bool,
+}
+
+impl SortOrder {
+    fn to_setting(self) -> String {
+        let direction = if self.descending { "desc" } else { "asc" };
+        format!("{}:{direction}", self.column.to_setting())
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        let (column, direction) = s.split_once(':')?;
+        Some(SortOrder {
+            column: ListColumn::parse(column)?,
+            descending: direction == "desc",
+        })
+    }
+
+    // `fields` holds the values of custom fields by playable and field
+    fn compare(
+        self,
+        a: &Playable,
+        b: &Playable,
+        fields: &HashMap<(PlayableId, i64), String>,
+    ) -> std::cmp::Ordering {
+        let text = |a: &str, b: &str| a.to_lowercase().cmp(&b.to_lowercase());
+        let ordering = match self.column {
+            ListColumn::Title => text(&a.title, &b.title),
+            ListColumn::Album => text(a.get_album(), b.get_album()),
+            ListColumn::Genre => text(a.get_genre(), b.get_genre()),
+            ListColumn::Duration => a.duration.cmp(&b.duration),
+            ListColumn::PlayCount => a.play_count.cmp(&b.play_count),
+            ListColumn::LastPlayed => a.last_played_at.cmp(&b.last_played_at),
+            ListColumn::Rating => a.rating.cmp(&b.rating),
+            // the year first, a date without a day goes before the dates of its year
+            ListColumn::Released => (a.year, &a.release_date).cmp(&(b.year, &b.release_date)),
+            ListColumn::Year => a.year.cmp(&b.year),
+            ListColumn::DateAdded => a.date_added.cmp(&b.date_added),
+            ListColumn::Kind => i64::from(a.type_id).cmp(&i64::from(b.type_id)),
+            ListColumn::Field(id) => {
+                let value = |p: &Playable| fields.get(&(p.id, id)).map_or("", String::as_str);
+                text(value(a), value(b))
+            }
+        };
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct PlayerState {
     pub current_playable: Option<PlayableId>,
     pub current_index: Option<usize>,
     pub shuffle: bool,
     pub is_playing: bool,
+    // Some while a podcast episode is playing instead of a playable
+    pub current_episode: Option<Episode>,
+    // last saved position of the playing audiobook, None for regular playables
+    pub audiobook_position: Option<u64>,
+    // the same for a playing long track, see `LONG_TRACK_KEY`
+    pub progress_position: Option<u64>,
+    // playback position in seconds, as last reported by the player
+    pub position: u64,
 }
 
 pub struct State {
     playlist_names: Vec<PlaylistNode>,
     tag_names: Vec<Tag>,
-    search_string: String,
+    tag_tree: Vec<TagNode>,
+    podcasts: Vec<Podcast>,
+    watches: Vec<Watch>,
+    // releases of the watches not seen yet
+    new_releases: Vec<WatchRelease>,
+    episodes: Vec<Episode>,
+    imports: Vec<ImportBatch>,
+    // soft deleted playables with the time they were deleted, refreshed when shown
+    deleted: Vec<(Playable, i64)>,
+    // refreshed whenever the snapshots are shown
+    snapshots: Vec<Snapshot>,
+    comparison: Option<SnapshotComparison>,
+    liked_ids: HashSet<PlayableId>,
+    // refreshed whenever the advanced settings are shown
+    database_stats: DatabaseStats,
+    // refreshed whenever the insights are shown
+    insights: LibraryInsights,
+    // albums released in the days around today in past years, refreshed with Listen Now
+    this_week: Vec<AlbumGroup>,
+    // the album page on screen and the artwork of its first track with one
+    album: Album,
+    album_artwork: Option<Vec<u8>>,
+    scan_options: ScanOptions,
+    watch_folders: Vec<PathBuf>,
+    recent_files: Vec<PathBuf>,
+    webdav_servers: Vec<webdav::Server>,
+    subsonic_servers: Vec<subsonic::Server>,
+    jellyfin_servers: Vec<jellyfin::Server>,
+    convert_options: ConvertOptions,
+    record_source: Option<String>,
+    record_format: RecordFormat,
+    discogs_token: Option<String>,
+    discogs_oauth: Option<OAuthTokens>,
+    alarm: Alarm,
+    // unix timestamp the alarm rings at next, None while it is off
+    next_alarm: Option<i64>,
+    context_end: ContextEnd,
+    close_behavior: CloseBehavior,
+    sort: Option<SortOrder>,
+    list_columns: Vec<ListColumn>,
+    // widths of the columns resized, by view and column, see `layout_view`
+    column_widths: HashMap<String, HashMap<String, f32>>,
+    custom_fields: Vec<CustomField>,
+    // values of the custom fields by playable and field, for the list and its order
+    field_values: HashMap<(PlayableId, i64), String>,
+    album_grid: bool,
+    theme: Theme,
+    // playback preferences of the playlist on screen, the defaults for other sections
+    section_playback: PlaylistPlayback,
+    fade_ms: u64,
+    crossfade_secs: u64,
+    output_latency_ms: u64,
+    match_source_rate: bool,
+    hover_preview: bool,
+    long_track_minutes: u32,
+    skip_silence: Option<i32>,
+    resample_quality: ResampleQuality,
+    volume_trims: VolumeTrims,
+    eq_curves: EqCurves,
+    history_retention: Option<u32>,
+    deleted_retention: Option<u32>,
+    // how a playlist is written out by Copy tracklist, see `clipboard::format_tracklist`
+    tracklist_template: String,
+    // plays are not recorded while set, only lasts for the session
+    incognito: bool,
+    // set by a `rating:N` term in the search, the least stars a shown playable has
+    search_rating: Option<u8>,
     storage: Box<dyn Storage>,
     section: Section,
     playables: Vec<Playable>,
-    recently_played: Vec<Playable>,
+    // when the playables in Recently Played were last played, refreshed when shown
+    last_played: HashMap<PlayableId, i64>,
+    // collection started from the sidebar, next/previous walk it instead of the visible list
+    queue: Option<Vec<Playable>>,
+    // queued by hand, played before anything else
+    up_next: Vec<Playable>,
+    // the playable taken off `up_next` while it plays, the list walked stays where it was
+    playing_up_next: Option<Playable>,
+    // chapter markers of the last inspected playable, true when they come from a linked release
+    chapters: Option<(PlayableId, Vec<Chapter>, bool)>,
+    // the same for the playing track, marked on the seek bar
+    playing_chapters: Option<(PlayableId, Vec<Chapter>)>,
+    // bookmarks of the last inspected playable
+    bookmarks: Option<(PlayableId, Vec<Bookmark>)>,
+    // notes of the last inspected playable
+    notes: Option<(PlayableId, String)>,
+    // playables linked to the last inspected one
+    links: Option<(PlayableId, Vec<PlayableLink>)>,
+    // whether the last inspected playable is an audiobook
+    audiobook: Option<(PlayableId, bool)>,
+    // artwork of the last inspected playable, list queries do not load it
+    artwork: Option<(PlayableId, Option<Vec<u8>>)>,
+    // Some while the song info panel is open
+    song_info: Option<SongInfo>,
+    // Some while the library is shared with other instances
+    sync_server: Option<SyncServer>,
+    // the pairing codes of the instances synced from, by name
+    peer_codes: HashMap<String, String>,
+    // why the database file did not open, the library is kept in memory then
+    storage_problem: Option<String>,
+    // the storage used off the UI thread, None unless the library is saved in a file
+    async_storage: Option<Arc<dyn AsyncStorage>>,
+    // playables read per page of the library, see `LibraryLoad`
+    library_page: usize,
+    // counts the loads of the track list, to drop the pages of an earlier one
+    library_generation: u64,
+    // the pages left to read, until the app takes them to read
+    library_load: Option<LibraryLoad>,
+    // songs of servers left out of the library as their local file is in it
+    shadowed: HashSet<PlayableId>,
+    // the only source the library is shown from, lasts until another section is shown
+    source_filter: Option<AudioFileKind>,
+    // the files remote playables were downloaded to
+    offline_copies: HashMap<PlayableId, String>,
+    // remote playables waiting to be downloaded, in order, and the one being downloaded
+    offline_queue: VecDeque<Playable>,
+    downloading: Option<PlayableId>,
     pub player: PlayerState,
     random_generator: rand::rngs::ThreadRng,
 }
 
 impl Default for State {
     fn default() -> Self {
-        let user_dirs = UserDirs::new().unwrap();
-        let music_dir = user_dirs.audio_dir().unwrap();
-        let res = init_storage(music_dir.join("music.db"));
-        let storage: Box<dyn Storage> = if let Ok(storage) = res {
-            info!("DB Storage initialization success");
-            Box::new(storage)
-        } else if let Ok(storage) = init_storage(":memory:") {
-            error!("Persistent Storage initialization failed with error {res:?}");
-            info!("Using Memory Storage");
-            Box::new(storage)
-        } else {
-            error!("In memory storage failed");
-            info!("Using Dummy Storage");
-            Box::new(DummyStorage)
+        let music_dir = UserDirs::new().and_then(|dirs| dirs.audio_dir().map(Path::to_path_buf));
+        let path = music_dir.map(|music_dir| music_dir.join("music.db"));
+        let res = match &path {
+            Some(path) => init_storage(path).map_err(|e| e.to_string()),
+            None => Err("there is no music folder".to_string()),
+        };
+        let (storage, problem): (Box<dyn Storage>, _) = match res {
+            Ok(storage) => {
+                info!("DB Storage initialization success");
+                (Box::new(storage), None)
+            }
+            Err(problem) => {
+                error!("Persistent Storage initialization failed with error {problem}");
+                match init_storage(":memory:") {
+                    Ok(storage) => {
+                        info!("Using Memory Storage");
+                        (Box::new(storage), Some(problem))
+                    }
+                    Err(e) => {
+                        error!("In memory storage failed");
+                        info!("Using Dummy Storage");
+                        let problem = format!("{problem}, nor could memory be used: {e}");
+                        (Box::new(DummyStorage), Some(problem))
+                    }
+                }
+            }
         };
-        State::new(storage)
+        // a connection of its own on the same file, a database in memory can not be shared
+        let async_storage: Option<Arc<dyn AsyncStorage>> = match (&problem, path) {
+            (None, Some(path)) => match init_storage(path) {
+                Ok(storage) => Some(Arc::new(Worker::spawn(storage))),
+                Err(err) => {
+                    error!("Error opening the storage worker: {err:?}");
+                    None
+                }
+            },
+            _ => None,
+        };
+        let mut state = State::with_async_storage(storage, async_storage);
+        state.storage_problem = problem;
+        state
     }
 }
 
 impl State {
     pub fn new(storage: Box<dyn Storage>) -> Self {
+        Self::with_async_storage(storage, None)
+    }
+
+    /// A state whose slow reads, e.g. of a large library, are made on `async_storage` when
+    /// there is one.
+    pub fn with_async_storage(
+        mut storage: Box<dyn Storage>,
+        async_storage: Option<Arc<dyn AsyncStorage>>,
+    ) -> Self {
+        Self::move_secrets(storage.as_mut());
         let section = Section::default();
         let playlist_names = Self::to_playlist_tree(storage.read_playlists().unwrap_or_default());
         let tag_names = storage.read_tags().unwrap_or_default();
+        let tag_tree = Self::to_tag_tree(&tag_names);
+        let podcasts = storage.read_podcasts().unwrap_or_default();
+        let watches = storage.read_watches().unwrap_or_default();
+        let new_releases = storage.read_new_releases().unwrap_or_default();
+        let liked_ids = storage.read_liked_ids().unwrap_or_default();
+        let scan_options = Self::read_scan_options(storage.as_ref());
+        let watch_folders = storage
+            .read_setting(WATCH_FOLDERS_KEY)
+            .unwrap_or_default()
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(PathBuf::from)
+            .collect();
+        let webdav_servers: Vec<webdav::Server> = storage
+            .read_setting(WEBDAV_SERVERS_KEY)
+            .unwrap_or_default()
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default();
+        let subsonic_servers: Vec<subsonic::Server> = storage
+            .read_setting(SUBSONIC_SERVERS_KEY)
+            .unwrap_or_default()
+            .and_then(|value| serde_json::from_str::<Vec<subsonic::Server>>(&value).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|server| subsonic::Server {
+                password: Self::read_secret(&server.secret_key()).unwrap_or_default(),
+                ..server
+            })
+            .collect();
+        let jellyfin_servers: Vec<jellyfin::Server> = storage
+            .read_setting(JELLYFIN_SERVERS_KEY)
+            .unwrap_or_default()
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default();
+        let offline_copies = storage.read_offline_copies().unwrap_or_default();
+        offline::set_copies(&offline_copies);
+        let recent_files = storage
+            .read_setting(RECENT_FILES_KEY)
+            .unwrap_or_default()
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(PathBuf::from)
+            .collect();
+        let convert_options = Self::read_convert_options(storage.as_ref());
+        let record_source = storage.read_setting(RECORD_SOURCE_KEY).unwrap_or_default();
+        let record_format = storage
+            .read_setting(RECORD_FORMAT_KEY)
+            .unwrap_or_default()
+            .map(|value| RecordFormat::from(value.as_str()))
+            .unwrap_or_default();
+        let discogs_token = Self::read_secret(DISCOGS_TOKEN_KEY);
+        let discogs_oauth = Self::read_secret(DISCOGS_OAUTH_KEY)
+            .and_then(|value| serde_json::from_str(&value).ok());
+        let alarm = Self::read_alarm(storage.as_ref());
+        let next_alarm = Self::next_alarm_after(&alarm, unix_now());
+        let context_end = storage
+            .read_setting(CONTEXT_END_KEY)
+            .unwrap_or_default()
+            .map(|value| ContextEnd::from(value.as_str()))
+            .unwrap_or_default();
+        let close_behavior = storage
+            .read_setting(CLOSE_BEHAVIOR_KEY)
+            .unwrap_or_default()
+            .map(|value| CloseBehavior::from(value.as_str()))
+            .unwrap_or_default();
+        let sort = storage
+            .read_setting(LIST_SORT_KEY)
+            .unwrap_or_default()
+            .and_then(|value| SortOrder::parse(&value));
+        let list_columns = storage
+            .read_setting(LIST_COLUMNS_KEY)
+            .unwrap_or_default()
+            .map(|value| value.split(',').filter_map(ListColumn::parse).collect())
+            .unwrap_or_default();
+        let column_widths = storage
+            .read_setting(LIST_WIDTHS_KEY)
+            .unwrap_or_default()
+            .and_then(|value| serde_json::from_str(&value).ok())
+            .unwrap_or_default();
+        let custom_fields = storage.read_custom_fields().unwrap_or_default();
+        let field_values = storage
+            .read_field_values()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(playable_id, field_id, value)| ((playable_id, field_id), value))
+            .collect();
+        let album_grid = storage
+            .read_setting(ALBUM_GRID_KEY)
+            .unwrap_or_default()
+            .is_some_and(|value| value == "true");
+        let theme = storage
+            .read_setting(THEME_KEY)
+            .unwrap_or_default()
+            .map(|value| Theme::from(value.as_str()))
+            .unwrap_or_default();
+        let fade_ms = storage
+            .read_setting(FADE_KEY)
+            .unwrap_or_default()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_FADE_MS);
+        let crossfade_secs = storage
+            .read_setting(CROSSFADE_KEY)
+            .unwrap_or_default()
+            .and_then(|value| value.parse::<u64>().ok())
+            .map_or(0, |secs| secs.min(MAX_CROSSFADE_SECS));
+        let output_latency_ms = storage
+            .read_setting(OUTPUT_LATENCY_KEY)
+            .unwrap_or_default()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0);
+        let match_source_rate = storage
+            .read_setting(MATCH_SOURCE_RATE_KEY)
+            .unwrap_or_default()
+            .is_some_and(|value| value == "true");
+        let hover_preview = storage
+            .read_setting(HOVER_PREVIEW_KEY)
+            .unwrap_or_default()
+            .is_none_or(|value| value != "false");
+        let long_track_minutes = storage
+            .read_setting(LONG_TRACK_KEY)
+            .unwrap_or_default()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_LONG_TRACK_MINUTES);
+        let skip_silence = storage
+            .read_setting(SKIP_SILENCE_KEY)
+            .unwrap_or_default()
+            .and_then(|value| value.parse().ok());
+        let volume_trims = storage
+            .read_setting(VOLUME_TRIMS_KEY)
+            .unwrap_or_default()
+            .map(|value| VolumeTrims::parse(&value))
+            .unwrap_or_default();
+        let eq_curves = storage
+            .read_setting(EQUALIZER_KEY)
+            .unwrap_or_default()
+            .map(|value| EqCurves::parse(&value))
+            .unwrap_or_default();
+        let resample_quality = storage
+            .read_setting(RESAMPLE_QUALITY_KEY)
+            .unwrap_or_default()
+            .map(|value| ResampleQuality::from(value.as_str()))
+            .unwrap_or_default();
+        let history_retention = storage
+            .read_setting(HISTORY_RETENTION_KEY)
+            .unwrap_or_default()
+            .and_then(|value| value.parse().ok());
+        let tracklist_template = match storage.read_setting(TRACKLIST_TEMPLATE_KEY) {
+            Ok(Some(template)) if !template.trim().is_empty() => template,
+            Ok(_) => DEFAULT_TRACKLIST_TEMPLATE.to_string(),
+            Err(err) => {
+                error!("Error reading tracklist template: {err:?}");
+                DEFAULT_TRACKLIST_TEMPLATE.to_string()
+            }
+        };
+        let deleted_retention = match storage.read_setting(DELETED_RETENTION_KEY) {
+            Ok(Some(value)) => value.parse().ok(),
+            _ => Some(DEFAULT_DELETED_RETENTION_DAYS),
+        };
 
         let mut instance = Self {
-            search_string: String::new(),
+            search_rating: None,
             playlist_names,
             tag_names,
+            tag_tree,
+            podcasts,
+            watches,
+            new_releases,
+            episodes: Vec::new(),
+            imports: Vec::new(),
+            deleted: Vec::new(),
+            snapshots: Vec::new(),
+            comparison: None,
+            liked_ids,
+            database_stats: DatabaseStats::default(),
+            insights: LibraryInsights::default(),
+            this_week: Vec::new(),
+            album: Album::default(),
+            album_artwork: None,
+            scan_options,
+            watch_folders,
+            recent_files,
+            webdav_servers,
+            subsonic_servers,
+            jellyfin_servers,
+            convert_options,
+            record_source,
+            record_format,
+            discogs_token,
+            discogs_oauth,
+            alarm,
+            next_alarm,
+            context_end,
+            close_behavior,
+            sort,
+            list_columns,
+            column_widths,
+            custom_fields,
+            field_values,
+            album_grid,
+            theme,
+            section_playback: PlaylistPlayback::default(),
+            fade_ms,
+            crossfade_secs,
+            output_latency_ms,
+            match_source_rate,
+            hover_preview,
+            long_track_minutes,
+            skip_silence,
+            resample_quality,
+            volume_trims,
+            eq_curves,
+            history_retention,
+            deleted_retention,
+            tracklist_template,
+            incognito: false,
             section,
             playables: Vec::new(),
             storage,
             player: PlayerState::default(),
             random_generator: rand::rng(),
-            recently_played: Vec::new(),
+            last_played: HashMap::new(),
+            queue: None,
+            up_next: Vec::new(),
+            playing_up_next: None,
+            chapters: None,
+            playing_chapters: None,
+            bookmarks: None,
+            notes: None,
+            links: None,
+            audiobook: None,
+            artwork: None,
+            song_info: None,
+            sync_server: None,
+            peer_codes: Self::read_secret(SYNC_PEER_CODES_KEY)
+                .and_then(|value| serde_json::from_str(&value).ok())
+                .unwrap_or_default(),
+            storage_problem: None,
+            async_storage,
+            library_page: LIBRARY_PAGE,
+            library_generation: 0,
+            library_load: None,
+            shadowed: HashSet::new(),
+            source_filter: None,
+            offline_copies,
+            offline_queue: VecDeque::new(),
+            downloading: None,
         };
-        if let Err(err) = instance.load_playables() {
+        instance.share_accounts();
+        if let Err(err) = instance.refresh_local_copies() {
+            error!("Error reading the local copies: {err:?}");
+        }
+        if let Err(err) = instance.prune_history() {
+            error!("Error pruning history: {err:?}");
+        }
+        if let Err(err) = instance.purge_expired() {
+            error!("Error purging deleted playables: {err:?}");
+        }
+        if let Err(err) = instance.load_section() {
             error!("Error loading library: {err:?}");
         }
+        let sharing = instance.storage.read_setting(SYNC_SHARING_KEY);
+        if sharing.is_ok_and(|value| value.is_some_and(|value| value == "true"))
+            && let Err(err) = instance.start_sharing()
+        {
+            error!("Error sharing the library: {err:?}");
+        }
         instance
     }
 
+    // older versions kept the secrets in the settings, they move to the keychain on startup
+    fn move_secrets(storage: &mut dyn Storage) {
+        for key in [DISCOGS_TOKEN_KEY, DISCOGS_OAUTH_KEY] {
+            let Ok(Some(value)) = storage.read_setting(key) else {
+                continue;
+            };
+            if value.is_empty() {
+                continue;
+            }
+            let moved = secrets::write(key, &value)
+                .map_err(StateError::from)
+                .and_then(|_| Ok(storage.write_setting(key, "")?));
+            if let Err(err) = moved {
+                error!("Error moving {key} to the keychain: {err:?}");
+            }
+        }
+        if let Ok(Some(value)) = storage.read_setting(SUBSONIC_SERVERS_KEY)
+            && let Ok(servers) = serde_json::from_str::<Vec<subsonic::Server>>(&value)
+            && servers.iter().any(|server| !server.password.is_empty())
+        {
+            let moved = servers
+                .iter()
+                .try_for_each(|server| secrets::write(&server.secret_key(), &server.password))
+                .map_err(StateError::from)
+                .and_then(|_| Ok(serde_json::to_string(&servers)?))
+                .and_then(|value| Ok(storage.write_setting(SUBSONIC_SERVERS_KEY, &value)?));
+            if let Err(err) = moved {
+                error!("Error moving the Subsonic passwords to the keychain: {err:?}");
+            }
+        }
+    }
+
+    // None when nothing is kept, or the keychain could not be read
+    fn read_secret(key: &str) -> Option<String> {
+        secrets::read(key)
+            .unwrap_or_else(|err| {
+                error!("Error reading {key} from the keychain: {err:?}");
+                None
+            })
+            .filter(|value| !value.is_empty())
+    }
+
     fn to_playlist_tree(playlists: Vec<Playlist>) -> Vec<PlaylistNode> {
         let mut tree = Vec::new();
         let mut children = Vec::new();
@@ -130,463 +1047,6461 @@ impl State {
         }
     }
 
-    pub fn load_playables(&mut self) -> Result<()> {
-        match &self.section {
-            Section::Library => self.playables = self.storage.read_library()?,
-            Section::Favorites => self.playables = self.storage.read_likes()?,
-            Section::Playlist(id) => self.playables = self.storage.read_playlist(*id)?,
-            Section::Tag(id) => self.playables = self.storage.read_tag(*id)?,
-            Section::RecentlyPlayed => self.playables = self.recently_played.clone(),
-            _ => {}
+    fn to_tag_tree(tags: &[Tag]) -> Vec<TagNode> {
+        let mut sorted: Vec<&Tag> = tags.iter().collect();
+        sorted.sort_by_key(|t| t.name.to_lowercase());
+        let mut tree = Vec::new();
+        sorted.into_iter().for_each(|tag| {
+            let segments: Vec<&str> = tag.name.split('/').collect();
+            Self::insert_tag(&mut tree, "", &segments, tag);
+        });
+        tree
+    }
+
+    fn insert_tag(nodes: &mut Vec<TagNode>, parent: &str, segments: &[&str], tag: &Tag) {
+        let Some((name, rest)) = segments.split_first() else {
+            return;
         };
+        let path = if parent.is_empty() {
+            name.to_string()
+        } else {
+            format!("{parent}/{name}")
+        };
+        let index = match nodes.iter().position(|n| n.name.eq_ignore_ascii_case(name)) {
+            Some(index) => index,
+            None => {
+                nodes.push(TagNode {
+                    name: name.to_string(),
+                    path: path.clone(),
+                    tag: None,
+                    children: Vec::new(),
+                });
+                nodes.len() - 1
+            }
+        };
+        if rest.is_empty() {
+            nodes[index].tag = Some(tag.clone());
+        } else {
+            Self::insert_tag(&mut nodes[index].children, &path, rest, tag);
+        }
+    }
+
+    fn reload_tags(&mut self) -> Result<()> {
+        self.tag_names = self.storage.read_tags()?;
+        self.tag_tree = Self::to_tag_tree(&self.tag_names);
         Ok(())
     }
 
-    pub fn playlists(&self) -> &[PlaylistNode] {
-        &self.playlist_names
+    fn apply(&mut self, change: Change) -> Result<()> {
+        match change {
+            Change::Added(ids) => {
+                let loaded: HashSet<PlayableId> = self.playables.iter().map(|p| p.id).collect();
+                let ids: Vec<PlayableId> =
+                    ids.into_iter().filter(|id| !loaded.contains(id)).collect();
+                if !ids.is_empty() {
+                    let added = self.storage.read_library_from_ids(&ids)?;
+                    self.playables.extend(added);
+                    self.sort_playables();
+                }
+            }
+            Change::Removed(ids) => {
+                let ids: HashSet<PlayableId> = ids.into_iter().collect();
+                self.playables.retain(|p| !ids.contains(&p.id));
+            }
+            Change::Refresh => self.load_playables()?,
+        }
+        Ok(())
     }
 
-    pub fn tags(&self) -> &[Tag] {
-        &self.tag_names
+    pub fn load_playables(&mut self) -> Result<()> {
+        // a library read in pages is read whole instead
+        self.library_generation += 1;
+        self.library_load = None;
+        if self.section == Section::Library {
+            self.refresh_local_copies()?;
+        }
+        match &self.section {
+            Section::Podcast(id) => self.episodes = self.storage.read_episodes(*id)?,
+            Section::ImportHistory => self.imports = self.storage.read_imports()?,
+            Section::RecentlyDeleted => self.deleted = self.storage.read_deleted()?,
+            Section::Snapshots => self.snapshots = self.storage.read_snapshots()?,
+            Section::AdvancedSettings => {
+                self.database_stats = self.storage.read_database_stats()?
+            }
+            Section::Insights => {
+                self.insights.never_played =
+                    self.storage.read_auto_playlist(AutoPlaylist::NeverPlayed)?;
+                self.insights.not_played =
+                    self.read_section(&Section::NotPlayedFor(self.insights.months))?;
+                self.insights.without_genre = self
+                    .storage
+                    .read_library()?
+                    .iter()
+                    .filter(|p| !genre_inference::has_genre(p))
+                    .count();
+            }
+            Section::RecentlyPlayed => {
+                let played = self.storage.read_recently_played(RECENTLY_PLAYED_LIMIT)?;
+                self.last_played = played.iter().map(|(p, at)| (p.id, *at)).collect();
+                self.playables = played.into_iter().map(|(playable, _)| playable).collect();
+            }
+            Section::ListenNow => {
+                let now = unix_now();
+                let today = timestamp_to_str(now + local_offset(now));
+                self.this_week = released_this_week(&self.storage.read_library()?, &today);
+            }
+            Section::Album(name) => {
+                self.album = self.storage.read_album(name)?;
+                self.album_artwork = self
+                    .album
+                    .tracks
+                    .iter()
+                    .find_map(|track| self.cover_artwork(track.playable.id));
+                self.playables = self.read_section(&Section::Album(name.clone()))?;
+                // the tracks play in the order of the release
+                return Ok(());
+            }
+            Section::Browse | Section::Queue => {}
+            section => self.playables = self.read_section(section)?,
+        };
+        self.sort_playables();
+        Ok(())
     }
 
-    pub fn set_section(&mut self, section: Section) -> Result<()> {
-        self.section = section;
-        self.search_string = String::new();
-        self.load_playables()
+    // the track list of a section opened. A large library shows its first page while the rest
+    // is read off the UI thread, reloads of the section shown read it whole to keep the rows
+    // scrolled to
+    fn load_section(&mut self) -> Result<()> {
+        if self.section != Section::Library || self.async_storage.is_none() {
+            return self.load_playables();
+        }
+        self.library_generation += 1;
+        self.refresh_local_copies()?;
+        let page = self.storage.read_library_page(0, self.library_page)?;
+        self.library_load = (page.len() == self.library_page)
+            .then(|| page.last())
+            .flatten()
+            .map(|last| LibraryLoad {
+                generation: self.library_generation,
+                after: last.id,
+                limit: self.library_page,
+            });
+        self.playables = page
+            .into_iter()
+            .filter(|playable| !self.shadowed.contains(&playable.id))
+            .collect();
+        self.sort_playables();
+        Ok(())
     }
 
-    pub fn section(&self) -> &Section {
-        &self.section
+    /// The rest of the library to read since it was shown, taken once to start reading it.
+    pub fn take_library_load(&mut self) -> Option<LibraryLoad> {
+        self.library_load.take()
     }
 
-    fn apply_search_filter(&self, v: &Playable, search: &str) -> bool {
-        let in_title = (*v).get_title().to_lowercase().contains(search);
-        let in_artist = (*v).get_artist().to_lowercase().contains(search);
-        let in_album = (*v).get_album().to_lowercase().contains(search);
-        in_title || in_artist || in_album
+    /// Adds a page of the library read to the track list. Returns the next page to read, None
+    /// once the whole library is in or when the track list was loaded again meanwhile.
+    pub fn append_library_page(
+        &mut self,
+        load: LibraryLoad,
+        page: Vec<Playable>,
+    ) -> Option<LibraryLoad> {
+        if load.generation != self.library_generation || self.section != Section::Library {
+            return None;
+        }
+        let next = (page.len() == load.limit)
+            .then(|| page.last())
+            .flatten()
+            .map(|last| LibraryLoad {
+                after: last.id,
+                ..load
+            });
+        // those added meanwhile are listed already
+        let listed: HashSet<PlayableId> = self.playables.iter().map(|p| p.id).collect();
+        self.playables.extend(page.into_iter().filter(|playable| {
+            !self.shadowed.contains(&playable.id) && !listed.contains(&playable.id)
+        }));
+        self.sort_playables();
+        next
     }
 
-    pub fn playables(&self) -> impl Iterator<Item = &Playable> {
-        self.playables
+    // which songs of servers have their album in the local files, to list the local file
+    // alone and to play it in place of streaming the song
+    fn refresh_local_copies(&mut self) -> Result<()> {
+        let copies = self.storage.read_local_copies()?;
+        self.shadowed = copies
             .iter()
-            .filter(|v| self.apply_search_filter(v, &self.search_string))
+            .filter(|copy| copy.path.is_some())
+            .map(|copy| copy.playable_id)
+            .collect();
+        remote::set_local_copies(&copies);
+        Ok(())
     }
 
-    pub fn add_to_likes(&mut self, playable_id: &PlayableId) {
-        if let Err(err) = self.storage.append_like(*playable_id) {
-            error!("Error adding playable to likes: {err:?}");
+    fn sort_playables(&mut self) {
+        if let Some(order) = self.sort {
+            self.playables
+                .sort_by(|a, b| order.compare(a, b, &self.field_values));
         }
     }
 
-    pub fn is_liked(&self, playable_id: &PlayableId) -> bool {
-        let result = self.storage.is_liked(*playable_id);
-        if let Err(err) = result {
-            error!("Error checking if playable is liked: {err:?}");
-            false
-        } else {
-            result.unwrap()
-        }
+    pub fn sort(&self) -> Option<SortOrder> {
+        self.sort
     }
 
-    pub fn remove_from_likes(&mut self, playable_id: &PlayableId) {
-        if let Err(err) = self.storage.remove_from_likes(*playable_id) {
-            error!("Error removing playable from likes: {err:?}");
-        }
+    /// Orders the track list, None goes back to the order of the section.
+    pub fn set_sort(&mut self, sort: Option<SortOrder>) -> Result<()> {
+        let value = sort.map(SortOrder::to_setting).unwrap_or_default();
+        self.storage.write_setting(LIST_SORT_KEY, &value)?;
+        self.sort = sort;
+        self.load_playables()
     }
 
-    pub fn search(&mut self, val: String) {
-        self.search_string = val;
+    /// The optional columns shown in the track list.
+    pub fn list_columns(&self) -> &[ListColumn] {
+        &self.list_columns
     }
 
-    pub fn append_bulk(&mut self, items: Vec<ScannedFile>) -> Result<()> {
-        log::info!("Appending {} items to {}", items.len(), &self.section);
-        let items: Vec<AudioFileDescriptor> =
-            items.into_iter().map(AudioFileDescriptor::from).collect();
-
-        if let Section::Playlist(id) = self.section {
-            self.storage.bulk_append_to_playlist(id, &items)?;
-            self.load_playables()?;
+    pub fn toggle_list_column(&mut self, column: ListColumn) -> Result<()> {
+        let mut columns = self.list_columns.clone();
+        if columns.contains(&column) {
+            columns.retain(|shown| *shown != column);
         } else {
-            self.storage.bulk_append_to_library(&items)?;
-
-            if let Section::Library = self.section {
-                self.load_playables()?;
-            }
+            columns.push(column);
         }
+        let value: Vec<String> = columns.iter().map(|column| column.to_setting()).collect();
+        self.storage
+            .write_setting(LIST_COLUMNS_KEY, &value.join(","))?;
+        self.list_columns = columns;
         Ok(())
     }
 
-    pub fn bulk_remove(&mut self, indexes: &[usize], to_trash: bool) {
-        let playables: Vec<&Playable> = indexes
-            .iter()
-            .map(|i| self.playables.get(*i).unwrap())
-            .collect();
-        let ids: Vec<PlayableId> = playables.iter().map(|p| p.get_id()).collect();
+    /// The width of a column of the track list in the current view, in pixels.
+    pub fn column_width(&self, column: ListColumn) -> f32 {
+        self.column_widths
+            .get(layout_view(&self.section))
+            .and_then(|widths| widths.get(&column.to_setting()))
+            .copied()
+            .unwrap_or_else(|| column.default_width())
+    }
 
-        if to_trash || self.section.eq(&Section::Library) {
-            if let Err(err) = self.storage.bulk_remove_from_library(&ids) {
-                error!("Error removing items from library\n{err:?}");
-            }
-            if to_trash {
-                for playable in playables {
-                    if playable.get_kind().eq(&PlayableKind::LocalFile)
-                        && let Err(err) = trash::delete(playable.get_path())
-                    {
-                        error!("Error deleting {}.\n{err}", playable.get_path());
-                    }
-                }
-            }
-        } else {
-            match &self.section {
-                Section::Playlist(id) => {
-                    if let Err(err) = self.storage.bulk_remove_from_playlist(*id, &ids) {
-                        error!("Error removing items from playlist {id}\n{err:?}");
-                    }
-                }
-                Section::Favorites => {
-                    // if let Err(err) = self.storage.bulk_remove_from_favorites(&ids) {
-                    //     error!("Error removing items from favorites\n{err:?}");
-                    // }
-                }
-                Section::Tag(_id) => {
-                    // if let Err(err) = self.storage.bulk_remove_from_tag(*id, &ids) {
-                    //     error!("Error removing items from tag {id}\n{err:?}");
-                    // }
-                }
-                _ => {}
-            };
-        }
+    /// Sets the width of a column in the current view, the other views keep theirs.
+    pub fn set_column_width(&mut self, column: ListColumn, width: f32) -> Result<()> {
+        let mut widths = self.column_widths.clone();
+        widths
+            .entry(layout_view(&self.section).to_string())
+            .or_default()
+            .insert(column.to_setting(), width.max(MIN_COLUMN_WIDTH).round());
+        self.write_column_widths(widths)
     }
 
-    pub fn create_playlist(
-        &mut self,
-        name: &str,
-        kind: Option<crate::storage::PlaylistKind>,
-    ) -> Result<i64> {
-        let id = self.storage.create_playlist(name, kind, None)?;
-        self.playlist_names = Self::to_playlist_tree(self.storage.read_playlists()?);
-        Ok(id)
+    /// Puts the columns of the current view back to their default widths.
+    pub fn reset_column_widths(&mut self) -> Result<()> {
+        let mut widths = self.column_widths.clone();
+        widths.remove(layout_view(&self.section));
+        self.write_column_widths(widths)
     }
 
-    pub fn delete_playlist(&mut self, id: i64) -> Result<()> {
-        let is_selected = self.section.eq(&Section::Playlist(id));
-        self.storage.delete_playlist(id)?;
-        self.playlist_names = Self::to_playlist_tree(self.storage.read_playlists()?);
-        if is_selected {
-            if self.playlist_names.is_empty() {
-                self.section = Section::Library;
-            } else {
-                let id = self.playlist_names[0].value.id;
-                self.section = Section::Playlist(id);
-            }
-            self.load_playables()?;
-        }
+    fn write_column_widths(&mut self, widths: HashMap<String, HashMap<String, f32>>) -> Result<()> {
+        let value = serde_json::to_string(&widths).unwrap_or_default();
+        self.storage.write_setting(LIST_WIDTHS_KEY, &value)?;
+        self.column_widths = widths;
         Ok(())
     }
 
-    pub fn clear_playlist(&mut self, id: i64) -> Result<()> {
-        self.storage.clear_playlist(id)?;
-        if self.section.eq(&Section::Playlist(id)) {
-            self.load_playables()?;
-        }
-        Ok(())
+    /// The fields defined by the user, by name.
+    pub fn custom_fields(&self) -> &[CustomField] {
+        &self.custom_fields
     }
 
-    pub fn rename_playlist(&mut self, id: i64, name: &str) -> Result<()> {
-        self.storage.rename_playlist(id, name)?;
-        self.playlist_names = Self::to_playlist_tree(self.storage.read_playlists()?);
-        Ok(())
+    pub fn field_value(&self, playable_id: PlayableId, field_id: i64) -> &str {
+        self.field_values
+            .get(&(playable_id, field_id))
+            .map_or("", String::as_str)
     }
 
-    pub fn append_to_tag(&mut self, tag_id: i64, playable_id: i64) -> Result<()> {
-        self.storage.append_to_tag(tag_id, playable_id)?;
-        if self.section.eq(&Section::Tag(tag_id)) {
-            self.load_playables()?;
-        }
+    pub fn create_custom_field(&mut self, name: &str) -> Result<i64> {
+        let id = self.storage.create_custom_field(name)?;
+        self.custom_fields = self.storage.read_custom_fields()?;
+        Ok(id)
+    }
 
+    pub fn rename_custom_field(&mut self, field_id: i64, name: &str) -> Result<()> {
+        self.storage.rename_custom_field(field_id, name)?;
+        self.custom_fields = self.storage.read_custom_fields()?;
         Ok(())
     }
 
-    pub fn create_tag(&mut self, name: &str) -> Result<()> {
-        self.storage.create_tag(name)?;
-        self.tag_names = self.storage.read_tags()?;
+    /// Deletes a field with its values, the list stops showing and ordering by it.
+    pub fn delete_custom_field(&mut self, field_id: i64) -> Result<()> {
+        let column = ListColumn::Field(field_id);
+        if self.list_columns.contains(&column) {
+            self.toggle_list_column(column)?;
+        }
+        if self.sort.is_some_and(|order| order.column == column) {
+            self.set_sort(None)?;
+        }
+        self.storage.delete_custom_field(field_id)?;
+        self.custom_fields.retain(|field| field.id != field_id);
+        self.field_values.retain(|(_, id), _| *id != field_id);
         Ok(())
     }
 
-    pub fn delete_tag(&mut self, id: i64) -> Result<()> {
-        let is_selected = self.section.eq(&Section::Tag(id));
-        self.storage.delete_tag(id)?;
-        self.tag_names = self.storage.read_tags()?;
-        if is_selected {
-            if self.tag_names.is_empty() {
-                self.section = Section::Library;
+    /// Sets the value of a field of a playable, empty clears it.
+    pub fn set_field_value(
+        &mut self,
+        playable_id: PlayableId,
+        field_id: i64,
+        value: &str,
+    ) -> Result<()> {
+        self.storage.set_field_value(playable_id, field_id, value)?;
+        if value.trim().is_empty() {
+            self.field_values.remove(&(playable_id, field_id));
+        } else {
+            self.field_values
+                .insert((playable_id, field_id), value.trim().to_string());
+        }
+        if self
+            .sort
+            .is_some_and(|order| order.column == ListColumn::Field(field_id))
+        {
+            self.sort_playables();
+        }
+        Ok(())
+    }
+
+    fn read_section(&self, section: &Section) -> Result<Vec<Playable>> {
+        let playables = match section {
+            // every source, the songs of servers played from a local file once
+            Section::Library => self
+                .storage
+                .read_library()?
+                .into_iter()
+                .filter(|playable| !self.shadowed.contains(&playable.id))
+                .collect(),
+            Section::Server(url) => self
+                .storage
+                .read_library()?
+                .into_iter()
+                .filter(|playable| {
+                    remote::is_remote(playable.type_id)
+                        && playable.source_url.starts_with(url.as_str())
+                })
+                .collect(),
+            Section::Favorites => self.storage.read_likes()?,
+            Section::Playlist(id) => self.read_playlist(*id)?,
+            Section::Tag(id) => self.storage.read_tag(*id)?,
+            Section::Tags(ids) => self.storage.read_tag_intersection(ids)?,
+            Section::TagPrefix(prefix) => self.storage.read_tag_prefix(prefix)?,
+            Section::Search(query) => self.storage.search_library(query)?,
+            Section::Import(id) => self.storage.read_import(*id)?,
+            Section::Album(name) => self
+                .storage
+                .read_album(name)?
+                .tracks
+                .into_iter()
+                .map(|track| track.playable)
+                .collect(),
+            Section::Auto(playlist) => self.storage.read_auto_playlist(*playlist)?,
+            Section::NotPlayedFor(months) => self
+                .storage
+                .read_not_played_since(unix_now() - *months as i64 * SECONDS_PER_MONTH)?,
+            Section::RecentlyPlayed => self
+                .storage
+                .read_recently_played(RECENTLY_PLAYED_LIMIT)?
+                .into_iter()
+                .map(|(playable, _)| playable)
+                .collect(),
+            _ => Vec::new(),
+        };
+        Ok(playables)
+    }
+
+    pub fn playlists(&self) -> &[PlaylistNode] {
+        &self.playlist_names
+    }
+
+    fn find_playlist(tree: &[PlaylistNode], id: i64) -> Option<&Playlist> {
+        tree.iter().find_map(|node| {
+            if node.value.id == id {
+                Some(&node.value)
             } else {
-                let id = self.tag_names[0].id;
-                self.section = Section::Tag(id);
+                Self::find_playlist(&node.children, id)
             }
+        })
+    }
+
+    /// The tracks of a playlist, those matching its rules for a dynamic one.
+    fn read_playlist(&self, id: i64) -> Result<Vec<Playable>> {
+        let kind = Self::find_playlist(&self.playlist_names, id).map(|p| p.kind);
+        let playables = if kind == Some(storage::PlaylistKind::Dynamic) {
+            self.storage.read_dynamic_playlist(id, unix_now())?
+        } else {
+            self.storage.read_playlist(id)?
+        };
+        Ok(playables)
+    }
+
+    pub fn playlist_playables(&self, id: i64) -> Result<Vec<Playable>> {
+        self.read_playlist(id)
+    }
+
+    pub fn playlist_rules(&self, id: i64) -> Result<Vec<PlaylistRule>> {
+        Ok(self.storage.read_playlist_rules(id)?)
+    }
+
+    pub fn set_playlist_rules(&mut self, id: i64, rules: &[PlaylistRule]) -> Result<()> {
+        self.storage.write_playlist_rules(id, rules)?;
+        if self.section == Section::Playlist(id) {
             self.load_playables()?;
         }
         Ok(())
     }
 
-    // pub fn add_to_recent_playables(&mut self, id: &PlayableId) {
-    //     trace!("add_to_recent_playables: adding {id:?} to recent playables");
-    //     if !self.recent_playables.contains(id) {
-    //         self.recent_playables.push(*id);
-    //     }
-    // }
-    pub fn next_playable(&mut self) {
-        if self.player.current_index.is_none() {
-            if !self.playables.is_empty() {
-                self.player.current_index = Some(0);
-                self.player.current_playable = Some(self.playables[0].id);
+    /// Appends the playables to a static playlist, those already in it are skipped. Returns
+    /// how many were added.
+    pub fn add_to_playlist(&mut self, id: i64, ids: &[PlayableId]) -> Result<usize> {
+        let mut contained: HashSet<PlayableId> = self
+            .storage
+            .read_playlist(id)?
+            .iter()
+            .map(|p| p.id)
+            .collect();
+        let mut added = Vec::new();
+        for playable_id in ids {
+            if contained.insert(*playable_id) {
+                self.storage.append_to_playlist(id, *playable_id)?;
+                added.push(*playable_id);
             }
-            return;
         }
+        if self.section == Section::Playlist(id) && !added.is_empty() {
+            self.load_playables()?;
+        }
+        Ok(added.len())
+    }
 
-        let index = self.player.current_index.unwrap();
-        let next_index = if self.player.shuffle {
-            self.random_generator.random_range(0..self.playables.len())
-        } else if index == self.playables.len() - 1 {
-            0
+    /// Whether the rows shown can be dragged to another place, the tracks of a static playlist
+    /// listed in their order and unfiltered.
+    pub fn can_reorder(&self) -> bool {
+        let Section::Playlist(id) = self.section else {
+            return false;
+        };
+        let kind = Self::find_playlist(&self.playlist_names, id).map(|p| p.kind);
+        kind == Some(storage::PlaylistKind::Static)
+            && self.sort.is_none()
+            && self.search_rating.is_none()
+            && self.source_filter.is_none()
+    }
+
+    /// Moves the rows of the playlist shown together before the row at `to`, or after the last
+    /// one when `to` is past it.
+    pub fn move_rows(&mut self, rows: &[usize], to: usize) -> Result<()> {
+        let Section::Playlist(playlist_id) = self.section else {
+            return Ok(());
+        };
+        let mut current: Vec<PlayableId> = self.playables.iter().map(|p| p.id).collect();
+        let to = to.min(current.len());
+        let (moved, mut target): (Vec<_>, Vec<_>) = current
+            .iter()
+            .enumerate()
+            .partition(|(row, _)| rows.contains(row));
+        let at = to - moved.iter().filter(|(row, _)| *row < to).count();
+        target.splice(at..at, moved);
+        let target: Vec<PlayableId> = target.into_iter().map(|(_, id)| *id).collect();
+        // one move for each track out of place, in the order they end up in
+        for (position, id) in target.iter().enumerate() {
+            let Some(from) = current.iter().position(|other| other == id) else {
+                continue;
+            };
+            if from != position {
+                self.storage.move_in_playlist(playlist_id, from, position)?;
+                let id = current.remove(from);
+                current.insert(position, id);
+            }
+        }
+        self.load_playables()
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        &self.tag_names
+    }
+
+    pub fn tag_tree(&self) -> &[TagNode] {
+        &self.tag_tree
+    }
+
+    pub fn imports(&self) -> &[ImportBatch] {
+        &self.imports
+    }
+
+    pub fn database_stats(&self) -> &DatabaseStats {
+        &self.database_stats
+    }
+
+    pub fn checkpoint_database(&mut self) -> Result<()> {
+        self.storage.checkpoint()?;
+        self.database_stats = self.storage.read_database_stats()?;
+        Ok(())
+    }
+
+    pub fn optimize_database(&mut self) -> Result<()> {
+        self.storage.optimize()?;
+        self.database_stats = self.storage.read_database_stats()?;
+        Ok(())
+    }
+
+    pub fn insights(&self) -> &LibraryInsights {
+        &self.insights
+    }
+
+    pub fn set_insight_months(&mut self, months: u32) -> Result<()> {
+        self.insights.months = months;
+        self.load_playables()
+    }
+
+    /// Tags every one of `ids` with the tag named `name`, the tag is created when missing.
+    pub fn tag_playables(&mut self, name: &str, ids: &[PlayableId]) -> Result<()> {
+        let path = tag_path(name);
+        let tag_id = match self
+            .tag_names
+            .iter()
+            .find(|tag| tag.name.eq_ignore_ascii_case(&path))
+        {
+            Some(tag) => tag.id,
+            None => {
+                let id = self.storage.create_tag(&path)?;
+                self.reload_tags()?;
+                id
+            }
+        };
+        for id in ids {
+            self.storage.append_to_tag(tag_id, *id)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the playables from the library, whatever section is shown.
+    pub fn remove_from_library(&mut self, ids: &[PlayableId]) -> Result<()> {
+        self.storage.soft_delete(ids)?;
+        self.liked_ids.retain(|id| !ids.contains(id));
+        self.load_playables()
+    }
+
+    pub fn podcasts(&self) -> &[Podcast] {
+        &self.podcasts
+    }
+
+    pub fn episodes(&self) -> &[Episode] {
+        &self.episodes
+    }
+
+    pub fn set_section(&mut self, section: Section) -> Result<()> {
+        self.section_playback = self.playlist_playback(&section).unwrap_or_default();
+        self.section = section;
+        self.search_rating = None;
+        self.source_filter = None;
+        self.load_section()
+    }
+
+    pub fn section(&self) -> &Section {
+        &self.section
+    }
+
+    /// How a section is called on screen, playlists, tags and podcasts by their name.
+    pub fn section_title(&self, section: &Section) -> String {
+        let tag_name = |id: &i64| {
+            self.tag_names
+                .iter()
+                .find(|tag| tag.id == *id)
+                .map(|tag| tag.name.clone())
+        };
+        let title = match section {
+            Section::Playlist(id) => {
+                Self::find_playlist(&self.playlist_names, *id).map(|p| p.name.clone())
+            }
+            Section::Tag(id) => tag_name(id),
+            Section::Tags(ids) => Some(
+                ids.iter()
+                    .filter_map(tag_name)
+                    .collect::<Vec<_>>()
+                    .join(" + "),
+            ),
+            Section::Podcast(id) => self
+                .podcasts
+                .iter()
+                .find(|podcast| podcast.id == *id)
+                .map(|podcast| podcast.title.clone()),
+            Section::Album(name) if name.is_empty() => Some("Unknown Album".to_string()),
+            Section::Album(name) => Some(name.clone()),
+            Section::Server(url) => self
+                .remote_servers()
+                .iter()
+                .find(|server| server.url() == url)
+                .map(|server| server.name().to_string()),
+            _ => None,
+        };
+        title.unwrap_or_else(|| section.to_string())
+    }
+
+    /// The tracks of a section other than the one shown, e.g. for the split pane.
+    pub fn section_playables(&self, section: &Section) -> Result<Vec<Playable>> {
+        self.read_section(section)
+    }
+
+    pub fn window_geometry(&self) -> WindowGeometry {
+        let setting = |key: &str| {
+            self.storage.read_setting(key).unwrap_or_else(|err| {
+                error!("Error reading setting {key}: {err:?}");
+                None
+            })
+        };
+        let pair = |value: String, separator: char| {
+            let (a, b) = value.split_once(separator)?;
+            Some((a.parse().ok()?, b.parse().ok()?))
+        };
+        WindowGeometry {
+            size: setting(WINDOW_SIZE_KEY).and_then(|value| pair(value, 'x')),
+            position: setting(WINDOW_POSITION_KEY).and_then(|value| pair(value, ',')),
+            maximized: setting(WINDOW_MAXIMIZED_KEY).is_some_and(|value| value == "true"),
+            pane_ratio: setting(PANE_RATIO_KEY).and_then(|value| value.parse().ok()),
+        }
+    }
+
+    /// Saves what `geometry` holds, settings it leaves at None keep their saved value.
+    pub fn set_window_geometry(&mut self, geometry: WindowGeometry) -> Result<()> {
+        if let Some((width, height)) = geometry.size {
+            self.storage
+                .write_setting(WINDOW_SIZE_KEY, &format!("{width}x{height}"))?;
+        }
+        if let Some((x, y)) = geometry.position {
+            self.storage
+                .write_setting(WINDOW_POSITION_KEY, &format!("{x},{y}"))?;
+        }
+        self.storage
+            .write_setting(WINDOW_MAXIMIZED_KEY, &geometry.maximized.to_string())?;
+        if let Some(ratio) = geometry.pane_ratio {
+            self.storage
+                .write_setting(PANE_RATIO_KEY, &ratio.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Playback preferences of a playlist, None for other sections.
+    pub fn playlist_playback(&self, section: &Section) -> Option<PlaylistPlayback> {
+        let Section::Playlist(id) = section else {
+            return None;
+        };
+        Some(
+            self.storage
+                .read_playlist_playback(*id)
+                .unwrap_or_else(|err| {
+                    error!("Error reading playback of playlist {id}: {err:?}");
+                    PlaylistPlayback::default()
+                }),
+        )
+    }
+
+    pub fn section_playback(&self) -> PlaylistPlayback {
+        self.section_playback
+    }
+
+    pub fn set_playlist_playback(&mut self, id: i64, playback: PlaylistPlayback) -> Result<()> {
+        self.storage.write_playlist_playback(id, &playback)?;
+        if self.section == Section::Playlist(id) {
+            self.section_playback = playback;
+        }
+        Ok(())
+    }
+
+    pub fn save_session(&mut self, scroll_offset: f32, selection: &[usize]) -> Result<()> {
+        let section = self.section.to_setting().unwrap_or_default();
+        self.storage.write_setting(SESSION_SECTION_KEY, &section)?;
+        self.storage
+            .write_setting(SESSION_SCROLL_KEY, &scroll_offset.to_string())?;
+        let selection: Vec<String> = selection.iter().map(|index| index.to_string()).collect();
+        self.storage
+            .write_setting(SESSION_SELECTION_KEY, &selection.join(","))?;
+        Ok(())
+    }
+
+    /// Opens the section saved by `save_session` and returns where its track list was left.
+    /// Sections which no longer exist leave the library open and start at the top.
+    pub fn restore_session(&mut self) -> Session {
+        let setting = |key: &str| {
+            self.storage.read_setting(key).unwrap_or_else(|err| {
+                error!("Error reading setting {key}: {err:?}");
+                None
+            })
+        };
+        let Some(section) = setting(SESSION_SECTION_KEY).and_then(|v| Section::from_setting(&v))
+        else {
+            return Session::default();
+        };
+        let scroll_offset = setting(SESSION_SCROLL_KEY)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default();
+        let selection = setting(SESSION_SELECTION_KEY).unwrap_or_default();
+        if let Err(err) = self.set_section(section) {
+            error!("Error restoring the last section: {err:?}");
+            self.section = Section::default();
+            return Session::default();
+        }
+        let count = self.playables.len();
+        // rows of a library still read in pages are not there yet
+        let loading = self.library_load.is_some();
+        Session {
+            scroll_offset,
+            selection: selection
+                .split(',')
+                .filter_map(|index| index.parse().ok())
+                .filter(|index| *index < count || loading)
+                .collect(),
+        }
+    }
+
+    pub fn playables(&self) -> impl Iterator<Item = &Playable> {
+        self.playables.iter().filter(|v| self.is_shown(v))
+    }
+
+    // whether a playable loaded passes the rating searched for and the source picked
+    fn is_shown(&self, playable: &Playable) -> bool {
+        self.search_rating.is_none_or(|min| playable.rating >= min)
+            && self
+                .source_filter
+                .is_none_or(|kind| playable.type_id == kind)
+    }
+
+    pub fn source_filter(&self) -> Option<AudioFileKind> {
+        self.source_filter
+    }
+
+    /// Shows only the playables of one source, or of all of them again.
+    pub fn set_source_filter(&mut self, kind: Option<AudioFileKind>) {
+        self.source_filter = kind;
+    }
+
+    /// The sources the playables loaded come from, the local files first.
+    pub fn sources(&self) -> Vec<AudioFileKind> {
+        let mut kinds: Vec<AudioFileKind> = Vec::new();
+        for playable in &self.playables {
+            if !kinds.contains(&playable.type_id) {
+                kinds.push(playable.type_id);
+            }
+        }
+        kinds.sort_by_key(|kind| *kind as i64);
+        kinds
+    }
+
+    /// The playables shown grouped by album, ordered by name with the tracks without an album
+    /// last, or by release while the list is.
+    pub fn albums(&self) -> Vec<AlbumGroup> {
+        let mut albums = group_albums(self.playables());
+        match self.sort {
+            Some(SortOrder {
+                column: ListColumn::Released,
+                descending,
+            }) => albums.sort_by(|a, b| {
+                let ordering = a.released.cmp(&b.released);
+                if descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }),
+            _ => albums
+                .sort_by_cached_key(|album| (album.name.is_empty(), album.name.to_lowercase())),
+        }
+        albums
+    }
+
+    /// The albums of the library released this week in past years, oldest first.
+    pub fn this_week(&self) -> &[AlbumGroup] {
+        &self.this_week
+    }
+
+    pub fn album_grid(&self) -> bool {
+        self.album_grid
+    }
+
+    /// Shows track lists as a grid of their albums, or as tracks again.
+    pub fn set_album_grid(&mut self, album_grid: bool) -> Result<()> {
+        self.storage
+            .write_setting(ALBUM_GRID_KEY, &album_grid.to_string())?;
+        self.album_grid = album_grid;
+        Ok(())
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) -> Result<()> {
+        self.storage.write_setting(THEME_KEY, theme.as_str())?;
+        self.theme = theme;
+        Ok(())
+    }
+
+    /// The album page on screen, its tracks in disc and track order.
+    pub fn album(&self) -> &Album {
+        &self.album
+    }
+
+    pub fn album_artwork(&self) -> Option<&Vec<u8>> {
+        self.album_artwork.as_ref()
+    }
+
+    /// The artwork of a playable, read for an album tile.
+    pub fn cover_artwork(&self, playable_id: PlayableId) -> Option<Vec<u8>> {
+        self.storage
+            .read_artwork(playable_id)
+            .unwrap_or_else(|err| {
+                error!("Error reading artwork of {playable_id}: {err:?}");
+                None
+            })
+    }
+
+    /// The lookups of the artwork missing from `ids`, or from every track of their albums when
+    /// `albums` is true.
+    pub fn artwork_lookups(&self, ids: &[PlayableId], albums: bool) -> Result<Vec<Lookup>> {
+        let names: HashSet<&str> = if albums {
+            self.playables
+                .iter()
+                .filter(|playable| ids.contains(&playable.id))
+                .filter_map(|playable| playable.album_name.as_deref())
+                .filter(|name| !name.trim().is_empty())
+                .collect()
         } else {
-            index + 1
+            HashSet::new()
         };
+        let missing: Vec<Playable> = self
+            .storage
+            .read_without_artwork()?
+            .into_iter()
+            .filter(|playable| {
+                ids.contains(&playable.id)
+                    || playable
+                        .album_name
+                        .as_deref()
+                        .is_some_and(|name| names.contains(name))
+            })
+            .collect();
+        Ok(artwork::lookups(&missing))
+    }
 
-        let next_id = self.playables[next_index].id;
-        self.player.current_index = Some(next_index);
-        self.player.current_playable = Some(next_id);
+    /// Saves the artwork found for `ids` and shows it where it was missing.
+    pub fn set_artwork(&mut self, ids: &[PlayableId], artwork: Vec<u8>) -> Result<()> {
+        self.storage.write_artwork(ids, &artwork)?;
+        if let Some((id, cached)) = &mut self.artwork
+            && ids.contains(&*id)
+        {
+            *cached = Some(artwork.clone());
+        }
+        if let Section::Album(_) = self.section
+            && self.album_artwork.is_none()
+            && self
+                .album
+                .tracks
+                .iter()
+                .any(|track| ids.contains(&track.playable.id))
+        {
+            self.album_artwork = Some(artwork);
+        }
+        Ok(())
+    }
+
+    pub fn add_to_likes(&mut self, playable_id: &PlayableId) {
+        if let Err(err) = self.storage.append_like(*playable_id) {
+            error!("Error adding playable to likes: {err:?}");
+            return;
+        }
+        self.liked_ids.insert(*playable_id);
+        let change = match self.section {
+            Section::Favorites => Change::Added(vec![*playable_id]),
+            // newest first and capped, a new like reorders the list
+            Section::Auto(AutoPlaylist::RecentlyLiked) => Change::Refresh,
+            _ => return,
+        };
+        if let Err(err) = self.apply(change) {
+            error!("Error updating favorites: {err:?}");
+        }
+    }
+
+    // answered from the cache, views call it for every rendered row
+    /// Likes a playable from outside the library, e.g. a streamed episode. It is added to the
+    /// library as a stream first, unless its URL is already there.
+    pub fn like_stream(&mut self, playable: &dyn AudioPlayable) -> Result<Playable> {
+        let id = self.stream_in_library(playable)?;
+        if !self.is_liked(&id) {
+            self.add_to_likes(&id);
+        }
+        let playable = self
+            .storage
+            .read_library_from_ids(&[id])?
+            .into_iter()
+            .next()
+            .ok_or(storage::StorageError::QueryError)?;
+        Ok(self.with_artwork(&playable))
+    }
+
+    // the library's copy of a stream from outside it, added unless a stream of the URL is there
+    fn stream_in_library(&mut self, playable: &dyn AudioPlayable) -> Result<PlayableId> {
+        let path = playable.get_path().to_string();
+        let existing = self
+            .storage
+            .filter_library_by_paths(std::slice::from_ref(&path))?;
+        if let Some(existing) = existing.first() {
+            return Ok(existing.id);
+        }
+        let id = self.storage.append_to_library(&AudioFileDescriptor {
+            title: playable.get_title().to_string(),
+            artist: playable.get_artist().to_string(),
+            album: playable.get_album().to_string(),
+            year: 0,
+            genre: playable.get_genre().to_string(),
+            duration: playable.get_duration(),
+            bitrate: 0,
+            sample_rate: 0,
+            lossless: None,
+            release_date: String::new(),
+            track_number: 0,
+            disc_number: 0,
+            label: playable.get_label().to_string(),
+            path,
+            artwork: playable.get_album_art().clone(),
+            kind: AudioFileKind::Stream,
+        })?;
+        if self.section == Section::Library {
+            self.apply(Change::Added(vec![id]))?;
+        }
+        Ok(id)
+    }
+
+    /// Saves streams from outside the library, e.g. the samples of a chart, as a new static
+    /// playlist named `name`, or with a number when taken. Returns its id and name.
+    pub fn save_streams_as_playlist(
+        &mut self,
+        name: &str,
+        playables: &[Playable],
+    ) -> Result<(i64, String)> {
+        let ids = playables
+            .iter()
+            .map(|playable| self.stream_in_library(playable).map(Some))
+            .collect::<Result<Vec<_>>>()?;
+        self.create_imported_playlist(name, &ids)
+    }
+
+    pub fn is_liked(&self, playable_id: &PlayableId) -> bool {
+        self.liked_ids.contains(playable_id)
+    }
+
+    pub fn remove_from_likes(&mut self, playable_id: &PlayableId) {
+        if let Err(err) = self.storage.remove_from_likes(*playable_id) {
+            error!("Error removing playable from likes: {err:?}");
+            return;
+        }
+        self.liked_ids.remove(playable_id);
+        if let Section::Favorites | Section::Auto(AutoPlaylist::RecentlyLiked) = self.section {
+            let _ = self.apply(Change::Removed(vec![*playable_id]));
+        }
+    }
+
+    /// Searches the whole library, `rating:N` keeps the tracks with at least N stars. A rating
+    /// alone filters the section shown, clearing the search leaves the results for the library.
+    pub fn search(&mut self, val: String) -> Result<()> {
+        let (rating, text) = parse_search(&val);
+        if !text.is_empty() {
+            self.set_section(Section::Search(text))?;
+        } else if val.trim().is_empty() && matches!(self.section, Section::Search(_)) {
+            self.set_section(Section::Library)?;
+        }
+        self.search_rating = rating;
+        Ok(())
+    }
+
+    /// Rates the playable with up to `MAX_RATING` stars, 0 clears its rating.
+    pub fn set_rating(&mut self, playable_id: PlayableId, rating: u8) -> Result<()> {
+        let rating = rating.min(MAX_RATING);
+        self.storage.set_rating(playable_id, rating)?;
+        let loaded = self
+            .playables
+            .iter_mut()
+            .chain(self.queue.iter_mut().flatten())
+            .chain(self.up_next.iter_mut())
+            .chain(self.playing_up_next.iter_mut());
+        for playable in loaded.filter(|playable| playable.id == playable_id) {
+            playable.rating = rating;
+        }
+        if self.section == Section::Auto(AutoPlaylist::TopRated) {
+            self.apply(Change::Refresh)?;
+        }
+        Ok(())
+    }
+
+    /// Genres suggested by the library for the tracks without one, and the tracks it has no
+    /// suggestion for.
+    pub fn genre_suggestions(&self) -> Result<(Vec<Suggestion>, Vec<Playable>)> {
+        Ok(genre_inference::from_library(&self.storage.read_library()?))
+    }
+
+    /// Gives the playable an accepted genre in the library, its file is left as it is.
+    pub fn set_genre(&mut self, playable_id: PlayableId, genre: &str) -> Result<()> {
+        self.storage.set_genre(playable_id, genre)?;
+        let genre = genre.trim();
+        let loaded = self
+            .playables
+            .iter_mut()
+            .chain(self.queue.iter_mut().flatten())
+            .chain(self.up_next.iter_mut())
+            .chain(self.playing_up_next.iter_mut());
+        for playable in loaded.filter(|playable| playable.id == playable_id) {
+            playable.genre_name = (!genre.is_empty()).then(|| genre.to_string());
+        }
+        Ok(())
+    }
+
+    /// Returns the ids of the playables which were not in the library yet.
+    pub fn append_bulk(&mut self, items: Vec<ScannedFile>) -> Result<Vec<PlayableId>> {
+        log::info!("Appending {} items to {}", items.len(), &self.section);
+        let items: Vec<AudioFileDescriptor> =
+            items.into_iter().map(AudioFileDescriptor::from).collect();
+
+        let ids = self.storage.bulk_append_to_library(&items)?;
+        if let Section::Playlist(id) = self.section {
+            self.storage.bulk_append_to_playlist(id, &items)?;
+            // files already in the library join the playlist as well
+            self.apply(Change::Refresh)?;
+        } else if let Section::Library = self.section {
+            self.apply(Change::Added(ids.clone()))?;
+        }
+        Ok(ids)
+    }
+
+    /// Appends scanned files like `append_bulk` and records them as one batch in the import history.
+    pub fn import(&mut self, source: &str, items: Vec<ScannedFile>) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        let file_count = items.len();
+        let playlist_id = match self.section {
+            Section::Playlist(id) => Some(id),
+            _ => None,
+        };
+        let ids = self.append_bulk(items)?;
+        self.storage
+            .create_import(source, playlist_id, file_count, &ids)?;
+        if let Section::ImportHistory = self.section {
+            self.load_playables()?;
+        }
+        Ok(())
+    }
+
+    /// An import to write on the storage worker, whose ids the app hands to `imported` once
+    /// written. Without a worker the files are imported right away and None is returned.
+    pub fn begin_import(
+        &mut self,
+        source: &str,
+        items: Vec<ScannedFile>,
+    ) -> Result<Option<NewImport>> {
+        if self.async_storage.is_none() {
+            self.import(source, items)?;
+            return Ok(None);
+        }
+        if items.is_empty() {
+            return Ok(None);
+        }
+        log::info!("Importing {} items to {}", items.len(), &self.section);
+        let playlist_id = match self.section {
+            Section::Playlist(id) => Some(id),
+            _ => None,
+        };
+        Ok(Some(NewImport {
+            source: source.to_string(),
+            playlist_id,
+            items: items.into_iter().map(AudioFileDescriptor::from).collect(),
+        }))
+    }
+
+    /// Shows the playables an import written by the storage worker added, `playlist_id` is the
+    /// playlist they joined.
+    pub fn imported(&mut self, playlist_id: Option<i64>, ids: Vec<PlayableId>) -> Result<()> {
+        match self.section {
+            // files already in the library joined the playlist as well
+            Section::Playlist(id) if playlist_id == Some(id) => self.apply(Change::Refresh),
+            Section::Library => self.apply(Change::Added(ids)),
+            Section::ImportHistory => self.load_playables(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Removes from the library every playable added by the import batch and forgets the batch.
+    pub fn remove_import(&mut self, id: i64) -> Result<()> {
+        let ids: Vec<PlayableId> = self.storage.read_import(id)?.iter().map(|p| p.id).collect();
+        self.storage.soft_delete(&ids)?;
+        // likes are hidden with the playables
+        self.liked_ids.retain(|id| !ids.contains(id));
+        self.storage.delete_import(id)?;
+        match self.section {
+            Section::Import(shown) if shown == id => {
+                self.section = Section::ImportHistory;
+                self.load_playables()
+            }
+            Section::ImportHistory => self.load_playables(),
+            _ => self.apply(Change::Removed(ids)),
+        }
+    }
+
+    pub fn bulk_remove(&mut self, indexes: &[usize], to_trash: bool) {
+        // the indexes are those of the rows shown
+        let playables: Vec<Playable> = self
+            .playables()
+            .enumerate()
+            .filter(|(i, _)| indexes.contains(i))
+            .map(|(_, playable)| playable.clone())
+            .collect();
+        let ids: Vec<PlayableId> = playables.iter().map(|p| p.get_id()).collect();
+
+        // an import batch and search results list library playables, removing from them
+        // removes from the library
+        if to_trash
+            || matches!(
+                self.section,
+                Section::Library | Section::Import(_) | Section::Search(_)
+            )
+        {
+            // a playable whose file went to the trash can not be restored
+            let result = if to_trash {
+                self.storage.bulk_remove_from_library(&ids)
+            } else {
+                self.storage.soft_delete(&ids)
+            };
+            if let Err(err) = result {
+                error!("Error removing items from library\n{err:?}");
+                return;
+            }
+            self.liked_ids.retain(|id| !ids.contains(id));
+            if to_trash {
+                for playable in playables {
+                    if playable.get_kind().eq(&PlayableKind::LocalFile)
+                        && let Err(err) = trash::delete(playable.get_path())
+                    {
+                        error!("Error deleting {}.\n{err}", playable.get_path());
+                    }
+                }
+            }
+        } else {
+            match &self.section {
+                Section::Playlist(id) => {
+                    if let Err(err) = self.storage.bulk_remove_from_playlist(*id, &ids) {
+                        error!("Error removing items from playlist {id}\n{err:?}");
+                        return;
+                    }
+                }
+                Section::Favorites => {
+                    // if let Err(err) = self.storage.bulk_remove_from_favorites(&ids) {
+                    //     error!("Error removing items from favorites\n{err:?}");
+                    // }
+                    return;
+                }
+                Section::Tag(_id) => {
+                    // if let Err(err) = self.storage.bulk_remove_from_tag(*id, &ids) {
+                    //     error!("Error removing items from tag {id}\n{err:?}");
+                    // }
+                    return;
+                }
+                _ => return,
+            };
+        }
+        let _ = self.apply(Change::Removed(ids));
+    }
+
+    pub fn create_playlist(
+        &mut self,
+        name: &str,
+        kind: Option<crate::storage::PlaylistKind>,
+    ) -> Result<i64> {
+        let id = self.storage.create_playlist(name, kind, None)?;
+        self.playlist_names = Self::to_playlist_tree(self.storage.read_playlists()?);
+        Ok(id)
+    }
+
+    pub fn delete_playlist(&mut self, id: i64) -> Result<()> {
+        let is_selected = self.section.eq(&Section::Playlist(id));
+        self.storage.delete_playlist(id)?;
+        self.playlist_names = Self::to_playlist_tree(self.storage.read_playlists()?);
+        if is_selected {
+            if self.playlist_names.is_empty() {
+                self.section = Section::Library;
+            } else {
+                let id = self.playlist_names[0].value.id;
+                self.section = Section::Playlist(id);
+            }
+            self.load_playables()?;
+        }
+        Ok(())
+    }
+
+    pub fn clear_playlist(&mut self, id: i64) -> Result<()> {
+        self.storage.clear_playlist(id)?;
+        if self.section.eq(&Section::Playlist(id)) {
+            self.playables.clear();
+        }
+        Ok(())
+    }
+
+    pub fn rename_playlist(&mut self, id: i64, name: &str) -> Result<()> {
+        self.storage.rename_playlist(id, name)?;
+        self.playlist_names = Self::to_playlist_tree(self.storage.read_playlists()?);
+        Ok(())
+    }
+
+    pub fn tracklist_template(&self) -> &str {
+        &self.tracklist_template
+    }
+
+    /// Saves the format of a tracklist line, an empty one goes back to the default.
+    pub fn set_tracklist_template(&mut self, template: &str) -> Result<()> {
+        let template = if template.trim().is_empty() {
+            DEFAULT_TRACKLIST_TEMPLATE
+        } else {
+            template
+        };
+        self.storage
+            .write_setting(TRACKLIST_TEMPLATE_KEY, template)?;
+        self.tracklist_template = template.to_string();
+        Ok(())
+    }
+
+    fn read_scan_options(storage: &dyn Storage) -> ScanOptions {
+        let setting = |key: &str| {
+            storage.read_setting(key).unwrap_or_else(|err| {
+                error!("Error reading setting {key}: {err:?}");
+                None
+            })
+        };
+        ScanOptions {
+            exclude: setting(SCAN_EXCLUDE_KEY)
+                .map(|value| {
+                    value
+                        .lines()
+                        .map(str::trim)
+                        .filter(|l| !l.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            min_duration: setting(SCAN_MIN_DURATION_KEY)
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(0),
+            follow_symlinks: setting(SCAN_FOLLOW_SYMLINKS_KEY).is_some_and(|value| value == "true"),
+            skip_hidden: setting(SCAN_SKIP_HIDDEN_KEY).is_some_and(|value| value == "true"),
+            max_depth: setting(SCAN_MAX_DEPTH_KEY).and_then(|value| value.parse().ok()),
+            threads: setting(SCAN_THREADS_KEY)
+                .and_then(|value| value.parse().ok())
+                .filter(|threads| *threads > 0),
+            artwork_max_size: match setting(SCAN_ARTWORK_MAX_SIZE_KEY) {
+                Some(value) => value.parse().ok().filter(|size| *size > 0),
+                None => Some(DEFAULT_ARTWORK_MAX_SIZE),
+            },
+        }
+    }
+
+    pub fn scan_options(&self) -> &ScanOptions {
+        &self.scan_options
+    }
+
+    pub fn set_scan_options(&mut self, options: ScanOptions) -> Result<()> {
+        self.storage
+            .write_setting(SCAN_EXCLUDE_KEY, &options.exclude.join("\n"))?;
+        self.storage
+            .write_setting(SCAN_MIN_DURATION_KEY, &options.min_duration.to_string())?;
+        self.storage.write_setting(
+            SCAN_FOLLOW_SYMLINKS_KEY,
+            &options.follow_symlinks.to_string(),
+        )?;
+        self.storage
+            .write_setting(SCAN_SKIP_HIDDEN_KEY, &options.skip_hidden.to_string())?;
+        let max_depth = options
+            .max_depth
+            .map(|depth| depth.to_string())
+            .unwrap_or_default();
+        self.storage.write_setting(SCAN_MAX_DEPTH_KEY, &max_depth)?;
+        let threads = options
+            .threads
+            .map(|threads| threads.to_string())
+            .unwrap_or_default();
+        self.storage.write_setting(SCAN_THREADS_KEY, &threads)?;
+        let artwork_max_size = options
+            .artwork_max_size
+            .map(|size| size.to_string())
+            .unwrap_or_default();
+        self.storage
+            .write_setting(SCAN_ARTWORK_MAX_SIZE_KEY, &artwork_max_size)?;
+        self.scan_options = options;
+        Ok(())
+    }
+
+    /// Why the database file did not open, None when the library is saved in it.
+    pub fn storage_problem(&self) -> Option<&str> {
+        self.storage_problem.as_deref()
+    }
+
+    /// The storage to await the slow calls on, without blocking the UI thread.
+    pub fn async_storage(&self) -> Option<Arc<dyn AsyncStorage>> {
+        self.async_storage.clone()
+    }
+
+    pub fn watch_folders(&self) -> &[PathBuf] {
+        &self.watch_folders
+    }
+
+    pub fn add_watch_folder(&mut self, folder: PathBuf) -> Result<()> {
+        if self.watch_folders.contains(&folder) {
+            return Ok(());
+        }
+        let mut folders = self.watch_folders.clone();
+        folders.push(folder);
+        self.write_watch_folders(folders)
+    }
+
+    pub fn remove_watch_folder(&mut self, folder: &Path) -> Result<()> {
+        let folders = self
+            .watch_folders
+            .iter()
+            .filter(|watched| watched.as_path() != folder)
+            .cloned()
+            .collect();
+        self.write_watch_folders(folders)
+    }
+
+    fn write_watch_folders(&mut self, folders: Vec<PathBuf>) -> Result<()> {
+        let value: Vec<String> = folders
+            .iter()
+            .map(|folder| folder.to_string_lossy().to_string())
+            .collect();
+        self.storage
+            .write_setting(WATCH_FOLDERS_KEY, &value.join("\n"))?;
+        self.watch_folders = folders;
+        Ok(())
+    }
+
+    pub fn webdav_servers(&self) -> &[webdav::Server] {
+        &self.webdav_servers
+    }
+
+    /// Adds a WebDAV share, or changes the account of the one at the same address.
+    pub fn add_webdav_server(&mut self, server: webdav::Server) -> Result<()> {
+        let mut servers: Vec<webdav::Server> = self
+            .webdav_servers
+            .iter()
+            .filter(|known| known.url != server.url)
+            .cloned()
+            .collect();
+        servers.push(server);
+        self.write_webdav_servers(servers)
+    }
+
+    /// Forgets a WebDAV share, the tracks indexed from it stay in the library.
+    pub fn remove_webdav_server(&mut self, url: &str) -> Result<()> {
+        let servers = self
+            .webdav_servers
+            .iter()
+            .filter(|server| server.url != url)
+            .cloned()
+            .collect();
+        self.write_webdav_servers(servers)
+    }
+
+    fn write_webdav_servers(&mut self, servers: Vec<webdav::Server>) -> Result<()> {
+        let value = serde_json::to_string(&servers).unwrap_or_default();
+        self.storage.write_setting(WEBDAV_SERVERS_KEY, &value)?;
+        self.webdav_servers = servers;
+        self.share_accounts();
+        Ok(())
+    }
+
+    pub fn subsonic_servers(&self) -> &[subsonic::Server] {
+        &self.subsonic_servers
+    }
+
+    pub fn jellyfin_servers(&self) -> &[jellyfin::Server] {
+        &self.jellyfin_servers
+    }
+
+    /// The Subsonic and Jellyfin servers mirrored, in the order they were added by kind.
+    pub fn remote_servers(&self) -> Vec<remote::Server> {
+        let subsonic = self
+            .subsonic_servers
+            .iter()
+            .cloned()
+            .map(remote::Server::from);
+        let jellyfin = self
+            .jellyfin_servers
+            .iter()
+            .cloned()
+            .map(remote::Server::from);
+        subsonic.chain(jellyfin).collect()
+    }
+
+    // the accounts of every server for the audio worker, once they changed
+    fn share_accounts(&self) {
+        let shares = self
+            .webdav_servers
+            .iter()
+            .cloned()
+            .map(remote::Account::WebDav);
+        let mirrored = self.remote_servers().into_iter().map(remote::Account::from);
+        remote::set_accounts(shares.chain(mirrored).collect());
+    }
+
+    /// Adds a server, or changes the account of the one at the same address.
+    pub fn add_remote_server(&mut self, server: remote::Server) -> Result<()> {
+        self.forget_remote_server(server.url())?;
+        match server {
+            remote::Server::Subsonic(server) => {
+                let mut servers = self.subsonic_servers.clone();
+                servers.push(server);
+                self.write_subsonic_servers(servers)
+            }
+            remote::Server::Jellyfin(server) => {
+                let mut servers = self.jellyfin_servers.clone();
+                servers.push(server);
+                self.write_jellyfin_servers(servers)
+            }
+        }
+    }
+
+    /// Forgets a server, the songs mirrored from it stay in the library.
+    pub fn remove_remote_server(&mut self, url: &str) -> Result<()> {
+        self.forget_remote_server(url)?;
+        if matches!(&self.section, Section::Server(shown) if shown == url) {
+            self.set_section(Section::Library)?;
+        }
+        Ok(())
+    }
+
+    fn forget_remote_server(&mut self, url: &str) -> Result<()> {
+        if self.subsonic_servers.iter().any(|server| server.url == url) {
+            let servers = self
+                .subsonic_servers
+                .iter()
+                .filter(|server| server.url != url)
+                .cloned()
+                .collect();
+            self.write_subsonic_servers(servers)?;
+        }
+        if self.jellyfin_servers.iter().any(|server| server.url == url) {
+            let servers = self
+                .jellyfin_servers
+                .iter()
+                .filter(|server| server.url != url)
+                .cloned()
+                .collect();
+            self.write_jellyfin_servers(servers)?;
+        }
+        Ok(())
+    }
+
+    fn write_subsonic_servers(&mut self, servers: Vec<subsonic::Server>) -> Result<()> {
+        // the passwords of the servers forgotten go with them
+        for server in &self.subsonic_servers {
+            let key = server.secret_key();
+            if !servers.iter().any(|kept| kept.secret_key() == key) {
+                secrets::write(&key, "")?;
+            }
+        }
+        for server in &servers {
+            secrets::write(&server.secret_key(), &server.password)?;
+        }
+        let value = serde_json::to_string(&servers)?;
+        self.storage.write_setting(SUBSONIC_SERVERS_KEY, &value)?;
+        self.subsonic_servers = servers;
+        self.share_accounts();
+        Ok(())
+    }
+
+    fn write_jellyfin_servers(&mut self, servers: Vec<jellyfin::Server>) -> Result<()> {
+        let value = serde_json::to_string(&servers).unwrap_or_default();
+        self.storage.write_setting(JELLYFIN_SERVERS_KEY, &value)?;
+        self.jellyfin_servers = servers;
+        self.share_accounts();
+        Ok(())
+    }
+
+    /// Brings what a server holds into the library. New songs are added, the server's
+    /// playlists fill a folder named after it and its starred or favorite songs are the liked
+    /// ones. Returns how many songs were added.
+    pub fn apply_mirror(&mut self, server: &remote::Server, mirror: Mirror) -> Result<usize> {
+        let added = self.storage.bulk_append_to_library(&mirror.tracks)?.len();
+        let paths: Vec<String> = mirror.tracks.iter().map(|d| d.path.clone()).collect();
+        let ids: HashMap<String, PlayableId> = self
+            .storage
+            .filter_library_by_paths(&paths)?
+            .into_iter()
+            .map(|playable| (playable.source_url, playable.id))
+            .collect();
+        let id_of = |path: &String| ids.get(path).copied();
+
+        // the server's stars win over likes of its songs made here
+        let starred: HashSet<PlayableId> = mirror.liked.iter().filter_map(id_of).collect();
+        for id in ids.values() {
+            let liked = self.liked_ids.contains(id);
+            if starred.contains(id) && !liked {
+                self.storage.append_like(*id)?;
+                self.liked_ids.insert(*id);
+            } else if !starred.contains(id) && liked {
+                self.storage.remove_from_likes(*id)?;
+                self.liked_ids.remove(id);
+            }
+        }
+
+        if !mirror.playlists.is_empty() {
+            let name = server.name().to_string();
+            let playlists = self.storage.read_playlists()?;
+            let folder = match playlists.iter().find(|playlist| {
+                playlist.kind == storage::PlaylistKind::Folder
+                    && playlist.parent_id.is_none()
+                    && playlist.name == name
+            }) {
+                Some(folder) => folder.id,
+                None => self.storage.create_playlist(
+                    &name,
+                    Some(storage::PlaylistKind::Folder),
+                    None,
+                )?,
+            };
+            for (playlist_name, songs) in &mirror.playlists {
+                let existing = playlists.iter().find(|playlist| {
+                    playlist.parent_id == Some(folder) && playlist.name == *playlist_name
+                });
+                let id = match existing {
+                    Some(playlist) => {
+                        self.storage.clear_playlist(playlist.id)?;
+                        playlist.id
+                    }
+                    None => self.storage.create_playlist(
+                        playlist_name,
+                        Some(storage::PlaylistKind::Static),
+                        Some(folder),
+                    )?,
+                };
+                for playable_id in songs.iter().filter_map(id_of) {
+                    self.storage.append_to_playlist(id, playable_id)?;
+                }
+            }
+            self.playlist_names = Self::to_playlist_tree(self.storage.read_playlists()?);
+        }
+
+        self.refresh_local_copies()?;
+        if matches!(
+            self.section,
+            Section::Library | Section::Server(_) | Section::Favorites | Section::Playlist(_)
+        ) {
+            self.load_playables()?;
+        }
+        Ok(added)
+    }
+
+    /// The files and folders last scanned into the library, newest first.
+    pub fn recent_files(&self) -> &[PathBuf] {
+        &self.recent_files
+    }
+
+    /// Puts `path` first among the recent files, the oldest beyond the limit are forgotten.
+    pub fn add_recent_file(&mut self, path: PathBuf) -> Result<()> {
+        let mut paths: Vec<PathBuf> = self
+            .recent_files
+            .iter()
+            .filter(|recent| **recent != path)
+            .cloned()
+            .collect();
+        paths.insert(0, path);
+        paths.truncate(RECENT_FILES_LIMIT);
+        self.write_recent_files(paths)
+    }
+
+    pub fn clear_recent_files(&mut self) -> Result<()> {
+        self.write_recent_files(Vec::new())
+    }
+
+    fn write_recent_files(&mut self, paths: Vec<PathBuf>) -> Result<()> {
+        let value: Vec<String> = paths
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        self.storage
+            .write_setting(RECENT_FILES_KEY, &value.join("\n"))?;
+        self.recent_files = paths;
+        Ok(())
+    }
+
+    /// The latest import batch of the files read from `path`.
+    pub fn last_import_of(&self, path: &Path) -> Result<Option<i64>> {
+        let source = path.to_string_lossy();
+        Ok(self
+            .storage
+            .read_imports()?
+            .into_iter()
+            .find(|batch| batch.source == source)
+            .map(|batch| batch.id))
+    }
+
+    /// The files of `paths` the library does not know yet. Playables removed from the library
+    /// count as known, a watched folder does not bring them back.
+    pub fn unknown_paths(&self, paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+        let paths: Vec<String> = paths
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        let known: HashSet<String> = self
+            .storage
+            .filter_library_by_paths(&paths)?
+            .into_iter()
+            .chain(self.storage.read_deleted()?.into_iter().map(|(p, _)| p))
+            .map(|playable| playable.source_url)
+            .collect();
+        Ok(paths
+            .into_iter()
+            .filter(|path| !known.contains(path))
+            .map(PathBuf::from)
+            .collect())
+    }
+
+    /// The files the library holds inside `folder`.
+    pub fn library_paths_under(&self, folder: &Path) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .storage
+            .read_library()?
+            .into_iter()
+            .filter(|playable| playable.get_kind() == PlayableKind::LocalFile)
+            .map(|playable| PathBuf::from(playable.source_url))
+            .filter(|path| path.starts_with(folder))
+            .collect())
+    }
+
+    /// Moves the playables of files which are gone to Recently Deleted, returns how many.
+    pub fn remove_missing(&mut self, paths: &[PathBuf]) -> Result<usize> {
+        if paths.is_empty() {
+            return Ok(0);
+        }
+        let paths: Vec<String> = paths
+            .iter()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        let ids: Vec<PlayableId> = self
+            .storage
+            .filter_library_by_paths(&paths)?
+            .iter()
+            .filter(|playable| playable.get_kind() == PlayableKind::LocalFile)
+            .map(|playable| playable.id)
+            .collect();
+        if ids.is_empty() {
+            return Ok(0);
+        }
+        self.storage.soft_delete(&ids)?;
+        self.liked_ids.retain(|id| !ids.contains(id));
+        let count = ids.len();
+        self.apply(Change::Removed(ids))?;
+        Ok(count)
+    }
+
+    fn read_convert_options(storage: &dyn Storage) -> ConvertOptions {
+        let setting = |key: &str| {
+            storage.read_setting(key).unwrap_or_else(|err| {
+                error!("Error reading setting {key}: {err:?}");
+                None
+            })
+        };
+        ConvertOptions {
+            format: setting(CONVERT_FORMAT_KEY)
+                .map(|value| Format::from(value.as_str()))
+                .unwrap_or_default(),
+            bitrate: setting(CONVERT_BITRATE_KEY)
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(convert::DEFAULT_BITRATE),
+            folder: setting(CONVERT_FOLDER_KEY)
+                .map(Into::into)
+                .or_else(|| {
+                    let user_dirs = UserDirs::new()?;
+                    Some(user_dirs.audio_dir()?.join("Phoniq Converted"))
+                })
+                .unwrap_or_default(),
+            template: setting(CONVERT_TEMPLATE_KEY)
+                .unwrap_or_else(|| convert::DEFAULT_TEMPLATE.to_string()),
+            replace: setting(CONVERT_REPLACE_KEY).is_some_and(|value| value == "true"),
+        }
+    }
+
+    pub fn convert_options(&self) -> &ConvertOptions {
+        &self.convert_options
+    }
+
+    pub fn set_convert_options(&mut self, options: ConvertOptions) -> Result<()> {
+        self.storage
+            .write_setting(CONVERT_FORMAT_KEY, options.format.as_str())?;
+        self.storage
+            .write_setting(CONVERT_BITRATE_KEY, &options.bitrate.to_string())?;
+        self.storage
+            .write_setting(CONVERT_FOLDER_KEY, &options.folder.to_string_lossy())?;
+        self.storage
+            .write_setting(CONVERT_TEMPLATE_KEY, &options.template)?;
+        self.storage
+            .write_setting(CONVERT_REPLACE_KEY, &options.replace.to_string())?;
+        self.convert_options = options;
+        Ok(())
+    }
+
+    /// Puts a converted file into the library, either in place of the playable it was converted
+    /// from or as a new playable with the same metadata.
+    pub fn finish_conversion(
+        &mut self,
+        id: PlayableId,
+        output: &Path,
+        replace: bool,
+    ) -> Result<()> {
+        let path = output.to_string_lossy().to_string();
+        if replace {
+            self.storage.relocate(id, &path)?;
+            if let Some(playable) = self.playables.iter_mut().find(|p| p.id == id) {
+                playable.source_url = path;
+            }
+            return Ok(());
+        }
+        let Some(original) = self
+            .storage
+            .read_library_from_ids(&[id])?
+            .into_iter()
+            .next()
+        else {
+            return Ok(());
+        };
+        let copy = AudioFileDescriptor {
+            title: original.title.clone(),
+            artist: original.get_artist().to_string(),
+            album: original.get_album().to_string(),
+            year: original.year.unwrap_or(0),
+            genre: original.get_genre().to_string(),
+            duration: original.get_duration(),
+            bitrate: 0,
+            sample_rate: 0,
+            lossless: None,
+            release_date: original.release_date.clone().unwrap_or_default(),
+            track_number: 0,
+            disc_number: 0,
+            label: String::new(),
+            path,
+            artwork: self.storage.read_artwork(id)?,
+            kind: AudioFileKind::LocalFile,
+        };
+        let copy_id = self.storage.append_to_library(&copy)?;
+        if let Section::Library = self.section {
+            self.apply(Change::Added(vec![copy_id]))?;
+        }
+        Ok(())
+    }
+
+    pub fn record_source(&self) -> Option<&str> {
+        self.record_source.as_deref()
+    }
+
+    pub fn set_record_source(&mut self, name: &str) -> Result<()> {
+        self.storage.write_setting(RECORD_SOURCE_KEY, name)?;
+        self.record_source = Some(name.to_string());
+        Ok(())
+    }
+
+    pub fn record_format(&self) -> RecordFormat {
+        self.record_format
+    }
+
+    pub fn set_record_format(&mut self, format: RecordFormat) -> Result<()> {
+        self.storage
+            .write_setting(RECORD_FORMAT_KEY, format.as_str())?;
+        self.record_format = format;
+        Ok(())
+    }
+
+    pub fn discogs_token(&self) -> Option<&str> {
+        self.discogs_token.as_deref()
+    }
+
+    /// Saves the token, an empty one forgets it.
+    pub fn set_discogs_token(&mut self, token: &str) -> Result<()> {
+        let token = token.trim();
+        secrets::write(DISCOGS_TOKEN_KEY, token)?;
+        self.discogs_token = (!token.is_empty()).then(|| token.to_string());
+        Ok(())
+    }
+
+    pub fn discogs_connected(&self) -> bool {
+        self.discogs_oauth.is_some()
+    }
+
+    /// Saves the tokens of an OAuth connection, None disconnects.
+    pub fn set_discogs_oauth(&mut self, tokens: Option<OAuthTokens>) -> Result<()> {
+        let value = match &tokens {
+            Some(tokens) => serde_json::to_string(tokens)?,
+            None => String::new(),
+        };
+        secrets::write(DISCOGS_OAUTH_KEY, &value)?;
+        self.discogs_oauth = tokens;
+        Ok(())
+    }
+
+    /// What Discogs searches are signed with, the OAuth connection when there is one.
+    pub fn discogs_credentials(&self) -> Option<Credentials> {
+        self.discogs_oauth
+            .clone()
+            .map(Credentials::OAuth)
+            .or_else(|| self.discogs_token.clone().map(Credentials::Token))
+    }
+
+    /// The library's copy of each track of `release`, matched by title and by the artist or
+    /// album, None for tracks the library lacks.
+    pub fn release_in_library(&self, release: &Release) -> Result<Vec<Option<Playable>>> {
+        let library = self.storage.read_library()?;
+        let same = |a: &str, b: &str| a.trim().to_lowercase() == b.trim().to_lowercase();
+        let artist = release.artist();
+        Ok(release
+            .side("")
+            .into_iter()
+            .map(|track| {
+                library
+                    .iter()
+                    .find(|p| {
+                        same(p.get_title(), &track.title)
+                            && (same(p.get_artist(), &artist)
+                                || same(p.get_album(), &release.title))
+                    })
+                    .cloned()
+            })
+            .collect())
+    }
+
+    /// Adds a finished recording to the library as an import of its own.
+    pub fn import_recording(&mut self, path: &Path, title: &str, duration: u64) -> Result<()> {
+        let source = path.parent().unwrap_or(path).to_string_lossy().to_string();
+        self.import(
+            &source,
+            vec![ScannedFile {
+                title: title.to_string(),
+                artist: String::new(),
+                album: String::new(),
+                year: 0,
+                genre: String::new(),
+                duration,
+                bitrate: 0,
+                sample_rate: 0,
+                lossless: None,
+                release_date: String::new(),
+                track_number: 0,
+                disc_number: 0,
+                label: String::new(),
+                path: path.to_string_lossy().to_string(),
+                artwork: None,
+                kind: ScannedKind::LocalFile,
+            }],
+        )
+    }
+
+    fn read_alarm(storage: &dyn Storage) -> Alarm {
+        let setting = |key: &str| {
+            storage.read_setting(key).unwrap_or_else(|err| {
+                error!("Error reading setting {key}: {err:?}");
+                None
+            })
+        };
+        let default = Alarm::default();
+        Alarm {
+            enabled: setting(ALARM_ENABLED_KEY).is_some_and(|value| value == "true"),
+            time: setting(ALARM_TIME_KEY)
+                .and_then(|value| schedule::parse_time(&value))
+                .unwrap_or(default.time),
+            playlist: setting(ALARM_PLAYLIST_KEY).and_then(|value| value.parse().ok()),
+            ramp_minutes: setting(ALARM_RAMP_KEY)
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(default.ramp_minutes),
+            daily: setting(ALARM_DAILY_KEY).is_none_or(|value| value == "true"),
+        }
+    }
+
+    fn next_alarm_after(alarm: &Alarm, now: i64) -> Option<i64> {
+        (alarm.enabled && alarm.playlist.is_some())
+            .then(|| schedule::next_ring(alarm.time, now, local_offset(now)))
+    }
+
+    pub fn alarm(&self) -> &Alarm {
+        &self.alarm
+    }
+
+    pub fn next_alarm(&self) -> Option<i64> {
+        self.next_alarm
+    }
+
+    pub fn set_alarm(&mut self, alarm: Alarm) -> Result<()> {
+        self.storage
+            .write_setting(ALARM_ENABLED_KEY, &alarm.enabled.to_string())?;
+        self.storage
+            .write_setting(ALARM_TIME_KEY, &schedule::time_to_str(alarm.time))?;
+        let playlist = alarm.playlist.map(|id| id.to_string()).unwrap_or_default();
+        self.storage.write_setting(ALARM_PLAYLIST_KEY, &playlist)?;
+        self.storage
+            .write_setting(ALARM_RAMP_KEY, &alarm.ramp_minutes.to_string())?;
+        self.storage
+            .write_setting(ALARM_DAILY_KEY, &alarm.daily.to_string())?;
+        self.next_alarm = Self::next_alarm_after(&alarm, unix_now());
+        self.alarm = alarm;
+        Ok(())
+    }
+
+    /// The alarm once its time has come, after which it is set for the next day or turned off.
+    pub fn take_due_alarm(&mut self, now: i64) -> Result<Option<Alarm>> {
+        if self.next_alarm.is_none_or(|ring| ring > now) {
+            return Ok(None);
+        }
+        let alarm = self.alarm.clone();
+        if alarm.daily {
+            self.next_alarm = Self::next_alarm_after(&alarm, now);
+        } else {
+            self.set_alarm(Alarm {
+                enabled: false,
+                ..alarm.clone()
+            })?;
+        }
+        Ok(Some(alarm))
+    }
+
+    pub fn context_end(&self) -> ContextEnd {
+        self.context_end
+    }
+
+    pub fn set_context_end(&mut self, context_end: ContextEnd) -> Result<()> {
+        self.storage
+            .write_setting(CONTEXT_END_KEY, context_end.as_str())?;
+        self.context_end = context_end;
+        Ok(())
+    }
+
+    pub fn close_behavior(&self) -> CloseBehavior {
+        self.close_behavior
+    }
+
+    pub fn set_close_behavior(&mut self, close_behavior: CloseBehavior) -> Result<()> {
+        self.storage
+            .write_setting(CLOSE_BEHAVIOR_KEY, close_behavior.as_str())?;
+        self.close_behavior = close_behavior;
+        Ok(())
+    }
+
+    pub fn fade_ms(&self) -> u64 {
+        self.fade_ms
+    }
+
+    pub fn set_fade_ms(&mut self, fade_ms: u64) -> Result<()> {
+        self.storage.write_setting(FADE_KEY, &fade_ms.to_string())?;
+        self.fade_ms = fade_ms;
+        Ok(())
+    }
+
+    pub fn crossfade_secs(&self) -> u64 {
+        self.crossfade_secs
+    }
+
+    pub fn set_crossfade_secs(&mut self, secs: u64) -> Result<()> {
+        let secs = secs.min(MAX_CROSSFADE_SECS);
+        self.storage
+            .write_setting(CROSSFADE_KEY, &secs.to_string())?;
+        self.crossfade_secs = secs;
+        Ok(())
+    }
+
+    pub fn output_latency_ms(&self) -> u64 {
+        self.output_latency_ms
+    }
+
+    pub fn set_output_latency_ms(&mut self, latency_ms: u64) -> Result<()> {
+        self.storage
+            .write_setting(OUTPUT_LATENCY_KEY, &latency_ms.to_string())?;
+        self.output_latency_ms = latency_ms;
+        Ok(())
+    }
+
+    pub fn match_source_rate(&self) -> bool {
+        self.match_source_rate
+    }
+
+    pub fn set_match_source_rate(&mut self, match_source_rate: bool) -> Result<()> {
+        self.storage
+            .write_setting(MATCH_SOURCE_RATE_KEY, &match_source_rate.to_string())?;
+        self.match_source_rate = match_source_rate;
+        Ok(())
+    }
+
+    pub fn hover_preview(&self) -> bool {
+        self.hover_preview
+    }
+
+    pub fn set_hover_preview(&mut self, hover_preview: bool) -> Result<()> {
+        self.storage
+            .write_setting(HOVER_PREVIEW_KEY, &hover_preview.to_string())?;
+        self.hover_preview = hover_preview;
+        Ok(())
+    }
+
+    pub fn long_track_minutes(&self) -> u32 {
+        self.long_track_minutes
+    }
+
+    pub fn set_long_track_minutes(&mut self, minutes: u32) -> Result<()> {
+        self.storage
+            .write_setting(LONG_TRACK_KEY, &minutes.to_string())?;
+        self.long_track_minutes = minutes;
+        Ok(())
+    }
+
+    pub fn skip_silence(&self) -> Option<i32> {
+        self.skip_silence
+    }
+
+    pub fn set_skip_silence(&mut self, threshold_db: Option<i32>) -> Result<()> {
+        let value = threshold_db.map(|db| db.to_string()).unwrap_or_default();
+        self.storage.write_setting(SKIP_SILENCE_KEY, &value)?;
+        self.skip_silence = threshold_db;
+        Ok(())
+    }
+
+    pub fn volume_trims(&self) -> &VolumeTrims {
+        &self.volume_trims
+    }
+
+    pub fn set_volume_trim(&mut self, target: TrimTarget, db: i32) -> Result<()> {
+        let mut trims = self.volume_trims.clone();
+        trims.set(target, db);
+        self.storage
+            .write_setting(VOLUME_TRIMS_KEY, &trims.to_setting())?;
+        self.volume_trims = trims;
+        Ok(())
+    }
+
+    pub fn remove_volume_trim(&mut self, target: &TrimTarget) -> Result<()> {
+        let mut trims = self.volume_trims.clone();
+        trims.remove(target);
+        self.storage
+            .write_setting(VOLUME_TRIMS_KEY, &trims.to_setting())?;
+        self.volume_trims = trims;
+        Ok(())
+    }
+
+    pub fn eq_curves(&self) -> &EqCurves {
+        &self.eq_curves
+    }
+
+    pub fn set_eq_preset(&mut self, output: &str, preset: Preset) -> Result<()> {
+        let mut curves = self.eq_curves.clone();
+        curves.set_preset(output, preset);
+        self.storage
+            .write_setting(EQUALIZER_KEY, &curves.to_setting())?;
+        self.eq_curves = curves;
+        Ok(())
+    }
+
+    pub fn set_eq_band(&mut self, output: &str, band: usize, db: i32) -> Result<()> {
+        let mut curves = self.eq_curves.clone();
+        curves.set_band(output, band, db);
+        self.storage
+            .write_setting(EQUALIZER_KEY, &curves.to_setting())?;
+        self.eq_curves = curves;
+        Ok(())
+    }
+
+    pub fn resample_quality(&self) -> ResampleQuality {
+        self.resample_quality
+    }
+
+    pub fn set_resample_quality(&mut self, quality: ResampleQuality) -> Result<()> {
+        self.storage
+            .write_setting(RESAMPLE_QUALITY_KEY, quality.as_str())?;
+        self.resample_quality = quality;
+        Ok(())
+    }
+
+    pub fn history_retention(&self) -> Option<u32> {
+        self.history_retention
+    }
+
+    /// Keeps plays for `days`, or forever when None. Older plays are deleted right away.
+    pub fn set_history_retention(&mut self, days: Option<u32>) -> Result<()> {
+        let value = days.map(|days| days.to_string()).unwrap_or_default();
+        self.storage.write_setting(HISTORY_RETENTION_KEY, &value)?;
+        self.history_retention = days;
+        self.prune_history()
+    }
+
+    fn prune_history(&mut self) -> Result<()> {
+        if let Some(days) = self.history_retention {
+            let removed = self
+                .storage
+                .prune_history(unix_now() - days as i64 * 86_400)?;
+            info!("Pruned {removed} plays older than {days} days");
+        }
+        Ok(())
+    }
+
+    /// When a playable shown in Recently Played was last played.
+    pub fn last_played(&self, id: PlayableId) -> Option<i64> {
+        self.last_played.get(&id).copied()
+    }
+
+    pub fn deleted(&self) -> &[(Playable, i64)] {
+        &self.deleted
+    }
+
+    pub fn deleted_retention(&self) -> Option<u32> {
+        self.deleted_retention
+    }
+
+    /// Keeps deleted playables for `days`, or until purged by hand when None. Older ones are
+    /// purged right away.
+    pub fn set_deleted_retention(&mut self, days: Option<u32>) -> Result<()> {
+        let value = days.map(|days| days.to_string()).unwrap_or_default();
+        self.storage.write_setting(DELETED_RETENTION_KEY, &value)?;
+        self.deleted_retention = days;
+        self.purge_expired()?;
+        self.load_playables()
+    }
+
+    fn purge_expired(&mut self) -> Result<()> {
+        if let Some(days) = self.deleted_retention {
+            let purged = self
+                .storage
+                .purge_deleted(unix_now() - days as i64 * 86_400)?;
+            info!("Purged {purged} playables deleted more than {days} days ago");
+        }
+        Ok(())
+    }
+
+    pub fn restore_deleted(&mut self, ids: &[PlayableId]) -> Result<()> {
+        self.storage.restore_deleted(ids)?;
+        // their likes show again
+        self.liked_ids = self.storage.read_liked_ids()?;
+        self.load_playables()
+    }
+
+    /// Deletes playables from Recently Deleted for good.
+    pub fn purge_deleted(&mut self, ids: &[PlayableId]) -> Result<()> {
+        self.storage.bulk_remove_from_library(ids)?;
+        self.load_playables()
+    }
+
+    pub fn empty_deleted(&mut self) -> Result<()> {
+        self.storage.purge_deleted(i64::MAX)?;
+        self.load_playables()
+    }
+
+    pub fn snapshots(&self) -> &[Snapshot] {
+        &self.snapshots
+    }
+
+    pub fn take_snapshot(&mut self, label: &str) -> Result<()> {
+        self.storage.create_snapshot(label.trim())?;
+        self.load_playables()
+    }
+
+    pub fn delete_snapshot(&mut self, id: i64) -> Result<()> {
+        self.storage.delete_snapshot(id)?;
+        if self
+            .comparison
+            .as_ref()
+            .is_some_and(|comparison| comparison.snapshot.id == id)
+        {
+            self.comparison = None;
+        }
+        self.load_playables()
+    }
+
+    /// Diffs a snapshot against the library as it is now.
+    pub fn compare_snapshot(&mut self, id: i64) -> Result<()> {
+        let Some(snapshot) = self.snapshots.iter().find(|s| s.id == id).cloned() else {
+            return Ok(());
+        };
+        let before = self.storage.read_snapshot_entries(Some(id))?;
+        let after = self.storage.read_snapshot_entries(None)?;
+        let counts = (
+            after.len() as i64,
+            self.tag_names.len() as i64,
+            self.storage.read_playlists()?.len() as i64,
+        );
+        self.comparison = Some(SnapshotComparison {
+            snapshot,
+            counts,
+            diff: snapshots::diff(before, after),
+        });
+        Ok(())
+    }
+
+    pub fn comparison(&self) -> Option<&SnapshotComparison> {
+        self.comparison.as_ref()
+    }
+
+    pub fn close_comparison(&mut self) {
+        self.comparison = None;
+    }
+
+    pub fn sharing(&self) -> bool {
+        self.sync_server.is_some()
+    }
+
+    pub fn set_sharing(&mut self, sharing: bool) -> Result<()> {
+        let value = if sharing { "true" } else { "false" };
+        self.storage.write_setting(SYNC_SHARING_KEY, value)?;
+        self.sync_server = None;
+        if sharing {
+            self.start_sharing()?;
+        }
+        Ok(())
+    }
+
+    fn start_sharing(&mut self) -> Result<()> {
+        let document = self.sync_document()?.to_text();
+        // the code stays the same from one start to the next, peers keep it
+        let code = match Self::read_secret(SYNC_PAIRING_KEY) {
+            Some(code) => code,
+            None => {
+                let code = sync::pairing_code();
+                secrets::write(SYNC_PAIRING_KEY, &code)?;
+                code
+            }
+        };
+        let server =
+            SyncServer::start(document, code).map_err(|e| StateError::SyncError(e.to_string()))?;
+        self.sync_server = Some(server);
+        Ok(())
+    }
+
+    /// The code peers give to read the library, None unless it is shared.
+    pub fn pairing_code(&self) -> Option<&str> {
+        self.sync_server.as_ref().map(SyncServer::pairing_code)
+    }
+
+    /// Shares the library under a new code, the peers paired so far can not read it anymore.
+    pub fn renew_pairing_code(&mut self) -> Result<()> {
+        secrets::write(SYNC_PAIRING_KEY, &sync::pairing_code())?;
+        if self.sync_server.take().is_some() {
+            self.start_sharing()?;
+        }
+        Ok(())
+    }
+
+    /// The code last given to the instance sharing as `name`, empty when there is none.
+    pub fn peer_code(&self, name: &str) -> &str {
+        self.peer_codes.get(name).map_or("", String::as_str)
+    }
+
+    /// Keeps the code an instance took, for the next sync from it.
+    pub fn set_peer_code(&mut self, name: &str, code: &str) -> Result<()> {
+        if self.peer_code(name) == code {
+            return Ok(());
+        }
+        self.peer_codes.insert(name.to_string(), code.to_string());
+        let value = serde_json::to_string(&self.peer_codes)?;
+        secrets::write(SYNC_PEER_CODES_KEY, &value)?;
+        Ok(())
+    }
+
+    /// Rebuilds the shared document, peers see the library as it was at the last refresh.
+    pub fn refresh_shared(&mut self) -> Result<()> {
+        if self.sync_server.is_none() {
+            return Ok(());
+        }
+        let document = self.sync_document()?.to_text();
+        if let Some(server) = &self.sync_server {
+            server.set_document(document);
+        }
+        Ok(())
+    }
+
+    // discovery also finds the announcement of this instance
+    pub fn is_own_peer(&self, peer: &Peer) -> bool {
+        self.sync_server
+            .as_ref()
+            .is_some_and(|server| server.is_own(peer))
+    }
+
+    /// The likes, ratings, tags and static playlists of the library, as shared with peers.
+    pub fn sync_document(&mut self) -> Result<SyncDocument> {
+        let likes = self.storage.read_likes()?;
+        let rated = self.storage.read_by_rating(1)?;
+        let mut tags = Vec::new();
+        for tag in &self.tag_names {
+            tags.push((tag.name.clone(), self.storage.read_tag(tag.id)?));
+        }
+        let mut playlists = Vec::new();
+        for playlist in self.storage.read_playlists()? {
+            if playlist.kind == storage::PlaylistKind::Static {
+                playlists.push((playlist.name, self.storage.read_playlist(playlist.id)?));
+            }
+        }
+        let grouped = tags.iter().chain(&playlists).flat_map(|(_, p)| p);
+        let all: Vec<&Playable> = likes.iter().chain(&rated).chain(grouped).collect();
+        let fingerprints = self.fingerprints(&all)?;
+
+        let mut tracks = Vec::new();
+        let mut indexes: HashMap<PlayableId, usize> = HashMap::new();
+        let mut index_of = |playable: &Playable| {
+            *indexes.entry(playable.id).or_insert_with(|| {
+                tracks.push(SyncTrack {
+                    path: playable.source_url.clone(),
+                    fingerprint: fingerprints.get(&playable.id).cloned(),
+                });
+                tracks.len() - 1
+            })
+        };
+        let likes = likes.iter().map(&mut index_of).collect();
+        let ratings = rated
+            .iter()
+            .map(|playable| (index_of(playable), playable.rating))
+            .collect();
+        let mut to_indexes = |groups: Vec<(String, Vec<Playable>)>| -> Vec<(String, Vec<usize>)> {
+            groups
+                .into_iter()
+                .map(|(name, playables)| (name, playables.iter().map(&mut index_of).collect()))
+                .collect()
+        };
+        let tags = to_indexes(tags);
+        let playlists = to_indexes(playlists);
+        Ok(SyncDocument {
+            tracks,
+            likes,
+            ratings,
+            tags,
+            playlists,
+        })
+    }
+
+    // fingerprints of local files, computed and cached on first use
+    fn fingerprints(&mut self, playables: &[&Playable]) -> Result<HashMap<PlayableId, String>> {
+        let ids: Vec<PlayableId> = playables.iter().map(|p| p.id).collect();
+        let mut fingerprints = self.storage.read_fingerprints(&ids)?;
+        for playable in playables {
+            if playable.type_id != AudioFileKind::LocalFile
+                || fingerprints.contains_key(&playable.id)
+            {
+                continue;
+            }
+            match sync::fingerprint(Path::new(&playable.source_url)) {
+                Ok(fingerprint) => {
+                    self.storage.set_fingerprint(playable.id, &fingerprint)?;
+                    fingerprints.insert(playable.id, fingerprint);
+                }
+                Err(e) => info!("No fingerprint for {}: {e}", playable.source_url),
+            }
+        }
+        Ok(fingerprints)
+    }
+
+    /// Fills the gaps in `matched` with the playables whose fingerprint is the one at the same
+    /// index, only files of a wanted size are fingerprinted.
+    fn match_fingerprints(
+        &mut self,
+        library: &[Playable],
+        fingerprints: &[Option<&str>],
+        matched: &mut [Option<PlayableId>],
+    ) -> Result<()> {
+        let sizes: HashSet<u64> = fingerprints
+            .iter()
+            .zip(matched.iter())
+            .filter(|(_, id)| id.is_none())
+            .filter_map(|(fingerprint, _)| sync::fingerprint_size((*fingerprint)?))
+            .collect();
+        if sizes.is_empty() {
+            return Ok(());
+        }
+        let candidates: Vec<&Playable> = library
+            .iter()
+            .filter(|p| p.type_id == AudioFileKind::LocalFile)
+            .filter(|p| std::fs::metadata(&p.source_url).is_ok_and(|m| sizes.contains(&m.len())))
+            .collect();
+        let found = self.fingerprints(&candidates)?;
+        let by_fingerprint: HashMap<&str, PlayableId> = found
+            .iter()
+            .map(|(id, fingerprint)| (fingerprint.as_str(), *id))
+            .collect();
+        for (fingerprint, id) in fingerprints.iter().zip(matched.iter_mut()) {
+            if id.is_none()
+                && let Some(fingerprint) = fingerprint
+            {
+                *id = by_fingerprint.get(fingerprint).copied();
+            }
+        }
+        Ok(())
+    }
+
+    /// The playlist with a fingerprint and the artist and title of each track, to be matched
+    /// against a friend's library.
+    pub fn share_playlist(&mut self, id: i64) -> Result<SharedPlaylist> {
+        let name = self
+            .storage
+            .read_playlists()?
+            .into_iter()
+            .find(|playlist| playlist.id == id)
+            .map(|playlist| playlist.name)
+            .unwrap_or_default();
+        let playables = self.read_playlist(id)?;
+        let all: Vec<&Playable> = playables.iter().collect();
+        let fingerprints = self.fingerprints(&all)?;
+        Ok(SharedPlaylist {
+            version: shared_playlist::VERSION,
+            name,
+            exported_at: unix_now(),
+            tracks: playables
+                .iter()
+                .map(|p| SharedTrack::new(p, fingerprints.get(&p.id).cloned()))
+                .collect(),
+        })
+    }
+
+    /// Creates a playlist of the tracks of `shared` found in the library, by fingerprint first
+    /// and by artist and title after. A playlist of the same name is left alone, the new one
+    /// gets a number.
+    pub fn import_shared_playlist(&mut self, shared: &SharedPlaylist) -> Result<SharedImport> {
+        let library = self.storage.read_library()?;
+        let mut matched = vec![None; shared.tracks.len()];
+        let fingerprints: Vec<Option<&str>> = shared
+            .tracks
+            .iter()
+            .map(|track| track.fingerprint.as_deref())
+            .collect();
+        self.match_fingerprints(&library, &fingerprints, &mut matched)?;
+        for (track, id) in shared.tracks.iter().zip(matched.iter_mut()) {
+            if id.is_none() {
+                *id = library.iter().find(|p| track.matches(p)).map(|p| p.id);
+            }
+        }
+
+        let name = if shared.name.trim().is_empty() {
+            "Shared Playlist"
+        } else {
+            shared.name.as_str()
+        };
+        let (playlist_id, name) = self.create_imported_playlist(name, &matched)?;
+        Ok(SharedImport {
+            playlist_id,
+            name,
+            matched: matched.iter().filter(|id| id.is_some()).count(),
+            missing: shared
+                .tracks
+                .iter()
+                .zip(&matched)
+                .filter(|(_, id)| id.is_none())
+                .map(|(track, _)| format!("{} - {}", track.artist, track.title))
+                .collect(),
+        })
+    }
+
+    // a static playlist of the tracks found, named `name` or with a number when taken
+    fn create_imported_playlist(
+        &mut self,
+        name: &str,
+        matched: &[Option<PlayableId>],
+    ) -> Result<(i64, String)> {
+        let names: HashSet<String> = self
+            .storage
+            .read_playlists()?
+            .into_iter()
+            .map(|playlist| playlist.name.to_lowercase())
+            .collect();
+        let base = if name.trim().is_empty() {
+            "Imported Playlist"
+        } else {
+            name.trim()
+        };
+        let mut name = base.to_string();
+        let mut number = 2;
+        while names.contains(&name.to_lowercase()) {
+            name = format!("{base} ({number})");
+            number += 1;
+        }
+        let playlist_id =
+            self.storage
+                .create_playlist(&name, Some(storage::PlaylistKind::Static), None)?;
+        for id in matched.iter().flatten() {
+            self.storage.append_to_playlist(playlist_id, *id)?;
+        }
+        self.playlist_names = Self::to_playlist_tree(self.storage.read_playlists()?);
+        Ok((playlist_id, name))
+    }
+
+    /// The files listed by a playlist file which exist but are not in the library, to scan
+    /// before importing it.
+    pub fn unknown_files(&self, playlist: &PlaylistFile) -> Result<Vec<PathBuf>> {
+        let known: HashSet<String> = self
+            .storage
+            .read_library()?
+            .into_iter()
+            .map(|playable| playable.source_url)
+            .collect();
+        let mut unknown: Vec<PathBuf> = Vec::new();
+        for entry in &playlist.entries {
+            let path = PathBuf::from(&entry.location);
+            if !known.contains(&entry.location) && path.is_file() && !unknown.contains(&path) {
+                unknown.push(path);
+            }
+        }
+        Ok(unknown)
+    }
+
+    /// Creates a playlist of the tracks of an M3U or PLS file, found in the library by path.
+    /// `scanned` are its files which were not in the library, they are added as one import.
+    /// A playlist of the same name is left alone, the new one gets a number.
+    pub fn import_playlist_file(
+        &mut self,
+        source: &str,
+        playlist: &PlaylistFile,
+        scanned: Vec<ScannedFile>,
+    ) -> Result<SharedImport> {
+        let items: Vec<AudioFileDescriptor> =
+            scanned.into_iter().map(AudioFileDescriptor::from).collect();
+        let ids = self.storage.bulk_append_to_library(&items)?;
+        let by_path: HashMap<String, PlayableId> = self
+            .storage
+            .read_library()?
+            .into_iter()
+            .map(|playable| (playable.source_url, playable.id))
+            .collect();
+        let matched: Vec<Option<PlayableId>> = playlist
+            .entries
+            .iter()
+            .map(|entry| by_path.get(&entry.location).copied())
+            .collect();
+        let (playlist_id, name) = self.create_imported_playlist(&playlist.name, &matched)?;
+        if !items.is_empty() {
+            self.storage
+                .create_import(source, Some(playlist_id), items.len(), &ids)?;
+        }
+        Ok(SharedImport {
+            playlist_id,
+            name,
+            matched: matched.iter().filter(|id| id.is_some()).count(),
+            missing: playlist
+                .entries
+                .iter()
+                .zip(&matched)
+                .filter(|(_, id)| id.is_none())
+                .map(|(entry, _)| entry.title.clone().unwrap_or(entry.location.clone()))
+                .collect(),
+        })
+    }
+
+    /// Adds the likes, ratings, tags and playlists of a peer to the library, nothing is ever
+    /// removed. A rating given here is kept over the peer's.
+    /// Tags and playlists are matched by name and created when missing.
+    pub fn merge_sync(&mut self, document: &SyncDocument) -> Result<SyncSummary> {
+        let library = self.storage.read_library()?;
+        let by_path: HashMap<&str, PlayableId> = library
+            .iter()
+            .map(|p| (p.source_url.as_str(), p.id))
+            .collect();
+        let mut matched: Vec<Option<PlayableId>> = document
+            .tracks
+            .iter()
+            .map(|track| by_path.get(track.path.as_str()).copied())
+            .collect();
+
+        // files moved on one of the machines
+        let fingerprints: Vec<Option<&str>> = document
+            .tracks
+            .iter()
+            .map(|track| track.fingerprint.as_deref())
+            .collect();
+        self.match_fingerprints(&library, &fingerprints, &mut matched)?;
+
+        let mut summary = SyncSummary {
+            unmatched: matched.iter().filter(|id| id.is_none()).count(),
+            ..Default::default()
+        };
+        for id in document.likes.iter().filter_map(|i| matched[*i]) {
+            if self.liked_ids.insert(id) {
+                self.storage.append_like(id)?;
+                summary.liked += 1;
+            }
+        }
+        let mut ratings: HashMap<PlayableId, u8> =
+            library.iter().map(|p| (p.id, p.rating)).collect();
+        for (index, rating) in &document.ratings {
+            if let Some(id) = matched[*index]
+                && *rating > 0
+                && ratings.get(&id) == Some(&0)
+            {
+                let rating = (*rating).min(MAX_RATING);
+                self.storage.set_rating(id, rating)?;
+                ratings.insert(id, rating);
+                summary.rated += 1;
+            }
+        }
+        for (name, indexes) in &document.tags {
+            let tag_id = match self.tag_names.iter().find(|tag| tag.name == *name) {
+                Some(tag) => tag.id,
+                None => {
+                    let id = self.storage.create_tag(name)?;
+                    self.reload_tags()?;
+                    id
+                }
+            };
+            let mut tagged: HashSet<PlayableId> = self
+                .storage
+                .read_tag(tag_id)?
+                .iter()
+                .map(|p| p.id)
+                .collect();
+            for id in indexes.iter().filter_map(|i| matched[*i]) {
+                if tagged.insert(id) {
+                    self.storage.append_to_tag(tag_id, id)?;
+                    summary.tagged += 1;
+                }
+            }
+        }
+        let playlists = self.storage.read_playlists()?;
+        for (name, indexes) in &document.playlists {
+            let existing = playlists
+                .iter()
+                .find(|p| p.name == *name && p.kind == storage::PlaylistKind::Static);
+            let playlist_id = match existing {
+                Some(playlist) => playlist.id,
+                None => {
+                    self.storage
+                        .create_playlist(name, Some(storage::PlaylistKind::Static), None)?
+                }
+            };
+            let mut contained: HashSet<PlayableId> = self
+                .storage
+                .read_playlist(playlist_id)?
+                .iter()
+                .map(|p| p.id)
+                .collect();
+            for id in indexes.iter().filter_map(|i| matched[*i]) {
+                if contained.insert(id) {
+                    self.storage.append_to_playlist(playlist_id, id)?;
+                    summary.added_to_playlists += 1;
+                }
+            }
+        }
+        self.playlist_names = Self::to_playlist_tree(self.storage.read_playlists()?);
+        self.refresh_shared()?;
+        self.load_playables()?;
+        Ok(summary)
+    }
+
+    pub fn clear_history(&mut self) -> Result<()> {
+        self.storage.clear_history()?;
+        Ok(())
+    }
+
+    pub fn incognito(&self) -> bool {
+        self.incognito
+    }
+
+    pub fn set_incognito(&mut self, incognito: bool) {
+        self.incognito = incognito;
+    }
+
+    /// Adds the playable which just finished to the history, unless listening incognito.
+    /// Podcast episodes are not part of the library and are left out.
+    pub fn record_play(&mut self) -> Result<()> {
+        if self.incognito || self.player.current_episode.is_some() {
+            return Ok(());
+        }
+        // streams from outside the library, e.g. chart samples, are not in the history
+        if let Some(id) = self.player.current_playable.filter(|id| *id >= 0) {
+            let now = unix_now();
+            self.storage.record_play(id, now)?;
+            let loaded = self
+                .playables
+                .iter_mut()
+                .chain(self.queue.iter_mut().flatten());
+            for playable in loaded.filter(|playable| playable.id == id) {
+                playable.play_count += 1;
+                playable.last_played_at = Some(now);
+            }
+            // newest first, the play moves the playable to the top
+            if self.section == Section::RecentlyPlayed {
+                self.apply(Change::Refresh)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts the playable being skipped as played once more than half of it was heard.
+    pub fn record_skip(&mut self) -> Result<()> {
+        let Some(id) = self.player.current_playable else {
+            return Ok(());
+        };
+        // played from the queue, queued by hand or from the visible list
+        let duration = self
+            .current_queued()
+            .into_iter()
+            .chain(&self.playables)
+            .find(|playable| playable.id == id)
+            .map_or(0, |playable| playable.duration);
+        if duration > 0 && self.player.position as i64 * 2 > duration {
+            self.record_play()?;
+        }
+        Ok(())
+    }
+
+    pub fn playlist_tracklist(&self, id: i64) -> Result<String> {
+        let playables = self.read_playlist(id)?;
+        let playables: Vec<&Playable> = playables.iter().collect();
+        Ok(clipboard::format_tracklist(
+            &playables,
+            &self.tracklist_template,
+        ))
+    }
+
+    pub fn append_to_tag(&mut self, tag_id: i64, playable_id: i64) -> Result<()> {
+        self.storage.append_to_tag(tag_id, playable_id)?;
+        match self.section {
+            Section::Tag(id) if id == tag_id => self.apply(Change::Added(vec![playable_id]))?,
+            // the playable may still miss one of the other selected tags
+            Section::Tags(_) if self.section.has_tag(tag_id) => self.apply(Change::Refresh)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Tags several playables at once, e.g. those dragged onto the tag.
+    pub fn add_to_tag(&mut self, tag_id: i64, ids: &[PlayableId]) -> Result<()> {
+        for id in ids {
+            self.append_to_tag(tag_id, *id)?;
+        }
+        Ok(())
+    }
+
+    pub fn create_tag(&mut self, name: &str) -> Result<()> {
+        self.storage.create_tag(&tag_path(name))?;
+        self.reload_tags()
+    }
+
+    /// Renames a tag, tags nested under it move along.
+    pub fn rename_tag(&mut self, id: i64, name: &str) -> Result<()> {
+        self.storage.rename_tag(id, &tag_path(name))?;
+        self.reload_tags()?;
+        if let Section::TagPrefix(_) = self.section {
+            // the group may not exist under its old name anymore
+            self.section = Section::Tag(id);
+            self.load_playables()?;
+        }
+        Ok(())
+    }
+
+    pub fn delete_tag(&mut self, id: i64) -> Result<()> {
+        let is_selected = self.section.has_tag(id);
+        self.storage.delete_tag(id)?;
+        self.reload_tags()?;
+        if is_selected {
+            if let Section::Tags(_) = self.section {
+                self.section = self.section.toggle_tag(id);
+            } else if self.tag_names.is_empty() {
+                self.section = Section::Library;
+            } else {
+                let id = self.tag_names[0].id;
+                self.section = Section::Tag(id);
+            }
+            self.load_playables()?;
+        }
+        Ok(())
+    }
+
+    /// Stores a fetched feed, subscribing to it if needed, and returns the podcast id.
+    pub fn save_podcast(&mut self, feed_url: &str, feed: Feed) -> Result<i64> {
+        let title = if feed.title.is_empty() {
+            feed_url
+        } else {
+            feed.title.as_str()
+        };
+        let id = self.storage.create_podcast(title, feed_url)?;
+        let episodes: Vec<EpisodeDescriptor> = feed
+            .episodes
+            .into_iter()
+            .map(EpisodeDescriptor::from)
+            .collect();
+        self.storage.bulk_append_to_podcast(id, &episodes)?;
+        self.podcasts = self.storage.read_podcasts()?;
+        if self.section.eq(&Section::Podcast(id)) {
+            self.load_playables()?;
+        }
+        Ok(id)
+    }
+
+    pub fn watches(&self) -> &[Watch] {
+        &self.watches
+    }
+
+    pub fn new_releases(&self) -> &[WatchRelease] {
+        &self.new_releases
+    }
+
+    /// Starts watching a Discogs label or artist, returns the id of the watch.
+    pub fn add_watch(&mut self, kind: WatchKind, discogs_id: u64, name: &str) -> Result<i64> {
+        let id =
+            self.storage
+                .create_watch(kind, discogs_id as i64, name, DEFAULT_WATCH_INTERVAL)?;
+        self.watches = self.storage.read_watches()?;
+        Ok(id)
+    }
+
+    pub fn remove_watch(&mut self, id: i64) -> Result<()> {
+        self.storage.delete_watch(id)?;
+        self.watches = self.storage.read_watches()?;
+        self.new_releases = self.storage.read_new_releases()?;
+        Ok(())
+    }
+
+    pub fn set_watch_interval(&mut self, id: i64, hours: u32) -> Result<()> {
+        self.storage.set_watch_interval(id, hours)?;
+        self.watches = self.storage.read_watches()?;
+        Ok(())
+    }
+
+    /// The watches whose interval has passed since they were last checked.
+    pub fn due_watches(&self, now: i64) -> Vec<Watch> {
+        self.watches
+            .iter()
+            .filter(|watch| {
+                watch
+                    .checked_at
+                    .is_none_or(|checked| checked + watch.interval_hours as i64 * 3600 <= now)
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Stores what a check of a watch found, returns how many releases are new. A failed check
+    /// passes no releases, it is tried again after the interval.
+    pub fn save_watch_check(
+        &mut self,
+        id: i64,
+        now: i64,
+        releases: &[WatchReleaseDescriptor],
+    ) -> Result<usize> {
+        let added = self.storage.write_watch_releases(id, now, releases)?;
+        self.watches = self.storage.read_watches()?;
+        self.new_releases = self.storage.read_new_releases()?;
+        Ok(added)
+    }
+
+    pub fn dismiss_new_releases(&mut self, id: i64) -> Result<()> {
+        self.storage.mark_releases_seen(id)?;
+        self.new_releases = self.storage.read_new_releases()?;
+        Ok(())
+    }
+
+    pub fn delete_podcast(&mut self, id: i64) -> Result<()> {
+        self.storage.delete_podcast(id)?;
+        self.podcasts = self.storage.read_podcasts()?;
+        if self.section.eq(&Section::Podcast(id)) {
+            self.section = Section::Library;
+            self.load_playables()?;
+        }
+        Ok(())
+    }
+
+    pub fn set_episode_downloaded(&mut self, id: i64, path: &str) -> Result<()> {
+        self.storage.set_episode_local_path(id, path)?;
+        if let Some(episode) = self.episodes.iter_mut().find(|e| e.id == id) {
+            episode.local_path = Some(path.to_string());
+        }
+        Ok(())
+    }
+
+    /// Where a playable stands with being available offline, None for those not downloadable.
+    pub fn offline_status(&self, playable: &Playable) -> Option<offline::Status> {
+        if !offline::is_downloadable(playable) {
+            return None;
+        }
+        Some(if self.offline_copies.contains_key(&playable.id) {
+            offline::Status::Offline
+        } else if self.downloading == Some(playable.id) {
+            offline::Status::Downloading
+        } else if self
+            .offline_queue
+            .iter()
+            .any(|queued| queued.id == playable.id)
+        {
+            offline::Status::Queued
+        } else {
+            offline::Status::Streamed
+        })
+    }
+
+    /// Queues the remote playables among `playables` to be downloaded, those offline or queued
+    /// already are left out. Returns how many were queued.
+    pub fn make_offline(&mut self, playables: Vec<Playable>) -> usize {
+        let mut queued = 0;
+        for playable in playables {
+            if self.offline_status(&playable) == Some(offline::Status::Streamed) {
+                self.offline_queue.push_back(playable);
+                queued += 1;
+            }
+        }
+        queued
+    }
+
+    /// Queues the remote tracks of a playlist to be downloaded.
+    pub fn make_playlist_offline(&mut self, id: i64) -> Result<usize> {
+        let playables = self.read_playlist(id)?;
+        Ok(self.make_offline(playables))
+    }
+
+    /// The next playable to download, None while one is being downloaded.
+    pub fn next_offline_download(&mut self) -> Option<Playable> {
+        if self.downloading.is_some() {
+            return None;
+        }
+        let playable = self.offline_queue.pop_front()?;
+        self.downloading = Some(playable.id);
+        Some(playable)
+    }
+
+    /// Records the file a playable was downloaded to, None when the download failed.
+    pub fn finish_offline_download(&mut self, id: PlayableId, path: Option<&str>) -> Result<()> {
+        self.downloading = None;
+        if let Some(path) = path {
+            self.storage.set_offline_copy(id, path)?;
+            self.offline_copies.insert(id, path.to_string());
+            offline::set_copies(&self.offline_copies);
+        }
+        Ok(())
+    }
+
+    /// Deletes the files the playables were downloaded to, they are streamed again.
+    pub fn remove_offline(&mut self, ids: &[PlayableId]) -> Result<()> {
+        self.offline_queue
+            .retain(|queued| !ids.contains(&queued.id));
+        for id in ids {
+            if let Some(path) = self.offline_copies.remove(id) {
+                self.storage.remove_offline_copy(*id)?;
+                if let Err(err) = std::fs::remove_file(&path) {
+                    error!("Error deleting the offline copy {path}: {err:?}");
+                }
+            }
+        }
+        offline::set_copies(&self.offline_copies);
+        Ok(())
+    }
+
+    /// Marks the episode as the one being played and returns it.
+    pub fn play_episode(&mut self, id: i64) -> Option<Episode> {
+        let episode = self.episodes.iter().find(|e| e.id == id)?.clone();
+        self.player.current_index = None;
+        self.player.current_playable = None;
+        self.player.current_episode = Some(episode.clone());
+        self.playing_up_next = None;
+        Some(episode)
+    }
+
+    /// Reads whether the playable at `index` is an audiobook, unless it is already cached.
+    pub fn load_audiobook(&mut self, index: usize) {
+        let Some(id) = self.playables().nth(index).map(|p| p.id) else {
+            return;
+        };
+        if self.audiobook.is_some_and(|(cached, _)| cached == id) {
+            return;
+        }
+        self.audiobook = Some((id, self.read_audiobook(id)));
+    }
+
+    pub fn is_audiobook(&self, playable_id: PlayableId) -> bool {
+        self.audiobook
+            .is_some_and(|(id, audiobook)| id == playable_id && audiobook)
+    }
+
+    fn read_audiobook(&self, playable_id: PlayableId) -> bool {
+        match self.storage.read_audiobook_position(playable_id) {
+            Ok(position) => position.is_some(),
+            Err(err) => {
+                error!("Error checking if playable is an audiobook: {err:?}");
+                false
+            }
+        }
+    }
+
+    pub fn set_audiobook(&mut self, playable_id: PlayableId, audiobook: bool) -> Result<()> {
+        self.storage.set_audiobook(playable_id, audiobook)?;
+        self.audiobook = Some((playable_id, audiobook));
+        // start tracking the playing playable right away
+        if self.player.current_playable == Some(playable_id) {
+            self.player.audiobook_position = None;
+            if audiobook {
+                let position = self.player.position;
+                self.storage.set_audiobook_position(playable_id, position)?;
+                self.player.audiobook_position = Some(position);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns where playback of the playable should start, which is 0 unless it is an audiobook
+    /// or a long track left unfinished.
+    pub fn resume_position(&mut self, playable_id: PlayableId) -> u64 {
+        let position = match self.storage.read_audiobook_position(playable_id) {
+            Ok(position) => position,
+            Err(err) => {
+                error!("Error reading audiobook position of {playable_id}: {err:?}");
+                None
+            }
+        };
+        self.player.audiobook_position = position;
+        self.player.progress_position = None;
+        if position.is_some() {
+            return position.unwrap_or(0);
+        }
+        self.player.progress_position = self.read_progress(playable_id);
+        self.player.progress_position.unwrap_or(0)
+    }
+
+    /// Whether playback of the playable starts somewhere else than at its beginning.
+    pub fn resumes_midway(&self, playable_id: PlayableId) -> bool {
+        self.read_audiobook(playable_id) || self.read_progress(playable_id).is_some_and(|p| p > 0)
+    }
+
+    // None unless the playable is a long track
+    fn read_progress(&self, playable_id: PlayableId) -> Option<u64> {
+        if self.long_track_minutes == 0 {
+            return None;
+        }
+        let min_duration = self.long_track_minutes as i64 * 60;
+        match self.storage.read_progress(playable_id, min_duration) {
+            Ok(position) => position,
+            Err(err) => {
+                error!("Error reading progress of {playable_id}: {err:?}");
+                None
+            }
+        }
+    }
+
+    pub fn update_playback_position(&mut self, position: u64) {
+        self.player.position = position;
+        // the player reports 0 whenever it loads or empties, finish_playback takes care of rewinding
+        if position == 0 {
+            return;
+        }
+        if self.player.current_episode.is_some() {
+            self.save_episode_position(position);
+        } else if let Some(saved) = self.player.audiobook_position
+            && let Some(id) = self.player.current_playable
+            && position.abs_diff(saved) >= POSITION_SAVE_STEP
+        {
+            self.player.audiobook_position = Some(position);
+            if let Err(err) = self.storage.set_audiobook_position(id, position) {
+                error!("Error saving position of audiobook {id}: {err:?}");
+            }
+        } else if let Some(saved) = self.player.progress_position
+            && let Some(id) = self.player.current_playable
+            && position.abs_diff(saved) >= POSITION_SAVE_STEP
+        {
+            self.player.progress_position = Some(position);
+            if let Err(err) = self.storage.set_progress(id, position) {
+                error!("Error saving progress of {id}: {err:?}");
+            }
+        }
+    }
+
+    fn save_episode_position(&mut self, position: u64) {
+        let Some(episode) = &mut self.player.current_episode else {
+            return;
+        };
+        if position.abs_diff(episode.position as u64) < POSITION_SAVE_STEP {
+            return;
+        }
+        episode.position = position as i64;
+        let id = episode.id;
+        if let Err(err) = self.storage.set_episode_position(id, position) {
+            error!("Error saving position of episode {id}: {err:?}");
+        }
+        if let Some(episode) = self.episodes.iter_mut().find(|e| e.id == id) {
+            episode.position = position as i64;
+        }
+    }
+
+    /// Rewinds the finished episode, audiobook or long track so that it starts over next time.
+    /// Returns true when an episode finished, those do not roll over into the track list.
+    pub fn finish_playback(&mut self) -> bool {
+        if self.player.audiobook_position.is_some()
+            && let Some(id) = self.player.current_playable
+        {
+            self.player.audiobook_position = Some(0);
+            if let Err(err) = self.storage.set_audiobook_position(id, 0) {
+                error!("Error resetting position of audiobook {id}: {err:?}");
+            }
+        }
+        if self.player.progress_position.is_some()
+            && let Some(id) = self.player.current_playable
+        {
+            self.player.progress_position = Some(0);
+            if let Err(err) = self.storage.set_progress(id, 0) {
+                error!("Error resetting progress of {id}: {err:?}");
+            }
+        }
+        let Some(episode) = self.player.current_episode.take() else {
+            return false;
+        };
+        if let Err(err) = self.storage.set_episode_position(episode.id, 0) {
+            error!(
+                "Error resetting position of episode {}: {err:?}",
+                episode.id
+            );
+        }
+        if let Some(episode) = self.episodes.iter_mut().find(|e| e.id == episode.id) {
+            episode.position = 0;
+        }
+        true
+    }
+
+    /// Reads the chapter markers of the playable at `index`, unless they are already cached.
+    pub fn load_chapters(&mut self, index: usize) {
+        let Some(playable) = self.playables().nth(index) else {
+            return;
+        };
+        let id = playable.id;
+        if self
+            .chapters
+            .as_ref()
+            .is_some_and(|(cached, _, _)| *cached == id)
+        {
+            return;
+        }
+        let (chapters, linked) = self.read_chapters(playable);
+        self.chapters = Some((id, chapters, linked));
+    }
+
+    /// Reads the chapter markers of the playing track, unless they are already cached.
+    pub fn load_playing_chapters(&mut self) {
+        let Some(id) = self.player.current_playable else {
+            self.playing_chapters = None;
+            return;
+        };
+        if self
+            .playing_chapters
+            .as_ref()
+            .is_some_and(|(cached, _)| *cached == id)
+        {
+            return;
+        }
+        let playable = self
+            .storage
+            .read_library_from_ids(&[id])
+            .unwrap_or_else(|err| {
+                error!("Error reading playable {id}: {err:?}");
+                Vec::new()
+            });
+        let chapters = playable
+            .first()
+            .map(|playable| self.read_chapters(playable).0)
+            .unwrap_or_default();
+        self.playing_chapters = Some((id, chapters));
+    }
+
+    // the markers of a linked release win over those of the file
+    fn read_chapters(&self, playable: &Playable) -> (Vec<Chapter>, bool) {
+        let linked = self
+            .storage
+            .read_release_chapters(playable.id)
+            .unwrap_or_else(|err| {
+                error!("Error reading release chapters of {}: {err:?}", playable.id);
+                Vec::new()
+            });
+        if !linked.is_empty() {
+            let chapters = linked
+                .into_iter()
+                .map(|(start, title)| Chapter { title, start })
+                .collect();
+            return (chapters, true);
+        }
+        let chapters = if playable.get_kind() == PlayableKind::LocalFile {
+            chapters::read_chapters(Path::new(playable.get_path())).unwrap_or_else(|err| {
+                error!("Error reading chapters of {}: {err:?}", playable.get_path());
+                Vec::new()
+            })
+        } else {
+            Vec::new()
+        };
+        (chapters, false)
+    }
+
+    pub fn chapters(&self, playable_id: PlayableId) -> &[Chapter] {
+        if let Some((id, chapters, _)) = &self.chapters
+            && *id == playable_id
+        {
+            return chapters;
+        }
+        match &self.playing_chapters {
+            Some((id, chapters)) if *id == playable_id => chapters,
+            _ => &[],
+        }
+    }
+
+    /// Whether the chapters of the inspected playable come from a linked release.
+    pub fn release_linked(&self, playable_id: PlayableId) -> bool {
+        self.chapters
+            .as_ref()
+            .is_some_and(|(id, _, linked)| *id == playable_id && *linked)
+    }
+
+    /// Marks a whole release played as one track with the chapters of its tracklist, none
+    /// unlinks the release. The cached markers are read again when next loaded.
+    pub fn link_release(&mut self, playable_id: PlayableId, chapters: &[Chapter]) -> Result<()> {
+        let chapters: Vec<(u64, String)> = chapters
+            .iter()
+            .map(|chapter| (chapter.start, chapter.title.clone()))
+            .collect();
+        self.storage.set_release_chapters(playable_id, &chapters)?;
+        if self
+            .chapters
+            .as_ref()
+            .is_some_and(|(id, _, _)| *id == playable_id)
+        {
+            self.chapters = None;
+        }
+        if self
+            .playing_chapters
+            .as_ref()
+            .is_some_and(|(id, _)| *id == playable_id)
+        {
+            self.playing_chapters = None;
+            self.load_playing_chapters();
+        }
+        Ok(())
+    }
+
+    /// Reads the bookmarks of the playable at `index`, unless they are already cached.
+    pub fn load_bookmarks(&mut self, index: usize) {
+        let Some(id) = self.playables().nth(index).map(|p| p.id) else {
+            return;
+        };
+        if self
+            .bookmarks
+            .as_ref()
+            .is_some_and(|(cached, _)| *cached == id)
+        {
+            return;
+        }
+        let bookmarks = self.storage.read_bookmarks(id).unwrap_or_else(|err| {
+            error!("Error reading bookmarks of {id}: {err:?}");
+            Vec::new()
+        });
+        self.bookmarks = Some((id, bookmarks));
+    }
+
+    pub fn bookmarks(&self, playable_id: PlayableId) -> &[Bookmark] {
+        match &self.bookmarks {
+            Some((id, bookmarks)) if *id == playable_id => bookmarks,
+            _ => &[],
+        }
+    }
+
+    /// Marks the moment `position` of a playable, the note may be empty.
+    pub fn add_bookmark(
+        &mut self,
+        playable_id: PlayableId,
+        position: u64,
+        note: &str,
+    ) -> Result<()> {
+        let id = self
+            .storage
+            .add_bookmark(playable_id, position, note.trim())?;
+        if let Some((cached, bookmarks)) = &mut self.bookmarks
+            && *cached == playable_id
+        {
+            let at = bookmarks.partition_point(|bookmark| bookmark.position <= position);
+            bookmarks.insert(
+                at,
+                Bookmark {
+                    id,
+                    position,
+                    note: note.trim().to_string(),
+                },
+            );
+        }
+        Ok(())
+    }
+
+    pub fn delete_bookmark(&mut self, bookmark_id: i64) -> Result<()> {
+        self.storage.delete_bookmark(bookmark_id)?;
+        if let Some((_, bookmarks)) = &mut self.bookmarks {
+            bookmarks.retain(|bookmark| bookmark.id != bookmark_id);
+        }
+        Ok(())
+    }
+
+    /// Reads the notes of the playable at `index`, unless they are already cached.
+    pub fn load_notes(&mut self, index: usize) {
+        let Some(id) = self.playables().nth(index).map(|p| p.id) else {
+            return;
+        };
+        if self.notes.as_ref().is_some_and(|(cached, _)| *cached == id) {
+            return;
+        }
+        let notes = self.storage.read_notes(id).unwrap_or_else(|err| {
+            error!("Error reading notes of {id}: {err:?}");
+            String::new()
+        });
+        self.notes = Some((id, notes));
+    }
+
+    pub fn notes(&self, playable_id: PlayableId) -> &str {
+        match &self.notes {
+            Some((id, notes)) if *id == playable_id => notes,
+            _ => "",
+        }
+    }
+
+    /// Writes the notes of a playable, empty clears them. Search results are read again, the
+    /// notes may have been what matched.
+    pub fn set_notes(&mut self, playable_id: PlayableId, notes: &str) -> Result<()> {
+        self.storage.set_notes(playable_id, notes)?;
+        self.notes = Some((playable_id, notes.trim().to_string()));
+        if matches!(self.section, Section::Search(_)) {
+            self.apply(Change::Refresh)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the links of the playable at `index`, unless they are already cached.
+    pub fn load_links(&mut self, index: usize) {
+        let Some(id) = self.playables().nth(index).map(|p| p.id) else {
+            return;
+        };
+        if self.links.as_ref().is_some_and(|(cached, _)| *cached == id) {
+            return;
+        }
+        let links = self.storage.read_links(id).unwrap_or_else(|err| {
+            error!("Error reading links of {id}: {err:?}");
+            Vec::new()
+        });
+        self.links = Some((id, links));
+    }
+
+    pub fn links(&self, playable_id: PlayableId) -> &[PlayableLink] {
+        match &self.links {
+            Some((id, links)) if *id == playable_id => links,
+            _ => &[],
+        }
+    }
+
+    /// Marks `playable_id` as a `kind` of `original_id`, replacing a link between them.
+    pub fn link_playables(
+        &mut self,
+        playable_id: PlayableId,
+        original_id: PlayableId,
+        kind: LinkKind,
+    ) -> Result<()> {
+        self.storage
+            .link_playables(playable_id, original_id, kind)?;
+        // read again when next shown
+        self.links = None;
+        Ok(())
+    }
+
+    pub fn unlink_playables(
+        &mut self,
+        playable_id: PlayableId,
+        other_id: PlayableId,
+    ) -> Result<()> {
+        self.storage.unlink_playables(playable_id, other_id)?;
+        if let Some((cached, links)) = &mut self.links
+            && (*cached == playable_id || *cached == other_id)
+        {
+            let other = if *cached == playable_id {
+                other_id
+            } else {
+                playable_id
+            };
+            links.retain(|link| link.playable.id != other);
+        }
+        Ok(())
+    }
+
+    /// Shows a playable in the list, in the library unless the section shown has it. The row
+    /// it is in, None when it is not in the library.
+    pub fn reveal(&mut self, playable_id: PlayableId) -> Result<Option<usize>> {
+        if let Some(index) = self.playables().position(|p| p.id == playable_id) {
+            return Ok(Some(index));
+        }
+        self.set_section(Section::Library)?;
+        Ok(self.playables().position(|p| p.id == playable_id))
+    }
+
+    pub fn playlist_notes(&self, playlist_id: i64) -> &str {
+        Self::find_playlist(&self.playlist_names, playlist_id).map_or("", |p| p.notes.as_str())
+    }
+
+    pub fn set_playlist_notes(&mut self, playlist_id: i64, notes: &str) -> Result<()> {
+        self.storage.set_playlist_notes(playlist_id, notes)?;
+        self.playlist_names = Self::to_playlist_tree(self.storage.read_playlists()?);
+        Ok(())
+    }
+
+    /// The playlists whose name or notes contain `query`, ignoring case.
+    pub fn playlists_matching(&self, query: &str) -> Vec<&Playlist> {
+        fn collect<'a>(tree: &'a [PlaylistNode], query: &str, out: &mut Vec<&'a Playlist>) {
+            for node in tree {
+                let playlist = &node.value;
+                if playlist.name.to_lowercase().contains(query)
+                    || playlist.notes.to_lowercase().contains(query)
+                {
+                    out.push(playlist);
+                }
+                collect(&node.children, query, out);
+            }
+        }
+        let query = query.trim().to_lowercase();
+        let mut out = Vec::new();
+        if !query.is_empty() {
+            collect(&self.playlist_names, &query, &mut out);
+        }
+        out
+    }
+
+    /// Reads the artwork of the playable at `index`, unless it is already cached.
+    pub fn load_artwork(&mut self, index: usize) {
+        let Some(id) = self.playables().nth(index).map(|p| p.id) else {
+            return;
+        };
+        if self
+            .artwork
+            .as_ref()
+            .is_some_and(|(cached, _)| *cached == id)
+        {
+            return;
+        }
+        let artwork = self.storage.read_artwork(id).unwrap_or_else(|err| {
+            error!("Error reading artwork of {id}: {err:?}");
+            None
+        });
+        self.artwork = Some((id, artwork));
+    }
+
+    pub fn artwork(&self, playable_id: PlayableId) -> Option<&Vec<u8>> {
+        match &self.artwork {
+            Some((id, artwork)) if *id == playable_id => artwork.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// A copy of `playable` with its artwork, for the now playing bar.
+    pub fn with_artwork(&self, playable: &Playable) -> Playable {
+        let mut playable = playable.clone();
+        if playable.artwork.is_none() {
+            playable.artwork = match self.artwork(playable.id) {
+                Some(artwork) => Some(artwork.clone()),
+                None => self
+                    .storage
+                    .read_artwork(playable.id)
+                    .unwrap_or_else(|err| {
+                        error!("Error reading artwork of {}: {err:?}", playable.id);
+                        None
+                    }),
+            };
+        }
+        playable
+    }
+
+    /// Reads the details of a playable for the song info panel.
+    pub fn open_song_info(&mut self, playable_id: PlayableId) -> Result<()> {
+        let Some(playable) = self
+            .storage
+            .read_library_from_ids(&[playable_id])?
+            .into_iter()
+            .next()
+        else {
+            self.song_info = None;
+            return Ok(());
+        };
+        let playable = self.with_artwork(&playable);
+        let tags = self.storage.read_tags_of(playable_id)?;
+        let playlists = self.storage.read_playlists_containing(playable_id)?;
+        self.song_info = Some(SongInfo {
+            playable,
+            tags,
+            playlists,
+        });
+        Ok(())
+    }
+
+    pub fn close_song_info(&mut self) {
+        self.song_info = None;
+    }
+
+    pub fn song_info(&self) -> Option<&SongInfo> {
+        self.song_info.as_ref()
+    }
+
+    /// Starts playing a playlist or tag without changing the visible section.
+    /// Returns the first playable of the collection.
+    pub fn play_collection(
+        &mut self,
+        section: &Section,
+        shuffle: bool,
+    ) -> Result<Option<Playable>> {
+        let mut playables = self.read_section(section)?;
+        if shuffle {
+            playables.shuffle(&mut self.random_generator);
+        }
+        Ok(self.play_playables(playables))
+    }
+
+    /// Starts playing `playables` in order without changing the visible section.
+    /// Returns the first of them.
+    pub fn play_playables(&mut self, playables: Vec<Playable>) -> Option<Playable> {
+        let first = playables.first().cloned();
+        self.player.current_episode = None;
+        self.player.current_index = first.as_ref().map(|_| 0);
+        self.player.current_playable = first.as_ref().map(|p| p.id);
+        self.queue = Some(playables);
+        self.playing_up_next = None;
+        first
+    }
+
+    /// Where playback is in the list it walks: the 1-based position, the number of playables
+    /// and the seconds left until the end of the list. None while nothing or an episode plays.
+    pub fn queue_progress(&self) -> Option<(usize, usize, u64)> {
+        if self.player.current_episode.is_some() {
+            return None;
+        }
+        let playables = self.queue.as_ref().unwrap_or(&self.playables);
+        let index = self.player.current_index?;
+        let current = playables.get(index)?;
+        let upcoming: u64 = playables[index + 1..]
+            .iter()
+            .map(|p| p.duration.max(0) as u64)
+            .sum();
+        let current_left = (current.duration.max(0) as u64).saturating_sub(self.player.position);
+        Some((index + 1, playables.len(), upcoming + current_left))
+    }
+
+    pub fn queue_active(&self) -> bool {
+        self.queue.is_some()
+    }
+
+    pub fn current_queued(&self) -> Option<&Playable> {
+        if let Some(playable) = &self.playing_up_next {
+            return Some(playable);
+        }
+        self.queue.as_ref()?.get(self.player.current_index?)
+    }
+
+    /// Whether the playable playing was queued by hand.
+    pub fn playing_up_next(&self) -> bool {
+        self.playing_up_next.is_some()
+    }
+
+    /// Goes back to playing from the visible list.
+    pub fn clear_queue(&mut self) {
+        self.queue = None;
+        self.playing_up_next = None;
+    }
+
+    pub fn up_next(&self) -> &[Playable] {
+        &self.up_next
+    }
+
+    /// Queues `playables` to play right after the current one, before those queued earlier.
+    pub fn enqueue_next(&mut self, playables: Vec<Playable>) {
+        self.up_next.splice(0..0, playables);
+    }
+
+    /// Queues `playables` after those queued earlier.
+    pub fn enqueue_last(&mut self, playables: Vec<Playable>) {
+        self.up_next.extend(playables);
+    }
+
+    /// Moves a queued playable from one position to another.
+    pub fn move_up_next(&mut self, from: usize, to: usize) {
+        if from >= self.up_next.len() || to >= self.up_next.len() {
+            return;
+        }
+        let playable = self.up_next.remove(from);
+        self.up_next.insert(to, playable);
+    }
+
+    pub fn remove_from_up_next(&mut self, index: usize) {
+        if index < self.up_next.len() {
+            self.up_next.remove(index);
+        }
+    }
+
+    pub fn clear_up_next(&mut self) {
+        self.up_next.clear();
+    }
+
+    // pub fn add_to_recent_playables(&mut self, id: &PlayableId) {
+    //     trace!("add_to_recent_playables: adding {id:?} to recent playables");
+    //     if !self.recent_playables.contains(id) {
+    //         self.recent_playables.push(*id);
+    //     }
+    // }
+    /// What `next_playable` moves on to, for the player to prepare it ahead. None when that is
+    /// not known yet, e.g. while shuffling or before continuing into the library.
+    pub fn peek_next(&self) -> Option<&Playable> {
+        if let Some(playable) = self.up_next.first() {
+            return Some(playable);
+        }
+        if self.player.current_episode.is_some() || self.player.shuffle {
+            return None;
+        }
+        let playables = self.queue.as_ref().unwrap_or(&self.playables);
+        let index = self.player.current_index?;
+        match playables.get(index + 1) {
+            Some(playable) => Some(playable),
+            None if self.context_end == ContextEnd::Repeat => playables.first(),
+            None => None,
+        }
+    }
+
+    /// Whether playback stops after the playing track: nothing is queued by hand and the list
+    /// ends here without repeating or going on into the library.
+    pub fn ends_after_current(&self) -> bool {
+        if !self.up_next.is_empty()
+            || self.player.current_episode.is_some()
+            || self.player.shuffle
+            || self.context_end != ContextEnd::Stop
+        {
+            return false;
+        }
+        let playables = self.queue.as_ref().unwrap_or(&self.playables);
+        self.player
+            .current_index
+            .is_some_and(|index| index + 1 >= playables.len())
+    }
+
+    /// Moves on to the next playable, returns false when there is nothing left to play.
+    /// Playables queued by hand come first, then the list walked goes on where it was.
+    pub fn next_playable(&mut self) -> bool {
+        if !self.up_next.is_empty() {
+            let playable = self.up_next.remove(0);
+            self.player.current_episode = None;
+            self.player.current_playable = Some(playable.id);
+            self.playing_up_next = Some(playable);
+            return true;
+        }
+        if self.playing_up_next.take().is_some()
+            && let Some(index) = self.player.current_index
+        {
+            // back to the playable the list was at when the queued ones started
+            let playables = self.queue.as_ref().unwrap_or(&self.playables);
+            if let Some(playable) = playables.get(index) {
+                self.player.current_playable = Some(playable.id);
+            }
+        }
+        let playables = self.queue.as_ref().unwrap_or(&self.playables);
+        if playables.is_empty() {
+            return false;
+        }
+        if self.player.current_index.is_none() {
+            self.player.current_index = Some(0);
+            self.player.current_playable = Some(playables[0].id);
+            return true;
+        }
+
+        let index = self.player.current_index.unwrap();
+        let next_index = if self.player.shuffle {
+            self.random_generator.random_range(0..playables.len())
+        } else if index >= playables.len() - 1 {
+            match self.context_end {
+                ContextEnd::Stop => return false,
+                ContextEnd::Repeat => 0,
+                ContextEnd::Library => return self.continue_into_library(),
+            }
+        } else {
+            index + 1
+        };
+
+        let next_id = playables[next_index].id;
+        self.player.current_index = Some(next_index);
+        self.player.current_playable = Some(next_id);
+        true
+    }
+
+    // Queues the whole library, starting after the playable which just ended.
+    fn continue_into_library(&mut self) -> bool {
+        let library = match self.storage.read_library() {
+            Ok(library) => library,
+            Err(err) => {
+                error!("Error reading library: {err:?}");
+                return false;
+            }
+        };
+        if library.is_empty() {
+            return false;
+        }
+        let index = self
+            .player
+            .current_playable
+            .and_then(|id| library.iter().position(|p| p.id == id))
+            .map_or(0, |index| (index + 1) % library.len());
+        self.player.current_index = Some(index);
+        self.player.current_playable = Some(library[index].id);
+        self.queue = Some(library);
+        true
+    }
+
+    pub fn previous_playable(&mut self) {
+        // a queued playable goes back to the one the list was at
+        if self.playing_up_next.take().is_some() {
+            let playables = self.queue.as_ref().unwrap_or(&self.playables);
+            if let Some(playable) = self.player.current_index.and_then(|i| playables.get(i)) {
+                self.player.current_playable = Some(playable.id);
+                return;
+            }
+        }
+        let playables = self.queue.as_ref().unwrap_or(&self.playables);
+        if playables.is_empty() {
+            return;
+        }
+        if self.player.current_index.is_none() {
+            self.player.current_index = Some(0);
+            self.player.current_playable = Some(playables[0].id);
+            return;
+        }
+
+        let index = self.player.current_index.unwrap();
+        let prev_index = if self.player.shuffle {
+            self.random_generator.random_range(0..playables.len())
+        } else if index == 0 {
+            playables.len() - 1
+        } else {
+            index - 1
+        };
+
+        let prev_id = playables[prev_index].id;
+        self.player.current_index = Some(prev_index);
+        self.player.current_playable = Some(prev_id);
+    }
+}
+
+// the views whose column widths are kept apart, sections of a kind share theirs
+fn layout_view(section: &Section) -> &'static str {
+    match section {
+        Section::Playlist(_) | Section::Auto(_) => "playlist",
+        Section::Tag(_) | Section::Tags(_) | Section::TagPrefix(_) => "tag",
+        Section::Search(_) => "search",
+        Section::RecentlyPlayed => "recently_played",
+        Section::Queue => "queue",
+        Section::Album(_) => "album",
+        Section::Server(_) => "server",
+        _ => "library",
+    }
+}
+
+// the tracks grouped by album in the order the albums first come up
+fn group_albums<'a>(playables: impl Iterator<Item = &'a Playable>) -> Vec<AlbumGroup> {
+    let mut albums: Vec<AlbumGroup> = Vec::new();
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for playable in playables {
+        let name = playable.get_album();
+        match positions.get(name) {
+            Some(&position) => {
+                let album = &mut albums[position];
+                album.tracks += 1;
+                if album.artist != playable.get_artist() {
+                    album.artist.clear();
+                }
+                if let Some(released) = playable.released()
+                    && album
+                        .released
+                        .as_ref()
+                        .is_none_or(|first| released < *first)
+                {
+                    album.released = Some(released);
+                }
+            }
+            None => {
+                positions.insert(name, albums.len());
+                albums.push(AlbumGroup {
+                    name: name.to_string(),
+                    artist: playable.get_artist().to_string(),
+                    tracks: 1,
+                    cover: playable.id,
+                    released: playable.released(),
+                });
+            }
+        }
+    }
+    albums
+}
+
+// the year, month and day of a `YYYY-MM-DD` date, which may go on with a time
+fn date_parts(date: &str) -> Option<(i64, i64, i64)> {
+    let mut parts = date.get(..10)?.split('-').map(|part| part.parse().ok());
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+/// The albums of `library` whose release date, in a year before the one of `today`, falls
+/// within `THIS_WEEK_DAYS` of the day and month of `today`, oldest first.
+fn released_this_week(library: &[Playable], today: &str) -> Vec<AlbumGroup> {
+    let Some((year, month, day)) = date_parts(today) else {
+        return Vec::new();
+    };
+    let today = date_to_timestamp(year, month, day);
+    let near = |(released, month, day): (i64, i64, i64)| {
+        // the days around new year are near the dates at the end of the year before
+        released < year
+            && (year - 1..=year + 1).any(|year| {
+                (date_to_timestamp(year, month, day) - today).abs() <= THIS_WEEK_DAYS * 86_400
+            })
+    };
+    let dated = library
+        .iter()
+        .filter(|p| !p.get_album().is_empty() && p.release_date.is_some());
+    let mut albums: Vec<AlbumGroup> = group_albums(dated)
+        .into_iter()
+        .filter(|album| {
+            album
+                .released
+                .as_deref()
+                .and_then(date_parts)
+                .is_some_and(near)
+        })
+        .collect();
+    albums.sort_by(|a, b| a.released.cmp(&b.released));
+    albums
+}
+
+impl From<ScannedFile> for AudioFileDescriptor {
+    fn from(val: ScannedFile) -> Self {
+        AudioFileDescriptor {
+            title: val.title,
+            artist: val.artist,
+            album: val.album,
+            year: val.year,
+            genre: val.genre,
+            duration: val.duration,
+            bitrate: val.bitrate,
+            sample_rate: val.sample_rate,
+            lossless: val.lossless,
+            release_date: val.release_date,
+            track_number: val.track_number,
+            disc_number: val.disc_number,
+            label: val.label,
+            path: val.path,
+            artwork: val.artwork,
+            kind: match val.kind {
+                ScannedKind::LocalFile => AudioFileKind::LocalFile,
+                ScannedKind::GoogleDrive => AudioFileKind::GoogleDrive,
+                ScannedKind::Dropbox => AudioFileKind::Dropbox,
+                ScannedKind::Youtube => AudioFileKind::Youtube,
+                ScannedKind::Stream => AudioFileKind::Stream,
+                ScannedKind::WebDav => AudioFileKind::WebDav,
+            },
+        }
+    }
+}
+
+impl From<FeedEpisode> for EpisodeDescriptor {
+    fn from(val: FeedEpisode) -> Self {
+        EpisodeDescriptor {
+            guid: val.guid,
+            title: val.title,
+            description: val.description,
+            published: val.published,
+            audio_url: val.audio_url,
+            duration: val.duration,
+        }
+    }
+}
+
+// splits `rating:N` terms off the search, the last one wins and the rest is matched as text
+fn parse_search(val: &str) -> (Option<u8>, String) {
+    let mut rating = None;
+    let mut text = Vec::new();
+    for term in val.split_whitespace() {
+        match term.strip_prefix("rating:").map(str::parse::<u8>) {
+            Some(Ok(stars)) => rating = Some(stars.min(MAX_RATING)),
+            _ => text.push(term),
+        }
+    }
+    (rating, text.join(" "))
+}
+
+// trims the segments of a tag path and drops empty ones, ` mood//dark ` becomes `mood/dark`
+fn tag_path(name: &str) -> String {
+    name.split('/')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join("/")
+}
+
+mod tests {
+    use super::*;
+
+    #[allow(dead_code)]
+    fn scanned_file(title: &str) -> ScannedFile {
+        ScannedFile {
+            title: title.to_string(),
+            artist: format!("artist_{title}"),
+            album: format!("album_{title}"),
+            year: 2001,
+            genre: format!("genre_{title}"),
+            duration: 100,
+            bitrate: 0,
+            sample_rate: 0,
+            lossless: None,
+            release_date: String::new(),
+            track_number: 0,
+            disc_number: 0,
+            label: String::new(),
+            path: format!("path_{title}"),
+            artwork: None,
+            kind: ScannedKind::LocalFile,
+        }
+    }
+
+    #[test]
+    fn test_initialized_correctly() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let _ = storage.append_to_library(&AudioFileDescriptor::from(scanned_file("Test1")));
+        let _ = storage.append_to_library(&AudioFileDescriptor::from(scanned_file("Test2")));
+        let _ = storage.create_tag("tag1");
+        let _ = storage.create_playlist("playlist1", None, None);
+
+        let state = State::new(storage);
+        assert_eq!(state.playables.len(), 2);
+        assert_eq!(state.playlist_names.len(), 1);
+        assert_eq!(state.tag_names.len(), 1);
+    }
+
+    #[test]
+    fn test_append_bulk() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let _ = storage.create_playlist("test_playlist", None, None);
+        let mut state = State::new(storage);
+        let files = vec![scanned_file("Test1"), scanned_file("Test2")];
+        assert!(state.append_bulk(files).is_ok());
+        assert_eq!(state.playables.len(), 2);
+
+        assert!(state.set_section(Section::Favorites).is_ok());
+        let files = vec![scanned_file("Test3"), scanned_file("Test4")];
+        assert!(state.append_bulk(files).is_ok());
+        assert_eq!(state.playables.len(), 0);
+
+        assert!(state.set_section(Section::Playlist(1)).is_ok());
+        let files = vec![scanned_file("Test5"), scanned_file("Test6")];
+        assert!(state.append_bulk(files).is_ok());
+        assert_eq!(state.playables.len(), 2);
+        assert_eq!(state.playables[0].get_title(), "Test5");
+        assert_eq!(state.playables[1].get_title(), "Test6");
+
+        assert!(state.set_section(Section::Library).is_ok());
+        assert_eq!(state.playables.len(), 6);
+    }
+
+    #[test]
+    fn test_begin_import() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        // without a worker the files are imported at once
+        let files = vec![scanned_file("Test1")];
+        assert!(matches!(state.begin_import("/music", files), Ok(None)));
+        assert!(state.set_section(Section::Library).is_ok());
+        assert_eq!(state.playables.len(), 1);
+
+        // only the worker's presence matters, the import is written here
+        let worker = Worker::spawn(init_storage(":memory:").unwrap());
+        state.async_storage = Some(Arc::new(worker));
+        let playlist_id = state.create_playlist("New", None).unwrap();
+        assert!(state.set_section(Section::Playlist(playlist_id)).is_ok());
+        let files = vec![scanned_file("Test1"), scanned_file("Test2")];
+        let import = state.begin_import("/music", files).unwrap().unwrap();
+        assert_eq!(import.playlist_id, Some(playlist_id));
+        assert!(state.playables.is_empty());
+        let ids = import.write(state.storage.as_mut()).unwrap();
+        assert_eq!(ids.len(), 1);
+        assert!(state.imported(import.playlist_id, ids).is_ok());
+        let titles: Vec<&str> = state.playables.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, ["Test1", "Test2"]);
+        assert_eq!(state.storage.read_imports().unwrap().len(), 2);
+        assert!(matches!(state.begin_import("/music", Vec::new()), Ok(None)));
+    }
+
+    #[test]
+    fn test_library_pages() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let files = (1..=5).map(|i| scanned_file(&format!("Test{i}"))).collect();
+        assert!(state.append_bulk(files).is_ok());
+        assert_eq!(state.take_library_load(), None);
+
+        // only the worker's presence matters, the pages are read here
+        let worker = Worker::spawn(init_storage(":memory:").unwrap());
+        state.async_storage = Some(Arc::new(worker));
+        state.library_page = 2;
+        assert!(state.set_section(Section::Library).is_ok());
+        assert_eq!(state.playables.len(), 2);
+        let load = state.take_library_load().unwrap();
+        assert_eq!((load.after, load.limit), (state.playables[1].id, 2));
+        assert_eq!(state.take_library_load(), None);
+
+        let page = state
+            .storage
+            .read_library_page(load.after, load.limit)
+            .unwrap();
+        let load = state.append_library_page(load, page).unwrap();
+        assert_eq!(state.playables.len(), 4);
+        // added while the rest is read, listed once
+        let added = state.append_bulk(vec![scanned_file("Test6")]).unwrap();
+        assert_eq!(state.playables.len(), 5);
+        let page = state
+            .storage
+            .read_library_page(load.after, load.limit)
+            .unwrap();
+        let load = state.append_library_page(load, page).unwrap();
+        let page = state
+            .storage
+            .read_library_page(load.after, load.limit)
+            .unwrap();
+        assert_eq!(state.append_library_page(load, page), None);
+        let titles: Vec<&str> = state.playables.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            ["Test1", "Test2", "Test3", "Test4", "Test6", "Test5"]
+        );
+        assert_eq!(state.playables[4].id, added[0]);
+
+        // a reload overtakes the pages still coming
+        assert!(state.set_section(Section::Library).is_ok());
+        let load = state.take_library_load().unwrap();
+        assert!(state.load_playables().is_ok());
+        assert_eq!(state.playables.len(), 6);
+        let page = state
+            .storage
+            .read_library_page(load.after, load.limit)
+            .unwrap();
+        assert_eq!(state.append_library_page(load, page), None);
+        assert_eq!(state.playables.len(), 6);
+    }
+
+    #[test]
+    fn test_delete_playlist() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let _ = storage.create_playlist("test_playlist", None, None);
+        let _ = storage.create_playlist("test_playlist2", None, None);
+
+        let mut state = State::new(storage);
+        assert!(state.set_section(Section::Playlist(2)).is_ok()); //test_playlist2
+        let files = vec![
+            scanned_file("Test1"),
+            scanned_file("Test2"),
+            scanned_file("Test3"),
+        ];
+        assert!(state.append_bulk(files).is_ok());
+        assert_eq!(state.playables.len(), 3);
+
+        assert!(state.set_section(Section::Playlist(1)).is_ok()); //test_playlist1
+        let files = vec![
+            scanned_file("Test4"),
+            scanned_file("Test5"),
+            scanned_file("Test6"),
+        ];
+        assert!(state.append_bulk(files).is_ok());
+        assert_eq!(state.playables.len(), 3);
+
+        assert!(state.set_section(Section::Library).is_ok());
+        assert_eq!(state.playables.len(), 6);
+
+        assert!(state.set_section(Section::Playlist(1)).is_ok());
+        assert!(state.delete_playlist(1).is_ok());
+
+        assert_eq!(state.section(), &Section::Playlist(2));
+        assert_eq!(state.playables.len(), 3);
+
+        let _ = state.delete_playlist(2);
+        assert_eq!(state.section(), &Section::Library);
+        assert_eq!(state.playables().count(), 6);
+    }
+
+    #[test]
+    fn test_clear_playlist() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let _ = storage.create_playlist("test_playlist", None, None);
+        let _ = storage.create_playlist("test_playlist2", None, None);
+
+        let mut state = State::new(storage);
+        assert!(state.set_section(Section::Playlist(1)).is_ok()); //test_playlist1
+        let files = vec![
+            scanned_file("Test4"),
+            scanned_file("Test5"),
+            scanned_file("Test6"),
+        ];
+        assert!(state.append_bulk(files).is_ok());
+        assert_eq!(state.playables.len(), 3);
+
+        assert!(state.set_section(Section::Playlist(2)).is_ok()); //test_playlist2
+        let files = vec![
+            scanned_file("Test1"),
+            scanned_file("Test2"),
+            scanned_file("Test3"),
+        ];
+        assert!(state.append_bulk(files).is_ok());
+        assert_eq!(state.playables.len(), 3);
+
+        assert!(state.set_section(Section::Library).is_ok());
+        assert_eq!(state.playables.len(), 6);
+
+        assert!(state.set_section(Section::Playlist(2)).is_ok());
+        assert_eq!(state.playables.len(), 3);
+        assert!(state.clear_playlist(1).is_ok());
+        assert_eq!(state.playables.len(), 3);
+        assert!(state.set_section(Section::Playlist(1)).is_ok());
+        assert_eq!(state.playables().count(), 0);
+
+        assert!(state.set_section(Section::Playlist(2)).is_ok());
+        assert_eq!(state.playables.len(), 3);
+        assert!(state.clear_playlist(2).is_ok());
+        assert_eq!(state.playables.len(), 0);
+    }
+
+    #[test]
+    fn test_nested_playlists() {
+        let mut storage = init_storage(":memory:").unwrap();
+        let _ = storage.create_playlist("Folder", Some(crate::storage::PlaylistKind::Folder), None);
+        let _ = storage.create_playlist("playlist", None, Some(1));
+        let state = State::new(Box::new(storage));
+        let playlists = state.playlists();
+        assert_eq!(playlists.len(), 1);
+        assert_eq!(playlists[0].children.len(), 1);
+        assert_eq!(playlists[0].value.id, 1);
+        assert_eq!(playlists[0].children[0].value.id, 2);
+    }
+
+    #[test]
+    fn test_playlist_tracklist() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let _ = storage.create_playlist("test_playlist", None, None);
+        let mut state = State::new(storage);
+        assert!(state.set_section(Section::Playlist(1)).is_ok());
+        let files = vec![scanned_file("Test1"), scanned_file("Test2")];
+        assert!(state.append_bulk(files).is_ok());
+
+        assert_eq!(
+            state.playlist_tracklist(1).unwrap(),
+            "01. artist_Test1 – Test1\n02. artist_Test2 – Test2"
+        );
+
+        assert!(state.set_tracklist_template("{n}) {title}").is_ok());
+        assert_eq!(state.playlist_tracklist(1).unwrap(), "01) Test1\n02) Test2");
+
+        assert!(state.set_tracklist_template("  ").is_ok());
+        assert_eq!(state.tracklist_template(), DEFAULT_TRACKLIST_TEMPLATE);
+    }
+
+    #[test]
+    fn test_tag_intersection() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let _ = storage.create_tag("tag1");
+        let _ = storage.create_tag("tag2");
+        let _ = storage.create_tag("tag3");
+
+        let mut state = State::new(storage);
+        let files = vec![
+            scanned_file("Test1"),
+            scanned_file("Test2"),
+            scanned_file("Test3"),
+        ];
+        let _ = state.append_bulk(files);
+        let _ = state.append_to_tag(1, 1);
+        let _ = state.append_to_tag(1, 2);
+        let _ = state.append_to_tag(2, 2);
+        let _ = state.append_to_tag(2, 3);
+
+        let section = Section::Tag(1).toggle_tag(2);
+        assert_eq!(section, Section::Tags(vec![1, 2]));
+        assert!(state.set_section(section).is_ok());
+        assert_eq!(state.playables().count(), 1);
+
+        // appending to a selected tag refreshes the intersection
+        let _ = state.append_to_tag(1, 3);
+        assert_eq!(state.playables().count(), 2);
+
+        assert_eq!(state.section().toggle_tag(3), Section::Tags(vec![1, 2, 3]));
+        assert_eq!(state.section().toggle_tag(1), Section::Tag(2));
+        assert_eq!(Section::Tag(2).toggle_tag(2), Section::Tag(2));
+
+        assert!(state.delete_tag(2).is_ok());
+        assert_eq!(state.section(), &Section::Tag(1));
+        assert_eq!(state.playables().count(), 3);
+    }
+
+    #[test]
+    fn test_tag_tree() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let _ = storage.create_tag("mood/dark");
+        let _ = storage.create_tag("event/boilerroom-2023");
+        let _ = storage.create_tag("mood");
+        let _ = storage.create_tag("mood/happy");
+
+        let mut state = State::new(storage);
+        let tree = state.tag_tree();
+        assert_eq!(tree.len(), 2);
+        // `event` only exists through its nested tag
+        assert_eq!(tree[0].path, "event");
+        assert!(tree[0].tag.is_none());
+        assert_eq!(tree[0].children[0].tag.as_ref().unwrap().id, 2);
+        assert_eq!(tree[1].path, "mood");
+        assert_eq!(tree[1].tag.as_ref().unwrap().id, 3);
+        assert_eq!(tree[1].children.len(), 2);
+        assert_eq!(tree[1].children[1].name, "happy");
+        assert_eq!(tree[1].children[1].path, "mood/happy");
+
+        assert!(state.create_tag(" event / afterparty/").is_ok());
+        assert_eq!(state.tag_tree()[0].children[0].path, "event/afterparty");
+
+        assert!(state.rename_tag(3, "vibe").is_ok());
+        assert_eq!(state.tag_tree()[1].path, "vibe");
+        assert_eq!(state.tag_tree()[1].children[0].path, "vibe/dark");
+    }
+
+    #[test]
+    fn test_tag_prefix() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let _ = storage.create_tag("mood/dark");
+        let _ = storage.create_tag("mood/happy");
+
+        let mut state = State::new(storage);
+        let files = vec![
+            scanned_file("Test1"),
+            scanned_file("Test2"),
+            scanned_file("Test3"),
+        ];
+        let _ = state.append_bulk(files);
+        let _ = state.append_to_tag(1, 1);
+        let _ = state.append_to_tag(2, 2);
+
+        assert!(
+            state
+                .set_section(Section::TagPrefix("mood".to_string()))
+                .is_ok()
+        );
+        assert_eq!(state.playables().count(), 2);
+
+        assert!(state.rename_tag(1, "vibe/dark").is_ok());
+        assert_eq!(state.section(), &Section::Tag(1));
+        assert_eq!(state.playables().count(), 1);
+    }
+
+    #[test]
+    fn test_import() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let _ = storage.create_playlist("playlist1", None, None);
+        let mut state = State::new(storage);
+
+        assert!(
+            state
+                .import("/music", vec![scanned_file("Test1"), scanned_file("Test2")])
+                .is_ok()
+        );
+        assert!(state.set_section(Section::Playlist(1)).is_ok());
+        // Test2 is already in the library, only Test3 is new to this batch
+        assert!(
+            state
+                .import("/crate", vec![scanned_file("Test2"), scanned_file("Test3")])
+                .is_ok()
+        );
+        assert_eq!(state.playables().count(), 2);
+
+        assert!(state.set_section(Section::ImportHistory).is_ok());
+        assert_eq!(state.imports().len(), 2);
+        let batch = &state.imports()[0];
+        assert_eq!(batch.source, "/crate");
+        assert_eq!(batch.file_count, 2);
+        assert_eq!(batch.playable_count, 1);
+        assert_eq!(batch.playlist_name.as_deref(), Some("playlist1"));
+
+        let batch_id = batch.id;
+        assert!(state.set_section(Section::Import(batch_id)).is_ok());
+        assert_eq!(state.playables().count(), 1);
+
+        assert!(state.remove_import(batch_id).is_ok());
+        assert_eq!(state.section(), &Section::ImportHistory);
+        assert_eq!(state.imports().len(), 1);
+        assert!(state.set_section(Section::Library).is_ok());
+        assert_eq!(state.playables().count(), 2);
+    }
+
+    #[test]
+    fn test_import_recording() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert!(state.set_record_format(RecordFormat::Wav).is_ok());
+        assert_eq!(state.record_format(), RecordFormat::Wav);
+
+        let path = Path::new("/recordings/Recording 2025-01-01 20-00.wav");
+        assert!(
+            state
+                .import_recording(path, "Recording 2025-01-01 20:00", 3600)
+                .is_ok()
+        );
+        let recording = state.playables().next().unwrap();
+        assert_eq!(recording.get_title(), "Recording 2025-01-01 20:00");
+        assert_eq!(recording.get_duration(), 3600);
+        assert!(state.set_section(Section::ImportHistory).is_ok());
+        assert_eq!(state.imports()[0].source, "/recordings");
+    }
+
+    #[test]
+    fn test_session() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let playlist_id = storage.create_playlist("playlist1", None, None).unwrap();
+        let files: Vec<AudioFileDescriptor> = ["Test1", "Test2", "Test3"]
+            .into_iter()
+            .map(|t| AudioFileDescriptor::from(scanned_file(t)))
+            .collect();
+        let _ = storage.bulk_append_to_playlist(playlist_id, &files);
+        let mut state = State::new(storage);
+        assert_eq!(state.restore_session(), Session::default());
+
+        assert!(state.set_section(Section::Playlist(playlist_id)).is_ok());
+        assert!(state.save_session(120.5, &[2, 0, 7]).is_ok());
+        state.set_section(Section::Library).unwrap();
+        let session = state.restore_session();
+        assert_eq!(state.section(), &Section::Playlist(playlist_id));
+        assert_eq!(session.scroll_offset, 120.5);
+        // rows past the end of the list are dropped
+        assert_eq!(session.selection, vec![2, 0]);
+
+        // tools start over
+        state.set_section(Section::Recorder).unwrap();
+        assert!(state.save_session(0.0, &[]).is_ok());
+        state.set_section(Section::Favorites).unwrap();
+        assert_eq!(state.restore_session(), Session::default());
+        assert_eq!(state.section(), &Section::Favorites);
+    }
+
+    #[test]
+    fn test_window_geometry() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert_eq!(state.window_geometry(), WindowGeometry::default());
+
+        let geometry = WindowGeometry {
+            size: Some((1280.0, 800.5)),
+            position: Some((-40.0, 12.0)),
+            maximized: false,
+            pane_ratio: Some(0.2),
+        };
+        assert!(state.set_window_geometry(geometry).is_ok());
+        assert_eq!(state.window_geometry(), geometry);
+
+        // a maximized window keeps the size it returns to
+        let maximized = WindowGeometry {
+            size: None,
+            position: None,
+            maximized: true,
+            pane_ratio: Some(0.25),
+        };
+        assert!(state.set_window_geometry(maximized).is_ok());
+        assert_eq!(
+            state.window_geometry(),
+            WindowGeometry {
+                maximized: true,
+                pane_ratio: Some(0.25),
+                ..geometry
+            }
+        );
+    }
+
+    #[test]
+    fn test_alarm() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert!(state.next_alarm().is_none());
+
+        let alarm = Alarm {
+            enabled: true,
+            playlist: Some(1),
+            daily: false,
+            ..Alarm::default()
+        };
+        assert!(state.set_alarm(alarm.clone()).is_ok());
+        let ring = state.next_alarm().unwrap();
+        assert!(ring > unix_now());
+        assert_eq!(state.take_due_alarm(ring - 1).unwrap(), None);
+        assert_eq!(state.take_due_alarm(ring).unwrap(), Some(alarm));
+        // rings once only
+        assert!(!state.alarm().enabled);
+        assert!(state.next_alarm().is_none());
+        assert_eq!(State::read_alarm(state.storage.as_ref()), *state.alarm());
+    }
+
+    #[test]
+    fn test_likes() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let ids = storage
+            .bulk_append_to_library(&[
+                AudioFileDescriptor::from(scanned_file("Test1")),
+                AudioFileDescriptor::from(scanned_file("Test2")),
+            ])
+            .unwrap();
+        storage.append_like(ids[0]).unwrap();
+
+        let mut state = State::new(storage);
+        assert!(state.is_liked(&ids[0]));
+        assert!(!state.is_liked(&ids[1]));
+
+        state.add_to_likes(&ids[1]);
+        state.remove_from_likes(&ids[0]);
+        assert!(!state.is_liked(&ids[0]));
+        assert!(state.is_liked(&ids[1]));
+        assert_eq!(
+            state.storage.read_liked_ids().unwrap(),
+            HashSet::from([ids[1]])
+        );
+
+        assert!(state.set_section(Section::Library).is_ok());
+        state.bulk_remove(&[1], false);
+        assert!(!state.is_liked(&ids[1]));
+    }
+
+    #[test]
+    fn test_granular_updates() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert!(state.set_section(Section::Library).is_ok());
+        let ids = state
+            .append_bulk(vec![scanned_file("Test1"), scanned_file("Test2")])
+            .unwrap();
+        assert_eq!(state.playables().count(), 2);
+        let ids = [ids, state.append_bulk(vec![scanned_file("Test3")]).unwrap()].concat();
+        assert_eq!(state.playables().count(), 3);
+
+        state.bulk_remove(&[0], false);
+        let remaining: Vec<PlayableId> = state.playables().map(|p| p.id).collect();
+        assert_eq!(remaining, vec![ids[1], ids[2]]);
+
+        assert!(state.set_section(Section::Favorites).is_ok());
+        state.add_to_likes(&ids[2]);
+        assert_eq!(state.playables().next().map(|p| p.id), Some(ids[2]));
+        state.remove_from_likes(&ids[2]);
+        assert_eq!(state.playables().count(), 0);
+
+        let tag_id = state.storage.create_tag("tag1").unwrap();
+        assert!(state.set_section(Section::Tag(tag_id)).is_ok());
+        assert!(state.append_to_tag(tag_id, ids[1]).is_ok());
+        // already tagged, the list must not show it twice
+        assert!(state.append_to_tag(tag_id, ids[1]).is_ok());
+        assert_eq!(state.playables().count(), 1);
+    }
+
+    #[test]
+    fn test_artwork() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let mut file = scanned_file("Test1");
+        file.artwork = Some(vec![1, 2, 3]);
+        let _ = state.append_bulk(vec![file, scanned_file("Test2")]);
+        assert!(state.set_section(Section::Library).is_ok());
+
+        let first = state.playables().next().unwrap().clone();
+        assert_eq!(first.artwork, None);
+        assert_eq!(state.artwork(first.id), None);
+        assert_eq!(state.with_artwork(&first).artwork, Some(vec![1, 2, 3]));
+
+        state.load_artwork(0);
+        assert_eq!(state.artwork(first.id), Some(&vec![1, 2, 3]));
+        state.load_artwork(1);
+        assert_eq!(state.artwork(first.id), None);
+    }
+
+    #[test]
+    fn test_database_stats() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let _ = state.append_bulk(vec![scanned_file("Test1"), scanned_file("Test2")]);
+        assert!(state.database_stats().row_counts.is_empty());
+
+        assert!(state.set_section(Section::AdvancedSettings).is_ok());
+        assert!(state.database_stats().file_size > 0);
+        assert!(
+            state
+                .database_stats()
+                .row_counts
+                .contains(&("Playable".to_string(), 2))
+        );
+        assert!(state.checkpoint_database().is_ok());
+        assert!(state.optimize_database().is_ok());
+        assert!(!state.database_stats().row_counts.is_empty());
+    }
+
+    #[test]
+    fn test_play_history() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let ids = storage
+            .bulk_append_to_library(&[AudioFileDescriptor::from(scanned_file("Test1"))])
+            .unwrap();
+        storage.record_play(ids[0], 0).unwrap();
+        storage.write_setting(HISTORY_RETENTION_KEY, "30").unwrap();
+        let plays = |state: &State| {
+            state
+                .storage
+                .read_database_stats()
+                .unwrap()
+                .row_counts
+                .into_iter()
+                .find(|(table, _)| table == "PlayHistory")
+                .map(|(_, count)| count)
+        };
+
+        // plays older than the retention are pruned on startup
+        let mut state = State::new(storage);
+        assert_eq!(state.history_retention(), Some(30));
+        assert_eq!(plays(&state), Some(0));
+
+        state.player.current_playable = Some(ids[0]);
+        assert!(state.record_play().is_ok());
+        assert_eq!(plays(&state), Some(1));
+
+        state.set_incognito(true);
+        assert!(state.record_play().is_ok());
+        assert_eq!(plays(&state), Some(1));
+        state.set_incognito(false);
+
+        // kept across restarts, shown with the time of the play
+        let mut state = State::new(state.storage);
+        assert!(state.set_section(Section::RecentlyPlayed).is_ok());
+        assert_eq!(
+            state.playables().map(|p| p.id).collect::<Vec<_>>(),
+            [ids[0]]
+        );
+        assert!(state.last_played(ids[0]).is_some_and(|at| at > 0));
+
+        assert!(state.set_history_retention(None).is_ok());
+        assert_eq!(
+            state.storage.read_setting(HISTORY_RETENTION_KEY).unwrap(),
+            Some(String::new())
+        );
+        assert!(state.clear_history().is_ok());
+        assert_eq!(plays(&state), Some(0));
+    }
+
+    #[test]
+    fn test_record_skip() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state
+            .append_bulk(vec![scanned_file("a"), scanned_file("b")])
+            .unwrap();
+        assert!(state.set_section(Section::Library).is_ok());
+        let play_count =
+            |state: &State, id| state.playables().find(|p| p.id == id).map(|p| p.play_count);
+
+        // played from the visible list, nothing is queued
+        state.player.current_index = Some(0);
+        state.player.current_playable = Some(ids[0]);
+        state.player.position = 40;
+        assert!(state.record_skip().is_ok());
+        assert_eq!(play_count(&state, ids[0]), Some(0));
+        state.player.position = 60;
+        assert!(state.record_skip().is_ok());
+        assert_eq!(play_count(&state, ids[0]), Some(1));
+
+        // played from a queue
+        let queued = state.playables().cloned().collect();
+        state.play_playables(queued);
+        state.player.position = 60;
+        assert!(state.record_skip().is_ok());
+        assert_eq!(play_count(&state, ids[0]), Some(2));
+        assert_eq!(play_count(&state, ids[1]), Some(0));
+    }
+
+    #[test]
+    fn test_list_sort() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state
+            .append_bulk(vec![
+                scanned_file("b"),
+                scanned_file("A"),
+                scanned_file("c"),
+            ])
+            .unwrap();
+        assert!(state.set_section(Section::Library).is_ok());
+        state.player.current_playable = Some(ids[2]);
+        assert!(state.record_play().is_ok());
+        let order = |state: &State| state.playables().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(order(&state), ids);
+        assert_eq!(state.playables().last().map(|p| p.play_count), Some(1));
+
+        let title = SortOrder {
+            column: ListColumn::Title,
+            descending: false,
+        };
+        assert!(state.set_sort(Some(title)).is_ok());
+        assert_eq!(order(&state), [ids[1], ids[0], ids[2]]);
+        let plays = SortOrder {
+            column: ListColumn::PlayCount,
+            descending: true,
+        };
+        assert!(state.set_sort(Some(plays)).is_ok());
+        // ties keep the order of the library
+        assert_eq!(order(&state), [ids[2], ids[0], ids[1]]);
+        assert!(state.toggle_list_column(ListColumn::LastPlayed).is_ok());
+
+        let mut state = State::new(state.storage);
+        assert_eq!(state.sort(), Some(plays));
+        assert_eq!(state.list_columns(), [ListColumn::LastPlayed]);
+        assert!(state.set_sort(None).is_ok());
+        assert!(state.toggle_list_column(ListColumn::LastPlayed).is_ok());
+        assert!(state.list_columns().is_empty());
+    }
+
+    #[test]
+    fn test_column_widths() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert!(state.set_section(Section::Library).is_ok());
+        assert_eq!(state.column_width(ListColumn::Album), 220.0);
+        assert!(state.set_column_width(ListColumn::Album, 300.4).is_ok());
+        assert!(state.set_column_width(ListColumn::Kind, 10.0).is_ok());
+        assert_eq!(state.column_width(ListColumn::Album), 300.0);
+        assert_eq!(state.column_width(ListColumn::Kind), MIN_COLUMN_WIDTH);
+
+        // every view keeps its own widths
+        assert!(state.set_section(Section::Search("a".to_string())).is_ok());
+        assert_eq!(state.column_width(ListColumn::Album), 220.0);
+        assert!(state.set_column_width(ListColumn::Album, 180.0).is_ok());
+
+        let mut state = State::new(state.storage);
+        assert!(state.set_section(Section::Library).is_ok());
+        assert_eq!(state.column_width(ListColumn::Album), 300.0);
+        assert!(state.reset_column_widths().is_ok());
+        assert_eq!(state.column_width(ListColumn::Album), 220.0);
+        assert!(state.set_section(Section::Search("a".to_string())).is_ok());
+        assert_eq!(state.column_width(ListColumn::Album), 180.0);
+    }
+
+    #[test]
+    fn test_links() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state
+            .append_bulk(vec![scanned_file("One"), scanned_file("Two")])
+            .unwrap();
+        assert!(state.set_section(Section::Favorites).is_ok());
+        // the library shows what the section does not
+        assert_eq!(state.reveal(ids[1]).unwrap(), Some(1));
+        assert_eq!(*state.section(), Section::Library);
+
+        assert!(state.link_playables(ids[1], ids[0], LinkKind::Edit).is_ok());
+        state.load_links(0);
+        let links = state.links(ids[0]);
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].playable.id, ids[1]);
+        assert!(links[0].of_other);
+        assert!(state.unlink_playables(ids[1], ids[0]).is_ok());
+        assert!(state.links(ids[0]).is_empty());
+    }
+
+    #[test]
+    fn test_custom_fields() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state
+            .append_bulk(vec![
+                scanned_file("One"),
+                scanned_file("Two"),
+                scanned_file("Three"),
+            ])
+            .unwrap();
+        assert!(state.set_section(Section::Library).is_ok());
+        let slot = state.create_custom_field("Set slot").unwrap();
+        assert!(state.set_field_value(ids[0], slot, "peak").is_ok());
+        assert!(state.set_field_value(ids[2], slot, "closing").is_ok());
+        assert_eq!(state.field_value(ids[2], slot), "closing");
+
+        let column = ListColumn::Field(slot);
+        let by_slot = SortOrder {
+            column,
+            descending: false,
+        };
+        assert!(state.set_sort(Some(by_slot)).is_ok());
+        assert!(state.toggle_list_column(column).is_ok());
+        let order = |state: &State| state.playables().map(|p| p.id).collect::<Vec<_>>();
+        // tracks without a value first
+        assert_eq!(order(&state), [ids[1], ids[2], ids[0]]);
+
+        let mut state = State::new(state.storage);
+        assert_eq!(state.custom_fields()[0].name, "Set slot");
+        assert_eq!(state.field_value(ids[0], slot), "peak");
+        assert_eq!(state.sort(), Some(by_slot));
+        assert_eq!(state.list_columns(), [column]);
+
+        assert!(state.delete_custom_field(slot).is_ok());
+        assert!(state.custom_fields().is_empty());
+        assert_eq!(state.field_value(ids[0], slot), "");
+        assert_eq!(state.sort(), None);
+        assert!(state.list_columns().is_empty());
+    }
+
+    #[test]
+    fn test_released() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        for (title, year, release_date) in [
+            ("june", 1997, "1997-06-16"),
+            ("december", 1999, "1999-12-30"),
+            ("march", 2001, "2001-03-01"),
+            ("undated", 1995, ""),
+            ("this_year", 2026, "2026-06-16"),
+        ] {
+            let file = ScannedFile {
+                year,
+                release_date: release_date.to_string(),
+                ..scanned_file(title)
+            };
+            storage
+                .append_to_library(&AudioFileDescriptor::from(file))
+                .unwrap();
+        }
+        let library = storage.read_library().unwrap();
+        let names = |albums: &[AlbumGroup]| -> Vec<String> {
+            albums.iter().map(|album| album.name.clone()).collect()
+        };
+        assert_eq!(
+            names(&released_this_week(&library, "2026-06-18")),
+            ["album_june"]
+        );
+        // the week goes past new year
+        assert_eq!(
+            names(&released_this_week(&library, "2027-01-02")),
+            ["album_december"]
+        );
+        assert!(released_this_week(&library, "2026-09-01").is_empty());
+
+        let mut state = State::new(storage);
+        assert!(state.set_section(Section::Library).is_ok());
+        let released = SortOrder {
+            column: ListColumn::Released,
+            descending: false,
+        };
+        assert!(state.set_sort(Some(released)).is_ok());
+        let titles: Vec<&str> = state.playables().map(|p| p.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            ["undated", "june", "december", "march", "this_year"]
+        );
+        let albums = state.albums();
+        assert_eq!(albums[0].released.as_deref(), Some("1995"));
+        assert_eq!(albums[1].released.as_deref(), Some("1997-06-16"));
+    }
+
+    #[test]
+    fn test_recent_files() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        for i in 0..10 {
+            assert!(
+                state
+                    .add_recent_file(PathBuf::from(format!("/music/{i}")))
+                    .is_ok()
+            );
+        }
+        // added again, it moves back to the front
+        assert!(state.add_recent_file(PathBuf::from("/music/5")).is_ok());
+        let mut state = State::new(state.storage);
+        let recent: Vec<&str> = state
+            .recent_files()
+            .iter()
+            .map(|path| path.to_str().unwrap())
+            .collect();
+        assert_eq!(
+            recent,
+            [
+                "/music/5", "/music/9", "/music/8", "/music/7", "/music/6", "/music/4", "/music/3",
+                "/music/2"
+            ]
+        );
+
+        state.import("/music/5", vec![scanned_file("One")]).unwrap();
+        state.import("/music/4", vec![scanned_file("Two")]).unwrap();
+        assert!(
+            state
+                .last_import_of(Path::new("/music/5"))
+                .unwrap()
+                .is_some()
+        );
+        assert_eq!(state.last_import_of(Path::new("/music/3")).unwrap(), None);
+        assert!(state.clear_recent_files().is_ok());
+        assert!(state.recent_files().is_empty());
+    }
+
+    #[test]
+    fn test_artwork_lookups() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let mut same_album = scanned_file("Three");
+        same_album.artist = "artist_One".to_string();
+        same_album.album = "album_One".to_string();
+        for file in [scanned_file("One"), scanned_file("Two"), same_album] {
+            storage
+                .append_to_library(&AudioFileDescriptor::from(file))
+                .unwrap();
+        }
+        let mut state = State::new(storage);
+        let id = |title: &str| {
+            state
+                .playables
+                .iter()
+                .find(|playable| playable.title == title)
+                .unwrap()
+                .id
+        };
+        let (one, three) = (id("One"), id("Three"));
+
+        let lookups = state.artwork_lookups(&[one], false).unwrap();
+        assert_eq!(lookups.len(), 1);
+        assert_eq!(lookups[0].ids, [one]);
+        let lookups = state.artwork_lookups(&[one], true).unwrap();
+        assert_eq!(lookups.len(), 1);
+        assert_eq!(lookups[0].release, "album_One");
+        assert_eq!(lookups[0].ids, [one, three]);
+
+        assert!(state.set_artwork(&[one], vec![1, 2, 3]).is_ok());
+        let lookups = state.artwork_lookups(&[one], true).unwrap();
+        assert_eq!(lookups[0].ids, [three]);
+        assert_eq!(state.cover_artwork(one), Some(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn test_webdav_servers() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let server = |url: &str, username: &str| webdav::Server {
+            url: url.to_string(),
+            username: username.to_string(),
+            password: "secret".to_string(),
+        };
+        assert!(
+            state
+                .add_webdav_server(server("https://nas.local/music/", "me"))
+                .is_ok()
+        );
+        assert!(
+            state
+                .add_webdav_server(server("https://other.local/", ""))
+                .is_ok()
+        );
+        assert!(
+            state
+                .add_webdav_server(server("https://nas.local/music/", "you"))
+                .is_ok()
+        );
+        assert!(state.remove_webdav_server("https://other.local/").is_ok());
+        let state = State::new(state.storage);
+        assert_eq!(
+            state.webdav_servers(),
+            [server("https://nas.local/music/", "you")]
+        );
+    }
+
+    #[test]
+    fn test_subsonic_passwords() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let server = subsonic::Server {
+            url: "https://music.example.com/".to_string(),
+            username: "me".to_string(),
+            password: "secret".to_string(),
+        };
+        assert!(state.add_remote_server(server.clone().into()).is_ok());
+        let setting = |state: &State| {
+            state
+                .storage
+                .read_setting(SUBSONIC_SERVERS_KEY)
+                .unwrap()
+                .unwrap_or_default()
+        };
+        assert!(!setting(&state).contains("secret"));
+        let mut state = State::new(state.storage);
+        assert_eq!(state.subsonic_servers(), [server.clone()]);
+
+        assert!(state.remove_remote_server(&server.url).is_ok());
+        assert_eq!(secrets::read(&server.secret_key()).unwrap(), None);
+
+        // older versions kept the passwords in the settings
+        let mut storage = state.storage;
+        let old =
+            r#"[{"url": "https://music.example.com/", "username": "me", "password": "secret"}]"#;
+        storage.write_setting(SUBSONIC_SERVERS_KEY, old).unwrap();
+        let state = State::new(storage);
+        assert_eq!(state.subsonic_servers(), [server]);
+        assert!(!setting(&state).contains("secret"));
+    }
+
+    #[test]
+    fn test_peer_codes() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert_eq!(state.peer_code("Studio"), "");
+        assert!(state.set_peer_code("Studio", "4821-0937").is_ok());
+        let state = State::new(state.storage);
+        assert_eq!(state.peer_code("Studio"), "4821-0937");
+        assert_eq!(state.peer_code("Kitchen"), "");
+    }
+
+    #[test]
+    fn test_remote_mirror() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let server = subsonic::Server {
+            url: "https://music.example.com/".to_string(),
+            username: "me".to_string(),
+            password: "secret".to_string(),
+        };
+        assert!(state.add_remote_server(server.clone().into()).is_ok());
+        let song = |id: &str| subsonic::Song {
+            id: id.to_string(),
+            title: format!("Song {id}"),
+            artist: "Artist".to_string(),
+            album: "Album".to_string(),
+            genre: String::new(),
+            year: 2020,
+            duration: 180,
+            track: 1,
+            disc_number: 1,
+            bit_rate: 320,
+            suffix: "mp3".to_string(),
+        };
+        let path = |id: &str| server.stream_url(id);
+        let mirror = Mirror {
+            tracks: ["1", "2", "3"]
+                .iter()
+                .map(|id| song(id).descriptor(&server))
+                .collect(),
+            playlists: vec![("Mix".to_string(), vec![path("3"), path("1")])],
+            liked: vec![path("2")],
+        };
+        let remote = remote::Server::from(server.clone());
+        assert_eq!(state.apply_mirror(&remote, mirror.clone()), Ok(3));
+
+        // the songs are listed in the library and under their server
+        assert!(state.set_section(Section::Library).is_ok());
+        assert_eq!(state.playables().count(), 3);
+        assert!(
+            state
+                .set_section(Section::Server(server.url.clone()))
+                .is_ok()
+        );
+        let ids: HashMap<String, PlayableId> =
+            state.playables().map(|p| (p.title.clone(), p.id)).collect();
+        assert_eq!(ids.len(), 3);
+        assert!(state.is_liked(&ids["Song 2"]));
+        assert_eq!(state.section_title(state.section()), "music.example.com");
+
+        let folder = &state.playlists()[0];
+        assert_eq!(folder.value.name, "music.example.com");
+        let mix = folder.children[0].value.id;
+        assert!(state.set_section(Section::Playlist(mix)).is_ok());
+        let titles: Vec<&str> = state.playables().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, ["Song 3", "Song 1"]);
+
+        // syncing again adds nothing and follows the server's stars and playlists
+        let mirror = Mirror {
+            playlists: vec![("Mix".to_string(), vec![path("2")])],
+            liked: vec![path("1")],
+            ..mirror
+        };
+        assert_eq!(state.apply_mirror(&remote, mirror), Ok(0));
+        assert!(state.is_liked(&ids["Song 1"]));
+        assert!(!state.is_liked(&ids["Song 2"]));
+        assert_eq!(state.playlists()[0].children.len(), 1);
+        let titles: Vec<&str> = state.playables().map(|p| p.title.as_str()).collect();
+        assert_eq!(titles, ["Song 2"]);
+
+        // a server is known by its address whatever its kind
+        let jellyfin = jellyfin::Server {
+            url: server.url.clone(),
+            username: "me".to_string(),
+            user_id: "u".to_string(),
+            token: "t".to_string(),
+            device_id: "d".to_string(),
+        };
+        assert!(state.add_remote_server(jellyfin.clone().into()).is_ok());
+        assert_eq!(state.remote_servers(), [remote::Server::Jellyfin(jellyfin)]);
+        assert!(state.remove_remote_server(&server.url).is_ok());
+        assert!(state.remote_servers().is_empty());
+    }
+
+    #[test]
+    fn test_library_sources() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let _ = storage.append_to_library(&AudioFileDescriptor::from(ScannedFile {
+            artist: "Artist".to_string(),
+            album: "Album".to_string(),
+            ..scanned_file("Song 1")
+        }));
+        let mut state = State::new(storage);
+        let server = subsonic::Server {
+            url: "https://sources.example.com/".to_string(),
+            username: "me".to_string(),
+            password: "secret".to_string(),
+        };
+        let song = |id: &str, album: &str| subsonic::Song {
+            id: id.to_string(),
+            title: format!("Song {id}"),
+            artist: "Artist".to_string(),
+            album: album.to_string(),
+            genre: String::new(),
+            year: 2020,
+            duration: 180,
+            track: 1,
+            disc_number: 1,
+            bit_rate: 320,
+            suffix: "mp3".to_string(),
+        };
+        let mirror = Mirror {
+            tracks: vec![
+                song("1", "Album").descriptor(&server),
+                song("2", "Album").descriptor(&server),
+                song("3", "Other").descriptor(&server),
+            ],
+            ..Mirror::default()
+        };
+        let remote = remote::Server::from(server.clone());
+        assert_eq!(state.apply_mirror(&remote, mirror), Ok(3));
+
+        // the song of the server in the local files too is listed once, as the local file
+        assert!(state.set_section(Section::Library).is_ok());
+        let mut listed: Vec<(&str, AudioFileKind)> = state
+            .playables()
+            .map(|p| (p.title.as_str(), p.type_id))
+            .collect();
+        listed.sort_by_key(|(title, _)| *title);
+        assert_eq!(
+            listed,
+            [
+                ("Song 1", AudioFileKind::LocalFile),
+                ("Song 2", AudioFileKind::Subsonic),
+                ("Song 3", AudioFileKind::Subsonic),
+            ]
+        );
+        assert_eq!(
+            state.sources(),
+            [AudioFileKind::LocalFile, AudioFileKind::Subsonic]
+        );
+
+        state.set_source_filter(Some(AudioFileKind::Subsonic));
+        assert_eq!(state.playables().count(), 2);
+        assert_eq!(state.albums().len(), 2);
+        assert!(state.set_section(Section::Library).is_ok());
+        assert_eq!(state.source_filter(), None);
+        assert_eq!(state.playables().count(), 3);
+    }
+
+    #[test]
+    fn test_offline_downloads() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let remote = |title: &str| ScannedFile {
+            path: format!("https://dav.example.com/{title}.mp3"),
+            kind: ScannedKind::WebDav,
+            ..scanned_file(title)
+        };
+        let ids = state
+            .append_bulk(vec![remote("One"), scanned_file("Local"), remote("Two")])
+            .unwrap();
+        let playlist_id = state.create_playlist("Trip", None).unwrap();
+        state.add_to_playlist(playlist_id, &ids).unwrap();
+        let playables = state.playlist_playables(playlist_id).unwrap();
+        let status = |state: &State, index: usize| state.offline_status(&playables[index]);
+
+        // the local file has nothing to download, the remote ones are queued once
+        assert_eq!(state.make_playlist_offline(playlist_id), Ok(2));
+        assert_eq!(state.make_playlist_offline(playlist_id), Ok(0));
+        assert_eq!(status(&state, 1), None);
+        assert_eq!(status(&state, 0), Some(offline::Status::Queued));
+
+        // one at a time, in order
+        let first = state.next_offline_download().unwrap();
+        assert_eq!(first.id, ids[0]);
+        assert!(state.next_offline_download().is_none());
+        assert_eq!(status(&state, 0), Some(offline::Status::Downloading));
+        assert!(
+            state
+                .finish_offline_download(first.id, Some("/tmp/offline/1.mp3"))
+                .is_ok()
+        );
+        assert_eq!(status(&state, 0), Some(offline::Status::Offline));
+
+        // a failed download is streamed still
+        let second = state.next_offline_download().unwrap();
+        assert!(state.finish_offline_download(second.id, None).is_ok());
+        assert_eq!(status(&state, 2), Some(offline::Status::Streamed));
+        assert!(state.next_offline_download().is_none());
+
+        assert!(state.remove_offline(&[ids[0]]).is_ok());
+        assert_eq!(status(&state, 0), Some(offline::Status::Streamed));
+    }
+
+    #[test]
+    fn test_watch_folders() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert!(state.add_watch_folder(PathBuf::from("/music")).is_ok());
+        assert!(state.add_watch_folder(PathBuf::from("/podcasts")).is_ok());
+        assert!(state.add_watch_folder(PathBuf::from("/music")).is_ok());
+        assert!(state.remove_watch_folder(Path::new("/podcasts")).is_ok());
+        let mut state = State::new(state.storage);
+        assert_eq!(state.watch_folders(), [PathBuf::from("/music")]);
+
+        let mut files = vec![scanned_file("One"), scanned_file("Two")];
+        files[0].path = "/music/one.mp3".to_string();
+        files[1].path = "/music/two.mp3".to_string();
+        let ids = state.append_bulk(files).unwrap();
+        assert!(state.set_section(Section::Library).is_ok());
+        assert_eq!(
+            state
+                .library_paths_under(Path::new("/music"))
+                .unwrap()
+                .len(),
+            2
+        );
+
+        assert_eq!(
+            state
+                .remove_missing(&[PathBuf::from("/music/one.mp3")])
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            state.playables().map(|p| p.id).collect::<Vec<_>>(),
+            [ids[1]]
+        );
+        // removed playables are not imported again
+        assert_eq!(
+            state
+                .unknown_paths(&[
+                    PathBuf::from("/music/one.mp3"),
+                    PathBuf::from("/music/two.mp3"),
+                    PathBuf::from("/music/three.mp3"),
+                ])
+                .unwrap(),
+            [PathBuf::from("/music/three.mp3")]
+        );
+    }
+
+    #[test]
+    fn test_link_release() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state.append_bulk(vec![scanned_file("Live")]).unwrap();
+        assert!(state.set_section(Section::Library).is_ok());
+        state.player.current_playable = Some(ids[0]);
+        state.load_playing_chapters();
+        assert!(state.chapters(ids[0]).is_empty());
+
+        let chapters = vec![
+            Chapter {
+                title: "1 Intro".to_string(),
+                start: 0,
+            },
+            Chapter {
+                title: "2 Encore".to_string(),
+                start: 90,
+            },
+        ];
+        assert!(state.link_release(ids[0], &chapters).is_ok());
+        // the playing track is marked at once
+        assert_eq!(state.chapters(ids[0]), chapters);
+        state.load_chapters(0);
+        assert!(state.release_linked(ids[0]));
+
+        assert!(state.link_release(ids[0], &[]).is_ok());
+        state.load_chapters(0);
+        assert!(!state.release_linked(ids[0]));
+        assert!(state.chapters(ids[0]).is_empty());
+    }
+
+    #[test]
+    fn test_albums() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let mut b_one = scanned_file("One");
+        b_one.album = "b".to_string();
+        let mut b_two = scanned_file("Two");
+        b_two.album = "B".to_string();
+        let mut single = scanned_file("Three");
+        single.album = String::new();
+        let ids = state
+            .append_bulk(vec![single, b_one, b_two, scanned_file("Four")])
+            .unwrap();
+        assert!(state.set_section(Section::Library).is_ok());
+        let albums = state.albums();
+        let names: Vec<&str> = albums.iter().map(|album| album.name.as_str()).collect();
+        // album names are matched as they are tagged
+        assert_eq!(names, ["album_Four", "b", "B", ""]);
+        assert_eq!(albums[0].artist, "artist_Four");
+        assert_eq!(albums[0].cover, ids[3]);
+
+        assert!(state.set_section(Section::Album("b".to_string())).is_ok());
+        assert_eq!(
+            state.playables().map(|p| p.id).collect::<Vec<_>>(),
+            [ids[1]]
+        );
+        assert_eq!(state.album().name, "b");
+        assert_eq!(state.album().tracks.len(), 1);
+    }
+
+    #[test]
+    fn test_album_page() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let files = [("Side B", 2), ("Side A", 1)].map(|(title, track)| ScannedFile {
+            album: "Tape".to_string(),
+            track_number: track,
+            ..scanned_file(title)
+        });
+        let ids = state.append_bulk(Vec::from(files)).unwrap();
+        assert!(
+            state
+                .set_sort(Some(SortOrder {
+                    column: ListColumn::Title,
+                    descending: true,
+                }))
+                .is_ok()
+        );
+        assert!(
+            state
+                .set_section(Section::Album("Tape".to_string()))
+                .is_ok()
+        );
+        // the page keeps the order of the release whatever the list is ordered by
+        assert_eq!(
+            state.playables().map(|p| p.id).collect::<Vec<_>>(),
+            [ids[1], ids[0]]
+        );
+        assert_eq!(state.album().duration(), 200);
+        assert!(
+            state
+                .play_collection(&Section::Album("Tape".to_string()), false)
+                .is_ok()
+        );
+        assert_eq!(state.current_queued().map(|p| p.id), Some(ids[1]));
+    }
+
+    #[test]
+    fn test_search() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state
+            .append_bulk(vec![scanned_file("One"), scanned_file("Two")])
+            .unwrap();
+        assert!(state.set_section(Section::Favorites).is_ok());
+        // the results come from the whole library, not the section shown
+        assert!(state.search("two".to_string()).is_ok());
+        assert_eq!(*state.section(), Section::Search("two".to_string()));
+        assert_eq!(
+            state.playables().map(|p| p.id).collect::<Vec<_>>(),
+            [ids[1]]
+        );
+        assert!(state.search(String::new()).is_ok());
+        assert_eq!(*state.section(), Section::Library);
+    }
+
+    #[test]
+    fn test_notes() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state
+            .append_bulk(vec![scanned_file("One"), scanned_file("Two")])
+            .unwrap();
+        assert!(state.search("hard wax".to_string()).is_ok());
+        assert_eq!(state.playables().count(), 0);
+        // the results are read again with the notes
+        assert!(state.set_notes(ids[0], "Bought at Hard Wax").is_ok());
+        assert_eq!(state.notes(ids[0]), "Bought at Hard Wax");
+        assert_eq!(
+            state.playables().map(|p| p.id).collect::<Vec<_>>(),
+            [ids[0]]
+        );
+
+        let id = state.create_playlist("Warm up", None).unwrap();
+        assert!(
+            state
+                .set_playlist_notes(id, "plays well after midnight")
+                .is_ok()
+        );
+        assert_eq!(state.playlist_notes(id), "plays well after midnight");
+        assert_eq!(state.playlists_matching("MIDNIGHT").len(), 1);
+        assert_eq!(state.playlists_matching("warm").len(), 1);
+        assert!(state.playlists_matching("wax").is_empty());
+    }
+
+    #[test]
+    fn test_ratings() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state
+            .append_bulk(vec![scanned_file("One"), scanned_file("Two")])
+            .unwrap();
+        assert!(state.set_section(Section::Library).is_ok());
+        assert!(state.set_rating(ids[1], 4).is_ok());
+        assert!(state.set_rating(ids[0], 7).is_ok());
+        let ratings = |state: &State| state.playables().map(|p| p.rating).collect::<Vec<_>>();
+        assert_eq!(ratings(&state), [MAX_RATING, 4]);
+
+        assert!(state.search("rating:5".to_string()).is_ok());
+        assert_eq!(ratings(&state), [MAX_RATING]);
+        assert!(state.search("two rating:3".to_string()).is_ok());
+        assert_eq!(ratings(&state), [4]);
+        assert!(state.search("rating:x".to_string()).is_ok());
+        assert_eq!(state.playables().count(), 0);
+
+        assert!(
+            state
+                .set_section(Section::Auto(AutoPlaylist::TopRated))
+                .is_ok()
+        );
+        assert!(state.set_rating(ids[0], 0).is_ok());
+        assert_eq!(
+            state.playables().map(|p| p.id).collect::<Vec<_>>(),
+            [ids[1]]
+        );
+    }
+
+    #[test]
+    fn test_auto_playlists() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state
+            .append_bulk(vec![scanned_file("Test1"), scanned_file("Test2")])
+            .unwrap();
+        state.player.current_playable = Some(ids[1]);
+        assert!(state.record_play().is_ok());
+
+        assert!(
+            state
+                .set_section(Section::Auto(AutoPlaylist::MostPlayed))
+                .is_ok()
+        );
+        assert_eq!(
+            state.playables().map(|p| p.id).collect::<Vec<_>>(),
+            [ids[1]]
+        );
+        assert!(
+            state
+                .set_section(Section::Auto(AutoPlaylist::NeverPlayed))
+                .is_ok()
+        );
+        assert_eq!(
+            state.playables().map(|p| p.id).collect::<Vec<_>>(),
+            [ids[0]]
+        );
+        // auto playlists are read-only
+        state.bulk_remove(&[0], false);
+        assert_eq!(state.playables().count(), 1);
+
+        assert!(
+            state
+                .set_section(Section::Auto(AutoPlaylist::RecentlyLiked))
+                .is_ok()
+        );
+        state.add_to_likes(&ids[0]);
+        assert_eq!(
+            state.playables().map(|p| p.id).collect::<Vec<_>>(),
+            [ids[0]]
+        );
+        state.remove_from_likes(&ids[0]);
+        assert_eq!(state.playables().count(), 0);
+    }
+
+    #[test]
+    fn test_insights() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state
+            .append_bulk(vec![
+                scanned_file("Test1"),
+                scanned_file("Test2"),
+                scanned_file("Test3"),
+            ])
+            .unwrap();
+        let long_ago = unix_now() - 13 * SECONDS_PER_MONTH;
+        state.storage.record_play(ids[0], long_ago).unwrap();
+        state.storage.record_play(ids[1], long_ago).unwrap();
+        state.storage.record_play(ids[1], unix_now()).unwrap();
+
+        assert!(state.set_section(Section::Insights).is_ok());
+        let ids_of = |playables: &[Playable]| playables.iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids_of(&state.insights().never_played), [ids[2]]);
+        assert_eq!(ids_of(&state.insights().not_played), [ids[0]]);
+        assert!(state.set_insight_months(24).is_ok());
+        assert!(state.insights().not_played.is_empty());
+
+        assert!(state.tag_playables("Relisten", &[ids[0], ids[2]]).is_ok());
+        assert_eq!(state.tags().len(), 1);
+        // an existing tag is reused
+        assert!(state.tag_playables("relisten", &[ids[1]]).is_ok());
+        assert_eq!(state.tags().len(), 1);
+        assert_eq!(state.storage.read_tag(state.tags()[0].id).unwrap().len(), 3);
+
+        assert!(state.remove_from_library(&[ids[2]]).is_ok());
+        assert!(state.insights().never_played.is_empty());
+    }
+
+    #[test]
+    fn test_queue_progress() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let _ = state.append_bulk(vec![
+            scanned_file("Test1"),
+            scanned_file("Test2"),
+            scanned_file("Test3"),
+        ]);
+        assert_eq!(state.queue_progress(), None);
+
+        // every test file lasts 100 seconds
+        state.player.current_index = Some(1);
+        state.player.position = 1;
+        assert_eq!(state.queue_progress(), Some((2, 3, 199)));
+        state.next_playable();
+        assert_eq!(
+            state.queue_progress().map(|(position, ..)| position),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn test_context_end() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let playlist_id = storage.create_playlist("playlist1", None, None).unwrap();
+        let files: Vec<AudioFileDescriptor> = ["Test1", "Test2", "Test3"]
+            .into_iter()
+            .map(|t| AudioFileDescriptor::from(scanned_file(t)))
+            .collect();
+        let _ = storage.bulk_append_to_playlist(playlist_id, &files[..2]);
+        let _ = storage.append_to_library(&files[2]);
+        let mut state = State::new(storage);
+        assert_eq!(state.context_end(), ContextEnd::Repeat);
+
+        assert!(state.set_context_end(ContextEnd::Stop).is_ok());
+        let _ = state.play_collection(&Section::Playlist(playlist_id), false);
+        assert!(!state.ends_after_current());
+        assert!(state.next_playable());
+        assert!(state.ends_after_current());
+        assert!(!state.next_playable());
+        assert_eq!(state.current_queued().unwrap().get_title(), "Test2");
+
+        // continues with the library playable after the last played one
+        assert!(state.set_context_end(ContextEnd::Library).is_ok());
+        assert!(!state.ends_after_current());
+        assert!(state.next_playable());
+        assert_eq!(state.current_queued().unwrap().get_title(), "Test3");
+        assert_eq!(state.queue_progress().map(|(_, count, _)| count), Some(3));
+
+        assert_eq!(
+            state.storage.read_setting(CONTEXT_END_KEY).unwrap(),
+            Some("library".to_string())
+        );
+    }
+
+    #[test]
+    fn test_playlist_playback() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let playlist_id = storage.create_playlist("Warmup", None, None).unwrap();
+        let mut state = State::new(storage);
+        assert_eq!(state.playlist_playback(&Section::Library), None);
+
+        let playback = PlaylistPlayback {
+            shuffle: true,
+            fade_ms: Some(300),
+        };
+        assert!(state.set_playlist_playback(playlist_id, playback).is_ok());
+        assert_eq!(state.section_playback(), PlaylistPlayback::default());
+        state.set_section(Section::Playlist(playlist_id)).unwrap();
+        assert_eq!(state.section_playback(), playback);
+        assert_eq!(
+            state.playlist_playback(&Section::Playlist(playlist_id)),
+            Some(playback)
+        );
+        state.set_section(Section::Library).unwrap();
+        assert_eq!(state.section_playback(), PlaylistPlayback::default());
+    }
+
+    #[test]
+    fn test_dynamic_playlist() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let _ = storage.append_to_library(&AudioFileDescriptor::from(scanned_file("Test1")));
+        let _ = storage.append_to_library(&AudioFileDescriptor::from(scanned_file("Test2")));
+        let playlist_id = storage
+            .create_playlist("Smart", Some(storage::PlaylistKind::Dynamic), None)
+            .unwrap();
+        let mut state = State::new(storage);
+        state.set_section(Section::Playlist(playlist_id)).unwrap();
+        assert_eq!(state.playables().count(), 0);
+
+        let rules = vec![PlaylistRule {
+            field: storage::RuleField::Artist,
+            text: "artist_Test2".to_string(),
+            ..Default::default()
+        }];
+        assert!(state.set_playlist_rules(playlist_id, &rules).is_ok());
+        assert_eq!(state.playlist_rules(playlist_id).unwrap(), rules);
+        let titles: Vec<&str> = state.playables().map(|p| p.get_title()).collect();
+        assert_eq!(titles, ["Test2"]);
+        assert!(
+            state
+                .playlist_tracklist(playlist_id)
+                .unwrap()
+                .contains("Test2")
+        );
+    }
+
+    #[test]
+    fn test_close_behavior() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert_eq!(state.close_behavior(), CloseBehavior::Ask);
+
+        assert!(state.set_close_behavior(CloseBehavior::KeepPlaying).is_ok());
+
+        let state = State::new(state.storage);
+        assert_eq!(state.close_behavior(), CloseBehavior::KeepPlaying);
+    }
+
+    #[test]
+    fn test_fade() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert_eq!(state.fade_ms(), DEFAULT_FADE_MS);
+        assert!(state.set_fade_ms(0).is_ok());
+
+        let state = State::new(state.storage);
+        assert_eq!(state.fade_ms(), 0);
+    }
+
+    #[test]
+    fn test_crossfade() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert_eq!(state.crossfade_secs(), 0);
+        assert!(state.set_crossfade_secs(30).is_ok());
+        assert_eq!(state.crossfade_secs(), MAX_CROSSFADE_SECS);
+        assert!(state.set_crossfade_secs(6).is_ok());
+
+        let state = State::new(state.storage);
+        assert_eq!(state.crossfade_secs(), 6);
+    }
+
+    #[test]
+    fn test_output_latency() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert_eq!(state.output_latency_ms(), 0);
+        assert!(state.set_output_latency_ms(200).is_ok());
+
+        let state = State::new(state.storage);
+        assert_eq!(state.output_latency_ms(), 200);
+    }
+
+    #[test]
+    fn test_match_source_rate() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert!(!state.match_source_rate());
+        assert!(state.set_match_source_rate(true).is_ok());
+
+        let state = State::new(state.storage);
+        assert!(state.match_source_rate());
+    }
+
+    #[test]
+    fn test_volume_trims() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let station = TrimTarget::Station("radio.example.com".to_string());
+        assert!(
+            state
+                .set_volume_trim(TrimTarget::Kind(PlayableKind::Stream), -4)
+                .is_ok()
+        );
+        assert!(state.set_volume_trim(station.clone(), 3).is_ok());
+        assert!(state.remove_volume_trim(&station).is_ok());
+
+        let state = State::new(state.storage);
+        assert_eq!(
+            state
+                .volume_trims()
+                .get(&TrimTarget::Kind(PlayableKind::Stream)),
+            Some(-4)
+        );
+        assert_eq!(state.volume_trims().stations().count(), 0);
+    }
+
+    #[test]
+    fn test_hover_preview() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert!(state.hover_preview());
+        assert!(state.set_hover_preview(false).is_ok());
+
+        let state = State::new(state.storage);
+        assert!(!state.hover_preview());
+    }
+
+    #[test]
+    fn test_resample_quality() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert_eq!(state.resample_quality(), ResampleQuality::Medium);
+        assert!(state.set_resample_quality(ResampleQuality::High).is_ok());
+
+        let state = State::new(state.storage);
+        assert_eq!(state.resample_quality(), ResampleQuality::High);
+    }
+
+    #[test]
+    fn test_scan_options() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert_eq!(state.scan_options(), &ScanOptions::default());
+
+        let options = ScanOptions {
+            exclude: vec!["**/samples/**".to_string(), "*.stem.mp4".to_string()],
+            min_duration: 30,
+            follow_symlinks: true,
+            skip_hidden: true,
+            max_depth: Some(4),
+            threads: Some(2),
+            artwork_max_size: Some(1000),
+        };
+        assert!(state.set_scan_options(options.clone()).is_ok());
+        assert_eq!(State::read_scan_options(state.storage.as_ref()), options);
+
+        let options = ScanOptions {
+            max_depth: None,
+            threads: None,
+            artwork_max_size: None,
+            ..options
+        };
+        assert!(state.set_scan_options(options.clone()).is_ok());
+        assert_eq!(State::read_scan_options(state.storage.as_ref()), options);
+    }
+
+    #[test]
+    fn test_delete_tag() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let _ = storage.create_tag("tag1");
+        let _ = storage.create_tag("tag2");
+
+        let mut state = State::new(storage);
+        let files = vec![
+            scanned_file("Test1"),
+            scanned_file("Test2"),
+            scanned_file("Test3"),
+            scanned_file("Test4"),
+            scanned_file("Test5"),
+            scanned_file("Test6"),
+        ];
+        let _ = state.append_bulk(files);
+        let _ = state.append_to_tag(1, 1);
+        let _ = state.append_to_tag(1, 2);
+        let _ = state.append_to_tag(1, 3);
+        let _ = state.append_to_tag(2, 4);
+        let _ = state.append_to_tag(2, 5);
+        let _ = state.append_to_tag(2, 6);
+
+        assert!(state.set_section(Section::Tag(1)).is_ok());
+        assert_eq!(state.playables().count(), 3);
+
+        assert!(state.delete_tag(1).is_ok());
+        assert_eq!(state.section(), &Section::Tag(2));
+        assert_eq!(state.playables().count(), 3);
+
+        assert!(state.delete_tag(2).is_ok());
+        assert_eq!(state.section(), &Section::Library);
+        assert_eq!(state.playables().count(), 6);
+    }
+
+    #[test]
+    fn test_episode_position() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let feed = Feed {
+            title: String::new(),
+            episodes: vec![FeedEpisode {
+                guid: "ep-1".to_string(),
+                title: "Episode 1".to_string(),
+                description: String::new(),
+                published: 0,
+                audio_url: "https://example.com/ep1.mp3".to_string(),
+                duration: 3600,
+            }],
+        };
+        let id = state
+            .save_podcast("https://example.com/feed", feed)
+            .unwrap();
+        assert_eq!(state.podcasts()[0].title, "https://example.com/feed");
+        state.set_section(Section::Podcast(id)).unwrap();
+        let episode_id = state.episodes()[0].id;
+
+        state.play_episode(episode_id).unwrap();
+        state.update_playback_position(5);
+        state.update_playback_position(125);
+        assert_eq!(state.episodes()[0].position, 125);
+        state.set_section(Section::Podcast(id)).unwrap();
+        assert_eq!(state.episodes()[0].position, 125);
+
+        assert!(state.finish_playback());
+        assert!(!state.finish_playback());
+        state.set_section(Section::Podcast(id)).unwrap();
+        assert_eq!(state.episodes()[0].position, 0);
+    }
+
+    #[test]
+    fn test_audiobook_position() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let id = storage
+            .append_to_library(&AudioFileDescriptor::from(scanned_file("Book")))
+            .unwrap();
+        let mut state = State::new(storage);
+        assert_eq!(state.resume_position(id), 0);
+
+        state.set_audiobook(id, true).unwrap();
+        assert!(state.is_audiobook(id));
+        state.player.current_playable = Some(id);
+        assert_eq!(state.resume_position(id), 0);
+        state.update_playback_position(600);
+        // reported by the player when a file is loaded
+        state.update_playback_position(0);
+        assert_eq!(state.resume_position(id), 600);
+
+        assert!(!state.finish_playback());
+        assert_eq!(state.resume_position(id), 0);
+
+        state.set_audiobook(id, false).unwrap();
+        assert!(!state.is_audiobook(id));
+
+        // read once for the selection, not by every frame of the details panel
+        state.set_audiobook(id, true).unwrap();
+        let mut state = State::new(state.storage);
+        assert!(!state.is_audiobook(id));
+        assert!(state.set_section(Section::Library).is_ok());
+        assert!(state.load_playables().is_ok());
+        state.load_audiobook(0);
+        assert!(state.is_audiobook(id));
+    }
+
+    #[test]
+    fn test_skip_silence() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert_eq!(state.skip_silence(), None);
+        assert!(state.set_skip_silence(Some(-50)).is_ok());
+        let mut state = State::new(state.storage);
+        assert_eq!(state.skip_silence(), Some(-50));
+        assert!(state.set_skip_silence(None).is_ok());
+        let state = State::new(state.storage);
+        assert_eq!(state.skip_silence(), None);
+    }
+
+    #[test]
+    fn test_eq_curves() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        assert!(state.set_eq_preset("Headphones", Preset::BassBoost).is_ok());
+        assert!(state.set_eq_band("Speakers", 9, 3).is_ok());
+        let state = State::new(state.storage);
+        assert_eq!(state.eq_curves().preset("Headphones"), Preset::BassBoost);
+        assert_eq!(state.eq_curves().curve("Speakers")[9], 3);
+    }
+
+    #[test]
+    fn test_long_track_progress() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let id = storage
+            .append_to_library(&AudioFileDescriptor::from(ScannedFile {
+                duration: 3600,
+                ..scanned_file("Mix")
+            }))
+            .unwrap();
+        let short = storage
+            .append_to_library(&AudioFileDescriptor::from(scanned_file("Track")))
+            .unwrap();
+        let mut state = State::new(storage);
+        assert_eq!(state.long_track_minutes(), DEFAULT_LONG_TRACK_MINUTES);
+
+        state.player.current_playable = Some(short);
+        assert_eq!(state.resume_position(short), 0);
+        state.update_playback_position(60);
+        assert_eq!(state.resume_position(short), 0);
+
+        state.player.current_playable = Some(id);
+        assert_eq!(state.resume_position(id), 0);
+        state.update_playback_position(1800);
+        state.update_playback_position(0);
+        assert!(state.resumes_midway(id));
+        assert_eq!(state.resume_position(id), 1800);
+
+        state.set_long_track_minutes(0).unwrap();
+        assert_eq!(state.resume_position(id), 0);
+        state.set_long_track_minutes(30).unwrap();
+        assert_eq!(state.resume_position(id), 1800);
+
+        assert!(!state.finish_playback());
+        assert!(!state.resumes_midway(id));
+        assert_eq!(state.resume_position(id), 0);
     }
 
-    pub fn previous_playable(&mut self) {
-        if self.player.current_index.is_none() {
-            if !self.playables.is_empty() {
-                self.player.current_index = Some(0);
-                self.player.current_playable = Some(self.playables[0].id);
-            }
-            return;
+    #[test]
+    fn test_up_next() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let playlist_id = storage.create_playlist("playlist1", None, None).unwrap();
+        let files: Vec<AudioFileDescriptor> = ["Test1", "Test2", "Extra1", "Extra2", "Extra3"]
+            .into_iter()
+            .map(|t| AudioFileDescriptor::from(scanned_file(t)))
+            .collect();
+        let _ = storage.bulk_append_to_playlist(playlist_id, &files[..2]);
+        for file in &files[2..] {
+            let _ = storage.append_to_library(file);
         }
+        let mut state = State::new(storage);
+        let extras: Vec<Playable> = state
+            .playables()
+            .filter(|p| p.get_title().starts_with("Extra"))
+            .cloned()
+            .collect();
 
-        let index = self.player.current_index.unwrap();
-        let prev_index = if self.player.shuffle {
-            self.random_generator.random_range(0..self.playables.len())
-        } else if index == 0 {
-            self.playables.len() - 1
-        } else {
-            index - 1
+        state
+            .play_collection(&Section::Playlist(playlist_id), false)
+            .unwrap();
+        state.enqueue_last(vec![extras[2].clone()]);
+        state.enqueue_next(extras[..2].to_vec());
+        let titles = |state: &State| -> Vec<String> {
+            state
+                .up_next()
+                .iter()
+                .map(|p| p.get_title().to_string())
+                .collect()
         };
+        assert_eq!(titles(&state), ["Extra1", "Extra2", "Extra3"]);
+        state.move_up_next(2, 0);
+        state.remove_from_up_next(2);
+        assert_eq!(titles(&state), ["Extra3", "Extra1"]);
 
-        let prev_id = self.playables[prev_index].id;
-        self.player.current_index = Some(prev_index);
-        self.player.current_playable = Some(prev_id);
-    }
-}
+        assert_eq!(state.peek_next().unwrap().get_title(), "Extra3");
+        assert!(state.next_playable());
+        assert!(state.playing_up_next());
+        assert_eq!(state.current_queued().unwrap().get_title(), "Extra3");
+        // back to the playable the queued ones interrupted
+        state.previous_playable();
+        assert_eq!(state.current_queued().unwrap().get_title(), "Test1");
+        state.next_playable();
+        assert_eq!(state.current_queued().unwrap().get_title(), "Extra1");
+        // then the playlist goes on where it was
+        assert_eq!(state.peek_next().unwrap().get_title(), "Test2");
+        state.next_playable();
+        assert!(!state.playing_up_next());
+        assert_eq!(state.current_queued().unwrap().get_title(), "Test2");
 
-impl From<ScannedFile> for AudioFileDescriptor {
-    fn from(val: ScannedFile) -> Self {
-        AudioFileDescriptor {
-            title: val.title,
-            artist: val.artist,
-            album: val.album,
-            year: val.year,
-            genre: val.genre,
-            duration: val.duration,
-            path: val.path,
-            artwork: val.artwork,
-            kind: match val.kind {
-                ScannedKind::LocalFile => AudioFileKind::LocalFile,
-                ScannedKind::GoogleDrive => AudioFileKind::GoogleDrive,
-                ScannedKind::Dropbox => AudioFileKind::Dropbox,
-                ScannedKind::Youtube => AudioFileKind::Youtube,
-                ScannedKind::Stream => AudioFileKind::Stream,
-            },
-        }
+        state.enqueue_last(extras.clone());
+        state.clear_up_next();
+        assert!(state.up_next().is_empty());
     }
-}
 
-mod tests {
-    use super::*;
+    #[test]
+    fn test_play_collection() {
+        let mut storage = Box::new(init_storage(":memory:").unwrap());
+        let playlist_id = storage.create_playlist("playlist1", None, None).unwrap();
+        let files: Vec<AudioFileDescriptor> = ["Test1", "Test2", "Test3"]
+            .into_iter()
+            .map(|t| AudioFileDescriptor::from(scanned_file(t)))
+            .collect();
+        let _ = storage.bulk_append_to_playlist(playlist_id, &files[..2]);
+        let _ = storage.append_to_library(&files[2]);
+        let mut state = State::new(storage);
 
-    #[allow(dead_code)]
-    fn scanned_file(title: &str) -> ScannedFile {
-        ScannedFile {
-            title: title.to_string(),
-            artist: format!("artist_{title}"),
-            album: format!("album_{title}"),
-            year: 2001,
-            genre: format!("genre_{title}"),
+        let first = state
+            .play_collection(&Section::Playlist(playlist_id), false)
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.get_title(), "Test1");
+        assert_eq!(state.section(), &Section::Library);
+        assert!(state.queue_active());
+
+        state.next_playable();
+        assert_eq!(state.current_queued().unwrap().get_title(), "Test2");
+        // wraps around the playlist, not the library
+        state.next_playable();
+        assert_eq!(state.current_queued().unwrap().get_title(), "Test1");
+
+        state.clear_queue();
+        assert!(!state.queue_active());
+        assert!(state.current_queued().is_none());
+
+        let empty = state.play_collection(&Section::Tag(1), true).unwrap();
+        assert!(empty.is_none());
+        state.next_playable();
+        assert_eq!(state.player.current_index, None);
+    }
+
+    #[test]
+    fn test_like_stream() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let episode = Episode {
+            id: 1,
+            podcast_id: 1,
+            title: "Episode".to_string(),
+            description: String::new(),
+            published: 0,
+            audio_url: "https://example.com/episode.mp3".to_string(),
             duration: 100,
-            path: format!("path_{title}"),
-            artwork: None,
-            kind: ScannedKind::LocalFile,
-        }
+            local_path: None,
+            position: 0,
+        };
+
+        let liked = state.like_stream(&episode).unwrap();
+        assert!(liked.id > 0);
+        assert_eq!(liked.type_id, AudioFileKind::Stream);
+        assert_eq!(liked.source_url, episode.audio_url);
+        assert!(state.is_liked(&liked.id));
+        assert_eq!(state.playables().count(), 1);
+
+        // liking it again does not add a second copy
+        assert_eq!(state.like_stream(&episode).unwrap().id, liked.id);
+        assert_eq!(state.playables().count(), 1);
     }
 
     #[test]
-    fn test_initialized_correctly() {
-        let mut storage = Box::new(init_storage(":memory:").unwrap());
-        let _ = storage.append_to_library(&AudioFileDescriptor::from(scanned_file("Test1")));
-        let _ = storage.append_to_library(&AudioFileDescriptor::from(scanned_file("Test2")));
-        let _ = storage.create_tag("tag1");
-        let _ = storage.create_playlist("playlist1", None, None);
+    fn test_save_streams_as_playlist() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let sample = |url: &str| Playable {
+            id: -1,
+            title: url.to_string(),
+            source_url: url.to_string(),
+            type_id: AudioFileKind::Stream,
+            label: Some("Warp".to_string()),
+            ..Default::default()
+        };
+        let chart = [
+            sample("https://samples.example.com/1.mp3"),
+            sample("https://samples.example.com/2.mp3"),
+        ];
 
-        let state = State::new(storage);
-        assert_eq!(state.playables.len(), 2);
-        assert_eq!(state.playlist_names.len(), 1);
-        assert_eq!(state.tag_names.len(), 1);
+        let (id, name) = state.save_streams_as_playlist("Top 100", &chart).unwrap();
+        assert_eq!(name, "Top 100");
+        let saved = state.playlist_playables(id).unwrap();
+        assert_eq!(saved.len(), 2);
+        assert!(
+            saved
+                .iter()
+                .all(|p| p.id > 0 && p.type_id == AudioFileKind::Stream)
+        );
+        assert_eq!(saved[0].source_url, chart[0].source_url);
+        assert_eq!(saved[0].label.as_deref(), Some("Warp"));
+
+        // saved again the streams are not added to the library twice
+        let (again, name) = state.save_streams_as_playlist("Top 100", &chart).unwrap();
+        assert_eq!(name, "Top 100 (2)");
+        let ids = |id| -> Vec<i64> {
+            state
+                .playlist_playables(id)
+                .unwrap()
+                .iter()
+                .map(|p| p.id)
+                .collect()
+        };
+        assert_eq!(ids(again), ids(id));
     }
 
     #[test]
-    fn test_append_bulk() {
+    fn test_recently_deleted() {
         let mut storage = Box::new(init_storage(":memory:").unwrap());
-        let _ = storage.create_playlist("test_playlist", None, None);
+        let ids = storage
+            .bulk_append_to_library(&[
+                AudioFileDescriptor::from(scanned_file("Test1")),
+                AudioFileDescriptor::from(scanned_file("Test2")),
+                AudioFileDescriptor::from(scanned_file("Test3")),
+            ])
+            .unwrap();
+        storage.append_like(ids[0]).unwrap();
         let mut state = State::new(storage);
-        let files = vec![scanned_file("Test1"), scanned_file("Test2")];
-        assert!(state.append_bulk(files).is_ok());
-        assert_eq!(state.playables.len(), 2);
+        assert_eq!(
+            state.deleted_retention(),
+            Some(DEFAULT_DELETED_RETENTION_DAYS)
+        );
 
-        assert!(state.set_section(Section::Favorites).is_ok());
-        let files = vec![scanned_file("Test3"), scanned_file("Test4")];
-        assert!(state.append_bulk(files).is_ok());
-        assert_eq!(state.playables.len(), 0);
+        assert!(state.set_section(Section::Library).is_ok());
+        state.bulk_remove(&[0, 1], false);
+        assert_eq!(state.playables().count(), 1);
+        assert!(!state.is_liked(&ids[0]));
 
-        assert!(state.set_section(Section::Playlist(1)).is_ok());
-        let files = vec![scanned_file("Test5"), scanned_file("Test6")];
-        assert!(state.append_bulk(files).is_ok());
-        assert_eq!(state.playables.len(), 2);
-        assert_eq!(state.playables[0].get_title(), "Test5");
-        assert_eq!(state.playables[1].get_title(), "Test6");
+        assert!(state.set_section(Section::RecentlyDeleted).is_ok());
+        assert_eq!(state.deleted().len(), 2);
+        assert!(state.restore_deleted(&[ids[0]]).is_ok());
+        assert_eq!(state.deleted().len(), 1);
+        assert!(state.is_liked(&ids[0]));
+        assert!(state.purge_deleted(&[ids[1]]).is_ok());
+        assert!(state.deleted().is_empty());
 
-        assert!(state.set_section(Section::Library).is_ok());
-        assert_eq!(state.playables.len(), 6);
+        // deleted just now, well within the retention
+        assert!(state.remove_from_library(&[ids[2]]).is_ok());
+        assert!(state.set_deleted_retention(Some(7)).is_ok());
+        assert_eq!(state.deleted().len(), 1);
+        assert!(state.set_deleted_retention(None).is_ok());
+        let mut state = State::new(state.storage);
+        assert_eq!(state.deleted_retention(), None);
+        assert!(state.empty_deleted().is_ok());
+        assert!(state.storage.read_deleted().unwrap().is_empty());
+        assert_eq!(state.playables().count(), 1);
     }
 
     #[test]
-    fn test_delete_playlist() {
-        let mut storage = Box::new(init_storage(":memory:").unwrap());
-        let _ = storage.create_playlist("test_playlist", None, None);
-        let _ = storage.create_playlist("test_playlist2", None, None);
+    fn test_snapshots() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state
+            .append_bulk(vec![scanned_file("Test1"), scanned_file("Test2")])
+            .unwrap();
+        assert!(state.set_section(Section::Snapshots).is_ok());
+        assert!(state.take_snapshot(" before sync ").is_ok());
+        assert_eq!(state.snapshots().len(), 1);
+        assert_eq!(state.snapshots()[0].label, "before sync");
+
+        let _ = state.append_bulk(vec![scanned_file("Test3")]);
+        assert!(state.remove_from_library(&ids[..1]).is_ok());
+        assert!(state.tag_playables("dub", &ids[1..]).is_ok());
+        let id = state.snapshots()[0].id;
+        assert!(state.compare_snapshot(id).is_ok());
+        let comparison = state.comparison().unwrap();
+        assert_eq!(comparison.counts, (2, 1, 0));
+        assert_eq!(comparison.diff.added[0].title, "Test3");
+        assert_eq!(comparison.diff.removed[0].title, "Test1");
+        assert_eq!(comparison.diff.changed[0].1.tags, ["dub"]);
+
+        assert!(state.delete_snapshot(id).is_ok());
+        assert!(state.snapshots().is_empty());
+        assert!(state.comparison().is_none());
+    }
 
+    #[test]
+    fn test_finish_conversion() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
         let mut state = State::new(storage);
-        assert!(state.set_section(Section::Playlist(2)).is_ok()); //test_playlist2
-        let files = vec![
-            scanned_file("Test1"),
-            scanned_file("Test2"),
-            scanned_file("Test3"),
-        ];
-        assert!(state.append_bulk(files).is_ok());
+        let ids = state
+            .append_bulk(vec![scanned_file("Test1"), scanned_file("Test2")])
+            .unwrap();
+
+        assert!(
+            state
+                .finish_conversion(ids[0], Path::new("/converted/Test1.opus"), false)
+                .is_ok()
+        );
         assert_eq!(state.playables.len(), 3);
+        let copy = state
+            .playables
+            .iter()
+            .find(|p| p.id != ids[0] && p.id != ids[1]);
+        assert_eq!(copy.unwrap().source_url, "/converted/Test1.opus");
+        assert_eq!(copy.unwrap().get_artist(), "artist_Test1");
 
-        assert!(state.set_section(Section::Playlist(1)).is_ok()); //test_playlist1
-        let files = vec![
-            scanned_file("Test4"),
-            scanned_file("Test5"),
-            scanned_file("Test6"),
-        ];
-        assert!(state.append_bulk(files).is_ok());
+        assert!(
+            state
+                .finish_conversion(ids[1], Path::new("/converted/Test2.mp3"), true)
+                .is_ok()
+        );
         assert_eq!(state.playables.len(), 3);
+        let library = state.storage.read_library_from_ids(&ids[1..]).unwrap();
+        assert_eq!(library[0].source_url, "/converted/Test2.mp3");
+    }
 
-        assert!(state.set_section(Section::Library).is_ok());
-        assert_eq!(state.playables.len(), 6);
+    #[test]
+    fn test_merge_sync() {
+        let moved = std::env::temp_dir().join("phoniq_test_merge_sync.mp3");
+        std::fs::write(&moved, b"not really audio").unwrap();
+        let mut file = scanned_file("Moved");
+        file.path = moved.to_string_lossy().to_string();
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state
+            .append_bulk(vec![scanned_file("Test1"), file])
+            .unwrap();
 
-        assert!(state.set_section(Section::Playlist(1)).is_ok());
-        assert!(state.delete_playlist(1).is_ok());
+        let document = SyncDocument {
+            tracks: vec![
+                SyncTrack {
+                    path: "path_Test1".to_string(),
+                    fingerprint: None,
+                },
+                SyncTrack {
+                    path: "/elsewhere/moved.mp3".to_string(),
+                    fingerprint: Some(sync::fingerprint(&moved).unwrap()),
+                },
+                SyncTrack {
+                    path: "/elsewhere/missing.mp3".to_string(),
+                    fingerprint: None,
+                },
+            ],
+            likes: vec![0, 2],
+            ratings: vec![(0, 4), (1, 2)],
+            tags: vec![("dub".to_string(), vec![0, 1])],
+            playlists: vec![("Peak".to_string(), vec![1, 0, 1])],
+        };
+        // rated here already, the peer's rating is not taken
+        assert!(state.set_rating(ids[1], 5).is_ok());
+        let summary = state.merge_sync(&document).unwrap();
+        assert_eq!(
+            summary,
+            SyncSummary {
+                liked: 1,
+                rated: 1,
+                tagged: 2,
+                added_to_playlists: 2,
+                unmatched: 1,
+            }
+        );
+        assert!(state.liked_ids.contains(&ids[0]));
+        let rated = state.storage.read_by_rating(1).unwrap();
+        assert_eq!(rated[0].id, ids[1]);
+        assert_eq!(rated[1].rating, 4);
+        assert_eq!(state.tag_names[0].name, "dub");
+        assert_eq!(state.playlist_names[0].value.name, "Peak");
 
-        assert_eq!(state.section(), &Section::Playlist(2));
-        assert_eq!(state.playables.len(), 3);
+        // merging again changes nothing
+        let summary = state.merge_sync(&document).unwrap();
+        assert_eq!(
+            summary.liked + summary.rated + summary.tagged + summary.added_to_playlists,
+            0
+        );
 
-        let _ = state.delete_playlist(2);
-        assert_eq!(state.section(), &Section::Library);
-        assert_eq!(state.playables().count(), 6);
+        let shared = state.sync_document().unwrap();
+        assert_eq!(shared.tracks.len(), 2);
+        assert_eq!(shared.likes.len(), 1);
+        assert_eq!(shared.ratings.len(), 2);
+        assert_eq!(shared.tags[0].1.len(), 2);
+        assert_eq!(shared.playlists[0].1.len(), 2);
+        std::fs::remove_file(&moved).unwrap();
     }
 
     #[test]
-    fn test_clear_playlist() {
+    fn test_shared_playlist() {
         let mut storage = Box::new(init_storage(":memory:").unwrap());
-        let _ = storage.create_playlist("test_playlist", None, None);
-        let _ = storage.create_playlist("test_playlist2", None, None);
+        let playlist_id = storage.create_playlist("Mixtape", None, None).unwrap();
+        let files: Vec<AudioFileDescriptor> = ["Test1", "Test2"]
+            .into_iter()
+            .map(|t| AudioFileDescriptor::from(scanned_file(t)))
+            .collect();
+        let _ = storage.bulk_append_to_playlist(playlist_id, &files);
+        let mut state = State::new(storage);
+
+        let mut shared = state.share_playlist(playlist_id).unwrap();
+        assert_eq!(shared.name, "Mixtape");
+        assert_eq!(shared.tracks.len(), 2);
+        assert_eq!(shared.tracks[0].title, "Test1");
+        shared.tracks.push(SharedTrack {
+            title: "Elsewhere".to_string(),
+            artist: "Someone".to_string(),
+            album: String::new(),
+            duration: 0,
+            fingerprint: None,
+            references: Vec::new(),
+        });
+
+        // matched by artist and title, the files do not exist
+        let import = state.import_shared_playlist(&shared).unwrap();
+        assert_eq!(import.name, "Mixtape (2)");
+        assert_eq!(import.matched, 2);
+        assert_eq!(import.missing, ["Someone - Elsewhere"]);
+        let titles: Vec<String> = state
+            .storage
+            .read_playlist(import.playlist_id)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.title)
+            .collect();
+        assert_eq!(titles, ["Test1", "Test2"]);
+    }
 
+    #[test]
+    fn test_section_title() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
         let mut state = State::new(storage);
-        assert!(state.set_section(Section::Playlist(1)).is_ok()); //test_playlist1
-        let files = vec![
-            scanned_file("Test4"),
-            scanned_file("Test5"),
-            scanned_file("Test6"),
-        ];
-        assert!(state.append_bulk(files).is_ok());
-        assert_eq!(state.playables.len(), 3);
+        let id = state
+            .create_playlist("Warm up", Some(storage::PlaylistKind::Static))
+            .unwrap();
+        assert_eq!(state.section_title(&Section::Playlist(id)), "Warm up");
+        assert_eq!(
+            state.section_title(&Section::Album("Amber".to_string())),
+            "Amber"
+        );
+        assert_eq!(
+            state.section_title(&Section::Album(String::new())),
+            "Unknown Album"
+        );
+        // deleted meanwhile
+        assert_eq!(state.section_title(&Section::Tag(99)), "Tag 99");
+        assert_eq!(state.section_title(&Section::Favorites), "Favorites");
+    }
 
-        assert!(state.set_section(Section::Playlist(2)).is_ok()); //test_playlist2
-        let files = vec![
-            scanned_file("Test1"),
-            scanned_file("Test2"),
-            scanned_file("Test3"),
-        ];
-        assert!(state.append_bulk(files).is_ok());
-        assert_eq!(state.playables.len(), 3);
+    #[test]
+    fn test_import_playlist_file() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        state
+            .append_bulk(vec![scanned_file("Test1"), scanned_file("Test2")])
+            .unwrap();
+        let entry = |location: &str, title: Option<&str>| crate::m3u::Entry {
+            location: location.to_string(),
+            title: title.map(str::to_string),
+        };
+        let playlist = PlaylistFile {
+            name: "Friday".to_string(),
+            entries: vec![
+                entry("path_Test2", None),
+                entry("/nowhere/gone.mp3", Some("Someone - Gone")),
+                entry("path_Test1", Some("Test1")),
+            ],
+        };
+        // the missing file does not exist, there is nothing to scan
+        assert!(state.unknown_files(&playlist).unwrap().is_empty());
 
-        assert!(state.set_section(Section::Library).is_ok());
-        assert_eq!(state.playables.len(), 6);
+        let import = state
+            .import_playlist_file("friday.m3u", &playlist, Vec::new())
+            .unwrap();
+        assert_eq!(import.name, "Friday");
+        assert_eq!(import.matched, 2);
+        assert_eq!(import.missing, ["Someone - Gone"]);
+        let titles: Vec<String> = state
+            .playlist_playables(import.playlist_id)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.title)
+            .collect();
+        assert_eq!(titles, ["Test2", "Test1"]);
+        assert!(state.storage.read_imports().unwrap().is_empty());
 
-        assert!(state.set_section(Section::Playlist(2)).is_ok());
-        assert_eq!(state.playables.len(), 3);
-        assert!(state.clear_playlist(1).is_ok());
-        assert_eq!(state.playables.len(), 3);
-        assert!(state.set_section(Section::Playlist(1)).is_ok());
-        assert_eq!(state.playables().count(), 0);
+        // files scanned on import join the library and the playlist
+        let gone = ScannedFile {
+            path: "/nowhere/gone.mp3".to_string(),
+            ..scanned_file("Gone")
+        };
+        let import = state
+            .import_playlist_file("friday.m3u", &playlist, vec![gone])
+            .unwrap();
+        assert_eq!(import.name, "Friday (2)");
+        assert_eq!(import.matched, 3);
+        let imports = state.storage.read_imports().unwrap();
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].playlist_name.as_deref(), Some("Friday (2)"));
+    }
 
-        assert!(state.set_section(Section::Playlist(2)).is_ok());
-        assert_eq!(state.playables.len(), 3);
-        assert!(state.clear_playlist(2).is_ok());
-        assert_eq!(state.playables.len(), 0);
+    #[test]
+    fn test_add_to_playlist() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state
+            .append_bulk(vec![
+                scanned_file("Test1"),
+                scanned_file("Test2"),
+                scanned_file("Test3"),
+            ])
+            .unwrap();
+        let playlist_id = state.create_playlist("Dropped", None).unwrap();
+        state.set_section(Section::Playlist(playlist_id)).unwrap();
+
+        assert_eq!(state.add_to_playlist(playlist_id, &ids[..2]).unwrap(), 2);
+        assert_eq!(state.playables().count(), 2);
+        // tracks already in the playlist are not added twice
+        assert_eq!(state.add_to_playlist(playlist_id, &ids).unwrap(), 1);
+        let titles: Vec<&str> = state.playables().map(|p| p.get_title()).collect();
+        assert_eq!(titles, ["Test1", "Test2", "Test3"]);
     }
 
     #[test]
-    fn test_nested_playlists() {
-        let mut storage = init_storage(":memory:").unwrap();
-        let _ = storage.create_playlist("Folder", Some(crate::storage::PlaylistKind::Folder), None);
-        let _ = storage.create_playlist("playlist", None, Some(1));
-        let state = State::new(Box::new(storage));
-        let playlists = state.playlists();
-        assert_eq!(playlists.len(), 1);
-        assert_eq!(playlists[0].children.len(), 1);
-        assert_eq!(playlists[0].value.id, 1);
-        assert_eq!(playlists[0].children[0].value.id, 2);
+    fn test_move_rows() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let ids = state
+            .append_bulk(
+                ["Test1", "Test2", "Test3", "Test4", "Test5"]
+                    .into_iter()
+                    .map(scanned_file)
+                    .collect(),
+            )
+            .unwrap();
+        let playlist_id = state.create_playlist("Reordered", None).unwrap();
+        state.add_to_playlist(playlist_id, &ids).unwrap();
+        assert!(!state.can_reorder());
+        state.set_section(Section::Playlist(playlist_id)).unwrap();
+        assert!(state.can_reorder());
+
+        let titles = |state: &State| -> Vec<String> {
+            state
+                .playables()
+                .map(|p| p.get_title().to_string())
+                .collect()
+        };
+        // down, before the fifth row
+        state.move_rows(&[0, 1], 4).unwrap();
+        assert_eq!(
+            titles(&state),
+            ["Test3", "Test4", "Test1", "Test2", "Test5"]
+        );
+        // up, to the top
+        state.move_rows(&[2, 4], 0).unwrap();
+        assert_eq!(
+            titles(&state),
+            ["Test1", "Test5", "Test3", "Test4", "Test2"]
+        );
+        // past the last row
+        state.move_rows(&[0], 10).unwrap();
+        assert_eq!(
+            titles(&state),
+            ["Test5", "Test3", "Test4", "Test2", "Test1"]
+        );
+        // the order is kept in the playlist
+        state.set_section(Section::Library).unwrap();
+        state.set_section(Section::Playlist(playlist_id)).unwrap();
+        assert_eq!(
+            titles(&state),
+            ["Test5", "Test3", "Test4", "Test2", "Test1"]
+        );
     }
 
     #[test]
-    fn test_delete_tag() {
+    fn test_release_in_library() {
         let mut storage = Box::new(init_storage(":memory:").unwrap());
-        let _ = storage.create_tag("tag1");
-        let _ = storage.create_tag("tag2");
+        let _ = storage.append_to_library(&AudioFileDescriptor::from(scanned_file("Test1")));
+        let mut state = State::new(storage);
 
+        let release: Release = serde_json::from_str(
+            r#"{
+                "id": 1,
+                "title": "album_Test1",
+                "artists": [{"name": "Someone Else"}],
+                "tracklist": [
+                    {"position": "A", "title": "test1", "type_": "track"},
+                    {"position": "B", "title": "Test2", "type_": "track"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let found = state.release_in_library(&release).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].as_ref().unwrap().title, "Test1");
+        assert!(found[1].is_none());
+
+        assert_eq!(state.discogs_token(), None);
+        state.set_discogs_token(" abc ").unwrap();
+        assert_eq!(state.discogs_token(), Some("abc"));
+        state.set_discogs_token("").unwrap();
+        assert_eq!(state.discogs_token(), None);
+    }
+
+    #[test]
+    fn test_discogs_oauth() {
+        use crate::discogs::{Consumer, OAuthToken};
+
+        let storage = Box::new(init_storage(":memory:").unwrap());
         let mut state = State::new(storage);
-        let files = vec![
-            scanned_file("Test1"),
-            scanned_file("Test2"),
-            scanned_file("Test3"),
-            scanned_file("Test4"),
-            scanned_file("Test5"),
-            scanned_file("Test6"),
-        ];
-        let _ = state.append_bulk(files);
-        let _ = state.append_to_tag(1, 1);
-        let _ = state.append_to_tag(1, 2);
-        let _ = state.append_to_tag(1, 3);
-        let _ = state.append_to_tag(2, 4);
-        let _ = state.append_to_tag(2, 5);
-        let _ = state.append_to_tag(2, 6);
+        assert_eq!(state.discogs_credentials(), None);
+        state.set_discogs_token("abc").unwrap();
+        assert_eq!(
+            state.discogs_credentials(),
+            Some(Credentials::Token("abc".to_string()))
+        );
 
-        assert!(state.set_section(Section::Tag(1)).is_ok());
-        assert_eq!(state.playables().count(), 3);
+        let tokens = OAuthTokens {
+            consumer: Consumer {
+                key: "key".to_string(),
+                secret: "secret".to_string(),
+            },
+            access: OAuthToken {
+                token: "token".to_string(),
+                secret: "token_secret".to_string(),
+            },
+        };
+        state.set_discogs_oauth(Some(tokens.clone())).unwrap();
+        let mut state = State::new(state.storage);
+        assert!(state.discogs_connected());
+        assert_eq!(
+            state.discogs_credentials(),
+            Some(Credentials::OAuth(tokens))
+        );
 
-        assert!(state.delete_tag(1).is_ok());
-        assert_eq!(state.section(), &Section::Tag(2));
-        assert_eq!(state.playables().count(), 3);
+        state.set_discogs_oauth(None).unwrap();
+        let state = State::new(state.storage);
+        assert!(!state.discogs_connected());
+        assert_eq!(
+            state.discogs_credentials(),
+            Some(Credentials::Token("abc".to_string()))
+        );
 
-        assert!(state.delete_tag(2).is_ok());
-        assert_eq!(state.section(), &Section::Library);
-        assert_eq!(state.playables().count(), 6);
+        // kept out of the settings, where older versions left them to move on startup
+        assert_eq!(state.storage.read_setting(DISCOGS_TOKEN_KEY).unwrap(), None);
+        let mut storage = state.storage;
+        storage.write_setting(DISCOGS_TOKEN_KEY, "old").unwrap();
+        let state = State::new(storage);
+        assert_eq!(state.discogs_token(), Some("old"));
+        assert_eq!(
+            state.storage.read_setting(DISCOGS_TOKEN_KEY).unwrap(),
+            Some(String::new())
+        );
+    }
+
+    #[test]
+    fn test_watches() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let id = state
+            .add_watch(WatchKind::Artist, 45, "Aphex Twin")
+            .unwrap();
+        assert_eq!(state.due_watches(1000).len(), 1);
+
+        let release = |id: i64| WatchReleaseDescriptor {
+            release_id: id,
+            title: format!("Release {id}"),
+            artist: "Aphex Twin".to_string(),
+            year: 2001,
+        };
+        assert_eq!(state.save_watch_check(id, 1000, &[release(1)]).unwrap(), 0);
+        assert!(state.due_watches(1000).is_empty());
+        assert_eq!(state.due_watches(1000 + 24 * 3600).len(), 1);
+        state.set_watch_interval(id, 6).unwrap();
+        assert_eq!(state.due_watches(1000 + 6 * 3600).len(), 1);
+
+        let added = state
+            .save_watch_check(id, 2000, &[release(1), release(2)])
+            .unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(state.new_releases().len(), 1);
+        state.dismiss_new_releases(id).unwrap();
+        assert!(state.new_releases().is_empty());
+
+        state.remove_watch(id).unwrap();
+        assert!(state.watches().is_empty());
+    }
+
+    #[test]
+    fn test_song_info() {
+        let storage = Box::new(init_storage(":memory:").unwrap());
+        let mut state = State::new(storage);
+        let _ = state.append_bulk(vec![scanned_file("Test1"), scanned_file("Test2")]);
+        let _ = state.create_tag("tag1");
+        let _ = state.append_to_tag(1, 1);
+        let _ = state.create_playlist("playlist1", None);
+        let _ = state.storage.append_to_playlist(1, 1);
+
+        assert!(state.open_song_info(1).is_ok());
+        let info = state.song_info().unwrap();
+        assert_eq!(info.playable.get_title(), "Test1");
+        assert_eq!(info.tags.len(), 1);
+        assert_eq!(info.playlists[0].name, "playlist1");
+
+        state.close_song_info();
+        assert!(state.song_info().is_none());
+        // unknown playables close the panel
+        assert!(state.open_song_info(1).is_ok());
+        assert!(state.open_song_info(42).is_ok());
+        assert!(state.song_info().is_none());
     }
 }
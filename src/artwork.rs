@@ -0,0 +1,190 @@
+// Artwork looked up online for playables without any, on Discogs when it is connected and on
+// the Cover Art Archive otherwise, which the release ids found on MusicBrainz lead to.
+use std::time::Duration;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{
+    app_state::PlayableId,
+    discogs::{self, Credentials, DiscogsError},
+    storage::Playable,
+};
+
+const MUSICBRAINZ_URL: &str = "https://musicbrainz.org/ws/2";
+const COVER_ART_URL: &str = "https://coverartarchive.org";
+// MusicBrainz asks for a user agent with a way to reach whoever makes the application
+const USER_AGENT: &str = concat!(
+    "Phoniq/",
+    env!("CARGO_PKG_VERSION"),
+    " ( https://github.com/dejang/phonique )"
+);
+// releases found on MusicBrainz whose cover is asked for, best match first
+const MAX_RELEASES: usize = 3;
+/// Time between two lookups, MusicBrainz answers a client once a second.
+pub const LOOKUP_INTERVAL: Duration = Duration::from_millis(1100);
+
+#[derive(Debug, Error)]
+pub enum ArtworkError {
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Invalid response: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Discogs(#[from] DiscogsError),
+}
+
+pub type Result<T> = std::result::Result<T, ArtworkError>;
+
+/// The playables of one release, its artwork is looked up once for all of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lookup {
+    pub artist: String,
+    // the album, or the title of a track without one, which is taken for a single
+    pub release: String,
+    pub ids: Vec<PlayableId>,
+}
+
+/// The lookups of `playables` by artist and album, in the order they come in.
+pub fn lookups(playables: &[Playable]) -> Vec<Lookup> {
+    let mut lookups: Vec<Lookup> = Vec::new();
+    for playable in playables {
+        let artist = playable.artist_name.clone().unwrap_or_default();
+        let release = match playable.album_name.as_deref() {
+            Some(album) if !album.trim().is_empty() => album.to_string(),
+            _ => playable.title.clone(),
+        };
+        match lookups
+            .iter_mut()
+            .find(|lookup| lookup.artist == artist && lookup.release == release)
+        {
+            Some(lookup) => lookup.ids.push(playable.id),
+            None => lookups.push(Lookup {
+                artist,
+                release,
+                ids: vec![playable.id],
+            }),
+        }
+    }
+    lookups
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseSearch {
+    #[serde(default)]
+    releases: Vec<Release>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    id: String,
+}
+
+// a search of the releases titled `release` by `artist`, quoted to take both literally
+fn musicbrainz_query(artist: &str, release: &str) -> String {
+    let quoted = |value: &str| format!("\"{}\"", value.trim().replace('"', "\\\""));
+    let mut query = format!("release:{}", quoted(release));
+    if !artist.trim().is_empty() {
+        query.push_str(&format!(" AND artist:{}", quoted(artist)));
+    }
+    format!(
+        "{MUSICBRAINZ_URL}/release/?fmt=json&limit={MAX_RELEASES}&query={}",
+        urlencoding::encode(&query)
+    )
+}
+
+async fn download(client: &reqwest::Client, url: &str) -> Result<Option<Vec<u8>>> {
+    let response = client.get(url).send().await?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(response.error_for_status()?.bytes().await?.to_vec()))
+}
+
+// the front cover of the first release found on MusicBrainz which has one
+async fn cover_art_archive(client: &reqwest::Client, lookup: &Lookup) -> Result<Option<Vec<u8>>> {
+    let body = client
+        .get(musicbrainz_query(&lookup.artist, &lookup.release))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    let search: ReleaseSearch = serde_json::from_str(&body)?;
+    for release in search.releases {
+        let url = format!("{COVER_ART_URL}/release/{}/front-500", release.id);
+        if let Some(artwork) = download(client, &url).await? {
+            return Ok(Some(artwork));
+        }
+    }
+    Ok(None)
+}
+
+/// The artwork of the release looked up, None when none was found.
+pub async fn fetch(lookup: Lookup, credentials: Option<Credentials>) -> Result<Option<Vec<u8>>> {
+    let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
+    if let Some(credentials) = credentials
+        && let Some(cover) =
+            discogs::search_cover(&credentials, &lookup.artist, &lookup.release).await?
+        && let Some(artwork) = download(&client, &cover).await?
+    {
+        return Ok(Some(artwork));
+    }
+    cover_art_archive(&client, &lookup).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playable(id: PlayableId, title: &str, artist: &str, album: &str) -> Playable {
+        Playable {
+            id,
+            title: title.to_string(),
+            artist_name: Some(artist.to_string()),
+            album_name: Some(album.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_lookups() {
+        let lookups = lookups(&[
+            playable(1, "So What", "Miles Davis", "Kind of Blue"),
+            playable(2, "Windowlicker", "Aphex Twin", ""),
+            playable(3, "Blue in Green", "Miles Davis", "Kind of Blue"),
+            playable(4, "Kind of Blue", "Someone Else", "Kind of Blue"),
+        ]);
+        let found: Vec<(&str, &str, Vec<PlayableId>)> = lookups
+            .iter()
+            .map(|lookup| {
+                (
+                    lookup.artist.as_str(),
+                    lookup.release.as_str(),
+                    lookup.ids.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("Miles Davis", "Kind of Blue", vec![1, 3]),
+                ("Aphex Twin", "Windowlicker", vec![2]),
+                ("Someone Else", "Kind of Blue", vec![4]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_musicbrainz_query() {
+        assert_eq!(
+            musicbrainz_query(" Miles Davis ", "Kind of \"Blue\""),
+            "https://musicbrainz.org/ws/2/release/?fmt=json&limit=3&query=\
+             release%3A%22Kind%20of%20%5C%22Blue%5C%22%22%20AND%20artist%3A%22Miles%20Davis%22"
+        );
+        assert_eq!(
+            musicbrainz_query("", "Windowlicker"),
+            "https://musicbrainz.org/ws/2/release/?fmt=json&limit=3&query=release%3A%22Windowlicker%22"
+        );
+    }
+}
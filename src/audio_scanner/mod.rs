@@ -1,8 +1,20 @@
-use std::{error::Error, path::PathBuf};
+use std::{
+    error::Error,
+    io::Cursor,
+    path::{Path, PathBuf},
+};
 
-use lofty::{file::{AudioFile, TaggedFileExt}, tag::Accessor};
+use image::{ImageReader, codecs::jpeg::JpegEncoder, imageops::FilterType};
+use lofty::{file::{AudioFile, TaggedFile, TaggedFileExt}, tag::{Accessor, ItemKey, Tag}};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use walkdir::WalkDir;
+use walkdir::{DirEntry, WalkDir};
+
+mod priority;
+
+pub const DEFAULT_ARTWORK_MAX_SIZE: u32 = 600;
+const ARTWORK_JPEG_QUALITY: u8 = 85;
+// the extensions of the files scanned, lowercase
+pub const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "flac", "ogg", "wav", "m4a", "aac", "aiff"];
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ScannedKind {
@@ -11,6 +23,7 @@ pub enum ScannedKind {
     Dropbox = 2,
     Youtube = 3,
     Stream = 4,
+    WebDav = 5,
 }
 
 #[derive(Debug, Clone)]
@@ -21,12 +34,111 @@ pub struct ScannedFile {
     pub year: u16,
     pub genre: String,
     pub duration: u64,
+    // in kbps, 0 when unknown
+    pub bitrate: u32,
+    // in Hz, 0 when unknown
+    pub sample_rate: u32,
+    // None when the format is unknown
+    pub lossless: Option<bool>,
+    // YYYY-MM-DD, empty unless the tags have the full date
+    pub release_date: String,
+    // the place on the release, 0 when unknown
+    pub track_number: u32,
+    pub disc_number: u32,
+    // the record label, empty when unknown
+    pub label: String,
     pub path: String,
     pub artwork: Option<Vec<u8>>,
     pub kind: ScannedKind,
 }
 
-pub fn scan_file(path: &PathBuf) -> Result<ScannedFile, Box<dyn Error>> {
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScanOptions {
+    // globs matched against paths relative to the scanned folder, e.g. `**/samples/**`.
+    // Patterns without a `/` only look at the file name, e.g. `*.stem.mp4`
+    pub exclude: Vec<String>,
+    // files shorter than this are skipped, in seconds
+    pub min_duration: u64,
+    pub follow_symlinks: bool,
+    // dot files and everything inside dot directories
+    pub skip_hidden: bool,
+    // how many directories deep to look, files right in the scanned folder are at depth 1
+    pub max_depth: Option<usize>,
+    // files read at the same time, None for half of the cores
+    pub threads: Option<usize>,
+    // larger artwork is downscaled to fit and stored as JPEG, None keeps the original
+    pub artwork_max_size: Option<u32>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            exclude: Vec::new(),
+            min_duration: 0,
+            follow_symlinks: false,
+            skip_hidden: false,
+            max_depth: None,
+            threads: None,
+            artwork_max_size: Some(DEFAULT_ARTWORK_MAX_SIZE),
+        }
+    }
+}
+
+impl ScanOptions {
+    pub fn is_excluded(&self, relative_path: &str) -> bool {
+        let file_name = relative_path.rsplit('/').next().unwrap_or_default();
+        self.exclude.iter().any(|pattern| {
+            if pattern.contains('/') {
+                glob_match(pattern, relative_path)
+            } else {
+                glob_match(pattern, file_name)
+            }
+        })
+    }
+}
+
+// `**` matches any number of directories, `*` and `?` stay within a path segment
+fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_segments(rest, &path[skip..])),
+        Some((segment, rest)) => match path.split_first() {
+            Some((name, path_rest)) => {
+                let segment: Vec<char> = segment.chars().collect();
+                let name: Vec<char> = name.chars().collect();
+                match_segment(&segment, &name) && match_segments(rest, path_rest)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_segment(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| match_segment(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && match_segment(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && match_segment(rest, &name[1..]),
+    }
+}
+
+// `/` separated on every platform so that patterns work the same everywhere
+fn relative_path(folder: &Path, path: &Path) -> String {
+    path.strip_prefix(folder)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub fn scan_file(path: &PathBuf, options: &ScanOptions) -> Result<ScannedFile, Box<dyn Error>> {
     if !path.exists() {
         Err(std::io::Error::new(
             std::io::ErrorKind::NotFound,
@@ -36,24 +148,38 @@ pub fn scan_file(path: &PathBuf) -> Result<ScannedFile, Box<dyn Error>> {
 
     // Read metadata using lofty
     let tagged_file = lofty::read_from_path(path)?;
+    let path = path.to_string_lossy().to_string();
+    let name = path.split("/").last().unwrap_or_default().to_string();
+    Ok(scanned_file(&tagged_file, path, &name, options))
+}
 
+/// The metadata of a file read by lofty, `name` is the title when the tags have none.
+pub fn scanned_file(
+    tagged_file: &TaggedFile,
+    path: String,
+    name: &str,
+    options: &ScanOptions,
+) -> ScannedFile {
     let tag = tagged_file
         .primary_tag()
         .or_else(|| tagged_file.first_tag());
-    let duration = tagged_file.properties().duration();
+    let properties = tagged_file.properties();
+    let duration = properties.duration();
     let pictures = tag.as_ref().map(|t| t.pictures()).unwrap_or_default();
     let cover_art = pictures.first().map(|pic| {
         let mut data = Vec::new();
         std::io::Read::read_to_end(&mut pic.data(), &mut data).unwrap();
-        data
+        match options.artwork_max_size {
+            Some(max_size) => downscale_artwork(data, max_size),
+            None => data,
+        }
     });
 
-    let path = path.to_string_lossy().to_string();
-    let metadata = ScannedFile {
+    ScannedFile {
         title: tag
             .as_ref()
             .and_then(|t| t.title().map(|s| s.to_string()))
-            .unwrap_or_else(|| path.split("/").last().unwrap_or_default().to_string()),
+            .unwrap_or_else(|| name.to_string()),
         artist: tag
             .as_ref()
             .and_then(|t| t.artist().map(|s| s.to_string()))
@@ -71,36 +197,330 @@ pub fn scan_file(path: &PathBuf) -> Result<ScannedFile, Box<dyn Error>> {
             .and_then(|t| t.genre().map(|s| s.to_string()))
             .unwrap_or_else(String::new),
         duration: duration.as_secs() as u64,
+        bitrate: properties.audio_bitrate().unwrap_or(0),
+        sample_rate: properties.sample_rate().unwrap_or(0),
+        // only lossless formats, FLAC, WAV, AIFF, ALAC and the like, have a bit depth
+        lossless: Some(properties.bit_depth().is_some()),
+        release_date: tag.and_then(release_date).unwrap_or_default(),
+        track_number: tag.and_then(|t| t.track()).unwrap_or(0),
+        disc_number: tag.and_then(|t| t.disk()).unwrap_or(0),
+        label: tag
+            .and_then(|t| t.get_string(&ItemKey::Label))
+            .map(|label| label.trim().to_string())
+            .unwrap_or_default(),
         artwork: cover_art,
         path,
         kind: ScannedKind::LocalFile,
+    }
+}
+
+// the release date of the tags, the original one of a reissue first. Most tags put the
+// recording date where the release date would be, it is taken when nothing better is there
+fn release_date(tag: &Tag) -> Option<String> {
+    [
+        ItemKey::OriginalReleaseDate,
+        ItemKey::ReleaseDate,
+        ItemKey::RecordingDate,
+    ]
+    .iter()
+    .find_map(|key| tag.get_string(key).and_then(parse_release_date))
+}
+
+/// `YYYY-MM-DD` from a tag's date, which may go on with a time. None when the month or the
+/// day is missing or not a date.
+fn parse_release_date(value: &str) -> Option<String> {
+    let date = value.trim().get(..10)?;
+    let mut parts = date.split('-');
+    let (year, month, day) = (parts.next()?, parts.next()?, parts.next()?);
+    let valid = year.len() == 4
+        && year.parse::<u16>().is_ok()
+        && month.parse::<u8>().is_ok_and(|month| (1..=12).contains(&month))
+        && day.parse::<u8>().is_ok_and(|day| (1..=31).contains(&day));
+    valid.then(|| date.to_string())
+}
+
+/// Shrinks artwork so that neither side is larger than `max_size` and re-encodes it as JPEG.
+/// Artwork that already fits, or that cannot be decoded, is returned untouched.
+pub fn downscale_artwork(data: Vec<u8>, max_size: u32) -> Vec<u8> {
+    let reader = || ImageReader::new(Cursor::new(&data)).with_guessed_format();
+    // only the header is read here, most covers fit and never get decoded
+    let fits = match reader().map(|r| r.into_dimensions()) {
+        Ok(Ok((width, height))) => width <= max_size && height <= max_size,
+        _ => true,
     };
+    if fits {
+        return data;
+    }
+    let Ok(Ok(image)) = reader().map(|r| r.decode()) else {
+        return data;
+    };
+
+    // JPEG has no alpha channel
+    let resized = image
+        .resize(max_size, max_size, FilterType::CatmullRom)
+        .into_rgb8();
+    let mut output = Vec::new();
+    let encoder = JpegEncoder::new_with_quality(&mut output, ARTWORK_JPEG_QUALITY);
+    match resized.write_with_encoder(encoder) {
+        Ok(()) => output,
+        Err(_) => data,
+    }
+}
+
+fn is_hidden(entry: &DirEntry) -> bool {
+    entry
+        .file_name()
+        .to_str()
+        .map(|name| name.starts_with('.'))
+        .unwrap_or(false)
+}
+
+pub fn default_scan_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get() / 2)
+        .unwrap_or(1)
+        .max(1)
+}
 
-    Ok(metadata)
+// a pool of its own so that scans neither use every core nor compete with the audio thread
+fn scan_pool(options: &ScanOptions) -> Option<rayon::ThreadPool> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(options.threads.unwrap_or_else(default_scan_threads).max(1))
+        .thread_name(|index| format!("phonique-scan-{index}"))
+        .start_handler(|_| priority::lower_thread_priority())
+        .build()
+        .inspect_err(|e| log::error!("scan_pool: falling back to the global pool\n{e:?}"))
+        .ok()
 }
 
-pub fn scan_folder(folder: &PathBuf) -> Vec<ScannedFile> {
-    // List of supported audio file extensions
-    let supported_exts = ["mp3", "flac", "ogg", "wav", "m4a", "aac", "aiff"];
+pub fn scan_folder(folder: &PathBuf, options: &ScanOptions) -> Vec<ScannedFile> {
+    let files = collect_files(folder, options);
+
+    // Process files in parallel
+    let scan = || {
+        files
+            .par_iter()
+            .filter_map(|path| scan_file(path, options).ok())
+            .filter(|file| file.duration >= options.min_duration)
+            .collect()
+    };
+    match scan_pool(options) {
+        Some(pool) => pool.install(scan),
+        None => scan(),
+    }
+}
+
+pub fn collect_files(folder: &Path, options: &ScanOptions) -> Vec<PathBuf> {
+    let mut walker = WalkDir::new(folder).follow_links(options.follow_symlinks);
+    if let Some(depth) = options.max_depth {
+        walker = walker.max_depth(depth);
+    }
 
     // Collect all file paths with supported extensions
-    let files: Vec<PathBuf> = WalkDir::new(folder)
+    walker
         .into_iter()
+        // the scanned folder itself is never skipped, even when it is hidden
+        .filter_entry(|e| !(options.skip_hidden && e.depth() > 0 && is_hidden(e)))
+        // unreadable entries and symlink loops
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_file())
         .filter(|e| {
             e.path()
                 .extension()
                 .and_then(|ext| ext.to_str())
-                .map(|ext| supported_exts.contains(&ext.to_lowercase().as_str()))
+                .map(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
                 .unwrap_or(false)
         })
+        .filter(|e| !options.is_excluded(&relative_path(folder, e.path())))
         .map(|e| e.path().to_path_buf())
-        .collect();
-
-    // Process files in parallel
-    files
-        .par_iter()
-        .filter_map(|path| scan_file(path).ok())
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(exclude: &[&str]) -> ScanOptions {
+        ScanOptions {
+            exclude: exclude.iter().map(|p| p.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    // a throwaway directory tree, removed when dropped
+    struct Fixture(PathBuf);
+
+    impl Fixture {
+        fn new(name: &str, files: &[&str]) -> Self {
+            let root =
+                std::env::temp_dir().join(format!("phonique-scan-{name}-{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            for file in files {
+                let path = root.join(file);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, b"").unwrap();
+            }
+            Fixture(root)
+        }
+
+        fn collect(&self, options: &ScanOptions) -> Vec<String> {
+            let mut files: Vec<String> = collect_files(&self.0, options)
+                .iter()
+                .map(|p| relative_path(&self.0, p))
+                .collect();
+            files.sort();
+            files
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    static TREE: &[&str] = &[
+        "a.mp3",
+        "notes.txt",
+        ".hidden.mp3",
+        ".cache/b.mp3",
+        "sub/c.flac",
+        "sub/deep/d.wav",
+    ];
+
+    #[test]
+    fn test_collect_files_defaults() {
+        let fixture = Fixture::new("defaults", TREE);
+        assert_eq!(
+            fixture.collect(&ScanOptions::default()),
+            vec![
+                ".cache/b.mp3",
+                ".hidden.mp3",
+                "a.mp3",
+                "sub/c.flac",
+                "sub/deep/d.wav"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_files_skip_hidden() {
+        let fixture = Fixture::new("hidden", TREE);
+        let options = ScanOptions {
+            skip_hidden: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            fixture.collect(&options),
+            vec!["a.mp3", "sub/c.flac", "sub/deep/d.wav"]
+        );
+    }
+
+    #[test]
+    fn test_collect_files_max_depth() {
+        let fixture = Fixture::new("depth", TREE);
+        let options = ScanOptions {
+            max_depth: Some(2),
+            skip_hidden: true,
+            ..Default::default()
+        };
+        assert_eq!(fixture.collect(&options), vec!["a.mp3", "sub/c.flac"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_files_symlinks() {
+        let fixture = Fixture::new("symlinks", &["music/a.mp3"]);
+        let outside = Fixture::new("symlinks-target", &["linked/b.mp3"]);
+        let music = fixture.0.join("music");
+        std::os::unix::fs::symlink(outside.0.join("linked"), music.join("linked")).unwrap();
+        // a loop back to the scanned folder must not hang the scan
+        std::os::unix::fs::symlink(&music, music.join("loop")).unwrap();
+
+        let files = |follow_symlinks| {
+            let options = ScanOptions {
+                follow_symlinks,
+                ..Default::default()
+            };
+            let mut files: Vec<String> = collect_files(&music, &options)
+                .iter()
+                .map(|p| relative_path(&music, p))
+                .collect();
+            files.sort();
+            files
+        };
+        assert_eq!(files(false), vec!["a.mp3"]);
+        assert_eq!(files(true), vec!["a.mp3", "linked/b.mp3"]);
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("**/samples/**", "samples/kick.wav"));
+        assert!(glob_match(
+            "**/samples/**",
+            "projects/track/samples/drums/kick.wav"
+        ));
+        assert!(!glob_match("**/samples/**", "projects/samples.wav"));
+        assert!(glob_match("*.stem.mp4", "Song.stem.mp4"));
+        assert!(!glob_match("*.stem.mp4", "Song.mp4"));
+        assert!(glob_match("live/*/set?.mp3", "live/2023/set1.mp3"));
+        assert!(!glob_match("live/*/set?.mp3", "live/2023/extra/set1.mp3"));
+    }
+
+    #[test]
+    fn test_is_excluded() {
+        let options = options(&["**/samples/**", "*.stem.mp4"]);
+        assert!(options.is_excluded("production/samples/hat.wav"));
+        assert!(options.is_excluded("mixes/deep/Intro.stem.mp4"));
+        assert!(!options.is_excluded("mixes/deep/Intro.mp3"));
+        assert!(!ScanOptions::default().is_excluded("samples/hat.wav"));
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let folder = Path::new("/music");
+        assert_eq!(
+            relative_path(folder, Path::new("/music/a/b.mp3")),
+            "a/b.mp3"
+        );
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut data = Vec::new();
+        image::RgbaImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut data), image::ImageFormat::Png)
+            .unwrap();
+        data
+    }
+
+    #[test]
+    fn test_downscale_artwork() {
+        let artwork = downscale_artwork(png(1200, 800), 600);
+        let image = image::load_from_memory(&artwork).unwrap();
+        assert_eq!((image.width(), image.height()), (600, 400));
+        assert_eq!(
+            image::guess_format(&artwork).unwrap(),
+            image::ImageFormat::Jpeg
+        );
+
+        let small = png(300, 300);
+        assert_eq!(downscale_artwork(small.clone(), 600), small);
+        assert_eq!(downscale_artwork(vec![1, 2, 3], 600), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_parse_release_date() {
+        assert_eq!(
+            parse_release_date("1997-06-16"),
+            Some("1997-06-16".to_string())
+        );
+        assert_eq!(
+            parse_release_date(" 1997-06-16T00:00:00 "),
+            Some("1997-06-16".to_string())
+        );
+        assert_eq!(parse_release_date("1997"), None);
+        assert_eq!(parse_release_date("1997-06"), None);
+        assert_eq!(parse_release_date("1997-13-01"), None);
+        assert_eq!(parse_release_date("16/06/1997"), None);
+    }
+}
@@ -0,0 +1,45 @@
+//! Background priority for scanner threads: they get CPU and disk time only when playback
+//! does not need it. Failures are ignored, the scan then runs at normal priority.
+
+#[cfg(target_os = "linux")]
+pub fn lower_thread_priority() {
+    // see ioprio_set(2)
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_IDLE: libc::c_int = 3;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+    // SAFETY: plain syscalls on the calling thread, Linux treats `who = 0` as the current
+    // thread rather than the whole process
+    unsafe {
+        libc::setpriority(libc::PRIO_PROCESS, 0, 19);
+        libc::syscall(
+            libc::SYS_ioprio_set,
+            IOPRIO_WHO_PROCESS,
+            0,
+            IOPRIO_CLASS_IDLE << IOPRIO_CLASS_SHIFT,
+        );
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn lower_thread_priority() {
+    // SAFETY: only changes the scheduling class of the calling thread,
+    // PRIO_DARWIN_BG throttles both CPU and IO
+    unsafe {
+        libc::setpriority(libc::PRIO_DARWIN_THREAD, 0, libc::PRIO_DARWIN_BG);
+    }
+}
+
+#[cfg(windows)]
+pub fn lower_thread_priority() {
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentThread, SetThreadPriority, THREAD_MODE_BACKGROUND_BEGIN,
+    };
+    // SAFETY: GetCurrentThread returns a pseudo handle which is always valid
+    unsafe {
+        SetThreadPriority(GetCurrentThread(), THREAD_MODE_BACKGROUND_BEGIN);
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn lower_thread_priority() {}
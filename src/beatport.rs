@@ -0,0 +1,360 @@
+// The Top 100 charts of the Beatport store. Beatport has no public API for them, the store pages
+// carry their content as JSON for the web app to start from, the genres in the menu of each page
+// and the tracks of a chart along with a short sample of each.
+use log::warn;
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::storage::{AudioFileKind, Playable};
+
+const STORE_URL: &str = "https://www.beatport.com";
+const USER_AGENT: &str = concat!("Phoniq/", env!("CARGO_PKG_VERSION"));
+// the script element holding the page's data
+const DATA_START: &str = "<script id=\"__NEXT_DATA__\" type=\"application/json\">";
+
+#[derive(Debug, Error)]
+pub enum BeatportError {
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Invalid response: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("No chart found on the page")]
+    NoChart,
+    #[error("No genres found on the page")]
+    NoGenres,
+}
+
+pub type Result<T> = std::result::Result<T, BeatportError>;
+
+/// A genre with a Top 100 chart, the slug and the id make up its address in the store.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Genre {
+    pub name: String,
+    slug: String,
+    id: u64,
+}
+
+impl std::fmt::Display for Genre {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChartTrack {
+    // 1-based
+    pub position: usize,
+    pub id: u64,
+    pub slug: String,
+    pub title: String,
+    // `Original Mix`, `Extended Mix` or the remixer's
+    pub mix: String,
+    pub artists: String,
+    pub label: String,
+    pub genre: String,
+    // 0 when unknown
+    pub bpm: u32,
+    pub key: String,
+    // in seconds
+    pub duration: i64,
+    // YYYY-MM-DD
+    pub release_date: Option<String>,
+    // a short excerpt, which is all of the track the store plays without buying it
+    pub sample_url: Option<String>,
+}
+
+impl ChartTrack {
+    /// The title with the mix, as the store shows it.
+    pub fn name(&self) -> String {
+        if self.mix.is_empty() {
+            self.title.clone()
+        } else {
+            format!("{} ({})", self.title, self.mix)
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!("{STORE_URL}/track/{}/{}", self.slug, self.id)
+    }
+
+    /// The sample as a stream outside the library, None when the store has none.
+    pub fn to_playable(&self) -> Option<Playable> {
+        Some(Playable {
+            id: -1,
+            title: self.name(),
+            artist_name: Some(self.artists.clone()),
+            album_name: None,
+            genre_name: Some(self.genre.clone()).filter(|genre| !genre.is_empty()),
+            duration: self.duration,
+            source_url: self.sample_url.clone()?,
+            type_id: AudioFileKind::Stream,
+            date_added: 0,
+            artwork: None,
+            play_count: 0,
+            last_played_at: None,
+            rating: 0,
+            year: self
+                .release_date
+                .as_deref()
+                .and_then(|date| date.get(..4)?.parse().ok()),
+            release_date: self.release_date.clone(),
+            label: Some(self.label.clone()).filter(|label| !label.is_empty()),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct Named {
+    #[serde(default)]
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct Release {
+    label: Option<Named>,
+}
+
+#[derive(Deserialize)]
+struct Track {
+    id: u64,
+    name: String,
+    #[serde(default)]
+    slug: String,
+    #[serde(default)]
+    mix_name: String,
+    #[serde(default)]
+    artists: Vec<Named>,
+    release: Option<Release>,
+    genre: Option<Named>,
+    bpm: Option<u32>,
+    key: Option<Named>,
+    length_ms: Option<u64>,
+    publish_date: Option<String>,
+    sample_url: Option<String>,
+}
+
+impl Track {
+    fn into_chart_track(self, position: usize) -> ChartTrack {
+        ChartTrack {
+            position,
+            id: self.id,
+            slug: self.slug,
+            title: self.name,
+            mix: self.mix_name,
+            artists: self
+                .artists
+                .into_iter()
+                .map(|artist| artist.name)
+                .collect::<Vec<_>>()
+                .join(", "),
+            label: self
+                .release
+                .and_then(|release| release.label)
+                .map(|label| label.name)
+                .unwrap_or_default(),
+            genre: self.genre.map(|genre| genre.name).unwrap_or_default(),
+            bpm: self.bpm.unwrap_or(0),
+            key: self.key.map(|key| key.name).unwrap_or_default(),
+            duration: self.length_ms.map_or(0, |ms| (ms / 1000) as i64),
+            release_date: self.publish_date.filter(|date| !date.is_empty()),
+            sample_url: self.sample_url.filter(|url| url.starts_with("http")),
+        }
+    }
+}
+
+/// Reads the store pages, cheap to clone as the connections are shared between the clones.
+#[derive(Debug, Clone)]
+pub struct BeatportClient {
+    http: reqwest::Client,
+}
+
+impl Default for BeatportClient {
+    fn default() -> Self {
+        let http = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .build()
+            .unwrap_or_else(|e| {
+                warn!("Beatport: no client with the user agent: {e}");
+                reqwest::Client::new()
+            });
+        Self { http }
+    }
+}
+
+impl BeatportClient {
+    /// The genres with a chart, by name.
+    pub async fn genres(self) -> Result<Vec<Genre>> {
+        parse_genres(&self.page(STORE_URL.to_string()).await?)
+    }
+
+    pub async fn top_100(self, genre: Genre) -> Result<Vec<ChartTrack>> {
+        let url = format!("{STORE_URL}/genre/{}/{}/top-100", genre.slug, genre.id);
+        parse_chart(&self.page(url).await?)
+    }
+
+    async fn page(&self, url: String) -> Result<String> {
+        Ok(self
+            .http
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?)
+    }
+}
+
+// the JSON the web app starts from, None when the page has none
+fn page_data(html: &str) -> Option<Result<Value>> {
+    let start = html.find(DATA_START)? + DATA_START.len();
+    let end = html[start..].find("</script>")?;
+    Some(serde_json::from_str(&html[start..start + end]).map_err(BeatportError::from))
+}
+
+/// The genres in the menu of a store page, sorted by name.
+pub fn parse_genres(html: &str) -> Result<Vec<Genre>> {
+    let data = page_data(html).ok_or(BeatportError::NoGenres)??;
+    let list = find_list(&data, &|item| {
+        item.get("url")
+            .and_then(Value::as_str)
+            .is_some_and(|url| url.contains("/genres/"))
+    })
+    .ok_or(BeatportError::NoGenres)?;
+    let mut genres: Vec<Genre> = serde_json::from_value(list.clone())?;
+    genres.sort_by(|a, b| a.name.cmp(&b.name));
+    genres.dedup_by_key(|genre| genre.id);
+    Ok(genres)
+}
+
+/// The tracks of a chart page in the order of the chart.
+pub fn parse_chart(html: &str) -> Result<Vec<ChartTrack>> {
+    let data = page_data(html).ok_or(BeatportError::NoChart)??;
+    let results =
+        find_list(&data, &|item| item.get("mix_name").is_some()).ok_or(BeatportError::NoChart)?;
+    let tracks: Vec<Track> = serde_json::from_value(results.clone())?;
+    Ok(tracks
+        .into_iter()
+        .enumerate()
+        .map(|(index, track)| track.into_chart_track(index + 1))
+        .collect())
+}
+
+// the first list in the page's data whose first item is of the kind wanted, wherever the web app
+// keeps it
+fn find_list<'a>(value: &'a Value, is_wanted: &impl Fn(&Value) -> bool) -> Option<&'a Value> {
+    match value {
+        Value::Object(object) => object
+            .values()
+            .find_map(|value| find_list(value, is_wanted)),
+        Value::Array(items) if items.first().is_some_and(is_wanted) => Some(value),
+        Value::Array(items) => items.iter().find_map(|value| find_list(value, is_wanted)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(data: &str) -> String {
+        format!("<html><head></head><body>{DATA_START}{data}</script></body></html>")
+    }
+
+    #[test]
+    fn test_parse_chart() {
+        let html = page(
+            r#"{"props": {"pageProps": {"dehydratedState": {"queries": [
+                {"state": {"data": {"results": [{"id": 5, "name": "Menu"}]}}},
+                {"state": {"data": {"results": [
+                    {
+                        "id": 101,
+                        "name": "Night Drive",
+                        "slug": "night-drive",
+                        "mix_name": "Extended Mix",
+                        "artists": [{"name": "Alpha"}, {"name": "Omega"}],
+                        "release": {"label": {"name": "Warp"}},
+                        "genre": {"name": "Techno (Peak Time / Driving)"},
+                        "bpm": 132,
+                        "key": {"name": "A Minor"},
+                        "length_ms": 391000,
+                        "publish_date": "2026-09-25",
+                        "sample_url": "https://samples.example.com/101.mp3"
+                    },
+                    {"id": 102, "name": "Second", "mix_name": "", "sample_url": ""}
+                ]}}}
+            ]}}}}"#,
+        );
+        let tracks = parse_chart(&html).unwrap();
+        assert_eq!(tracks.len(), 2);
+        let first = &tracks[0];
+        assert_eq!(first.position, 1);
+        assert_eq!(first.name(), "Night Drive (Extended Mix)");
+        assert_eq!(first.artists, "Alpha, Omega");
+        assert_eq!(first.label, "Warp");
+        assert_eq!(first.bpm, 132);
+        assert_eq!(first.key, "A Minor");
+        assert_eq!(first.duration, 391);
+        assert_eq!(
+            first.url(),
+            "https://www.beatport.com/track/night-drive/101"
+        );
+        assert_eq!(tracks[1].position, 2);
+        assert_eq!(tracks[1].name(), "Second");
+        assert_eq!(tracks[1].sample_url, None);
+    }
+
+    #[test]
+    fn test_parse_chart_without_data() {
+        assert!(matches!(
+            parse_chart("<html></html>"),
+            Err(BeatportError::NoChart)
+        ));
+        assert!(matches!(
+            parse_chart(&page(r#"{"props": {"pageProps": {}}}"#)),
+            Err(BeatportError::NoChart)
+        ));
+    }
+
+    #[test]
+    fn test_parse_genres() {
+        let html = page(
+            r#"{"props": {"pageProps": {"menu": {"genres": [
+                {"id": 5, "name": "House", "slug": "house",
+                 "url": "https://api.beatport.com/v4/catalog/genres/5/"},
+                {"id": 89, "name": "Afro House", "slug": "afro-house",
+                 "url": "https://api.beatport.com/v4/catalog/genres/89/"}
+            ]}}}}"#,
+        );
+        let genres = parse_genres(&html).unwrap();
+        assert_eq!(genres.len(), 2);
+        assert_eq!(genres[0].to_string(), "Afro House");
+        assert_eq!((genres[0].slug.as_str(), genres[0].id), ("afro-house", 89));
+        assert_eq!(genres[1].name, "House");
+        assert!(matches!(
+            parse_genres(&page(r#"{"props": {}}"#)),
+            Err(BeatportError::NoGenres)
+        ));
+    }
+
+    #[test]
+    fn test_to_playable() {
+        let html = page(
+            r#"{"results": [{
+                "id": 7, "name": "Dawn", "mix_name": "Original Mix",
+                "artists": [{"name": "Alpha"}], "length_ms": 300500,
+                "publish_date": "2026-01-02", "sample_url": "https://samples.example.com/7.mp3"
+            }, {"id": 8, "name": "Dusk", "mix_name": ""}]}"#,
+        );
+        let tracks = parse_chart(&html).unwrap();
+        let playable = tracks[0].to_playable().unwrap();
+        assert_eq!(playable.id, -1);
+        assert_eq!(playable.type_id, AudioFileKind::Stream);
+        assert_eq!(playable.title, "Dawn (Original Mix)");
+        assert_eq!(playable.source_url, "https://samples.example.com/7.mp3");
+        assert_eq!(playable.duration, 300);
+        assert_eq!(playable.year, Some(2026));
+        assert_eq!(playable.label, None);
+        assert_eq!(tracks[1].to_playable(), None);
+    }
+}
@@ -0,0 +1,800 @@
+// Casting to UPnP/DLNA media renderers. Renderers are found with SSDP, local files are served to
+// them over a small HTTP server and playback is driven with AVTransport SOAP calls.
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    net::{IpAddr, TcpStream, UdpSocket},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, RecvTimeoutError},
+    },
+    time::{Duration, Instant},
+};
+
+use log::{debug, warn};
+use roxmltree::Document;
+use thiserror::Error;
+
+use crate::{
+    app_state::{AudioPlayable, PlayableKind},
+    listener::Listener,
+};
+
+const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+const MEDIA_RENDERER: &str = "urn:schemas-upnp-org:device:MediaRenderer:1";
+const AV_TRANSPORT: &str = "urn:schemas-upnp-org:service:AVTransport:1";
+const RENDERING_CONTROL: &str = "urn:schemas-upnp-org:service:RenderingControl:1";
+// how often a playing renderer is asked for its position
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+// a renderer which does not answer in time is taken as gone
+const SOAP_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Error)]
+pub enum CastError {
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Invalid renderer response: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("Invalid URL: {0}")]
+    Url(String),
+    #[error("Renderer has no {0} service")]
+    MissingService(&'static str),
+    #[error("Renderer refused {0}")]
+    Refused(String),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, CastError>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Renderer {
+    pub name: String,
+    // address of the device description, also identifies the renderer
+    pub location: String,
+    av_transport: String,
+    rendering_control: Option<String>,
+}
+
+/// Searches the local network for media renderers, waits `timeout` for answers.
+/// Renderers whose description can not be read are left out.
+pub fn discover(timeout: Duration) -> Result<Vec<Renderer>> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.set_read_timeout(Some(Duration::from_millis(200)))?;
+    let search = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {SSDP_ADDRESS}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {MEDIA_RENDERER}\r\n\r\n"
+    );
+    socket.send_to(search.as_bytes(), SSDP_ADDRESS)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut locations = Vec::new();
+    let mut buffer = [0u8; 2048];
+    while Instant::now() < deadline {
+        match socket.recv_from(&mut buffer) {
+            Ok((len, _)) => {
+                let response = String::from_utf8_lossy(&buffer[..len]);
+                if let Some(location) = header(&response, "location")
+                    && !locations.contains(&location)
+                {
+                    locations.push(location);
+                }
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(locations
+        .iter()
+        .filter_map(|location| match fetch_renderer(location) {
+            Ok(renderer) => Some(renderer),
+            Err(e) => {
+                warn!("discover: skipping renderer at {location}: {e}");
+                None
+            }
+        })
+        .collect())
+}
+
+fn fetch_renderer(location: &str) -> Result<Renderer> {
+    let body = reqwest::blocking::get(location)?
+        .error_for_status()?
+        .text()?;
+    parse_description(location, &body)
+}
+
+fn parse_description(location: &str, xml: &str) -> Result<Renderer> {
+    let document = Document::parse(xml)?;
+    let text_of = |name: &str| {
+        document
+            .descendants()
+            .find(|n| n.has_tag_name(name))
+            .and_then(|n| n.text())
+            .map(|t| t.trim().to_string())
+    };
+    // control URLs are relative to URLBase, or to the description when there is none
+    let base = text_of("URLBase").unwrap_or_else(|| location.to_string());
+    let base = reqwest::Url::parse(&base).map_err(|e| CastError::Url(e.to_string()))?;
+
+    let mut av_transport = None;
+    let mut rendering_control = None;
+    for service in document.descendants().filter(|n| n.has_tag_name("service")) {
+        let child_text = |name: &str| {
+            service
+                .children()
+                .find(|n| n.has_tag_name(name))
+                .and_then(|n| n.text())
+                .map(str::trim)
+        };
+        let (Some(service_type), Some(control_url)) =
+            (child_text("serviceType"), child_text("controlURL"))
+        else {
+            continue;
+        };
+        let control_url = base
+            .join(control_url)
+            .map_err(|e| CastError::Url(e.to_string()))?
+            .to_string();
+        match service_type {
+            AV_TRANSPORT => av_transport = Some(control_url),
+            RENDERING_CONTROL => rendering_control = Some(control_url),
+            _ => {}
+        }
+    }
+
+    Ok(Renderer {
+        name: text_of("friendlyName").unwrap_or_else(|| location.to_string()),
+        location: location.to_string(),
+        av_transport: av_transport.ok_or(CastError::MissingService("AVTransport"))?,
+        rendering_control,
+    })
+}
+
+impl Renderer {
+    pub fn load(&self, url: &str, title: &str, mime_type: &str) -> Result<()> {
+        let metadata = format!(
+            "<DIDL-Lite xmlns=\"urn:schemas-upnp-org:metadata-1-0/DIDL-Lite/\" \
+             xmlns:dc=\"http://purl.org/dc/elements/1.1/\" \
+             xmlns:upnp=\"urn:schemas-upnp-org:metadata-1-0/upnp/\">\
+             <item id=\"0\" parentID=\"-1\" restricted=\"1\">\
+             <dc:title>{}</dc:title>\
+             <upnp:class>object.item.audioItem.musicTrack</upnp:class>\
+             <res protocolInfo=\"http-get:*:{mime_type}:*\">{}</res>\
+             </item></DIDL-Lite>",
+            xml_escape(title),
+            xml_escape(url)
+        );
+        self.transport(
+            "SetAVTransportURI",
+            &[("CurrentURI", url), ("CurrentURIMetaData", &metadata)],
+        )
+        .map(|_| ())
+    }
+
+    pub fn play(&self) -> Result<()> {
+        self.transport("Play", &[("Speed", "1")]).map(|_| ())
+    }
+
+    pub fn pause(&self) -> Result<()> {
+        self.transport("Pause", &[]).map(|_| ())
+    }
+
+    pub fn stop(&self) -> Result<()> {
+        self.transport("Stop", &[]).map(|_| ())
+    }
+
+    pub fn seek(&self, position: u64) -> Result<()> {
+        self.transport(
+            "Seek",
+            &[("Unit", "REL_TIME"), ("Target", &format_time(position))],
+        )
+        .map(|_| ())
+    }
+
+    /// Sets the volume from 0 to 100, renderers without volume control are left alone.
+    pub fn set_volume(&self, volume: u8) -> Result<()> {
+        let Some(control_url) = &self.rendering_control else {
+            return Ok(());
+        };
+        soap(
+            control_url,
+            RENDERING_CONTROL,
+            "SetVolume",
+            &[
+                ("InstanceID", "0"),
+                ("Channel", "Master"),
+                ("DesiredVolume", &volume.to_string()),
+            ],
+        )
+        .map(|_| ())
+    }
+
+    /// The transport state, e.g. `PLAYING` or `STOPPED`.
+    pub fn transport_state(&self) -> Result<String> {
+        let response = self.transport("GetTransportInfo", &[])?;
+        Ok(response_value(&response, "CurrentTransportState")?.unwrap_or_default())
+    }
+
+    /// Position and duration of the current track in seconds.
+    pub fn position(&self) -> Result<(u64, u64)> {
+        let response = self.transport("GetPositionInfo", &[])?;
+        let time = |name: &str| -> Result<u64> {
+            Ok(response_value(&response, name)?
+                .and_then(|value| parse_time(&value))
+                .unwrap_or(0))
+        };
+        Ok((time("RelTime")?, time("TrackDuration")?))
+    }
+
+    fn transport(&self, action: &str, arguments: &[(&str, &str)]) -> Result<String> {
+        let mut all_arguments = vec![("InstanceID", "0")];
+        all_arguments.extend_from_slice(arguments);
+        soap(&self.av_transport, AV_TRANSPORT, action, &all_arguments)
+    }
+}
+
+fn soap(
+    control_url: &str,
+    service: &str,
+    action: &str,
+    arguments: &[(&str, &str)],
+) -> Result<String> {
+    let arguments: String = arguments
+        .iter()
+        .map(|(name, value)| format!("<{name}>{}</{name}>", xml_escape(value)))
+        .collect();
+    let body = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\
+         <s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\
+         <s:Body><u:{action} xmlns:u=\"{service}\">{arguments}</u:{action}></s:Body>\
+         </s:Envelope>"
+    );
+    let response = reqwest::blocking::Client::builder()
+        .timeout(SOAP_TIMEOUT)
+        .build()?
+        .post(control_url)
+        .header("Content-Type", "text/xml; charset=\"utf-8\"")
+        .header("SOAPAction", format!("\"{service}#{action}\""))
+        .body(body)
+        .send()?;
+    if !response.status().is_success() {
+        return Err(CastError::Refused(format!(
+            "{action} ({})",
+            response.status()
+        )));
+    }
+    Ok(response.text()?)
+}
+
+fn response_value(response: &str, name: &str) -> Result<Option<String>> {
+    let document = Document::parse(response)?;
+    Ok(document
+        .descendants()
+        .find(|n| n.has_tag_name(name))
+        .and_then(|n| n.text())
+        .map(str::to_string))
+}
+
+/// What a cast session reports back, from the thread it runs on.
+#[derive(Debug)]
+pub enum CastEvent {
+    // position and duration of the playing track in seconds
+    Progress(u64, u64),
+    Ended,
+    // the session stops after a failure
+    Failed(CastError),
+}
+
+enum Command {
+    Play(Arc<dyn AudioPlayable>, u64),
+    Cue(Arc<dyn AudioPlayable>, u64),
+    Pause,
+    Resume,
+    Seek(u64),
+    Volume(f32),
+}
+
+/// Playback on a renderer, the audio worker drives it in place of the local sink. The calls to
+/// the renderer wait on the network, they run on a thread of the session so that the worker goes
+/// on meanwhile. Dropping the session stops the renderer and then the thread.
+pub struct CastSession {
+    commands: mpsc::Sender<Command>,
+    // the last known position in seconds
+    position: Arc<AtomicU64>,
+    failed: Arc<AtomicBool>,
+}
+
+impl CastSession {
+    pub fn start(
+        renderer: Renderer,
+        volume: f32,
+        mut events: impl FnMut(CastEvent) + Send + 'static,
+    ) -> Result<Self> {
+        let (commands, received) = mpsc::channel();
+        let position = Arc::new(AtomicU64::new(0));
+        let failed = Arc::new(AtomicBool::new(false));
+        let (known_position, failing) = (position.clone(), failed.clone());
+        std::thread::Builder::new()
+            .name("cast".to_string())
+            .spawn(move || {
+                if let Err(e) = run(renderer, volume, &received, &known_position, &mut events) {
+                    failing.store(true, Ordering::Release);
+                    events(CastEvent::Failed(e));
+                }
+            })?;
+        Ok(Self {
+            commands,
+            position,
+            failed,
+        })
+    }
+
+    pub fn play(&self, playable: Arc<dyn AudioPlayable>, position: u64) {
+        self.position.store(position, Ordering::Relaxed);
+        self.send(Command::Play(playable, position));
+    }
+
+    /// Remembers the playable to start on the next resume.
+    pub fn cue(&self, playable: Arc<dyn AudioPlayable>, position: u64) {
+        self.position.store(position, Ordering::Relaxed);
+        self.send(Command::Cue(playable, position));
+    }
+
+    pub fn pause(&self) {
+        self.send(Command::Pause);
+    }
+
+    pub fn resume(&self) {
+        self.send(Command::Resume);
+    }
+
+    pub fn seek(&self, position: u64) {
+        self.position.store(position, Ordering::Relaxed);
+        self.send(Command::Seek(position));
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.send(Command::Volume(volume));
+    }
+
+    /// The last known position in seconds.
+    pub fn position(&self) -> u64 {
+        self.position.load(Ordering::Relaxed)
+    }
+
+    /// True once the session stopped on a failure, it plays nothing anymore.
+    pub fn has_failed(&self) -> bool {
+        self.failed.load(Ordering::Acquire)
+    }
+
+    // a session which failed has no thread left to receive it, the failure was reported
+    fn send(&self, command: Command) {
+        let _ = self.commands.send(command);
+    }
+}
+
+// Runs the session until its handle is dropped, which stops the renderer.
+fn run(
+    renderer: Renderer,
+    volume: f32,
+    commands: &mpsc::Receiver<Command>,
+    position: &AtomicU64,
+    events: &mut impl FnMut(CastEvent),
+) -> Result<()> {
+    let mut session = Session::start(renderer)?;
+    session.set_volume(volume)?;
+    let mut playing = false;
+    loop {
+        match commands.recv_timeout(POLL_INTERVAL) {
+            Ok(Command::Play(playable, at)) => {
+                session.play(playable.as_ref(), at)?;
+                playing = true;
+            }
+            Ok(Command::Cue(playable, at)) => session.cue(playable, at),
+            Ok(Command::Pause) => {
+                session.pause()?;
+                playing = false;
+            }
+            Ok(Command::Resume) => {
+                session.resume()?;
+                playing = true;
+            }
+            Ok(Command::Seek(at)) => session.seek(at)?,
+            Ok(Command::Volume(volume)) => session.set_volume(volume)?,
+            Err(RecvTimeoutError::Timeout) if playing => match session.poll()? {
+                Some((at, duration)) => events(CastEvent::Progress(at, duration)),
+                None => {
+                    playing = false;
+                    events(CastEvent::Ended);
+                }
+            },
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => {
+                if let Err(e) = session.stop() {
+                    warn!("cast: failed to stop {}: {e}", session.renderer.name);
+                }
+                return Ok(());
+            }
+        }
+        position.store(session.position, Ordering::Relaxed);
+    }
+}
+
+// The state of the playback on the renderer, kept by the thread of the session.
+struct Session {
+    renderer: Renderer,
+    server: MediaServer,
+    // seen playing since the last track was loaded, a stop after that is the end of the track
+    started: bool,
+    // renderers often refuse to seek before they play, the seek waits until then
+    pending_seek: Option<u64>,
+    // loaded on resume when casting starts while paused
+    cued: Option<(Arc<dyn AudioPlayable>, u64)>,
+    position: u64,
+}
+
+impl Session {
+    fn start(renderer: Renderer) -> Result<Self> {
+        Ok(Self {
+            renderer,
+            server: MediaServer::start()?,
+            started: false,
+            pending_seek: None,
+            cued: None,
+            position: 0,
+        })
+    }
+
+    fn play(&mut self, playable: &dyn AudioPlayable, position: u64) -> Result<()> {
+        let path = playable.get_path();
+        let url = match playable.get_kind() {
+            PlayableKind::LocalFile => self.server.url_for(path, &self.renderer)?,
+            _ => path.to_string(),
+        };
+        self.renderer
+            .load(&url, playable.get_title(), mime_type(path))?;
+        self.renderer.play()?;
+        self.started = false;
+        self.pending_seek = (position > 0).then_some(position);
+        self.cued = None;
+        self.position = position;
+        Ok(())
+    }
+
+    fn cue(&mut self, playable: Arc<dyn AudioPlayable>, position: u64) {
+        self.cued = Some((playable, position));
+        self.position = position;
+    }
+
+    fn pause(&self) -> Result<()> {
+        self.renderer.pause()
+    }
+
+    fn resume(&mut self) -> Result<()> {
+        match self.cued.take() {
+            Some((playable, position)) => self.play(playable.as_ref(), position),
+            None => self.renderer.play(),
+        }
+    }
+
+    fn seek(&mut self, position: u64) -> Result<()> {
+        self.position = position;
+        if self.started {
+            self.renderer.seek(position)
+        } else {
+            self.pending_seek = Some(position);
+            Ok(())
+        }
+    }
+
+    fn set_volume(&self, volume: f32) -> Result<()> {
+        self.renderer
+            .set_volume((volume.clamp(0.0, 1.0) * 100.0).round() as u8)
+    }
+
+    fn stop(&self) -> Result<()> {
+        self.renderer.stop()
+    }
+
+    // Position and duration of the playing track, None once the track has ended.
+    fn poll(&mut self) -> Result<Option<(u64, u64)>> {
+        match self.renderer.transport_state()?.as_str() {
+            "PLAYING" => {
+                self.started = true;
+                if let Some(position) = self.pending_seek.take() {
+                    self.renderer.seek(position)?;
+                }
+            }
+            "STOPPED" | "NO_MEDIA_PRESENT" if self.started => {
+                self.started = false;
+                return Ok(None);
+            }
+            _ => {}
+        }
+        let (position, duration) = self.renderer.position()?;
+        self.position = position;
+        Ok(Some((position, duration)))
+    }
+}
+
+/// Serves the cast files over HTTP. Each file gets a random token for its path, so renderers never
+/// play a cached earlier track and nobody else on the network can guess what to ask for. Only
+/// the track loaded last is served.
+struct MediaServer {
+    listener: Listener,
+    files: Arc<Mutex<HashMap<String, PathBuf>>>,
+}
+
+impl MediaServer {
+    fn start() -> io::Result<Self> {
+        let files: Arc<Mutex<HashMap<String, PathBuf>>> = Arc::default();
+        let served_files = files.clone();
+        let listener = Listener::start("cast-server", move |stream| {
+            if let Err(e) = serve(stream, &served_files) {
+                debug!("MediaServer: request failed: {e}");
+            }
+        })?;
+        Ok(Self { listener, files })
+    }
+
+    fn url_for(&self, path: &str, renderer: &Renderer) -> Result<String> {
+        let host = local_address_towards(renderer)?;
+        let token = format!("{:032x}", rand::random::<u128>());
+        let mut files = self.files.lock().unwrap();
+        files.clear();
+        files.insert(token.clone(), PathBuf::from(path));
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("bin");
+        Ok(format!(
+            "http://{host}:{}/{token}.{extension}",
+            self.listener.port()
+        ))
+    }
+}
+
+impl Drop for MediaServer {
+    fn drop(&mut self) {
+        // requests still in flight find nothing to serve
+        self.files.lock().unwrap().clear();
+    }
+}
+
+// The address of this computer on the network the renderer is on.
+fn local_address_towards(renderer: &Renderer) -> Result<IpAddr> {
+    let location =
+        reqwest::Url::parse(&renderer.location).map_err(|e| CastError::Url(e.to_string()))?;
+    let host = location
+        .host_str()
+        .ok_or_else(|| CastError::Url(renderer.location.clone()))?;
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect((host, location.port_or_known_default().unwrap_or(80)))?;
+    Ok(socket.local_addr()?.ip())
+}
+
+fn serve(mut stream: TcpStream, files: &Mutex<HashMap<String, PathBuf>>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut range = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some(value) = header(&line, "range") {
+            range = Some(value);
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = target
+        .trim_start_matches('/')
+        .split('.')
+        .next()
+        .and_then(|token| files.lock().unwrap().get(token).cloned());
+    let Some(path) = path else {
+        return stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+    };
+
+    let mut file = File::open(&path)?;
+    let len = file.metadata()?.len();
+    let (status, bytes) = byte_range(range.as_deref(), len);
+    let mut response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\n\
+         transferMode.dlna.org: Streaming\r\nConnection: close\r\n",
+        mime_type(&path.to_string_lossy()),
+        bytes.map_or(0, |(start, end)| end + 1 - start)
+    );
+    if status.starts_with("206")
+        && let Some((start, end)) = bytes
+    {
+        response.push_str(&format!("Content-Range: bytes {start}-{end}/{len}\r\n"));
+    } else if status.starts_with("416") {
+        response.push_str(&format!("Content-Range: bytes */{len}\r\n"));
+    }
+    response.push_str("\r\n");
+    stream.write_all(response.as_bytes())?;
+    if method == "GET"
+        && let Some((start, end)) = bytes
+    {
+        file.seek(SeekFrom::Start(start))?;
+        io::copy(&mut file.take(end + 1 - start), &mut stream)?;
+    }
+    Ok(())
+}
+
+// The status of the response for a file of `len` bytes and the inclusive range of the bytes it
+// sends, None when it sends none. An empty file has no byte a range could ask for.
+fn byte_range(range: Option<&str>, len: u64) -> (&'static str, Option<(u64, u64)>) {
+    if len == 0 {
+        return match range {
+            Some(_) => ("416 Range Not Satisfiable", None),
+            None => ("200 OK", None),
+        };
+    }
+    match range.and_then(|range| parse_range(range, len)) {
+        Some(bytes) => ("206 Partial Content", Some(bytes)),
+        None => ("200 OK", Some((0, len - 1))),
+    }
+}
+
+// Reads a single `bytes=start-end` range, the end is inclusive and may be left out.
+fn parse_range(value: &str, len: u64) -> Option<(u64, u64)> {
+    let (start, end) = value.trim().strip_prefix("bytes=")?.split_once('-')?;
+    let start: u64 = start.trim().parse().ok()?;
+    let end = match end.trim() {
+        "" => len.checked_sub(1)?,
+        end => end.parse::<u64>().ok()?.min(len.checked_sub(1)?),
+    };
+    (start <= end).then_some((start, end))
+}
+
+// Finds a header in an HTTP message, names are case insensitive.
+fn header(message: &str, name: &str) -> Option<String> {
+    message.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_string())
+    })
+}
+
+fn mime_type(path: &str) -> &'static str {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match extension.as_str() {
+        "mp3" => "audio/mpeg",
+        "flac" => "audio/flac",
+        "wav" => "audio/wav",
+        "aif" | "aiff" => "audio/aiff",
+        "m4a" | "mp4" | "aac" => "audio/mp4",
+        "ogg" | "oga" | "opus" => "audio/ogg",
+        _ => "application/octet-stream",
+    }
+}
+
+// Seconds as the `H:MM:SS` used by AVTransport.
+fn format_time(seconds: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn parse_time(value: &str) -> Option<u64> {
+    // fractions of a second are dropped
+    let value = value.split('.').next()?;
+    let mut parts = value.split(':').map(|part| part.parse::<u64>().ok());
+    let (hours, minutes, seconds) = (parts.next()??, parts.next()??, parts.next()??);
+    Some(hours * 3600 + minutes * 60 + seconds)
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_description() {
+        let xml = r#"<?xml version="1.0"?>
+            <root xmlns="urn:schemas-upnp-org:device-1-0">
+              <device>
+                <friendlyName>Living Room</friendlyName>
+                <serviceList>
+                  <service>
+                    <serviceType>urn:schemas-upnp-org:service:RenderingControl:1</serviceType>
+                    <controlURL>/RenderingControl/ctrl</controlURL>
+                  </service>
+                  <service>
+                    <serviceType>urn:schemas-upnp-org:service:AVTransport:1</serviceType>
+                    <controlURL>AVTransport/ctrl</controlURL>
+                  </service>
+                </serviceList>
+              </device>
+            </root>"#;
+        let renderer = parse_description("http://192.168.1.20:49152/desc/device.xml", xml).unwrap();
+        assert_eq!(renderer.name, "Living Room");
+        assert_eq!(
+            renderer.av_transport,
+            "http://192.168.1.20:49152/desc/AVTransport/ctrl"
+        );
+        assert_eq!(
+            renderer.rendering_control.as_deref(),
+            Some("http://192.168.1.20:49152/RenderingControl/ctrl")
+        );
+
+        let no_transport = xml.replace("AVTransport:1", "ConnectionManager:1");
+        assert!(matches!(
+            parse_description("http://192.168.1.20:49152/", &no_transport),
+            Err(CastError::MissingService(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-", 100), Some((0, 99)));
+        assert_eq!(parse_range("bytes=10-19", 100), Some((10, 19)));
+        assert_eq!(parse_range("bytes=90-200", 100), Some((90, 99)));
+        assert_eq!(parse_range("bytes=50-10", 100), None);
+        assert_eq!(parse_range("items=0-", 100), None);
+    }
+
+    #[test]
+    fn test_byte_range() {
+        assert_eq!(byte_range(None, 100), ("200 OK", Some((0, 99))));
+        assert_eq!(
+            byte_range(Some("bytes=10-"), 100),
+            ("206 Partial Content", Some((10, 99)))
+        );
+        // a range which can not be read sends the whole file
+        assert_eq!(
+            byte_range(Some("bytes=50-10"), 100),
+            ("200 OK", Some((0, 99)))
+        );
+        // an empty file sends no body
+        assert_eq!(byte_range(None, 0), ("200 OK", None));
+        assert_eq!(
+            byte_range(Some("bytes=0-"), 0),
+            ("416 Range Not Satisfiable", None)
+        );
+    }
+
+    #[test]
+    fn test_time() {
+        assert_eq!(format_time(3725), "1:02:05");
+        assert_eq!(parse_time("1:02:05"), Some(3725));
+        assert_eq!(parse_time("0:03:12.500"), Some(192));
+        assert_eq!(parse_time("NOT_IMPLEMENTED"), None);
+        assert_eq!(
+            header(
+                "HTTP/1.1 200 OK\r\nLOCATION: http://x/d.xml\r\n",
+                "location"
+            )
+            .as_deref(),
+            Some("http://x/d.xml")
+        );
+    }
+}
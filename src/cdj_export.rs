@@ -0,0 +1,383 @@
+// Preparing playlists for Pioneer CDJs. The players read a narrow set of formats and sample
+// rates and skip files with long names, each file is checked against that before the export
+// and converted or renamed where needed. Files are copied into `Contents/<artist>/<album>` on the
+// drive like rekordbox lays out its exports, the playlist is written next to them as an M3U
+// file which rekordbox imports. The rekordbox database itself is left to rekordbox.
+use std::{
+    collections::HashSet,
+    fmt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use iced::futures::channel::oneshot;
+use lofty::file::AudioFile;
+
+use crate::{
+    app_state::AudioPlayable,
+    convert::{self, ConvertError, Result, file_name_part},
+    storage::{AudioFileKind, Playable},
+};
+
+pub const CONTENTS_FOLDER: &str = "Contents";
+// characters of a file or folder name, extension included. Older players cut longer names
+// short on their screens or do not list the file at all
+pub const MAX_NAME_LENGTH: usize = 64;
+const SAMPLE_RATES: [u32; 2] = [44_100, 48_000];
+// read by every player since the CDJ-2000
+const FORMATS: [&str; 6] = ["mp3", "m4a", "aac", "wav", "aif", "aiff"];
+// converted lossy files, the most the players read
+const MP3_BITRATE: u32 = 320;
+
+/// Something about a file the players can not cope with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Issue {
+    // the extension of a format the players do not read
+    Format(String),
+    SampleRate(u32),
+    // the length of the file name
+    NameLength(usize),
+    Missing,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Issue::Format(extension) if extension.is_empty() => write!(f, "Unknown format"),
+            Issue::Format(extension) => write!(f, "{} is not supported", extension.to_uppercase()),
+            Issue::SampleRate(rate) => write!(f, "{:.1} kHz sample rate", *rate as f64 / 1000.0),
+            Issue::NameLength(length) => write!(f, "File name of {length} characters"),
+            Issue::Missing => write!(f, "File not found"),
+        }
+    }
+}
+
+/// What the players need a file converted to: 16 or 24 bit AIFF for lossless files, MP3
+/// otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conversion {
+    pub lossless: bool,
+    pub sample_rate: u32,
+    pub bit_depth: u8,
+}
+
+impl Conversion {
+    fn extension(&self) -> &'static str {
+        if self.lossless { "aiff" } else { "mp3" }
+    }
+}
+
+impl fmt::Display for Conversion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rate = self.sample_rate as f64 / 1000.0;
+        if self.lossless {
+            write!(
+                f,
+                "Converted to {}-bit AIFF at {rate:.1} kHz",
+                self.bit_depth
+            )
+        } else {
+            write!(f, "Converted to {MP3_BITRATE} kbit/s MP3 at {rate:.1} kHz")
+        }
+    }
+}
+
+/// The audio properties of a file as read from its header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Probe {
+    pub sample_rate: Option<u32>,
+    // only lossless formats have one
+    pub bit_depth: Option<u8>,
+}
+
+impl Probe {
+    fn read(path: &Path) -> Option<Probe> {
+        let file = lofty::read_from_path(path).ok()?;
+        let properties = file.properties();
+        Some(Probe {
+            sample_rate: properties.sample_rate(),
+            bit_depth: properties.bit_depth(),
+        })
+    }
+}
+
+/// One track of the export and how it gets onto the drive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportItem {
+    pub title: String,
+    pub source: PathBuf,
+    // inside the drive
+    pub target: PathBuf,
+    pub issues: Vec<Issue>,
+    // None copies the file as it is
+    pub conversion: Option<Conversion>,
+}
+
+impl ExportItem {
+    pub fn is_missing(&self) -> bool {
+        self.issues.contains(&Issue::Missing)
+    }
+
+    pub fn is_renamed(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| matches!(issue, Issue::NameLength(_)))
+    }
+}
+
+/// Checks the local files among `playables` on a thread of its own, in order. `flac` is set for
+/// players which read FLAC, the CDJ-2000NXS2, CDJ-3000 and newer.
+pub async fn check(playables: Vec<Playable>, flac: bool) -> Vec<ExportItem> {
+    let (sender, receiver) = oneshot::channel();
+    std::thread::spawn(move || {
+        let mut taken = HashSet::new();
+        let items = playables
+            .iter()
+            .filter(|playable| playable.type_id == AudioFileKind::LocalFile)
+            .map(|playable| {
+                let source = PathBuf::from(&playable.source_url);
+                let probe = Probe::read(&source);
+                plan(playable, probe, flac, &mut taken)
+            })
+            .collect();
+        let _ = sender.send(items);
+    });
+    receiver.await.unwrap_or_default()
+}
+
+// `taken` holds the targets of the tracks planned before, in lower case as the drives are
+// formatted with FAT32 or exFAT which do not tell case apart
+fn plan(
+    playable: &Playable,
+    probe: Option<Probe>,
+    flac: bool,
+    taken: &mut HashSet<String>,
+) -> ExportItem {
+    let source = PathBuf::from(&playable.source_url);
+    let mut issues = Vec::new();
+    let extension = source
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let probe = probe.unwrap_or_else(|| {
+        issues.push(Issue::Missing);
+        Probe::default()
+    });
+    let supported = FORMATS.contains(&extension.as_str()) || (flac && extension == "flac");
+    if !supported {
+        issues.push(Issue::Format(extension.clone()));
+    }
+    let sample_rate = probe.sample_rate.unwrap_or(SAMPLE_RATES[0]);
+    if !SAMPLE_RATES.contains(&sample_rate) {
+        issues.push(Issue::SampleRate(sample_rate));
+    }
+    let conversion = (!supported || !SAMPLE_RATES.contains(&sample_rate)).then(|| Conversion {
+        lossless: probe.bit_depth.is_some(),
+        // high rates are mostly multiples of one of the two
+        sample_rate: if sample_rate % SAMPLE_RATES[1] == 0 {
+            SAMPLE_RATES[1]
+        } else {
+            SAMPLE_RATES[0]
+        },
+        bit_depth: if probe.bit_depth.is_some_and(|depth| depth > 16) {
+            24
+        } else {
+            16
+        },
+    });
+    let extension = conversion.map_or(extension, |conversion| conversion.extension().to_string());
+
+    let stem = source
+        .file_stem()
+        .map(|stem| file_name_part(&stem.to_string_lossy()))
+        .unwrap_or_else(|| file_name_part(""));
+    let name_length = stem.chars().count() + extension.len() + 1;
+    if name_length > MAX_NAME_LENGTH {
+        issues.push(Issue::NameLength(name_length));
+    }
+    let folders = [playable.get_artist(), playable.get_album()]
+        .map(|name| shorten(&file_name_part(name), MAX_NAME_LENGTH));
+    let mut target = PathBuf::from(CONTENTS_FOLDER);
+    target.extend(&folders);
+    // a number tells files apart which end up with the same name
+    let stem = shorten(&stem, MAX_NAME_LENGTH - extension.len() - 1);
+    let mut number = 1;
+    let mut file_name = format!("{stem}.{extension}");
+    while !taken.insert(target.join(&file_name).to_string_lossy().to_lowercase()) {
+        number += 1;
+        let suffix = format!(" ({number})");
+        let stem = shorten(&stem, MAX_NAME_LENGTH - extension.len() - 1 - suffix.len());
+        file_name = format!("{stem}{suffix}.{extension}");
+    }
+    target.push(file_name);
+
+    let title = match playable.get_artist() {
+        "" => playable.get_title().to_string(),
+        artist => format!("{artist} - {}", playable.get_title()),
+    };
+    ExportItem {
+        title,
+        source,
+        target,
+        issues,
+        conversion,
+    }
+}
+
+// the first `length` characters, trailing spaces and dots left out for Windows
+fn shorten(name: &str, length: usize) -> String {
+    let name: String = name.chars().take(length).collect();
+    name.trim_end_matches([' ', '.']).to_string()
+}
+
+/// Copies or converts one track onto the drive mounted at `drive`. A file already there is
+/// taken to be from an earlier export and kept.
+pub async fn export(item: ExportItem, drive: PathBuf) -> Result<()> {
+    let (sender, receiver) = oneshot::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(write(&item, &drive.join(&item.target)));
+    });
+    receiver
+        .await
+        .unwrap_or_else(|_| Err(ConvertError::Encoder("export stopped".into())))
+}
+
+fn write(item: &ExportItem, target: &Path) -> Result<()> {
+    if target.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let Some(conversion) = item.conversion else {
+        std::fs::copy(&item.source, target)?;
+        return Ok(());
+    };
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-nostdin", "-n", "-loglevel", "error", "-i"])
+        .arg(&item.source)
+        .args(["-map", "0:a", "-map_metadata", "0"])
+        .args(["-ar", &conversion.sample_rate.to_string()]);
+    if conversion.lossless {
+        let codec = if conversion.bit_depth > 16 {
+            "pcm_s24be"
+        } else {
+            "pcm_s16be"
+        };
+        command.args(["-c:a", codec, "-write_id3v2", "1"]);
+    } else {
+        command.args(["-c:a", "libmp3lame", "-b:a", &format!("{MP3_BITRATE}k")]);
+        command.args(["-id3v2_version", "3"]);
+    }
+    convert::run(command.arg(target), target)
+}
+
+/// The exported tracks as an M3U playlist, with paths relative to the root of the drive.
+pub fn playlist(name: &str, items: &[ExportItem]) -> String {
+    let mut lines = vec!["#EXTM3U".to_string(), format!("#PLAYLIST:{name}")];
+    for item in items.iter().filter(|item| !item.is_missing()) {
+        lines.push(format!("#EXTINF:-1,{}", item.title));
+        let segments: Vec<String> = item
+            .target
+            .iter()
+            .map(|segment| segment.to_string_lossy().to_string())
+            .collect();
+        lines.push(segments.join("/"));
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Where the playlist is saved on the drive.
+pub fn playlist_path(drive: &Path, name: &str) -> PathBuf {
+    drive.join(format!("{}.m3u8", file_name_part(name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playable(path: &str) -> Playable {
+        Playable {
+            id: 1,
+            title: "Title".to_string(),
+            artist_name: Some("Artist".to_string()),
+            duration: 100,
+            source_url: path.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let cd = Some(Probe {
+            sample_rate: Some(44_100),
+            bit_depth: Some(16),
+        });
+        let mut taken = HashSet::new();
+        let item = plan(&playable("/music/a.mp3"), cd, false, &mut taken);
+        assert_eq!(item.target, PathBuf::from("Contents/Artist/Unknown/a.mp3"));
+        assert!(item.issues.is_empty());
+        assert_eq!(item.conversion, None);
+
+        // the same name again, in another case
+        let item = plan(&playable("/other/A.wav"), cd, false, &mut taken);
+        assert_eq!(item.target, PathBuf::from("Contents/Artist/Unknown/A.wav"));
+        let item = plan(&playable("/other/A.mp3"), cd, false, &mut taken);
+        assert_eq!(
+            item.target,
+            PathBuf::from("Contents/Artist/Unknown/A (2).mp3")
+        );
+
+        let hi_res = Some(Probe {
+            sample_rate: Some(96_000),
+            bit_depth: Some(24),
+        });
+        let item = plan(&playable("/music/b.flac"), hi_res, false, &mut taken);
+        assert_eq!(
+            item.issues,
+            [Issue::Format("flac".to_string()), Issue::SampleRate(96_000)]
+        );
+        assert_eq!(
+            item.conversion,
+            Some(Conversion {
+                lossless: true,
+                sample_rate: 48_000,
+                bit_depth: 24,
+            })
+        );
+        assert_eq!(item.target, PathBuf::from("Contents/Artist/Unknown/b.aiff"));
+        let item = plan(&playable("/music/c.flac"), cd, true, &mut taken);
+        assert_eq!(item.conversion, None);
+
+        let long = format!("/music/{}.ogg", "x".repeat(80));
+        let item = plan(&playable(&long), None, false, &mut taken);
+        assert_eq!(
+            item.issues,
+            [
+                Issue::Missing,
+                Issue::Format("ogg".to_string()),
+                Issue::NameLength(84)
+            ]
+        );
+        assert!(item.is_missing() && item.is_renamed());
+        let file_name = item.target.file_name().unwrap().to_string_lossy();
+        assert_eq!(file_name.chars().count(), MAX_NAME_LENGTH);
+        assert!(file_name.ends_with("x.mp3"));
+    }
+
+    #[test]
+    fn test_playlist() {
+        let mut taken = HashSet::new();
+        let probe = Some(Probe::default());
+        let items = [
+            plan(&playable("/music/a.mp3"), probe, false, &mut taken),
+            plan(&playable("/music/b.mp3"), None, false, &mut taken),
+        ];
+        assert_eq!(
+            playlist("Friday", &items),
+            "#EXTM3U\n#PLAYLIST:Friday\n#EXTINF:-1,Artist - Title\n\
+             Contents/Artist/Unknown/a.mp3\n"
+        );
+    }
+}
@@ -0,0 +1,343 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chapter {
+    pub title: String,
+    // seconds from the start of the file
+    pub start: u64,
+}
+
+/// Reads the chapter markers of an MP3 (ID3v2 `CHAP` frames) or M4B/M4A (Nero `chpl` atom) file.
+pub fn read_chapters(path: &Path) -> std::io::Result<Vec<Chapter>> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .unwrap_or_default();
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut chapters = match extension.as_str() {
+        "mp3" => id3_chapters(&mut reader)?,
+        "m4a" | "m4b" | "mp4" => mp4_chapters(&mut reader)?,
+        _ => Vec::new(),
+    };
+    chapters.sort_by_key(|c| c.start);
+    Ok(chapters)
+}
+
+/// Index of the chapter playing at `position`.
+pub fn current_chapter(chapters: &[Chapter], position: u64) -> Option<usize> {
+    chapters.iter().rposition(|c| c.start <= position)
+}
+
+fn id3_chapters<R: Read>(reader: &mut R) -> std::io::Result<Vec<Chapter>> {
+    let mut header = [0u8; 10];
+    if reader.read_exact(&mut header).is_err() || &header[0..3] != b"ID3" {
+        return Ok(Vec::new());
+    }
+    let version = header[3];
+    let flags = header[5];
+    let mut tag = vec![0u8; syncsafe(&header[6..10]) as usize];
+    reader.read_exact(&mut tag)?;
+
+    let mut offset = 0;
+    // skip the extended header
+    if flags & 0x40 != 0 && tag.len() >= 4 {
+        offset = if version == 4 {
+            syncsafe(&tag[0..4]) as usize
+        } else {
+            u32::from_be_bytes([tag[0], tag[1], tag[2], tag[3]]) as usize + 4
+        };
+    }
+
+    let mut chapters = Vec::new();
+    for (id, body) in id3_frames(&tag[offset.min(tag.len())..], version) {
+        if id == b"CHAP"
+            && let Some(chapter) = chap_frame(body, version)
+        {
+            chapters.push(chapter);
+        }
+    }
+    Ok(chapters)
+}
+
+fn id3_frames(mut data: &[u8], version: u8) -> Vec<(&[u8], &[u8])> {
+    let mut frames = Vec::new();
+    while data.len() >= 10 && data[0] != 0 {
+        let size = if version == 4 {
+            syncsafe(&data[4..8])
+        } else {
+            u32::from_be_bytes([data[4], data[5], data[6], data[7]])
+        } as usize;
+        if data.len() < 10 + size {
+            break;
+        }
+        frames.push((&data[0..4], &data[10..10 + size]));
+        data = &data[10 + size..];
+    }
+    frames
+}
+
+fn chap_frame(body: &[u8], version: u8) -> Option<Chapter> {
+    let id_end = body.iter().position(|b| *b == 0)?;
+    let times = body.get(id_end + 1..id_end + 17)?;
+    let start_ms = u32::from_be_bytes([times[0], times[1], times[2], times[3]]);
+    let title = id3_frames(&body[id_end + 17..], version)
+        .into_iter()
+        .find(|(id, _)| *id == b"TIT2")
+        .map(|(_, text)| decode_id3_text(text))
+        .unwrap_or_else(|| String::from_utf8_lossy(&body[..id_end]).to_string());
+    Some(Chapter {
+        title,
+        start: start_ms as u64 / 1000,
+    })
+}
+
+fn decode_id3_text(text: &[u8]) -> String {
+    let Some((encoding, text)) = text.split_first() else {
+        return String::new();
+    };
+    let decoded = match encoding {
+        // UTF-16 with BOM / UTF-16BE
+        1 | 2 => {
+            let mut units: Vec<u16> = text
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            if units.first() == Some(&0xFFFE) {
+                units = units.iter().map(|u| u.swap_bytes()).collect();
+            }
+            if units.first() == Some(&0xFEFF) {
+                units.remove(0);
+            }
+            String::from_utf16_lossy(&units)
+        }
+        3 => String::from_utf8_lossy(text).to_string(),
+        // ISO-8859-1 maps 1:1 to the first unicode code points
+        _ => text.iter().map(|b| *b as char).collect(),
+    };
+    decoded.trim_end_matches('\0').to_string()
+}
+
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .take(4)
+        .fold(0, |acc, b| (acc << 7) | (*b & 0x7F) as u32)
+}
+
+fn mp4_chapters<R: Read + Seek>(reader: &mut R) -> std::io::Result<Vec<Chapter>> {
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let Some(moov) = find_atom(reader, b"moov", end)? else {
+        return Ok(Vec::new());
+    };
+    let Some(udta) = find_atom(reader, b"udta", moov)? else {
+        return Ok(Vec::new());
+    };
+    let Some(chpl) = find_atom(reader, b"chpl", udta)? else {
+        return Ok(Vec::new());
+    };
+    let start = reader.stream_position()?;
+    let mut body = vec![0u8; (chpl - start) as usize];
+    reader.read_exact(&mut body)?;
+    Ok(chpl_atom(&body))
+}
+
+// Scans sibling atoms up to `end` and leaves the reader at the start of the body of the
+// first one named `name`. Returns where that atom ends, None as well for an atom which
+// claims to run past `end`.
+fn find_atom<R: Read + Seek>(
+    reader: &mut R,
+    name: &[u8; 4],
+    end: u64,
+) -> std::io::Result<Option<u64>> {
+    let mut position = reader.stream_position()?;
+    while position + 8 <= end {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        let mut size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        let mut header_len = 8;
+        if size == 1 {
+            let mut large = [0u8; 8];
+            reader.read_exact(&mut large)?;
+            size = u64::from_be_bytes(large);
+            header_len = 16;
+        } else if size == 0 {
+            size = end - position;
+        }
+        if size < header_len {
+            return Ok(None);
+        }
+        let Some(atom_end) = position
+            .checked_add(size)
+            .filter(|atom_end| *atom_end <= end)
+        else {
+            return Ok(None);
+        };
+        if &header[4..8] == name {
+            return Ok(Some(atom_end));
+        }
+        position = atom_end;
+        reader.seek(SeekFrom::Start(position))?;
+    }
+    Ok(None)
+}
+
+fn chpl_atom(body: &[u8]) -> Vec<Chapter> {
+    let Some(version) = body.first() else {
+        return Vec::new();
+    };
+    // version, flags and, since version 1, four reserved bytes
+    let mut offset = if *version == 1 { 8 } else { 4 };
+    let Some(count) = body.get(offset) else {
+        return Vec::new();
+    };
+    offset += 1;
+    let mut chapters = Vec::with_capacity(*count as usize);
+    for _ in 0..*count {
+        let Some(entry) = body.get(offset..offset + 9) else {
+            break;
+        };
+        let start = u64::from_be_bytes(entry[0..8].try_into().unwrap());
+        let title_len = entry[8] as usize;
+        let Some(title) = body.get(offset + 9..offset + 9 + title_len) else {
+            break;
+        };
+        chapters.push(Chapter {
+            title: String::from_utf8_lossy(title).to_string(),
+            // stored in 100ns units
+            start: start / 10_000_000,
+        });
+        offset += 9 + title_len;
+    }
+    chapters
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn id3_frame(id: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut frame = id.to_vec();
+        frame.extend((body.len() as u32).to_be_bytes());
+        frame.extend([0, 0]);
+        frame.extend(body);
+        frame
+    }
+
+    fn chap(element_id: &str, start_ms: u32, title: &str) -> Vec<u8> {
+        let mut body = element_id.as_bytes().to_vec();
+        body.push(0);
+        body.extend(start_ms.to_be_bytes());
+        body.extend((start_ms + 1000).to_be_bytes());
+        body.extend([0xFF; 8]);
+        let mut text = vec![3];
+        text.extend(title.as_bytes());
+        body.extend(id3_frame(b"TIT2", &text));
+        id3_frame(b"CHAP", &body)
+    }
+
+    fn atom(name: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut atom = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        atom.extend(name);
+        atom.extend(body);
+        atom
+    }
+
+    #[test]
+    fn test_id3_chapters() {
+        let mut frames = id3_frame(b"TIT2", b"\x03Book");
+        frames.extend(chap("ch0", 0, "Intro"));
+        frames.extend(chap("ch1", 90_500, "Chapter One"));
+        // v2.3 tags use plain frame sizes, the tag size is always syncsafe
+        let size = frames.len() as u32;
+        let mut tag = b"ID3\x03\x00\x00".to_vec();
+        tag.extend([
+            (size >> 21 & 0x7F) as u8,
+            (size >> 14 & 0x7F) as u8,
+            (size >> 7 & 0x7F) as u8,
+            (size & 0x7F) as u8,
+        ]);
+        tag.extend(frames);
+
+        let chapters = id3_chapters(&mut Cursor::new(tag)).unwrap();
+        assert_eq!(
+            chapters,
+            vec![
+                Chapter {
+                    title: "Intro".to_string(),
+                    start: 0
+                },
+                Chapter {
+                    title: "Chapter One".to_string(),
+                    start: 90
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_id3_without_tag() {
+        let chapters = id3_chapters(&mut Cursor::new(vec![0xFF, 0xFB, 0x90])).unwrap();
+        assert!(chapters.is_empty());
+    }
+
+    #[test]
+    fn test_mp4_chapters() {
+        let mut chpl = vec![1, 0, 0, 0, 0, 0, 0, 0, 2];
+        for (start, title) in [(0u64, "Opening"), (125 * 10_000_000, "Part 2")] {
+            chpl.extend(start.to_be_bytes());
+            chpl.push(title.len() as u8);
+            chpl.extend(title.as_bytes());
+        }
+        let udta = atom(b"udta", &atom(b"chpl", &chpl));
+        let mut moov_body = atom(b"mvhd", &[0; 12]);
+        moov_body.extend(udta);
+        let mut file = atom(b"ftyp", b"M4B isom");
+        file.extend(atom(b"moov", &moov_body));
+        file.extend(atom(b"mdat", &[0; 32]));
+
+        let chapters = mp4_chapters(&mut Cursor::new(file)).unwrap();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[1].title, "Part 2");
+        assert_eq!(chapters[1].start, 125);
+    }
+
+    #[test]
+    fn test_mp4_atom_past_the_end() {
+        // a 64-bit size near u64::MAX, as a corrupt file might have
+        let mut file = 1u32.to_be_bytes().to_vec();
+        file.extend(b"moov");
+        file.extend((u64::MAX - 4).to_be_bytes());
+        file.extend(atom(b"udta", &atom(b"chpl", &[0; 5])));
+        assert!(mp4_chapters(&mut Cursor::new(file)).unwrap().is_empty());
+
+        let mut file = atom(b"ftyp", b"M4B isom");
+        file.extend(atom(b"moov", &atom(b"udta", &atom(b"chpl", &[0; 5]))));
+        file.truncate(file.len() - 2);
+        assert!(mp4_chapters(&mut Cursor::new(file)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_current_chapter() {
+        let chapters = vec![
+            Chapter {
+                title: "One".to_string(),
+                start: 0,
+            },
+            Chapter {
+                title: "Two".to_string(),
+                start: 60,
+            },
+        ];
+        assert_eq!(current_chapter(&chapters, 30), Some(0));
+        assert_eq!(current_chapter(&chapters, 60), Some(1));
+        assert_eq!(current_chapter(&[], 60), None);
+    }
+}
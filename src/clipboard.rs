@@ -0,0 +1,185 @@
+use serde::Serialize;
+
+use crate::{app_state::AudioPlayable, util::duration_to_str};
+
+pub const DEFAULT_TRACKLIST_TEMPLATE: &str = "{n}. {artist} – {title} [{label}]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardFormat {
+    // one "Artist - Title" line per playable
+    ArtistTitle,
+    // an array with all the metadata we know about each playable
+    Json,
+    // one source path per line
+    Paths,
+}
+
+pub fn format_playables<T: AudioPlayable + ?Sized>(
+    playables: &[&T],
+    format: ClipboardFormat,
+) -> String {
+    match format {
+        ClipboardFormat::ArtistTitle => playables
+            .iter()
+            .map(|p| artist_title(*p))
+            .collect::<Vec<String>>()
+            .join("\n"),
+        ClipboardFormat::Json => {
+            let entries = playables
+                .iter()
+                .map(|p| JsonEntry::new(*p))
+                .collect::<Vec<JsonEntry>>();
+            serde_json::to_string_pretty(&entries).unwrap_or_default()
+        }
+        ClipboardFormat::Paths => playables
+            .iter()
+            .map(|p| p.get_path().to_string())
+            .collect::<Vec<String>>()
+            .join("\n"),
+    }
+}
+
+/// Formats playables as a numbered tracklist, one line per playable.
+/// Supported placeholders: `{n}`, `{artist}`, `{title}`, `{album}`, `{genre}`, `{label}` and
+/// `{duration}`. `{n}` has at least two digits, e.g. `01`.
+pub fn format_tracklist<T: AudioPlayable + ?Sized>(playables: &[&T], template: &str) -> String {
+    let width = playables.len().to_string().len().max(2);
+    playables
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let line = template.replace("{n}", &format!("{:0width$}", i + 1));
+            [
+                ("{artist}", p.get_artist()),
+                ("{title}", p.get_title()),
+                ("{album}", p.get_album()),
+                ("{genre}", p.get_genre()),
+                ("{label}", p.get_label()),
+                ("{duration}", &duration_to_str(p.get_duration())),
+            ]
+            .into_iter()
+            .fold(line, |line, (placeholder, value)| {
+                fill(line, placeholder, value)
+            })
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Fills in a placeholder, an empty value also takes the brackets around it and the space before
+// them, so that `Title [{label}]` reads `Title` for a track without a label.
+fn fill(line: String, placeholder: &str, value: &str) -> String {
+    if !value.is_empty() {
+        return line.replace(placeholder, value);
+    }
+    [("[", "]"), ("(", ")")]
+        .into_iter()
+        .fold(line, |line, (open, close)| {
+            let bracketed = format!("{open}{placeholder}{close}");
+            line.replace(&format!(" {bracketed}"), "")
+                .replace(&bracketed, "")
+        })
+        .replace(placeholder, "")
+}
+
+pub fn artist_title<T: AudioPlayable + ?Sized>(playable: &T) -> String {
+    if playable.get_artist().is_empty() {
+        playable.get_title().to_string()
+    } else {
+        format!("{} - {}", playable.get_artist(), playable.get_title())
+    }
+}
+
+#[derive(Serialize)]
+struct JsonEntry<'a> {
+    id: i64,
+    title: &'a str,
+    artist: &'a str,
+    album: &'a str,
+    genre: &'a str,
+    duration: u64,
+    path: &'a str,
+}
+
+impl<'a> JsonEntry<'a> {
+    fn new<T: AudioPlayable + ?Sized>(playable: &'a T) -> Self {
+        Self {
+            id: playable.get_id(),
+            title: playable.get_title(),
+            artist: playable.get_artist(),
+            album: playable.get_album(),
+            genre: playable.get_genre(),
+            duration: playable.get_duration(),
+            path: playable.get_path(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Playable;
+
+    fn playable(id: i64, title: &str, artist: Option<&str>) -> Playable {
+        Playable {
+            id,
+            title: title.to_string(),
+            artist_name: artist.map(|a| a.to_string()),
+            album_name: Some("Album".to_string()),
+            duration: 245,
+            source_url: format!("/music/{title}.mp3"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_artist_title() {
+        let first = playable(1, "Track", Some("Artist"));
+        let second = playable(2, "Untitled", None);
+        let text = format_playables(&[&first, &second], ClipboardFormat::ArtistTitle);
+        assert_eq!(text, "Artist - Track\nUntitled");
+    }
+
+    #[test]
+    fn test_paths() {
+        let first = playable(1, "one", None);
+        let second = playable(2, "two", None);
+        let text = format_playables(&[&first, &second], ClipboardFormat::Paths);
+        assert_eq!(text, "/music/one.mp3\n/music/two.mp3");
+    }
+
+    #[test]
+    fn test_json_is_escaped() {
+        let first = playable(7, "Say \"Hi\"", Some("A\\B"));
+        let text = format_playables(&[&first], ClipboardFormat::Json);
+        assert!(text.starts_with("[\n"));
+        assert!(text.contains("\"id\": 7"));
+        assert!(text.contains("\"title\": \"Say \\\"Hi\\\"\""));
+        assert!(text.contains("\"artist\": \"A\\\\B\""));
+        assert!(text.contains("\"genre\": \"\""));
+        assert!(text.contains("\"duration\": 245"));
+    }
+
+    #[test]
+    fn test_tracklist() {
+        let first = playable(1, "Track", Some("Artist"));
+        let second = playable(2, "Other", Some("Someone"));
+        let text = format_tracklist(&[&first, &second], DEFAULT_TRACKLIST_TEMPLATE);
+        assert_eq!(text, "01. Artist – Track\n02. Someone – Other");
+
+        let text = format_tracklist(&[&first], "{n} {title} [{album}] ({duration})");
+        assert_eq!(text, "01 Track [Album] (4:05)");
+
+        let labelled = Playable {
+            label: Some("Warp".to_string()),
+            ..playable(3, "Windowlicker", Some("Aphex Twin"))
+        };
+        let text = format_tracklist(&[&labelled, &second], DEFAULT_TRACKLIST_TEMPLATE);
+        assert_eq!(
+            text,
+            "01. Aphex Twin – Windowlicker [Warp]\n02. Someone – Other"
+        );
+        let text = format_tracklist(&[&second], "{title} ({label}) {album}");
+        assert_eq!(text, "Other Album");
+    }
+}
@@ -0,0 +1,265 @@
+// Converting library files to other formats. Encoding is left to ffmpeg, which has to be
+// installed and on the PATH, tags are copied over from the source file.
+use std::{
+    fmt,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use iced::futures::channel::oneshot;
+use thiserror::Error;
+
+use crate::{app_state::AudioPlayable, storage::Playable};
+
+// artist and album folders, the extension is added for the format
+pub const DEFAULT_TEMPLATE: &str = "{artist}/{album}/{title}";
+pub const DEFAULT_BITRATE: u32 = 256;
+
+#[derive(Debug, Error)]
+pub enum ConvertError {
+    #[error("ffmpeg was not found, install it to convert files")]
+    MissingEncoder,
+    #[error("ffmpeg failed: {0}")]
+    Encoder(String),
+    #[error("{0} already exists")]
+    Exists(PathBuf),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, ConvertError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    #[default]
+    Mp3,
+    Aac,
+    Opus,
+    // lossless, the bitrate is ignored
+    Flac,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Mp3, Format::Aac, Format::Opus, Format::Flac];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Format::Mp3 => "mp3",
+            Format::Aac => "aac",
+            Format::Opus => "opus",
+            Format::Flac => "flac",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Aac => "m4a",
+            format => format.as_str(),
+        }
+    }
+
+    pub fn is_lossless(&self) -> bool {
+        *self == Format::Flac
+    }
+
+    fn codec(&self) -> &'static str {
+        match self {
+            Format::Mp3 => "libmp3lame",
+            Format::Aac => "aac",
+            Format::Opus => "libopus",
+            Format::Flac => "flac",
+        }
+    }
+}
+
+impl From<&str> for Format {
+    fn from(s: &str) -> Self {
+        match s {
+            "aac" => Format::Aac,
+            "opus" => Format::Opus,
+            "flac" => Format::Flac,
+            _ => Format::Mp3,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Mp3 => write!(f, "MP3"),
+            Format::Aac => write!(f, "AAC"),
+            Format::Opus => write!(f, "Opus"),
+            Format::Flac => write!(f, "FLAC"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConvertOptions {
+    pub format: Format,
+    // kbit/s
+    pub bitrate: u32,
+    pub folder: PathBuf,
+    // path inside `folder`, see `output_path`
+    pub template: String,
+    // points the library entry to the converted file instead of adding it next to the original
+    pub replace: bool,
+}
+
+/// Where a playable is converted to. The template may use `{artist}`, `{album}`, `{genre}` and
+/// `{title}`, each `/` in it starts a folder.
+pub fn output_path(options: &ConvertOptions, playable: &Playable) -> PathBuf {
+    let template = match options.template.trim() {
+        "" => DEFAULT_TEMPLATE,
+        template => template,
+    };
+    let segments: Vec<String> = template
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|segment| {
+            segment
+                .replace("{artist}", &file_name_part(playable.get_artist()))
+                .replace("{album}", &file_name_part(playable.get_album()))
+                .replace("{genre}", &file_name_part(playable.get_genre()))
+                .replace("{title}", &file_name_part(playable.get_title()))
+        })
+        .collect();
+    let mut path = options.folder.clone();
+    if let Some((file_name, folders)) = segments.split_last() {
+        path.extend(folders);
+        // not `set_extension`, titles like `Mr. Blue` would lose their end
+        path.push(format!("{file_name}.{}", options.format.extension()));
+    }
+    path
+}
+
+/// A tag value made safe to use in a file name, empty values become `Unknown`.
+pub fn file_name_part(value: &str) -> String {
+    let value: String = value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect();
+    match value.trim() {
+        "" => "Unknown".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// Encodes `input` to `output` on a thread of its own, existing files are never overwritten.
+pub async fn convert(
+    input: String,
+    output: PathBuf,
+    format: Format,
+    bitrate: u32,
+) -> Result<PathBuf> {
+    let (sender, receiver) = oneshot::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(encode(Path::new(&input), &output, format, bitrate).map(|_| output));
+    });
+    receiver
+        .await
+        .unwrap_or_else(|_| Err(ConvertError::Encoder("conversion stopped".into())))
+}
+
+fn encode(input: &Path, output: &Path, format: Format, bitrate: u32) -> Result<()> {
+    if output.exists() {
+        return Err(ConvertError::Exists(output.to_path_buf()));
+    }
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-nostdin", "-n", "-loglevel", "error", "-i"])
+        .arg(input)
+        .args(["-map", "0:a", "-map_metadata", "0", "-c:a", format.codec()]);
+    if !format.is_lossless() {
+        command.args(["-b:a", &format!("{bitrate}k")]);
+    }
+    run(command.arg(output), output)
+}
+
+/// Writes the part of `input` between `start_ms` and `end_ms` to `output` as FLAC, replacing the
+/// tags of `input` with `tags`.
+pub fn extract(
+    input: &Path,
+    output: &Path,
+    start_ms: u64,
+    end_ms: u64,
+    tags: &[(&str, &str)],
+) -> Result<()> {
+    if output.exists() {
+        return Err(ConvertError::Exists(output.to_path_buf()));
+    }
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let seconds = |ms: u64| format!("{}.{:03}", ms / 1000, ms % 1000);
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-nostdin", "-n", "-loglevel", "error", "-i"])
+        .arg(input)
+        .args(["-ss", &seconds(start_ms), "-to", &seconds(end_ms)])
+        .args(["-map", "0:a", "-map_metadata", "-1", "-c:a", "flac"]);
+    for (key, value) in tags {
+        command.arg("-metadata").arg(format!("{key}={value}"));
+    }
+    run(command.arg(output), output)
+}
+
+/// Runs an ffmpeg `command` writing to `output`, which is removed again when it fails.
+pub fn run(command: &mut Command, output: &Path) -> Result<()> {
+    let result = command.output();
+    let result = match result {
+        Err(e) if e.kind() == ErrorKind::NotFound => return Err(ConvertError::MissingEncoder),
+        result => result?,
+    };
+    if !result.status.success() {
+        // a partly written file would block the next attempt
+        let _ = std::fs::remove_file(output);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        return Err(ConvertError::Encoder(
+            stderr.lines().last().unwrap_or("unknown error").to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_path() {
+        let playable = Playable {
+            id: 1,
+            title: "Why? / Live v1.2".to_string(),
+            artist_name: Some("Bronski Beat".to_string()),
+            duration: 100,
+            source_url: "/music/why.flac".to_string(),
+            ..Default::default()
+        };
+        let mut options = ConvertOptions {
+            format: Format::Aac,
+            bitrate: DEFAULT_BITRATE,
+            folder: PathBuf::from("/converted"),
+            template: DEFAULT_TEMPLATE.to_string(),
+            replace: false,
+        };
+        assert_eq!(
+            output_path(&options, &playable),
+            PathBuf::from("/converted/Bronski Beat/Unknown/Why_ _ Live v1.2.m4a")
+        );
+
+        options.template = "{genre} - {title}".to_string();
+        options.format = Format::Opus;
+        assert_eq!(
+            output_path(&options, &playable),
+            PathBuf::from("/converted/Unknown - Why_ _ Live v1.2.opus")
+        );
+    }
+}
@@ -0,0 +1,231 @@
+// Checks run on launch and from the Diagnostics settings, so that a library kept in memory, a
+// missing output device or an unreachable service shows up with what may fix it instead of
+// passing unnoticed.
+use std::{path::PathBuf, time::Duration};
+
+use rodio::cpal::traits::{DeviceTrait, HostTrait};
+
+use crate::{app_state::Section, discogs, storage::Podcast};
+
+// how long a service has to answer before it counts as unreachable
+const NETWORK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    // works, though not fully, e.g. a service which does not answer
+    Warning,
+    // a fallback is used, e.g. a library which is not saved
+    Error,
+}
+
+/// What one check found, a problem comes with what may fix it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub check: String,
+    pub status: Status,
+    pub detail: String,
+    pub fix: Option<String>,
+    // the settings the fix is made in
+    pub settings: Option<Section>,
+}
+
+impl Finding {
+    fn ok(check: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            check: check.into(),
+            status: Status::Ok,
+            detail: detail.into(),
+            fix: None,
+            settings: None,
+        }
+    }
+
+    fn problem(
+        check: impl Into<String>,
+        status: Status,
+        detail: impl Into<String>,
+        fix: impl Into<String>,
+    ) -> Self {
+        Self {
+            check: check.into(),
+            status,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+            settings: None,
+        }
+    }
+
+    fn settings(self, section: Section) -> Self {
+        Self {
+            settings: Some(section),
+            ..self
+        }
+    }
+}
+
+/// Whether the database file opened, `problem` is why it did not and the library is kept in
+/// memory instead.
+pub fn check_storage(problem: Option<&str>) -> Finding {
+    match problem {
+        None => Finding::ok(
+            "Database",
+            "The library is saved in music.db of the music folder",
+        ),
+        Some(problem) => Finding::problem(
+            "Database",
+            Status::Error,
+            format!("The library is kept in memory and lost on quit: {problem}"),
+            "Check that the music folder exists and music.db can be written to, then restart",
+        ),
+    }
+}
+
+/// Whether there is an output device to play on.
+pub fn check_audio() -> Finding {
+    let Some(device) = rodio::cpal::default_host().default_output_device() else {
+        return Finding::problem(
+            "Audio output",
+            Status::Error,
+            "No output device was found",
+            "Connect or enable speakers or headphones, playback starts once there is one",
+        );
+    };
+    let name = device
+        .name()
+        .unwrap_or_else(|_| "Unnamed device".to_string());
+    match device.default_output_config() {
+        Ok(config) => Finding::ok(
+            "Audio output",
+            format!("{name}, {} Hz", config.sample_rate().0),
+        ),
+        Err(error) => Finding::problem(
+            "Audio output",
+            Status::Error,
+            format!("{name} can not be opened: {error}"),
+            "Close the applications holding the device exclusively or pick another one in the \
+             system settings",
+        ),
+    }
+}
+
+/// One finding per watched folder which is not there, e.g. on a drive which is not mounted.
+pub fn check_watch_folders(folders: &[PathBuf]) -> Vec<Finding> {
+    let missing: Vec<Finding> = folders
+        .iter()
+        .filter(|folder| !folder.is_dir())
+        .map(|folder| {
+            Finding::problem(
+                "Watched folder",
+                Status::Warning,
+                format!(
+                    "{} is not there, its new files are not imported",
+                    folder.display()
+                ),
+                "Mount the drive it is on, or stop watching it",
+            )
+            .settings(Section::ScannerSettings)
+        })
+        .collect();
+    if missing.is_empty() {
+        vec![Finding::ok(
+            "Watched folders",
+            format!("{} folders, all of them there", folders.len()),
+        )]
+    } else {
+        missing
+    }
+}
+
+/// The services the integrations talk to, by name and address: Discogs and the host of each
+/// podcast feed.
+pub fn services(podcasts: &[Podcast]) -> Vec<(String, String)> {
+    let mut services = vec![("Discogs".to_string(), discogs::API_URL.to_string())];
+    for podcast in podcasts {
+        let Ok(url) = reqwest::Url::parse(&podcast.feed_url) else {
+            continue;
+        };
+        let Some(host) = url.host_str() else {
+            continue;
+        };
+        let address = format!("{}://{host}", url.scheme());
+        if services.iter().all(|(_, known)| *known != address) {
+            services.push((podcast.title.clone(), address));
+        }
+    }
+    services
+}
+
+/// Whether each service answers, whatever it answers.
+pub async fn check_network(services: Vec<(String, String)>) -> Vec<Finding> {
+    let client = match reqwest::Client::builder().timeout(NETWORK_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(error) => {
+            return vec![Finding::problem(
+                "Network",
+                Status::Warning,
+                error.to_string(),
+                "Restart the application",
+            )];
+        }
+    };
+    let mut findings = Vec::with_capacity(services.len());
+    for (name, address) in services {
+        let check = format!("Network: {name}");
+        findings.push(match client.head(&address).send().await {
+            Ok(_) => Finding::ok(check, format!("{address} answers")),
+            Err(error) => Finding::problem(
+                check,
+                Status::Warning,
+                format!("{address} does not answer: {error}"),
+                "Check the internet connection, a proxy or firewall may be in the way",
+            ),
+        });
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_services() {
+        let podcast = |id: i64, feed_url: &str| Podcast {
+            id,
+            title: format!("Podcast {id}"),
+            feed_url: feed_url.to_string(),
+        };
+        let services = services(&[
+            podcast(1, "https://feeds.example.com/one.xml"),
+            podcast(2, "https://feeds.example.com/two.xml"),
+            podcast(3, "not a url"),
+            podcast(4, "http://other.example.org/feed"),
+        ]);
+        let addresses: Vec<&str> = services
+            .iter()
+            .map(|(_, address)| address.as_str())
+            .collect();
+        assert_eq!(
+            addresses,
+            [
+                discogs::API_URL,
+                "https://feeds.example.com",
+                "http://other.example.org"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_watch_folders() {
+        let findings = check_watch_folders(&[std::env::temp_dir()]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].status, Status::Ok);
+
+        let missing = std::env::temp_dir().join("phonique-missing-folder");
+        let findings = check_watch_folders(&[std::env::temp_dir(), missing]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].status, Status::Warning);
+        assert_eq!(findings[0].settings, Some(Section::ScannerSettings));
+    }
+}
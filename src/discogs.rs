@@ -0,0 +1,855 @@
+// Release data from the public Discogs API, which answers without a token for releases. Searching
+// the database needs a personal access token, or the tokens of an OAuth connection.
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    chapters::Chapter,
+    storage::{WatchKind, WatchReleaseDescriptor},
+    util::unix_now,
+};
+
+pub const API_URL: &str = "https://api.discogs.com";
+const AUTHORIZE_URL: &str = "https://www.discogs.com/oauth/authorize";
+// Discogs refuses requests without a user agent naming the application
+const USER_AGENT: &str = concat!("Phoniq/", env!("CARGO_PKG_VERSION"));
+// Discogs serves no more than this many results of one search
+const MAX_SEARCH_RESULTS: u64 = 10_000;
+// label releases come in no particular order, all of them are read up to this many pages
+const MAX_LABEL_PAGES: u64 = 10;
+
+#[derive(Debug, Error)]
+pub enum DiscogsError {
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Invalid response: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Not a Discogs release: {0}")]
+    InvalidRelease(String),
+    #[error("No release matches the filters")]
+    NoResults,
+    #[error("Not a Discogs label or artist: {0}")]
+    InvalidWatch(String),
+    #[error("Discogs sent no token: {0}")]
+    InvalidToken(String),
+}
+
+pub type Result<T> = std::result::Result<T, DiscogsError>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Release {
+    pub id: u64,
+    pub title: String,
+    #[serde(default)]
+    pub artists: Vec<Artist>,
+    #[serde(default)]
+    pub tracklist: Vec<Track>,
+    // 0 when unknown
+    #[serde(default)]
+    pub year: u32,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub styles: Vec<String>,
+    // mostly YouTube videos of the tracks, uploaded by Discogs users
+    #[serde(default)]
+    pub videos: Vec<Video>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Artist {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Track {
+    // `A1`, `B2` and the like on records
+    #[serde(default)]
+    pub position: String,
+    pub title: String,
+    // `track`, or `heading` and `index` for the lines grouping tracks
+    #[serde(default, rename = "type_")]
+    pub kind: String,
+    // `4:05` or `1:02:30`, empty when the tracklist leaves it out
+    #[serde(default)]
+    pub duration: String,
+}
+
+impl Track {
+    /// The length in seconds, None when unknown.
+    pub fn seconds(&self) -> Option<u64> {
+        self.duration.trim().split(':').try_fold(0, |total, part| {
+            Some(total * 60 + part.parse::<u64>().ok()?)
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Video {
+    pub uri: String,
+    #[serde(default)]
+    pub title: String,
+}
+
+/// The key and secret of an application registered with Discogs, which OAuth tokens are issued
+/// to. A build may come with its own, otherwise they are made under Developers in the Discogs
+/// settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Consumer {
+    pub key: String,
+    pub secret: String,
+}
+
+impl Consumer {
+    pub fn bundled() -> Option<Self> {
+        Some(Self {
+            key: option_env!("PHONIQ_DISCOGS_CONSUMER_KEY")?.to_string(),
+            secret: option_env!("PHONIQ_DISCOGS_CONSUMER_SECRET")?.to_string(),
+        })
+    }
+}
+
+/// A token with its secret, either the temporary one to authorize or the one to keep.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OAuthToken {
+    pub token: String,
+    pub secret: String,
+}
+
+impl OAuthToken {
+    /// The page where the user allows the application and is shown the verifier.
+    pub fn authorize_url(&self) -> String {
+        format!(
+            "{AUTHORIZE_URL}?oauth_token={}",
+            urlencoding::encode(&self.token)
+        )
+    }
+}
+
+/// An OAuth connection, the access token only works with the application it was issued to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OAuthTokens {
+    pub consumer: Consumer,
+    pub access: OAuthToken,
+}
+
+/// What searches are signed with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Credentials {
+    Token(String),
+    OAuth(OAuthTokens),
+}
+
+impl Credentials {
+    fn authorization(&self) -> String {
+        match self {
+            Credentials::Token(token) => format!("Discogs token={token}"),
+            Credentials::OAuth(tokens) => oauth_header(
+                &tokens.consumer,
+                Some(&tokens.access),
+                &[],
+                &nonce(),
+                unix_now(),
+            ),
+        }
+    }
+}
+
+/// The `Authorization` header of an OAuth 1.0a request. It is signed with PLAINTEXT, the
+/// secrets themselves, which Discogs accepts since every request goes over HTTPS.
+fn oauth_header(
+    consumer: &Consumer,
+    token: Option<&OAuthToken>,
+    extra: &[(&str, &str)],
+    nonce: &str,
+    timestamp: i64,
+) -> String {
+    let encode = |value: &str| urlencoding::encode(value).into_owned();
+    let signature = format!(
+        "{}&{}",
+        encode(&consumer.secret),
+        token.map(|token| encode(&token.secret)).unwrap_or_default()
+    );
+    let timestamp = timestamp.to_string();
+    let mut params = vec![
+        ("oauth_consumer_key", consumer.key.as_str()),
+        ("oauth_nonce", nonce),
+        ("oauth_signature", signature.as_str()),
+        ("oauth_signature_method", "PLAINTEXT"),
+        ("oauth_timestamp", timestamp.as_str()),
+    ];
+    if let Some(token) = token {
+        params.push(("oauth_token", token.token.as_str()));
+    }
+    params.extend_from_slice(extra);
+    params.push(("oauth_version", "1.0"));
+    let params: Vec<String> = params
+        .into_iter()
+        .map(|(key, value)| format!("{key}=\"{}\"", encode(value)))
+        .collect();
+    format!("OAuth {}", params.join(", "))
+}
+
+fn nonce() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+/// The token in the form encoded answer of the token endpoints.
+fn parse_token(body: &str) -> Result<OAuthToken> {
+    let value = |name: &str| {
+        body.trim().split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=')?;
+            if key != name {
+                return None;
+            }
+            urlencoding::decode(value)
+                .ok()
+                .map(|value| value.into_owned())
+        })
+    };
+    match (value("oauth_token"), value("oauth_token_secret")) {
+        (Some(token), Some(secret)) if !token.is_empty() => Ok(OAuthToken { token, secret }),
+        _ => Err(DiscogsError::InvalidToken(body.trim().to_string())),
+    }
+}
+
+async fn token_request(url: String, authorization: String, post: bool) -> Result<OAuthToken> {
+    let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
+    let request = if post {
+        client.post(url)
+    } else {
+        client.get(url)
+    };
+    let body = request
+        .header("Authorization", authorization)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_token(&body)
+}
+
+/// The first step of connecting: a temporary token for the user to authorize. Without a web
+/// page to come back to, Discogs shows the verifier for the user to copy.
+pub async fn request_token(consumer: Consumer) -> Result<OAuthToken> {
+    let authorization = oauth_header(
+        &consumer,
+        None,
+        &[("oauth_callback", "oob")],
+        &nonce(),
+        unix_now(),
+    );
+    token_request(
+        format!("{API_URL}/oauth/request_token"),
+        authorization,
+        false,
+    )
+    .await
+}
+
+/// Trades the authorized temporary token and its verifier for the token to keep.
+pub async fn access_token(
+    consumer: Consumer,
+    request: OAuthToken,
+    verifier: String,
+) -> Result<OAuthTokens> {
+    let authorization = oauth_header(
+        &consumer,
+        Some(&request),
+        &[("oauth_verifier", verifier.trim())],
+        &nonce(),
+        unix_now(),
+    );
+    let access =
+        token_request(format!("{API_URL}/oauth/access_token"), authorization, true).await?;
+    Ok(OAuthTokens { consumer, access })
+}
+
+/// What a random release is picked from, empty fields match everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DigFilters {
+    pub genre: String,
+    pub style: String,
+    pub year_from: Option<u32>,
+    pub year_to: Option<u32>,
+    pub country: String,
+}
+
+impl DigFilters {
+    /// The query searching for releases of `year`, `page` is 1-based with one result per page.
+    pub fn query(&self, year: Option<u32>, page: u64) -> String {
+        let mut query = format!("type=release&per_page=1&page={page}");
+        for (key, value) in [
+            ("genre", &self.genre),
+            ("style", &self.style),
+            ("country", &self.country),
+        ] {
+            if !value.trim().is_empty() {
+                query.push_str(&format!("&{key}={}", urlencoding::encode(value.trim())));
+            }
+        }
+        if let Some(year) = year {
+            query.push_str(&format!("&year={year}"));
+        }
+        query
+    }
+
+    /// The years to pick from, None when no year is set. A single bound is a single year.
+    pub fn years(&self) -> Option<(u32, u32)> {
+        match (self.year_from, self.year_to) {
+            (Some(from), Some(to)) => Some((from.min(to), from.max(to))),
+            (Some(year), None) | (None, Some(year)) => Some((year, year)),
+            (None, None) => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchPage {
+    pagination: Pagination,
+    #[serde(default)]
+    results: Vec<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Pagination {
+    items: u64,
+    #[serde(default)]
+    pages: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResult {
+    id: u64,
+    #[serde(default)]
+    genre: Vec<String>,
+    #[serde(default)]
+    style: Vec<String>,
+    // the large image of the release, empty when it has none
+    #[serde(default)]
+    cover_image: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Named {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleasesPage {
+    pagination: Pagination,
+    #[serde(default)]
+    releases: Vec<ListedRelease>,
+}
+
+/// A release in the list of a label or artist. Artists list masters, which stand for all
+/// versions of a release, next to single releases.
+#[derive(Debug, Deserialize)]
+struct ListedRelease {
+    id: u64,
+    title: String,
+    #[serde(default)]
+    artist: String,
+    #[serde(default)]
+    year: u32,
+    #[serde(default, rename = "type")]
+    kind: String,
+    // the release standing for a master
+    #[serde(default)]
+    main_release: Option<u64>,
+    // `Main` for the artist's own releases, otherwise e.g. `Appearance` or `Remix`
+    #[serde(default)]
+    role: Option<String>,
+}
+
+impl ListedRelease {
+    fn descriptor(self) -> Option<WatchReleaseDescriptor> {
+        if self.role.as_deref().is_some_and(|role| role != "Main") {
+            return None;
+        }
+        let release_id = if self.kind == "master" {
+            self.main_release?
+        } else {
+            self.id
+        };
+        Some(WatchReleaseDescriptor {
+            release_id: release_id as i64,
+            title: self.title,
+            artist: self.artist,
+            year: self.year,
+        })
+    }
+}
+
+impl Release {
+    pub fn url(&self) -> String {
+        format!("https://www.discogs.com/release/{}", self.id)
+    }
+
+    /// The artists as credited, without the `(2)` Discogs appends to tell same named ones apart.
+    pub fn artist(&self) -> String {
+        self.artists
+            .iter()
+            .map(|artist| match artist.name.rsplit_once(" (") {
+                Some((name, number))
+                    if number.ends_with(')')
+                        && number[..number.len() - 1]
+                            .chars()
+                            .all(|c| c.is_ascii_digit()) =>
+                {
+                    name
+                }
+                _ => artist.name.as_str(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The tracks of one side, e.g. `A`, or every track when `side` is empty.
+    pub fn side(&self, side: &str) -> Vec<&Track> {
+        let side = side.trim().to_uppercase();
+        self.tracklist
+            .iter()
+            .filter(|track| track.kind.is_empty() || track.kind == "track")
+            .filter(|track| side.is_empty() || track.position.to_uppercase().starts_with(&side))
+            .collect()
+    }
+
+    /// Markers for the release played as one long track, e.g. a whole album in one stream. Each
+    /// track starts where the lengths of those before it add up to, the tracks following one of
+    /// unknown length cannot be placed and are left out.
+    pub fn chapters(&self) -> Vec<Chapter> {
+        let mut chapters = Vec::new();
+        let mut start = 0;
+        for track in self.side("") {
+            let title = if track.position.trim().is_empty() {
+                track.title.clone()
+            } else {
+                format!("{} {}", track.position.trim(), track.title)
+            };
+            chapters.push(Chapter { title, start });
+            let Some(seconds) = track.seconds() else {
+                break;
+            };
+            start += seconds;
+        }
+        chapters
+    }
+}
+
+/// The release id in a Discogs URL, in `[r123]` or on its own.
+pub fn release_id(input: &str) -> Option<u64> {
+    let input = input.trim();
+    if let Some(id) = input
+        .strip_prefix("[r")
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        return id.parse().ok();
+    }
+    if let Ok(id) = input.parse() {
+        return Some(id);
+    }
+    // https://www.discogs.com/release/123-Artist-Title
+    let (_, rest) = input.split_once("/release/")?;
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// The label or artist in a Discogs URL, or in `[l123]` or `[a123]`.
+pub fn watch_target(input: &str) -> Option<(WatchKind, u64)> {
+    let input = input.trim();
+    for (prefix, kind) in [("[l", WatchKind::Label), ("[a", WatchKind::Artist)] {
+        if let Some(id) = input
+            .strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            return Some((kind, id.parse().ok()?));
+        }
+    }
+    // https://www.discogs.com/label/23528-Warp-Records
+    for (path, kind) in [
+        ("/label/", WatchKind::Label),
+        ("/artist/", WatchKind::Artist),
+    ] {
+        if let Some((_, rest)) = input.split_once(path) {
+            let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+            return Some((kind, digits.parse().ok()?));
+        }
+    }
+    None
+}
+
+fn watch_url(kind: WatchKind, id: u64) -> String {
+    match kind {
+        WatchKind::Label => format!("{API_URL}/labels/{id}"),
+        WatchKind::Artist => format!("{API_URL}/artists/{id}"),
+    }
+}
+
+async fn get<T: serde::de::DeserializeOwned>(url: String) -> Result<T> {
+    let body = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .build()?
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// The kind, id and name of the label or artist `input` points at.
+pub async fn fetch_watch(input: String) -> Result<(WatchKind, u64, String)> {
+    let (kind, id) = watch_target(&input).ok_or(DiscogsError::InvalidWatch(input))?;
+    let named: Named = get(watch_url(kind, id)).await?;
+    Ok((kind, id, named.name))
+}
+
+/// What a label or artist has released. Artists are listed newest first and one page is
+/// enough, labels are read whole.
+pub async fn fetch_watch_releases(kind: WatchKind, id: u64) -> Result<Vec<WatchReleaseDescriptor>> {
+    let mut releases = Vec::new();
+    let mut page = 1;
+    loop {
+        let url = match kind {
+            WatchKind::Label => {
+                format!("{}/releases?per_page=100&page={page}", watch_url(kind, id))
+            }
+            WatchKind::Artist => format!(
+                "{}/releases?sort=year&sort_order=desc&per_page=100",
+                watch_url(kind, id)
+            ),
+        };
+        let listed: ReleasesPage = get(url).await?;
+        releases.extend(
+            listed
+                .releases
+                .into_iter()
+                .filter_map(ListedRelease::descriptor),
+        );
+        if kind == WatchKind::Artist || page >= listed.pagination.pages.min(MAX_LABEL_PAGES) {
+            return Ok(releases);
+        }
+        page += 1;
+    }
+}
+
+pub async fn fetch_release(input: String) -> Result<Release> {
+    let id = release_id(&input).ok_or(DiscogsError::InvalidRelease(input))?;
+    get(format!("{API_URL}/releases/{id}")).await
+}
+
+async fn search(credentials: &Credentials, query: &str) -> Result<SearchPage> {
+    let body = reqwest::Client::builder()
+        .user_agent(USER_AGENT)
+        .build()?
+        .get(format!("{API_URL}/database/search?{query}"))
+        .header("Authorization", credentials.authorization())
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+/// The query searching for the releases of `artist` titled `release`.
+fn release_query(artist: &str, release: &str) -> String {
+    format!(
+        "type=release&per_page=5&artist={}&release_title={}",
+        urlencoding::encode(artist.trim()),
+        urlencoding::encode(release.trim())
+    )
+}
+
+// the genres of the releases found, the most common first, then their styles
+fn ranked_genres(results: &[SearchResult]) -> Vec<String> {
+    let mut ranked: Vec<(String, usize)> = Vec::new();
+    for name in results.iter().flat_map(|result| &result.genre) {
+        match ranked.iter_mut().find(|(genre, _)| genre == name) {
+            Some((_, count)) => *count += 1,
+            None => ranked.push((name.clone(), 1)),
+        }
+    }
+    // stable, equally common genres stay in the order Discogs ranked their releases
+    ranked.sort_by(|a, b| b.1.cmp(&a.1));
+    let mut genres: Vec<String> = ranked.into_iter().map(|(genre, _)| genre).collect();
+    for style in results.iter().flat_map(|result| &result.style) {
+        if !genres.contains(style) {
+            genres.push(style.clone());
+        }
+    }
+    genres
+}
+
+/// The genres Discogs gives the releases of `artist` titled `release`, best first, empty when
+/// none matches.
+pub async fn search_genres(
+    credentials: &Credentials,
+    artist: &str,
+    release: &str,
+) -> Result<Vec<String>> {
+    let page = search(credentials, &release_query(artist, release)).await?;
+    Ok(ranked_genres(&page.results))
+}
+
+/// The address of the cover of the best match among the releases of `artist` titled
+/// `release`, None when no match has one.
+pub async fn search_cover(
+    credentials: &Credentials,
+    artist: &str,
+    release: &str,
+) -> Result<Option<String>> {
+    let page = search(credentials, &release_query(artist, release)).await?;
+    Ok(page
+        .results
+        .into_iter()
+        .map(|result| result.cover_image)
+        // Discogs hands out a placeholder to those without access to the images
+        .find(|cover| !cover.is_empty() && !cover.ends_with("spacer.gif")))
+}
+
+/// A release picked at random from those matching `filters`. A year range is narrowed to one
+/// year at random first, the search takes a single year.
+pub async fn random_release(filters: DigFilters, credentials: Credentials) -> Result<Release> {
+    let year = filters
+        .years()
+        .map(|(from, to)| rand::random_range(from..=to));
+    let items = search(&credentials, &filters.query(year, 1))
+        .await?
+        .pagination
+        .items
+        .min(MAX_SEARCH_RESULTS);
+    if items == 0 {
+        return Err(DiscogsError::NoResults);
+    }
+    let page = rand::random_range(1..=items);
+    let id = search(&credentials, &filters.query(year, page))
+        .await?
+        .results
+        .first()
+        .ok_or(DiscogsError::NoResults)?
+        .id;
+    fetch_release(id.to_string()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_release_id() {
+        assert_eq!(release_id("123"), Some(123));
+        assert_eq!(release_id("[r4567]"), Some(4567));
+        assert_eq!(
+            release_id(
+                "https://www.discogs.com/release/249504-Rick-Astley-Never-Gonna-Give-You-Up"
+            ),
+            Some(249504)
+        );
+        assert_eq!(release_id("https://www.discogs.com/master/96559"), None);
+        assert_eq!(release_id(""), None);
+    }
+
+    #[test]
+    fn test_release() {
+        let release: Release = serde_json::from_str(
+            r#"{
+                "id": 1,
+                "title": "Side Effects",
+                "artists": [{"name": "Alpha (2)"}, {"name": "Omega"}],
+                "tracklist": [
+                    {"position": "", "title": "Part One", "type_": "heading"},
+                    {"position": "A1", "title": "First", "type_": "track"},
+                    {"position": "A2", "title": "Second", "type_": "track"},
+                    {"position": "B1", "title": "Third", "type_": "track"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(release.artist(), "Alpha, Omega");
+        let titles = |side| -> Vec<String> {
+            release
+                .side(side)
+                .iter()
+                .map(|track| track.title.clone())
+                .collect()
+        };
+        assert_eq!(titles("a"), ["First", "Second"]);
+        assert_eq!(titles("B"), ["Third"]);
+        assert_eq!(titles(""), ["First", "Second", "Third"]);
+        assert_eq!(release.year, 0);
+        assert!(release.videos.is_empty());
+    }
+
+    #[test]
+    fn test_chapters() {
+        let release: Release = serde_json::from_str(
+            r#"{
+                "id": 1,
+                "title": "Live at the Hall",
+                "tracklist": [
+                    {"position": "1", "title": "Intro", "duration": "1:30"},
+                    {"position": "", "title": "Set", "type_": "heading"},
+                    {"position": "2", "title": "Long One", "duration": "1:02:05"},
+                    {"position": "3", "title": "Unknown", "duration": ""},
+                    {"position": "4", "title": "Encore", "duration": "5:00"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let chapter = |title: &str, start| Chapter {
+            title: title.to_string(),
+            start,
+        };
+        assert_eq!(
+            release.chapters(),
+            [
+                chapter("1 Intro", 0),
+                chapter("2 Long One", 90),
+                chapter("3 Unknown", 3815),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_oauth_header() {
+        let consumer = Consumer {
+            key: "key".to_string(),
+            secret: "s&cret".to_string(),
+        };
+        assert_eq!(
+            oauth_header(&consumer, None, &[("oauth_callback", "oob")], "abc", 100),
+            "OAuth oauth_consumer_key=\"key\", oauth_nonce=\"abc\", \
+             oauth_signature=\"s%2526cret%26\", oauth_signature_method=\"PLAINTEXT\", \
+             oauth_timestamp=\"100\", oauth_callback=\"oob\", oauth_version=\"1.0\""
+        );
+        let token = OAuthToken {
+            token: "tok".to_string(),
+            secret: "sec".to_string(),
+        };
+        assert!(
+            oauth_header(&consumer, Some(&token), &[], "abc", 100)
+                .contains("oauth_signature=\"s%2526cret%26sec\", oauth_signature_method")
+        );
+        assert!(
+            oauth_header(&consumer, Some(&token), &[], "abc", 100).contains("oauth_token=\"tok\"")
+        );
+    }
+
+    #[test]
+    fn test_parse_token() {
+        assert_eq!(
+            parse_token("oauth_token=abc&oauth_token_secret=d%2Fe&oauth_callback_confirmed=true")
+                .unwrap(),
+            OAuthToken {
+                token: "abc".to_string(),
+                secret: "d/e".to_string(),
+            }
+        );
+        assert!(matches!(
+            parse_token("Invalid consumer."),
+            Err(DiscogsError::InvalidToken(_))
+        ));
+        assert_eq!(
+            OAuthToken {
+                token: "a b".to_string(),
+                secret: String::new(),
+            }
+            .authorize_url(),
+            "https://www.discogs.com/oauth/authorize?oauth_token=a%20b"
+        );
+    }
+
+    #[test]
+    fn test_watch_target() {
+        assert_eq!(
+            watch_target("https://www.discogs.com/label/23528-Warp-Records"),
+            Some((WatchKind::Label, 23528))
+        );
+        assert_eq!(
+            watch_target("https://www.discogs.com/artist/45-Aphex-Twin"),
+            Some((WatchKind::Artist, 45))
+        );
+        assert_eq!(watch_target("[l1818]"), Some((WatchKind::Label, 1818)));
+        assert_eq!(watch_target("[a45]"), Some((WatchKind::Artist, 45)));
+        assert_eq!(watch_target("https://www.discogs.com/release/1"), None);
+        assert_eq!(watch_target("45"), None);
+    }
+
+    #[test]
+    fn test_listed_releases() {
+        let page: ReleasesPage = serde_json::from_str(
+            r#"{
+                "pagination": {"items": 3, "pages": 1},
+                "releases": [
+                    {"id": 10, "title": "Drukqs", "artist": "Aphex Twin", "year": 2001,
+                     "type": "master", "main_release": 11, "role": "Main"},
+                    {"id": 20, "title": "Compilation", "type": "release", "role": "Appearance"},
+                    {"id": 30, "title": "On Label", "artist": "Someone", "year": 2024}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let ids: Vec<i64> = page
+            .releases
+            .into_iter()
+            .filter_map(ListedRelease::descriptor)
+            .map(|release| release.release_id)
+            .collect();
+        assert_eq!(ids, [11, 30]);
+    }
+
+    #[test]
+    fn test_dig_filters() {
+        let mut filters = DigFilters {
+            genre: "Electronic".to_string(),
+            style: "Deep House".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            filters.query(None, 3),
+            "type=release&per_page=1&page=3&genre=Electronic&style=Deep%20House"
+        );
+        assert_eq!(filters.years(), None);
+        filters.year_to = Some(1995);
+        assert_eq!(filters.years(), Some((1995, 1995)));
+        filters.year_from = Some(1999);
+        assert_eq!(filters.years(), Some((1995, 1999)));
+        filters.country = "UK".to_string();
+        assert!(
+            filters
+                .query(Some(1996), 1)
+                .ends_with("&country=UK&year=1996")
+        );
+    }
+
+    #[test]
+    fn test_ranked_genres() {
+        let page: SearchPage = serde_json::from_str(
+            r#"{
+                "pagination": {"items": 3, "pages": 1},
+                "results": [
+                    {"id": 1, "genre": ["Jazz"], "style": ["Fusion"]},
+                    {"id": 2, "genre": ["Electronic", "Jazz"], "style": ["Downtempo", "Fusion"]},
+                    {"id": 3}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            ranked_genres(&page.results),
+            ["Jazz", "Electronic", "Fusion", "Downtempo"]
+        );
+        assert!(ranked_genres(&[]).is_empty());
+        assert_eq!(
+            release_query(" Miles Davis ", "Kind of Blue"),
+            "type=release&per_page=5&artist=Miles%20Davis&release_title=Kind%20of%20Blue"
+        );
+    }
+}
@@ -0,0 +1,359 @@
+// A ten band equalizer in the audio pipeline, one peaking filter per band an octave apart. The
+// curve is kept per output device, headphones and speakers rarely want the same one, and
+// changes while a track plays.
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicI32, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{Source, source::SeekError};
+use serde::{Deserialize, Serialize};
+
+// centre frequencies of the bands in Hz
+pub const BANDS: [f32; 10] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+pub const MAX_GAIN_DB: i32 = 12;
+// the width of a band, about an octave
+const Q: f32 = 1.41;
+
+/// The gain of each band in dB.
+pub type Curve = [i32; 10];
+
+pub const FLAT: Curve = [0; 10];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    Flat,
+    BassBoost,
+    Vocal,
+    // the curve set by hand
+    Custom,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 4] = [
+        Preset::Flat,
+        Preset::BassBoost,
+        Preset::Vocal,
+        Preset::Custom,
+    ];
+
+    // None for the curve set by hand
+    pub fn curve(self) -> Option<Curve> {
+        match self {
+            Preset::Flat => Some(FLAT),
+            Preset::BassBoost => Some([6, 5, 4, 2, 0, 0, 0, 0, 0, 0]),
+            Preset::Vocal => Some([-2, -2, -1, 0, 2, 4, 4, 3, 1, 0]),
+            Preset::Custom => None,
+        }
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Preset::Flat => f.write_str("Flat"),
+            Preset::BassBoost => f.write_str("Bass Boost"),
+            Preset::Vocal => f.write_str("Vocal"),
+            Preset::Custom => f.write_str("Custom"),
+        }
+    }
+}
+
+// the curve playing on an output and the one last set by hand on it, kept while a preset plays
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct OutputCurve {
+    output: String,
+    curve: Curve,
+    custom: Curve,
+}
+
+/// The curves of the outputs, kept in one setting as JSON. Outputs without one play flat.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EqCurves {
+    outputs: Vec<OutputCurve>,
+}
+
+impl EqCurves {
+    pub fn parse(value: &str) -> Self {
+        let mut outputs: Vec<OutputCurve> = serde_json::from_str(value).unwrap_or_default();
+        for output in &mut outputs {
+            for db in output.curve.iter_mut().chain(output.custom.iter_mut()) {
+                *db = (*db).clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+            }
+        }
+        Self { outputs }
+    }
+
+    pub fn to_setting(&self) -> String {
+        serde_json::to_string(&self.outputs).unwrap_or_default()
+    }
+
+    pub fn curve(&self, output: &str) -> Curve {
+        self.outputs
+            .iter()
+            .find(|curve| curve.output == output)
+            .map_or(FLAT, |curve| curve.curve)
+    }
+
+    /// The preset playing on `output`, Custom when its curve is none of them.
+    pub fn preset(&self, output: &str) -> Preset {
+        let curve = self.curve(output);
+        Preset::ALL
+            .into_iter()
+            .find(|preset| preset.curve() == Some(curve))
+            .unwrap_or(Preset::Custom)
+    }
+
+    fn entry(&mut self, output: &str) -> &mut OutputCurve {
+        let index = match self.outputs.iter().position(|c| c.output == output) {
+            Some(index) => index,
+            None => {
+                self.outputs.push(OutputCurve {
+                    output: output.to_string(),
+                    curve: FLAT,
+                    custom: FLAT,
+                });
+                self.outputs.len() - 1
+            }
+        };
+        &mut self.outputs[index]
+    }
+
+    /// Plays a preset on `output`, Custom goes back to the curve last set by hand.
+    pub fn set_preset(&mut self, output: &str, preset: Preset) {
+        let entry = self.entry(output);
+        entry.curve = preset.curve().unwrap_or(entry.custom);
+    }
+
+    /// Sets the gain of one band of `output`, the curve becomes its custom one.
+    pub fn set_band(&mut self, output: &str, band: usize, db: i32) {
+        let entry = self.entry(output);
+        if let Some(gain) = entry.curve.get_mut(band) {
+            *gain = db.clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+            entry.custom = entry.curve;
+        }
+    }
+}
+
+/// Shared by the worker and the tracks it plays, the curve changes while a track plays.
+#[derive(Debug, Default)]
+pub struct EqualizerShared {
+    gains: [AtomicI32; 10],
+    // counts the changes, the tracks set up their filters again after one
+    version: AtomicU64,
+}
+
+impl EqualizerShared {
+    pub fn set(&self, curve: &Curve) {
+        for (gain, db) in self.gains.iter().zip(curve) {
+            gain.store(*db, Ordering::Relaxed);
+        }
+        self.version.fetch_add(1, Ordering::Release);
+    }
+
+    fn curve(&self) -> Curve {
+        std::array::from_fn(|band| self.gains[band].load(Ordering::Relaxed))
+    }
+}
+
+// A peaking filter, in the transposed direct form II.
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    fn peaking(frequency: f32, gain_db: i32, sample_rate: f32) -> Self {
+        let a = 10f32.powf(gain_db as f32 / 40.0);
+        let w0 = 2.0 * std::f32::consts::PI * frequency / sample_rate;
+        let alpha = w0.sin() / (2.0 * Q);
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha / a;
+        Self {
+            b0: (1.0 + alpha * a) / a0,
+            b1: -2.0 * cos / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: -2.0 * cos / a0,
+            a2: (1.0 - alpha / a) / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+/// Runs the samples through the bands of the shared curve. A flat curve leaves them untouched,
+/// bit-perfect playback stays so.
+pub struct Equalizer<S> {
+    source: S,
+    shared: Arc<EqualizerShared>,
+    // the version of the curve the filters are set up for, None to set them up again
+    version: Option<u64>,
+    // the bands which are not flat for each channel
+    filters: Vec<Vec<Biquad>>,
+    // boosts are taken off the whole signal so they do not clip
+    preamp: f32,
+    // channel of the next sample
+    channel: usize,
+}
+
+impl<S> Equalizer<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(source: S, shared: Arc<EqualizerShared>) -> Self {
+        Self {
+            source,
+            shared,
+            version: None,
+            filters: Vec::new(),
+            preamp: 1.0,
+            channel: 0,
+        }
+    }
+
+    fn set_up(&mut self) {
+        let version = self.shared.version.load(Ordering::Acquire);
+        if self.version == Some(version) {
+            return;
+        }
+        self.version = Some(version);
+        let curve = self.shared.curve();
+        let sample_rate = self.source.sample_rate() as f32;
+        // bands near the top of the sample rate would not be stable
+        let bands: Vec<Biquad> = BANDS
+            .iter()
+            .zip(curve)
+            .filter(|(frequency, db)| *db != 0 && **frequency < sample_rate * 0.45)
+            .map(|(frequency, db)| Biquad::peaking(*frequency, db, sample_rate))
+            .collect();
+        let boost = curve.into_iter().max().unwrap_or(0).max(0);
+        self.preamp = 10f32.powf(-boost as f32 / 20.0);
+        self.filters = vec![bands; self.source.channels().max(1) as usize];
+    }
+}
+
+impl<S> Iterator for Equalizer<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        // the curve only changes between frames
+        if self.channel == 0 {
+            self.set_up();
+        }
+        let sample = self.source.next()?;
+        let channel = self.channel;
+        self.channel = (channel + 1) % self.filters.len().max(1);
+        let preamp = self.preamp;
+        match self.filters.get_mut(channel) {
+            Some(filters) if !filters.is_empty() => Some(
+                filters
+                    .iter_mut()
+                    .fold(sample * preamp, |x, filter| filter.process(x)),
+            ),
+            _ => Some(sample),
+        }
+    }
+}
+
+impl<S> Source for Equalizer<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.source.try_seek(pos)?;
+        // what the filters hold belongs to the old position
+        self.version = None;
+        self.channel = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    // the level of a second of a sine at `frequency` through `curve`, without the first tenth
+    fn level(curve: &Curve, frequency: f32) -> f32 {
+        let samples: Vec<f32> = (0..44100)
+            .map(|n| 0.5 * (2.0 * std::f32::consts::PI * frequency * n as f32 / 44100.0).sin())
+            .collect();
+        let shared = Arc::new(EqualizerShared::default());
+        shared.set(curve);
+        let played: Vec<f32> = Equalizer::new(SamplesBuffer::new(1, 44100, samples), shared)
+            .skip(4410)
+            .collect();
+        (played.iter().map(|s| s * s).sum::<f32>() / played.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_equalizer() {
+        let unfiltered = 0.5 / 2f32.sqrt();
+        assert!((level(&FLAT, 1000.0) - unfiltered).abs() < 1e-4);
+        let bass_boost = Preset::BassBoost.curve().unwrap();
+        assert!(level(&bass_boost, 62.0) > unfiltered * 1.05);
+        // the boost is taken off the bands left alone
+        assert!(level(&bass_boost, 4000.0) < unfiltered * 0.6);
+    }
+
+    #[test]
+    fn test_eq_curves() {
+        let mut curves = EqCurves::default();
+        assert_eq!(curves.curve("Speakers"), FLAT);
+        assert_eq!(curves.preset("Speakers"), Preset::Flat);
+
+        curves.set_band("Headphones", 0, 20);
+        let mut custom = FLAT;
+        custom[0] = MAX_GAIN_DB;
+        assert_eq!(curves.curve("Headphones"), custom);
+        assert_eq!(curves.preset("Headphones"), Preset::Custom);
+
+        curves.set_preset("Headphones", Preset::Vocal);
+        assert_eq!(curves.preset("Headphones"), Preset::Vocal);
+        curves.set_preset("Headphones", Preset::Custom);
+        assert_eq!(curves.curve("Headphones"), custom);
+        assert_eq!(curves.curve("Speakers"), FLAT);
+
+        assert_eq!(EqCurves::parse(&curves.to_setting()), curves);
+        assert_eq!(EqCurves::parse("not json"), EqCurves::default());
+    }
+}
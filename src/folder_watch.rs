@@ -0,0 +1,135 @@
+// Library folders watched for audio files coming and going. Each folder is listed again every
+// little while and compared with its last listing, which works the same on every platform and
+// on network shares, where change notifications are unreliable.
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::audio_scanner::{ScanOptions, ScannedFile, collect_files, scan_file};
+
+// the size and modification time of each audio file of a folder
+pub type Listing = HashMap<PathBuf, (u64, Option<SystemTime>)>;
+
+/// Lists the audio files of `folder` the way a scan would find them. None when the folder is
+/// gone, e.g. on a drive which is not mounted, its files are not taken for removed then.
+pub fn list(folder: &Path, options: &ScanOptions) -> Option<Listing> {
+    if !folder.is_dir() {
+        return None;
+    }
+    let listing = collect_files(folder, options)
+        .into_iter()
+        .filter_map(|path| {
+            let metadata = path.metadata().ok()?;
+            Some((path, (metadata.len(), metadata.modified().ok())))
+        })
+        .collect();
+    Some(listing)
+}
+
+/// Reads the tags of new files, like a scan of their folder would.
+pub async fn scan(paths: Vec<PathBuf>, options: ScanOptions) -> Vec<ScannedFile> {
+    paths
+        .iter()
+        .filter_map(|path| {
+            scan_file(path, &options)
+                .inspect_err(|e| log::error!("folder_watch: failed to scan {path:?}\n{e:?}"))
+                .ok()
+        })
+        .filter(|file| file.duration >= options.min_duration)
+        .collect()
+}
+
+/// What changed in a folder since it was last listed.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Changes {
+    // the first listing of the folder, every file in it is settled and the library catches up
+    pub first: bool,
+    // new or changed files which stayed the same since the last listing, i.e. done copying
+    pub settled: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+/// The last listing of each watched folder and the files in it waiting to settle.
+#[derive(Debug, Default)]
+pub struct FolderWatcher {
+    listings: HashMap<PathBuf, Listing>,
+    pending: HashMap<PathBuf, Listing>,
+}
+
+impl FolderWatcher {
+    pub fn update(&mut self, folder: &Path, listing: Listing) -> Changes {
+        let pending = self.pending.entry(folder.to_path_buf()).or_default();
+        let Some(before) = self.listings.insert(folder.to_path_buf(), listing.clone()) else {
+            return Changes {
+                first: true,
+                settled: listing.into_keys().collect(),
+                removed: Vec::new(),
+            };
+        };
+        let mut changes = Changes::default();
+        for (path, stamp) in &listing {
+            if before.get(path) != Some(stamp) {
+                pending.insert(path.clone(), *stamp);
+            } else if pending.get(path) == Some(stamp) {
+                pending.remove(path);
+                changes.settled.push(path.clone());
+            }
+        }
+        pending.retain(|path, _| listing.contains_key(path));
+        changes.removed = before
+            .into_keys()
+            .filter(|path| !listing.contains_key(path))
+            .collect();
+        changes.settled.sort();
+        changes.removed.sort();
+        changes
+    }
+
+    /// Forgets the folders no longer watched.
+    pub fn retain(&mut self, folders: &[PathBuf]) {
+        self.listings.retain(|folder, _| folders.contains(folder));
+        self.pending.retain(|folder, _| folders.contains(folder));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update() {
+        let folder = Path::new("/music");
+        let listing = |files: &[(&str, u64)]| -> Listing {
+            files
+                .iter()
+                .map(|(name, size)| (folder.join(name), (*size, None)))
+                .collect()
+        };
+        let mut watcher = FolderWatcher::default();
+        let changes = watcher.update(folder, listing(&[("a.mp3", 1)]));
+        assert!(changes.first);
+        assert_eq!(changes.settled, [folder.join("a.mp3")]);
+
+        // a file being copied settles once it stops growing
+        let changes = watcher.update(folder, listing(&[("a.mp3", 1), ("b.mp3", 1)]));
+        assert_eq!(changes, Changes::default());
+        let changes = watcher.update(folder, listing(&[("a.mp3", 1), ("b.mp3", 2)]));
+        assert_eq!(changes, Changes::default());
+        let changes = watcher.update(folder, listing(&[("a.mp3", 1), ("b.mp3", 2)]));
+        assert_eq!(changes.settled, [folder.join("b.mp3")]);
+        let changes = watcher.update(folder, listing(&[("b.mp3", 2)]));
+        assert_eq!(
+            changes,
+            Changes {
+                first: false,
+                settled: Vec::new(),
+                removed: vec![folder.join("a.mp3")],
+            }
+        );
+
+        watcher.retain(&[]);
+        assert!(watcher.update(folder, listing(&[])).first);
+    }
+}
@@ -0,0 +1,207 @@
+// Genres suggested for the tracks of the library without one, nothing is written before the
+// user accepts a suggestion. The library answers first: the tracks of an album, or else of an
+// artist, mostly share a genre. Tracks it knows nothing about are looked up on Discogs by
+// artist and album.
+use std::fmt;
+
+use crate::{
+    app_state::AudioPlayable,
+    discogs::{self, Credentials},
+    storage::Playable,
+};
+
+// Discogs allows 60 signed requests a minute, a review starts with at most this many lookups
+pub const MAX_LOOKUPS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Album,
+    Artist,
+    Discogs,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Album => f.write_str("Same album"),
+            Source::Artist => f.write_str("Same artist"),
+            Source::Discogs => f.write_str("Discogs"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    pub playable: Playable,
+    // best first, never empty
+    pub genres: Vec<String>,
+    pub source: Source,
+}
+
+pub fn has_genre(playable: &Playable) -> bool {
+    !playable.get_genre().trim().is_empty()
+}
+
+fn same(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+// the genres of `playables`, the most common first
+fn ranked<'a>(playables: impl Iterator<Item = &'a Playable>) -> Vec<String> {
+    let mut ranked: Vec<(&str, usize)> = Vec::new();
+    for genre in playables.map(|p| p.get_genre().trim()) {
+        match ranked.iter_mut().find(|(name, _)| same(name, genre)) {
+            Some((_, count)) => *count += 1,
+            None => ranked.push((genre, 1)),
+        }
+    }
+    ranked.sort_by(|a, b| b.1.cmp(&a.1));
+    ranked
+        .into_iter()
+        .map(|(genre, _)| genre.to_string())
+        .collect()
+}
+
+/// Suggestions for the tracks of `library` without a genre from the tracks sharing their album
+/// or artist, and the tracks left without a suggestion.
+pub fn from_library(library: &[Playable]) -> (Vec<Suggestion>, Vec<Playable>) {
+    let tagged: Vec<&Playable> = library.iter().filter(|p| has_genre(p)).collect();
+    let mut suggestions = Vec::new();
+    let mut unknown = Vec::new();
+    for playable in library.iter().filter(|p| !has_genre(p)) {
+        let (artist, album) = (playable.get_artist(), playable.get_album());
+        let by_album = ranked(tagged.iter().copied().filter(|p| {
+            !album.trim().is_empty() && same(p.get_album(), album) && same(p.get_artist(), artist)
+        }));
+        let by_artist = ranked(
+            tagged
+                .iter()
+                .copied()
+                .filter(|p| !artist.trim().is_empty() && same(p.get_artist(), artist)),
+        );
+        let suggestion = if !by_album.is_empty() {
+            Some((by_album, Source::Album))
+        } else if !by_artist.is_empty() {
+            Some((by_artist, Source::Artist))
+        } else {
+            None
+        };
+        match suggestion {
+            Some((genres, source)) => suggestions.push(Suggestion {
+                playable: playable.clone(),
+                genres,
+                source,
+            }),
+            None => unknown.push(playable.clone()),
+        }
+    }
+    (suggestions, unknown)
+}
+
+/// What Discogs is asked for a track, its artist and its album, or its title for a single.
+/// None without an artist, the search would match anything.
+fn lookup(playable: &Playable) -> Option<(String, String)> {
+    let artist = playable.get_artist().trim();
+    if artist.is_empty() {
+        return None;
+    }
+    let release = match playable.get_album().trim() {
+        "" => playable.get_title().trim(),
+        album => album,
+    };
+    Some((artist.to_lowercase(), release.to_lowercase()))
+}
+
+/// Looks up the genres of `playables` on Discogs, once per release. Releases past
+/// `MAX_LOOKUPS` and those Discogs does not know are left out.
+pub async fn from_discogs(playables: Vec<Playable>, credentials: Credentials) -> Vec<Suggestion> {
+    let mut releases: Vec<((String, String), Vec<Playable>)> = Vec::new();
+    for playable in playables {
+        let Some(key) = lookup(&playable) else {
+            continue;
+        };
+        match releases.iter_mut().find(|(release, _)| *release == key) {
+            Some((_, tracks)) => tracks.push(playable),
+            None => releases.push((key, vec![playable])),
+        }
+    }
+    let mut suggestions = Vec::new();
+    for ((artist, release), tracks) in releases.into_iter().take(MAX_LOOKUPS) {
+        let genres = match discogs::search_genres(&credentials, &artist, &release).await {
+            Ok(genres) if !genres.is_empty() => genres,
+            Ok(_) => continue,
+            Err(e) => {
+                log::error!("genre_inference: failed to look up {artist} - {release}\n{e:?}");
+                continue;
+            }
+        };
+        suggestions.extend(tracks.into_iter().map(|playable| Suggestion {
+            playable,
+            genres: genres.clone(),
+            source: Source::Discogs,
+        }));
+    }
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playable(id: i64, artist: &str, album: &str, genre: &str) -> Playable {
+        let name = |value: &str| (!value.is_empty()).then(|| value.to_string());
+        Playable {
+            id,
+            title: format!("title_{id}"),
+            artist_name: name(artist),
+            album_name: name(album),
+            genre_name: name(genre),
+            duration: 100,
+            source_url: format!("/music/{id}.mp3"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_from_library() {
+        let library = [
+            playable(1, "Slowdive", "Souvlaki", "Shoegaze"),
+            playable(2, "Slowdive", "Pygmalion", "Ambient"),
+            playable(3, "Slowdive", "Pygmalion", "ambient"),
+            playable(4, "Slowdive", "Souvlaki", ""),
+            playable(5, "slowdive", "Just for a Day", ""),
+            playable(6, "Unknown", "Souvlaki", ""),
+            playable(7, "", "", ""),
+        ];
+        let (suggestions, unknown) = from_library(&library);
+        let found: Vec<(i64, Vec<String>, Source)> = suggestions
+            .into_iter()
+            .map(|s| (s.playable.id, s.genres, s.source))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (4, vec!["Shoegaze".to_string()], Source::Album),
+                (
+                    5,
+                    vec!["Ambient".to_string(), "Shoegaze".to_string()],
+                    Source::Artist
+                ),
+            ]
+        );
+        assert_eq!(unknown.iter().map(|p| p.id).collect::<Vec<_>>(), [6, 7]);
+    }
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(
+            lookup(&playable(1, "Slowdive ", "Souvlaki", "")),
+            Some(("slowdive".to_string(), "souvlaki".to_string()))
+        );
+        assert_eq!(
+            lookup(&playable(2, "Slowdive", "", "")),
+            Some(("slowdive".to_string(), "title_2".to_string()))
+        );
+        assert_eq!(lookup(&playable(3, "", "Souvlaki", "")), None);
+    }
+}
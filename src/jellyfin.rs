@@ -0,0 +1,497 @@
+// Jellyfin media servers. The account signs in once for a token, the music of the server is
+// mirrored into the library and songs are streamed from it, transcoded by the server when
+// their codec can not be decoded here.
+use std::{collections::HashSet, io};
+
+use reqwest::{Method, Url};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use thiserror::Error;
+
+use crate::{
+    remote::{self, Mirror},
+    storage::{AudioFileDescriptor, AudioFileKind},
+};
+
+const CLIENT_NAME: &str = "Phoniq";
+const DEVICE_NAME: &str = "Desktop";
+// items asked for at once
+const ITEM_PAGE: usize = 500;
+// what the decoder here plays, as containers with the codec they must hold, empty for any.
+// The server sends these as they are and transcodes everything else
+const DIRECT_PLAY: [(&str, &str); 11] = [
+    ("mp3", ""),
+    ("flac", ""),
+    ("wav", ""),
+    ("aiff", ""),
+    ("ogg", "vorbis"),
+    ("oga", "vorbis"),
+    ("m4a", "aac"),
+    ("m4a", "alac"),
+    ("m4b", "aac"),
+    ("mp4", "aac"),
+    ("aac", ""),
+];
+// songs which can not be played as they are come as MP3 at this bitrate
+const TRANSCODE_BITRATE: u32 = 320_000;
+// high enough for any lossless file, only the codec decides whether a song is transcoded
+const MAX_STREAMING_BITRATE: u32 = 100_000_000;
+const TICKS_PER_SECOND: u64 = 10_000_000;
+
+#[derive(Debug, Error)]
+pub enum JellyfinError {
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Invalid address: {0}")]
+    InvalidUrl(String),
+}
+
+pub type Result<T> = std::result::Result<T, JellyfinError>;
+
+/// A server signed in to, the token stands for the password.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Server {
+    // where the server is reached, e.g. `https://jellyfin.example.com/`
+    pub url: String,
+    pub username: String,
+    pub user_id: String,
+    pub token: String,
+    // the token is bound to it, it is made up when signing in
+    pub device_id: String,
+}
+
+impl Server {
+    fn endpoint(&self, path: &str) -> Result<Url> {
+        Url::parse(&self.url)
+            .and_then(|url| url.join(path))
+            .map_err(|e| JellyfinError::InvalidUrl(e.to_string()))
+    }
+
+    fn request(
+        &self,
+        client: &reqwest::Client,
+        method: Method,
+        url: Url,
+    ) -> reqwest::RequestBuilder {
+        client.request(method, url).header(
+            "Authorization",
+            authorization(&self.device_id, Some(&self.token)),
+        )
+    }
+
+    /// The address a song is kept at in the library, without the token.
+    pub fn stream_path(&self, item_id: &str) -> String {
+        format!("{}Audio/{item_id}/universal", self.url)
+    }
+
+    // the address a song is streamed from. The server is told what plays here, it sends the
+    // file as it is when it does and an MP3 otherwise
+    fn stream_url(&self, item_id: &str) -> Result<Url> {
+        let mut url = self.endpoint(&format!("Audio/{item_id}/universal"))?;
+        let containers: Vec<String> = DIRECT_PLAY
+            .iter()
+            .map(|(container, codec)| {
+                if codec.is_empty() {
+                    container.to_string()
+                } else {
+                    format!("{container}|{codec}")
+                }
+            })
+            .collect();
+        url.query_pairs_mut()
+            .append_pair("UserId", &self.user_id)
+            .append_pair("DeviceId", &self.device_id)
+            .append_pair("api_key", &self.token)
+            .append_pair("Container", &containers.join(","))
+            .append_pair("TranscodingContainer", "mp3")
+            .append_pair("TranscodingProtocol", "http")
+            .append_pair("AudioCodec", "mp3")
+            .append_pair("AudioBitRate", &TRANSCODE_BITRATE.to_string())
+            .append_pair("MaxStreamingBitrate", &MAX_STREAMING_BITRATE.to_string());
+        Ok(url)
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        client: &reqwest::Client,
+        path: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T> {
+        let mut url = self.endpoint(path)?;
+        url.query_pairs_mut().extend_pairs(params);
+        Ok(self
+            .request(client, Method::GET, url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+}
+
+fn authorization(device_id: &str, token: Option<&str>) -> String {
+    let version = env!("CARGO_PKG_VERSION");
+    let mut header = format!(
+        r#"MediaBrowser Client="{CLIENT_NAME}", Device="{DEVICE_NAME}", DeviceId="{device_id}", "#
+    );
+    header.push_str(&format!(r#"Version="{version}""#));
+    if let Some(token) = token {
+        header.push_str(&format!(r#", Token="{token}""#));
+    }
+    header
+}
+
+/// Whether a song plays here as the server keeps it.
+fn direct_play(container: &str, codec: &str) -> bool {
+    let (container, codec) = (container.to_lowercase(), codec.to_lowercase());
+    DIRECT_PLAY
+        .iter()
+        .any(|(c, required)| *c == container && (required.is_empty() || *required == codec))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Authentication {
+    access_token: String,
+    user: User,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct User {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Items {
+    #[serde(default)]
+    items: Vec<Item>,
+    #[serde(default)]
+    total_record_count: usize,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+struct Item {
+    id: String,
+    name: String,
+    #[serde(rename = "Type")]
+    kind: String,
+    album: Option<String>,
+    album_artist: Option<String>,
+    artists: Vec<String>,
+    genres: Vec<String>,
+    production_year: Option<u16>,
+    run_time_ticks: Option<u64>,
+    index_number: Option<u32>,
+    parent_index_number: Option<u32>,
+    container: Option<String>,
+    media_sources: Vec<MediaSource>,
+    user_data: Option<UserData>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+struct MediaSource {
+    // in bits per second
+    bitrate: Option<u32>,
+    media_streams: Vec<MediaStream>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+struct MediaStream {
+    #[serde(rename = "Type")]
+    kind: String,
+    codec: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+struct UserData {
+    is_favorite: bool,
+}
+
+impl Item {
+    fn codec(&self) -> &str {
+        self.media_sources
+            .iter()
+            .flat_map(|source| &source.media_streams)
+            .find(|stream| stream.kind == "Audio")
+            .and_then(|stream| stream.codec.as_deref())
+            .unwrap_or("")
+    }
+
+    fn is_favorite(&self) -> bool {
+        self.user_data.as_ref().is_some_and(|data| data.is_favorite)
+    }
+
+    // a song transcoded by the server is described as it is played
+    fn descriptor(&self, server: &Server) -> AudioFileDescriptor {
+        let container = self.container.as_deref().unwrap_or("");
+        let (bitrate, lossless) = if direct_play(container, self.codec()) {
+            let bitrate = self
+                .media_sources
+                .iter()
+                .find_map(|source| source.bitrate)
+                .unwrap_or(0);
+            let lossless = match self.codec() {
+                "flac" | "alac" | "pcm_s16le" | "pcm_s24le" => Some(true),
+                "" => None,
+                _ => Some(false),
+            };
+            (bitrate / 1000, lossless)
+        } else {
+            (TRANSCODE_BITRATE / 1000, Some(false))
+        };
+        let artist = self
+            .artists
+            .first()
+            .or(self.album_artist.as_ref())
+            .cloned()
+            .unwrap_or_default();
+        AudioFileDescriptor {
+            title: self.name.clone(),
+            artist,
+            album: self.album.clone().unwrap_or_default(),
+            year: self.production_year.unwrap_or(0),
+            genre: self.genres.first().cloned().unwrap_or_default(),
+            duration: self.run_time_ticks.unwrap_or(0) / TICKS_PER_SECOND,
+            bitrate,
+            sample_rate: 0,
+            lossless,
+            release_date: String::new(),
+            track_number: self.index_number.unwrap_or(0),
+            disc_number: self.parent_index_number.unwrap_or(0),
+            label: String::new(),
+            path: server.stream_path(&self.id),
+            artwork: None,
+            kind: AudioFileKind::Jellyfin,
+        }
+    }
+}
+
+/// The server a library address streams from.
+pub fn find_server(url: &str) -> Option<Server> {
+    match remote::find_account(url)? {
+        remote::Account::Jellyfin(server) => Some(server),
+        _ => None,
+    }
+}
+
+/// The id of the song a library address streams, None for other addresses.
+pub fn item_id(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let segments: Vec<&str> = url.path_segments()?.collect();
+    match segments.as_slice() {
+        [.., "Audio", id, "universal"] => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Signs in with the account, the server answers with a token kept in place of the password.
+pub async fn authenticate(url: String, username: String, password: String) -> Result<Server> {
+    let device_id = format!("{:016x}", rand::random::<u64>());
+    let endpoint = Url::parse(&url)
+        .and_then(|base| base.join("Users/AuthenticateByName"))
+        .map_err(|e| JellyfinError::InvalidUrl(e.to_string()))?;
+    let authentication: Authentication = reqwest::Client::new()
+        .post(endpoint)
+        .header("Authorization", authorization(&device_id, None))
+        .json(&serde_json::json!({ "Username": username, "Pw": password }))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(Server {
+        url,
+        username,
+        user_id: authentication.user.id,
+        token: authentication.access_token,
+        device_id,
+    })
+}
+
+/// Reads the songs, playlists and favorites of the account.
+pub async fn mirror(server: Server) -> Result<Mirror> {
+    let client = reqwest::Client::new();
+    let items_path = format!("Users/{}/Items", server.user_id);
+    let mut mirror = Mirror::default();
+    let mut known = HashSet::new();
+    let mut add = |mirror: &mut Mirror, item: &Item| {
+        let path = server.stream_path(&item.id);
+        if item.is_favorite() {
+            mirror.liked.push(path.clone());
+        }
+        if known.insert(path) {
+            mirror.tracks.push(item.descriptor(&server));
+        }
+    };
+
+    let mut start = 0;
+    loop {
+        let (from, limit) = (start.to_string(), ITEM_PAGE.to_string());
+        let params = [
+            ("IncludeItemTypes", "Audio"),
+            ("Recursive", "true"),
+            ("Fields", "Genres,MediaSources"),
+            ("StartIndex", from.as_str()),
+            ("Limit", limit.as_str()),
+        ];
+        let page: Items = server.get(&client, &items_path, &params).await?;
+        for item in &page.items {
+            add(&mut mirror, item);
+        }
+        start += page.items.len();
+        if page.items.is_empty() || start >= page.total_record_count {
+            break;
+        }
+    }
+
+    let params = [("IncludeItemTypes", "Playlist"), ("Recursive", "true")];
+    let playlists: Items = server.get(&client, &items_path, &params).await?;
+    for playlist in playlists.items {
+        let params = [
+            ("UserId", server.user_id.as_str()),
+            ("Fields", "Genres,MediaSources"),
+        ];
+        let entries: Items = server
+            .get(
+                &client,
+                &format!("Playlists/{}/Items", playlist.id),
+                &params,
+            )
+            .await?;
+        // playlists of videos are left out
+        let songs: Vec<&Item> = entries
+            .items
+            .iter()
+            .filter(|item| item.kind == "Audio")
+            .collect();
+        if songs.is_empty() {
+            continue;
+        }
+        for item in &songs {
+            add(&mut mirror, item);
+        }
+        let paths = songs
+            .iter()
+            .map(|item| server.stream_path(&item.id))
+            .collect();
+        mirror.playlists.push((playlist.name, paths));
+    }
+    mirror.liked.sort();
+    mirror.liked.dedup();
+    Ok(mirror)
+}
+
+/// Marks a song as a favorite on the server, or takes the mark away, as it is liked in the
+/// library.
+pub async fn set_favorite(server: Server, item_id: String, favorite: bool) -> Result<()> {
+    let url = server.endpoint(&format!("Users/{}/FavoriteItems/{item_id}", server.user_id))?;
+    let method = if favorite {
+        Method::POST
+    } else {
+        Method::DELETE
+    };
+    server
+        .request(&reqwest::Client::new(), method, url)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+impl From<Server> for remote::Server {
+    fn from(server: Server) -> Self {
+        remote::Server::Jellyfin(server)
+    }
+}
+
+/// Blocking read used by the audio worker to stream a song of a server.
+pub fn fetch_audio(url: &str) -> io::Result<Vec<u8>> {
+    let (Some(server), Some(id)) = (find_server(url), item_id(url)) else {
+        return Err(io::Error::other(format!("No Jellyfin server for {url}")));
+    };
+    let url = server.stream_url(&id).map_err(io::Error::other)?;
+    reqwest::blocking::get(url)
+        .and_then(|r| r.error_for_status())
+        .and_then(|r| r.bytes())
+        .map(|bytes| bytes.to_vec())
+        .map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> Server {
+        Server {
+            url: "https://jellyfin.example.com/".to_string(),
+            username: "alice".to_string(),
+            user_id: "u1".to_string(),
+            token: "t0k".to_string(),
+            device_id: "d1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_direct_play() {
+        assert!(direct_play("flac", "flac"));
+        assert!(direct_play("MP3", "mp3"));
+        assert!(direct_play("m4a", "alac"));
+        assert!(!direct_play("ogg", "opus"));
+        assert!(!direct_play("wma", "wmav2"));
+        assert!(!direct_play("ape", "ape"));
+    }
+
+    #[test]
+    fn test_stream_url() {
+        let path = server().stream_path("abc");
+        assert_eq!(path, "https://jellyfin.example.com/Audio/abc/universal");
+        assert_eq!(item_id(&path).as_deref(), Some("abc"));
+        assert_eq!(item_id("https://jellyfin.example.com/Items/abc"), None);
+
+        let url = server().stream_url("abc").unwrap();
+        let param = |key: &str| {
+            url.query_pairs()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v.into_owned())
+        };
+        assert_eq!(url.path(), "/Audio/abc/universal");
+        assert_eq!(param("api_key").as_deref(), Some("t0k"));
+        assert_eq!(param("TranscodingContainer").as_deref(), Some("mp3"));
+        assert!(
+            param("Container")
+                .unwrap()
+                .starts_with("mp3,flac,wav,aiff,ogg|vorbis")
+        );
+    }
+
+    #[test]
+    fn test_descriptor() {
+        let json = r#"{"Items": [
+            {"Id": "1", "Name": "One", "Type": "Audio", "Album": "LP", "Artists": ["A"],
+             "RunTimeTicks": 1800000000, "IndexNumber": 2, "Container": "flac",
+             "MediaSources": [{"Bitrate": 900000,
+                 "MediaStreams": [{"Type": "Audio", "Codec": "flac"}]}],
+             "UserData": {"IsFavorite": true}},
+            {"Id": "2", "Name": "Two", "Type": "Audio", "AlbumArtist": "B",
+             "Container": "ogg", "MediaSources": [{"Bitrate": 128000,
+                 "MediaStreams": [{"Type": "Audio", "Codec": "opus"}]}]}
+        ], "TotalRecordCount": 2}"#;
+        let items: Items = serde_json::from_str(json).unwrap();
+        assert_eq!(items.total_record_count, 2);
+        assert!(items.items[0].is_favorite());
+
+        let flac = items.items[0].descriptor(&server());
+        assert_eq!(flac.artist, "A");
+        assert_eq!(flac.duration, 180);
+        assert_eq!((flac.bitrate, flac.lossless), (900, Some(true)));
+        // opus is transcoded, it plays as an MP3
+        let opus = items.items[1].descriptor(&server());
+        assert_eq!(opus.artist, "B");
+        assert_eq!((opus.bitrate, opus.lossless), (320, Some(false)));
+    }
+}
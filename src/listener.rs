@@ -0,0 +1,104 @@
+// The listening side of the small HTTP servers on the local network, the one serving files to cast
+// and the one sharing the library. The thread accepting connections stops when it is dropped.
+use std::{
+    io,
+    net::{TcpListener, TcpStream},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use log::{debug, error, warn};
+
+// how long the wake up connection of a drop may take, the thread is left behind after that
+const WAKE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Hands each connection on any address of this computer to `serve`, on a thread of its own.
+pub struct Listener {
+    port: u16,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Listener {
+    pub fn start(
+        name: &'static str,
+        serve: impl Fn(TcpStream) + Send + Sync + 'static,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind("0.0.0.0:0")?;
+        let port = listener.local_addr()?.port();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let stopping = stopped.clone();
+        let serve = Arc::new(serve);
+        let thread = thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || {
+                for stream in listener.incoming() {
+                    if stopping.load(Ordering::Acquire) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    let serve = serve.clone();
+                    thread::spawn(move || serve(stream));
+                }
+                debug!("{name}: stopped listening");
+            })?;
+        Ok(Self {
+            port,
+            stopped,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+        // the thread waits for the next connection before it sees the flag
+        let woken = TcpStream::connect_timeout(&([127, 0, 0, 1], self.port).into(), WAKE_TIMEOUT);
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+        match woken {
+            Ok(_) => {
+                if thread.join().is_err() {
+                    error!("Listener: the thread on port {} panicked", self.port);
+                }
+            }
+            Err(e) => warn!("Listener: could not stop port {}: {e}", self.port),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+
+    #[test]
+    fn test_stops_on_drop() {
+        let listener = Listener::start("test-listener", |mut stream| {
+            let _ = stream.write_all(b"hello");
+        })
+        .unwrap();
+        let port = listener.port();
+        let mut reply = String::new();
+        TcpStream::connect(("127.0.0.1", port))
+            .unwrap()
+            .read_to_string(&mut reply)
+            .unwrap();
+        assert_eq!(reply, "hello");
+
+        drop(listener);
+        assert!(TcpStream::connect(("127.0.0.1", port)).is_err());
+    }
+}
@@ -0,0 +1,325 @@
+// Playlists as M3U files, which nearly every player and DJ software reads. Static playlists are
+// written as UTF-8 M3U8 with paths relative to the file or absolute. M3U and PLS files are read
+// back into a playlist, their tracks are found in the library by path.
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+    app_state::AudioPlayable,
+    storage::{AudioFileKind, Playable},
+};
+
+pub const EXTENSIONS: [&str; 3] = ["m3u", "m3u8", "pls"];
+
+#[derive(Debug, Error)]
+pub enum M3uError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, M3uError>;
+
+/// A track listed by a playlist file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    // an absolute path, or the address of a stream
+    pub location: String,
+    pub title: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaylistFile {
+    pub name: String,
+    pub entries: Vec<Entry>,
+}
+
+fn is_url(location: &str) -> bool {
+    location.contains("://") && !location.starts_with("file://")
+}
+
+/// `target` relative to the folder `base`, None when they share no root, e.g. on two drives.
+fn relative_path(target: &Path, base: &Path) -> Option<PathBuf> {
+    let target: Vec<Component> = target.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    if target.first() != base.first() {
+        return None;
+    }
+    let common = target.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    let mut path = PathBuf::new();
+    for _ in common..base.len() {
+        path.push("..");
+    }
+    path.extend(&target[common..]);
+    Some(path)
+}
+
+/// The tracks as an M3U8 playlist saved in `folder`. Files are listed relative to it when
+/// `relative` is set and they can be, streams by their address.
+pub fn write(name: &str, playables: &[Playable], folder: &Path, relative: bool) -> String {
+    let mut lines = vec!["#EXTM3U".to_string(), format!("#PLAYLIST:{name}")];
+    for playable in playables {
+        let title = match playable.get_artist() {
+            "" => playable.get_title().to_string(),
+            artist => format!("{artist} - {}", playable.get_title()),
+        };
+        // -1 for an unknown length
+        let duration = if playable.duration > 0 {
+            playable.duration
+        } else {
+            -1
+        };
+        lines.push(format!("#EXTINF:{duration},{title}"));
+        let path = Path::new(playable.get_path());
+        let location = match relative_path(path, folder) {
+            Some(path) if relative && playable.type_id == AudioFileKind::LocalFile => {
+                let segments: Vec<String> = path
+                    .iter()
+                    .map(|segment| segment.to_string_lossy().to_string())
+                    .collect();
+                segments.join("/")
+            }
+            _ => playable.get_path().to_string(),
+        };
+        lines.push(location);
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Saves the tracks to `path`, the playlist is named after the file.
+pub fn save(path: &Path, playables: &[Playable], relative: bool) -> Result<()> {
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let folder = path.parent().unwrap_or(Path::new(""));
+    fs::write(path, write(&name, playables, folder, relative))?;
+    Ok(())
+}
+
+// where a track of a playlist in `folder` is, as it would be stored in the library
+fn resolve(location: &str, folder: &Path) -> String {
+    if is_url(location) {
+        return location.to_string();
+    }
+    let location = location.strip_prefix("file://").unwrap_or(location);
+    // playlists made on Windows
+    let location = if cfg!(windows) {
+        location.to_string()
+    } else {
+        location.replace('\\', "/")
+    };
+    let mut path = PathBuf::new();
+    for component in folder.join(location).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                path.pop();
+            }
+            component => path.push(component),
+        }
+    }
+    path.to_string_lossy().to_string()
+}
+
+fn parse_m3u(content: &str, folder: &Path, name: &mut String) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    let mut title = None;
+    for line in content.lines().map(str::trim) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            title = info
+                .split_once(',')
+                .map(|(_, title)| title.trim().to_string())
+                .filter(|title| !title.is_empty());
+        } else if let Some(playlist) = line.strip_prefix("#PLAYLIST:") {
+            *name = playlist.trim().to_string();
+        } else if !line.is_empty() && !line.starts_with('#') {
+            entries.push(Entry {
+                location: resolve(line, folder),
+                title: title.take(),
+            });
+        }
+    }
+    entries
+}
+
+fn parse_pls(content: &str, folder: &Path) -> Vec<Entry> {
+    // entries are numbered, File1=... and Title1=..., in any order
+    let mut numbered: Vec<(u32, Entry)> = Vec::new();
+    for line in content.lines().map(str::trim) {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let (field, number) = if let Some(number) = key.strip_prefix("file") {
+            ("file", number)
+        } else if let Some(number) = key.strip_prefix("title") {
+            ("title", number)
+        } else {
+            continue;
+        };
+        let Ok(number) = number.parse::<u32>() else {
+            continue;
+        };
+        let index = match numbered.iter().position(|(n, _)| *n == number) {
+            Some(index) => index,
+            None => {
+                numbered.push((
+                    number,
+                    Entry {
+                        location: String::new(),
+                        title: None,
+                    },
+                ));
+                numbered.len() - 1
+            }
+        };
+        let entry = &mut numbered[index].1;
+        match field {
+            "file" => entry.location = resolve(value.trim(), folder),
+            _ => entry.title = Some(value.trim().to_string()).filter(|t| !t.is_empty()),
+        }
+    }
+    numbered.sort_by_key(|(number, _)| *number);
+    numbered
+        .into_iter()
+        .map(|(_, entry)| entry)
+        .filter(|entry| !entry.location.is_empty())
+        .collect()
+}
+
+/// Reads a playlist saved in `folder`, PLS when it starts like one and M3U otherwise. The name
+/// is the one the file gives, `name` when it gives none.
+pub fn parse(content: &str, folder: &Path, name: &str) -> PlaylistFile {
+    let mut name = name.to_string();
+    let is_pls = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .is_some_and(|line| line.eq_ignore_ascii_case("[playlist]"));
+    let entries = if is_pls {
+        parse_pls(content, folder)
+    } else {
+        parse_m3u(content.trim_start_matches('\u{feff}'), folder, &mut name)
+    };
+    PlaylistFile { name, entries }
+}
+
+pub fn load(path: &Path) -> Result<PlaylistFile> {
+    // M3U files which are not M3U8 are often Latin-1, their paths are read as well as can be
+    let content = String::from_utf8_lossy(&fs::read(path)?).to_string();
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Ok(parse(
+        &content,
+        path.parent().unwrap_or(Path::new("")),
+        &name,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playable(title: &str, artist: &str, url: &str, kind: AudioFileKind) -> Playable {
+        Playable {
+            id: 1,
+            title: title.to_string(),
+            artist_name: Some(artist.to_string()).filter(|artist| !artist.is_empty()),
+            duration: 180,
+            source_url: url.to_string(),
+            type_id: kind,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let playables = [
+            playable(
+                "Xtal",
+                "Aphex Twin",
+                "/music/aphex/xtal.flac",
+                AudioFileKind::LocalFile,
+            ),
+            playable("Intro", "", "/other/intro.mp3", AudioFileKind::LocalFile),
+            playable(
+                "Radio",
+                "",
+                "https://example.com/live",
+                AudioFileKind::Stream,
+            ),
+        ];
+        let folder = Path::new("/music/playlists");
+        assert_eq!(
+            write("Mix", &playables, folder, true),
+            "#EXTM3U\n#PLAYLIST:Mix\n\
+             #EXTINF:180,Aphex Twin - Xtal\n../aphex/xtal.flac\n\
+             #EXTINF:180,Intro\n../../other/intro.mp3\n\
+             #EXTINF:180,Radio\nhttps://example.com/live\n"
+        );
+        let absolute = write("Mix", &playables, folder, false);
+        assert!(absolute.contains("\n/music/aphex/xtal.flac\n"));
+    }
+
+    #[test]
+    fn test_parse_m3u() {
+        let folder = Path::new("/music/playlists");
+        let playlist = parse(
+            "\u{feff}#EXTM3U\n#PLAYLIST:Friday\n#EXTINF:180,Aphex Twin - Xtal\n\
+             ../aphex/xtal.flac\n\n/music/b.mp3\nfile:///music/c.mp3\nhttp://example.com/live\n",
+            folder,
+            "friday",
+        );
+        assert_eq!(playlist.name, "Friday");
+        let locations: Vec<&str> = playlist
+            .entries
+            .iter()
+            .map(|entry| entry.location.as_str())
+            .collect();
+        assert_eq!(
+            locations,
+            [
+                "/music/aphex/xtal.flac",
+                "/music/b.mp3",
+                "/music/c.mp3",
+                "http://example.com/live",
+            ]
+        );
+        assert_eq!(
+            playlist.entries[0].title.as_deref(),
+            Some("Aphex Twin - Xtal")
+        );
+        assert_eq!(playlist.entries[1].title, None);
+        assert_eq!(parse("a.mp3", folder, "file name").name, "file name");
+    }
+
+    #[test]
+    fn test_parse_pls() {
+        let playlist = parse(
+            "[playlist]\nTitle2=Second\nFile2=b.mp3\nFile1=/music/a.mp3\n\
+             NumberOfEntries=2\nVersion=2\n",
+            Path::new("/music"),
+            "mix",
+        );
+        assert_eq!(playlist.name, "mix");
+        assert_eq!(
+            playlist.entries,
+            [
+                Entry {
+                    location: "/music/a.mp3".to_string(),
+                    title: None,
+                },
+                Entry {
+                    location: "/music/b.mp3".to_string(),
+                    title: Some("Second".to_string()),
+                },
+            ]
+        );
+    }
+}
@@ -1,43 +1,130 @@
 mod app_state;
+mod artwork;
 mod audio_scanner;
+mod beatport;
+mod cast;
+mod cdj_export;
+mod chapters;
+mod clipboard;
+mod convert;
+mod diagnostics;
+mod discogs;
+mod equalizer;
+mod folder_watch;
 mod fonts;
+mod genre_inference;
 mod icons;
+mod jellyfin;
+mod listener;
+mod m3u;
+mod media_controls;
 mod menu_bar;
+mod meter;
+mod navigation;
+mod offline;
 mod player;
+mod podcasts;
+mod recorder;
+mod remote;
+mod resampler;
+mod schedule;
+mod secrets;
+mod session_log;
+mod shared_playlist;
 mod sidebar;
+mod sidecar;
+mod skip_silence;
+mod snapshots;
 mod storage;
+mod subsonic;
+mod sync;
 mod theme;
 mod util;
 mod view_types;
+mod vinyl;
+mod volume_trim;
+mod webdav;
 mod widgets;
 
 use crate::{
-    app_state::{Section, state_impl::State as AppState},
-    audio_scanner::{ScannedFile, scan_file, scan_folder},
+    app_state::{
+        AudioPlayable, PlayableId, PlayableKind, Section,
+        state_impl::{
+            CloseBehavior, LibraryLoad, ListColumn, SortOrder, State as AppState, WindowGeometry,
+        },
+    },
+    audio_scanner::{ScanOptions, ScannedFile, scan_file, scan_folder},
+    folder_watch::{FolderWatcher, Listing},
+    m3u,
     menu_bar::MenuBar,
+    navigation::History,
     player::Player,
-    sidebar::{Sidebar, playlists::MenuOptions},
-    theme::Theme,
-    view_types::compact_view::{self, CompactView},
+    podcasts::Feed,
+    shared_playlist::SharedPlaylist,
+    sidebar::{Drag, DropTarget, Sidebar, drop_target, playlists::MenuOptions},
+    sidecar::Sidecar,
+    storage::{PlaylistKind, Watch, WatchReleaseDescriptor},
+    util::unix_now,
+    view_types::{
+        advanced_settings,
+        album_grid::{self, AlbumGrid},
+        album_view, beatport_charts, breadcrumbs,
+        browse_view::{self, BrowseView},
+        cdj_export_view::{self, CdjExportView},
+        command_palette::{self, Action, CommandPalette},
+        compact_view::{self, CompactView, NoteTarget},
+        conversions_view::{self, ConversionsView},
+        diagnostics_view::{self, DiagnosticsView},
+        discogs_connect,
+        field_settings::{self, FieldSettings},
+        genre_suggestions_view::{self, GenreSuggestionsView},
+        import_history,
+        insights_view::{self, InsightsView},
+        listen_now::{self, ListenNowView},
+        playback_settings,
+        playlist_settings::{self, PlaylistSettings},
+        podcast_view::{self, PodcastView},
+        privacy_settings, queue_view, quit_prompt, recently_deleted,
+        recorder_view::{self, RecorderView},
+        scanner_settings::{self, ScannerSettings},
+        server_settings::{self, ServerSettings},
+        settings_tabs,
+        snapshots_view::{self, SnapshotsView},
+        song_info,
+        split_view::{self, SplitView},
+        sync_settings::{self, SyncSettings},
+        vinyl_view::{self, VinylView},
+    },
 };
 use iced::{
-    Element, Length, Settings, Subscription, Task, Theme as IcedTheme, event,
+    Element, Length, Point, Settings, Size, Subscription, Task, Theme as IcedTheme, event,
     futures::{SinkExt, Stream, channel::mpsc::Sender},
+    mouse,
     widget::{Column, Container, PaneGrid, container, pane_grid, text, vertical_rule},
-    window::Event as WindowEvent,
+    window::{self, Event as WindowEvent},
+};
+use log::{error, info};
+use std::{
+    collections::{HashSet, VecDeque},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
 };
-use log::error;
-use std::{path::PathBuf, sync::Arc};
 
 use std::pin::Pin;
 
 pub fn main() -> iced::Result {
     env_logger::init();
     fonts::set();
-    iced::application(Phonique::default, Phonique::update, Phonique::view)
+    iced::application(Phonique::new, Phonique::update, Phonique::view)
         .theme(Phonique::theme)
         .title("Phoniq")
         .subscription(Phonique::subscription)
+        // the session is saved before the window closes
+        .window(window::Settings {
+            exit_on_close_request: false,
+            ..Default::default()
+        })
         .settings(Settings {
             default_font: fonts::SANS.clone().into(),
             fonts: fonts::load(),
@@ -49,32 +136,139 @@ pub fn main() -> iced::Result {
 
 const MIN_SIDEBAR_WIDTH: f32 = 200.0;
 const MAX_SIDEBAR_WIDTH: f32 = 275.0;
+// windows moved left of or above this are minimized, their position is not kept
+const MINIMIZED_POSITION: f32 = -10_000.0;
+// how long a search for peers waits for answers
+const PEER_DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+// how often the shared library is rebuilt while sharing
+const SHARE_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+// how often the alarm is checked and its volume raised
+const ALARM_INTERVAL: Duration = Duration::from_secs(1);
+// how often the Discogs watches are looked at, each is checked once its own interval passed
+const WATCH_INTERVAL: Duration = Duration::from_secs(600);
+// how often the watched folders are listed, a new file is imported once it stayed the same over
+// a whole interval
+const FOLDER_WATCH_INTERVAL: Duration = Duration::from_secs(30);
 
 #[derive(Clone, Debug)]
 enum Panes {
     Sidebar,
     Central,
+    // a second section beside the central one
+    Split,
 }
 
 #[derive(Debug, Clone)]
 pub enum Message {
     PaneResize(pane_grid::ResizeEvent),
-    // only interested in the width for now
-    WindowResize(f32),
+    WindowResize(Size),
+    WindowMoved(Point),
     Sidebar(sidebar::Message),
     Player(player::Message),
     CompactView(compact_view::Message),
+    AlbumGrid(album_grid::Message),
+    AlbumView(album_view::Message),
+    Navigate(breadcrumbs::Message),
+    PodcastView(podcast_view::Message),
+    ImportHistory(import_history::Message),
+    Queue(queue_view::Message),
+    RecentlyDeleted(recently_deleted::Message),
+    Snapshots(snapshots_view::Message),
+    Conversions(conversions_view::Message),
+    CdjExport(cdj_export_view::Message),
+    Vinyl(vinyl_view::Message),
+    Recorder(recorder_view::Message),
+    Browse(browse_view::Message),
+    Insights(insights_view::Message),
+    GenreSuggestions(genre_suggestions_view::Message),
+    ScannerSettings(scanner_settings::Message),
+    PlaybackSettings(playback_settings::Message),
+    PrivacySettings(privacy_settings::Message),
+    FieldSettings(field_settings::Message),
+    PlaylistSettings(playlist_settings::Message),
+    SyncSettings(sync_settings::Message),
+    ServerSettings(server_settings::Message),
+    AdvancedSettings(advanced_settings::Message),
+    Diagnostics(diagnostics_view::Message),
+    SongInfo(song_info::Message),
+    // one of the settings tabs
+    OpenSettings(Section),
+    PodcastFetched(String, Result<Feed, String>),
+    EpisodeDownloaded(i64, Result<PathBuf, String>),
+    // the ids an import written by the storage worker added, with the playlist they joined
+    Imported(Option<i64>, Result<Vec<PlayableId>, String>),
+    // a remote playable downloaded to be played offline, with the file it was written to
+    OfflineDownloaded(PlayableId, Result<PathBuf, String>),
+    // the playlist to save and where, None when the dialog was cancelled
+    SharePlaylist(i64, Option<PathBuf>),
+    // the playlist to export, whether paths are relative and where
+    ExportM3u(i64, bool, Option<PathBuf>),
+    // tracks dragged came over a playlist or tag, or left it
+    DragEntered(DropTarget),
+    DragLeft(DropTarget),
+    // the button was released while tracks were dragged from the list
+    DragEnded,
+    Split(split_view::Message),
     MetadataScanResult(ScannedFile),
     MetadataScanningStarted(Option<PathBuf>),
     MetadataScanningEnded,
     MenuBar(menu_bar::Message),
+    // rebuilds what the library shares with peers
+    RefreshShared,
+    // checks whether the alarm is due and raises the volume while it ramps up
+    AlarmTick,
+    // checks the Discogs watches which are due
+    WatchTick,
+    WatchChecked(i64, Result<Vec<WatchReleaseDescriptor>, String>),
+    // lists the watched folders again
+    FolderWatchTick,
+    // the listing of each watched folder, None for a folder which is not there
+    FoldersListed(Vec<(PathBuf, Option<Listing>)>),
+    // the new files of a watched folder, read and ready to import
+    FolderScanned(PathBuf, Vec<ScannedFile>),
+    // looks up the artwork of the next release queued
+    ArtworkTick,
+    // the tracks of the release looked up and its artwork, None when none was found
+    ArtworkFetched(Vec<PlayableId>, Result<Option<Vec<u8>>, String>),
+    // a page of the library shown, read off the UI thread
+    LibraryPage(LibraryLoad, Result<Vec<storage::Playable>, String>),
+    // a like of a server's song starred or marked as a favorite on it
+    Starred(Result<(), String>),
+    ListenNow(listen_now::Message),
+    CloseRequested(window::Id),
+    QuitPrompt(quit_prompt::Message),
+    Palette(command_palette::Message),
+    // whether the window was maximized when it was closed
+    Close(bool),
     Error(String),
 }
 
 struct Phonique {
     pane_state: pane_grid::State<Panes>,
     pane_ratio: f32,
+    // how the central pane and the split pane share their space
+    split_ratio: f32,
+    split_view: Option<SplitView>,
+    // last size and position of the window, kept while it is maximized
+    window_size: Option<Size>,
+    window_position: Option<Point>,
     compact_view: CompactView,
+    album_grid: AlbumGrid,
+    podcast_view: PodcastView,
+    insights_view: InsightsView,
+    genre_suggestions_view: GenreSuggestionsView,
+    snapshots_view: SnapshotsView,
+    conversions_view: ConversionsView,
+    diagnostics_view: DiagnosticsView,
+    cdj_export_view: CdjExportView,
+    vinyl_view: VinylView,
+    recorder_view: RecorderView,
+    browse_view: BrowseView,
+    scanner_settings: ScannerSettings,
+    field_settings: FieldSettings,
+    playlist_settings: PlaylistSettings,
+    sync_settings: SyncSettings,
+    server_settings: ServerSettings,
     player: Player,
     state: AppState,
     // we use this both as a flag and something to hold the value in when the files are dropped on the main window
@@ -84,68 +278,291 @@ struct Phonique {
     scannned_files: Vec<ScannedFile>,
     menubar: MenuBar,
     sidebar: Sidebar,
+    // when the alarm started, the volume it rises to and over how many minutes
+    alarm_ramp: Option<(Instant, f32, u32)>,
+    // watches being checked
+    checking_watches: HashSet<i64>,
+    folder_watcher: FolderWatcher,
+    // set while the watched folders are listed
+    listing_folders: bool,
+    // the releases whose artwork is looked up, one at a time
+    artwork_queue: VecDeque<artwork::Lookup>,
+    // set while a release is looked up
+    fetching_artwork: bool,
+    listen_now: ListenNowView,
+    // the window asked to close while playing, waiting for an answer
+    quit_prompt: Option<window::Id>,
+    palette: CommandPalette,
+    // the sections visited, to go back and forward
+    history: History,
+    // tracks dragged from the list, the sidebar takes them
+    drag: Option<Drag>,
 }
 
 impl Default for Phonique {
     fn default() -> Self {
-        let ratio = 0.25;
-        let pane_state = pane_grid::State::with_configuration(pane_grid::Configuration::Split {
-            axis: pane_grid::Axis::Vertical,
-            ratio,
-            a: Box::new(pane_grid::Configuration::Pane(Panes::Sidebar)),
-            b: Box::new(pane_grid::Configuration::Pane(Panes::Central)),
-        });
-
         let state = AppState::default();
+        let ratio = state.window_geometry().pane_ratio.unwrap_or(0.25);
+        let mut player = Player::default();
+        // there is no audio worker yet, the player hands it over once it is ready
+        let _ = player.update(player::Message::FadeChanged(state.fade_ms()));
+        let _ = player.update(player::Message::CrossfadeChanged(state.crossfade_secs()));
+        let _ = player.update(player::Message::LatencyChanged(state.output_latency_ms()));
+        let _ = player.update(player::Message::MatchSourceRateChanged(
+            state.match_source_rate(),
+        ));
+        let _ = player.update(player::Message::ResampleQualityChanged(
+            state.resample_quality(),
+        ));
+        let _ = player.update(player::Message::TrimsChanged(state.volume_trims().clone()));
+        let _ = player.update(player::Message::SkipSilenceChanged(state.skip_silence()));
+        let _ = player.update(player::Message::EqCurvesChanged(state.eq_curves().clone()));
+        let mut menubar = MenuBar::default();
+        menubar.set_recent(state.recent_files());
 
         Self {
-            pane_state,
+            pane_state: split_panes(ratio, None),
             pane_ratio: ratio,
-            player: Player::default(),
+            split_ratio: 0.5,
+            split_view: None,
+            window_size: None,
+            window_position: None,
+            player,
             state,
             scanning_files: None,
             scannned_files: Vec::new(),
             compact_view: CompactView::default(),
-            menubar: MenuBar::default(),
+            album_grid: AlbumGrid::default(),
+            podcast_view: PodcastView::default(),
+            insights_view: InsightsView::default(),
+            genre_suggestions_view: GenreSuggestionsView::default(),
+            snapshots_view: SnapshotsView::default(),
+            conversions_view: ConversionsView::default(),
+            diagnostics_view: DiagnosticsView::default(),
+            cdj_export_view: CdjExportView::default(),
+            vinyl_view: VinylView::default(),
+            recorder_view: RecorderView::default(),
+            browse_view: BrowseView::default(),
+            scanner_settings: ScannerSettings::default(),
+            field_settings: FieldSettings::default(),
+            playlist_settings: PlaylistSettings::default(),
+            sync_settings: SyncSettings::default(),
+            server_settings: ServerSettings::default(),
+            menubar,
             sidebar: Sidebar::default(),
+            alarm_ramp: None,
+            checking_watches: HashSet::new(),
+            folder_watcher: FolderWatcher::default(),
+            listing_folders: false,
+            artwork_queue: VecDeque::new(),
+            fetching_artwork: false,
+            listen_now: ListenNowView::default(),
+            quit_prompt: None,
+            palette: CommandPalette::default(),
+            history: History::default(),
+            drag: None,
         }
     }
 }
 
 impl Phonique {
+    fn new() -> (Self, Task<Message>) {
+        let mut phonique = Self::default();
+        let session = phonique.state.restore_session();
+        let task = phonique
+            .compact_view
+            .restore(session.selection, session.scroll_offset)
+            .map(Message::CompactView);
+        let thumbnails = phonique.load_thumbnails();
+        let library = phonique.load_library_pages();
+        let mut diagnostics = phonique
+            .diagnostics_view
+            .run(&phonique.state)
+            .map(Message::Diagnostics);
+        // problems found on launch are shown rather than worked around silently, the section
+        // restored can be gone back to
+        if phonique.diagnostics_view.has_problems() {
+            diagnostics = Task::batch([
+                diagnostics,
+                Task::done(Message::OpenSettings(Section::Diagnostics)),
+            ]);
+        }
+        let geometry = phonique.state.window_geometry();
+        let window = window::oldest().and_then(move |id| {
+            let mut tasks = Vec::new();
+            if let Some((width, height)) = geometry.size {
+                tasks.push(window::resize(id, Size::new(width, height)));
+            }
+            if let Some((x, y)) = geometry.position {
+                tasks.push(window::move_to(id, Point::new(x, y)));
+            }
+            if geometry.maximized {
+                tasks.push(window::maximize(id, true));
+            }
+            Task::batch(tasks)
+        });
+        let media_controls = window::oldest()
+            .and_then(|id| window::run(id, media_controls::window_handle))
+            .map(|hwnd| Message::Player(player::Message::MediaWindow(hwnd)));
+        (
+            phonique,
+            Task::batch([
+                task,
+                window,
+                media_controls,
+                thumbnails,
+                library,
+                diagnostics,
+                Task::done(Message::WatchTick),
+                Task::done(Message::FolderWatchTick),
+            ]),
+        )
+    }
+
     pub fn update(&mut self, message: Message) -> Task<Message> {
+        let current = self.state.section().clone();
+        let section = match message {
+            Message::Navigate(breadcrumbs::Message::Back) => self.history.back(current),
+            Message::Navigate(breadcrumbs::Message::Forward) => self.history.forward(current),
+            Message::Navigate(breadcrumbs::Message::BackTo(index)) => {
+                self.history.back_to(index, current)
+            }
+            message => {
+                let task = self.handle(message);
+                // however the section was changed, it can be gone back to
+                if *self.state.section() != current {
+                    self.history.visit(current);
+                }
+                return Task::batch([task, self.load_library_pages()]);
+            }
+        };
+        match section {
+            Some(section) => {
+                let task = self.handle(Message::Sidebar(sidebar::Message::Selected(section)));
+                Task::batch([task, self.load_library_pages()])
+            }
+            None => Task::none(),
+        }
+    }
+
+    fn handle(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::WindowResize(width) => {
-                let sidebar_width = self.pane_ratio * width;
+            Message::WindowResize(size) => {
+                self.window_size = Some(size);
+                let sidebar_width = self.pane_ratio * size.width;
                 let sidebar_width = sidebar_width.clamp(MIN_SIDEBAR_WIDTH, MAX_SIDEBAR_WIDTH);
-                let new_ratio = sidebar_width / width;
+                let new_ratio = sidebar_width / size.width;
                 self.pane_ratio = new_ratio;
-                self.pane_state =
-                    pane_grid::State::with_configuration(pane_grid::Configuration::Split {
-                        axis: pane_grid::Axis::Vertical,
-                        ratio: self.pane_ratio,
-                        a: Box::new(pane_grid::Configuration::Pane(Panes::Sidebar)),
-                        b: Box::new(pane_grid::Configuration::Pane(Panes::Central)),
-                    });
+                self.pane_state = split_panes(self.pane_ratio, self.split());
+            }
+            Message::WindowMoved(position) => {
+                // minimized windows are moved far off screen on Windows
+                if position.x > MINIMIZED_POSITION && position.y > MINIMIZED_POSITION {
+                    self.window_position = Some(position);
+                }
             }
             Message::MenuBar(msg) => {
                 match msg.clone() {
                     menu_bar::Message::Search(val) => {
-                        self.state.search(val);
+                        if let Err(error) = self.state.search(val) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        return Task::batch([
+                            self.menubar.update(msg).map(Message::MenuBar),
+                            self.load_thumbnails(),
+                        ]);
                     }
                     menu_bar::Message::MetadataScanningStarted(path_buf) => {
                         self.scanning_files = path_buf.clone();
                     }
+                    menu_bar::Message::ImportPlaylistFrom(Some(path)) => {
+                        let result = self.import_playlist_file(&path).and_then(|playlist_id| {
+                            self.state
+                                .set_section(Section::Playlist(playlist_id))
+                                .map_err(|e| e.to_string())
+                        });
+                        if let Err(error) = result {
+                            return Task::done(Message::Error(error));
+                        }
+                    }
+                    menu_bar::Message::OpenSettings => {
+                        return Task::done(Message::OpenSettings(Section::ScannerSettings));
+                    }
+                    menu_bar::Message::SplitRecording => {
+                        if let Err(error) = self.state.set_section(Section::SplitRecording) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                    }
+                    menu_bar::Message::Record => {
+                        self.recorder_view.reset(&self.state);
+                        if let Err(error) = self.state.set_section(Section::Recorder) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                    }
+                    menu_bar::Message::ToggleSplit => {
+                        if self.split_view.is_some() {
+                            return self.handle(Message::Split(split_view::Message::Close));
+                        }
+                        // the library beside the section shown, e.g. to fill a playlist
+                        let mut split_view = SplitView::new(Section::Library);
+                        if let Err(error) = split_view.reload(&self.state) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        self.split_view = Some(split_view);
+                        self.pane_state = split_panes(self.pane_ratio, self.split());
+                    }
+                    menu_bar::Message::ToggleTheme => {
+                        let theme = self.state.theme().toggled();
+                        if let Err(error) = self.state.set_theme(theme) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                    }
+                    menu_bar::Message::OpenRecent(path) => match self.state.last_import_of(&path) {
+                        Ok(Some(id)) => {
+                            if let Err(error) = self.state.set_section(Section::Import(id)) {
+                                return Task::done(Message::Error(error.to_string()));
+                            }
+                        }
+                        // nothing left of its imports, it is read again instead
+                        Ok(None) => self.scanning_files = Some(path),
+                        Err(error) => return Task::done(Message::Error(error.to_string())),
+                    },
+                    menu_bar::Message::RescanRecent(path) => self.scanning_files = Some(path),
+                    menu_bar::Message::ClearRecent => {
+                        if let Err(error) = self.state.clear_recent_files() {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        self.menubar.set_recent(&[]);
+                    }
                     _ => {}
                 };
                 return self.menubar.update(msg).map(Message::MenuBar);
             }
             Message::PaneResize(event) => {
+                // the sidebar is split off first, the split pane within what is left
+                match self.pane_state.layout() {
+                    pane_grid::Node::Split { id, .. } if *id == event.split => {
+                        self.pane_ratio = event.ratio
+                    }
+                    _ => self.split_ratio = event.ratio,
+                }
                 self.pane_state.resize(event.split, event.ratio);
             }
             Message::Sidebar(msg) => {
+                let mut task = Task::none();
                 match &msg {
+                    // released over an entry which does not take tracks
+                    sidebar::Message::Selected(_) if self.drag.is_some() => return Task::none(),
+                    sidebar::Message::DragEntered(target) => {
+                        return self.handle(Message::DragEntered(*target));
+                    }
+                    sidebar::Message::DragLeft(target) => {
+                        return self.handle(Message::DragLeft(*target));
+                    }
                     sidebar::Message::Selected(section) => {
+                        if *section == Section::Conversions {
+                            self.conversions_view.reset(self.state.convert_options());
+                        }
                         if let Err(error) = self.state.set_section(section.to_owned()) {
                             return Task::done(Message::Error(error.to_string()));
                         }
@@ -165,10 +582,21 @@ impl Phonique {
                                 if let Err(error) = self.state.set_section(Section::Playlist(id)) {
                                     return Task::done(Message::Error(error.to_string()));
                                 }
+                                // a new dynamic playlist stays empty until it has rules
+                                if *kind == Some(PlaylistKind::Dynamic) {
+                                    task =
+                                        Task::done(Message::Sidebar(sidebar::Message::Playlists(
+                                            sidebar::playlists::Message::EditRules(id, Vec::new()),
+                                        )));
+                                }
                             }
                         }
-                        sidebar::playlists::Message::ContextAction(option, id, _) => {
+                        sidebar::playlists::Message::ContextAction(option, id, name) => {
                             match option {
+                                MenuOptions::Play | MenuOptions::Shuffle => {
+                                    let shuffle = *option == MenuOptions::Shuffle;
+                                    task = self.play_collection(&Section::Playlist(*id), shuffle);
+                                }
                                 MenuOptions::Rename => {
                                     // self.state.set_section(Section::RenamePlaylist);
                                 }
@@ -182,19 +610,109 @@ impl Phonique {
                                         return Task::done(Message::Error(error.to_string()));
                                     }
                                 }
+                                MenuOptions::Share => {
+                                    let id = *id;
+                                    let file_name =
+                                        format!("{}.{}", name, shared_playlist::EXTENSION);
+                                    task = Task::perform(
+                                        async move {
+                                            rfd::AsyncFileDialog::new()
+                                                .set_file_name(file_name)
+                                                .save_file()
+                                                .await
+                                                .map(|file| file.path().to_path_buf())
+                                        },
+                                        move |path| Message::SharePlaylist(id, path),
+                                    );
+                                }
+                                MenuOptions::ExportM3u(relative) => {
+                                    let (id, relative) = (*id, *relative);
+                                    let file_name = format!("{name}.m3u8");
+                                    task = Task::perform(
+                                        async move {
+                                            rfd::AsyncFileDialog::new()
+                                                .set_file_name(file_name)
+                                                .save_file()
+                                                .await
+                                                .map(|file| file.path().to_path_buf())
+                                        },
+                                        move |path| Message::ExportM3u(id, relative, path),
+                                    );
+                                }
+                                MenuOptions::EditRules => match self.state.playlist_rules(*id) {
+                                    Ok(rules) => {
+                                        task = Task::done(Message::Sidebar(
+                                            sidebar::Message::Playlists(
+                                                sidebar::playlists::Message::EditRules(*id, rules),
+                                            ),
+                                        ));
+                                    }
+                                    Err(error) => {
+                                        return Task::done(Message::Error(error.to_string()));
+                                    }
+                                },
+                                MenuOptions::ExportCdj => {
+                                    let playables = match self.state.playlist_playables(*id) {
+                                        Ok(playables) => playables,
+                                        Err(error) => {
+                                            return Task::done(Message::Error(error.to_string()));
+                                        }
+                                    };
+                                    if let Err(error) = self.state.set_section(Section::CdjExport) {
+                                        return Task::done(Message::Error(error.to_string()));
+                                    }
+                                    task = self
+                                        .cdj_export_view
+                                        .open(name.clone(), playables)
+                                        .map(Message::CdjExport);
+                                }
+                                MenuOptions::MakeOffline => {
+                                    if let Err(error) = self.state.make_playlist_offline(*id) {
+                                        return Task::done(Message::Error(error.to_string()));
+                                    }
+                                    task = self.download_offline();
+                                }
+                                MenuOptions::CopyTracklist => {
+                                    match self.state.playlist_tracklist(*id) {
+                                        Ok(tracklist) => {
+                                            task = iced::clipboard::write(tracklist);
+                                        }
+                                        Err(error) => {
+                                            return Task::done(Message::Error(error.to_string()));
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        sidebar::playlists::Message::SaveRules(id, rules) => {
+                            if let Err(error) = self.state.set_playlist_rules(*id, rules) {
+                                return Task::done(Message::Error(error.to_string()));
                             }
                         }
                         _ => {}
                     },
                     sidebar::Message::Tags(msg) => match msg {
-                        sidebar::tags::Message::Created(_maybe_id, name) => {
-                            if let Err(error) = self.state.create_tag(name) {
+                        sidebar::tags::Message::Created(maybe_id, name) => {
+                            let result = if let Some(id) = maybe_id {
+                                self.state.rename_tag(*id, name)
+                            } else {
+                                self.state.create_tag(name)
+                            };
+                            if let Err(error) = result {
                                 return Task::done(Message::Error(error.to_string()));
                             }
                         }
                         sidebar::tags::Message::ContextAction(menu_option, id, _) => {
                             match menu_option {
-                                sidebar::tags::MenuOptions::Rename => todo!(),
+                                sidebar::tags::MenuOptions::Play
+                                | sidebar::tags::MenuOptions::Shuffle => {
+                                    let shuffle =
+                                        *menu_option == sidebar::tags::MenuOptions::Shuffle;
+                                    task = self.play_collection(&Section::Tag(*id), shuffle);
+                                }
+                                sidebar::tags::MenuOptions::Rename => {
+                                    // the sidebar switches the tag to an editable field
+                                }
                                 sidebar::tags::MenuOptions::Delete => {
                                     if let Err(error) = self.state.delete_tag(*id) {
                                         return Task::done(Message::Error(error.to_string()));
@@ -205,8 +723,30 @@ impl Phonique {
                         }
                         _ => {}
                     },
+                    sidebar::Message::Podcasts(msg) => match msg {
+                        sidebar::podcasts::Message::Subscribe(url) => {
+                            task = fetch_podcast(url.clone());
+                        }
+                        sidebar::podcasts::Message::ContextAction(option, id, url) => {
+                            match option {
+                                sidebar::podcasts::MenuOptions::Refresh => {
+                                    task = fetch_podcast(url.clone());
+                                }
+                                sidebar::podcasts::MenuOptions::Unsubscribe => {
+                                    if let Err(error) = self.state.delete_podcast(*id) {
+                                        return Task::done(Message::Error(error.to_string()));
+                                    }
+                                }
+                            }
+                        }
+                        _ => {}
+                    },
                 };
-                return self.sidebar.update(msg).map(Message::Sidebar);
+                return Task::batch([
+                    task,
+                    self.sidebar.update(msg).map(Message::Sidebar),
+                    self.load_thumbnails(),
+                ]);
             }
             Message::CompactView(compact_view_msg) => {
                 let task = self.compact_view.update(compact_view_msg.clone());
@@ -216,115 +756,2082 @@ impl Phonique {
                         Task::none()
                     }
                     compact_view::Message::DblClick(index, id) => {
-                        self.state.player.current_index = Some(index);
-                        self.state.player.current_playable = Some(id);
-                        let playable = self.state.playables().nth(index).unwrap();
-                        Task::done(player::Message::Play(Arc::new(playable.clone())))
-                            .map(Message::Player)
+                        let position = self.state.resume_position(id);
+                        self.play_at(index, id, position)
+                    }
+                    compact_view::Message::PlayChapter(index, id, start)
+                    | compact_view::Message::PlayBookmark(index, id, start) => {
+                        if self.state.player.current_playable == Some(id) {
+                            Task::done(player::Message::Seek(start)).map(Message::Player)
+                        } else {
+                            self.state.resume_position(id);
+                            self.play_at(index, id, start)
+                        }
+                    }
+                    compact_view::Message::ReleaseFetched(id, result) => {
+                        let chapters = match result {
+                            Ok(release) => release.chapters(),
+                            Err(error) => return Task::done(Message::Error(error)),
+                        };
+                        if chapters.len() < 2 {
+                            return Task::done(Message::Error(
+                                "The Discogs tracklist gives no track lengths".to_string(),
+                            ));
+                        }
+                        // the details read the markers again below
+                        match self.state.link_release(id, &chapters) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::AddBookmark(id, ref note) => {
+                        let position = self.state.player.position;
+                        match self.state.add_bookmark(id, position, note) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::DeleteBookmark(bookmark_id) => {
+                        match self.state.delete_bookmark(bookmark_id) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::SaveNotes(target, ref notes) => {
+                        let result = match target {
+                            NoteTarget::Playable(id) => self.state.set_notes(id, notes),
+                            NoteTarget::Playlist(id) => self.state.set_playlist_notes(id, notes),
+                        };
+                        match result {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::SaveField(id, field_id, ref value) => {
+                        match self.state.set_field_value(id, field_id, value) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::Link(playable_id, original_id, kind) => {
+                        match self.state.link_playables(playable_id, original_id, kind) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::Unlink(playable_id, other_id) => {
+                        match self.state.unlink_playables(playable_id, other_id) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::ShowPlayable(id) => match self.state.reveal(id) {
+                        Ok(Some(index)) => {
+                            Task::done(Message::CompactView(compact_view::Message::Reveal(index)))
+                        }
+                        // deleted from the library meanwhile
+                        Ok(None) => Task::none(),
+                        Err(error) => Task::done(Message::Error(error.to_string())),
+                    },
+                    compact_view::Message::OpenAlbum(ref name) => self.open_album(name.clone()),
+                    compact_view::Message::OpenPlaylist(id) => {
+                        match self.state.set_section(Section::Playlist(id)) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::UnlinkRelease(id) => {
+                        match self.state.link_release(id, &[]) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::SetAudiobook(id, audiobook) => {
+                        match self.state.set_audiobook(id, audiobook) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::Rate(id, rating) => {
+                        match self.state.set_rating(id, rating) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::SortBy(column) => {
+                        // a second click turns the order around, counts, dates and ratings start
+                        // high
+                        let sort = column.map(|column| match self.state.sort() {
+                            Some(order) if order.column == column => SortOrder {
+                                column,
+                                descending: !order.descending,
+                            },
+                            _ => SortOrder {
+                                column,
+                                descending: matches!(
+                                    column,
+                                    ListColumn::PlayCount
+                                        | ListColumn::LastPlayed
+                                        | ListColumn::Rating
+                                        | ListColumn::Released
+                                        | ListColumn::Year
+                                        | ListColumn::DateAdded
+                                ),
+                            },
+                        });
+                        match self.state.set_sort(sort) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::SyncServer(url) => {
+                        match self
+                            .state
+                            .remote_servers()
+                            .into_iter()
+                            .find(|server| server.url() == url)
+                        {
+                            Some(server) => Task::done(Message::ServerSettings(
+                                server_settings::Message::Sync(server),
+                            )),
+                            None => Task::none(),
+                        }
+                    }
+                    compact_view::Message::FilterSource(kind) => {
+                        self.state.set_source_filter(kind);
+                        Task::none()
+                    }
+                    compact_view::Message::ShowAlbums => match self.state.set_album_grid(true) {
+                        Ok(_) => self.load_thumbnails(),
+                        Err(error) => Task::done(Message::Error(error.to_string())),
+                    },
+                    compact_view::Message::ToggleColumn(column) => {
+                        match self.state.toggle_list_column(column) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::ColumnResized(column, width) => {
+                        match self.state.set_column_width(column, width) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::ResetColumnWidths => {
+                        match self.state.reset_column_widths() {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::PlaylistPlayback(id, playback) => {
+                        match self.state.set_playlist_playback(id, playback) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::CopyToClipboard(format, indexes) => {
+                        let playables: Vec<&storage::Playable> = self
+                            .state
+                            .playables()
+                            .enumerate()
+                            .filter(|(i, _)| indexes.contains(i))
+                            .map(|(_, p)| p)
+                            .collect();
+                        iced::clipboard::write(clipboard::format_playables(&playables, format))
+                    }
+                    compact_view::Message::Enqueue(indexes, next) => {
+                        let playables: Vec<storage::Playable> = self
+                            .state
+                            .playables()
+                            .enumerate()
+                            .filter(|(i, _)| indexes.contains(i))
+                            .map(|(_, p)| p.clone())
+                            .collect();
+                        if next {
+                            self.state.enqueue_next(playables);
+                        } else {
+                            self.state.enqueue_last(playables);
+                        }
+                        return Task::batch([task.map(Message::CompactView), self.prefetch()]);
+                    }
+                    compact_view::Message::Drag(index) => {
+                        let rows = self.compact_view.dragged_rows(index);
+                        let ids = self
+                            .state
+                            .playables()
+                            .enumerate()
+                            .filter(|(i, _)| rows.contains(i))
+                            .map(|(_, p)| p.get_id())
+                            .collect();
+                        self.drag = Some(Drag { ids, target: None });
+                        Task::none()
+                    }
+                    compact_view::Message::PlaySelection(indexes) => {
+                        let rows: Vec<&storage::Playable> = self.state.playables().collect();
+                        let playables: Vec<storage::Playable> = indexes
+                            .iter()
+                            .filter_map(|i| rows.get(*i).map(|p| (*p).clone()))
+                            .collect();
+                        self.state.player.shuffle = false;
+                        self.state.play_playables(playables);
+                        return Task::batch([
+                            task.map(Message::CompactView),
+                            self.apply_fade(None),
+                            self.play_queued(),
+                            self.prefetch(),
+                        ]);
+                    }
+                    compact_view::Message::ConvertSelection(indexes) => {
+                        let playables: Vec<storage::Playable> = self
+                            .state
+                            .playables()
+                            .enumerate()
+                            .filter(|(i, _)| indexes.contains(i))
+                            .map(|(_, p)| p.clone())
+                            .collect();
+                        let options = self.state.convert_options().clone();
+                        self.conversions_view.reset(&options);
+                        if let Err(error) = self.state.set_section(Section::Conversions) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        return self
+                            .conversions_view
+                            .enqueue(playables, &options)
+                            .map(Message::Conversions);
+                    }
+                    compact_view::Message::MakeOffline(indexes, true) => {
+                        let playables: Vec<storage::Playable> = self
+                            .state
+                            .playables()
+                            .enumerate()
+                            .filter(|(i, _)| indexes.contains(i))
+                            .map(|(_, p)| p.clone())
+                            .collect();
+                        self.state.make_offline(playables);
+                        self.download_offline()
+                    }
+                    compact_view::Message::MakeOffline(indexes, false) => {
+                        let ids: Vec<PlayableId> = self
+                            .state
+                            .playables()
+                            .enumerate()
+                            .filter(|(i, _)| indexes.contains(i))
+                            .map(|(_, p)| p.id)
+                            .collect();
+                        match self.state.remove_offline(&ids) {
+                            Ok(_) => Task::none(),
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    compact_view::Message::FetchArtwork(indexes, albums) => {
+                        let ids: Vec<PlayableId> = self
+                            .state
+                            .playables()
+                            .enumerate()
+                            .filter(|(i, _)| indexes.contains(i))
+                            .map(|(_, p)| p.id)
+                            .collect();
+                        match self.state.artwork_lookups(&ids, albums) {
+                            Ok(lookups) => {
+                                for lookup in lookups {
+                                    if !self.artwork_queue.contains(&lookup) {
+                                        self.artwork_queue.push_back(lookup);
+                                    }
+                                }
+                                Task::none()
+                            }
+                            Err(error) => Task::done(Message::Error(error.to_string())),
+                        }
                     }
                     _ => Task::none(),
                 };
+                if self.compact_view.details_visible()
+                    && let Some(index) = self.compact_view.single_selection()
+                {
+                    self.state.load_chapters(index);
+                    self.state.load_bookmarks(index);
+                    self.state.load_notes(index);
+                    self.state.load_links(index);
+                    self.state.load_audiobook(index);
+                    self.state.load_artwork(index);
+                }
                 return Task::batch([task.map(Message::CompactView), main_task]);
             }
-            Message::Player(msg) => match msg {
-                player::Message::Next => {
-                    self.state.next_playable();
-                    if let Some(next_index) = self.state.player.current_index
-                        && let Some(next_id) = self.state.player.current_playable
-                    {
-                        return Task::batch([
-                            Task::done(Message::CompactView(compact_view::Message::ScrollTo(
-                                next_index,
-                            ))),
-                            Task::done(Message::CompactView(compact_view::Message::DblClick(
-                                next_index, next_id,
-                            ))),
-                        ]);
+            Message::AlbumGrid(msg) => {
+                let task = self.album_grid.update(msg.clone()).map(Message::AlbumGrid);
+                match msg {
+                    album_grid::Message::Open(album) => {
+                        return Task::batch([task, self.open_album(album)]);
+                    }
+                    album_grid::Message::ShowTracks => {
+                        if let Err(error) = self.state.set_album_grid(false) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                    }
+                    album_grid::Message::Scrolled(_) => {
+                        return Task::batch([task, self.load_thumbnails()]);
                     }
+                    album_grid::Message::Thumbnail(..) => {}
                 }
-                player::Message::Prev => {
-                    self.state.previous_playable();
-                    if let Some(prev_index) = self.state.player.current_index
-                        && let Some(prev_id) = self.state.player.current_playable
-                    {
-                        return Task::batch([
-                            Task::done(Message::CompactView(compact_view::Message::ScrollTo(
-                                prev_index,
-                            ))),
-                            Task::done(Message::CompactView(compact_view::Message::DblClick(
-                                prev_index, prev_id,
-                            ))),
-                        ]);
+                return task;
+            }
+            Message::ScannerSettings(msg) => {
+                let result = match &msg {
+                    scanner_settings::Message::Save => {
+                        let options = self.scanner_settings.options();
+                        self.state.set_scan_options(options)
+                    }
+                    scanner_settings::Message::WatchFolderPicked(Some(folder)) => {
+                        self.state.add_watch_folder(folder.clone())
+                    }
+                    scanner_settings::Message::RemoveWatchFolder(folder) => {
+                        self.state.remove_watch_folder(folder)
                     }
+                    _ => Ok(()),
+                };
+                if let Err(error) = result {
+                    return Task::done(Message::Error(error.to_string()));
                 }
-                player::Message::Like(id) => {
-                    if self.state.is_liked(&id) {
-                        self.state.remove_from_likes(&id);
-                    } else {
-                        self.state.add_to_likes(&id);
+                if let scanner_settings::Message::WatchFolderPicked(Some(_)) = msg {
+                    // a new folder is imported right away rather than at the next tick
+                    return Task::done(Message::FolderWatchTick);
+                }
+                return self
+                    .scanner_settings
+                    .update(msg)
+                    .map(Message::ScannerSettings);
+            }
+            Message::OpenSettings(section) => {
+                if section == Section::ScannerSettings {
+                    self.scanner_settings = ScannerSettings::new(self.state.scan_options());
+                }
+                if let Err(error) = self.state.set_section(section) {
+                    return Task::done(Message::Error(error.to_string()));
+                }
+            }
+            Message::PlaybackSettings(msg) => {
+                let result = match msg {
+                    playback_settings::Message::ContextEndChanged(context_end) => {
+                        self.state.set_context_end(context_end)
+                    }
+                    playback_settings::Message::FadeChanged(fade_ms) => {
+                        if let Err(error) = self.state.set_fade_ms(fade_ms) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        return self
+                            .player
+                            .update(player::Message::FadeChanged(fade_ms))
+                            .map(Message::Player);
+                    }
+                    playback_settings::Message::CrossfadeChanged(secs) => {
+                        if let Err(error) = self.state.set_crossfade_secs(secs) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        return self
+                            .player
+                            .update(player::Message::CrossfadeChanged(secs))
+                            .map(Message::Player);
+                    }
+                    playback_settings::Message::LatencyChanged(latency_ms) => {
+                        if let Err(error) = self.state.set_output_latency_ms(latency_ms) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        return self
+                            .player
+                            .update(player::Message::LatencyChanged(latency_ms))
+                            .map(Message::Player);
+                    }
+                    playback_settings::Message::MatchSourceRate(match_source_rate) => {
+                        if let Err(error) = self.state.set_match_source_rate(match_source_rate) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        return self
+                            .player
+                            .update(player::Message::MatchSourceRateChanged(match_source_rate))
+                            .map(Message::Player);
+                    }
+                    playback_settings::Message::TrimChanged(target, db) => {
+                        if let Err(error) = self.state.set_volume_trim(target, db) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        return self
+                            .player
+                            .update(player::Message::TrimsChanged(
+                                self.state.volume_trims().clone(),
+                            ))
+                            .map(Message::Player);
+                    }
+                    playback_settings::Message::RemoveTrim(target) => {
+                        if let Err(error) = self.state.remove_volume_trim(&target) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        return self
+                            .player
+                            .update(player::Message::TrimsChanged(
+                                self.state.volume_trims().clone(),
+                            ))
+                            .map(Message::Player);
+                    }
+                    playback_settings::Message::HoverPreview(hover_preview) => {
+                        self.state.set_hover_preview(hover_preview)
+                    }
+                    playback_settings::Message::SkipSilenceChanged(threshold_db) => {
+                        if let Err(error) = self.state.set_skip_silence(threshold_db) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        return self
+                            .player
+                            .update(player::Message::SkipSilenceChanged(threshold_db))
+                            .map(Message::Player);
+                    }
+                    playback_settings::Message::LongTrackChanged(minutes) => {
+                        self.state.set_long_track_minutes(minutes)
+                    }
+                    playback_settings::Message::CloseBehaviorChanged(behavior) => {
+                        self.state.set_close_behavior(behavior)
                     }
+                    playback_settings::Message::AlarmChanged(alarm) => self.state.set_alarm(alarm),
+                    playback_settings::Message::ResampleQualityChanged(quality) => {
+                        if let Err(error) = self.state.set_resample_quality(quality) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        return self
+                            .player
+                            .update(player::Message::ResampleQualityChanged(quality))
+                            .map(Message::Player);
+                    }
+                };
+                if let Err(error) = result {
+                    return Task::done(Message::Error(error.to_string()));
                 }
-                _ => return self.player.update(msg).map(Message::Player),
-            },
-            Message::MetadataScanningStarted(path) => {
-                self.scanning_files = path;
             }
-            Message::MetadataScanningEnded => {
-                self.scanning_files = None;
-                let files = self.scannned_files.clone();
-                self.scannned_files.clear();
-                if let Err(error) = self.state.append_bulk(files) {
+            Message::PrivacySettings(msg) => {
+                let result = match msg {
+                    privacy_settings::Message::RetentionChanged(days) => {
+                        self.state.set_history_retention(days)
+                    }
+                    privacy_settings::Message::Incognito(incognito) => {
+                        self.state.set_incognito(incognito);
+                        Ok(())
+                    }
+                    privacy_settings::Message::ClearHistory => self.state.clear_history(),
+                };
+                if let Err(error) = result {
                     return Task::done(Message::Error(error.to_string()));
                 }
             }
-            Message::MetadataScanResult(metadata) => {
-                self.scannned_files.push(metadata);
+            Message::FieldSettings(msg) => {
+                let result = match &msg {
+                    field_settings::Message::Create(name) => {
+                        self.state.create_custom_field(name).map(|_| ())
+                    }
+                    field_settings::Message::Rename(id, name) => {
+                        self.state.rename_custom_field(*id, name)
+                    }
+                    field_settings::Message::Delete(id) => self.state.delete_custom_field(*id),
+                    _ => Ok(()),
+                };
+                if let Err(error) = result {
+                    return Task::done(Message::Error(error.to_string()));
+                }
+                self.field_settings.update(msg);
             }
-            Message::Error(message) => {
-                log::error!("{message}");
+            Message::PlaylistSettings(msg) => {
+                if let playlist_settings::Message::SaveTemplate(template) = &msg
+                    && let Err(error) = self.state.set_tracklist_template(template)
+                {
+                    return Task::done(Message::Error(error.to_string()));
+                }
+                self.playlist_settings.update(msg);
             }
-        }
-
-        Task::none()
-    }
-
-    pub fn view(&self) -> Element<Message> {
-        let pane_grid = PaneGrid::new(
-            &self.pane_state,
-            |_pane, state, _is_maximized| match state {
-                Panes::Sidebar => pane_grid::Content::new(container(
-                    self.sidebar
-                        .view(self.state.section(), &self.state)
-                        .map(Message::Sidebar),
-                )),
-                Panes::Central => {
-                    let central_element = match self.state.section() {
-                        Section::Library
-                        | Section::Favorites
-                        | Section::RecentlyPlayed
-                        | Section::Playlist(_)
-                        | Section::Tag(_) => self
-                            .compact_view
-                            .view(&self.state)
-                            .map(Message::CompactView),
-                        _ => text("Empty").into(),
-                    };
+            Message::ServerSettings(msg) => {
+                let task = match msg.clone() {
+                    server_settings::Message::Add(server) => {
+                        if let Err(error) = self.state.add_webdav_server(server) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        Task::none()
+                    }
+                    server_settings::Message::Remove(url) => {
+                        if let Err(error) = self.state.remove_webdav_server(&url) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        Task::none()
+                    }
+                    server_settings::Message::Index(server, folder) => {
+                        let options = self.state.scan_options().clone();
+                        Task::perform(
+                            webdav::scan(server, folder.clone(), options),
+                            move |result| {
+                                server_settings::Message::Scanned(
+                                    folder,
+                                    result.map_err(|e| e.to_string()),
+                                )
+                            },
+                        )
+                    }
+                    // the files count as indexed, failing to write them is reported as an error
+                    server_settings::Message::Scanned(folder, Ok(files)) => {
+                        let count = files.len();
+                        let import = self.import(&folder, files);
+                        let task = self
+                            .server_settings
+                            .update(msg)
+                            .map(Message::ServerSettings);
+                        return Task::batch([
+                            import,
+                            task,
+                            Task::done(Message::ServerSettings(server_settings::Message::Indexed(
+                                folder,
+                                Ok(count),
+                            ))),
+                        ]);
+                    }
+                    server_settings::Message::Scanned(folder, Err(error)) => {
+                        Task::done(server_settings::Message::Indexed(folder, Err(error)))
+                    }
+                    server_settings::Message::Connected(_, Ok(server)) => {
+                        if let Err(error) = self.state.add_remote_server(server) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        Task::none()
+                    }
+                    server_settings::Message::RemoveServer(url) => {
+                        if let Err(error) = self.state.remove_remote_server(&url) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        Task::none()
+                    }
+                    server_settings::Message::Mirrored(server, result) => {
+                        let result = result.and_then(|mirror| {
+                            self.state
+                                .apply_mirror(&server, mirror)
+                                .map_err(|e| e.to_string())
+                        });
+                        Task::done(server_settings::Message::Synced(
+                            server.name().to_string(),
+                            result,
+                        ))
+                    }
+                    // syncs started from the track list of a server fail out of the settings
+                    server_settings::Message::Synced(name, Err(error))
+                        if *self.state.section() != Section::ServerSettings =>
+                    {
+                        let task = self
+                            .server_settings
+                            .update(msg)
+                            .map(Message::ServerSettings);
+                        return Task::batch([
+                            task,
+                            Task::done(Message::Error(format!("Syncing {name} failed: {error}"))),
+                        ]);
+                    }
+                    _ => Task::none(),
+                };
+                return Task::batch([task, self.server_settings.update(msg)])
+                    .map(Message::ServerSettings);
+            }
+            Message::SyncSettings(msg) => {
+                let task = match msg.clone() {
+                    sync_settings::Message::Sharing(sharing) => {
+                        if let Err(error) = self.state.set_sharing(sharing) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        Task::none()
+                    }
+                    sync_settings::Message::Search => {
+                        Task::perform(sync::discover(PEER_DISCOVERY_TIMEOUT), |result| {
+                            sync_settings::Message::PeersFound(result.map_err(|e| e.to_string()))
+                        })
+                    }
+                    sync_settings::Message::NewPairingCode => {
+                        if let Err(error) = self.state.renew_pairing_code() {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        Task::none()
+                    }
+                    sync_settings::Message::Pull(peer, code) => {
+                        let name = peer.name.clone();
+                        Task::perform(sync::pull(peer.url, code.clone()), move |result| {
+                            sync_settings::Message::Pulled(
+                                name.clone(),
+                                code.clone(),
+                                result.map_err(|e| e.to_string()),
+                            )
+                        })
+                    }
+                    sync_settings::Message::ExportTo(Some(path)) => {
+                        let result = self
+                            .state
+                            .sync_document()
+                            .map_err(|e| e.to_string())
+                            .and_then(|document| {
+                                let sidecar = Sidecar::from_document(&document, unix_now());
+                                sidecar.save(&path).map_err(|e| e.to_string())?;
+                                Ok(format!(
+                                    "Exported {} tracks to {}",
+                                    sidecar.tracks.len(),
+                                    path.display()
+                                ))
+                            });
+                        Task::done(sync_settings::Message::SidecarDone(result))
+                    }
+                    sync_settings::Message::ImportFrom(Some(path)) => {
+                        let result = Sidecar::load(&path)
+                            .map_err(|e| e.to_string())
+                            .and_then(|sidecar| {
+                                self.state
+                                    .merge_sync(&sidecar.to_document())
+                                    .map_err(|e| e.to_string())
+                            })
+                            .map(|summary| {
+                                format!(
+                                    "Imported {} likes, {} ratings and {} tags, {} tracks not found here",
+                                    summary.liked, summary.rated, summary.tagged, summary.unmatched
+                                )
+                            });
+                        Task::done(sync_settings::Message::SidecarDone(result))
+                    }
+                    sync_settings::Message::ImportPlaylistFrom(Some(path)) => {
+                        let result = SharedPlaylist::load(&path)
+                            .map_err(|e| e.to_string())
+                            .and_then(|shared| {
+                                self.state
+                                    .import_shared_playlist(&shared)
+                                    .map_err(|e| e.to_string())
+                            })
+                            .map(|import| {
+                                let mut status = format!(
+                                    "Imported {} with {} tracks",
+                                    import.name, import.matched
+                                );
+                                if !import.missing.is_empty() {
+                                    status.push_str(&format!(
+                                        ", not found here: {}",
+                                        import.missing.join("; ")
+                                    ));
+                                }
+                                status
+                            });
+                        Task::done(sync_settings::Message::PlaylistImported(result))
+                    }
+                    sync_settings::Message::Pulled(name, code, document) => {
+                        let result = document.and_then(|document| {
+                            // the peer took the code, it is given again next time
+                            if let Err(error) = self.state.set_peer_code(&name, &code) {
+                                error!("Error keeping the pairing code of {name}: {error:?}");
+                            }
+                            self.state.merge_sync(&document).map_err(|e| e.to_string())
+                        });
+                        Task::done(sync_settings::Message::Merged(name, result))
+                    }
+                    _ => Task::none(),
+                };
+                return Task::batch([task, self.sync_settings.update(msg)])
+                    .map(Message::SyncSettings);
+            }
+            Message::RefreshShared => {
+                if let Err(error) = self.state.refresh_shared() {
+                    return Task::done(Message::Error(error.to_string()));
+                }
+            }
+            Message::CloseRequested(id) => {
+                if self.player.is_playing() {
+                    match self.state.close_behavior() {
+                        CloseBehavior::Quit => {}
+                        CloseBehavior::KeepPlaying => return window::minimize(id, true),
+                        CloseBehavior::Ask => {
+                            self.quit_prompt = Some(id);
+                            return Task::none();
+                        }
+                    }
+                }
+                return window::is_maximized(id).map(Message::Close);
+            }
+            Message::Palette(msg) => {
+                let task = self
+                    .palette
+                    .update(msg.clone(), &self.state)
+                    .map(Message::Palette);
+                if let command_palette::Message::Run(action) = msg {
+                    return Task::batch([task, self.run_action(action)]);
+                }
+                return task;
+            }
+            Message::QuitPrompt(msg) => {
+                let Some(id) = self.quit_prompt.take() else {
+                    return Task::none();
+                };
+                match msg {
+                    quit_prompt::Message::Quit => {
+                        return window::is_maximized(id).map(Message::Close);
+                    }
+                    quit_prompt::Message::KeepPlaying => return window::minimize(id, true),
+                    quit_prompt::Message::Cancel => {}
+                }
+            }
+            Message::Close(maximized) => {
+                // a maximized window is as large as the screen, it opens again at the size it had
+                // before it was maximized
+                let geometry = WindowGeometry {
+                    size: self
+                        .window_size
+                        .filter(|_| !maximized)
+                        .map(|size| (size.width, size.height)),
+                    position: self
+                        .window_position
+                        .filter(|_| !maximized)
+                        .map(|position| (position.x, position.y)),
+                    maximized,
+                    pane_ratio: Some(self.pane_ratio),
+                };
+                if let Err(error) = self.state.set_window_geometry(geometry) {
+                    error!("Error saving the window geometry: {error}");
+                }
+                let selection = self.compact_view.selection();
+                let scroll_offset = self.compact_view.scroll_offset();
+                if let Err(error) = self.state.save_session(scroll_offset, &selection) {
+                    error!("Error saving the session: {error}");
+                }
+                return iced::exit();
+            }
+            Message::AlarmTick => {
+                if let Some((started, target, ramp_minutes)) = self.alarm_ramp {
+                    let volume = schedule::ramp_volume(started.elapsed(), ramp_minutes, target);
+                    if volume >= target {
+                        self.alarm_ramp = None;
+                    }
+                    return self
+                        .player
+                        .update(player::Message::VolumeChanged(volume))
+                        .map(Message::Player);
+                }
+                let alarm = match self.state.take_due_alarm(unix_now()) {
+                    Ok(Some(alarm)) => alarm,
+                    Ok(None) => return Task::none(),
+                    Err(error) => return Task::done(Message::Error(error.to_string())),
+                };
+                let Some(id) = alarm.playlist else {
+                    return Task::none();
+                };
+                let mut target = self.player.volume_level();
+                if target == 0.0 {
+                    target = 100.0;
+                }
+                let volume = if alarm.ramp_minutes == 0 {
+                    target
+                } else {
+                    self.alarm_ramp = Some((Instant::now(), target, alarm.ramp_minutes));
+                    0.0
+                };
+                let volume_task = self
+                    .player
+                    .update(player::Message::VolumeChanged(volume))
+                    .map(Message::Player);
+                return Task::batch([
+                    volume_task,
+                    self.play_collection(&Section::Playlist(id), false),
+                ]);
+            }
+            Message::AdvancedSettings(msg) => {
+                let result = match msg {
+                    advanced_settings::Message::Refresh => self.state.load_playables(),
+                    advanced_settings::Message::Checkpoint => self.state.checkpoint_database(),
+                    advanced_settings::Message::Optimize => self.state.optimize_database(),
+                };
+                if let Err(error) = result {
+                    return Task::done(Message::Error(error.to_string()));
+                }
+            }
+            Message::Diagnostics(msg) => {
+                if let diagnostics_view::Message::OpenSettings(section) = msg {
+                    return self.handle(Message::OpenSettings(section));
+                }
+                return self
+                    .diagnostics_view
+                    .update(msg, &self.state)
+                    .map(Message::Diagnostics);
+            }
+            Message::AlbumView(msg) => {
+                return match msg {
+                    album_view::Message::Play(shuffle) => {
+                        let section = self.state.section().clone();
+                        self.play_collection(&section, shuffle)
+                    }
+                    album_view::Message::PlayTrack(index, id) => {
+                        let position = self.state.resume_position(id);
+                        self.play_at(index, id, position)
+                    }
+                    album_view::Message::ShowAlbums => {
+                        if let Err(error) = self
+                            .state
+                            .set_album_grid(true)
+                            .and_then(|_| self.state.set_section(Section::Library))
+                        {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        self.load_thumbnails()
+                    }
+                };
+            }
+            Message::ImportHistory(msg) => {
+                let result = match msg {
+                    import_history::Message::Show(id) => {
+                        self.state.set_section(Section::Import(id))
+                    }
+                    import_history::Message::Remove(id) => self.state.remove_import(id),
+                };
+                if let Err(error) = result {
+                    return Task::done(Message::Error(error.to_string()));
+                }
+            }
+            Message::Queue(msg) => {
+                match msg {
+                    queue_view::Message::Move(from, to) => self.state.move_up_next(from, to),
+                    queue_view::Message::Remove(index) => self.state.remove_from_up_next(index),
+                    queue_view::Message::Clear => self.state.clear_up_next(),
+                }
+                return self.prefetch();
+            }
+            Message::RecentlyDeleted(msg) => {
+                let result = match msg {
+                    recently_deleted::Message::RetentionChanged(days) => {
+                        self.state.set_deleted_retention(days)
+                    }
+                    recently_deleted::Message::Restore(id) => self.state.restore_deleted(&[id]),
+                    recently_deleted::Message::Purge(id) => self.state.purge_deleted(&[id]),
+                    recently_deleted::Message::RestoreAll => {
+                        let ids: Vec<PlayableId> =
+                            self.state.deleted().iter().map(|(p, _)| p.id).collect();
+                        self.state.restore_deleted(&ids)
+                    }
+                    recently_deleted::Message::Empty => self.state.empty_deleted(),
+                };
+                if let Err(error) = result {
+                    return Task::done(Message::Error(error.to_string()));
+                }
+            }
+            Message::Snapshots(msg) => {
+                let result = match msg.clone() {
+                    snapshots_view::Message::Take => {
+                        let label = self.snapshots_view.label().to_string();
+                        self.state.take_snapshot(&label)
+                    }
+                    snapshots_view::Message::Compare(id) => self.state.compare_snapshot(id),
+                    snapshots_view::Message::Delete(id) => self.state.delete_snapshot(id),
+                    snapshots_view::Message::CloseComparison => {
+                        self.state.close_comparison();
+                        Ok(())
+                    }
+                    snapshots_view::Message::LabelChanged(_) => Ok(()),
+                };
+                if let Err(error) = result {
+                    return Task::done(Message::Error(error.to_string()));
+                }
+                return self.snapshots_view.update(msg).map(Message::Snapshots);
+            }
+            Message::Conversions(msg) => {
+                let result = match &msg {
+                    conversions_view::Message::Save => self
+                        .state
+                        .set_convert_options(self.conversions_view.options()),
+                    conversions_view::Message::JobFinished(index, Ok(output)) => {
+                        match self.conversions_view.job(*index) {
+                            Some(job) => {
+                                self.state
+                                    .finish_conversion(job.playable.id, output, job.replace)
+                            }
+                            None => Ok(()),
+                        }
+                    }
+                    _ => Ok(()),
+                };
+                let task = self.conversions_view.update(msg).map(Message::Conversions);
+                if let Err(error) = result {
+                    return Task::batch([task, Task::done(Message::Error(error.to_string()))]);
+                }
+                return task;
+            }
+            Message::CdjExport(msg) => {
+                return self.cdj_export_view.update(msg).map(Message::CdjExport);
+            }
+            Message::Vinyl(msg) => {
+                if let vinyl_view::Message::Done(recording, Ok(files)) = &msg {
+                    let options = self.state.scan_options().clone();
+                    let scanned = files
+                        .iter()
+                        .filter_map(|file| match scan_file(file, &options) {
+                            Ok(scanned) => Some(scanned),
+                            Err(e) => {
+                                error!("vinyl: failed to scan {file:?}\n{e:?}");
+                                None
+                            }
+                        })
+                        .collect();
+                    let import = self.import(&recording.to_string_lossy(), scanned);
+                    return Task::batch([import, self.vinyl_view.update(msg).map(Message::Vinyl)]);
+                }
+                return self.vinyl_view.update(msg).map(Message::Vinyl);
+            }
+            Message::Browse(msg) => {
+                let mut preview = Task::none();
+                match &msg {
+                    browse_view::Message::SaveToken(token) => {
+                        if let Err(error) = self.state.set_discogs_token(token) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                    }
+                    browse_view::Message::Connect(discogs_connect::Message::Connected(Ok(
+                        tokens,
+                    ))) => {
+                        if let Err(error) = self.state.set_discogs_oauth(Some(tokens.clone())) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                    }
+                    browse_view::Message::Disconnect => {
+                        if let Err(error) = self.state.set_discogs_oauth(None) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                    }
+                    browse_view::Message::Preview => {
+                        self.state.player.shuffle = false;
+                        self.state.play_playables(self.browse_view.found());
+                        preview = self.play_queued();
+                    }
+                    browse_view::Message::Charts(beatport_charts::Message::Play(index)) => {
+                        self.state.player.shuffle = false;
+                        self.state
+                            .play_playables(self.browse_view.charts().samples_from(*index));
+                        preview = self.play_queued();
+                    }
+                    browse_view::Message::Charts(beatport_charts::Message::SaveAsPlaylist) => {
+                        let charts = self.browse_view.charts();
+                        let Some(genre) = charts.genre() else {
+                            return Task::none();
+                        };
+                        let samples = charts.samples_from(0);
+                        let name = format!("Beatport Top 100 · {genre}");
+                        match self.state.save_streams_as_playlist(&name, &samples) {
+                            Ok((_, name)) => self.browse_view.charts_mut().set_status(format!(
+                                "Saved {} samples as the playlist {name}",
+                                samples.len()
+                            )),
+                            Err(error) => return Task::done(Message::Error(error.to_string())),
+                        }
+                    }
+                    browse_view::Message::HoverTick => {
+                        if self.state.hover_preview()
+                            && let Some(playable) = self.browse_view.preview_due()
+                        {
+                            preview = self
+                                .player
+                                .update(player::Message::Preview(Some(Arc::new(playable))))
+                                .map(Message::Player);
+                        }
+                    }
+                    browse_view::Message::Unhover(index)
+                        if self.browse_view.previewing() == Some(*index) =>
+                    {
+                        preview = self
+                            .player
+                            .update(player::Message::Preview(None))
+                            .map(Message::Player);
+                    }
+                    // the rows under the pointer go away with the release or the tab shown
+                    browse_view::Message::Dug(Ok(_))
+                    | browse_view::Message::Show(_)
+                    | browse_view::Message::ShowTab(_)
+                        if self.browse_view.previewing().is_some() =>
+                    {
+                        preview = self
+                            .player
+                            .update(player::Message::Preview(None))
+                            .map(Message::Player);
+                    }
+                    _ => {}
+                }
+                let refresh = matches!(
+                    msg,
+                    browse_view::Message::Dug(Ok(_)) | browse_view::Message::Show(_)
+                );
+                let task = self
+                    .browse_view
+                    .update(msg, self.state.discogs_credentials())
+                    .map(Message::Browse);
+                if refresh && let Some(release) = self.browse_view.shown() {
+                    match self.state.release_in_library(release) {
+                        Ok(found) => self.browse_view.set_found(found),
+                        Err(error) => return Task::done(Message::Error(error.to_string())),
+                    }
+                }
+                return Task::batch([task, preview]);
+            }
+            Message::Recorder(msg) => {
+                let result = match &msg {
+                    recorder_view::Message::SourceSelected(source) => {
+                        self.state.set_record_source(&source.name)
+                    }
+                    recorder_view::Message::FormatSelected(format) => {
+                        self.state.set_record_format(*format)
+                    }
+                    recorder_view::Message::Finished(path, title, duration) => {
+                        self.state.import_recording(path, title, *duration)
+                    }
+                    _ => Ok(()),
+                };
+                let task = self.recorder_view.update(msg).map(Message::Recorder);
+                if let Err(error) = result {
+                    return Task::batch([task, Task::done(Message::Error(error.to_string()))]);
+                }
+                return task;
+            }
+            Message::SongInfo(msg) => {
+                self.state.close_song_info();
+                let result = match msg {
+                    song_info::Message::Close => Ok(()),
+                    song_info::Message::ShowAlbum(name) => return self.open_album(name),
+                    song_info::Message::ShowArtist(name) => {
+                        if let Err(error) = self.state.set_section(Section::Library) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        return Task::batch([
+                            Task::done(Message::MenuBar(menu_bar::Message::SearchTypeIn(
+                                name.clone(),
+                            ))),
+                            Task::done(Message::MenuBar(menu_bar::Message::Search(name))),
+                        ]);
+                    }
+                    song_info::Message::ShowPlaylist(id) => {
+                        self.state.set_section(Section::Playlist(id))
+                    }
+                    song_info::Message::ShowTag(id) => self.state.set_section(Section::Tag(id)),
+                };
+                if let Err(error) = result {
+                    return Task::done(Message::Error(error.to_string()));
+                }
+            }
+            Message::Insights(msg) => {
+                let result = match msg.clone() {
+                    insights_view::Message::MonthsChanged(months) => {
+                        self.state.set_insight_months(months)
+                    }
+                    insights_view::Message::Show(section) => self.state.set_section(section),
+                    insights_view::Message::Queue(section) => {
+                        return self.play_collection(&section, false);
+                    }
+                    insights_view::Message::Tag(ids) => {
+                        let name = self.insights_view.tag_name().to_string();
+                        self.state.tag_playables(&name, &ids)
+                    }
+                    insights_view::Message::Remove(ids) => self.state.remove_from_library(&ids),
+                    insights_view::Message::SuggestGenres => {
+                        let (suggestions, unknown) = match self.state.genre_suggestions() {
+                            Ok(found) => found,
+                            Err(error) => return Task::done(Message::Error(error.to_string())),
+                        };
+                        if let Err(error) = self.state.set_section(Section::GenreSuggestions) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        let credentials = self.state.discogs_credentials();
+                        return self
+                            .genre_suggestions_view
+                            .open(suggestions, unknown, credentials)
+                            .map(Message::GenreSuggestions);
+                    }
+                    insights_view::Message::TagNameChanged(_) => Ok(()),
+                };
+                if let Err(error) = result {
+                    return Task::done(Message::Error(error.to_string()));
+                }
+                return self.insights_view.update(msg).map(Message::Insights);
+            }
+            Message::GenreSuggestions(msg) => {
+                if let genre_suggestions_view::Message::Save(genres) = &msg {
+                    for (id, genre) in genres {
+                        if let Err(error) = self.state.set_genre(*id, genre) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                    }
+                }
+                return self
+                    .genre_suggestions_view
+                    .update(msg)
+                    .map(Message::GenreSuggestions);
+            }
+            Message::PodcastView(msg) => {
+                let task = self
+                    .podcast_view
+                    .update(msg.clone())
+                    .map(Message::PodcastView);
+                let main_task = match msg {
+                    podcast_view::Message::Play(id) => match self.state.play_episode(id) {
+                        Some(episode) => {
+                            let position = episode.position as u64;
+                            Task::done(player::Message::PlayFrom(Arc::new(episode), position))
+                                .map(Message::Player)
+                        }
+                        None => Task::none(),
+                    },
+                    podcast_view::Message::Download(id) => {
+                        let episode = self.state.episodes().iter().find(|e| e.id == id);
+                        match (episode, podcasts::downloads_dir()) {
+                            (Some(episode), Some(dir)) => Task::perform(
+                                podcasts::download_episode(
+                                    episode.audio_url.clone(),
+                                    dir,
+                                    self.state
+                                        .podcasts()
+                                        .iter()
+                                        .find(|podcast| podcast.id == episode.podcast_id)
+                                        .map(|podcast| podcast.title.clone())
+                                        .unwrap_or_default(),
+                                    id,
+                                ),
+                                move |result| {
+                                    Message::EpisodeDownloaded(
+                                        id,
+                                        result.map_err(|e| e.to_string()),
+                                    )
+                                },
+                            ),
+                            _ => Task::done(Message::EpisodeDownloaded(
+                                id,
+                                Err("no download directory".to_string()),
+                            )),
+                        }
+                    }
+                    _ => Task::none(),
+                };
+                return Task::batch([task, main_task]);
+            }
+            Message::PodcastFetched(url, result) => match result {
+                Ok(feed) => {
+                    let result = self
+                        .state
+                        .save_podcast(&url, feed)
+                        .and_then(|id| self.state.set_section(Section::Podcast(id)));
+                    if let Err(error) = result {
+                        return Task::done(Message::Error(error.to_string()));
+                    }
+                }
+                Err(error) => {
+                    return Task::done(Message::Error(format!("Failed to fetch {url}: {error}")));
+                }
+            },
+            Message::EpisodeDownloaded(id, result) => {
+                let task = self
+                    .podcast_view
+                    .update(podcast_view::Message::DownloadFinished(id))
+                    .map(Message::PodcastView);
+                let result = match result {
+                    Ok(path) => self
+                        .state
+                        .set_episode_downloaded(id, &path.to_string_lossy())
+                        .map_err(|e| e.to_string()),
+                    Err(error) => Err(error),
+                };
+                if let Err(error) = result {
+                    return Task::batch([
+                        task,
+                        Task::done(Message::Error(format!(
+                            "Failed to download episode {id}: {error}"
+                        ))),
+                    ]);
+                }
+                return task;
+            }
+            Message::Player(msg) => match msg {
+                player::Message::Next => {
+                    if let Err(error) = self.state.record_skip() {
+                        log::error!("Error recording play: {error}");
+                    }
+                    if !self.state.next_playable() {
+                        return Task::none();
+                    }
+                    return self.play_current();
+                }
+                player::Message::Prev => {
+                    if let Err(error) = self.state.record_skip() {
+                        log::error!("Error recording play: {error}");
+                    }
+                    self.state.previous_playable();
+                    return self.play_current();
+                }
+                player::Message::Play(_) | player::Message::PlayFrom(..) => {
+                    let task = self.player.update(msg).map(Message::Player);
+                    return Task::batch([task, self.prefetch()]);
+                }
+                player::Message::NextStarted(ref playable) => {
+                    let id = playable.get_id();
+                    if let Err(error) = self.state.record_play() {
+                        log::error!("Error recording play: {error}");
+                    }
+                    self.state.finish_playback();
+                    let task = self.player.update(msg.clone()).map(Message::Player);
+                    if !self.state.next_playable() {
+                        return task;
+                    }
+                    if self.state.player.current_playable != Some(id) {
+                        // the list changed since the track was prepared, the state's pick plays
+                        return Task::batch([task, self.play_current()]);
+                    }
+                    self.state.resume_position(id);
+                    return Task::batch([task, self.prefetch()]);
+                }
+                player::Message::ProgressUpdate(current, _) => {
+                    self.state.update_playback_position(current);
+                    self.state.load_playing_chapters();
+                    return self.player.update(msg).map(Message::Player);
+                }
+                player::Message::EndPlay => {
+                    if let Err(error) = self.state.record_play() {
+                        log::error!("Error recording play: {error}");
+                    }
+                    // counted already, the player follows up with `Next`
+                    self.state.player.position = 0;
+                    let task = self.player.update(msg).map(Message::Player);
+                    // a finished episode does not roll over into the track list
+                    if !self.state.finish_playback() {
+                        return task;
+                    }
+                }
+                player::Message::VolumeChanged(_) | player::Message::ToggleVolume => {
+                    // the listener took over, the alarm stops raising the volume
+                    self.alarm_ramp = None;
+                    return self.player.update(msg).map(Message::Player);
+                }
+                player::Message::EqPreset(preset) => {
+                    let output = self.player.output().to_string();
+                    if let Err(error) = self.state.set_eq_preset(&output, preset) {
+                        return Task::done(Message::Error(error.to_string()));
+                    }
+                    return self
+                        .player
+                        .update(player::Message::EqCurvesChanged(
+                            self.state.eq_curves().clone(),
+                        ))
+                        .map(Message::Player);
+                }
+                player::Message::EqBand(band, db) => {
+                    let output = self.player.output().to_string();
+                    if let Err(error) = self.state.set_eq_band(&output, band, db) {
+                        return Task::done(Message::Error(error.to_string()));
+                    }
+                    return self
+                        .player
+                        .update(player::Message::EqCurvesChanged(
+                            self.state.eq_curves().clone(),
+                        ))
+                        .map(Message::Player);
+                }
+                player::Message::CastFailed(ref error) => {
+                    let error = error.clone();
+                    let task = self.player.update(msg).map(Message::Player);
+                    return Task::batch([task, Task::done(Message::Error(error))]);
+                }
+                player::Message::ShowSongInfo(id) => {
+                    // a second click on the title closes the panel
+                    if self
+                        .state
+                        .song_info()
+                        .is_some_and(|info| info.playable.id == id)
+                    {
+                        self.state.close_song_info();
+                    } else if let Err(error) = self.state.open_song_info(id) {
+                        return Task::done(Message::Error(error.to_string()));
+                    }
+                }
+                player::Message::Like(id) if id < 0 => {
+                    let Some(playable) = self.player.current_playable() else {
+                        return Task::none();
+                    };
+                    let liked = match self.state.like_stream(playable.as_ref()) {
+                        Ok(liked) => liked,
+                        Err(error) => return Task::done(Message::Error(error.to_string())),
+                    };
+                    let task = self.player.update(msg).map(Message::Player);
+                    let adopt = self
+                        .player
+                        .update(player::Message::Adopt(Arc::new(liked)))
+                        .map(Message::Player);
+                    return Task::batch([task, adopt]);
+                }
+                player::Message::Like(id) => {
+                    let liked = !self.state.is_liked(&id);
+                    if liked {
+                        self.state.add_to_likes(&id);
+                    } else {
+                        self.state.remove_from_likes(&id);
+                    }
+                    let star = match self.player.current_playable() {
+                        Some(playable) if playable.get_id() == id => {
+                            star_on_server(playable.as_ref(), liked)
+                        }
+                        _ => Task::none(),
+                    };
+                    return Task::batch([star, self.player.update(msg).map(Message::Player)]);
+                }
+                _ => return self.player.update(msg).map(Message::Player),
+            },
+            Message::SharePlaylist(id, Some(path)) => {
+                let result = self
+                    .state
+                    .share_playlist(id)
+                    .map_err(|e| e.to_string())
+                    .and_then(|shared| shared.save(&path).map_err(|e| e.to_string()));
+                if let Err(error) = result {
+                    return Task::done(Message::Error(error));
+                }
+            }
+            Message::SharePlaylist(_, None) => {}
+            Message::ExportM3u(id, relative, Some(path)) => {
+                let result = self
+                    .state
+                    .playlist_playables(id)
+                    .map_err(|e| e.to_string())
+                    .and_then(|playables| {
+                        m3u::save(&path, &playables, relative).map_err(|e| e.to_string())
+                    });
+                if let Err(error) = result {
+                    return Task::done(Message::Error(error));
+                }
+            }
+            Message::ExportM3u(_, _, None) => {}
+            Message::DragEntered(target) => {
+                if let Some(drag) = &mut self.drag {
+                    drag.target = Some(target);
+                }
+            }
+            Message::DragLeft(target) => {
+                // the next target may have been entered first
+                if let Some(drag) = &mut self.drag
+                    && drag.target == Some(target)
+                {
+                    drag.target = None;
+                }
+            }
+            Message::DragEnded => {
+                let Some(drag) = self.drag.take() else {
+                    return Task::none();
+                };
+                // dropped on a row of the playlist shown, the pane of which is a target too
+                let reorder = self.compact_view.end_drag().filter(|_| {
+                    self.state.can_reorder()
+                        && (drag.target.is_none()
+                            || drag.target == self.pane_target(self.state.section()))
+                });
+                let result = if let Some((rows, to)) = reorder {
+                    self.state.move_rows(&rows, to)
+                } else {
+                    match drag.target {
+                        Some(DropTarget::Playlist(id)) => {
+                            self.state.add_to_playlist(id, &drag.ids).map(|_| ())
+                        }
+                        Some(DropTarget::Tag(id)) => self.state.add_to_tag(id, &drag.ids),
+                        // dropped anywhere else
+                        None => Ok(()),
+                    }
+                };
+                let result = result.and_then(|_| match &mut self.split_view {
+                    Some(split_view) => split_view.reload(&self.state),
+                    None => Ok(()),
+                });
+                if let Err(error) = result {
+                    return Task::done(Message::Error(error.to_string()));
+                }
+            }
+            Message::Split(msg) => {
+                let Some(split_view) = &mut self.split_view else {
+                    return Task::none();
+                };
+                split_view.update(msg.clone());
+                match msg {
+                    split_view::Message::Pick(_) => {
+                        if let Err(error) = split_view.reload(&self.state) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                    }
+                    split_view::Message::Drag(index) => {
+                        self.drag = split_view.dragged(index).map(|id| Drag {
+                            ids: vec![id],
+                            target: None,
+                        });
+                    }
+                    split_view::Message::Close => {
+                        self.split_view = None;
+                        self.pane_state = split_panes(self.pane_ratio, None);
+                    }
+                    split_view::Message::Selected(_) => {}
+                }
+            }
+            Message::WatchTick => {
+                let due = self.state.due_watches(unix_now());
+                return self.check_watches(due);
+            }
+            Message::WatchChecked(id, result) => {
+                self.checking_watches.remove(&id);
+                let releases = result.unwrap_or_else(|error| {
+                    error!("Error checking watch {id}: {error}");
+                    Vec::new()
+                });
+                if let Err(error) = self.state.save_watch_check(id, unix_now(), &releases) {
+                    return Task::done(Message::Error(error.to_string()));
+                }
+            }
+            Message::FolderWatchTick => {
+                let folders = self.state.watch_folders().to_vec();
+                self.folder_watcher.retain(&folders);
+                if folders.is_empty() || self.listing_folders {
+                    return Task::none();
+                }
+                self.listing_folders = true;
+                let options = self.state.scan_options().clone();
+                return Task::perform(
+                    async move {
+                        folders
+                            .into_iter()
+                            .map(|folder| {
+                                let listing = folder_watch::list(&folder, &options);
+                                (folder, listing)
+                            })
+                            .collect()
+                    },
+                    Message::FoldersListed,
+                );
+            }
+            Message::ArtworkTick => {
+                if self.fetching_artwork {
+                    return Task::none();
+                }
+                let Some(lookup) = self.artwork_queue.pop_front() else {
+                    return Task::none();
+                };
+                self.fetching_artwork = true;
+                let ids = lookup.ids.clone();
+                return Task::perform(
+                    artwork::fetch(lookup, self.state.discogs_credentials()),
+                    move |result| Message::ArtworkFetched(ids, result.map_err(|e| e.to_string())),
+                );
+            }
+            Message::ArtworkFetched(ids, result) => {
+                self.fetching_artwork = false;
+                match result {
+                    Ok(Some(artwork)) => {
+                        if let Err(error) = self.state.set_artwork(&ids, artwork) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        self.album_grid.forget(&ids);
+                        return self.load_thumbnails();
+                    }
+                    Ok(None) => info!("No artwork found for {} playables", ids.len()),
+                    Err(error) => {
+                        // the lookups left would most likely fail the same way
+                        self.artwork_queue.clear();
+                        return Task::done(Message::Error(error));
+                    }
+                }
+            }
+            Message::OfflineDownloaded(id, result) => {
+                let path = result.as_ref().ok().map(|path| path.to_string_lossy());
+                let recorded = self.state.finish_offline_download(id, path.as_deref());
+                let error = match (result, recorded) {
+                    (Err(error), _) => Some(format!("Failed to download track {id}: {error}")),
+                    (Ok(_), Err(error)) => Some(error.to_string()),
+                    (Ok(_), Ok(_)) => None,
+                };
+                // the downloads left go on, a failed one stays streamed
+                let next = self.download_offline();
+                return match error {
+                    Some(error) => Task::batch([next, Task::done(Message::Error(error))]),
+                    None => next,
+                };
+            }
+            Message::LibraryPage(load, result) => match result {
+                Ok(page) => {
+                    if let Some(next) = self.state.append_library_page(load, page) {
+                        return self.read_library_page(next);
+                    }
+                }
+                Err(error) => return Task::done(Message::Error(error)),
+            },
+            Message::Starred(result) => {
+                if let Err(error) = result {
+                    return Task::done(Message::Error(error));
+                }
+            }
+            Message::FoldersListed(listings) => {
+                self.listing_folders = false;
+                let mut scans = Vec::new();
+                for (folder, listing) in listings {
+                    let Some(listing) = listing else {
+                        continue;
+                    };
+                    let changes = self.folder_watcher.update(&folder, listing);
+                    let removed = if changes.first {
+                        // files deleted while the app was closed
+                        let listed: HashSet<&PathBuf> = changes.settled.iter().collect();
+                        self.state.library_paths_under(&folder).map(|paths| {
+                            paths
+                                .into_iter()
+                                .filter(|path| !listed.contains(path))
+                                .collect()
+                        })
+                    } else {
+                        Ok(changes.removed)
+                    };
+                    let result = removed
+                        .and_then(|removed| self.state.remove_missing(&removed))
+                        .and_then(|_| self.state.unknown_paths(&changes.settled));
+                    match result {
+                        Ok(added) if !added.is_empty() => {
+                            let options = self.state.scan_options().clone();
+                            scans.push(Task::perform(
+                                folder_watch::scan(added, options),
+                                move |files| Message::FolderScanned(folder.clone(), files),
+                            ));
+                        }
+                        Ok(_) => {}
+                        Err(error) => error!("Error updating watched folder {folder:?}: {error}"),
+                    }
+                }
+                return Task::batch(scans);
+            }
+            Message::FolderScanned(folder, files) => {
+                return self.import(&folder.to_string_lossy(), files);
+            }
+            Message::Imported(playlist_id, result) => {
+                let result = result.and_then(|ids| {
+                    self.state
+                        .imported(playlist_id, ids)
+                        .map_err(|e| e.to_string())
+                });
+                if let Err(error) = result {
+                    return Task::done(Message::Error(format!("Import failed: {error}")));
+                }
+            }
+            Message::ListenNow(msg) => {
+                let result = match &msg {
+                    listen_now::Message::Watched(Ok((kind, discogs_id, name))) => {
+                        match self.state.add_watch(*kind, *discogs_id, name) {
+                            Ok(_) => {
+                                let due = self.state.due_watches(unix_now());
+                                let task = self.listen_now.update(msg).map(Message::ListenNow);
+                                return Task::batch([task, self.check_watches(due)]);
+                            }
+                            Err(error) => Err(error),
+                        }
+                    }
+                    listen_now::Message::IntervalChanged(id, hours) => {
+                        self.state.set_watch_interval(*id, *hours)
+                    }
+                    listen_now::Message::Remove(id) => self.state.remove_watch(*id),
+                    listen_now::Message::Dismiss(id) => self.state.dismiss_new_releases(*id),
+                    listen_now::Message::Open(release_id) => {
+                        if let Err(error) = self.state.set_section(Section::Browse) {
+                            return Task::done(Message::Error(error.to_string()));
+                        }
+                        return Task::done(Message::Browse(browse_view::Message::Open(
+                            *release_id,
+                        )));
+                    }
+                    listen_now::Message::OpenAlbum(album) => {
+                        return self.open_album(album.clone());
+                    }
+                    listen_now::Message::CheckNow => {
+                        let watches = self.state.watches().to_vec();
+                        return self.check_watches(watches);
+                    }
+                    _ => Ok(()),
+                };
+                let task = self.listen_now.update(msg).map(Message::ListenNow);
+                if let Err(error) = result {
+                    return Task::batch([task, Task::done(Message::Error(error.to_string()))]);
+                }
+                return task;
+            }
+            Message::MetadataScanningStarted(path) => {
+                self.scanning_files = path;
+            }
+            Message::MetadataScanningEnded => {
+                let source = self.scanning_files.take().unwrap_or_default();
+                let files = self.scannned_files.clone();
+                self.scannned_files.clear();
+                let import = self.import(&source.to_string_lossy(), files);
+                if !source.as_os_str().is_empty() {
+                    if let Err(error) = self.state.add_recent_file(source) {
+                        return Task::batch([
+                            import,
+                            Task::done(Message::Error(error.to_string())),
+                        ]);
+                    }
+                    self.menubar.set_recent(self.state.recent_files());
+                }
+                return import;
+            }
+            Message::MetadataScanResult(metadata) => {
+                self.scannned_files.push(metadata);
+            }
+            Message::Error(message) => {
+                log::error!("{message}");
+            }
+            // taken care of before
+            Message::Navigate(_) => {}
+        }
+
+        Task::none()
+    }
+
+    fn play_at(&mut self, index: usize, id: PlayableId, position: u64) -> Task<Message> {
+        // a selection restored from the last session can point past the rows loaded so far
+        let Some(playable) = self.state.playables().nth(index) else {
+            return Task::none();
+        };
+        let playable = Arc::new(self.state.with_artwork(playable));
+        self.state.clear_queue();
+        self.state.player.current_episode = None;
+        self.state.player.current_index = Some(index);
+        self.state.player.current_playable = Some(id);
+        let playback = self.state.playlist_playback(self.state.section());
+        // a playlist which always shuffles picks each next track at random
+        self.state.player.shuffle = playback.is_some_and(|playback| playback.shuffle);
+        let fade = self.apply_fade(playback);
+        let play = Task::done(player::Message::PlayFrom(playable, position)).map(Message::Player);
+        Task::batch([fade, play])
+    }
+
+    // fetches the releases of each watch not being checked already
+    fn check_watches(&mut self, watches: Vec<Watch>) -> Task<Message> {
+        Task::batch(
+            watches
+                .into_iter()
+                .filter(|watch| self.checking_watches.insert(watch.id))
+                .map(|watch| {
+                    Task::perform(
+                        discogs::fetch_watch_releases(watch.kind, watch.discogs_id as u64),
+                        move |result| {
+                            Message::WatchChecked(watch.id, result.map_err(|e| e.to_string()))
+                        },
+                    )
+                }),
+        )
+    }
+
+    // makes a playlist of an M3U or PLS file, scanning the files it lists which are not in the
+    // library yet
+    fn import_playlist_file(&mut self, path: &Path) -> Result<i64, String> {
+        let playlist = m3u::load(path).map_err(|e| e.to_string())?;
+        let options = self.state.scan_options().clone();
+        let scanned = self
+            .state
+            .unknown_files(&playlist)
+            .map_err(|e| e.to_string())?
+            .iter()
+            .filter_map(|file| match scan_file(file, &options) {
+                Ok(scanned) => Some(scanned),
+                Err(e) => {
+                    error!("import_playlist: failed to scan {file:?}\n{e:?}");
+                    None
+                }
+            })
+            .collect();
+        let import = self
+            .state
+            .import_playlist_file(&path.to_string_lossy(), &playlist, scanned)
+            .map_err(|e| e.to_string())?;
+        if !import.missing.is_empty() {
+            log::warn!(
+                "import_playlist: {} tracks of {} not found: {}",
+                import.missing.len(),
+                import.name,
+                import.missing.join("; ")
+            );
+        }
+        Ok(import.playlist_id)
+    }
+
+    fn open_album(&mut self, name: String) -> Task<Message> {
+        match self.state.set_section(Section::Album(name)) {
+            Ok(_) => Task::none(),
+            Err(error) => Task::done(Message::Error(error.to_string())),
+        }
+    }
+
+    // starts a playlist or tag from the sidebar, the central view is left as it is
+    // carries out what was picked in the command palette
+    fn run_action(&mut self, action: Action) -> Task<Message> {
+        let message = match action {
+            Action::Open(section) => Message::Sidebar(sidebar::Message::Selected(section)),
+            Action::CreatePlaylist => Message::Sidebar(sidebar::Message::Playlists(
+                sidebar::playlists::Message::AddingPlaylist,
+            )),
+            Action::TogglePlay => Message::Player(player::Message::TogglePlay),
+            Action::Next => Message::Player(player::Message::Next),
+            Action::Previous => Message::Player(player::Message::Prev),
+            Action::ToggleShuffle => Message::Player(player::Message::ShuffleToggle),
+            Action::ToggleTheme => Message::MenuBar(menu_bar::Message::ToggleTheme),
+            Action::ToggleSplit => Message::MenuBar(menu_bar::Message::ToggleSplit),
+            Action::AddFolder => Message::MenuBar(menu_bar::Message::OpenFolder),
+            Action::ImportPlaylist => Message::MenuBar(menu_bar::Message::ImportPlaylist),
+            Action::Settings => Message::OpenSettings(Section::ScannerSettings),
+        };
+        self.handle(message)
+    }
 
-                    let content = container(central_element).width(Length::Fill).padding(16);
+    fn play_collection(&mut self, section: &Section, shuffle: bool) -> Task<Message> {
+        let playback = self.state.playlist_playback(section);
+        let shuffle = shuffle || playback.is_some_and(|playback| playback.shuffle);
+        self.state.player.shuffle = false;
+        if let Err(error) = self.state.play_collection(section, shuffle) {
+            return Task::done(Message::Error(error.to_string()));
+        }
+        Task::batch([self.apply_fade(playback), self.play_queued()])
+    }
+
+    // the playlist's own fade while it plays, the playback setting for everything else
+    fn apply_fade(&mut self, playback: Option<storage::PlaylistPlayback>) -> Task<Message> {
+        let fade_ms = playback
+            .and_then(|playback| playback.fade_ms)
+            .unwrap_or(self.state.fade_ms());
+        self.player
+            .update(player::Message::FadeChanged(fade_ms))
+            .map(Message::Player)
+    }
+
+    // plays the playable the state moved on to, from a queue or the visible list
+    fn play_current(&mut self) -> Task<Message> {
+        if self.state.queue_active() || self.state.playing_up_next() {
+            return self.play_queued();
+        }
+        if let Some(index) = self.state.player.current_index
+            && let Some(id) = self.state.player.current_playable
+        {
+            return Task::batch([
+                Task::done(Message::CompactView(compact_view::Message::ScrollTo(index))),
+                Task::done(Message::CompactView(compact_view::Message::DblClick(
+                    index, id,
+                ))),
+            ]);
+        }
+        Task::none()
+    }
+
+    // reads the rest of a library shown with its first page, once it was shown
+    fn load_library_pages(&mut self) -> Task<Message> {
+        match self.state.take_library_load() {
+            Some(load) => self.read_library_page(load),
+            None => Task::none(),
+        }
+    }
+
+    // adds scanned files to the library, written on the storage worker when there is one so a
+    // large import does not hold up the UI thread
+    fn import(&mut self, source: &str, files: Vec<ScannedFile>) -> Task<Message> {
+        let import = match self.state.begin_import(source, files) {
+            Ok(Some(import)) => import,
+            Ok(None) => return Task::none(),
+            Err(error) => return Task::done(Message::Error(error.to_string())),
+        };
+        let Some(storage) = self.state.async_storage() else {
+            return Task::none();
+        };
+        let playlist_id = import.playlist_id;
+        Task::perform(storage.import(import), move |result| {
+            Message::Imported(playlist_id, result.map_err(|e| e.to_string()))
+        })
+    }
+
+    // downloads the next remote playable queued to be made available offline, one at a time
+    fn download_offline(&mut self) -> Task<Message> {
+        let Some(playable) = self.state.next_offline_download() else {
+            return Task::none();
+        };
+        let id = playable.id;
+        match offline::offline_dir() {
+            Some(dir) => Task::perform(offline::download(playable, dir), move |result| {
+                Message::OfflineDownloaded(id, result.map_err(|e| e.to_string()))
+            }),
+            None => Task::done(Message::OfflineDownloaded(
+                id,
+                Err("no folder for offline files".to_string()),
+            )),
+        }
+    }
+
+    fn read_library_page(&self, load: LibraryLoad) -> Task<Message> {
+        let Some(storage) = self.state.async_storage() else {
+            return Task::none();
+        };
+        Task::perform(
+            storage.read_library_page(load.after, load.limit),
+            move |result| Message::LibraryPage(load, result.map_err(|e| e.to_string())),
+        )
+    }
+
+    // makes the thumbnails of the album tiles on screen which have none yet
+    fn load_thumbnails(&mut self) -> Task<Message> {
+        if !self.state.album_grid() {
+            return Task::none();
+        }
+        let covers = self.album_grid.missing(&self.state.albums());
+        let storage = self.state.async_storage();
+        Task::batch(covers.into_iter().map(|id| {
+            let done =
+                move |thumbnail| Message::AlbumGrid(album_grid::Message::Thumbnail(id, thumbnail));
+            match &storage {
+                // the artwork is read off the UI thread as well
+                Some(storage) => {
+                    let artwork = storage.read_artwork(id);
+                    let thumbnail = async move {
+                        let artwork = artwork.await.unwrap_or_else(|err| {
+                            error!("Error reading artwork of {id}: {err:?}");
+                            None
+                        });
+                        album_grid::thumbnail(artwork).await
+                    };
+                    Task::perform(thumbnail, done)
+                }
+                None => Task::perform(album_grid::thumbnail(self.state.cover_artwork(id)), done),
+            }
+        }))
+    }
+
+    // hands the player what follows the playing track, to start it without a gap
+    fn prefetch(&mut self) -> Task<Message> {
+        let next = self
+            .state
+            .peek_next()
+            .filter(|playable| !self.state.resumes_midway(playable.id))
+            .map(|playable| Arc::new(self.state.with_artwork(playable)) as Arc<dyn AudioPlayable>);
+        self.player
+            .update(player::Message::Prefetch(next))
+            .map(Message::Player)
+    }
+
+    fn play_queued(&mut self) -> Task<Message> {
+        let Some(playable) = self
+            .state
+            .current_queued()
+            .map(|playable| self.state.with_artwork(playable))
+        else {
+            return Task::none();
+        };
+        let position = self.state.resume_position(playable.id);
+        Task::done(player::Message::PlayFrom(Arc::new(playable), position)).map(Message::Player)
+    }
+
+    // the ratio of the split pane while it is open
+    fn split(&self) -> Option<f32> {
+        self.split_view.as_ref().map(|_| self.split_ratio)
+    }
+
+    // a static playlist or a tag shown in a pane takes the tracks dropped onto it
+    fn pane_target(&self, section: &Section) -> Option<DropTarget> {
+        match section {
+            Section::Playlist(id) => self
+                .state
+                .playlists()
+                .iter()
+                .any(|node| node.value.id == *id && node.value.kind == PlaylistKind::Static)
+                .then_some(DropTarget::Playlist(*id)),
+            Section::Tag(id) => Some(DropTarget::Tag(*id)),
+            _ => None,
+        }
+    }
+
+    fn pane_content<'a>(
+        &'a self,
+        content: Element<'a, Message>,
+        section: &Section,
+    ) -> Element<'a, Message> {
+        match self.pane_target(section) {
+            Some(target) => drop_target(
+                content,
+                target,
+                self.drag.as_ref(),
+                Message::DragEntered,
+                Message::DragLeft,
+            ),
+            None => content,
+        }
+    }
+
+    pub fn view(&self) -> Element<Message> {
+        let pane_grid = PaneGrid::new(
+            &self.pane_state,
+            |_pane, state, _is_maximized| match state {
+                Panes::Sidebar => pane_grid::Content::new(container(
+                    self.sidebar
+                        .view(self.state.section(), &self.state, self.drag.as_ref())
+                        .map(Message::Sidebar),
+                )),
+                Panes::Central => {
+                    let central_element = match self.state.section() {
+                        Section::Library
+                        | Section::Favorites
+                        | Section::RecentlyPlayed
+                        | Section::Playlist(_)
+                        | Section::Tag(_)
+                        | Section::Tags(_)
+                        | Section::TagPrefix(_)
+                        | Section::Search(_)
+                        | Section::Import(_)
+                        | Section::Auto(_)
+                        | Section::NotPlayedFor(_)
+                        | Section::Server(_) => {
+                            if self.state.album_grid() {
+                                self.album_grid.view(&self.state).map(Message::AlbumGrid)
+                            } else {
+                                self.compact_view
+                                    .view(&self.state)
+                                    .map(Message::CompactView)
+                            }
+                        }
+                        Section::Album(_) => album_view::view(&self.state).map(Message::AlbumView),
+                        Section::Podcast(_) => self
+                            .podcast_view
+                            .view(&self.state)
+                            .map(Message::PodcastView),
+                        Section::Queue => queue_view::view(&self.state).map(Message::Queue),
+                        Section::ImportHistory => {
+                            import_history::view(&self.state).map(Message::ImportHistory)
+                        }
+                        Section::RecentlyDeleted => {
+                            recently_deleted::view(&self.state).map(Message::RecentlyDeleted)
+                        }
+                        Section::Snapshots => self
+                            .snapshots_view
+                            .view(&self.state)
+                            .map(Message::Snapshots),
+                        Section::Conversions => {
+                            self.conversions_view.view().map(Message::Conversions)
+                        }
+                        Section::CdjExport => self.cdj_export_view.view().map(Message::CdjExport),
+                        Section::SplitRecording => self.vinyl_view.view().map(Message::Vinyl),
+                        Section::Recorder => self.recorder_view.view().map(Message::Recorder),
+                        Section::Browse => self.browse_view.view(&self.state).map(Message::Browse),
+                        Section::ListenNow => {
+                            self.listen_now.view(&self.state).map(Message::ListenNow)
+                        }
+                        Section::Insights => {
+                            self.insights_view.view(&self.state).map(Message::Insights)
+                        }
+                        Section::GenreSuggestions => self
+                            .genre_suggestions_view
+                            .view()
+                            .map(Message::GenreSuggestions),
+                        Section::ScannerSettings => iced::widget::column![
+                            settings_tabs::view(self.state.section(), Message::OpenSettings),
+                            self.scanner_settings
+                                .view(self.state.watch_folders())
+                                .map(Message::ScannerSettings),
+                        ]
+                        .spacing(16)
+                        .into(),
+                        Section::PlaybackSettings => iced::widget::column![
+                            settings_tabs::view(self.state.section(), Message::OpenSettings),
+                            playback_settings::view(&self.state).map(Message::PlaybackSettings),
+                        ]
+                        .spacing(16)
+                        .into(),
+                        Section::PrivacySettings => iced::widget::column![
+                            settings_tabs::view(self.state.section(), Message::OpenSettings),
+                            privacy_settings::view(&self.state).map(Message::PrivacySettings),
+                        ]
+                        .spacing(16)
+                        .into(),
+                        Section::FieldSettings => iced::widget::column![
+                            settings_tabs::view(self.state.section(), Message::OpenSettings),
+                            self.field_settings
+                                .view(&self.state)
+                                .map(Message::FieldSettings),
+                        ]
+                        .spacing(16)
+                        .into(),
+                        Section::PlaylistSettings => iced::widget::column![
+                            settings_tabs::view(self.state.section(), Message::OpenSettings),
+                            self.playlist_settings
+                                .view(&self.state)
+                                .map(Message::PlaylistSettings),
+                        ]
+                        .spacing(16)
+                        .into(),
+                        Section::SyncSettings => iced::widget::column![
+                            settings_tabs::view(self.state.section(), Message::OpenSettings),
+                            self.sync_settings
+                                .view(&self.state)
+                                .map(Message::SyncSettings),
+                        ]
+                        .spacing(16)
+                        .into(),
+                        Section::ServerSettings => iced::widget::column![
+                            settings_tabs::view(self.state.section(), Message::OpenSettings),
+                            self.server_settings
+                                .view(&self.state)
+                                .map(Message::ServerSettings),
+                        ]
+                        .spacing(16)
+                        .into(),
+                        Section::AdvancedSettings => iced::widget::column![
+                            settings_tabs::view(self.state.section(), Message::OpenSettings),
+                            advanced_settings::view(&self.state).map(Message::AdvancedSettings),
+                        ]
+                        .spacing(16)
+                        .into(),
+                        Section::Diagnostics => iced::widget::column![
+                            settings_tabs::view(self.state.section(), Message::OpenSettings),
+                            self.diagnostics_view.view().map(Message::Diagnostics),
+                        ]
+                        .spacing(16)
+                        .into(),
+                        _ => text("Empty").into(),
+                    };
+
+                    let content = container(
+                        iced::widget::column![
+                            breadcrumbs::view(&self.history, &self.state).map(Message::Navigate),
+                            self.pane_content(central_element, self.state.section()),
+                        ]
+                        .spacing(12),
+                    )
+                    .width(Length::Fill)
+                    .padding(16);
                     pane_grid::Content::new(
                         iced::widget::row![vertical_rule(1), content]
                             .width(Length::Fill)
                             .height(Length::Fill),
                     )
                 }
+                Panes::Split => {
+                    let content: Element<Message> = match &self.split_view {
+                        Some(split_view) => self.pane_content(
+                            split_view.view(&self.state).map(Message::Split),
+                            split_view.section(),
+                        ),
+                        None => text("").into(),
+                    };
+                    pane_grid::Content::new(
+                        iced::widget::row![vertical_rule(1), container(content).padding(16)]
+                            .width(Length::Fill)
+                            .height(Length::Fill),
+                    )
+                }
             },
         )
         .on_resize(10, Message::PaneResize);
 
-        let main_layout = Column::new()
-            .push(self.menubar.view().map(Message::MenuBar))
-            .push(pane_grid.height(Length::Fill))
-            .push(self.player.view(&self.state).map(Message::Player));
+        let mut main_layout =
+            Column::new().push(self.menubar.view(self.state.theme()).map(Message::MenuBar));
+        if self.quit_prompt.is_some() {
+            main_layout = main_layout.push(quit_prompt::view().map(Message::QuitPrompt));
+        }
+        if self.palette.is_open() {
+            main_layout = main_layout.push(self.palette.view(&self.state).map(Message::Palette));
+        }
+        main_layout = main_layout.push(pane_grid.height(Length::Fill));
+        if let Some(info) = self.state.song_info() {
+            main_layout = main_layout.push(song_info::view(info).map(Message::SongInfo));
+        }
+        let main_layout = main_layout.push(self.player.view(&self.state).map(Message::Player));
 
         Container::new(main_layout)
             .width(Length::Fill)
@@ -332,12 +2839,12 @@ impl Phonique {
             .into()
     }
     fn theme(&self) -> IcedTheme {
-        crate::Theme::Light.into()
+        self.state.theme().clone().into()
     }
 
     pub fn subscription(&self) -> Subscription<Message> {
         let scanning_subscription = if let Some(path) = self.scanning_files.clone() {
-            Subscription::run_with(path, scan_files)
+            Subscription::run_with((path, self.state.scan_options().clone()), scan_files)
         } else {
             Subscription::none()
         };
@@ -346,28 +2853,147 @@ impl Phonique {
             event::Event::Window(WindowEvent::FileDropped(path_buf)) => {
                 Some(Message::MetadataScanningStarted(Some(path_buf)))
             }
-            event::Event::Window(WindowEvent::Resized(size)) => {
-                Some(Message::WindowResize(size.width))
+            event::Event::Window(WindowEvent::Resized(size)) => Some(Message::WindowResize(size)),
+            event::Event::Window(WindowEvent::Moved(position)) => {
+                Some(Message::WindowMoved(position))
             }
             _ => None,
         });
 
+        let share_refresh = if self.state.sharing() {
+            iced::time::every(SHARE_REFRESH_INTERVAL).map(|_| Message::RefreshShared)
+        } else {
+            Subscription::none()
+        };
+
+        let alarm = if self.state.next_alarm().is_some() || self.alarm_ramp.is_some() {
+            iced::time::every(ALARM_INTERVAL).map(|_| Message::AlarmTick)
+        } else {
+            Subscription::none()
+        };
+
+        let hover_preview =
+            if self.state.hover_preview() && *self.state.section() == Section::Browse {
+                self.browse_view.subscription().map(Message::Browse)
+            } else {
+                Subscription::none()
+            };
+
+        let watches = if self.state.watches().is_empty() {
+            Subscription::none()
+        } else {
+            iced::time::every(WATCH_INTERVAL).map(|_| Message::WatchTick)
+        };
+
+        let folder_watches = if self.state.watch_folders().is_empty() {
+            Subscription::none()
+        } else {
+            iced::time::every(FOLDER_WATCH_INTERVAL).map(|_| Message::FolderWatchTick)
+        };
+
+        let artwork_lookups = if self.artwork_queue.is_empty() {
+            Subscription::none()
+        } else {
+            iced::time::every(artwork::LOOKUP_INTERVAL).map(|_| Message::ArtworkTick)
+        };
+
+        let drag_end = if self.drag.is_some() {
+            event::listen_with(|ev, _, _| match ev {
+                event::Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                    Some(Message::DragEnded)
+                }
+                _ => None,
+            })
+        } else {
+            Subscription::none()
+        };
+
         Subscription::batch([
             self.compact_view.subscription().map(Message::CompactView),
+            drag_end,
+            self.sidebar.subscription().map(Message::Sidebar),
             file_drop_subscription,
+            breadcrumbs::subscription().map(Message::Navigate),
+            self.palette.subscription().map(Message::Palette),
             scanning_subscription,
             self.player.subscription().map(Message::Player),
+            share_refresh,
+            self.recorder_view.subscription().map(Message::Recorder),
+            alarm,
+            hover_preview,
+            watches,
+            folder_watches,
+            artwork_lookups,
+            window::close_requests().map(Message::CloseRequested),
         ])
     }
 }
 
-fn scan_files(path: &PathBuf) -> Pin<Box<dyn Stream<Item = Message> + Send>> {
+// the sidebar and the central pane, with the split pane beside it at `split` when open
+fn split_panes(ratio: f32, split: Option<f32>) -> pane_grid::State<Panes> {
+    let central = pane_grid::Configuration::Pane(Panes::Central);
+    let content = match split {
+        Some(split_ratio) => pane_grid::Configuration::Split {
+            axis: pane_grid::Axis::Vertical,
+            ratio: split_ratio,
+            a: Box::new(central),
+            b: Box::new(pane_grid::Configuration::Pane(Panes::Split)),
+        },
+        None => central,
+    };
+    pane_grid::State::with_configuration(pane_grid::Configuration::Split {
+        axis: pane_grid::Axis::Vertical,
+        ratio,
+        a: Box::new(pane_grid::Configuration::Pane(Panes::Sidebar)),
+        b: Box::new(content),
+    })
+}
+
+fn fetch_podcast(url: String) -> Task<Message> {
+    Task::perform(podcasts::fetch_feed(url.clone()), move |result| {
+        Message::PodcastFetched(url, result.map_err(|e| e.to_string()))
+    })
+}
+
+// likes of a server's song are kept on the server, as a star on Subsonic and a favorite on
+// Jellyfin
+fn star_on_server(playable: &dyn AudioPlayable, starred: bool) -> Task<Message> {
+    let path = playable.get_path();
+    match playable.get_kind() {
+        PlayableKind::Subsonic => {
+            let (Some(server), Some(song_id)) =
+                (subsonic::find_server(path), subsonic::song_id(path))
+            else {
+                return Task::none();
+            };
+            Task::perform(subsonic::star(server, song_id, starred), |result| {
+                Message::Starred(result.map_err(|e| e.to_string()))
+            })
+        }
+        PlayableKind::Jellyfin => {
+            let (Some(server), Some(item_id)) =
+                (jellyfin::find_server(path), jellyfin::item_id(path))
+            else {
+                return Task::none();
+            };
+            Task::perform(jellyfin::set_favorite(server, item_id, starred), |result| {
+                Message::Starred(result.map_err(|e| e.to_string()))
+            })
+        }
+        _ => Task::none(),
+    }
+}
+
+fn scan_files(
+    (path, options): &(PathBuf, ScanOptions),
+) -> Pin<Box<dyn Stream<Item = Message> + Send>> {
     let path = path.clone();
+    let options = options.clone();
     Box::pin(iced::stream::channel(
         100,
         |mut output: Sender<Message>| async move {
             if path.is_file() {
-                match scan_file(&path).map_err(|e| format!("{e}")) {
+                match scan_file(&path, &options).map_err(|e| format!("{e}")) {
                     Ok(metadata) => {
                         let _ = output.send(Message::MetadataScanResult(metadata)).await;
                         let _ = output.send(Message::MetadataScanningEnded).await;
@@ -377,7 +3003,7 @@ fn scan_files(path: &PathBuf) -> Pin<Box<dyn Stream<Item = Message> + Send>> {
                     }
                 }
             } else if path.is_dir() {
-                let files = scan_folder(&path);
+                let files = scan_folder(&path, &options);
                 for file in files {
                     let _ = output.send(Message::MetadataScanResult(file)).await;
                 }
@@ -0,0 +1,159 @@
+// The media controls of the operating system: media keys, MPRIS on Linux, the Now Playing
+// widget on macOS and the media overlay on Windows. They live on a thread of their own, the
+// player tells them what plays and their buttons come back as player messages, the same ones
+// the controls of the window send.
+use std::{sync::mpsc, time::Duration};
+
+use iced::futures::{SinkExt, channel::mpsc::Sender};
+use log::{error, info};
+use souvlaki::{
+    MediaControlEvent, MediaControls, MediaMetadata, MediaPlayback, MediaPosition, PlatformConfig,
+    SeekDirection,
+};
+
+use crate::player::Message;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Playback {
+    Stopped,
+    Paused,
+    Playing,
+}
+
+/// What the controls show, sent whenever the track or the playback changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NowPlaying {
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    // in seconds, 0 when unknown
+    pub duration: u64,
+    pub position: u64,
+    pub playback: Playback,
+}
+
+/// The handle of the window on Windows, which the media overlay belongs to. None elsewhere,
+/// the controls do without.
+pub fn window_handle(window: &dyn iced::window::Window) -> Option<isize> {
+    #[cfg(windows)]
+    {
+        use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+        match window.window_handle().ok()?.as_raw() {
+            RawWindowHandle::Win32(handle) => Some(handle.hwnd.get()),
+            _ => None,
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = window;
+        None
+    }
+}
+
+// the player message of a button, seeking jumps like the rewind and fast forward buttons
+fn to_message(event: MediaControlEvent) -> Option<Message> {
+    let message = match event {
+        MediaControlEvent::Play => Message::Resume,
+        MediaControlEvent::Pause | MediaControlEvent::Stop => Message::Paused,
+        MediaControlEvent::Toggle => Message::TogglePlay,
+        MediaControlEvent::Next => Message::Next,
+        MediaControlEvent::Previous => Message::Prev,
+        MediaControlEvent::Seek(SeekDirection::Forward) => Message::FastForward,
+        MediaControlEvent::Seek(SeekDirection::Backward) => Message::Rewind,
+        MediaControlEvent::SetPosition(MediaPosition(position)) => {
+            Message::Seek(position.as_secs())
+        }
+        _ => return None,
+    };
+    Some(message)
+}
+
+fn show(controls: &mut MediaControls, now_playing: &NowPlaying) {
+    let metadata = MediaMetadata {
+        title: Some(now_playing.title.as_str()),
+        artist: Some(now_playing.artist.as_str()).filter(|artist| !artist.is_empty()),
+        album: Some(now_playing.album.as_str()).filter(|album| !album.is_empty()),
+        cover_url: None,
+        duration: (now_playing.duration > 0).then(|| Duration::from_secs(now_playing.duration)),
+    };
+    let progress = Some(MediaPosition(Duration::from_secs(now_playing.position)));
+    let playback = match now_playing.playback {
+        Playback::Stopped => MediaPlayback::Stopped,
+        Playback::Paused => MediaPlayback::Paused { progress },
+        Playback::Playing => MediaPlayback::Playing { progress },
+    };
+    if let Err(e) = controls
+        .set_metadata(metadata)
+        .and_then(|_| controls.set_playback(playback))
+    {
+        error!("media_controls: failed to update\n{e:?}");
+    }
+}
+
+fn run(hwnd: Option<isize>, output: Sender<Message>, updates: mpsc::Receiver<NowPlaying>) {
+    let config = PlatformConfig {
+        dbus_name: "phoniq",
+        display_name: "Phoniq",
+        hwnd: hwnd.map(|hwnd| hwnd as *mut std::ffi::c_void),
+    };
+    let mut controls = match MediaControls::new(config) {
+        Ok(controls) => controls,
+        Err(e) => {
+            error!("media_controls: not available\n{e:?}");
+            return;
+        }
+    };
+    let mut events = output;
+    if let Err(e) = controls.attach(move |event| {
+        if let Some(message) = to_message(event) {
+            let _ = events.try_send(message);
+        }
+    }) {
+        error!("media_controls: failed to listen\n{e:?}");
+        return;
+    }
+    info!("media_controls: started");
+    // the controls are dropped, and gone from the system, when the player goes away
+    for now_playing in updates {
+        show(&mut controls, &now_playing);
+    }
+}
+
+/// Starts the controls for the window `hwnd`. The first message hands over where to send
+/// what plays, the rest are the buttons pressed.
+pub fn start(hwnd: Option<isize>) -> impl iced::futures::Stream<Item = Message> {
+    iced::stream::channel(100, move |mut output: Sender<Message>| async move {
+        let (updates_tx, updates_rx) = mpsc::channel::<NowPlaying>();
+        let events = output.clone();
+        std::thread::spawn(move || run(hwnd, events, updates_rx));
+        if let Err(e) = output.send(Message::MediaControlsReady(updates_tx)).await {
+            error!("media_controls: failed to send the sender\n{e:?}");
+        }
+        // the thread sends the buttons from now on
+        std::future::pending::<()>().await;
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_message() {
+        assert!(matches!(
+            to_message(MediaControlEvent::Toggle),
+            Some(Message::TogglePlay)
+        ));
+        assert!(matches!(
+            to_message(MediaControlEvent::Stop),
+            Some(Message::Paused)
+        ));
+        assert!(matches!(
+            to_message(MediaControlEvent::SetPosition(MediaPosition(
+                Duration::from_millis(90_500)
+            ))),
+            Some(Message::Seek(90))
+        ));
+        assert!(to_message(MediaControlEvent::Raise).is_none());
+    }
+}
@@ -1,30 +1,51 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use iced::{
     Border, Element, Length, Padding, Task,
     advanced::widget::Id,
     alignment::{Horizontal, Vertical},
-    widget::{Column, Row, container, horizontal_rule, text_input},
+    widget::{Button, Column, Row, container, horizontal_rule, text, text_input},
 };
 
 use crate::{
-    icons,
+    fonts::ICON,
+    icons, m3u,
+    theme::Theme,
     widgets::button_with_menu::{ButtonWithMenu, clicked_overlay},
 };
 
 #[derive(Clone, Debug)]
 pub enum Message {
     FileOptionSelected(usize),
+    RecentOptionSelected(usize),
     OpenFile,
     OpenFolder,
+    // picks an M3U or PLS file to make a playlist of
+    ImportPlaylist,
+    ImportPlaylistFrom(Option<PathBuf>),
+    OpenSettings,
+    SplitRecording,
+    Record,
+    // opens a second section beside the central one, or closes it
+    ToggleSplit,
+    ToggleTheme,
     SearchTypeIn(String),
     Search(String),
     MetadataScanningStarted(Option<PathBuf>),
+    // shows the tracks last imported from a recent file or folder
+    OpenRecent(PathBuf),
+    // scans a recent file or folder again, for what was added since
+    RescanRecent(PathBuf),
+    ClearRecent,
 }
 
 pub struct MenuBar {
     search_string: String,
     file_button_menu_id: Id,
+    recent_button_menu_id: Id,
+    recent: Vec<PathBuf>,
+    // each recent path opens with its name and rescans with the next option
+    recent_options: Vec<String>,
 }
 
 impl Default for MenuBar {
@@ -32,22 +53,57 @@ impl Default for MenuBar {
         Self {
             search_string: Default::default(),
             file_button_menu_id: Id::unique(),
+            recent_button_menu_id: Id::unique(),
+            recent: Vec::new(),
+            recent_options: Vec::new(),
         }
     }
 }
 
+// the name of the file or folder, the whole path when it has none
+fn recent_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .to_string()
+}
+
 impl MenuBar {
+    /// The files and folders listed in the Recent menu, newest first.
+    pub fn set_recent(&mut self, paths: &[PathBuf]) {
+        self.recent = paths.to_vec();
+        self.recent_options = paths
+            .iter()
+            .flat_map(|path| {
+                let name = recent_name(path);
+                [name.clone(), format!("Rescan {name}")]
+            })
+            .collect();
+        if !paths.is_empty() {
+            self.recent_options.push("Clear Recent".to_string());
+        }
+    }
+
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::FileOptionSelected(option) => {
-                clicked_overlay(self.file_button_menu_id.clone()).map(move |_| {
-                    if option == 0 {
-                        return Message::OpenFile;
-                    } else {
-                        return Message::OpenFolder;
-                    }
+                clicked_overlay(self.file_button_menu_id.clone()).map(move |_| match option {
+                    0 => Message::OpenFile,
+                    1 => Message::OpenFolder,
+                    2 => Message::ImportPlaylist,
+                    3 => Message::SplitRecording,
+                    4 => Message::Record,
+                    _ => Message::OpenSettings,
                 })
             }
+            Message::RecentOptionSelected(option) => {
+                let message = match self.recent.get(option / 2) {
+                    Some(path) if option % 2 == 0 => Message::OpenRecent(path.clone()),
+                    Some(path) => Message::RescanRecent(path.clone()),
+                    None => Message::ClearRecent,
+                };
+                clicked_overlay(self.recent_button_menu_id.clone()).map(move |_| message.clone())
+            }
             Message::OpenFile => {
                 let open_file_task = Task::perform(
                     async {
@@ -69,6 +125,16 @@ impl MenuBar {
                 },
                 Message::MetadataScanningStarted,
             ),
+            Message::ImportPlaylist => Task::perform(
+                async {
+                    rfd::AsyncFileDialog::new()
+                        .add_filter("Playlists", &m3u::EXTENSIONS)
+                        .pick_file()
+                        .await
+                        .map(|file| file.path().to_path_buf())
+                },
+                Message::ImportPlaylistFrom,
+            ),
             Message::SearchTypeIn(v) => {
                 self.search_string = v;
                 if self.search_string.is_empty() {
@@ -79,9 +145,9 @@ impl MenuBar {
             _ => Task::none(),
         }
     }
-    pub fn view(&self) -> Element<Message> {
+    pub fn view(&self, theme: &Theme) -> Element<Message> {
         let search = container(
-            text_input("Search", &self.search_string)
+            text_input("Search library", &self.search_string)
                 .width(Length::Fixed(200.0))
                 .icon(icons::input_icon(icons::ICON_SEARCH))
                 .on_input(Message::SearchTypeIn)
@@ -89,12 +155,46 @@ impl MenuBar {
         )
         .width(Length::Fill)
         .align_x(Horizontal::Right);
-        let file_menu = ButtonWithMenu::new("File", &["Add File", "Add Folder"])
-            .set_id(&self.file_button_menu_id)
-            .on_option_select(Message::FileOptionSelected);
+        let file_menu = ButtonWithMenu::new(
+            "File",
+            &[
+                "Add File",
+                "Add Folder",
+                "Import Playlist…",
+                "Split Recording…",
+                "Record…",
+                "Settings",
+            ],
+        )
+        .set_id(&self.file_button_menu_id)
+        .menu_style(theme.menu_style())
+        .on_option_select(Message::FileOptionSelected);
+        let recent_options: Vec<&str> = if self.recent_options.is_empty() {
+            vec!["No Recent Files"]
+        } else {
+            self.recent_options.iter().map(String::as_str).collect()
+        };
+        let recent_menu = ButtonWithMenu::new("Recent", recent_options)
+            .set_id(&self.recent_button_menu_id)
+            .menu_style(theme.menu_style())
+            .on_option_select(Message::RecentOptionSelected);
+        // the icon of the theme switched to
+        let theme_icon = match theme {
+            Theme::Light => icons::ICON_MOON,
+            Theme::Dark => icons::ICON_SUN,
+        };
+        let split_toggle = Button::new(text(icons::ICON_COLUMNS_2).font(ICON).size(16))
+            .style(iced::widget::button::text)
+            .on_press(Message::ToggleSplit);
+        let theme_toggle = Button::new(text(theme_icon).font(ICON).size(16))
+            .style(iced::widget::button::text)
+            .on_press(Message::ToggleTheme);
         let menubar = Row::new()
             .push(file_menu)
+            .push(recent_menu)
             .push(search)
+            .push(split_toggle)
+            .push(theme_toggle)
             .padding(Padding {
                 top: 5.0,
                 right: 16.0,
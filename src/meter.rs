@@ -0,0 +1,171 @@
+use std::time::Duration;
+
+use iced::futures::channel::mpsc::Sender;
+use rodio::{Source, source::SeekError};
+
+use crate::player::Message;
+
+// readings sent to the player each second
+const READINGS_PER_SECOND: u32 = 20;
+// the quietest level the meters show, in dB below full scale
+const METER_RANGE_DB: f32 = 60.0;
+
+/// Peak and RMS amplitude of the left and right channel, from 0 to 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Levels {
+    pub peak: [f32; 2],
+    pub rms: [f32; 2],
+}
+
+/// How far along a meter an amplitude reaches, the meter covers the top `METER_RANGE_DB`.
+pub fn meter_fraction(amplitude: f32) -> f32 {
+    if amplitude <= 0.0 {
+        return 0.0;
+    }
+    ((20.0 * amplitude.log10() + METER_RANGE_DB) / METER_RANGE_DB).clamp(0.0, 1.0)
+}
+
+/// Passes the samples through untouched and reports their levels to the player.
+pub struct Meter<S> {
+    source: S,
+    sender: Sender<Message>,
+    // frames in one reading
+    window: usize,
+    // channel of the next sample
+    channel: usize,
+    frames: usize,
+    peak: [f32; 2],
+    squares: [f32; 2],
+}
+
+impl<S> Meter<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(source: S, sender: Sender<Message>) -> Self {
+        let window = (source.sample_rate() / READINGS_PER_SECOND).max(1) as usize;
+        Self {
+            source,
+            sender,
+            window,
+            channel: 0,
+            frames: 0,
+            peak: [0.0; 2],
+            squares: [0.0; 2],
+        }
+    }
+
+    fn measure(&mut self, sample: f32) {
+        let channels = self.source.channels().max(1) as usize;
+        // mono feeds both meters, channels past the first two are left out
+        let sides: &[usize] = match (channels, self.channel) {
+            (1, _) => &[0, 1],
+            (_, 0) => &[0],
+            (_, 1) => &[1],
+            _ => &[],
+        };
+        for &side in sides {
+            self.peak[side] = self.peak[side].max(sample.abs());
+            self.squares[side] += sample * sample;
+        }
+
+        self.channel += 1;
+        if self.channel < channels {
+            return;
+        }
+        self.channel = 0;
+        self.frames += 1;
+        if self.frames == self.window {
+            self.report();
+        }
+    }
+
+    fn report(&mut self) {
+        let frames = self.frames as f32;
+        let levels = Levels {
+            peak: self.peak,
+            rms: self.squares.map(|squares| (squares / frames).sqrt()),
+        };
+        let _ = self.sender.try_send(Message::Levels(levels));
+        self.frames = 0;
+        self.peak = [0.0; 2];
+        self.squares = [0.0; 2];
+    }
+}
+
+impl<S> Iterator for Meter<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.source.next()?;
+        self.measure(sample);
+        Some(sample)
+    }
+}
+
+impl<S> Source for Meter<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.source.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.source.try_seek(pos)?;
+        self.channel = 0;
+        self.frames = 0;
+        self.peak = [0.0; 2];
+        self.squares = [0.0; 2];
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::futures::channel::mpsc;
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    #[test]
+    fn test_meter() {
+        // two readings of a steady stereo signal
+        let samples: Vec<f32> = [0.5, -0.25].repeat(2 * 40);
+        let (sender, mut receiver) = mpsc::channel(10);
+        let meter = Meter::new(SamplesBuffer::new(2, 800, samples.clone()), sender);
+        assert_eq!(meter.collect::<Vec<f32>>(), samples);
+
+        for _ in 0..2 {
+            let Ok(Some(Message::Levels(levels))) = receiver.try_next() else {
+                panic!("no levels");
+            };
+            assert_eq!(levels.peak, [0.5, 0.25]);
+            assert_eq!(levels.rms, [0.5, 0.25]);
+        }
+        assert!(matches!(receiver.try_next(), Ok(None)));
+    }
+
+    #[test]
+    fn test_meter_fraction() {
+        assert_eq!(meter_fraction(1.0), 1.0);
+        assert_eq!(meter_fraction(0.0), 0.0);
+        assert_eq!(meter_fraction(0.0001), 0.0);
+        assert!((meter_fraction(0.1) - 2.0 / 3.0).abs() < 1e-6);
+    }
+}
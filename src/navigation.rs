@@ -0,0 +1,117 @@
+// Back and forward between the sections visited, like in a browser. The section left is put on
+// the back stack, going back moves the current one onto the forward stack. Visiting a section
+// anew forgets what was ahead.
+use crate::app_state::Section;
+
+// sections kept to go back to, the oldest are dropped past this
+const MAX_HISTORY: usize = 50;
+
+#[derive(Debug, Default)]
+pub struct History {
+    back: Vec<Section>,
+    // the next section to go forward to last
+    forward: Vec<Section>,
+}
+
+impl History {
+    /// Records leaving `from` for another section.
+    pub fn visit(&mut self, from: Section) {
+        self.forward.clear();
+        self.back.push(from);
+        if self.back.len() > MAX_HISTORY {
+            self.back.remove(0);
+        }
+    }
+
+    /// The section to go back to from `current`, None at the start.
+    pub fn back(&mut self, current: Section) -> Option<Section> {
+        let section = self.back.pop()?;
+        self.forward.push(current);
+        Some(section)
+    }
+
+    pub fn forward(&mut self, current: Section) -> Option<Section> {
+        let section = self.forward.pop()?;
+        self.back.push(current);
+        Some(section)
+    }
+
+    /// Goes back several steps at once, to the section at `index` of the trail.
+    pub fn back_to(&mut self, index: usize, current: Section) -> Option<Section> {
+        if index >= self.back.len() {
+            return None;
+        }
+        self.forward.push(current);
+        while self.back.len() > index + 1 {
+            self.forward.extend(self.back.pop());
+        }
+        self.back.pop()
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+
+    /// The sections visited before the current one, oldest first.
+    pub fn trail(&self) -> &[Section] {
+        &self.back
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history() {
+        let mut history = History::default();
+        assert_eq!(history.back(Section::Library), None);
+
+        history.visit(Section::Library);
+        history.visit(Section::Playlist(1));
+        history.visit(Section::Album("Amber".to_string()));
+        // on Browse now
+        assert_eq!(
+            history.back(Section::Browse),
+            Some(Section::Album("Amber".to_string()))
+        );
+        assert_eq!(
+            history.back(Section::Album("Amber".to_string())),
+            Some(Section::Playlist(1))
+        );
+        assert!(history.can_go_forward());
+        assert_eq!(
+            history.forward(Section::Playlist(1)),
+            Some(Section::Album("Amber".to_string()))
+        );
+        assert_eq!(history.trail(), [Section::Library, Section::Playlist(1)]);
+
+        // a new visit forgets what was ahead
+        history.visit(Section::Album("Amber".to_string()));
+        assert!(!history.can_go_forward());
+
+        // on Favorites, straight back to the library
+        assert_eq!(
+            history.back_to(0, Section::Favorites),
+            Some(Section::Library)
+        );
+        assert!(!history.can_go_back());
+        assert_eq!(
+            history.forward(Section::Library),
+            Some(Section::Playlist(1))
+        );
+        assert_eq!(
+            history.forward(Section::Playlist(1)),
+            Some(Section::Album("Amber".to_string()))
+        );
+        assert_eq!(
+            history.forward(Section::Album("Amber".to_string())),
+            Some(Section::Favorites)
+        );
+        assert_eq!(history.forward(Section::Favorites), None);
+    }
+}
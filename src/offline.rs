@@ -0,0 +1,134 @@
+// Remote playables made available offline, downloaded into a folder of the app and played from
+// there in place of streaming them: songs of WebDAV, Subsonic and Jellyfin servers and streams
+// liked from outside the library.
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use directories::BaseDirs;
+use iced::futures::channel::oneshot;
+use log::error;
+
+use crate::{
+    app_state::{AudioPlayable, PlayableId},
+    storage::{AudioFileKind, Playable},
+};
+
+// the files downloaded, by playable, for the audio worker which only has the playable
+static COPIES: RwLock<BTreeMap<PlayableId, String>> = RwLock::new(BTreeMap::new());
+
+/// Where a remote playable stands with being available offline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Streamed,
+    // waiting for the downloads before it
+    Queued,
+    Downloading,
+    Offline,
+}
+
+/// Whether a playable is played from elsewhere and can be downloaded.
+pub fn is_downloadable(playable: &Playable) -> bool {
+    match playable.type_id {
+        AudioFileKind::WebDav | AudioFileKind::Subsonic | AudioFileKind::Jellyfin => true,
+        AudioFileKind::Stream => playable.source_url.starts_with("http"),
+        _ => false,
+    }
+}
+
+pub fn offline_dir() -> Option<PathBuf> {
+    Some(
+        BaseDirs::new()?
+            .data_local_dir()
+            .join("phoniq")
+            .join("offline"),
+    )
+}
+
+/// Makes the files downloaded known to the audio worker, which plays those instead of
+/// streaming their playables.
+pub fn set_copies(copies: &HashMap<PlayableId, String>) {
+    match COPIES.write() {
+        Ok(mut known) => {
+            *known = copies
+                .iter()
+                .map(|(id, path)| (*id, path.clone()))
+                .collect()
+        }
+        Err(err) => error!("Error setting the offline copies: {err:?}"),
+    }
+}
+
+/// The file a playable was downloaded to.
+pub fn copy_of(playable_id: PlayableId) -> Option<String> {
+    COPIES.read().ok()?.get(&playable_id).cloned()
+}
+
+// named after the playable, with the extension of its address for the decoder to go by
+fn file_name(playable: &Playable) -> String {
+    let path = playable
+        .source_url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default();
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .filter(|ext| ext.len() <= 4 && ext.chars().all(|c| c.is_ascii_alphanumeric()))
+        .unwrap_or("audio");
+    format!("{}.{}", playable.id, extension.to_lowercase())
+}
+
+/// Downloads a playable into `dir` on a thread of its own, as the fetches of the sources block.
+pub async fn download(playable: Playable, dir: PathBuf) -> io::Result<PathBuf> {
+    let (sender, receiver) = oneshot::channel();
+    std::thread::spawn(move || {
+        let downloaded = playable.stream().and_then(|audio| {
+            fs::create_dir_all(&dir)?;
+            let path = dir.join(file_name(&playable));
+            fs::write(&path, audio.into_inner())?;
+            Ok(path)
+        });
+        let _ = sender.send(downloaded);
+    });
+    receiver
+        .await
+        .unwrap_or_else(|_| Err(io::Error::other("the download stopped")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_name() {
+        let playable = |url: &str, kind: AudioFileKind| Playable {
+            id: 7,
+            source_url: url.to_string(),
+            type_id: kind,
+            ..Default::default()
+        };
+        let webdav = playable(
+            "https://dav.example.com/Music/a.FLAC",
+            AudioFileKind::WebDav,
+        );
+        assert_eq!(file_name(&webdav), "7.flac");
+        assert!(is_downloadable(&webdav));
+        let song = playable(
+            "https://music.example.com/rest/stream?id=1&format=raw",
+            AudioFileKind::Subsonic,
+        );
+        assert_eq!(file_name(&song), "7.audio");
+        assert!(!is_downloadable(&playable(
+            "/music/a.mp3",
+            AudioFileKind::LocalFile
+        )));
+        assert!(!is_downloadable(&playable(
+            "/music/a.mp3",
+            AudioFileKind::Stream
+        )));
+    }
+}
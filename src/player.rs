@@ -1,9 +1,10 @@
 use std::{
     ops::Deref,
     sync::{Arc, mpsc::RecvTimeoutError},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+use directories::UserDirs;
 use iced::{
     Border, Color, Element, Length, Padding, Shadow, Subscription, Task,
     alignment::{Horizontal, Vertical},
@@ -12,21 +13,37 @@ use iced::{
     keyboard::{self, key::Named},
     widget::{
         Button, Column, Row, Space, Text, button::Status, column, container, horizontal_rule, row,
-        slider, text,
+        slider, text, vertical_slider,
     },
 };
+use lofty::file::AudioFile;
 use log::{debug, error, info};
-use rodio::{Decoder, OutputStream, Sink, Source};
+use rodio::{
+    Decoder, OutputStream, OutputStreamHandle, Sink, Source,
+    cpal::{
+        SampleRate,
+        traits::{DeviceTrait, HostTrait},
+    },
+};
 
 use crate::{
-    app_state::{AudioPlayable, PlayableId, state_impl::State},
+    app_state::{AudioPlayable, PlayableId, PlayableKind, state_impl::State},
+    cast::{self, CastError, CastEvent, CastSession, Renderer},
+    chapters::current_chapter,
+    equalizer::{self, EqCurves, Equalizer, EqualizerShared, Preset},
     fonts,
     icons::{
-        ICON_CIRCLE_PAUSE, ICON_CIRCLE_PLAY, ICON_FAST_FORWARD, ICON_HEART, ICON_REWIND,
-        ICON_SHUFFLE, ICON_SKIP_BACK, ICON_SKIP_FORWARD, ICON_VOLUME, ICON_VOLUME_1, ICON_VOLUME_2,
-        ICON_VOLUME_OFF,
+        ICON_CAST, ICON_CIRCLE_PAUSE, ICON_CIRCLE_PLAY, ICON_FAST_FORWARD, ICON_HEART, ICON_REWIND,
+        ICON_SCROLL_TEXT, ICON_SHUFFLE, ICON_SKIP_BACK, ICON_SKIP_FORWARD, ICON_SLIDERS_VERTICAL,
+        ICON_VOLUME, ICON_VOLUME_1, ICON_VOLUME_2, ICON_VOLUME_OFF,
     },
-    util::{duration_to_str, playable_artwork},
+    media_controls::{self, NowPlaying, Playback},
+    meter::{Levels, Meter, meter_fraction},
+    resampler::{ResampleQuality, Resampled},
+    session_log::{SessionLog, Transition},
+    skip_silence::{SilenceSkip, SkipSilence},
+    util::{duration_to_str, playable_artwork, remaining_to_str},
+    volume_trim::VolumeTrims,
 };
 
 #[derive(Debug, Clone)]
@@ -39,6 +56,8 @@ pub enum Message {
     Rewind,
     FastForward,
     Play(Arc<dyn AudioPlayable>),
+    // plays starting at the given position, in seconds
+    PlayFrom(Arc<dyn AudioPlayable>, u64),
     EndPlay,
     Seek(u64),
     ProgressUpdate(u64, u64),
@@ -47,9 +66,84 @@ pub enum Message {
     ShuffleToggle,
     ToggleVolume,
     TogglePlay,
+    ToggleSessionLog,
+    // negative for a stream from outside the library, it is added to the library first
     Like(PlayableId),
+    // the playing stream after it was added to the library, playback is left alone
+    Adopt(Arc<dyn AudioPlayable>),
+    LikeAnimationFrame(Instant),
+    // opens the song info panel, sent by clicking the title
+    ShowSongInfo(PlayableId),
+    // length of the fades on pause, resume and skip, in milliseconds
+    FadeChanged(u64),
+    // seconds the end of a track overlaps the next one, 0 for gapless
+    CrossfadeChanged(u64),
+    // the track to follow the playing one, prepared ahead to start without a gap
+    Prefetch(Option<Arc<dyn AudioPlayable>>),
+    // the prepared track took over from the one which ended, nothing was sent to play it
+    NextStarted(Arc<dyn AudioPlayable>),
+    // a snippet of a track played over the ducked playing one, None stops it
+    Preview(Option<Arc<dyn AudioPlayable>>),
+    // delay of the output device, in milliseconds
+    LatencyChanged(u64),
+    MatchSourceRateChanged(bool),
+    ResampleQualityChanged(ResampleQuality),
+    // applied to each track as it starts, the playing one keeps its gain
+    TrimsChanged(VolumeTrims),
+    // the level in dB below which pauses are cut short, None plays them whole
+    SkipSilenceChanged(Option<i32>),
+    // measured on the audio thread while the sink plays
+    Levels(Levels),
+    ToggleCastMenu,
+    ToggleEqualizer,
+    // a preset picked or a band moved for the output played on, saved by the app which hands
+    // the curves back
+    EqPreset(Preset),
+    EqBand(usize, i32),
+    // the curves of all outputs, the worker plays the one of its output
+    EqCurvesChanged(EqCurves),
+    // the device the worker plays on, empty when its name is unknown
+    OutputChanged(String),
+    DiscoverRenderers,
+    RenderersFound(Vec<Renderer>),
+    // None plays on this computer again
+    CastTo(Option<Renderer>),
+    CastFailed(String),
+    // shown above the controls, e.g. when playback moved to another output device
+    Notice(String),
+    DismissNotice,
+    // the handle of the window, the system's media controls start once it is known
+    MediaWindow(Option<isize>),
+    // where the media controls are told what plays
+    MediaControlsReady(std::sync::mpsc::Sender<NowPlaying>),
 }
 
+// how often the volume changes during a fade
+const FADE_STEP: Duration = Duration::from_millis(10);
+// how often the volumes change while two tracks overlap, the worker keeps taking messages
+const CROSSFADE_STEP: Duration = Duration::from_millis(50);
+// how long a preview plays, from a third into the track
+const PREVIEW_LENGTH: Duration = Duration::from_secs(20);
+// previews play quieter than the volume set
+const PREVIEW_VOLUME: f32 = 0.5;
+// the share of its volume the playing track keeps under a preview
+const DUCK_VOLUME: f32 = 0.2;
+// how long the playing track takes to duck and to come back
+const DUCK_FADE: Duration = Duration::from_millis(400);
+// how long before the end the prepared track is queued behind the playing one, more than the
+// time between two progress checks
+const GAPLESS_AHEAD: Duration = Duration::from_secs(1);
+// how long renderers get to answer a search
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(3);
+// how often the worker looks for a change of the default output device
+const DEVICE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+const NOTICE_DURATION: Duration = Duration::from_secs(5);
+// how long before the end of a track the next one is announced, in seconds
+const ANNOUNCE_AHEAD: u64 = 10;
+// the heart grows and shrinks back once when a like is toggled
+const LIKE_PULSE: Duration = Duration::from_millis(300);
+const HEART_SIZE: f32 = 13.0;
+
 pub struct Player {
     sender: Option<iced::futures::channel::mpsc::Sender<Message>>,
     volume_level: f32,
@@ -58,6 +152,29 @@ pub struct Player {
     shuffle_enabled: bool,
     duration: (u64, u64),
     current_playable: Option<Arc<dyn AudioPlayable>>,
+    // Some while the listening session is being recorded
+    session_log: Option<SessionLog>,
+    fade_ms: u64,
+    crossfade_secs: u64,
+    latency_ms: u64,
+    match_source_rate: bool,
+    resample_quality: ResampleQuality,
+    volume_trims: VolumeTrims,
+    skip_silence: Option<i32>,
+    levels: Levels,
+    show_cast_menu: bool,
+    show_equalizer: bool,
+    eq_curves: EqCurves,
+    output: String,
+    discovering: bool,
+    renderers: Vec<Renderer>,
+    cast_to: Option<Renderer>,
+    // shown above the controls for a few seconds
+    notice: Option<String>,
+    // when the like button was last pressed, while its pulse runs
+    like_pulse: Option<Instant>,
+    // the media keys and the system's Now Playing, None until they started
+    media_controls: Option<std::sync::mpsc::Sender<NowPlaying>>,
 }
 
 impl Default for Player {
@@ -70,11 +187,63 @@ impl Default for Player {
             shuffle_enabled: false,
             duration: (0, 0),
             current_playable: None,
+            session_log: None,
+            fade_ms: 0,
+            crossfade_secs: 0,
+            latency_ms: 0,
+            match_source_rate: false,
+            resample_quality: ResampleQuality::default(),
+            volume_trims: VolumeTrims::default(),
+            skip_silence: None,
+            levels: Levels::default(),
+            show_cast_menu: false,
+            show_equalizer: false,
+            eq_curves: EqCurves::default(),
+            output: String::new(),
+            discovering: false,
+            renderers: Vec::new(),
+            cast_to: None,
+            notice: None,
+            like_pulse: None,
+            media_controls: None,
         }
     }
 }
 
 impl Player {
+    // tells the system's media controls what plays, called when the track or the playback changes
+    fn show_now_playing(&self) {
+        let Some(media_controls) = &self.media_controls else {
+            return;
+        };
+        let playback = if self.is_playing {
+            Playback::Playing
+        } else if self.is_paused {
+            Playback::Paused
+        } else {
+            Playback::Stopped
+        };
+        let now_playing = match &self.current_playable {
+            Some(playable) => NowPlaying {
+                title: playable.get_title().to_string(),
+                artist: playable.get_artist().to_string(),
+                album: playable.get_album().to_string(),
+                duration: playable.get_duration(),
+                position: self.duration.0,
+                playback,
+            },
+            None => NowPlaying {
+                title: String::new(),
+                artist: String::new(),
+                album: String::new(),
+                duration: 0,
+                position: 0,
+                playback: Playback::Stopped,
+            },
+        };
+        let _ = media_controls.send(now_playing);
+    }
+
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::ProgressChanged(current, total) => {
@@ -89,9 +258,39 @@ impl Player {
                 if let Some(sender) = &mut self.sender {
                     let _ = sender.try_send(Message::Play(playable.clone()));
                 }
+                // a track which ended is no longer playing, one still going was skipped
+                let transition = match self.current_playable {
+                    Some(_) if self.is_playing || self.is_paused => {
+                        Transition::Skip(self.duration.0)
+                    }
+                    Some(_) => Transition::Next,
+                    None => Transition::Start,
+                };
+                if let Some(session_log) = &mut self.session_log
+                    && let Err(e) = session_log.record(playable.as_ref(), transition)
+                {
+                    error!("Failed to write to session log: {e}");
+                }
                 self.current_playable = Some(playable);
                 self.is_playing = true;
                 self.is_paused = false;
+                self.show_now_playing();
+            }
+            Message::PlayFrom(playable, position) => {
+                let task = self.update(Message::Play(playable));
+                if position > 0
+                    && let Some(sender) = &mut self.sender
+                {
+                    let _ = sender.try_send(Message::Seek(position));
+                }
+                return task;
+            }
+            Message::Seek(position) => {
+                if let Some(sender) = &mut self.sender {
+                    let _ = sender.try_send(Message::Seek(position));
+                }
+                self.duration.0 = position;
+                self.show_now_playing();
             }
             Message::TogglePlay => {
                 if self.is_playing {
@@ -106,6 +305,8 @@ impl Player {
                 }
                 self.is_playing = false;
                 self.is_paused = true;
+                self.levels = Levels::default();
+                self.show_now_playing();
             }
             Message::Resume => {
                 if let Some(sender) = &mut self.sender {
@@ -113,6 +314,7 @@ impl Player {
                 }
                 self.is_playing = true;
                 self.is_paused = false;
+                self.show_now_playing();
             }
             Message::Rewind => {
                 if (self.is_playing || self.is_paused)
@@ -137,6 +339,28 @@ impl Player {
             Message::ShuffleToggle => {
                 self.shuffle_enabled = !self.shuffle_enabled;
             }
+            Message::ToggleSessionLog => {
+                if let Some(session_log) = self.session_log.take() {
+                    info!("Session log saved to {:?}", session_log.path());
+                    match session_log.finish() {
+                        Ok(Some(path)) => {
+                            self.notice =
+                                Some(format!("Set tracklist saved to {}", path.display()));
+                        }
+                        Ok(None) => {}
+                        Err(e) => error!("Failed to save the set tracklist: {e}"),
+                    }
+                } else {
+                    match session_logs_dir().map(|dir| SessionLog::start(&dir)) {
+                        Some(Ok(session_log)) => {
+                            info!("Recording session to {:?}", session_log.path());
+                            self.session_log = Some(session_log);
+                        }
+                        Some(Err(e)) => error!("Failed to start session log: {e}"),
+                        None => error!("Failed to start session log: no music directory"),
+                    }
+                }
+            }
             Message::ToggleVolume => {
                 if let Some(sender) = &mut self.sender {
                     if self.volume_level == 0.0 {
@@ -158,15 +382,154 @@ impl Player {
             Message::ProgressUpdate(current, total) => {
                 self.duration = (current, total);
             }
-            Message::AudioReady(sender) => {
+            Message::AudioReady(mut sender) => {
                 info!("Got sender: {sender:?}");
+                let _ = sender.try_send(Message::FadeChanged(self.fade_ms));
+                let _ = sender.try_send(Message::CrossfadeChanged(self.crossfade_secs));
+                let _ = sender.try_send(Message::LatencyChanged(self.latency_ms));
+                let _ = sender.try_send(Message::MatchSourceRateChanged(self.match_source_rate));
+                let _ = sender.try_send(Message::ResampleQualityChanged(self.resample_quality));
+                let _ = sender.try_send(Message::TrimsChanged(self.volume_trims.clone()));
+                let _ = sender.try_send(Message::SkipSilenceChanged(self.skip_silence));
+                let _ = sender.try_send(Message::EqCurvesChanged(self.eq_curves.clone()));
                 self.sender.replace(sender);
             }
+            Message::FadeChanged(fade_ms) => {
+                self.fade_ms = fade_ms;
+                if let Some(sender) = &mut self.sender {
+                    let _ = sender.try_send(Message::FadeChanged(fade_ms));
+                }
+            }
+            Message::CrossfadeChanged(secs) => {
+                self.crossfade_secs = secs;
+                if let Some(sender) = &mut self.sender {
+                    let _ = sender.try_send(Message::CrossfadeChanged(secs));
+                }
+            }
+            Message::Prefetch(playable) => {
+                if let Some(sender) = &mut self.sender {
+                    let _ = sender.try_send(Message::Prefetch(playable));
+                }
+            }
+            Message::Preview(playable) => {
+                if let Some(sender) = &mut self.sender {
+                    let _ = sender.try_send(Message::Preview(playable));
+                }
+            }
+            Message::NextStarted(playable) => {
+                if let Some(session_log) = &mut self.session_log
+                    && let Err(e) = session_log.record(playable.as_ref(), Transition::Next)
+                {
+                    error!("Failed to write to session log: {e}");
+                }
+                self.current_playable = Some(playable);
+                self.is_playing = true;
+                self.is_paused = false;
+                self.show_now_playing();
+            }
+            Message::LatencyChanged(latency_ms) => {
+                self.latency_ms = latency_ms;
+                if let Some(sender) = &mut self.sender {
+                    let _ = sender.try_send(Message::LatencyChanged(latency_ms));
+                }
+            }
+            Message::MatchSourceRateChanged(match_source_rate) => {
+                self.match_source_rate = match_source_rate;
+                if let Some(sender) = &mut self.sender {
+                    let _ = sender.try_send(Message::MatchSourceRateChanged(match_source_rate));
+                }
+            }
+            Message::Levels(levels) => {
+                self.levels = levels;
+            }
+            Message::ResampleQualityChanged(quality) => {
+                self.resample_quality = quality;
+                if let Some(sender) = &mut self.sender {
+                    let _ = sender.try_send(Message::ResampleQualityChanged(quality));
+                }
+            }
+            Message::TrimsChanged(trims) => {
+                self.volume_trims = trims.clone();
+                if let Some(sender) = &mut self.sender {
+                    let _ = sender.try_send(Message::TrimsChanged(trims));
+                }
+            }
+            Message::SkipSilenceChanged(threshold_db) => {
+                self.skip_silence = threshold_db;
+                if let Some(sender) = &mut self.sender {
+                    let _ = sender.try_send(Message::SkipSilenceChanged(threshold_db));
+                }
+            }
             Message::EndPlay => {
                 self.is_playing = false;
                 self.is_paused = false;
+                self.levels = Levels::default();
+                self.show_now_playing();
                 return Task::done(Message::Next);
             }
+            Message::ToggleEqualizer => {
+                self.show_equalizer = !self.show_equalizer;
+            }
+            Message::EqPreset(_) | Message::EqBand(..) => {}
+            Message::EqCurvesChanged(curves) => {
+                self.eq_curves = curves.clone();
+                if let Some(sender) = &mut self.sender {
+                    let _ = sender.try_send(Message::EqCurvesChanged(curves));
+                }
+            }
+            Message::OutputChanged(output) => {
+                self.output = output;
+            }
+            Message::ToggleCastMenu => {
+                self.show_cast_menu = !self.show_cast_menu;
+                if self.show_cast_menu
+                    && let Some(sender) = &mut self.sender
+                {
+                    self.discovering = true;
+                    let _ = sender.try_send(Message::DiscoverRenderers);
+                }
+            }
+            Message::RenderersFound(renderers) => {
+                self.discovering = false;
+                self.renderers = renderers;
+            }
+            Message::CastTo(renderer) => {
+                if let Some(sender) = &mut self.sender {
+                    let _ = sender.try_send(Message::CastTo(renderer.clone()));
+                }
+                self.cast_to = renderer;
+                self.show_cast_menu = false;
+            }
+            Message::CastFailed(_) => {
+                self.cast_to = None;
+            }
+            Message::Notice(notice) => {
+                self.notice = Some(notice);
+            }
+            Message::MediaWindow(hwnd) => {
+                return Task::run(media_controls::start(hwnd), |message| message);
+            }
+            Message::MediaControlsReady(media_controls) => {
+                self.media_controls = Some(media_controls);
+                self.show_now_playing();
+            }
+            Message::DismissNotice => {
+                self.notice = None;
+            }
+            Message::Like(_) => {
+                self.like_pulse = Some(Instant::now());
+            }
+            Message::Adopt(playable) => {
+                self.current_playable = Some(playable);
+            }
+            Message::LikeAnimationFrame(now) => {
+                if self
+                    .like_pulse
+                    .is_some_and(|start| now.duration_since(start) >= LIKE_PULSE)
+                {
+                    self.like_pulse = None;
+                }
+            }
             _ => {}
         };
         Task::none()
@@ -184,17 +547,21 @@ impl Player {
             .align_y(Vertical::Center)
             .width(Length::Fixed(300.0))
             .max_width(400.0);
-        let player_controls = container(self.player_controls())
+        let player_controls = container(self.player_controls(state))
             .align_x(Horizontal::Right)
             .align_y(Vertical::Center)
             .width(Length::FillPortion(9));
         let misc_controls = container(self.misc_controls())
             .align_x(Horizontal::Right)
             .align_y(Vertical::Center)
-            .width(Length::Fixed(200.0));
+            .width(Length::Fixed(280.0));
 
         column![
             horizontal_rule(1),
+            self.cast_menu(),
+            self.equalizer(),
+            self.notice(),
+            self.announcement(state),
             row![song_info, player_controls, misc_controls]
                 .spacing(50)
                 .align_y(Vertical::Center)
@@ -205,7 +572,7 @@ impl Player {
         .width(Length::Fill)
         .into()
     }
-    fn player_controls(&self) -> Column<Message> {
+    fn player_controls(&self, state: &State) -> Column<Message> {
         let (play_icon, play_message) = if self.is_playing {
             (ICON_CIRCLE_PAUSE, Message::Paused)
         } else {
@@ -220,15 +587,30 @@ impl Player {
         ]
         .spacing(12)
         .align_y(Vertical::Center);
+        let buttons = match state.queue_progress() {
+            Some((position, count, remaining)) => buttons.push(
+                text(format!(
+                    "{position} of {count} · {} left",
+                    remaining_to_str(remaining)
+                ))
+                .size(12),
+            ),
+            None => buttons,
+        };
 
         let (current, total) = self.duration;
-        let slider = row![
-            text(duration_to_str(current)).size(12),
+        let seek_bar = column![
             slider(0.0..=total as f64, current as f64, move |v| {
                 info!("{v}");
                 Message::ProgressChanged(v as u64, total)
             })
             .step(0.1),
+            chapter_markers(state, current, total),
+        ]
+        .spacing(4);
+        let slider = row![
+            text(duration_to_str(current)).size(12),
+            seek_bar,
             text(duration_to_str(total)).size(12)
         ]
         .spacing(8);
@@ -236,23 +618,114 @@ impl Player {
             .align_x(Horizontal::Center)
             .spacing(12)
     }
-    fn misc_controls(&self) -> Row<Message> {
-        let shuffle_button = Button::new(text(ICON_SHUFFLE).font(fonts::ICON).size(20))
-            .padding(0)
-            .style(|theme: &iced::Theme, status| {
-                let palette = theme.palette();
-                let text_color = if self.shuffle_enabled {
-                    palette.text
-                } else {
-                    palette.text.scale_alpha(0.7)
-                };
-                iced::widget::button::Style {
-                    text_color,
-                    background: Some(iced::Background::Color(Color::TRANSPARENT)),
-                    ..button_style(theme, status)
-                }
+    fn cast_menu(&self) -> Element<Message> {
+        if !self.show_cast_menu {
+            return Space::with_height(Length::Shrink).into();
+        }
+        let status = if self.discovering {
+            "Searching the network…"
+        } else if self.renderers.is_empty() {
+            "No renderers found"
+        } else {
+            ""
+        };
+        row![
+            text("Play on").size(12),
+            cast_target_button("This computer", None, self.cast_to.is_none())
+        ]
+        .extend(self.renderers.iter().map(|renderer| {
+            let selected = self.cast_to.as_ref() == Some(renderer);
+            cast_target_button(&renderer.name, Some(renderer), selected)
+        }))
+        .push(text(status).size(12))
+        .spacing(8)
+        .padding([8, 16])
+        .align_y(Vertical::Center)
+        .into()
+    }
+    fn equalizer(&self) -> Element<Message> {
+        if !self.show_equalizer {
+            return Space::with_height(Length::Shrink).into();
+        }
+        let playing = self.eq_curves.preset(&self.output);
+        let presets = row(Preset::ALL.into_iter().map(|preset| {
+            let style = if preset == playing {
+                iced::widget::button::primary
+            } else {
+                iced::widget::button::secondary
+            };
+            Button::new(text(preset.to_string()).size(12))
+                .style(style)
+                .on_press(Message::EqPreset(preset))
+                .into()
+        }))
+        .spacing(8);
+        let output = match self.output.as_str() {
+            "" => "Equalizer".to_string(),
+            output => format!("Equalizer for {output}"),
+        };
+        let curve = self.eq_curves.curve(&self.output);
+        let bands = row(equalizer::BANDS
+            .iter()
+            .zip(curve)
+            .enumerate()
+            .map(|(band, (frequency, db))| eq_band(band, *frequency, db)));
+        column![
+            row![text(output).size(12), presets]
+                .spacing(16)
+                .align_y(Vertical::Center),
+            bands,
+        ]
+        .spacing(8)
+        .padding([8, 16])
+        .into()
+    }
+    fn notice(&self) -> Element<Message> {
+        let Some(notice) = &self.notice else {
+            return Space::with_height(Length::Shrink).into();
+        };
+        row![
+            text(notice).size(12),
+            Button::new(text("Dismiss").size(12))
+                .style(iced::widget::button::text)
+                .on_press(Message::DismissNotice),
+        ]
+        .spacing(8)
+        .padding([8, 16])
+        .align_y(Vertical::Center)
+        .into()
+    }
+    // what comes after the playing track, shown shortly before it ends
+    fn announcement(&self, state: &State) -> Element<Message> {
+        let (current, total) = self.duration;
+        // short tracks would be announced most of the time, streams have no end
+        let ending = total > 2 * ANNOUNCE_AHEAD && total.saturating_sub(current) <= ANNOUNCE_AHEAD;
+        let announcement = if !ending {
+            None
+        } else if state.ends_after_current() {
+            Some("The queue ends after this track".to_string())
+        } else {
+            state.peek_next().map(|next| match next.get_artist() {
+                "" => format!("Up next: {}", next.get_title()),
+                artist => format!("Up next: {artist} – {}", next.get_title()),
             })
-            .on_press(Message::ShuffleToggle);
+        };
+        let Some(announcement) = announcement else {
+            return Space::with_height(Length::Shrink).into();
+        };
+        container(text(announcement).size(12).style(text::secondary))
+            .padding([8, 16])
+            .into()
+    }
+    fn misc_controls(&self) -> Row<Message> {
+        let cast_button =
+            toggle_button(ICON_CAST, self.cast_to.is_some()).on_press(Message::ToggleCastMenu);
+        let equalizer_button = toggle_button(ICON_SLIDERS_VERTICAL, self.show_equalizer)
+            .on_press(Message::ToggleEqualizer);
+        let session_log_button = toggle_button(ICON_SCROLL_TEXT, self.session_log.is_some())
+            .on_press(Message::ToggleSessionLog);
+        let shuffle_button =
+            toggle_button(ICON_SHUFFLE, self.shuffle_enabled).on_press(Message::ShuffleToggle);
         let volume_icon = if self.volume_level == 0.0 {
             ICON_VOLUME_OFF
         } else if self.volume_level > 0.0 && self.volume_level < 50.0 {
@@ -263,13 +736,21 @@ impl Player {
             ICON_VOLUME_2
         };
         row![
+            cast_button,
+            equalizer_button,
+            session_log_button,
             shuffle_button,
             // player_button(ICON_LIST_MUSIC, None),
             player_button(volume_icon, None).on_press(Message::ToggleVolume),
             slider(0.0..=100.0, self.volume_level, |v| {
                 Message::VolumeChanged(v)
             })
-            .width(100)
+            .width(100),
+            column![
+                level_meter(self.levels.rms[0], self.levels.peak[0]),
+                level_meter(self.levels.rms[1], self.levels.peak[1]),
+            ]
+            .spacing(2),
         ]
         .align_y(Vertical::Center)
         .spacing(8)
@@ -291,26 +772,45 @@ impl Player {
                         .size(14)
                         .wrapping(text::Wrapping::WordOrGlyph),
                 )
-                .spacing(8)
-                .width(Length::Fill);
-
-            let like_button = Button::new(text(ICON_HEART).font(fonts::ICON).size(13))
-                .width(Length::Fixed(30.0))
-                .on_press(Message::Like(current_playable.get_id()))
-                .style(|theme: &iced::Theme, _| {
-                    let palette = theme.palette();
-                    let color = if state.is_liked(&current_playable.get_id()) {
-                        palette.danger
-                    } else {
-                        palette.text
-                    };
-                    iced::widget::button::Style {
-                        background: Some(iced::Background::Color(Color::TRANSPARENT)),
-                        text_color: color,
-                        border: Border::default().width(0),
-                        ..Default::default()
-                    }
-                });
+                .spacing(8);
+            // only playables from the library have details to show
+            let title_and_artist: Element<'a, Message> = if current_playable.get_id() < 0 {
+                title_and_artist.width(Length::Fill).into()
+            } else {
+                Button::new(title_and_artist)
+                    .padding(0)
+                    .width(Length::Fill)
+                    .style(iced::widget::button::text)
+                    .on_press(Message::ShowSongInfo(current_playable.get_id()))
+                    .into()
+            };
+
+            let like_button =
+                Button::new(text(ICON_HEART).font(fonts::ICON).size(self.heart_size()))
+                    .width(Length::Fixed(30.0))
+                    .on_press(Message::Like(current_playable.get_id()))
+                    .style(|theme: &iced::Theme, _| {
+                        let palette = theme.palette();
+                        let color = if state.is_liked(&current_playable.get_id()) {
+                            palette.danger
+                        } else {
+                            palette.text
+                        };
+                        iced::widget::button::Style {
+                            background: Some(iced::Background::Color(Color::TRANSPARENT)),
+                            text_color: color,
+                            border: Border::default().width(0),
+                            ..Default::default()
+                        }
+                    });
+            // playables from the library and streams can be liked, the latter join the library
+            let likeable = current_playable.get_id() >= 0
+                || current_playable.get_kind() == PlayableKind::Stream;
+            let like_button: Element<'a, Message> = if !likeable {
+                Space::with_width(Length::Fixed(30.0)).into()
+            } else {
+                like_button.into()
+            };
             return Some(
                 row![artwork, title_and_artist, like_button]
                     .align_y(Vertical::Center)
@@ -320,10 +820,41 @@ impl Player {
 
         None
     }
+
+    pub fn is_playing(&self) -> bool {
+        self.is_playing
+    }
+
+    /// The device played on, its equalizer curve is the one shown.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// The volume set on the slider, 0 to 100.
+    pub fn volume_level(&self) -> f32 {
+        self.volume_level
+    }
+
+    pub fn current_playable(&self) -> Option<Arc<dyn AudioPlayable>> {
+        self.current_playable.clone()
+    }
+
+    fn heart_size(&self) -> f32 {
+        let Some(start) = self.like_pulse else {
+            return HEART_SIZE;
+        };
+        let progress = (start.elapsed().as_secs_f32() / LIKE_PULSE.as_secs_f32()).min(1.0);
+        HEART_SIZE + 5.0 * (progress * std::f32::consts::PI).sin()
+    }
+
     pub fn subscription(&self) -> Subscription<Message> {
         let keyboard_events = event::listen_with(|event, status, _| {
-            if let iced::event::Event::Keyboard(keyboard::Event::KeyPressed { key, .. }) = event
+            if let iced::event::Event::Keyboard(keyboard::Event::KeyPressed {
+                key, modifiers, ..
+            }) = event
                 && status == iced::event::Status::Ignored
+                // Alt with the arrows goes back and forward between sections
+                && !modifiers.alt()
             {
                 match key {
                     keyboard::Key::Named(Named::Space) => Some(Message::TogglePlay),
@@ -335,10 +866,66 @@ impl Player {
                 None
             }
         });
-        Subscription::batch([Subscription::run(start_audio), keyboard_events])
+        let notice_timeout = if self.notice.is_some() {
+            iced::time::every(NOTICE_DURATION).map(|_| Message::DismissNotice)
+        } else {
+            Subscription::none()
+        };
+        let like_animation = if self.like_pulse.is_some() {
+            iced::window::frames().map(Message::LikeAnimationFrame)
+        } else {
+            Subscription::none()
+        };
+        Subscription::batch([
+            Subscription::run(start_audio),
+            keyboard_events,
+            notice_timeout,
+            like_animation,
+        ])
     }
 }
 
+// the chapters of the playing track as a strip under the seek bar, a click jumps to one
+fn chapter_markers<'a>(state: &State, current: u64, total: u64) -> Element<'a, Message> {
+    let chapters = state
+        .player
+        .current_playable
+        .map(|id| state.chapters(id))
+        .unwrap_or_default();
+    if chapters.is_empty() || total == 0 {
+        return Space::with_height(Length::Shrink).into();
+    }
+    // seconds as the share of the strip, long enough to fit in a portion
+    let portion = |seconds: u64| Length::FillPortion(seconds.clamp(1, u16::MAX as u64) as u16);
+    let playing = current_chapter(chapters, current);
+    let mut markers = Row::new().spacing(2);
+    if chapters[0].start > 0 {
+        markers = markers.push(Space::with_width(portion(chapters[0].start)));
+    }
+    for (index, chapter) in chapters.iter().enumerate() {
+        if chapter.start >= total {
+            break;
+        }
+        let end = chapters
+            .get(index + 1)
+            .map_or(total, |next| next.start)
+            .min(total);
+        markers = markers.push(
+            Button::new(Space::with_height(Length::Fixed(4.0)))
+                .padding(0)
+                .width(portion(end - chapter.start))
+                .style(if playing == Some(index) {
+                    iced::widget::button::primary
+                } else {
+                    iced::widget::button::secondary
+                })
+                .on_press(Message::Seek(chapter.start)),
+        );
+    }
+    let title = playing.map_or(String::new(), |index| chapters[index].title.clone());
+    column![markers, text(title).size(11)].spacing(2).into()
+}
+
 fn player_button<'a>(icon: char, size: Option<u8>) -> Button<'a, Message> {
     let size = size.unwrap_or(20);
     Button::new(text(icon).font(fonts::ICON).size(size as f32))
@@ -351,6 +938,99 @@ fn player_button<'a>(icon: char, size: Option<u8>) -> Button<'a, Message> {
         .style(button_style)
 }
 
+// A thin bar filled up to the RMS level, it turns red when the peak clips.
+fn level_meter<'a>(rms: f32, peak: f32) -> Element<'a, Message> {
+    const WIDTH: f32 = 40.0;
+    let clipping = peak >= 1.0;
+    let level = container(Space::new(
+        Length::Fixed(WIDTH * meter_fraction(rms)),
+        Length::Fixed(3.0),
+    ))
+    .style(move |theme: &iced::Theme| {
+        let palette = theme.palette();
+        let color = if clipping {
+            palette.danger
+        } else {
+            palette.success
+        };
+        container::Style {
+            background: Some(iced::Background::Color(color)),
+            ..Default::default()
+        }
+    });
+    container(level)
+        .width(Length::Fixed(WIDTH))
+        .style(|theme: &iced::Theme| container::Style {
+            background: Some(iced::Background::Color(
+                theme.palette().text.scale_alpha(0.15),
+            )),
+            ..Default::default()
+        })
+        .into()
+}
+
+// the slider of one band of the equalizer, with its gain and its frequency
+fn eq_band<'a>(band: usize, frequency: f32, db: i32) -> Element<'a, Message> {
+    let label = if frequency >= 1000.0 {
+        format!("{}k", frequency / 1000.0)
+    } else {
+        format!("{frequency}")
+    };
+    column![
+        text(format!("{db:+}")).size(11),
+        vertical_slider(
+            -equalizer::MAX_GAIN_DB..=equalizer::MAX_GAIN_DB,
+            db,
+            move |db| Message::EqBand(band, db)
+        )
+        .height(80),
+        text(label).size(11),
+    ]
+    .spacing(4)
+    .align_x(Horizontal::Center)
+    .width(36)
+    .into()
+}
+
+fn cast_target_button<'a>(
+    label: &str,
+    renderer: Option<&Renderer>,
+    selected: bool,
+) -> Element<'a, Message> {
+    let style = if selected {
+        iced::widget::button::primary
+    } else {
+        iced::widget::button::secondary
+    };
+    Button::new(text(label.to_string()).size(12))
+        .style(style)
+        .on_press(Message::CastTo(renderer.cloned()))
+        .into()
+}
+
+fn toggle_button<'a>(icon: char, enabled: bool) -> Button<'a, Message> {
+    Button::new(text(icon).font(fonts::ICON).size(20))
+        .padding(0)
+        .style(move |theme: &iced::Theme, status| {
+            let palette = theme.palette();
+            let text_color = if enabled {
+                palette.text
+            } else {
+                palette.text.scale_alpha(0.7)
+            };
+            iced::widget::button::Style {
+                text_color,
+                background: Some(iced::Background::Color(Color::TRANSPARENT)),
+                ..button_style(theme, status)
+            }
+        })
+}
+
+fn session_logs_dir() -> Option<std::path::PathBuf> {
+    let user_dirs = UserDirs::new()?;
+    Some(user_dirs.audio_dir()?.join("Phoniq Sessions"))
+}
+
 fn button_style(theme: &iced::Theme, status: Status) -> iced::widget::button::Style {
     let palette = theme.palette();
     let extended_palette = theme.extended_palette();
@@ -416,6 +1096,11 @@ fn start_audio() -> impl iced::futures::Stream<Item = Message> {
                 (handle, audio_tx) = spawn_audio_worker(sender.clone());
             }
             let input = receiver.select_next_some().await;
+            // levels only matter to the UI, the worker would wake up for each of them
+            if let Message::Levels(levels) = input {
+                let _ = output.send(Message::Levels(levels)).await;
+                continue;
+            }
             if let Err(e) = audio_tx.send(input.clone()) {
                 info!("Failed to send message to audio worker: {e:?}");
             }
@@ -426,31 +1111,113 @@ fn start_audio() -> impl iced::futures::Stream<Item = Message> {
                 Message::EndPlay => {
                     let _ = output.send(Message::EndPlay).await;
                 }
+                Message::NextStarted(playable) => {
+                    let _ = output.send(Message::NextStarted(playable)).await;
+                }
+                Message::RenderersFound(renderers) => {
+                    let _ = output.send(Message::RenderersFound(renderers)).await;
+                }
+                Message::CastFailed(error) => {
+                    let _ = output.send(Message::CastFailed(error)).await;
+                }
+                Message::Notice(notice) => {
+                    let _ = output.send(Message::Notice(notice)).await;
+                }
+                Message::OutputChanged(name) => {
+                    let _ = output.send(Message::OutputChanged(name)).await;
+                }
                 _ => {}
             }
         }
     })
 }
 
-fn load_and_play_audio(
+type Decoded = Box<dyn Source<Item = f32> + Send>;
+
+// A track which follows the playing one, decoded up to the sink.
+struct Prepared {
     playable: Arc<dyn AudioPlayable>,
-    sink: &Sink,
-) -> Result<u64, Box<dyn std::error::Error>> {
-    let source = Decoder::new(playable.stream()?)?;
+    source: Decoded,
+    duration: u64,
+}
+
+// Opens the track and readies it for the sink, returns it with its duration in seconds.
+// Silence is only skipped with `silence`, left out for previews.
+fn decode(
+    playable: &dyn AudioPlayable,
+    resample_to: Option<(u32, ResampleQuality)>,
+    trims: &VolumeTrims,
+    silence: Option<&Arc<SilenceSkip>>,
+    equalizer: &Arc<EqualizerShared>,
+    levels: &Sender<Message>,
+) -> Result<(Decoded, u64), Box<dyn std::error::Error>> {
+    let source = Decoder::new(playable.stream()?)?
+        .convert_samples::<f32>()
+        .amplify(trims.gain(playable));
+    let source = SkipSilence::new(source, silence.cloned());
+    let source = Equalizer::new(source, equalizer.clone());
 
     let duration = source.total_duration().map(|d| d.as_secs()).unwrap_or(0);
 
+    // rodio converts other sample rates with linear interpolation, the resampler does better
+    let source: Decoded = match resample_to {
+        Some((sample_rate, quality)) => match Resampled::new(source, sample_rate, quality) {
+            Ok(resampled) => Box::new(Meter::new(resampled, levels.clone())),
+            Err(source) => Box::new(Meter::new(source, levels.clone())),
+        },
+        None => Box::new(Meter::new(source, levels.clone())),
+    };
+    Ok((source, duration))
+}
+
+fn load_and_play_audio(
+    playable: Arc<dyn AudioPlayable>,
+    sink: &Sink,
+    resample_to: Option<(u32, ResampleQuality)>,
+    trims: &VolumeTrims,
+    silence: &Arc<SilenceSkip>,
+    equalizer: &Arc<EqualizerShared>,
+    levels: &Sender<Message>,
+) -> Result<u64, Box<dyn std::error::Error>> {
+    let (source, duration) = decode(
+        playable.as_ref(),
+        resample_to,
+        trims,
+        Some(silence),
+        equalizer,
+        levels,
+    )?;
     sink.append(source);
     sink.play();
 
     Ok(duration)
 }
 
+// Stops the preview, if any, and brings the track it ducked back to its volume.
+fn end_preview(previewing: &mut Option<(Sink, bool)>, sink: &Sink, volume: f32) {
+    if let Some((preview, ducked)) = previewing.take() {
+        preview.stop();
+        if ducked {
+            fade(sink, volume * DUCK_VOLUME, volume, DUCK_FADE);
+        }
+    }
+}
+
+// How long the playing track has left, None when its length is unknown.
+fn remaining(sink: &Sink, total_duration: u64, silence: &SilenceSkip) -> Option<Duration> {
+    if total_duration == 0 {
+        return None;
+    }
+    Some(Duration::from_secs(total_duration).saturating_sub(sink.get_pos() + silence.skipped()))
+}
+
 // Helper function to handle the timeout case (send progress updates)
 fn handle_timeout(
     sink: &Sink,
     sender: &mut Sender<Message>,
     total_duration: &mut u64, // Pass mutably in case we need to reset it
+    latency: Duration,
+    silence: &SilenceSkip,
 ) {
     if !sink.empty() {
         if sink.is_paused() {
@@ -458,7 +1225,7 @@ fn handle_timeout(
             let _ = sender.try_send(Message::Paused);
         } else {
             // Send ProgressUpdate periodically
-            let current_pos = sink.get_pos().as_secs();
+            let current_pos = heard_position(sink, latency, silence);
             // Ensure current_pos doesn't exceed total_duration visually
             let display_pos = current_pos.min(*total_duration);
             let _ = sender.try_send(Message::ProgressUpdate(display_pos, *total_duration));
@@ -473,62 +1240,651 @@ fn handle_timeout(
     }
 }
 
+// The position which reaches the ears, the device plays `latency` behind the sink. The silence
+// skipped never went through the sink and is added back.
+fn heard_position(sink: &Sink, latency: Duration, silence: &SilenceSkip) -> u64 {
+    (sink.get_pos() + silence.skipped())
+        .saturating_sub(latency)
+        .as_secs()
+}
+
+// Ramps the volume of the sink in small steps, blocks the worker until the fade is done.
+fn fade(sink: &Sink, from: f32, to: f32, duration: Duration) {
+    let steps = (duration.as_millis() / FADE_STEP.as_millis()) as u32;
+    for step in 1..=steps {
+        sink.set_volume(from + (to - from) * step as f32 / steps as f32);
+        std::thread::sleep(FADE_STEP);
+    }
+    sink.set_volume(to);
+}
+
+// The name of the default output device, None when there is none.
+fn default_output_name() -> Option<String> {
+    rodio::cpal::default_host()
+        .default_output_device()?
+        .name()
+        .ok()
+}
+
+// The sample rate the sink plays at, the one of the track when matching the source rate or else
+// the default of the device.
+fn output_rate(output_format: Option<(u32, u16)>) -> Option<u32> {
+    match output_format {
+        Some((sample_rate, _)) => Some(sample_rate),
+        None => rodio::cpal::default_host()
+            .default_output_device()?
+            .default_output_config()
+            .ok()
+            .map(|config| config.sample_rate().0),
+    }
+}
+
+// Opens the default output device, the playing track carries on from the position given with it,
+// paused unless it was playing.
+fn open_output(
+    current: Option<(&Arc<dyn AudioPlayable>, u64, bool)>,
+    volume: f32,
+    quality: ResampleQuality,
+    trims: &VolumeTrims,
+    silence: &Arc<SilenceSkip>,
+    equalizer: &Arc<EqualizerShared>,
+    levels: &Sender<Message>,
+) -> Result<(OutputStream, OutputStreamHandle, Sink), Box<dyn std::error::Error>> {
+    let (stream, stream_handle) = OutputStream::try_default()?;
+    let sink = Sink::try_new(&stream_handle)?;
+    sink.set_volume(volume);
+    if let Some((playable, position, is_playing)) = current {
+        let resample_to = output_rate(None).map(|rate| (rate, quality));
+        load_and_play_audio(
+            playable.clone(),
+            &sink,
+            resample_to,
+            trims,
+            silence,
+            equalizer,
+            levels,
+        )?;
+        if position > 0 {
+            sink.try_seek(Duration::from_secs(position))?;
+        }
+        if !is_playing {
+            sink.pause();
+        }
+    }
+    Ok((stream, stream_handle, sink))
+}
+
+// Sample rate and channel count of a local file, read from its header.
+fn source_format(playable: &dyn AudioPlayable) -> Option<(u32, u16)> {
+    if playable.get_kind() != PlayableKind::LocalFile {
+        return None;
+    }
+    let tagged_file = lofty::read_from_path(playable.get_path()).ok()?;
+    let properties = tagged_file.properties();
+    Some((properties.sample_rate()?, properties.channels()? as u16))
+}
+
+// Opens the default device at the format of the track, nothing is resampled on the way.
+// Fails when the device has no such configuration.
+fn open_output_at(
+    (sample_rate, channels): (u32, u16),
+) -> Result<(OutputStream, OutputStreamHandle, Sink), Box<dyn std::error::Error>> {
+    let device = rodio::cpal::default_host()
+        .default_output_device()
+        .ok_or("no output device")?;
+    let config = device
+        .supported_output_configs()?
+        .find(|config| {
+            config.channels() == channels
+                && config.min_sample_rate().0 <= sample_rate
+                && sample_rate <= config.max_sample_rate().0
+        })
+        .ok_or_else(|| format!("{sample_rate} Hz with {channels} channels is not supported"))?
+        .with_sample_rate(SampleRate(sample_rate));
+    let (stream, stream_handle) = OutputStream::try_from_device_config(&device, config)?;
+    let sink = Sink::try_new(&stream_handle)?;
+    Ok((stream, stream_handle, sink))
+}
+
+// Starts a session on `renderer`, what it reports comes back to the worker and the UI as messages.
+fn start_cast(
+    renderer: Renderer,
+    volume: f32,
+    sender: &Sender<Message>,
+) -> Result<CastSession, CastError> {
+    let mut sender = sender.clone();
+    CastSession::start(renderer, volume, move |event| {
+        let message = match event {
+            CastEvent::Progress(position, duration) => Message::ProgressUpdate(position, duration),
+            CastEvent::Ended => Message::EndPlay,
+            CastEvent::Failed(error) => {
+                error!("[AudioWorker] Casting failed: {error}");
+                Message::CastFailed(error.to_string())
+            }
+        };
+        let _ = sender.try_send(message);
+    })
+}
+
 // Main audio worker function, now using helper functions
 fn audio_worker(
     mut sender: Sender<Message>, // No longer needs to be explicitly futures::channel::mpsc::Sender
     receiver: std::sync::mpsc::Receiver<Message>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let (_stream, stream_handle) = OutputStream::try_default()?;
-    let sink = Sink::try_new(&stream_handle)?;
+    let (mut _stream, mut stream_handle) = OutputStream::try_default()?;
+    let mut sink = Sink::try_new(&stream_handle)?;
+    // a sink stays on the device it was opened on, it is moved when the default changes
+    let mut output_name = default_output_name();
+    let mut last_device_check = Instant::now();
+    let mut match_source_rate = false;
+    // Some while the device is open at the format of the track instead of the default one
+    let mut output_format: Option<(u32, u16)> = None;
+    let mut quality = ResampleQuality::default();
+    // the trims applied to each track, none while matching the source rate
+    let mut trims = VolumeTrims::default();
+    let mut chosen_trims = VolumeTrims::default();
+    let silence = Arc::new(SilenceSkip::default());
+    let equalizer = Arc::new(EqualizerShared::default());
+    // the curve of each output, the one of the device played on is set on `equalizer`
+    let mut eq_curves = EqCurves::default();
+    // the last format the device refused, tracks in it go through the mixer without a notice
+    let mut refused_format: Option<(u32, u16)> = None;
     let mut total_duration: u64 = 0;
     let progress_update_interval = Duration::from_millis(500);
     let mut is_playing = false;
+    // the volume the sink plays at, fades return to it, full while matching the source rate
+    let mut volume = 1.0;
+    // the volume set by the user
+    let mut chosen_volume = 1.0;
+    let mut fade_duration = Duration::ZERO;
+    let mut crossfade = Duration::ZERO;
+    // the track to follow, decoded while the current one plays
+    let mut prepared: Option<Prepared> = None;
+    // the prepared track once it waits in the sink behind the playing one, with its duration
+    let mut queued: Option<(Arc<dyn AudioPlayable>, u64)> = None;
+    // the sink of the track fading out under the next one, and since when
+    let mut fading_out: Option<(Sink, Instant)> = None;
+    let mut last_progress = Instant::now();
+    // the sink of a preview, and whether the playing track was ducked under it
+    let mut previewing: Option<(Sink, bool)> = None;
+    let mut latency = Duration::ZERO;
+    // Some while a renderer plays in place of the sink
+    let mut cast: Option<CastSession> = None;
+    // kept to move playback between this computer and a renderer
+    let mut current: Option<Arc<dyn AudioPlayable>> = None;
     info!("[AudioWorker] Started");
+    let _ = sender.try_send(Message::OutputChanged(
+        output_name.clone().unwrap_or_default(),
+    ));
 
     loop {
-        if is_playing && sink.empty() {
+        if cast.is_none() && is_playing {
+            // the queued track took over in the same sink
+            if sink.len() <= 1
+                && let Some((playable, duration)) = queued.take()
+            {
+                current = Some(playable.clone());
+                total_duration = duration;
+                let _ = sender.try_send(Message::NextStarted(playable));
+                let _ = sender.try_send(Message::ProgressUpdate(0, total_duration));
+            }
+            let starts = match remaining(&sink, total_duration, &silence) {
+                Some(left) if queued.is_none() && fading_out.is_none() => {
+                    left <= crossfade.max(GAPLESS_AHEAD)
+                }
+                _ => false,
+            };
+            if starts && let Some(next) = prepared.take() {
+                if crossfade.is_zero() {
+                    sink.append(next.source);
+                    queued = Some((next.playable, next.duration));
+                } else {
+                    let next_sink = Sink::try_new(&stream_handle)?;
+                    next_sink.set_volume(0.0);
+                    next_sink.append(next.source);
+                    fading_out = Some((std::mem::replace(&mut sink, next_sink), Instant::now()));
+                    current = Some(next.playable.clone());
+                    total_duration = next.duration;
+                    let _ = sender.try_send(Message::NextStarted(next.playable));
+                    let _ = sender.try_send(Message::ProgressUpdate(0, total_duration));
+                }
+            }
+        }
+        // the share of the volume the playing tracks keep
+        let duck = match &previewing {
+            Some((_, true)) => DUCK_VOLUME,
+            _ => 1.0,
+        };
+        if let Some((old, started)) = &fading_out {
+            let done = if crossfade.is_zero() {
+                1.0
+            } else {
+                (started.elapsed().as_secs_f32() / crossfade.as_secs_f32()).min(1.0)
+            };
+            old.set_volume(volume * duck * (1.0 - done));
+            sink.set_volume(volume * duck * done);
+            if done >= 1.0 {
+                old.stop();
+                fading_out = None;
+            }
+        }
+        // a preview which ran out gives way to the ducked track
+        if previewing
+            .as_ref()
+            .is_some_and(|(preview, _)| preview.empty())
+        {
+            end_preview(&mut previewing, &sink, volume);
+        }
+        if cast.is_none() && is_playing && sink.empty() {
             is_playing = false;
             let _ = sender.try_send(Message::EndPlay);
         }
-        match receiver.recv_timeout(progress_update_interval) {
+        let timeout = if fading_out.is_some() {
+            CROSSFADE_STEP
+        } else {
+            progress_update_interval
+        };
+        match receiver.recv_timeout(timeout) {
             Ok(message) => match message {
                 Message::Play(playable) => {
-                    sink.stop();
-                    sink.clear();
+                    current = Some(playable.clone());
+                    if let Some((preview, _)) = previewing.take() {
+                        preview.stop();
+                    }
+                    // whatever was prepared or is fading out belongs to the list played before
+                    prepared = None;
+                    queued = None;
+                    if let Some((old, _)) = fading_out.take() {
+                        old.stop();
+                    }
+                    if let Some(session) = &cast {
+                        session.play(playable.clone(), 0);
+                        total_duration = playable.get_duration();
+                        is_playing = true;
+                        let _ = sender.try_send(Message::ProgressUpdate(0, total_duration));
+                    } else {
+                        // a skip fades the playing track out and the new one in, a track
+                        // which ended on its own is followed right away
+                        let skipping = is_playing && !sink.empty();
+                        if skipping {
+                            fade(&sink, volume * duck, 0.0, fade_duration);
+                        }
+                        sink.stop();
+                        sink.clear();
 
-                    let duration = load_and_play_audio(playable.clone(), &sink)?;
-                    total_duration = duration;
-                    is_playing = true;
-                    let _ = sender.try_send(Message::ProgressUpdate(0, total_duration));
+                        let wanted = if match_source_rate {
+                            source_format(playable.as_ref()).filter(|f| refused_format != Some(*f))
+                        } else {
+                            None
+                        };
+                        if wanted != output_format {
+                            let mut opened = None;
+                            if let Some(format) = wanted {
+                                match open_output_at(format) {
+                                    Ok(output) => opened = Some((output, wanted)),
+                                    Err(e) => {
+                                        error!("[AudioWorker] Source rate output failed: {e}");
+                                        refused_format = Some(format);
+                                        let _ = sender.try_send(Message::Notice(format!(
+                                            "The output can not play {} kHz directly, \
+                                             it goes through the system mixer",
+                                            format.0 as f32 / 1000.0
+                                        )));
+                                    }
+                                }
+                            }
+                            // back to the shared default format
+                            if opened.is_none() && output_format.is_some() {
+                                let (stream, handle) = OutputStream::try_default()?;
+                                let sink = Sink::try_new(&handle)?;
+                                opened = Some(((stream, handle, sink), None));
+                            }
+                            if let Some(((new_stream, new_handle, new_sink), format)) = opened {
+                                _stream = new_stream;
+                                stream_handle = new_handle;
+                                sink = new_sink;
+                                output_format = format;
+                            }
+                        }
+                        // pausing leaves the volume at zero
+                        sink.set_volume(if skipping { 0.0 } else { volume });
+
+                        let resample_to = output_rate(output_format).map(|rate| (rate, quality));
+                        let duration = load_and_play_audio(
+                            playable.clone(),
+                            &sink,
+                            resample_to,
+                            &trims,
+                            &silence,
+                            &equalizer,
+                            &sender,
+                        )?;
+                        total_duration = duration;
+                        is_playing = true;
+                        if skipping {
+                            fade(&sink, 0.0, volume, fade_duration);
+                        }
+                        let _ = sender.try_send(Message::ProgressUpdate(0, total_duration));
+                    }
                 }
                 Message::Paused => {
+                    if let Some(session) = &cast {
+                        session.pause();
+                        is_playing = false;
+                        continue;
+                    }
+                    if let Some((old, _)) = fading_out.take() {
+                        old.stop();
+                    }
+                    if let Some((preview, _)) = previewing.take() {
+                        preview.stop();
+                    }
+                    if !sink.is_paused() && !sink.empty() {
+                        fade(&sink, volume * duck, 0.0, fade_duration);
+                    }
                     sink.pause();
                     is_playing = false;
-                    let current_progress = sink.get_pos().as_secs();
+                    let current_progress = heard_position(&sink, latency, &silence);
                     let _ =
                         sender.try_send(Message::ProgressUpdate(current_progress, total_duration));
                 }
                 Message::Resume => {
+                    if let Some(session) = &cast {
+                        session.resume();
+                        is_playing = true;
+                        continue;
+                    }
+                    if let Some((preview, _)) = previewing.take() {
+                        preview.stop();
+                    }
+                    sink.set_volume(0.0);
                     sink.play();
+                    fade(&sink, 0.0, volume, fade_duration);
                     is_playing = true;
-                    let current_progress = sink.get_pos().as_secs();
+                    let current_progress = heard_position(&sink, latency, &silence);
                     let _ =
                         sender.try_send(Message::ProgressUpdate(current_progress, total_duration));
                 }
                 Message::Seek(pos) => {
-                    if !sink.empty() {
+                    if let Some(session) = &cast {
+                        session.seek(pos);
+                    } else if !sink.empty() {
                         let seek_duration = Duration::from_secs(pos);
                         sink.try_seek(seek_duration)?;
                     }
                 }
                 Message::VolumeChanged(vol) => {
-                    sink.set_volume(vol);
+                    chosen_volume = vol;
+                    if !match_source_rate {
+                        volume = vol;
+                    }
+                    if let Some(session) = &cast {
+                        session.set_volume(vol);
+                    } else {
+                        sink.set_volume(volume * duck);
+                        if let Some((preview, _)) = &previewing {
+                            preview.set_volume(volume * PREVIEW_VOLUME);
+                        }
+                    }
+                }
+                Message::FadeChanged(fade_ms) => {
+                    fade_duration = Duration::from_millis(fade_ms);
+                }
+                Message::CrossfadeChanged(secs) => {
+                    crossfade = Duration::from_secs(secs);
+                }
+                Message::Prefetch(playable) => {
+                    prepared = None;
+                    // only local files, others would hold up the worker on the network
+                    let Some(playable) =
+                        playable.filter(|p| p.get_kind() == PlayableKind::LocalFile)
+                    else {
+                        continue;
+                    };
+                    // a track wanting another device format needs the device reopened
+                    let format = if match_source_rate {
+                        source_format(playable.as_ref()).filter(|f| refused_format != Some(*f))
+                    } else {
+                        None
+                    };
+                    if cast.is_some() || format != output_format {
+                        continue;
+                    }
+                    let resample_to = output_rate(output_format).map(|rate| (rate, quality));
+                    match decode(
+                        playable.as_ref(),
+                        resample_to,
+                        &trims,
+                        Some(&silence),
+                        &equalizer,
+                        &sender,
+                    ) {
+                        Ok((source, duration)) => {
+                            prepared = Some(Prepared {
+                                playable,
+                                source,
+                                duration,
+                            })
+                        }
+                        Err(e) => error!("[AudioWorker] Failed to prepare the next track: {e}"),
+                    }
+                }
+                Message::Preview(playable) => {
+                    end_preview(&mut previewing, &sink, volume);
+                    // only local files, others would hold up the worker on the network
+                    let Some(playable) =
+                        playable.filter(|p| p.get_kind() == PlayableKind::LocalFile)
+                    else {
+                        continue;
+                    };
+                    if cast.is_some() {
+                        continue;
+                    }
+                    let resample_to = output_rate(output_format).map(|rate| (rate, quality));
+                    match decode(
+                        playable.as_ref(),
+                        resample_to,
+                        &trims,
+                        None,
+                        &equalizer,
+                        &sender,
+                    ) {
+                        Ok((source, duration)) => {
+                            let preview = Sink::try_new(&stream_handle)?;
+                            preview.set_volume(volume * PREVIEW_VOLUME);
+                            preview.append(
+                                source
+                                    .skip_duration(Duration::from_secs(duration / 3))
+                                    .take_duration(PREVIEW_LENGTH),
+                            );
+                            // a crossfade in progress goes on from the ducked volume
+                            let ducked = is_playing && !sink.is_paused() && !sink.empty();
+                            if ducked && fading_out.is_none() {
+                                fade(&sink, volume, volume * DUCK_VOLUME, DUCK_FADE);
+                            }
+                            previewing = Some((preview, ducked));
+                        }
+                        Err(e) => error!("[AudioWorker] Failed to preview the track: {e}"),
+                    }
+                }
+                Message::LatencyChanged(latency_ms) => {
+                    latency = Duration::from_millis(latency_ms);
+                }
+                Message::ResampleQualityChanged(value) => {
+                    quality = value;
+                }
+                Message::TrimsChanged(value) => {
+                    if !match_source_rate {
+                        trims = value.clone();
+                    }
+                    chosen_trims = value;
+                }
+                Message::SkipSilenceChanged(threshold_db) => {
+                    silence.set_threshold(threshold_db);
+                }
+                Message::EqCurvesChanged(curves) => {
+                    equalizer.set(&curves.curve(output_name.as_deref().unwrap_or_default()));
+                    eq_curves = curves;
+                }
+                Message::MatchSourceRateChanged(value) => {
+                    match_source_rate = value;
+                    refused_format = None;
+                    // the samples go out as decoded, without the volume or trims
+                    (volume, trims) = if value {
+                        (1.0, VolumeTrims::default())
+                    } else {
+                        (chosen_volume, chosen_trims.clone())
+                    };
+                    if cast.is_none() {
+                        sink.set_volume(volume * duck);
+                    }
+                }
+                Message::DiscoverRenderers => {
+                    // the search waits for answers, playback goes on meanwhile
+                    let mut found = sender.clone();
+                    std::thread::spawn(move || {
+                        let renderers = cast::discover(DISCOVERY_TIMEOUT).unwrap_or_else(|e| {
+                            error!("[AudioWorker] Renderer search failed: {e}");
+                            Vec::new()
+                        });
+                        let _ = found.try_send(Message::RenderersFound(renderers));
+                    });
+                }
+                // reported by the cast session
+                Message::ProgressUpdate(_, duration) if cast.is_some() => {
+                    total_duration = duration;
+                }
+                Message::EndPlay if cast.is_some() => {
+                    is_playing = false;
+                }
+                // the next command plays on this computer again
+                Message::CastFailed(_) => {
+                    if cast.as_ref().is_some_and(CastSession::has_failed) {
+                        cast = None;
+                    }
+                }
+                Message::CastTo(renderer) => {
+                    let previous = cast.take();
+                    let position = match &previous {
+                        Some(session) => session.position(),
+                        None => heard_position(&sink, latency, &silence),
+                    };
+                    // the session stops the renderer on its own thread once dropped
+                    let was_casting = previous.is_some();
+                    drop(previous);
+                    prepared = None;
+                    queued = None;
+                    if let Some((old, _)) = fading_out.take() {
+                        old.stop();
+                    }
+                    end_preview(&mut previewing, &sink, volume);
+                    match renderer {
+                        Some(renderer) => {
+                            sink.stop();
+                            sink.clear();
+                            match start_cast(renderer, chosen_volume, &sender) {
+                                Ok(session) => {
+                                    // playback moves over where it was, paused tracks start on
+                                    // resume
+                                    match &current {
+                                        Some(playable) if is_playing => {
+                                            session.play(playable.clone(), position)
+                                        }
+                                        Some(playable) => session.cue(playable.clone(), position),
+                                        None => {}
+                                    }
+                                    cast = Some(session);
+                                }
+                                Err(e) => {
+                                    error!("[AudioWorker] Casting failed: {e}");
+                                    let _ = sender.try_send(Message::CastFailed(e.to_string()));
+                                }
+                            }
+                        }
+                        // back from a renderer, this computer picks up where it was
+                        None if was_casting => {
+                            if let Some(playable) = &current {
+                                sink.stop();
+                                sink.clear();
+                                sink.set_volume(volume);
+                                let resample_to =
+                                    output_rate(output_format).map(|rate| (rate, quality));
+                                total_duration = load_and_play_audio(
+                                    playable.clone(),
+                                    &sink,
+                                    resample_to,
+                                    &trims,
+                                    &silence,
+                                    &equalizer,
+                                    &sender,
+                                )?;
+                                if position > 0 {
+                                    sink.try_seek(Duration::from_secs(position))?;
+                                }
+                                if !is_playing {
+                                    sink.pause();
+                                }
+                            }
+                        }
+                        None => {}
+                    }
                 }
                 _ => {}
             },
-            Err(RecvTimeoutError::Timeout) => {
-                handle_timeout(&sink, &mut sender, &mut total_duration);
-            }
+            Err(RecvTimeoutError::Timeout) => match &cast {
+                // the session polls the renderer itself
+                Some(_) => {}
+                None => {
+                    // the loop wakes up more often while a crossfade runs
+                    if last_progress.elapsed() >= progress_update_interval {
+                        last_progress = Instant::now();
+                        handle_timeout(&sink, &mut sender, &mut total_duration, latency, &silence);
+                    }
+                    if last_device_check.elapsed() < DEVICE_CHECK_INTERVAL {
+                        continue;
+                    }
+                    last_device_check = Instant::now();
+                    let name = default_output_name();
+                    if name.is_none() || name == output_name {
+                        continue;
+                    }
+                    info!("[AudioWorker] Default output changed to {name:?}");
+                    output_name = name.clone();
+                    let output = name.clone().unwrap_or_default();
+                    equalizer.set(&eq_curves.curve(&output));
+                    let _ = sender.try_send(Message::OutputChanged(output));
+                    let position = heard_position(&sink, latency, &silence);
+                    let playing = current
+                        .as_ref()
+                        .filter(|_| !sink.empty())
+                        .map(|playable| (playable, position, is_playing));
+                    match open_output(
+                        playing, volume, quality, &trims, &silence, &equalizer, &sender,
+                    ) {
+                        Ok((new_stream, new_handle, new_sink)) => {
+                            sink.stop();
+                            if let Some((old, _)) = fading_out.take() {
+                                old.stop();
+                            }
+                            if let Some((preview, _)) = previewing.take() {
+                                preview.stop();
+                            }
+                            _stream = new_stream;
+                            stream_handle = new_handle;
+                            sink = new_sink;
+                            output_format = None;
+                            // prepared for the old device, the next track follows with a gap
+                            prepared = None;
+                            queued = None;
+                            let _ = sender.try_send(Message::Notice(format!(
+                                "Output switched to {}",
+                                name.unwrap_or_default()
+                            )));
+                        }
+                        Err(e) => error!("[AudioWorker] Failed to open the new output: {e}"),
+                    }
+                }
+            },
             Err(RecvTimeoutError::Disconnected) => {
                 info!("[AudioWorker] Channel disconnected. Exiting.");
                 break; // Exit the loop cleanly
@@ -0,0 +1,370 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use directories::UserDirs;
+use roxmltree::{Document, Node, ParsingOptions};
+use thiserror::Error;
+
+use crate::util::date_to_timestamp;
+
+const ITUNES_NS: &str = "http://www.itunes.com/dtds/podcast-1.0.dtd";
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+#[derive(Debug, Error)]
+pub enum PodcastError {
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Invalid feed: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("Feed has no channel")]
+    MissingChannel,
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, PodcastError>;
+
+#[derive(Debug, Clone)]
+pub struct Feed {
+    pub title: String,
+    pub episodes: Vec<FeedEpisode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedEpisode {
+    pub guid: String,
+    pub title: String,
+    pub description: String,
+    // unix timestamp, 0 when the feed does not say
+    pub published: i64,
+    pub audio_url: String,
+    pub duration: u64,
+}
+
+pub async fn fetch_feed(url: String) -> Result<Feed> {
+    let body = reqwest::get(&url).await?.error_for_status()?.text().await?;
+    parse_feed(&body)
+}
+
+/// Writes the episode to disk as it arrives, see `episode_path` for where.
+pub async fn download_episode(
+    url: String,
+    dir: PathBuf,
+    podcast: String,
+    episode_id: i64,
+) -> Result<PathBuf> {
+    let mut response = reqwest::get(&url).await?.error_for_status()?;
+    let path = episode_path(&dir, &podcast, episode_id, &url);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    // renamed once complete, an interrupted download leaves no half episode behind
+    let partial = path.with_extension("part");
+    let mut file = BufWriter::new(File::create(&partial)?);
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk)?;
+    }
+    file.flush()?;
+    drop(file);
+    std::fs::rename(&partial, &path)?;
+    Ok(path)
+}
+
+/// Blocking download used by the audio worker to stream episodes which are not on disk.
+pub fn fetch_audio(url: &str) -> std::io::Result<Vec<u8>> {
+    let response = reqwest::blocking::get(url)
+        .and_then(|r| r.error_for_status())
+        .map_err(std::io::Error::other)?;
+    let bytes = response.bytes().map_err(std::io::Error::other)?;
+    Ok(bytes.to_vec())
+}
+
+pub fn downloads_dir() -> Option<PathBuf> {
+    let user_dirs = UserDirs::new()?;
+    Some(user_dirs.audio_dir()?.join("Phoniq Podcasts"))
+}
+
+pub fn parse_feed(xml: &str) -> Result<Feed> {
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let doc = Document::parse_with_options(xml, options)?;
+    let channel = doc
+        .descendants()
+        .find(|n| n.has_tag_name("channel"))
+        .ok_or(PodcastError::MissingChannel)?;
+
+    let episodes = channel
+        .children()
+        .filter(|n| n.has_tag_name("item"))
+        .filter_map(|item| {
+            // items without an enclosure have nothing to play
+            let audio_url = item
+                .children()
+                .find(|n| n.has_tag_name("enclosure"))?
+                .attribute("url")?
+                .to_string();
+            Some(FeedEpisode {
+                guid: child_text(item, "guid").unwrap_or_else(|| audio_url.clone()),
+                title: child_text(item, "title").unwrap_or_default(),
+                description: child_text(item, "description")
+                    .map(|d| strip_html(&d))
+                    .unwrap_or_default(),
+                published: child_text(item, "pubDate")
+                    .and_then(|d| parse_pub_date(&d))
+                    .unwrap_or(0),
+                duration: item
+                    .children()
+                    .find(|n| n.has_tag_name((ITUNES_NS, "duration")))
+                    .and_then(|n| n.text())
+                    .map(parse_duration)
+                    .unwrap_or(0),
+                audio_url,
+            })
+        })
+        .collect();
+
+    Ok(Feed {
+        title: child_text(channel, "title").unwrap_or_default(),
+        episodes,
+    })
+}
+
+// only matches elements without a namespace, so that `itunes:title` does not shadow `title`
+fn child_text(node: Node<'_, '_>, name: &str) -> Option<String> {
+    node.children()
+        .find(|n| {
+            n.is_element() && n.tag_name().namespace().is_none() && n.tag_name().name() == name
+        })
+        .and_then(|n| n.text())
+        .map(|t| t.trim().to_string())
+}
+
+// accepts `HH:MM:SS`, `MM:SS` and plain seconds
+fn parse_duration(value: &str) -> u64 {
+    value
+        .trim()
+        .split(':')
+        .try_fold(0u64, |acc, part| {
+            part.split('.')
+                .next()
+                .and_then(|p| p.parse::<u64>().ok())
+                .map(|n| acc * 60 + n)
+        })
+        .unwrap_or(0)
+}
+
+// RFC 2822 dates, e.g. `Wed, 02 Oct 2002 13:00:00 GMT`
+fn parse_pub_date(value: &str) -> Option<i64> {
+    let mut parts = value
+        .split_whitespace()
+        .skip_while(|p| p.parse::<i64>().is_err());
+    let day: i64 = parts.next()?.parse().ok()?;
+    let month = parts.next()?;
+    let month = MONTHS.iter().position(|m| month.starts_with(m))? as i64 + 1;
+    let year: i64 = parts.next()?.parse().ok()?;
+    let mut time = parts.next().unwrap_or("00:00").split(':');
+    let hours: i64 = time.next()?.parse().ok()?;
+    let minutes: i64 = time.next()?.parse().ok()?;
+    let seconds: i64 = time.next().and_then(|s| s.parse().ok()).unwrap_or(0);
+    let offset = parts.next().map(zone_offset).unwrap_or(0);
+    Some(date_to_timestamp(year, month, day) + hours * 3600 + minutes * 60 + seconds - offset)
+}
+
+fn zone_offset(zone: &str) -> i64 {
+    match zone {
+        "EST" => -5 * 3600,
+        "EDT" => -4 * 3600,
+        "CST" => -6 * 3600,
+        "CDT" => -5 * 3600,
+        "MST" => -7 * 3600,
+        "MDT" => -6 * 3600,
+        "PST" => -8 * 3600,
+        "PDT" => -7 * 3600,
+        _ => {
+            let sign = match zone.chars().next() {
+                Some('+') => 1,
+                Some('-') => -1,
+                _ => return 0,
+            };
+            let digits = &zone[1..];
+            match (digits.get(0..2), digits.get(2..4)) {
+                (Some(h), Some(m)) => match (h.parse::<i64>(), m.parse::<i64>()) {
+                    (Ok(h), Ok(m)) => sign * (h * 3600 + m * 60),
+                    _ => 0,
+                },
+                _ => 0,
+            }
+        }
+    }
+}
+
+fn strip_html(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut in_tag = false;
+    for c in value.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                out.push(' ');
+            }
+            c if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+// `<dir>/<podcast>/<episode id>-<name in the url>`, feeds often give every episode the same
+// name like `audio.mp3`
+fn episode_path(dir: &Path, podcast: &str, episode_id: i64, url: &str) -> PathBuf {
+    let podcast = sanitize(podcast);
+    let podcast = if podcast.trim_matches(['.', ' ']).is_empty() {
+        "Podcast".to_string()
+    } else {
+        podcast
+    };
+    dir.join(podcast)
+        .join(format!("{episode_id}-{}", file_name(url)))
+}
+
+fn file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let name = urlencoding::decode(name)
+        .map(|n| n.into_owned())
+        .unwrap_or_else(|_| name.to_string());
+    let name = sanitize(&name);
+    if name.trim_matches(['.', ' ']).is_empty() {
+        "episode.mp3".to_string()
+    } else {
+        name
+    }
+}
+
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || "._- ".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static FEED: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+  <channel>
+    <title>Test Podcast</title>
+    <item>
+      <title>Second</title>
+      <itunes:title>Ignored</itunes:title>
+      <guid isPermaLink="false">ep-2</guid>
+      <description><![CDATA[<p>Hello &amp; <b>welcome</b></p>]]></description>
+      <pubDate>Wed, 02 Oct 2002 15:00:00 +0200</pubDate>
+      <enclosure url="https://example.com/ep%202.mp3?x=1" type="audio/mpeg" length="1"/>
+      <itunes:duration>1:02:03</itunes:duration>
+    </item>
+    <item>
+      <title>No audio</title>
+    </item>
+    <item>
+      <title>First</title>
+      <enclosure url="https://example.com/ep1.mp3" type="audio/mpeg" length="1"/>
+      <itunes:duration>95</itunes:duration>
+    </item>
+  </channel>
+</rss>"#;
+
+    #[test]
+    fn test_parse_feed() {
+        let feed = parse_feed(FEED).unwrap();
+        assert_eq!(feed.title, "Test Podcast");
+        assert_eq!(feed.episodes.len(), 2);
+
+        let episode = &feed.episodes[0];
+        assert_eq!(episode.guid, "ep-2");
+        assert_eq!(episode.title, "Second");
+        assert_eq!(episode.description, "Hello & welcome");
+        assert_eq!(episode.published, 1_033_563_600);
+        assert_eq!(episode.audio_url, "https://example.com/ep%202.mp3?x=1");
+        assert_eq!(episode.duration, 3723);
+
+        let episode = &feed.episodes[1];
+        assert_eq!(episode.guid, "https://example.com/ep1.mp3");
+        assert_eq!(episode.published, 0);
+        assert_eq!(episode.duration, 95);
+    }
+
+    #[test]
+    fn test_parse_feed_without_channel() {
+        assert!(matches!(
+            parse_feed("<rss></rss>"),
+            Err(PodcastError::MissingChannel)
+        ));
+    }
+
+    #[test]
+    fn test_parse_pub_date() {
+        assert_eq!(
+            parse_pub_date("Wed, 02 Oct 2002 13:00:00 GMT"),
+            Some(1_033_563_600)
+        );
+        assert_eq!(
+            parse_pub_date("02 Oct 2002 08:00:00 EST"),
+            Some(1_033_563_600)
+        );
+        assert_eq!(parse_pub_date("yesterday"), None);
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("1:02:03"), 3723);
+        assert_eq!(parse_duration("02:03"), 123);
+        assert_eq!(parse_duration("300.5"), 300);
+        assert_eq!(parse_duration("n/a"), 0);
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(
+            file_name("https://example.com/a/ep%202.mp3?x=1"),
+            "ep 2.mp3"
+        );
+        assert_eq!(file_name("https://example.com/"), "episode.mp3");
+    }
+
+    #[test]
+    fn test_episode_path() {
+        let dir = Path::new("/podcasts");
+        assert_eq!(
+            episode_path(dir, "Talk: Show", 3, "https://example.com/audio.mp3"),
+            dir.join("Talk_ Show").join("3-audio.mp3")
+        );
+        assert_eq!(
+            episode_path(dir, "..", 4, "https://example.com/audio.mp3"),
+            dir.join("Podcast").join("4-audio.mp3")
+        );
+    }
+}
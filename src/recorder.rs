@@ -0,0 +1,335 @@
+// Recording from an input device, or what an output device plays, into a WAV file. The audio
+// callback hands the samples to a thread of its own which writes them, FLAC recordings are
+// encoded from that file once the recording stops.
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use directories::UserDirs;
+use log::error;
+use rodio::cpal::{
+    Device, FromSample, Sample, SampleFormat, SizedSample, Stream, StreamConfig,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RecorderError {
+    #[error("{0} is not available")]
+    MissingDevice(String),
+    #[error("Recording device failed: {0}")]
+    Device(String),
+    #[error("The recording stopped unexpectedly")]
+    Stopped,
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, RecorderError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordFormat {
+    #[default]
+    Flac,
+    Wav,
+}
+
+impl RecordFormat {
+    pub const ALL: [RecordFormat; 2] = [RecordFormat::Flac, RecordFormat::Wav];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecordFormat::Flac => "flac",
+            RecordFormat::Wav => "wav",
+        }
+    }
+}
+
+impl From<&str> for RecordFormat {
+    fn from(s: &str) -> Self {
+        match s {
+            "wav" => RecordFormat::Wav,
+            _ => RecordFormat::Flac,
+        }
+    }
+}
+
+impl fmt::Display for RecordFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordFormat::Flac => write!(f, "FLAC"),
+            RecordFormat::Wav => write!(f, "WAV"),
+        }
+    }
+}
+
+/// A device to record from. Loopback sources are output devices, recording one captures what
+/// the computer plays.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputSource {
+    pub name: String,
+    pub loopback: bool,
+}
+
+impl fmt::Display for InputSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.loopback {
+            write!(f, "{} (what plays)", self.name)
+        } else {
+            f.write_str(&self.name)
+        }
+    }
+}
+
+/// The sources which can be recorded from. Only WASAPI records output devices directly, on
+/// other systems the sound server lists a monitor of each output as an input.
+pub fn input_sources() -> Vec<InputSource> {
+    let host = rodio::cpal::default_host();
+    let mut sources = Vec::new();
+    if let Ok(devices) = host.input_devices() {
+        sources.extend(
+            devices
+                .filter_map(|device| device.name().ok())
+                .map(|name| InputSource {
+                    name,
+                    loopback: false,
+                }),
+        );
+    }
+    if cfg!(windows)
+        && let Ok(devices) = host.output_devices()
+    {
+        sources.extend(
+            devices
+                .filter_map(|device| device.name().ok())
+                .map(|name| InputSource {
+                    name,
+                    loopback: true,
+                }),
+        );
+    }
+    sources
+}
+
+pub fn recordings_dir() -> Option<PathBuf> {
+    let user_dirs = UserDirs::new()?;
+    Some(user_dirs.audio_dir()?.join("Phoniq Recordings"))
+}
+
+/// A finished recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Take {
+    pub path: PathBuf,
+    // in seconds
+    pub duration: u64,
+}
+
+enum Chunk {
+    Samples(Vec<i16>),
+    Stop,
+}
+
+pub struct Recorder {
+    sender: mpsc::Sender<Chunk>,
+    handle: JoinHandle<Result<Take>>,
+    started: Instant,
+}
+
+impl Recorder {
+    /// Starts recording `source` to a WAV file at `path`.
+    pub fn start(source: InputSource, path: PathBuf) -> Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let (ready, started) = mpsc::channel();
+        let samples = sender.clone();
+        // cpal streams can not move between threads, this one owns it until the recording ends
+        let handle = std::thread::spawn(move || {
+            let (stream, config) = match open(&source, samples) {
+                Ok(opened) => opened,
+                Err(error) => {
+                    let _ = ready.send(Err(error));
+                    return Err(RecorderError::Stopped);
+                }
+            };
+            let writer = WavWriter::create(&path, config.channels, config.sample_rate.0);
+            let mut writer = match writer {
+                Ok(writer) => writer,
+                Err(error) => {
+                    let _ = ready.send(Err(error.into()));
+                    return Err(RecorderError::Stopped);
+                }
+            };
+            let _ = ready.send(Ok(()));
+            while let Ok(Chunk::Samples(samples)) = receiver.recv() {
+                writer.write(&samples)?;
+            }
+            drop(stream);
+            let duration = writer.finish()?;
+            Ok(Take { path, duration })
+        });
+        started.recv().map_err(|_| RecorderError::Stopped)??;
+        Ok(Self {
+            sender,
+            handle,
+            started: Instant::now(),
+        })
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Stops recording and completes the file.
+    pub fn stop(self) -> Result<Take> {
+        let _ = self.sender.send(Chunk::Stop);
+        self.handle.join().map_err(|_| RecorderError::Stopped)?
+    }
+}
+
+fn open(source: &InputSource, sender: mpsc::Sender<Chunk>) -> Result<(Stream, StreamConfig)> {
+    let host = rodio::cpal::default_host();
+    let named = |device: &Device| device.name().ok().as_ref() == Some(&source.name);
+    let device = if source.loopback {
+        host.output_devices().map_err(device_error)?.find(named)
+    } else {
+        host.input_devices().map_err(device_error)?.find(named)
+    }
+    .ok_or_else(|| RecorderError::MissingDevice(source.name.clone()))?;
+    let supported = if source.loopback {
+        device.default_output_config()
+    } else {
+        device.default_input_config()
+    }
+    .map_err(device_error)?;
+    let config = supported.config();
+    let stream = match supported.sample_format() {
+        SampleFormat::F32 => build::<f32>(&device, &config, sender),
+        SampleFormat::I16 => build::<i16>(&device, &config, sender),
+        SampleFormat::U16 => build::<u16>(&device, &config, sender),
+        SampleFormat::I32 => build::<i32>(&device, &config, sender),
+        format => {
+            return Err(RecorderError::Device(format!(
+                "{format:?} samples are not supported"
+            )));
+        }
+    }
+    .map_err(device_error)?;
+    stream.play().map_err(device_error)?;
+    Ok((stream, config))
+}
+
+fn device_error(error: impl fmt::Display) -> RecorderError {
+    RecorderError::Device(error.to_string())
+}
+
+fn build<T>(
+    device: &Device,
+    config: &StreamConfig,
+    sender: mpsc::Sender<Chunk>,
+) -> std::result::Result<Stream, rodio::cpal::BuildStreamError>
+where
+    T: SizedSample,
+    i16: FromSample<T>,
+{
+    device.build_input_stream(
+        config,
+        move |data: &[T], _| {
+            let samples = data.iter().copied().map(Sample::to_sample::<i16>).collect();
+            let _ = sender.send(Chunk::Samples(samples));
+        },
+        |e| error!("recorder: {e}"),
+        None,
+    )
+}
+
+/// 16 bit PCM WAV, the sizes in the header are filled in by `finish`.
+struct WavWriter {
+    file: BufWriter<File>,
+    channels: u16,
+    sample_rate: u32,
+    samples: u64,
+}
+
+impl WavWriter {
+    fn create(path: &Path, channels: u16, sample_rate: u32) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+        let block_align = channels * 2;
+        file.write_all(b"RIFF")?;
+        file.write_all(&0u32.to_le_bytes())?;
+        file.write_all(b"WAVEfmt ")?;
+        file.write_all(&16u32.to_le_bytes())?;
+        // PCM
+        file.write_all(&1u16.to_le_bytes())?;
+        file.write_all(&channels.to_le_bytes())?;
+        file.write_all(&sample_rate.to_le_bytes())?;
+        file.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        file.write_all(&block_align.to_le_bytes())?;
+        file.write_all(&16u16.to_le_bytes())?;
+        file.write_all(b"data")?;
+        file.write_all(&0u32.to_le_bytes())?;
+        Ok(Self {
+            file,
+            channels,
+            sample_rate,
+            samples: 0,
+        })
+    }
+
+    fn write(&mut self, samples: &[i16]) -> io::Result<()> {
+        for sample in samples {
+            self.file.write_all(&sample.to_le_bytes())?;
+        }
+        self.samples += samples.len() as u64;
+        Ok(())
+    }
+
+    /// Writes the sizes into the header, returns the length of the recording in seconds.
+    fn finish(mut self) -> io::Result<u64> {
+        // a RIFF size can not go past 4 GiB, players read on to the end of the file after that
+        let data_size = (self.samples * 2).min(u32::MAX as u64 - 36) as u32;
+        self.file.seek(SeekFrom::Start(4))?;
+        self.file.write_all(&(36 + data_size).to_le_bytes())?;
+        self.file.seek(SeekFrom::Start(40))?;
+        self.file.write_all(&data_size.to_le_bytes())?;
+        self.file.flush()?;
+        Ok(self.samples / self.channels.max(1) as u64 / self.sample_rate.max(1) as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wav_writer() {
+        let path = std::env::temp_dir().join("phoniq_test_recorder.wav");
+        let mut writer = WavWriter::create(&path, 2, 8000).unwrap();
+        writer.write(&[1000; 8000]).unwrap();
+        writer.write(&[-1000; 8000]).unwrap();
+        assert_eq!(writer.finish().unwrap(), 1);
+
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(bytes.len(), 44 + 32_000);
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(
+            u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+            36 + 32_000
+        );
+        assert_eq!(u16::from_le_bytes(bytes[22..24].try_into().unwrap()), 2);
+        assert_eq!(u32::from_le_bytes(bytes[24..28].try_into().unwrap()), 8000);
+        assert_eq!(
+            u32::from_le_bytes(bytes[40..44].try_into().unwrap()),
+            32_000
+        );
+        assert_eq!(i16::from_le_bytes(bytes[44..46].try_into().unwrap()), 1000);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,185 @@
+// Music servers whose libraries are mirrored into the library, e.g. Navidrome or Jellyfin.
+// Their songs are listed in the library and under them in the sidebar, and streamed from them
+// unless their album is in the local files too.
+use std::{collections::BTreeMap, sync::RwLock};
+
+use log::error;
+
+use crate::{
+    jellyfin,
+    storage::{AudioFileDescriptor, AudioFileKind, LocalCopy},
+    subsonic, webdav,
+};
+
+// the accounts of the servers added, for the audio worker which only has the address it streams
+// from
+static ACCOUNTS: RwLock<Vec<Account>> = RwLock::new(Vec::new());
+// the local files songs of servers are played from instead, by the address they stream from
+static LOCAL_COPIES: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+/// What a server holds, as mirrored into the library. Songs are known by the address they
+/// stream from.
+#[derive(Debug, Clone, Default)]
+pub struct Mirror {
+    pub tracks: Vec<AudioFileDescriptor>,
+    // by name, with the addresses of their songs in order
+    pub playlists: Vec<(String, Vec<String>)>,
+    // the songs starred or marked as favorites on the server
+    pub liked: Vec<String>,
+}
+
+/// A server added, of any of the kinds spoken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Server {
+    Subsonic(subsonic::Server),
+    Jellyfin(jellyfin::Server),
+}
+
+impl Server {
+    pub fn url(&self) -> &str {
+        match self {
+            Server::Subsonic(server) => &server.url,
+            Server::Jellyfin(server) => &server.url,
+        }
+    }
+
+    /// How the server is called in the sidebar, its host.
+    pub fn name(&self) -> &str {
+        host(self.url())
+    }
+
+    pub fn username(&self) -> &str {
+        match self {
+            Server::Subsonic(server) => &server.username,
+            Server::Jellyfin(server) => &server.username,
+        }
+    }
+
+    /// Reads what the server holds.
+    pub async fn mirror(self) -> Result<Mirror, String> {
+        match self {
+            Server::Subsonic(server) => subsonic::mirror(server).await.map_err(|e| e.to_string()),
+            Server::Jellyfin(server) => jellyfin::mirror(server).await.map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// The account of a server music is streamed from, of any kind.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Account {
+    WebDav(webdav::Server),
+    Subsonic(subsonic::Server),
+    Jellyfin(jellyfin::Server),
+}
+
+impl Account {
+    fn url(&self) -> &str {
+        match self {
+            Account::WebDav(server) => &server.url,
+            Account::Subsonic(server) => &server.url,
+            Account::Jellyfin(server) => &server.url,
+        }
+    }
+}
+
+impl From<Server> for Account {
+    fn from(server: Server) -> Self {
+        match server {
+            Server::Subsonic(server) => Account::Subsonic(server),
+            Server::Jellyfin(server) => Account::Jellyfin(server),
+        }
+    }
+}
+
+/// Makes the accounts known to the audio worker, which streams from their servers.
+pub fn set_accounts(accounts: Vec<Account>) {
+    match ACCOUNTS.write() {
+        Ok(mut known) => *known = accounts,
+        Err(err) => error!("Error setting the accounts: {err:?}"),
+    }
+}
+
+/// The account of the server a library address streams from.
+pub fn find_account(url: &str) -> Option<Account> {
+    find(&ACCOUNTS.read().ok()?, url).cloned()
+}
+
+// the one with the longest address when servers are nested, e.g. WebDAV shares
+fn find<'a>(accounts: &'a [Account], url: &str) -> Option<&'a Account> {
+    accounts
+        .iter()
+        .filter(|account| url.starts_with(account.url()))
+        .max_by_key(|account| account.url().len())
+}
+
+/// Whether playables of the kind are songs mirrored from a server.
+pub fn is_remote(kind: AudioFileKind) -> bool {
+    matches!(kind, AudioFileKind::Subsonic | AudioFileKind::Jellyfin)
+}
+
+/// Makes the local files of songs mirrored from servers known to the audio worker, which
+/// plays those instead of streaming the songs.
+pub fn set_local_copies(copies: &[LocalCopy]) {
+    let paths = copies
+        .iter()
+        .filter_map(|copy| Some((copy.source_url.clone(), copy.path.clone()?)))
+        .collect();
+    match LOCAL_COPIES.write() {
+        Ok(mut known) => *known = paths,
+        Err(err) => error!("Error setting the local copies: {err:?}"),
+    }
+}
+
+/// The local file a song streamed from `url` is played from instead.
+pub fn local_copy(url: &str) -> Option<String> {
+    LOCAL_COPIES.read().ok()?.get(url).cloned()
+}
+
+/// The host of an address, e.g. `music.example.com` for `https://music.example.com:4533/`.
+pub fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', ':']).next().unwrap_or(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host() {
+        assert_eq!(host("https://music.example.com:4533/"), "music.example.com");
+        assert_eq!(host("http://nas/jellyfin/"), "nas");
+        assert_eq!(host("nas.local"), "nas.local");
+    }
+
+    #[test]
+    fn test_find() {
+        let share = |url: &str| {
+            Account::WebDav(webdav::Server {
+                url: url.to_string(),
+                username: String::new(),
+                password: String::new(),
+            })
+        };
+        let accounts = [
+            share("https://nas.local/"),
+            share("https://nas.local/music/"),
+            Account::Subsonic(subsonic::Server {
+                url: "https://music.example.com/".to_string(),
+                username: "me".to_string(),
+                password: String::new(),
+            }),
+        ];
+        let url = |file: &str| find(&accounts, file).map(Account::url);
+        assert_eq!(
+            url("https://nas.local/music/a.mp3"),
+            Some("https://nas.local/music/")
+        );
+        assert_eq!(url("https://nas.local/b.mp3"), Some("https://nas.local/"));
+        assert_eq!(
+            url("https://music.example.com/rest/stream?id=1"),
+            Some("https://music.example.com/")
+        );
+        assert_eq!(url("https://other.local/c.mp3"), None);
+    }
+}
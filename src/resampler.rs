@@ -0,0 +1,275 @@
+use std::time::Duration;
+
+use rodio::{Source, source::SeekError};
+use rubato::{
+    FastFixedIn, PolynomialDegree, Resampler, SincFixedIn, SincInterpolationParameters,
+    SincInterpolationType, WindowFunction,
+};
+
+// frames handed to the resampler at once
+const CHUNK_FRAMES: usize = 1024;
+
+/// How carefully tracks are converted when the device runs at another sample rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResampleQuality {
+    // polynomial interpolation, cheap enough for slow machines
+    Fast,
+    #[default]
+    Medium,
+    // long sinc filter, inaudible artifacts at a higher CPU cost
+    High,
+}
+
+impl ResampleQuality {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResampleQuality::Fast => "fast",
+            ResampleQuality::Medium => "medium",
+            ResampleQuality::High => "high",
+        }
+    }
+}
+
+impl From<&str> for ResampleQuality {
+    fn from(s: &str) -> Self {
+        match s {
+            "fast" => ResampleQuality::Fast,
+            "high" => ResampleQuality::High,
+            _ => ResampleQuality::Medium,
+        }
+    }
+}
+
+enum Engine {
+    Fast(FastFixedIn<f32>),
+    Sinc(SincFixedIn<f32>),
+}
+
+impl Engine {
+    fn new(quality: ResampleQuality, ratio: f64, channels: usize) -> Option<Self> {
+        let sinc = |sinc_len, f_cutoff, oversampling_factor, interpolation, window| {
+            let parameters = SincInterpolationParameters {
+                sinc_len,
+                f_cutoff,
+                oversampling_factor,
+                interpolation,
+                window,
+            };
+            SincFixedIn::new(ratio, 1.0, parameters, CHUNK_FRAMES, channels)
+                .ok()
+                .map(Engine::Sinc)
+        };
+        match quality {
+            ResampleQuality::Fast => {
+                FastFixedIn::new(ratio, 1.0, PolynomialDegree::Cubic, CHUNK_FRAMES, channels)
+                    .ok()
+                    .map(Engine::Fast)
+            }
+            ResampleQuality::Medium => sinc(
+                64,
+                0.91,
+                128,
+                SincInterpolationType::Linear,
+                WindowFunction::Blackman2,
+            ),
+            ResampleQuality::High => sinc(
+                256,
+                0.95,
+                256,
+                SincInterpolationType::Cubic,
+                WindowFunction::BlackmanHarris2,
+            ),
+        }
+    }
+
+    fn input_frames_next(&self) -> usize {
+        match self {
+            Engine::Fast(resampler) => resampler.input_frames_next(),
+            Engine::Sinc(resampler) => resampler.input_frames_next(),
+        }
+    }
+
+    // a short last chunk is padded by the resampler
+    fn process(&mut self, input: &[Vec<f32>], last: bool) -> Option<Vec<Vec<f32>>> {
+        let output = match (self, last) {
+            (Engine::Fast(resampler), false) => resampler.process(input, None),
+            (Engine::Fast(resampler), true) => resampler.process_partial(Some(input), None),
+            (Engine::Sinc(resampler), false) => resampler.process(input, None),
+            (Engine::Sinc(resampler), true) => resampler.process_partial(Some(input), None),
+        };
+        output.ok()
+    }
+
+    fn reset(&mut self) {
+        match self {
+            Engine::Fast(resampler) => resampler.reset(),
+            Engine::Sinc(resampler) => resampler.reset(),
+        }
+    }
+}
+
+/// Converts a source to the sample rate of the output device, rodio would otherwise fall back
+/// to linear interpolation.
+pub struct Resampled<S> {
+    source: S,
+    engine: Engine,
+    channels: u16,
+    sample_rate: u32,
+    // interleaved samples at the target rate, read from `position`
+    output: Vec<f32>,
+    position: usize,
+    finished: bool,
+}
+
+impl<S> Resampled<S>
+where
+    S: Source<Item = f32>,
+{
+    /// Hands the source back when it already has the target rate or the resampler can not be
+    /// built.
+    pub fn new(source: S, sample_rate: u32, quality: ResampleQuality) -> Result<Self, S> {
+        if source.sample_rate() == sample_rate || source.sample_rate() == 0 {
+            return Err(source);
+        }
+        let channels = source.channels();
+        let ratio = sample_rate as f64 / source.sample_rate() as f64;
+        let Some(engine) = Engine::new(quality, ratio, channels as usize) else {
+            return Err(source);
+        };
+        Ok(Self {
+            source,
+            engine,
+            channels,
+            sample_rate,
+            output: Vec::new(),
+            position: 0,
+            finished: false,
+        })
+    }
+
+    // resamples the next chunk, false once the source is drained
+    fn refill(&mut self) -> bool {
+        if self.finished {
+            return false;
+        }
+        let channels = self.channels as usize;
+        let frames = self.engine.input_frames_next();
+        let mut input = vec![Vec::with_capacity(frames); channels];
+        'frames: for _ in 0..frames {
+            for channel in input.iter_mut() {
+                match self.source.next() {
+                    Some(sample) => channel.push(sample),
+                    None => {
+                        self.finished = true;
+                        break 'frames;
+                    }
+                }
+            }
+        }
+        // a frame cut short at the end of the source is dropped
+        let complete = input.iter().map(Vec::len).min().unwrap_or(0);
+        input
+            .iter_mut()
+            .for_each(|channel| channel.truncate(complete));
+        if complete == 0 {
+            self.finished = true;
+            return false;
+        }
+
+        let Some(output) = self.engine.process(&input, self.finished) else {
+            self.finished = true;
+            return false;
+        };
+        let out_frames = output.first().map(Vec::len).unwrap_or(0);
+        self.output.clear();
+        self.position = 0;
+        for frame in 0..out_frames {
+            self.output
+                .extend(output.iter().map(|channel| channel[frame]));
+        }
+        !self.output.is_empty() || self.refill()
+    }
+}
+
+impl<S> Iterator for Resampled<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position >= self.output.len() && !self.refill() {
+            return None;
+        }
+        let sample = self.output[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl<S> Source for Resampled<S>
+where
+    S: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.source.try_seek(pos)?;
+        self.engine.reset();
+        self.output.clear();
+        self.position = 0;
+        self.finished = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    #[test]
+    fn test_resampled() {
+        // one second of stereo silence
+        let source = SamplesBuffer::new(2, 44_100, vec![0.0f32; 88_200]);
+        let Ok(resampled) = Resampled::new(source, 48_000, ResampleQuality::Fast) else {
+            panic!("no resampler");
+        };
+        assert_eq!(resampled.sample_rate(), 48_000);
+        assert_eq!(resampled.channels(), 2);
+        let samples = resampled.count();
+        assert_eq!(samples % 2, 0);
+        // the resampler delays and pads the output a little
+        assert!((95_000..=100_000).contains(&samples), "{samples}");
+
+        let source = SamplesBuffer::new(2, 48_000, vec![0.0f32; 16]);
+        assert!(Resampled::new(source, 48_000, ResampleQuality::High).is_err());
+    }
+
+    #[test]
+    fn test_resample_quality() {
+        for quality in [
+            ResampleQuality::Fast,
+            ResampleQuality::Medium,
+            ResampleQuality::High,
+        ] {
+            assert_eq!(ResampleQuality::from(quality.as_str()), quality);
+        }
+        assert_eq!(ResampleQuality::from(""), ResampleQuality::Medium);
+    }
+}
@@ -0,0 +1,98 @@
+// Alarm clock: a playlist starts at a time of day and its volume rises slowly from silence.
+use std::time::Duration;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+pub const DEFAULT_RAMP_MINUTES: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alarm {
+    pub enabled: bool,
+    // minutes after local midnight
+    pub time: u32,
+    pub playlist: Option<i64>,
+    // 0 starts at full volume
+    pub ramp_minutes: u32,
+    // rings every day, otherwise the alarm turns itself off after ringing once
+    pub daily: bool,
+}
+
+impl Default for Alarm {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time: 7 * 60,
+            playlist: None,
+            ramp_minutes: DEFAULT_RAMP_MINUTES,
+            daily: true,
+        }
+    }
+}
+
+/// `07:30` for 450 minutes after midnight.
+pub fn time_to_str(time: u32) -> String {
+    format!("{:02}:{:02}", time / 60 % 24, time % 60)
+}
+
+/// Minutes after midnight for `7:30` or `07:30`.
+pub fn parse_time(value: &str) -> Option<u32> {
+    let (hours, minutes) = value.trim().split_once(':')?;
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// The unix timestamp after `now` at which the clock reads `time`, for a time zone `offset`
+/// seconds ahead of UTC.
+pub fn next_ring(time: u32, now: i64, offset: i64) -> i64 {
+    let local = now + offset;
+    let mut ring = local - local.rem_euclid(SECONDS_PER_DAY) + time as i64 * 60;
+    if ring <= local {
+        ring += SECONDS_PER_DAY;
+    }
+    ring - offset
+}
+
+/// How loud the ramp plays `elapsed` into it, rising evenly from silence to `target`.
+pub fn ramp_volume(elapsed: Duration, ramp_minutes: u32, target: f32) -> f32 {
+    let ramp = Duration::from_secs(ramp_minutes as u64 * 60);
+    if elapsed >= ramp {
+        return target;
+    }
+    target * elapsed.as_secs_f32() / ramp.as_secs_f32()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time() {
+        assert_eq!(parse_time("7:30"), Some(450));
+        assert_eq!(parse_time(" 23:59 "), Some(1439));
+        assert_eq!(parse_time("24:00"), None);
+        assert_eq!(parse_time("7"), None);
+        assert_eq!(time_to_str(450), "07:30");
+    }
+
+    #[test]
+    fn test_next_ring() {
+        // 2023-11-14 22:13:20 UTC
+        let now = 1_700_000_000;
+        let midnight = 1_699_920_000;
+        assert_eq!(next_ring(23 * 60, now, 0), midnight + 23 * 3600);
+        assert_eq!(next_ring(7 * 60, now, 0), midnight + 86_400 + 7 * 3600);
+        // 23:13 at UTC+1, 23:00 has passed there
+        assert_eq!(
+            next_ring(23 * 60, now, 3600),
+            midnight + 86_400 + 23 * 3600 - 3600
+        );
+    }
+
+    #[test]
+    fn test_ramp_volume() {
+        assert_eq!(ramp_volume(Duration::ZERO, 5, 80.0), 0.0);
+        assert_eq!(ramp_volume(Duration::from_secs(150), 5, 80.0), 40.0);
+        assert_eq!(ramp_volume(Duration::from_secs(600), 5, 80.0), 80.0);
+        assert_eq!(ramp_volume(Duration::ZERO, 0, 80.0), 80.0);
+    }
+}
@@ -0,0 +1,69 @@
+// Passwords and tokens of the accounts connected, kept in the keychain of the system (Keychain
+// on macOS, the Credential Manager on Windows, the Secret Service elsewhere) rather than in the
+// database. Tests keep them in memory, per thread.
+use thiserror::Error;
+
+// the keychain entries are filed under the app, by key
+#[cfg(not(test))]
+const SERVICE: &str = "phoniq";
+
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("Keychain failed: {0}")]
+    Keychain(String),
+}
+
+pub type Result<T> = std::result::Result<T, SecretError>;
+
+#[cfg(not(test))]
+fn entry(key: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, key).map_err(|e| SecretError::Keychain(e.to_string()))
+}
+
+/// The secret kept under `key`, None when there is none.
+#[cfg(not(test))]
+pub fn read(key: &str) -> Result<Option<String>> {
+    match entry(key)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(SecretError::Keychain(e.to_string())),
+    }
+}
+
+/// Keeps `secret` under `key`, an empty one takes away what was kept.
+#[cfg(not(test))]
+pub fn write(key: &str, secret: &str) -> Result<()> {
+    let entry = entry(key)?;
+    let result = if secret.is_empty() {
+        match entry.delete_credential() {
+            Err(keyring::Error::NoEntry) => Ok(()),
+            result => result,
+        }
+    } else {
+        entry.set_password(secret)
+    };
+    result.map_err(|e| SecretError::Keychain(e.to_string()))
+}
+
+#[cfg(test)]
+thread_local! {
+    static SECRETS: std::cell::RefCell<std::collections::HashMap<String, String>> =
+        Default::default();
+}
+
+#[cfg(test)]
+pub fn read(key: &str) -> Result<Option<String>> {
+    Ok(SECRETS.with_borrow(|secrets| secrets.get(key).cloned()))
+}
+
+#[cfg(test)]
+pub fn write(key: &str, secret: &str) -> Result<()> {
+    SECRETS.with_borrow_mut(|secrets| {
+        if secret.is_empty() {
+            secrets.remove(key);
+        } else {
+            secrets.insert(key.to_string(), secret.to_string());
+        }
+    });
+    Ok(())
+}
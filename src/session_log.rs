@@ -0,0 +1,291 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    app_state::AudioPlayable,
+    clipboard::artist_title,
+    util::{duration_to_str, local_offset, timestamp_to_str, unix_now},
+};
+
+// columns added later go last, readers of older logs find theirs where they were
+const HEADER: &str = "time,artist,title,album,duration,path,elapsed,transition,previous_played";
+
+/// How a track came to play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    // nothing was playing before it
+    Start,
+    // the previous track played to its end
+    Next,
+    // started by hand, the previous track was left after this many seconds
+    Skip(u64),
+}
+
+impl Transition {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Transition::Start => "start",
+            Transition::Next => "next",
+            Transition::Skip(_) => "skip",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    timestamp: i64,
+    name: String,
+    transition: Transition,
+}
+
+/// Records every played track of a listening session into its own CSV file, in the order
+/// they started. When the session ends it is also saved as a DJ set tracklist.
+#[derive(Debug)]
+pub struct SessionLog {
+    path: PathBuf,
+    entries: Vec<Entry>,
+}
+
+impl SessionLog {
+    pub fn start(dir: &Path) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let stem = format!("session-{}", local_time(unix_now()))
+            .replace(' ', "_")
+            .replace(':', "-");
+        // a session started within the same second gets a file of its own
+        for n in 1.. {
+            let path = match n {
+                1 => dir.join(format!("{stem}.csv")),
+                n => dir.join(format!("{stem}-{n}.csv")),
+            };
+            let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            };
+            writeln!(file, "{HEADER}")?;
+            return Ok(Self {
+                path,
+                entries: Vec::new(),
+            });
+        }
+        unreachable!()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(
+        &mut self,
+        playable: &dyn AudioPlayable,
+        transition: Transition,
+    ) -> std::io::Result<()> {
+        self.record_at(unix_now(), playable, transition)
+    }
+
+    fn record_at(
+        &mut self,
+        timestamp: i64,
+        playable: &dyn AudioPlayable,
+        transition: Transition,
+    ) -> std::io::Result<()> {
+        let elapsed = self.elapsed(timestamp);
+        self.entries.push(Entry {
+            timestamp,
+            name: artist_title(playable),
+            transition,
+        });
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        writeln!(file, "{}", entry(timestamp, elapsed, playable, transition))
+    }
+
+    /// The tracks played as a DJ set tracklist, each with the time into the set it started
+    /// at. A track skipped by hand is marked with how long it played.
+    pub fn tracklist(&self) -> String {
+        let last = self
+            .entries
+            .last()
+            .map_or(0, |last| self.elapsed(last.timestamp));
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let mut line = format!(
+                    "[{}] {}",
+                    set_time(self.elapsed(entry.timestamp), last),
+                    entry.name
+                );
+                if let Some(Transition::Skip(played)) =
+                    self.entries.get(i + 1).map(|next| next.transition)
+                {
+                    line.push_str(&format!(" (skipped after {})", duration_to_str(played)));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // seconds since the first track started
+    fn elapsed(&self, timestamp: i64) -> u64 {
+        self.entries
+            .first()
+            .map_or(0, |first| (timestamp - first.timestamp).max(0) as u64)
+    }
+
+    /// Ends the session, the tracklist is saved next to the log. Returns its path, None when
+    /// nothing was played.
+    pub fn finish(self) -> std::io::Result<Option<PathBuf>> {
+        if self.entries.is_empty() {
+            return Ok(None);
+        }
+        let path = self.path.with_extension("txt");
+        fs::write(&path, self.tracklist() + "\n")?;
+        Ok(Some(path))
+    }
+}
+
+// `YYYY-MM-DD HH:MM:SS` in the local time zone
+fn local_time(timestamp: i64) -> String {
+    timestamp_to_str(timestamp + local_offset(timestamp))
+}
+
+// minutes and seconds into the set, with hours once the set is that long
+fn set_time(elapsed: u64, longest: u64) -> String {
+    if longest >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            elapsed / 3600,
+            elapsed % 3600 / 60,
+            elapsed % 60
+        )
+    } else {
+        format!("{:02}:{:02}", elapsed / 60, elapsed % 60)
+    }
+}
+
+fn entry(
+    timestamp: i64,
+    elapsed: u64,
+    playable: &dyn AudioPlayable,
+    transition: Transition,
+) -> String {
+    let previous_played = match transition {
+        Transition::Skip(played) => played.to_string(),
+        _ => String::new(),
+    };
+    [
+        local_time(timestamp),
+        escape_csv(playable.get_artist()),
+        escape_csv(playable.get_title()),
+        escape_csv(playable.get_album()),
+        playable.get_duration().to_string(),
+        escape_csv(playable.get_path()),
+        elapsed.to_string(),
+        transition.as_str().to_string(),
+        previous_played,
+    ]
+    .join(",")
+}
+
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Playable;
+
+    fn playable(title: &str) -> Playable {
+        Playable {
+            id: 1,
+            title: title.to_string(),
+            artist_name: Some("Artist".to_string()),
+            duration: 180,
+            source_url: format!("/music/{title}.mp3"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_entry() {
+        let time = local_time(0);
+        let line = entry(0, 0, &playable("Track"), Transition::Start);
+        assert_eq!(
+            line,
+            format!("{time},Artist,Track,,180,/music/Track.mp3,0,start,")
+        );
+
+        let line = entry(0, 95, &playable("One, \"Two\""), Transition::Skip(42));
+        assert_eq!(
+            line,
+            format!(
+                "{time},Artist,\"One, \"\"Two\"\"\",,180,\"/music/One, \"\"Two\"\".mp3\",95,skip,42"
+            )
+        );
+    }
+
+    #[test]
+    fn test_record() {
+        let dir = std::env::temp_dir().join("phoniq-session-log-test");
+        let mut log = SessionLog::start(&dir).unwrap();
+        log.record(&playable("Track"), Transition::Start).unwrap();
+        let contents = fs::read_to_string(log.path()).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], HEADER);
+        assert!(lines[1].ends_with("Artist,Track,,180,/music/Track.mp3,0,start,"));
+
+        let path = log.finish().unwrap().unwrap();
+        assert!(
+            fs::read_to_string(path)
+                .unwrap()
+                .starts_with("[00:00] Artist - Track")
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_start_twice() {
+        let dir = std::env::temp_dir().join("phoniq-session-twice-test");
+        let first = SessionLog::start(&dir).unwrap();
+        let second = SessionLog::start(&dir).unwrap();
+        assert_ne!(first.path(), second.path());
+        for log in [&first, &second] {
+            assert_eq!(
+                fs::read_to_string(log.path()).unwrap(),
+                format!("{HEADER}\n")
+            );
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_tracklist() {
+        let dir = std::env::temp_dir().join("phoniq-session-tracklist-test");
+        let mut log = SessionLog::start(&dir).unwrap();
+        log.record_at(1000, &playable("One"), Transition::Start)
+            .unwrap();
+        log.record_at(1180, &playable("Two"), Transition::Next)
+            .unwrap();
+        log.record_at(1250, &playable("Three"), Transition::Skip(70))
+            .unwrap();
+        assert_eq!(
+            log.tracklist(),
+            "[00:00] Artist - One\n[03:00] Artist - Two (skipped after 1:10)\n[04:10] Artist - Three"
+        );
+        log.record_at(4700, &playable("Four"), Transition::Next)
+            .unwrap();
+        assert!(log.tracklist().ends_with("[1:01:40] Artist - Four"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+// A playlist packed into one `.phoniq-playlist` file to hand to a friend. The files behind it
+// stay at home, each track carries what it takes to find the same music in another library:
+// its tags, a fingerprint of the file and the places it can be found on the web.
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    app_state::AudioPlayable,
+    storage::{AudioFileKind, Playable},
+};
+
+pub const EXTENSION: &str = "phoniq-playlist";
+pub const VERSION: u32 = 1;
+// encodes of the same track differ by a few frames
+const DURATION_TOLERANCE: i64 = 3;
+
+#[derive(Debug, Error)]
+pub enum SharedPlaylistError {
+    #[error("Invalid playlist file: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Unsupported playlist file version {0}")]
+    Version(u32),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, SharedPlaylistError>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedPlaylist {
+    pub version: u32,
+    pub name: String,
+    pub exported_at: i64,
+    pub tracks: Vec<SharedTrack>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SharedTrack {
+    pub title: String,
+    #[serde(default)]
+    pub artist: String,
+    #[serde(default)]
+    pub album: String,
+    // in seconds
+    #[serde(default)]
+    pub duration: i64,
+    // None for files which could not be read on export
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    // where the track can be found, the address of a stream or video or e.g. a Discogs release
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<String>,
+}
+
+impl SharedTrack {
+    pub fn new(playable: &Playable, fingerprint: Option<String>) -> Self {
+        // local paths mean nothing on another machine
+        let references = match playable.type_id {
+            AudioFileKind::Youtube | AudioFileKind::Stream => vec![playable.source_url.clone()],
+            _ => Vec::new(),
+        };
+        Self {
+            title: playable.get_title().to_string(),
+            artist: playable.get_artist().to_string(),
+            album: playable.get_album().to_string(),
+            duration: playable.duration,
+            fingerprint,
+            references,
+        }
+    }
+
+    /// Whether `playable` looks like this track: the same address on the web, or the same
+    /// artist and title of about the same length.
+    pub fn matches(&self, playable: &Playable) -> bool {
+        if self.references.contains(&playable.source_url) {
+            return true;
+        }
+        let same = |a: &str, b: &str| a.trim().to_lowercase() == b.trim().to_lowercase();
+        let close = self.duration <= 0
+            || playable.duration <= 0
+            || (self.duration - playable.duration).abs() <= DURATION_TOLERANCE;
+        !self.title.trim().is_empty()
+            && same(&self.title, playable.get_title())
+            && same(&self.artist, playable.get_artist())
+            && close
+    }
+}
+
+impl SharedPlaylist {
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let playlist: SharedPlaylist = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        if playlist.version > VERSION {
+            return Err(SharedPlaylistError::Version(playlist.version));
+        }
+        Ok(playlist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playable(title: &str, artist: &str, duration: i64, url: &str) -> Playable {
+        Playable {
+            id: 1,
+            title: title.to_string(),
+            artist_name: Some(artist.to_string()),
+            duration,
+            source_url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_matches() {
+        let track = SharedTrack {
+            title: "Windowlicker".to_string(),
+            artist: "Aphex Twin".to_string(),
+            album: String::new(),
+            duration: 367,
+            fingerprint: None,
+            references: vec!["https://example.com/stream".to_string()],
+        };
+        assert!(track.matches(&playable("windowlicker ", "APHEX TWIN", 365, "/a.flac")));
+        assert!(track.matches(&playable("Windowlicker", "Aphex Twin", 0, "/a.flac")));
+        // a different edit
+        assert!(!track.matches(&playable("Windowlicker", "Aphex Twin", 420, "/a.flac")));
+        assert!(!track.matches(&playable("Flim", "Aphex Twin", 367, "/b.flac")));
+        assert!(track.matches(&playable("Live", "", 0, "https://example.com/stream")));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join("phoniq_test_shared.phoniq-playlist");
+        let playlist = SharedPlaylist {
+            version: VERSION,
+            name: "Mixtape".to_string(),
+            exported_at: 100,
+            tracks: vec![SharedTrack::new(
+                &playable("Flim", "Aphex Twin", 177, "/music/flim.mp3"),
+                Some("1-abc".to_string()),
+            )],
+        };
+        playlist.save(&path).unwrap();
+        assert_eq!(SharedPlaylist::load(&path).unwrap(), playlist);
+        assert!(playlist.tracks[0].references.is_empty());
+
+        std::fs::write(
+            &path,
+            r#"{"version": 2, "name": "", "exported_at": 0, "tracks": []}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            SharedPlaylist::load(&path),
+            Err(SharedPlaylistError::Version(2))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,14 +1,24 @@
 pub mod playlists;
+pub mod podcasts;
 pub mod tags;
 use std::fmt::Debug;
 
-use iced::{Background, Element, Length, Padding, Task, alignment::Vertical, widget};
+use iced::{
+    Background, Border, Color, Element, Length, Padding, Subscription, Task, alignment::Vertical,
+    mouse, widget,
+};
 
 use crate::{
-    app_state::{Section, state_impl::State},
+    app_state::{PlayableId, Section, state_impl::State},
     fonts::{ICON, SANS_BOLD},
-    icons::{ICON_CLOCK, ICON_HEART, ICON_HOUSE, ICON_LIBRARY, ICON_SEARCH},
-    sidebar::{playlists::Playlists, tags::Tags},
+    icons::{
+        ICON_CHART_PIE, ICON_CLOCK, ICON_FILE_OUTPUT, ICON_GIT_COMPARE, ICON_HEART,
+        ICON_HEART_PULSE, ICON_HISTORY, ICON_HOUSE, ICON_LIBRARY, ICON_LIST_ORDERED, ICON_SEARCH,
+        ICON_SERVER, ICON_SPARKLES, ICON_STAR, ICON_TRASH_2, ICON_TROPHY,
+    },
+    remote,
+    sidebar::{playlists::Playlists, podcasts::Podcasts, tags::Tags},
+    storage::AutoPlaylist,
     widgets::container::{Container, MenuState, Style},
 };
 
@@ -19,11 +29,40 @@ pub const SECTION_SPACING: f32 = 16.0;
 pub const ITEM_PADDING_TOP_BOTTOM: f32 = 8.0;
 pub const ITEM_PADDING_LEFT_RIGHT: f32 = 16.0;
 
+const AUTO_PLAYLISTS: [(&str, char, AutoPlaylist); 4] = [
+    ("Top 25 Most Played", ICON_TROPHY, AutoPlaylist::MostPlayed),
+    (
+        "Recently Liked",
+        ICON_HEART_PULSE,
+        AutoPlaylist::RecentlyLiked,
+    ),
+    ("Never Played", ICON_SPARKLES, AutoPlaylist::NeverPlayed),
+    ("Top Rated", ICON_STAR, AutoPlaylist::TopRated),
+];
+
 #[derive(Debug, Clone)]
 pub enum Message {
     Selected(Section),
     Playlists(playlists::Message),
     Tags(tags::Message),
+    Podcasts(podcasts::Message),
+    // tracks dragged from the list came over an entry which takes them, or left it
+    DragEntered(DropTarget),
+    DragLeft(DropTarget),
+}
+
+/// An entry tracks can be dropped onto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropTarget {
+    Playlist(i64),
+    Tag(i64),
+}
+
+/// Tracks being dragged from the list and the entry they are over.
+#[derive(Debug)]
+pub struct Drag {
+    pub ids: Vec<PlayableId>,
+    pub target: Option<DropTarget>,
 }
 
 #[derive(Clone, Eq, PartialEq)]
@@ -120,17 +159,96 @@ where
     item(content, selected, menu_state)
 }
 
-pub fn static_content<'a>(selected_section: &Section) -> Vec<Element<'a, Message>> {
+/// An item with an icon and, while `count` is above zero, the count at its end.
+pub fn item_with_badge<'a, T, M>(
+    text: &'a str,
+    icon: char,
+    count: usize,
+    selected: bool,
+    menu_state: Option<MenuState<'a, T>>,
+) -> Container<'a, T, M, iced::Theme>
+where
+    T: 'a + Clone + std::fmt::Display + Eq,
+    M: 'a + Clone,
+{
+    let mut content = widget::Row::from_vec(vec![
+        widget::Text::new(icon)
+            .font(ICON)
+            .size(ITEM_FONT_SIZE - 1.0)
+            .into(),
+        widget::Text::new(text)
+            .size(ITEM_FONT_SIZE)
+            .width(Length::Fill)
+            .into(),
+    ])
+    .spacing(ITEM_SPACING)
+    .width(Length::Fill)
+    .align_y(Vertical::Center);
+    if count > 0 {
+        content = content.push(
+            widget::Text::new(count.to_string())
+                .font(SANS_BOLD)
+                .size(ITEM_FONT_SIZE - 2.0)
+                .style(widget::text::primary),
+        );
+    }
+    item(content, selected, menu_state)
+}
+
+/// Wraps an entry taking dropped tracks, it is outlined while they are dragged over it. The
+/// content keeps its state whether tracks are dragged or not.
+pub fn drop_target<'a, M>(
+    content: impl Into<Element<'a, M>>,
+    target: DropTarget,
+    drag: Option<&Drag>,
+    on_enter: fn(DropTarget) -> M,
+    on_leave: fn(DropTarget) -> M,
+) -> Element<'a, M>
+where
+    M: 'a + Clone,
+{
+    let hovered = drag.is_some_and(|drag| drag.target == Some(target));
+    let area = widget::mouse_area(
+        widget::container(content).style(move |theme: &iced::Theme| widget::container::Style {
+            border: Border {
+                color: if hovered {
+                    theme.palette().primary
+                } else {
+                    Color::TRANSPARENT
+                },
+                width: 2.0,
+                radius: 4.0.into(),
+            },
+            ..Default::default()
+        }),
+    );
+    match drag {
+        Some(_) => area
+            .interaction(mouse::Interaction::Grabbing)
+            .on_enter(on_enter(target))
+            .on_exit(on_leave(target))
+            .into(),
+        None => area.into(),
+    }
+}
+
+pub fn static_content<'a>(
+    selected_section: &Section,
+    new_releases: usize,
+    queued: usize,
+) -> Vec<Element<'a, Message>> {
     vec![
         // Music section
         widget::Column::from_vec(vec![
             header("Music").width(Length::Fill).into(),
-            item_with_icon(
+            item_with_badge(
                 "Listen Now",
                 ICON_HOUSE,
+                new_releases,
                 selected_section.eq(&Section::ListenNow),
                 None::<MenuState<'a, NoMenu>>,
             )
+            .on_select(|_| Message::Selected(Section::ListenNow))
             .into(),
             item_with_icon(
                 "Browse",
@@ -170,9 +288,75 @@ pub fn static_content<'a>(selected_section: &Section) -> Vec<Element<'a, Message
             )
             .on_select(|_| Message::Selected(Section::RecentlyPlayed))
             .into(),
+            item_with_badge(
+                "Queue",
+                ICON_LIST_ORDERED,
+                queued,
+                selected_section.eq(&Section::Queue),
+                None::<MenuState<'a, NoMenu>>,
+            )
+            .on_select(|_| Message::Selected(Section::Queue))
+            .into(),
+            item_with_icon(
+                "Import History",
+                ICON_HISTORY,
+                selected_section.eq(&Section::ImportHistory),
+                None::<MenuState<'a, NoMenu>>,
+            )
+            .on_select(|_| Message::Selected(Section::ImportHistory))
+            .into(),
+            item_with_icon(
+                "Recently Deleted",
+                ICON_TRASH_2,
+                selected_section.eq(&Section::RecentlyDeleted),
+                None::<MenuState<'a, NoMenu>>,
+            )
+            .on_select(|_| Message::Selected(Section::RecentlyDeleted))
+            .into(),
+            item_with_icon(
+                "Snapshots",
+                ICON_GIT_COMPARE,
+                selected_section.eq(&Section::Snapshots),
+                None::<MenuState<'a, NoMenu>>,
+            )
+            .on_select(|_| Message::Selected(Section::Snapshots))
+            .into(),
+            item_with_icon(
+                "Conversions",
+                ICON_FILE_OUTPUT,
+                selected_section.eq(&Section::Conversions),
+                None::<MenuState<'a, NoMenu>>,
+            )
+            .on_select(|_| Message::Selected(Section::Conversions))
+            .into(),
+            item_with_icon(
+                "Insights",
+                ICON_CHART_PIE,
+                selected_section.eq(&Section::Insights),
+                None::<MenuState<'a, NoMenu>>,
+            )
+            .on_select(|_| Message::Selected(Section::Insights))
+            .into(),
         ])
         .width(Length::Fill)
         .into(),
+        // Auto section, built-in playlists which can not be edited
+        widget::Column::from_vec(
+            std::iter::once(header("Auto").width(Length::Fill).into())
+                .chain(AUTO_PLAYLISTS.into_iter().map(|(label, icon, playlist)| {
+                    item_with_icon(
+                        label,
+                        icon,
+                        selected_section.eq(&Section::Auto(playlist)),
+                        None::<MenuState<'a, NoMenu>>,
+                    )
+                    .on_select(move |_| Message::Selected(Section::Auto(playlist)))
+                    .into()
+                }))
+                .collect(),
+        )
+        .width(Length::Fill)
+        .into(),
     ]
 }
 
@@ -180,38 +364,111 @@ pub fn static_content<'a>(selected_section: &Section) -> Vec<Element<'a, Message
 pub struct Sidebar {
     playlists: Playlists,
     tags: Tags,
+    podcasts: Podcasts,
 }
 impl Sidebar {
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::Playlists(msg) => {
-                if let playlists::Message::Selected(v) = msg {
-                    return Task::done(Message::Selected(v));
+                match msg {
+                    playlists::Message::Selected(v) => return Task::done(Message::Selected(v)),
+                    playlists::Message::DragEntered(target) => {
+                        return Task::done(Message::DragEntered(target));
+                    }
+                    playlists::Message::DragLeft(target) => {
+                        return Task::done(Message::DragLeft(target));
+                    }
+                    _ => {}
                 }
                 return self.playlists.update(msg).map(Message::Playlists);
             }
             Message::Tags(msg) => {
-                if let tags::Message::Selected(v) = msg {
-                    return Task::done(Message::Selected(v));
+                match msg {
+                    tags::Message::Selected(v) => return Task::done(Message::Selected(v)),
+                    tags::Message::DragEntered(target) => {
+                        return Task::done(Message::DragEntered(target));
+                    }
+                    tags::Message::DragLeft(target) => {
+                        return Task::done(Message::DragLeft(target));
+                    }
+                    _ => {}
                 }
                 return self.tags.update(msg).map(Message::Tags);
             }
+            Message::Podcasts(msg) => {
+                if let podcasts::Message::Selected(v) = msg {
+                    return Task::done(Message::Selected(v));
+                }
+                return self.podcasts.update(msg).map(Message::Podcasts);
+            }
             _ => {}
         };
         Task::none()
     }
+    pub fn subscription(&self) -> Subscription<Message> {
+        self.tags.subscription().map(Message::Tags)
+    }
+    /// `drag` is set while tracks are dragged from the list, playlists and tags take them.
     pub fn view<'a>(
         &'a self,
         selected_section: &'a Section,
         state: &'a State,
+        drag: Option<&'a Drag>,
     ) -> Element<'a, Message> {
-        let mut elements = static_content(selected_section);
+        let mut elements = static_content(
+            selected_section,
+            state.new_releases().len(),
+            state.up_next().len(),
+        );
+        // the Subsonic and Jellyfin servers mirrored, each a source of its own beside the
+        // library
+        let servers: Vec<(&'a str, &'a str)> = state
+            .subsonic_servers()
+            .iter()
+            .map(|server| (server.url.as_str(), server.name()))
+            .chain(
+                state
+                    .jellyfin_servers()
+                    .iter()
+                    .map(|server| (server.url.as_str(), remote::host(&server.url))),
+            )
+            .collect();
+        if !servers.is_empty() {
+            elements.push(
+                widget::Column::from_vec(
+                    std::iter::once(header("Servers").width(Length::Fill).into())
+                        .chain(servers.into_iter().map(|(url, name)| {
+                            let section = Section::Server(url.to_string());
+                            item_with_icon(
+                                name,
+                                ICON_SERVER,
+                                selected_section.eq(&section),
+                                None::<MenuState<'a, NoMenu>>,
+                            )
+                            .on_select(move |_| Message::Selected(section.clone()))
+                            .into()
+                        }))
+                        .collect(),
+                )
+                .width(Length::Fill)
+                .into(),
+            );
+        }
         elements.push(
             self.playlists
-                .view(state, selected_section)
+                .view(state, selected_section, drag)
                 .map(Message::Playlists),
         );
-        elements.push(self.tags.view(state, selected_section).map(Message::Tags));
+        elements.push(
+            self.tags
+                .view(state, selected_section, drag)
+                .map(Message::Tags),
+        );
+        elements.push(
+            self.podcasts
+                .view(state, selected_section)
+                .map(Message::Podcasts),
+        );
         widget::Column::from_vec(elements)
             .spacing(SECTION_SPACING)
             .width(Length::Fill)
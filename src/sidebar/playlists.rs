@@ -1,36 +1,84 @@
 use iced::{
     Element, Length, Padding, Task,
     alignment::Vertical,
-    widget::{self, mouse_area, text_input::focus},
+    widget::{self, Button, mouse_area, pick_list, row, text, text_input::focus},
 };
 
 use crate::{
     app_state::{Section, state_impl::State},
     fonts::ICON,
-    icons::{ICON_LIST_MUSIC, ICON_PLUS},
-    sidebar::{ITEM_PADDING_LEFT_RIGHT, ITEM_PADDING_TOP_BOTTOM, header, item_with_icon},
-    storage::{Playlist, PlaylistKind},
+    icons::{ICON_LIST_MUSIC, ICON_PLUS, ICON_WAND, ICON_X},
+    sidebar::{
+        Drag, DropTarget, ITEM_PADDING_LEFT_RIGHT, ITEM_PADDING_TOP_BOTTOM, ITEM_SPACING,
+        drop_target, header, item_with_icon,
+    },
+    storage::{Playlist, PlaylistKind, PlaylistRule, RuleField},
     widgets::container::MenuState,
 };
 
-static PLAYLIST_CONTEXT_MENU: &[MenuOptions] =
-    &[MenuOptions::Rename, MenuOptions::Delete, MenuOptions::Clear];
+static PLAYLIST_CONTEXT_MENU: &[MenuOptions] = &[
+    MenuOptions::Play,
+    MenuOptions::Shuffle,
+    MenuOptions::Rename,
+    MenuOptions::Delete,
+    MenuOptions::Clear,
+    MenuOptions::CopyTracklist,
+    MenuOptions::Share,
+    MenuOptions::ExportM3u(true),
+    MenuOptions::ExportM3u(false),
+    MenuOptions::ExportCdj,
+    MenuOptions::MakeOffline,
+];
+
+// dynamic playlists fill themselves, they can not be cleared
+static DYNAMIC_PLAYLIST_CONTEXT_MENU: &[MenuOptions] = &[
+    MenuOptions::Play,
+    MenuOptions::Shuffle,
+    MenuOptions::EditRules,
+    MenuOptions::Rename,
+    MenuOptions::Delete,
+    MenuOptions::CopyTracklist,
+    MenuOptions::Share,
+    MenuOptions::ExportCdj,
+    MenuOptions::MakeOffline,
+];
 
 static NEW_PLAYLIST_INPUT_ID: &str = "adding_playlist";
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum MenuOptions {
+    Play,
+    Shuffle,
     Rename,
     Delete,
     Clear,
+    CopyTracklist,
+    // saves the playlist to a file for friends to import
+    Share,
+    EditRules,
+    // saves the playlist as an M3U8 file, with paths relative to it when true
+    ExportM3u(bool),
+    // checks the tracks against what CDJs play and copies them onto a USB drive
+    ExportCdj,
+    // downloads the remote tracks of the playlist
+    MakeOffline,
 }
 
 impl std::fmt::Display for MenuOptions {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            MenuOptions::Play => f.write_str("Play Playlist"),
+            MenuOptions::Shuffle => f.write_str("Shuffle Playlist"),
             MenuOptions::Rename => f.write_str("Rename Playlist"),
             MenuOptions::Delete => f.write_str("Delete Playlist"),
             MenuOptions::Clear => f.write_str("Clear Playlist"),
+            MenuOptions::CopyTracklist => f.write_str("Copy as Tracklist"),
+            MenuOptions::Share => f.write_str("Share Playlist…"),
+            MenuOptions::EditRules => f.write_str("Edit Rules…"),
+            MenuOptions::ExportM3u(true) => f.write_str("Export as M3U…"),
+            MenuOptions::ExportM3u(false) => f.write_str("Export as M3U (Absolute Paths)…"),
+            MenuOptions::ExportCdj => f.write_str("Export for CDJ…"),
+            MenuOptions::MakeOffline => f.write_str("Make Available Offline"),
         }
     }
 }
@@ -38,28 +86,97 @@ impl std::fmt::Display for MenuOptions {
 #[derive(Debug, Clone)]
 pub enum Message {
     AddingPlaylist,
+    AddingDynamicPlaylist,
     TypingPlaylistName(String),
     CreatedPlaylist(Option<i64>, String, Option<PlaylistKind>),
     ContextAction(MenuOptions, i64, String),
     Selected(Section),
     ContextMenuHover(Option<usize>),
     ContextHide,
+    // opens the rule editor of a dynamic playlist with its saved rules
+    EditRules(i64, Vec<PlaylistRule>),
+    RuleFieldChanged(usize, RuleField),
+    RuleTextChanged(usize, String),
+    RuleMinChanged(usize, String),
+    RuleMaxChanged(usize, String),
+    AddRule,
+    RemoveRule(usize),
+    SaveRules(i64, Vec<PlaylistRule>),
+    CancelRules,
+    DragEntered(DropTarget),
+    DragLeft(DropTarget),
+}
+
+/// A rule as typed into the editor. Durations are typed in minutes.
+#[derive(Debug, Clone, Default)]
+struct RuleDraft {
+    field: RuleField,
+    text: String,
+    min: String,
+    max: String,
+}
+
+impl RuleDraft {
+    fn scale(field: RuleField) -> i64 {
+        if field == RuleField::Duration { 60 } else { 1 }
+    }
+
+    fn new(rule: &PlaylistRule) -> Self {
+        let bound = |value: Option<i64>| {
+            value
+                .map(|value| (value / Self::scale(rule.field)).to_string())
+                .unwrap_or_default()
+        };
+        Self {
+            field: rule.field,
+            text: rule.text.clone(),
+            min: bound(rule.min),
+            max: bound(rule.max),
+        }
+    }
+
+    fn rule(&self) -> PlaylistRule {
+        let bound = |value: &str| Some(value.trim().parse::<i64>().ok()? * Self::scale(self.field));
+        let (text, min, max) = match self.field {
+            RuleField::Genre | RuleField::Artist | RuleField::Tag | RuleField::Custom => {
+                (self.text.trim().to_string(), None, None)
+            }
+            RuleField::Year | RuleField::Duration | RuleField::Bitrate | RuleField::SampleRate => {
+                (String::new(), bound(&self.min), bound(&self.max))
+            }
+            RuleField::Added | RuleField::Rating => (String::new(), bound(&self.min), None),
+            RuleField::Liked | RuleField::Lossless => (String::new(), None, None),
+        };
+        PlaylistRule {
+            field: self.field,
+            text,
+            min,
+            max,
+        }
+    }
 }
 
 pub struct Playlists {
     new_playlist: bool,
+    // the playlist being named is a dynamic one
+    new_dynamic: bool,
     editing_playlist: Option<i64>,
     name: String,
     menu_state: MenuState<'static, MenuOptions>,
+    dynamic_menu_state: MenuState<'static, MenuOptions>,
+    rules: Option<(i64, Vec<RuleDraft>)>,
 }
 
 impl Default for Playlists {
     fn default() -> Self {
         Self {
             new_playlist: false,
+            new_dynamic: false,
             editing_playlist: None,
             name: String::new(),
             menu_state: MenuState::new(PLAYLIST_CONTEXT_MENU),
+            dynamic_menu_state: MenuState::new(DYNAMIC_PLAYLIST_CONTEXT_MENU),
+            rules: None,
         }
     }
 }
@@ -69,28 +186,47 @@ impl Playlists {
         &'a self,
         playlist: &'a Playlist,
         selected: bool,
+        drag: Option<&'a Drag>,
     ) -> Element<'a, Message> {
-        item_with_icon(
-            &playlist.name,
-            ICON_LIST_MUSIC,
-            selected,
-            Some(self.menu_state.clone()),
-        )
-        .on_select(|_| Message::Selected(Section::Playlist(playlist.id)))
-        .on_menu_select(|_, option| {
-            Message::ContextAction(option, playlist.id, playlist.name.clone())
-        })
-        .on_menu_hover(|option| {
-            Message::ContextMenuHover(PLAYLIST_CONTEXT_MENU.iter().position(|p| p.eq(&option)))
-        })
-        .on_menu_close(Message::ContextHide)
-        .into()
+        let (icon, menu, menu_state) = if playlist.kind == PlaylistKind::Dynamic {
+            (
+                ICON_WAND,
+                DYNAMIC_PLAYLIST_CONTEXT_MENU,
+                &self.dynamic_menu_state,
+            )
+        } else {
+            (ICON_LIST_MUSIC, PLAYLIST_CONTEXT_MENU, &self.menu_state)
+        };
+        let item = item_with_icon(&playlist.name, icon, selected, Some(menu_state.clone()))
+            .on_menu_select(|_, option| {
+                Message::ContextAction(option, playlist.id, playlist.name.clone())
+            })
+            .on_menu_hover(move |option| {
+                Message::ContextMenuHover(menu.iter().position(|p| p.eq(&option)))
+            })
+            .on_menu_close(Message::ContextHide);
+        match drag {
+            None => item
+                .on_select(|_| Message::Selected(Section::Playlist(playlist.id)))
+                .into(),
+            // tracks dropped are added, the playlist is not opened
+            Some(drag) if playlist.kind == PlaylistKind::Static => drop_target(
+                item,
+                DropTarget::Playlist(playlist.id),
+                Some(drag),
+                Message::DragEntered,
+                Message::DragLeft,
+            ),
+            // dynamic playlists choose their own tracks
+            Some(_) => item.into(),
+        }
     }
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
-            Message::AddingPlaylist => {
+            Message::AddingPlaylist | Message::AddingDynamicPlaylist => {
                 self.name = String::new();
                 self.new_playlist = true;
+                self.new_dynamic = matches!(message, Message::AddingDynamicPlaylist);
                 self.editing_playlist = None;
                 return focus(NEW_PLAYLIST_INPUT_ID);
             }
@@ -111,21 +247,69 @@ impl Playlists {
             }
             Message::ContextMenuHover(index) => {
                 self.menu_state.selected = index;
+                self.dynamic_menu_state.selected = index;
             }
             Message::ContextHide => {
                 self.menu_state.selected = None;
+                self.dynamic_menu_state.selected = None;
+            }
+            Message::EditRules(id, rules) => {
+                let mut drafts: Vec<RuleDraft> = rules.iter().map(RuleDraft::new).collect();
+                if drafts.is_empty() {
+                    drafts.push(RuleDraft::default());
+                }
+                self.rules = Some((id, drafts));
             }
+            Message::RuleFieldChanged(index, field) => {
+                if let Some(draft) = self.draft(index) {
+                    draft.field = field;
+                }
+            }
+            Message::RuleTextChanged(index, value) => {
+                if let Some(draft) = self.draft(index) {
+                    draft.text = value;
+                }
+            }
+            Message::RuleMinChanged(index, value) => {
+                if let Some(draft) = self.draft(index) {
+                    draft.min = value;
+                }
+            }
+            Message::RuleMaxChanged(index, value) => {
+                if let Some(draft) = self.draft(index) {
+                    draft.max = value;
+                }
+            }
+            Message::AddRule => {
+                if let Some((_, drafts)) = &mut self.rules {
+                    drafts.push(RuleDraft::default());
+                }
+            }
+            Message::RemoveRule(index) => {
+                if let Some((_, drafts)) = &mut self.rules
+                    && index < drafts.len()
+                {
+                    drafts.remove(index);
+                }
+            }
+            Message::SaveRules(..) | Message::CancelRules => self.rules = None,
             _ => {}
         };
         Task::none()
     }
 
+    fn draft(&mut self, index: usize) -> Option<&mut RuleDraft> {
+        self.rules.as_mut()?.1.get_mut(index)
+    }
+
     fn editing_field<'a>(&'a self, id: Option<i64>) -> Element<'a, Message> {
+        // renaming keeps the kind
+        let kind = (id.is_none() && self.new_dynamic).then_some(PlaylistKind::Dynamic);
         widget::container(
             widget::TextInput::new("Name your playlist", &self.name)
                 .id(NEW_PLAYLIST_INPUT_ID)
                 .on_input(Message::TypingPlaylistName)
-                .on_submit(Message::CreatedPlaylist(id, self.name.to_string(), None)),
+                .on_submit(Message::CreatedPlaylist(id, self.name.to_string(), kind)),
         )
         .padding(Padding {
             top: ITEM_PADDING_TOP_BOTTOM,
@@ -135,14 +319,130 @@ impl Playlists {
         })
         .into()
     }
-    pub fn view<'a>(&'a self, state: &'a State, section: &'a Section) -> Element<'a, Message> {
+
+    fn rule_editor<'a>(&'a self, id: i64, drafts: &'a [RuleDraft]) -> Element<'a, Message> {
+        let bound = |placeholder: &'a str,
+                     value: &'a str,
+                     on_input: fn(usize, String) -> Message,
+                     index: usize| {
+            widget::TextInput::new(placeholder, value)
+                .on_input(move |value| on_input(index, value))
+                .size(12)
+                .width(Length::Fill)
+        };
+        let mut elements: Vec<Element<'a, Message>> = drafts
+            .iter()
+            .enumerate()
+            .map(|(index, draft)| {
+                let value: Element<'a, Message> = match draft.field {
+                    RuleField::Genre | RuleField::Artist | RuleField::Tag => {
+                        widget::TextInput::new("contains", &draft.text)
+                            .on_input(move |value| Message::RuleTextChanged(index, value))
+                            .size(12)
+                            .into()
+                    }
+                    RuleField::Custom => widget::TextInput::new("field=contains", &draft.text)
+                        .on_input(move |value| Message::RuleTextChanged(index, value))
+                        .size(12)
+                        .into(),
+                    RuleField::Year => row![
+                        bound("from", &draft.min, Message::RuleMinChanged, index),
+                        bound("to", &draft.max, Message::RuleMaxChanged, index),
+                    ]
+                    .spacing(ITEM_SPACING)
+                    .into(),
+                    RuleField::Duration => row![
+                        bound("min. minutes", &draft.min, Message::RuleMinChanged, index),
+                        bound("max. minutes", &draft.max, Message::RuleMaxChanged, index),
+                    ]
+                    .spacing(ITEM_SPACING)
+                    .into(),
+                    RuleField::Bitrate => row![
+                        bound("min. kbps", &draft.min, Message::RuleMinChanged, index),
+                        bound("max. kbps", &draft.max, Message::RuleMaxChanged, index),
+                    ]
+                    .spacing(ITEM_SPACING)
+                    .into(),
+                    RuleField::SampleRate => row![
+                        bound("min. Hz", &draft.min, Message::RuleMinChanged, index),
+                        bound("max. Hz", &draft.max, Message::RuleMaxChanged, index),
+                    ]
+                    .spacing(ITEM_SPACING)
+                    .into(),
+                    RuleField::Added => {
+                        bound("days", &draft.min, Message::RuleMinChanged, index).into()
+                    }
+                    RuleField::Rating => {
+                        bound("stars", &draft.min, Message::RuleMinChanged, index).into()
+                    }
+                    RuleField::Liked | RuleField::Lossless => text("").size(12).into(),
+                };
+                widget::column![
+                    row![
+                        pick_list(RuleField::ALL, Some(draft.field), move |field| {
+                            Message::RuleFieldChanged(index, field)
+                        })
+                        .text_size(12)
+                        .width(Length::Fill),
+                        mouse_area(text(ICON_X).font(ICON).size(14))
+                            .interaction(iced::mouse::Interaction::Pointer)
+                            .on_press(Message::RemoveRule(index)),
+                    ]
+                    .spacing(ITEM_SPACING)
+                    .align_y(Vertical::Center),
+                    value,
+                ]
+                .spacing(ITEM_SPACING)
+                .into()
+            })
+            .collect();
+        elements.push(text("Tracks matching every rule").size(12).into());
+        elements.push(
+            row![
+                Button::new(text("Add rule").size(12))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::AddRule),
+                Button::new(text("Save").size(12))
+                    .style(iced::widget::button::primary)
+                    .on_press(Message::SaveRules(
+                        id,
+                        drafts.iter().map(RuleDraft::rule).collect()
+                    )),
+                Button::new(text("Cancel").size(12))
+                    .style(iced::widget::button::text)
+                    .on_press(Message::CancelRules),
+            ]
+            .spacing(ITEM_SPACING)
+            .into(),
+        );
+        widget::container(widget::Column::from_vec(elements).spacing(ITEM_SPACING * 2.0))
+            .padding(Padding {
+                top: ITEM_PADDING_TOP_BOTTOM,
+                bottom: ITEM_PADDING_TOP_BOTTOM,
+                left: ITEM_PADDING_LEFT_RIGHT / 2.0,
+                right: ITEM_PADDING_LEFT_RIGHT / 2.0,
+            })
+            .into()
+    }
+
+    pub fn view<'a>(
+        &'a self,
+        state: &'a State,
+        section: &'a Section,
+        drag: Option<&'a Drag>,
+    ) -> Element<'a, Message> {
         let header_row = widget::Row::from_vec(vec![
             header("Playlists").width(Length::Fill).into(),
+            mouse_area(widget::Text::new(ICON_WAND).font(ICON).size(18))
+                .interaction(iced::mouse::Interaction::Pointer)
+                .on_press(Message::AddingDynamicPlaylist)
+                .into(),
             mouse_area(widget::Text::new(ICON_PLUS).font(ICON).size(20))
                 .interaction(iced::mouse::Interaction::Pointer)
                 .on_press(Message::AddingPlaylist)
                 .into(),
         ])
+        .spacing(ITEM_SPACING * 2.0)
         .align_y(Vertical::Center)
         .padding(Padding::default().right(ITEM_PADDING_LEFT_RIGHT / 2.0))
         .width(Length::Fill);
@@ -157,9 +457,14 @@ impl Playlists {
             {
                 self.editing_field(Some(id))
             } else {
-                self.playlist_element(&p.value, section.eq(&Section::Playlist(p.value.id)))
+                self.playlist_element(&p.value, section.eq(&Section::Playlist(p.value.id)), drag)
             };
             elements.push(element);
+            if let Some((id, drafts)) = &self.rules
+                && *id == p.value.id
+            {
+                elements.push(self.rule_editor(*id, drafts));
+            }
         });
 
         widget::Column::from_vec(elements)
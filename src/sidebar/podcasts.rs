@@ -0,0 +1,147 @@
+use iced::{
+    Element, Length, Padding, Task,
+    alignment::Vertical,
+    widget::{self, text_input::focus},
+};
+
+use crate::{
+    app_state::{Section, state_impl::State},
+    fonts::ICON,
+    icons::{ICON_PLUS, ICON_PODCAST},
+    sidebar::{ITEM_PADDING_LEFT_RIGHT, ITEM_PADDING_TOP_BOTTOM, header, item_with_icon},
+    storage::Podcast,
+    widgets::container::MenuState,
+};
+
+static FEED_URL_INPUT_ID: &str = "adding_podcast";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MenuOptions {
+    Refresh,
+    Unsubscribe,
+}
+
+impl std::fmt::Display for MenuOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MenuOptions::Refresh => f.write_str("Refresh Feed"),
+            MenuOptions::Unsubscribe => f.write_str("Unsubscribe"),
+        }
+    }
+}
+
+static CONTEXT_MENU: &[MenuOptions] = &[MenuOptions::Refresh, MenuOptions::Unsubscribe];
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Adding,
+    TypingUrl(String),
+    Subscribe(String),
+    // option, podcast id, feed url
+    ContextAction(MenuOptions, i64, String),
+    Selected(Section),
+    ContextMenuHover(Option<usize>),
+    ContextHide,
+}
+
+pub struct Podcasts {
+    adding: bool,
+    url: String,
+    menu_state: MenuState<'static, MenuOptions>,
+}
+
+impl Default for Podcasts {
+    fn default() -> Self {
+        Podcasts {
+            adding: false,
+            url: String::new(),
+            menu_state: MenuState::new(CONTEXT_MENU),
+        }
+    }
+}
+
+impl Podcasts {
+    fn podcast_element<'a>(&'a self, podcast: &'a Podcast, selected: bool) -> Element<'a, Message> {
+        item_with_icon(
+            &podcast.title,
+            ICON_PODCAST,
+            selected,
+            Some(self.menu_state.clone()),
+        )
+        .on_select(|_| Message::Selected(Section::Podcast(podcast.id)))
+        .on_menu_select(|_, option| {
+            Message::ContextAction(option, podcast.id, podcast.feed_url.clone())
+        })
+        .on_menu_hover(|option| {
+            Message::ContextMenuHover(CONTEXT_MENU.iter().position(|t| t.eq(&option)))
+        })
+        .on_menu_close(Message::ContextHide)
+        .into()
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Adding => {
+                self.url = String::new();
+                self.adding = true;
+                return focus(FEED_URL_INPUT_ID);
+            }
+            Message::TypingUrl(value) => {
+                self.url = value;
+            }
+            Message::Subscribe(_) => {
+                self.adding = false;
+            }
+            Message::ContextMenuHover(index) => {
+                self.menu_state.selected = index;
+            }
+            Message::ContextHide => {
+                self.menu_state.selected = None;
+            }
+            _ => {}
+        };
+        Task::none()
+    }
+
+    fn url_field<'a>(&'a self) -> Element<'a, Message> {
+        widget::container(
+            widget::TextInput::new("Feed URL", &self.url)
+                .id(FEED_URL_INPUT_ID)
+                .on_input(Message::TypingUrl)
+                .on_submit(Message::Subscribe(self.url.trim().to_string())),
+        )
+        .padding(Padding {
+            top: ITEM_PADDING_TOP_BOTTOM,
+            bottom: ITEM_PADDING_TOP_BOTTOM,
+            left: ITEM_PADDING_LEFT_RIGHT / 2.0,
+            right: ITEM_PADDING_LEFT_RIGHT / 2.0,
+        })
+        .into()
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State, section: &'a Section) -> Element<'a, Message> {
+        let header_row = widget::Row::from_vec(vec![
+            header("Podcasts").width(Length::Fill).into(),
+            widget::mouse_area(widget::Text::new(ICON_PLUS).font(ICON).size(20))
+                .interaction(iced::mouse::Interaction::Pointer)
+                .on_press(Message::Adding)
+                .into(),
+        ])
+        .align_y(Vertical::Center)
+        .padding(Padding::default().right(ITEM_PADDING_LEFT_RIGHT / 2.0))
+        .width(Length::Fill);
+
+        let mut elements: Vec<Element<'a, Message>> = vec![header_row.into()];
+        if self.adding {
+            elements.push(self.url_field());
+        }
+
+        state.podcasts().iter().for_each(|p| {
+            elements.push(self.podcast_element(p, section.eq(&Section::Podcast(p.id))));
+        });
+
+        widget::Column::from_vec(elements)
+            .width(Length::Fill)
+            .into()
+    }
+}
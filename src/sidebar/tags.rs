@@ -1,22 +1,33 @@
 use iced::{
-    Element, Length, Padding, Task,
+    Element, Length, Padding, Subscription, Task,
     alignment::Vertical,
+    event,
     widget::{self, text_input::focus},
 };
 
 use crate::{
-    app_state::{Section, state_impl::State},
+    app_state::{
+        Section,
+        state_impl::{State, TagNode},
+    },
     fonts::ICON,
     icons::{ICON_BOOK_MARKED, ICON_BOOKMARK, ICON_PLUS},
-    sidebar::{ITEM_PADDING_LEFT_RIGHT, ITEM_PADDING_TOP_BOTTOM, header, item_with_icon},
+    sidebar::{
+        Drag, DropTarget, ITEM_PADDING_LEFT_RIGHT, ITEM_PADDING_TOP_BOTTOM, drop_target, header,
+        item_with_icon,
+    },
     storage::Tag,
     widgets::container::MenuState,
 };
 
 static NEW_TAG_INPUT_ID: &str = "adding_playlist";
+// left padding added for every level of nesting
+const NESTING_INDENT: f32 = 12.0;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum MenuOptions {
+    Play,
+    Shuffle,
     Rename,
     Delete,
     Clear,
@@ -25,6 +36,8 @@ pub enum MenuOptions {
 impl std::fmt::Display for MenuOptions {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            MenuOptions::Play => f.write_str("Play Tag"),
+            MenuOptions::Shuffle => f.write_str("Shuffle Tag"),
             MenuOptions::Rename => f.write_str("Rename Tag"),
             MenuOptions::Delete => f.write_str("Delete Tag"),
             MenuOptions::Clear => f.write_str("Clear Tag"),
@@ -32,8 +45,13 @@ impl std::fmt::Display for MenuOptions {
     }
 }
 
-static CONTEXT_MENU: &[MenuOptions] =
-    &[MenuOptions::Rename, MenuOptions::Delete, MenuOptions::Clear];
+static CONTEXT_MENU: &[MenuOptions] = &[
+    MenuOptions::Play,
+    MenuOptions::Shuffle,
+    MenuOptions::Rename,
+    MenuOptions::Delete,
+    MenuOptions::Clear,
+];
 
 #[derive(Debug, Clone)]
 pub enum Message {
@@ -44,6 +62,9 @@ pub enum Message {
     Selected(Section),
     ContextMenuHover(Option<usize>),
     ContextHide,
+    MultiSelectKey(bool),
+    DragEntered(DropTarget),
+    DragLeft(DropTarget),
 }
 
 pub struct Tags {
@@ -51,6 +72,8 @@ pub struct Tags {
     editing: Option<i64>,
     name: String,
     menu_state: MenuState<'static, MenuOptions>,
+    // Ctrl is held, clicking a tag adds it to the selected ones
+    multi_select: bool,
 }
 
 impl Default for Tags {
@@ -60,26 +83,103 @@ impl Default for Tags {
             editing: None,
             name: String::new(),
             menu_state: MenuState::new(CONTEXT_MENU),
+            multi_select: false,
         }
     }
 }
 
 impl Tags {
-    fn tag_element<'a>(&'a self, tag: &'a Tag, selected: bool) -> Element<'a, Message> {
-        item_with_icon(
-            &tag.name,
-            ICON_BOOKMARK,
-            selected,
+    fn tag_element<'a>(
+        &'a self,
+        tag: &'a Tag,
+        node: &'a TagNode,
+        section: &'a Section,
+        drag: Option<&'a Drag>,
+    ) -> Element<'a, Message> {
+        let is_group = !node.children.is_empty();
+        let group_section = Section::TagPrefix(node.path.clone());
+        let item = item_with_icon(
+            &node.name,
+            if is_group {
+                ICON_BOOK_MARKED
+            } else {
+                ICON_BOOKMARK
+            },
+            section.has_tag(tag.id) || section.eq(&group_section),
             Some(self.menu_state.clone()),
         )
-        .on_select(|_| Message::Selected(Section::Tag(tag.id)))
         .on_menu_select(|_, option| Message::ContextAction(option, tag.id, tag.name.clone()))
         .on_menu_hover(|option| {
             Message::ContextMenuHover(CONTEXT_MENU.iter().position(|t| t.eq(&option)))
         })
-        .on_menu_close(Message::ContextHide)
+        .on_menu_close(Message::ContextHide);
+        // tracks dropped are tagged, the tag is not opened
+        if let Some(drag) = drag {
+            return drop_target(
+                item,
+                DropTarget::Tag(tag.id),
+                Some(drag),
+                Message::DragEntered,
+                Message::DragLeft,
+            );
+        }
+        item.on_select(move |_| {
+            if self.multi_select {
+                Message::Selected(section.toggle_tag(tag.id))
+            } else if is_group {
+                // a group shows its own tracks and those of every nested tag
+                Message::Selected(group_section.clone())
+            } else {
+                Message::Selected(Section::Tag(tag.id))
+            }
+        })
         .into()
     }
+    // a group without a tag of its own, e.g. `event` when only `event/boilerroom-2023` exists
+    fn group_element<'a>(
+        &'a self,
+        node: &'a TagNode,
+        section: &'a Section,
+        drag: Option<&'a Drag>,
+    ) -> Element<'a, Message> {
+        let group_section = Section::TagPrefix(node.path.clone());
+        let item = item_with_icon::<MenuOptions, Message>(
+            &node.name,
+            ICON_BOOK_MARKED,
+            section.eq(&group_section),
+            None,
+        );
+        // there is no tag to drop onto
+        if drag.is_some() {
+            return item.into();
+        }
+        item.on_select(move |_| Message::Selected(group_section.clone()))
+            .into()
+    }
+
+    fn tag_nodes<'a>(
+        &'a self,
+        nodes: &'a [TagNode],
+        section: &'a Section,
+        drag: Option<&'a Drag>,
+        depth: usize,
+        elements: &mut Vec<Element<'a, Message>>,
+    ) {
+        nodes.iter().for_each(|node| {
+            let element = match &node.tag {
+                Some(tag) if self.editing == Some(tag.id) => self.editing_field(Some(tag.id)),
+                Some(tag) => self.tag_element(tag, node, section, drag),
+                None => self.group_element(node, section, drag),
+            };
+            elements.push(
+                widget::container(element)
+                    .padding(Padding::default().left(depth as f32 * NESTING_INDENT))
+                    .into(),
+            );
+            self.tag_nodes(&node.children, section, drag, depth + 1, elements);
+        });
+    }
+
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::Adding => {
@@ -109,11 +209,23 @@ impl Tags {
             Message::ContextHide => {
                 self.menu_state.selected = None;
             }
+            Message::MultiSelectKey(pressed) => {
+                self.multi_select = pressed;
+            }
             _ => {}
         };
         Task::none()
     }
 
+    pub fn subscription(&self) -> Subscription<Message> {
+        event::listen_with(|event, _, _| match event {
+            iced::Event::Keyboard(iced::keyboard::Event::ModifiersChanged(modifiers)) => {
+                Some(Message::MultiSelectKey(modifiers.control()))
+            }
+            _ => None,
+        })
+    }
+
     fn editing_field<'a>(&'a self, id: Option<i64>) -> Element<'a, Message> {
         widget::container(
             widget::TextInput::new("Name your tag", &self.name)
@@ -129,7 +241,12 @@ impl Tags {
         })
         .into()
     }
-    pub fn view<'a>(&'a self, state: &'a State, section: &'a Section) -> Element<'a, Message> {
+    pub fn view<'a>(
+        &'a self,
+        state: &'a State,
+        section: &'a Section,
+        drag: Option<&'a Drag>,
+    ) -> Element<'a, Message> {
         let header_row = widget::Row::from_vec(vec![
             header("Tags").width(Length::Fill).into(),
             widget::mouse_area(widget::Text::new(ICON_PLUS).font(ICON).size(20))
@@ -146,16 +263,7 @@ impl Tags {
             elements.push(self.editing_field(None));
         }
 
-        state.tags().iter().for_each(|t| {
-            let element = if let Some(id) = self.editing
-                && id == t.id
-            {
-                self.editing_field(Some(id))
-            } else {
-                self.tag_element(&t, section.eq(&Section::Tag(t.id)))
-            };
-            elements.push(element);
-        });
+        self.tag_nodes(state.tag_tree(), section, drag, 0, &mut elements);
 
         widget::Column::from_vec(elements)
             .width(Length::Fill)
@@ -0,0 +1,185 @@
+// Portable JSON copy of what the user added to the library, likes, ratings and tags per track.
+// Tracks are keyed by the fingerprint of their content, a library rebuilt on another machine or
+// under other paths gets them attached again on import.
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::sync::{SyncDocument, SyncTrack};
+
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum SidecarError {
+    #[error("Invalid sidecar: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Unsupported sidecar version {0}")]
+    Version(u32),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, SidecarError>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sidecar {
+    pub version: u32,
+    pub exported_at: i64,
+    pub tracks: Vec<SidecarTrack>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SidecarTrack {
+    // None for files which could not be read on export, those only match by path
+    pub fingerprint: Option<String>,
+    pub path: String,
+    #[serde(default)]
+    pub liked: bool,
+    // stars, 0 while unrated
+    #[serde(default)]
+    pub rating: u8,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl Sidecar {
+    /// Keeps the tracks which are liked, rated or tagged, playlists are not part of a sidecar.
+    pub fn from_document(document: &SyncDocument, exported_at: i64) -> Self {
+        let tracks = document
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(index, track)| SidecarTrack {
+                fingerprint: track.fingerprint.clone(),
+                path: track.path.clone(),
+                liked: document.likes.contains(&index),
+                rating: document
+                    .ratings
+                    .iter()
+                    .find(|(rated, _)| *rated == index)
+                    .map_or(0, |(_, rating)| *rating),
+                tags: document
+                    .tags
+                    .iter()
+                    .filter(|(_, indexes)| indexes.contains(&index))
+                    .map(|(name, _)| name.clone())
+                    .collect(),
+            })
+            .filter(|track| track.liked || track.rating > 0 || !track.tags.is_empty())
+            .collect();
+        Self {
+            version: VERSION,
+            exported_at,
+            tracks,
+        }
+    }
+
+    /// The sidecar as a sync document, merged the same way as a peer's library.
+    pub fn to_document(&self) -> SyncDocument {
+        let mut document = SyncDocument::default();
+        for (index, track) in self.tracks.iter().enumerate() {
+            document.tracks.push(SyncTrack {
+                path: track.path.clone(),
+                fingerprint: track.fingerprint.clone(),
+            });
+            if track.liked {
+                document.likes.push(index);
+            }
+            if track.rating > 0 {
+                document.ratings.push((index, track.rating));
+            }
+            for name in &track.tags {
+                match document.tags.iter_mut().find(|(tag, _)| tag == name) {
+                    Some((_, indexes)) => indexes.push(index),
+                    None => document.tags.push((name.clone(), vec![index])),
+                }
+            }
+        }
+        document
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let sidecar: Sidecar = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        if sidecar.version > VERSION {
+            return Err(SidecarError::Version(sidecar.version));
+        }
+        Ok(sidecar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_conversion() {
+        let track = |path: &str| SyncTrack {
+            path: path.to_string(),
+            fingerprint: Some(format!("1-{path}")),
+        };
+        let document = SyncDocument {
+            tracks: vec![track("a"), track("b"), track("c"), track("d")],
+            likes: vec![2],
+            ratings: vec![(3, 5)],
+            tags: vec![
+                ("dub".to_string(), vec![0, 2]),
+                ("live".to_string(), vec![2]),
+            ],
+            playlists: vec![("Peak".to_string(), vec![1])],
+        };
+        let sidecar = Sidecar::from_document(&document, 100);
+        assert_eq!(sidecar.tracks.len(), 3);
+        assert_eq!(sidecar.tracks[1].tags, ["dub", "live"]);
+        assert!(sidecar.tracks[1].liked);
+        assert_eq!(sidecar.tracks[2].rating, 5);
+
+        let document = sidecar.to_document();
+        assert_eq!(document.tracks.len(), 3);
+        assert_eq!(document.likes, [1]);
+        assert_eq!(document.ratings, [(2, 5)]);
+        assert_eq!(
+            document.tags,
+            [
+                ("dub".to_string(), vec![0, 1]),
+                ("live".to_string(), vec![1])
+            ]
+        );
+        assert!(document.playlists.is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join("phoniq_test_sidecar.json");
+        let sidecar = Sidecar {
+            version: VERSION,
+            exported_at: 100,
+            tracks: vec![SidecarTrack {
+                fingerprint: None,
+                path: "/music/a.mp3".to_string(),
+                liked: true,
+                rating: 3,
+                tags: Vec::new(),
+            }],
+        };
+        sidecar.save(&path).unwrap();
+        assert_eq!(Sidecar::load(&path).unwrap(), sidecar);
+
+        std::fs::write(&path, r#"{"version": 2, "exported_at": 0, "tracks": []}"#).unwrap();
+        assert!(matches!(
+            Sidecar::load(&path),
+            Err(SidecarError::Version(2))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
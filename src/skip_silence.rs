@@ -0,0 +1,220 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU32, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{Source, source::SeekError};
+
+// how much of a pause plays before the rest of it is skipped
+const KEEP_SILENCE: Duration = Duration::from_millis(500);
+// silence dropped in one go, a frame plays after it so the output thread is not held up
+const MAX_SKIP: Duration = Duration::from_secs(1);
+
+/// Shared by the worker and the tracks it plays: the threshold, changed while a track plays,
+/// and the silence skipped in the playing track since it started or was last sought.
+#[derive(Debug, Default)]
+pub struct SilenceSkip {
+    // amplitude below which a frame is silent as f32 bits, 0 while skipping is off
+    threshold: AtomicU32,
+    // the track whose skipped time is kept, the last one to start
+    track: AtomicU64,
+    skipped_ms: AtomicU64,
+}
+
+impl SilenceSkip {
+    // None turns skipping off
+    pub fn set_threshold(&self, threshold_db: Option<i32>) {
+        let amplitude = threshold_db.map_or(0.0, |db| 10f32.powf(db as f32 / 20.0));
+        self.threshold.store(amplitude.to_bits(), Ordering::Relaxed);
+    }
+
+    fn threshold(&self) -> f32 {
+        f32::from_bits(self.threshold.load(Ordering::Relaxed))
+    }
+
+    /// Silence skipped in the playing track, the sink's position is behind the track's by it.
+    pub fn skipped(&self) -> Duration {
+        Duration::from_millis(self.skipped_ms.load(Ordering::Relaxed))
+    }
+}
+
+/// Plays the first `KEEP_SILENCE` of a pause and drops what follows until the sound comes back.
+/// Without a shared `SilenceSkip`, e.g. for previews, the samples pass through untouched.
+pub struct SkipSilence<S> {
+    source: S,
+    shared: Option<Arc<SilenceSkip>>,
+    // taken when the first frame is read, 0 before
+    track: u64,
+    frame: Vec<f32>,
+    // next sample of the frame
+    position: usize,
+    silent_frames: u64,
+    skipped_frames: u64,
+}
+
+impl<S> SkipSilence<S>
+where
+    S: Source<Item = f32>,
+{
+    pub fn new(source: S, shared: Option<Arc<SilenceSkip>>) -> Self {
+        Self {
+            source,
+            shared,
+            track: 0,
+            frame: Vec::new(),
+            position: 0,
+            silent_frames: 0,
+            skipped_frames: 0,
+        }
+    }
+
+    // None at the end of the source, the last frame may be short
+    fn read_frame(&mut self) -> Option<()> {
+        self.frame.clear();
+        self.position = 0;
+        let channels = self.source.channels().max(1);
+        for _ in 0..channels {
+            match self.source.next() {
+                Some(sample) => self.frame.push(sample),
+                None => break,
+            }
+        }
+        (!self.frame.is_empty()).then_some(())
+    }
+
+    fn next_frame(&mut self) -> Option<()> {
+        let frames_per =
+            |duration: Duration| (duration.as_secs_f64() * self.source.sample_rate() as f64) as u64;
+        let (keep, max_skip) = (frames_per(KEEP_SILENCE), frames_per(MAX_SKIP));
+        let mut skipped = 0;
+        loop {
+            self.read_frame()?;
+            let Some(shared) = &self.shared else {
+                return Some(());
+            };
+            if self.track == 0 {
+                // a new track starts counting from nothing
+                self.track = shared.track.fetch_add(1, Ordering::Relaxed) + 1;
+                shared.skipped_ms.store(0, Ordering::Relaxed);
+            }
+            let threshold = shared.threshold();
+            if threshold <= 0.0 || self.frame.iter().any(|sample| sample.abs() >= threshold) {
+                self.silent_frames = 0;
+                return Some(());
+            }
+            self.silent_frames += 1;
+            if self.silent_frames <= keep || skipped >= max_skip {
+                return Some(());
+            }
+            skipped += 1;
+            self.skipped_frames += 1;
+            self.report();
+        }
+    }
+
+    fn report(&self) {
+        let Some(shared) = &self.shared else {
+            return;
+        };
+        // a track fading out under the next one no longer counts
+        if shared.track.load(Ordering::Relaxed) != self.track {
+            return;
+        }
+        let sample_rate = self.source.sample_rate().max(1) as u64;
+        shared
+            .skipped_ms
+            .store(self.skipped_frames * 1000 / sample_rate, Ordering::Relaxed);
+    }
+}
+
+impl<S> Iterator for SkipSilence<S>
+where
+    S: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position == self.frame.len() {
+            self.next_frame()?;
+        }
+        let sample = self.frame[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl<S> Source for SkipSilence<S>
+where
+    S: Source<Item = f32>,
+{
+    // frames are dropped at any point, the length of the source's frames no longer holds
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.source.try_seek(pos)?;
+        self.frame.clear();
+        self.position = 0;
+        self.silent_frames = 0;
+        self.skipped_frames = 0;
+        self.report();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::*;
+
+    #[test]
+    fn test_skip_silence() {
+        // a second of sound, one and a half of silence and another second of sound, in mono
+        // at 100 Hz
+        let samples: Vec<f32> = [vec![0.5; 100], vec![0.0; 150], vec![0.5; 100]].concat();
+        let shared = Arc::new(SilenceSkip::default());
+        shared.set_threshold(Some(-40));
+        let played: Vec<f32> = SkipSilence::new(
+            SamplesBuffer::new(1, 100, samples.clone()),
+            Some(shared.clone()),
+        )
+        .collect();
+        // half a second of the silence is kept
+        assert_eq!(
+            played,
+            [vec![0.5; 100], vec![0.0; 50], vec![0.5; 100]].concat()
+        );
+        assert_eq!(shared.skipped(), Duration::from_secs(1));
+
+        // a track starting over resets the skipped time
+        shared.set_threshold(None);
+        let played: Vec<f32> = SkipSilence::new(
+            SamplesBuffer::new(1, 100, samples.clone()),
+            Some(shared.clone()),
+        )
+        .collect();
+        assert_eq!(played, samples);
+        assert_eq!(shared.skipped(), Duration::ZERO);
+
+        let played: Vec<f32> =
+            SkipSilence::new(SamplesBuffer::new(1, 100, samples.clone()), None).collect();
+        assert_eq!(played, samples);
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use crate::storage::SnapshotEntry;
+
+/// What changed in the library since a snapshot, entries are matched by path.
+#[derive(Debug, Default, PartialEq)]
+pub struct SnapshotDiff {
+    pub added: Vec<SnapshotEntry>,
+    pub removed: Vec<SnapshotEntry>,
+    // the entry in the snapshot followed by the one in the library now
+    pub changed: Vec<(SnapshotEntry, SnapshotEntry)>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compares the entries of a snapshot with later ones, each list of the result is sorted by path.
+pub fn diff(before: Vec<SnapshotEntry>, after: Vec<SnapshotEntry>) -> SnapshotDiff {
+    let mut before: HashMap<String, SnapshotEntry> = before
+        .into_iter()
+        .map(|entry| (entry.path.clone(), entry))
+        .collect();
+    let mut diff = SnapshotDiff::default();
+    for entry in after {
+        match before.remove(&entry.path) {
+            Some(old) if old != entry => diff.changed.push((old, entry)),
+            Some(_) => {}
+            None => diff.added.push(entry),
+        }
+    }
+    diff.removed = before.into_values().collect();
+
+    diff.added.sort_by(|a, b| a.path.cmp(&b.path));
+    diff.removed.sort_by(|a, b| a.path.cmp(&b.path));
+    diff.changed.sort_by(|(a, _), (b, _)| a.path.cmp(&b.path));
+    diff
+}
+
+/// One line per changed field, e.g. `title: Old → New` or `tags: +dub -techno`.
+pub fn describe_changes(before: &SnapshotEntry, after: &SnapshotEntry) -> Vec<String> {
+    let mut changes = Vec::new();
+    let fields = [
+        ("title", Some(&before.title), Some(&after.title)),
+        ("artist", before.artist.as_ref(), after.artist.as_ref()),
+        ("album", before.album.as_ref(), after.album.as_ref()),
+    ];
+    for (name, old, new) in fields {
+        if old != new {
+            let value = |value: Option<&String>| value.cloned().unwrap_or_else(|| "none".into());
+            changes.push(format!("{name}: {} → {}", value(old), value(new)));
+        }
+    }
+
+    let added = after
+        .tags
+        .iter()
+        .filter(|tag| !before.tags.contains(tag))
+        .map(|tag| format!("+{tag}"));
+    let removed = before
+        .tags
+        .iter()
+        .filter(|tag| !after.tags.contains(tag))
+        .map(|tag| format!("-{tag}"));
+    let tags: Vec<String> = added.chain(removed).collect();
+    if !tags.is_empty() {
+        changes.push(format!("tags: {}", tags.join(" ")));
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, title: &str, tags: &[&str]) -> SnapshotEntry {
+        SnapshotEntry {
+            path: path.to_string(),
+            title: title.to_string(),
+            artist: Some("Basic Channel".to_string()),
+            album: None,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = vec![
+            entry("/b", "Phylyps", &["dub"]),
+            entry("/a", "Octagon", &[]),
+            entry("/c", "Radiance", &[]),
+        ];
+        let after = vec![
+            entry("/d", "Quadrant", &[]),
+            entry("/a", "Octagon", &[]),
+            entry("/b", "Phylyps Trak", &["dub"]),
+        ];
+        let diff = diff(before, after);
+        assert_eq!(diff.added, [entry("/d", "Quadrant", &[])]);
+        assert_eq!(diff.removed, [entry("/c", "Radiance", &[])]);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].1.title, "Phylyps Trak");
+        assert!(!diff.is_empty());
+
+        let same = vec![entry("/a", "Octagon", &[])];
+        assert!(super::diff(same.clone(), same).is_empty());
+    }
+
+    #[test]
+    fn test_describe_changes() {
+        let before = entry("/a", "Octagon", &["dub", "techno"]);
+        let mut after = entry("/a", "Octagon", &["dub", "minimal"]);
+        after.artist = None;
+        assert_eq!(
+            describe_changes(&before, &after),
+            ["artist: Basic Channel → none", "tags: +minimal -techno"]
+        );
+        assert!(describe_changes(&before, &before).is_empty());
+    }
+}
@@ -1,73 +1,203 @@
 use super::{
-    AudioFileDescriptor, AudioFileKind, Playable, Playlist, Result, Storage, StorageError,
+    Album, AlbumTrack, AudioFileDescriptor, AudioFileKind, AutoPlaylist, Bookmark, CustomField,
+    DatabaseStats, Episode, EpisodeDescriptor, ImportBatch, LinkKind, LocalCopy, MAX_RATING,
+    Playable, PlayableLink, Playlist, PlaylistPlayback, PlaylistRule, Podcast, Result, RuleField,
+    Snapshot, SnapshotEntry, Storage, StorageError, Watch, WatchKind, WatchRelease,
+    WatchReleaseDescriptor,
 };
 use log::trace;
-use rusqlite::{Connection, OpenFlags, params};
-use std::{collections::HashSet, path::Path};
+use rusqlite::{
+    Connection, OpenFlags, TransactionBehavior, params,
+    types::Value,
+    vtab::array::{self, Array},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    rc::Rc,
+    time::Duration,
+};
 
 const SCHEMA: &str = include_str!("schema.sql");
+// fills the search index with the library so far, after it was made again
+const FILL_SEARCH: &str = "DELETE FROM PlayableSearch;
+     INSERT INTO PlayableSearch (rowid, title, artist, album, genre, tags, notes)
+     SELECT p.id, p.title, a.name, al.name, g.name, (
+         SELECT group_concat(t.name, ' ') FROM PlayableTag pt
+         JOIN Tag t ON t.id = pt.tag_id
+         WHERE pt.playable_id = p.id
+     ), p.notes
+     FROM Playable p
+     LEFT JOIN Artist a  ON a.id  = p.artist_id
+     LEFT JOIN Album al  ON al.id = p.album_id
+     LEFT JOIN Genre g   ON g.id  = p.genre_id;";
 
 #[derive(Debug)]
 pub struct LocalStorage {
     conn: Connection,
+    // read-only connection for the view queries so that long reads and imports do not block
+    // each other, None for in memory databases which can not be shared between connections
+    reader: Option<Connection>,
 }
 
 impl LocalStorage {
-    pub(crate) fn maybe_insert_artist(&mut self, name: &str) -> Result<i64> {
-        let mut stmt = self.conn.prepare(
-            "INSERT INTO Artist (name) VALUES (?1) ON CONFLICT(name) DO NOTHING RETURNING id",
-        )?;
-        let mut rows = stmt.query([name])?;
-        if let Some(row) = rows.next()? {
-            Ok(row.get(0)?)
-        } else {
-            let mut stmt = self.conn.prepare("SELECT id FROM Artist WHERE name = ?1")?;
-            let mut rows = stmt.query([name])?;
-            if let Some(row) = rows.next()? {
-                Ok(row.get(0)?)
-            } else {
-                Err(StorageError::QueryError)
-            }
-        }
+    fn reader(&self) -> &Connection {
+        self.reader.as_ref().unwrap_or(&self.conn)
     }
-    pub(crate) fn maybe_insert_genre(&mut self, name: &str) -> Result<i64> {
-        let mut stmt = self.conn.prepare(
-            "INSERT INTO Genre (name) VALUES (?1) ON CONFLICT(name) DO NOTHING RETURNING id",
-        )?;
-        let mut rows = stmt.query([name])?;
-        if let Some(row) = rows.next()? {
-            Ok(row.get(0)?)
-        } else {
-            let mut stmt = self.conn.prepare("SELECT id FROM Genre WHERE name = ?1")?;
-            let mut rows = stmt.query([name])?;
-            if let Some(row) = rows.next()? {
-                Ok(row.get(0)?)
-            } else {
-                Err(StorageError::QueryError)
-            }
+}
+
+// the columns `to_playable` reads followed by `$filter`, artwork is left out and loaded with
+// `read_artwork` when a view needs it. A literal keeps the whole query a
+// `&'static str`, ready for the statement cache
+macro_rules! playable_query {
+    () => {
+        playable_query!("")
+    };
+    // soft deleted playables are left out, SQLite flattens the subquery
+    ($filter:literal) => {
+        playable_query!(
+            "",
+            "(SELECT * FROM Playable WHERE deleted_at IS NULL)",
+            $filter
+        )
+    };
+    // `$columns` come after the ones `to_playable` reads, `$source` is read as `p`
+    ($columns:literal, $source:literal, $filter:literal) => {
+        concat!(
+            "SELECT p.id, p.title,
+                    a.name  AS artist_name,
+                    al.name AS album_name,
+                    g.name  AS genre_name,
+                    p.duration,
+                    p.source_url,
+                    p.type_id,
+                    p.date_added,
+                    p.play_count,
+                    p.last_played_at,
+                    p.rating,
+                    p.year,
+                    p.release_date,
+                    p.label",
+            $columns,
+            "
+             FROM ",
+            $source,
+            " p
+             LEFT JOIN Artist a  ON p.artist_id = a.id
+             LEFT JOIN Album al  ON p.album_id   = al.id
+             LEFT JOIN Genre g   ON p.genre_id   = g.id
+             ",
+            $filter
+        )
+    };
+}
+
+const SECONDS_PER_DAY: i64 = 86_400;
+// the best matches of a library search, the rest of a long tail is left out
+const SEARCH_LIMIT: usize = 500;
+// files added per transaction of a bulk write
+const WRITE_CHUNK: usize = 250;
+// how long a connection waits on the writes of the others, the app and the storage worker each
+// have their own, before it gives up with `SQLITE_BUSY`
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The SQL condition a rule puts on `p` and the values it binds, in order. Parameters are not
+/// numbered, the conditions of all rules are joined into one query.
+fn rule_condition(rule: &PlaylistRule, now: i64) -> (String, Vec<Value>) {
+    let text = rule.text.trim().to_string();
+    let bounds = |column: &str| {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if let Some(min) = rule.min {
+            conditions.push(format!("{column} >= ?"));
+            values.push(Value::from(min));
         }
-    }
-    pub(crate) fn maybe_insert_album(&mut self, name: &str, artist_id: i64) -> Result<i64> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT id FROM Album WHERE name = ?1 AND artist_id = ?2")?;
-        let mut rows = stmt.query(params![name, artist_id])?;
-        if let Some(row) = rows.next()? {
-            Ok(row.get(0)?)
-        } else {
-            let mut stmt = self
-                .conn
-                .prepare("INSERT INTO Album (name, artist_id) VALUES (?1, ?2) RETURNING id")?;
-            let mut rows = stmt.query(params![name, artist_id])?;
-            if let Some(row) = rows.next()? {
-                Ok(row.get(0)?)
-            } else {
-                Err(StorageError::QueryError)
-            }
+        if let Some(max) = rule.max {
+            conditions.push(format!("{column} <= ?"));
+            values.push(Value::from(max));
+        }
+        if conditions.is_empty() {
+            // an open range still wants the value to be known
+            conditions.push(format!("{column} IS NOT NULL"));
+        }
+        (conditions.join(" AND "), values)
+    };
+    match rule.field {
+        RuleField::Genre => (
+            "g.name LIKE '%' || ? || '%'".to_string(),
+            vec![Value::from(text)],
+        ),
+        RuleField::Artist => (
+            "a.name LIKE '%' || ? || '%'".to_string(),
+            vec![Value::from(text)],
+        ),
+        RuleField::Year => bounds("p.year"),
+        RuleField::Duration => bounds("p.duration"),
+        RuleField::Rating => bounds("p.rating"),
+        RuleField::Bitrate => bounds("p.bitrate"),
+        RuleField::SampleRate => bounds("p.sample_rate"),
+        RuleField::Lossless => ("p.lossless = 1".to_string(), vec![]),
+        RuleField::Added => (
+            "p.date_added >= ?".to_string(),
+            vec![Value::from(now - rule.min.unwrap_or(0) * SECONDS_PER_DAY)],
+        ),
+        RuleField::Liked => ("p.id IN (SELECT playable_id FROM Like)".to_string(), vec![]),
+        RuleField::Tag => (
+            "p.id IN (
+                SELECT pt.playable_id FROM PlayableTag pt JOIN Tag t ON t.id = pt.tag_id
+                WHERE t.name = ? OR t.name LIKE ? || '/%'
+             )"
+            .to_string(),
+            vec![Value::from(text.clone()), Value::from(text)],
+        ),
+        RuleField::Custom => {
+            // `name=value`, a name alone matches any value
+            let (name, value) = text.split_once('=').unwrap_or((text.as_str(), ""));
+            (
+                "p.id IN (
+                    SELECT fv.playable_id FROM FieldValue fv
+                    JOIN CustomField f ON f.id = fv.field_id
+                    WHERE f.name = ? AND fv.value LIKE '%' || ? || '%'
+                 )"
+                .to_string(),
+                vec![
+                    Value::from(name.trim().to_string()),
+                    Value::from(value.trim().to_string()),
+                ],
+            )
         }
     }
 }
 
+// binds a whole list to a single `rarray(?)` parameter
+fn id_array(ids: &[i64]) -> Array {
+    Rc::new(ids.iter().copied().map(Value::from).collect())
+}
+
+fn text_array(values: &[String]) -> Array {
+    Rc::new(values.iter().cloned().map(Value::from).collect())
+}
+
+// path, title, artist, album and the tag names one per line
+fn to_snapshot_entry(
+    row: &rusqlite::Row<'_>,
+) -> std::result::Result<SnapshotEntry, rusqlite::Error> {
+    let tags: String = row.get(4)?;
+    let mut tags: Vec<String> = tags
+        .lines()
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect();
+    tags.sort();
+    Ok(SnapshotEntry {
+        path: row.get(0)?,
+        title: row.get(1)?,
+        artist: row.get(2)?,
+        album: row.get(3)?,
+        tags,
+    })
+}
+
 fn to_playable(row: &rusqlite::Row<'_>) -> std::result::Result<Playable, rusqlite::Error> {
     let id = row.get(0)?;
     let title = row.get(1)?;
@@ -78,7 +208,12 @@ fn to_playable(row: &rusqlite::Row<'_>) -> std::result::Result<Playable, rusqlit
     let source_url = row.get(6)?;
     let type_id = AudioFileKind::try_from(row.get::<usize, i64>(7)?).unwrap();
     let date_added = row.get(8)?;
-    let artwork = row.get(9)?;
+    let play_count = row.get(9)?;
+    let last_played_at = row.get(10)?;
+    let rating = row.get(11)?;
+    let year = row.get(12)?;
+    let release_date = row.get(13)?;
+    let label = row.get(14)?;
 
     Ok(Playable {
         id,
@@ -90,28 +225,157 @@ fn to_playable(row: &rusqlite::Row<'_>) -> std::result::Result<Playable, rusqlit
         source_url,
         type_id,
         date_added,
-        artwork,
+        artwork: None,
+        play_count,
+        last_played_at,
+        rating,
+        year,
+        release_date,
+        label,
     })
 }
 
+fn maybe_insert_artist(conn: &Connection, name: &str) -> Result<i64> {
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO Artist (name) VALUES (?1) ON CONFLICT(name) DO NOTHING RETURNING id",
+    )?;
+    let mut rows = stmt.query([name])?;
+    if let Some(row) = rows.next()? {
+        Ok(row.get(0)?)
+    } else {
+        let mut stmt = conn.prepare_cached("SELECT id FROM Artist WHERE name = ?1")?;
+        let mut rows = stmt.query([name])?;
+        if let Some(row) = rows.next()? {
+            Ok(row.get(0)?)
+        } else {
+            Err(StorageError::QueryError)
+        }
+    }
+}
+
+fn maybe_insert_genre(conn: &Connection, name: &str) -> Result<i64> {
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO Genre (name) VALUES (?1) ON CONFLICT(name) DO NOTHING RETURNING id",
+    )?;
+    let mut rows = stmt.query([name])?;
+    if let Some(row) = rows.next()? {
+        Ok(row.get(0)?)
+    } else {
+        let mut stmt = conn.prepare_cached("SELECT id FROM Genre WHERE name = ?1")?;
+        let mut rows = stmt.query([name])?;
+        if let Some(row) = rows.next()? {
+            Ok(row.get(0)?)
+        } else {
+            Err(StorageError::QueryError)
+        }
+    }
+}
+
+fn maybe_insert_album(conn: &Connection, name: &str, artist_id: i64) -> Result<i64> {
+    let mut stmt =
+        conn.prepare_cached("SELECT id FROM Album WHERE name = ?1 AND artist_id = ?2")?;
+    let mut rows = stmt.query(params![name, artist_id])?;
+    if let Some(row) = rows.next()? {
+        Ok(row.get(0)?)
+    } else {
+        let mut stmt = conn
+            .prepare_cached("INSERT INTO Album (name, artist_id) VALUES (?1, ?2) RETURNING id")?;
+        let mut rows = stmt.query(params![name, artist_id])?;
+        if let Some(row) = rows.next()? {
+            Ok(row.get(0)?)
+        } else {
+            Err(StorageError::QueryError)
+        }
+    }
+}
+
+// adds a file to the library on `conn`, a transaction when it is one of many
+fn insert_playable(conn: &Connection, arg: &AudioFileDescriptor) -> Result<i64> {
+    // a file imported again takes the place of its deleted copy
+    conn.execute(
+        "DELETE FROM Playable WHERE source_url = ? AND deleted_at IS NOT NULL",
+        params![arg.path],
+    )?;
+    let existing = playables_by_paths(conn, std::slice::from_ref(&arg.path))?;
+    if !existing.is_empty() {
+        return Err(StorageError::DuplicateEntry);
+    }
+    let artist_name = &arg.artist;
+    let artist_id: Option<i64> = if !artist_name.is_empty() {
+        Some(maybe_insert_artist(conn, artist_name)?)
+    } else {
+        None
+    };
+    trace!("insert_playable: artist_id: {artist_id:?}");
+
+    let genre = &arg.genre;
+    let genre_id: Option<i64> = if !genre.is_empty() {
+        Some(maybe_insert_genre(conn, genre)?)
+    } else {
+        None
+    };
+    trace!("insert_playable: genre_id: {genre_id:?}");
+    let album_name = &arg.album;
+    let album_id: Option<i64> = if !album_name.is_empty() && artist_id.is_some() {
+        Some(maybe_insert_album(conn, album_name, artist_id.unwrap())?)
+    } else {
+        None
+    };
+    trace!("insert_playable: album_id: {album_id:?}");
+    let title = &arg.title;
+    let source_url = &arg.path;
+    let duration = arg.duration;
+    let kind = arg.kind as i64;
+
+    let mut stmt = conn.prepare_cached(
+        "INSERT INTO Playable(title,artist_id,album_id,genre_id,duration,source_url,type_id,artwork,year,bitrate,sample_rate,lossless,release_date,track_number,disc_number,label) VALUES(?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12,?13,?14,?15,?16)"
+    )?;
+    trace!("insert_playable: execute");
+    let year = (arg.year > 0).then_some(arg.year);
+    let bitrate = (arg.bitrate > 0).then_some(arg.bitrate);
+    let sample_rate = (arg.sample_rate > 0).then_some(arg.sample_rate);
+    let release_date = (!arg.release_date.is_empty()).then_some(&arg.release_date);
+    let track_number = (arg.track_number > 0).then_some(arg.track_number);
+    let disc_number = (arg.disc_number > 0).then_some(arg.disc_number);
+    let label = (!arg.label.is_empty()).then_some(&arg.label);
+    let _ = stmt.execute(params![
+        title,
+        artist_id,
+        album_id,
+        genre_id,
+        duration,
+        source_url,
+        kind,
+        arg.artwork,
+        year,
+        bitrate,
+        sample_rate,
+        arg.lossless,
+        release_date,
+        track_number,
+        disc_number,
+        label,
+    ])?;
+    trace!("insert_playable: done");
+    Ok(conn.last_insert_rowid())
+}
+
+// also the duplicate check of the writes, it has to see their uncommitted rows
+fn playables_by_paths(conn: &Connection, paths: &[String]) -> Result<Vec<Playable>> {
+    let mut stmt = conn.prepare_cached(playable_query!("WHERE p.source_url IN rarray(?1)"))?;
+    trace!("filter_library_by_paths: query {} paths", paths.len());
+    let rows = stmt
+        .query_map(params![text_array(paths)], to_playable)?
+        .map(|result| result.map_err(StorageError::from))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(rows)
+}
+
 impl Storage for LocalStorage {
     // Library
     fn read_library(&self) -> Result<Vec<Playable>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT p.id, p.title,
-                    a.name  AS artist_name,
-                    al.name AS album_name,
-                    g.name  AS genre_name,
-                    p.duration,
-                    p.source_url,
-                    p.type_id,
-                    p.date_added,
-                    p.artwork
-             FROM Playable p
-             LEFT JOIN Artist a  ON p.artist_id = a.id
-             LEFT JOIN Album al  ON p.album_id   = al.id
-             LEFT JOIN Genre g   ON p.genre_id   = g.id",
-        )?;
+        let mut stmt = self.reader().prepare_cached(playable_query!())?;
         trace!("read_library: Query");
         let rows: Vec<Playable> = stmt
             .query_map([], to_playable)?
@@ -122,85 +386,68 @@ impl Storage for LocalStorage {
         Ok(rows)
     }
 
-    fn read_library_from_ids(&self, ids: &[i64]) -> Result<Vec<Playable>> {
-        let query = format!(
-            "SELECT p.id, p.title,
-                    a.name  AS artist_name,
-                    al.name AS album_name,
-                    g.name  AS genre_name,
-                    p.duration,
-                    p.source_url,
-                    p.type_id,
-                    p.date_added,
-                    p.artwork
-             FROM Playable p
-             LEFT JOIN Artist a  ON p.artist_id = a.id
-             LEFT JOIN Album al  ON p.album_id   = al.id
-             LEFT JOIN Genre g   ON p.genre_id   = g.id
-             WHERE p.id IN ({})",
-            ids.iter()
-                .map(|id| id.to_string())
-                .collect::<Vec<String>>()
-                .join(",")
-        );
+    fn read_library_page(&self, after: i64, limit: usize) -> Result<Vec<Playable>> {
+        let mut stmt = self
+            .reader()
+            .prepare_cached(playable_query!("WHERE p.id > ?1 ORDER BY p.id LIMIT ?2"))?;
+        trace!("read_library_page: Query {after} +{limit}");
+        let rows = stmt
+            .query_map(params![after, limit as i64], to_playable)?
+            .map(|result| result.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_library_page: Done {} entries", rows.len());
+        Ok(rows)
+    }
+
+    fn read_artwork(&self, playable_id: i64) -> Result<Option<Vec<u8>>> {
+        let mut stmt = self
+            .reader()
+            .prepare_cached("SELECT artwork FROM Playable WHERE id = ?")?;
+        trace!("read_artwork: query {playable_id}");
+        let mut rows = stmt.query([playable_id])?;
+        match rows.next()? {
+            Some(row) => Ok(row.get(0)?),
+            None => Ok(None),
+        }
+    }
+
+    fn read_without_artwork(&self) -> Result<Vec<Playable>> {
+        let mut stmt = self
+            .reader()
+            .prepare_cached(playable_query!("WHERE p.artwork IS NULL"))?;
+        trace!("read_without_artwork: query");
+        let out = stmt
+            .query_map([], to_playable)?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_without_artwork: done {} entries", out.len());
+        Ok(out)
+    }
+
+    fn write_artwork(&mut self, playable_ids: &[i64], artwork: &[u8]) -> Result<()> {
+        trace!("write_artwork: execute");
+        self.conn.execute(
+            "UPDATE Playable SET artwork = ? WHERE id IN rarray(?)",
+            params![artwork, id_array(playable_ids)],
+        )?;
+        trace!("write_artwork: set for {} playables", playable_ids.len());
+        Ok(())
+    }
 
-        let mut stmt = self.conn.prepare(&query)?;
+    fn read_library_from_ids(&self, ids: &[i64]) -> Result<Vec<Playable>> {
+        let mut stmt = self
+            .reader()
+            .prepare_cached(playable_query!("WHERE p.id IN rarray(?1)"))?;
         trace!("read_library_from_ids: Query");
         let out = stmt
-            .query_map(params![], to_playable)?
+            .query_map(params![id_array(ids)], to_playable)?
             .map(|r| r.map_err(StorageError::from))
             .collect::<Result<Vec<_>>>()?;
         trace!("read_library_from_ids: Done {} entries", out.len());
         Ok(out)
     }
     fn append_to_library(&mut self, arg: &AudioFileDescriptor) -> Result<i64> {
-        let existing = self.filter_library_by_paths(std::slice::from_ref(&arg.path))?;
-        if !existing.is_empty() {
-            return Err(StorageError::DuplicateEntry);
-        }
-        let artist_name = &arg.artist;
-        let artist_id: Option<i64> = if !artist_name.is_empty() {
-            Some(self.maybe_insert_artist(artist_name)?)
-        } else {
-            None
-        };
-        trace!("append_to_library: artist_id: {artist_id:?}");
-
-        let genre = &arg.genre;
-        let genre_id: Option<i64> = if !genre.is_empty() {
-            Some(self.maybe_insert_genre(genre)?)
-        } else {
-            None
-        };
-        trace!("append_to_library: genre_id: {genre_id:?}");
-        let album_name = &arg.album;
-        let album_id: Option<i64> = if !album_name.is_empty() && artist_id.is_some() {
-            Some(self.maybe_insert_album(album_name, artist_id.unwrap())?)
-        } else {
-            None
-        };
-        trace!("append_to_library: album_id: {album_id:?}");
-        let title = &arg.title;
-        let source_url = &arg.path;
-        let duration = arg.duration;
-        let kind = arg.kind as i64;
-
-        let mut stmt = self.conn.prepare(
-            "INSERT INTO Playable(title,artist_id,album_id,genre_id,duration,source_url,type_id,artwork) VALUES(?1,?2,?3,?4,?5,?6,?7,?8)"
-        )?;
-        trace!("append_to_library: execute");
-        let _ = stmt.execute(params![
-            title,
-            artist_id,
-            album_id,
-            genre_id,
-            duration,
-            source_url,
-            kind,
-            arg.artwork,
-        ])?;
-        trace!("append_to_library: done");
-        Ok(self.conn.last_insert_rowid())
+        insert_playable(&self.conn, arg)
     }
     fn remove_from_library(&mut self, id: i64) -> Result<()> {
         trace!("remove_from_library: execute");
@@ -212,22 +459,9 @@ impl Storage for LocalStorage {
 
     // Likes
     fn read_likes(&self) -> Result<Vec<Playable>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT p.id, p.title,
-                    a.name  AS artist_name,
-                    al.name AS album_name,
-                    g.name  AS genre_name,
-                    p.duration,
-                    p.source_url,
-                    p.type_id,
-                    p.date_added,
-                    p.artwork
-             FROM Playable p
-             LEFT JOIN Artist a  ON p.artist_id = a.id
-             LEFT JOIN Album al  ON p.album_id   = al.id
-             LEFT JOIN Genre g   ON p.genre_id   = g.id
-             WHERE p.id IN (SELECT playable_id FROM Like)",
-        )?;
+        let mut stmt = self.reader().prepare_cached(playable_query!(
+            "WHERE p.id IN (SELECT playable_id FROM Like)"
+        ))?;
         trace!("read_likes: query");
         let out = stmt
             .query_map([], to_playable)?
@@ -239,7 +473,7 @@ impl Storage for LocalStorage {
     fn append_like(&mut self, playable_id: i64) -> Result<()> {
         trace!("append_like: execute");
         self.conn.execute(
-            "INSERT OR IGNORE INTO Like(playable_id) VALUES (?)",
+            "INSERT OR IGNORE INTO Like(playable_id, liked_at) VALUES (?, strftime('%s', 'now'))",
             params![playable_id],
         )?;
         trace!("append_like: added {playable_id}");
@@ -263,7 +497,7 @@ impl Storage for LocalStorage {
         parent_id: Option<i64>,
     ) -> Result<i64> {
         let playlist_kind = kind.unwrap_or_default();
-        let mut stmt = self.conn.prepare(
+        let mut stmt = self.conn.prepare_cached(
             "INSERT INTO Playlist (name, kind, parent_id) VALUES (?, ?, ?) ON CONFLICT(name) DO NOTHING RETURNING id",
         )?;
         trace!("create_playlist: query");
@@ -282,8 +516,8 @@ impl Storage for LocalStorage {
     }
     fn read_playlists(&self) -> Result<Vec<Playlist>> {
         let mut stmt = self
-            .conn
-            .prepare("SELECT id, parent_id, name, kind, position FROM Playlist")?;
+            .reader()
+            .prepare_cached("SELECT id, parent_id, name, kind, position, notes FROM Playlist")?;
         trace!("read_playlists: query");
         let mut rows = stmt.query(())?;
         let mut out = Vec::new();
@@ -296,11 +530,146 @@ impl Storage for LocalStorage {
                 name: row.get(2)?,
                 kind: super::PlaylistKind::from(kind_str),
                 position: row.get(4)?,
+                notes: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
             });
         }
         trace!("read_playlists: done {} entries", out.len());
         Ok(out)
     }
+    fn read_playlist_rules(&self, playlist_id: i64) -> Result<Vec<PlaylistRule>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT field, text, min, max FROM PlaylistRule WHERE playlist_id = ?
+             ORDER BY position",
+        )?;
+        trace!("read_playlist_rules: query {playlist_id}");
+        let out = stmt
+            .query_map([playlist_id], |row| {
+                let field: String = row.get(0)?;
+                Ok(PlaylistRule {
+                    field: RuleField::from(field.as_str()),
+                    text: row.get(1)?,
+                    min: row.get(2)?,
+                    max: row.get(3)?,
+                })
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_playlist_rules: done {} entries", out.len());
+        Ok(out)
+    }
+    fn write_playlist_rules(&mut self, playlist_id: i64, rules: &[PlaylistRule]) -> Result<()> {
+        self.conn.execute("BEGIN IMMEDIATE", ())?;
+        trace!("write_playlist_rules: execute");
+        self.conn.execute(
+            "DELETE FROM PlaylistRule WHERE playlist_id = ?",
+            params![playlist_id],
+        )?;
+        for (position, rule) in rules.iter().enumerate() {
+            self.conn.execute(
+                "INSERT INTO PlaylistRule (playlist_id, position, field, text, min, max)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                params![
+                    playlist_id,
+                    position as i64,
+                    rule.field.as_str(),
+                    rule.text,
+                    rule.min,
+                    rule.max
+                ],
+            )?;
+        }
+        self.conn.execute("COMMIT", ())?;
+        trace!(
+            "write_playlist_rules: {} rules for {playlist_id}",
+            rules.len()
+        );
+        Ok(())
+    }
+    fn read_dynamic_playlist(&self, playlist_id: i64, now: i64) -> Result<Vec<Playable>> {
+        let rules = self.read_playlist_rules(playlist_id)?;
+        if rules.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        for rule in &rules {
+            let (condition, rule_values) = rule_condition(rule, now);
+            conditions.push(format!("({condition})"));
+            values.extend(rule_values);
+        }
+        let query = format!("{}WHERE {}", playable_query!(), conditions.join(" AND "));
+        let mut stmt = self.reader().prepare_cached(&query)?;
+        trace!(
+            "read_dynamic_playlist: query {playlist_id} with {} rules",
+            rules.len()
+        );
+        let out = stmt
+            .query_map(rusqlite::params_from_iter(values), to_playable)?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_dynamic_playlist: done {} entries", out.len());
+        Ok(out)
+    }
+    fn read_playlist_playback(&self, playlist_id: i64) -> Result<PlaylistPlayback> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT shuffle, fade_ms FROM PlaylistPlayback WHERE playlist_id = ?",
+        )?;
+        trace!("read_playlist_playback: query {playlist_id}");
+        let mut rows = stmt.query([playlist_id])?;
+        if let Some(row) = rows.next()? {
+            let fade_ms: Option<i64> = row.get(1)?;
+            Ok(PlaylistPlayback {
+                shuffle: row.get(0)?,
+                fade_ms: fade_ms.map(|ms| ms.max(0) as u64),
+            })
+        } else {
+            Ok(PlaylistPlayback::default())
+        }
+    }
+    fn write_playlist_playback(
+        &mut self,
+        playlist_id: i64,
+        playback: &PlaylistPlayback,
+    ) -> Result<()> {
+        trace!("write_playlist_playback: execute");
+        self.conn.execute(
+            "INSERT INTO PlaylistPlayback(playlist_id, shuffle, fade_ms) VALUES (?1, ?2, ?3)
+             ON CONFLICT(playlist_id) DO UPDATE SET shuffle = excluded.shuffle, fade_ms = excluded.fade_ms",
+            params![
+                playlist_id,
+                playback.shuffle,
+                playback.fade_ms.map(|ms| ms as i64)
+            ],
+        )?;
+        trace!("write_playlist_playback: {playlist_id} = {playback:?}");
+        Ok(())
+    }
+    fn read_playlists_containing(&self, playable_id: i64) -> Result<Vec<Playlist>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT DISTINCT pl.id, pl.parent_id, pl.name, pl.kind, pl.position, pl.notes
+             FROM Playlist pl
+             JOIN PlaylistPlayable pp ON pp.playlist_id = pl.id
+             WHERE pp.playable_id = ?
+             ORDER BY pl.name COLLATE NOCASE",
+        )?;
+        trace!("read_playlists_containing: query");
+        let out = stmt
+            .query_map(params![playable_id], |row| {
+                let kind_str: Option<String> = row.get(3)?;
+                Ok(Playlist {
+                    id: row.get(0)?,
+                    parent_id: row.get(1)?,
+                    name: row.get(2)?,
+                    kind: super::PlaylistKind::from(kind_str),
+                    position: row.get(4)?,
+                    notes: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+                })
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_playlists_containing: done {} entries", out.len());
+        Ok(out)
+    }
     fn delete_playlist(&mut self, playlist_id: i64) -> Result<()> {
         trace!("delete_playlist: execute");
         self.conn
@@ -309,22 +678,11 @@ impl Storage for LocalStorage {
         Ok(())
     }
     fn read_playlist(&self, playlist_id: i64) -> Result<Vec<Playable>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT p.id, p.title,
-                    a.name  AS artist_name,
-                    al.name AS album_name,
-                    g.name  AS genre_name,
-                    p.duration,
-                    p.source_url,
-                    p.type_id,
-                    p.date_added,
-                    p.artwork
-             FROM Playable p
-             LEFT JOIN Artist a  ON p.artist_id = a.id
-             LEFT JOIN Album al  ON p.album_id   = al.id
-             LEFT JOIN Genre g   ON p.genre_id   = g.id
-             WHERE p.id IN (SELECT playable_id FROM PlaylistPlayable WHERE playlist_id = ?)",
-        )?;
+        let mut stmt = self.reader().prepare_cached(playable_query!(
+            "JOIN PlaylistPlayable pp ON pp.playable_id = p.id
+             WHERE pp.playlist_id = ?
+             ORDER BY pp.position, p.id"
+        ))?;
         trace!("read_playlist: query");
         let out = stmt
             .query_map(params![playlist_id], to_playable)?
@@ -360,6 +718,31 @@ impl Storage for LocalStorage {
         );
         Ok(())
     }
+    fn move_in_playlist(&mut self, playlist_id: i64, from: usize, to: usize) -> Result<()> {
+        let mut ids: Vec<i64> = self
+            .read_playlist(playlist_id)?
+            .iter()
+            .map(|p| p.id)
+            .collect();
+        if from >= ids.len() {
+            return Ok(());
+        }
+        let id = ids.remove(from);
+        ids.insert(to.min(ids.len()), id);
+        // positions are numbered anew from 1, in the order the tracks are listed
+        self.conn.execute("BEGIN IMMEDIATE", ())?;
+        trace!("move_in_playlist: execute");
+        for (position, id) in ids.iter().enumerate() {
+            self.conn.execute(
+                "UPDATE PlaylistPlayable SET position = ? WHERE playlist_id = ? AND playable_id = ?",
+                params![position as i64 + 1, playlist_id, id],
+            )?;
+        }
+        self.conn.execute("COMMIT", ())?;
+        trace!("move_in_playlist: moved {from} to {to} in {playlist_id}");
+        Ok(())
+    }
+
     fn remove_from_playlist(&mut self, playlist_id: i64, playable_id: i64) -> Result<()> {
         trace!("remove_from_playlist: execute");
         self.conn.execute(
@@ -390,9 +773,18 @@ impl Storage for LocalStorage {
         Ok(())
     }
 
+    fn set_playlist_notes(&mut self, playlist_id: i64, notes: &str) -> Result<()> {
+        trace!("set_playlist_notes: execute");
+        self.conn.execute(
+            "UPDATE Playlist SET notes = NULLIF(?, '') WHERE id = ?",
+            params![notes.trim(), playlist_id],
+        )?;
+        Ok(())
+    }
+
     // Tags
     fn create_tag(&mut self, name: &str) -> Result<i64> {
-        let mut stmt = self.conn.prepare(
+        let mut stmt = self.conn.prepare_cached(
             "INSERT INTO Tag (name) VALUES (?) ON CONFLICT(name) DO NOTHING RETURNING id",
         )?;
         trace!("create_tag: query");
@@ -418,22 +810,9 @@ impl Storage for LocalStorage {
         Ok(())
     }
     fn read_tag(&self, tag_id: i64) -> Result<Vec<Playable>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT p.id, p.title,
-                    a.name  AS artist_name,
-                    al.name AS album_name,
-                    g.name  AS genre_name,
-                    p.duration,
-                    p.source_url,
-                    p.type_id,
-                    p.date_added,
-                    p.artwork
-             FROM Playable p
-             LEFT JOIN Artist a  ON p.artist_id = a.id
-             LEFT JOIN Album al  ON p.album_id   = al.id
-             LEFT JOIN Genre g   ON p.genre_id   = g.id
-             WHERE p.id IN (SELECT playable_id FROM PlayableTag WHERE tag_id = ?)",
-        )?;
+        let mut stmt = self.reader().prepare_cached(playable_query!(
+            "WHERE p.id IN (SELECT playable_id FROM PlayableTag WHERE tag_id = ?)"
+        ))?;
         trace!("read_tag: query");
         let out = stmt
             .query_map(params![tag_id], to_playable)?
@@ -442,6 +821,143 @@ impl Storage for LocalStorage {
         trace!("read_tag: done {} entries", out.len());
         Ok(out)
     }
+    fn read_tag_intersection(&self, tag_ids: &[i64]) -> Result<Vec<Playable>> {
+        let mut stmt = self.reader().prepare_cached(playable_query!(
+            "WHERE p.id IN (
+                SELECT playable_id FROM PlayableTag
+                WHERE tag_id IN rarray(?1)
+                GROUP BY playable_id
+                HAVING COUNT(tag_id) = ?2
+             )"
+        ))?;
+        trace!("read_tag_intersection: query {tag_ids:?}");
+        let out = stmt
+            .query_map(
+                params![id_array(tag_ids), tag_ids.len() as i64],
+                to_playable,
+            )?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_tag_intersection: done {} entries", out.len());
+        Ok(out)
+    }
+    fn read_tag_prefix(&self, prefix: &str) -> Result<Vec<Playable>> {
+        let mut stmt = self.reader().prepare_cached(playable_query!(
+            "WHERE p.id IN (
+                SELECT pt.playable_id FROM PlayableTag pt
+                JOIN Tag t ON t.id = pt.tag_id
+                WHERE t.name = ?1
+                   OR substr(t.name, 1, length(?1) + 1) = ?1 || '/' COLLATE NOCASE
+             )"
+        ))?;
+        trace!("read_tag_prefix: query {prefix}");
+        let out = stmt
+            .query_map(params![prefix], to_playable)?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_tag_prefix: done {} entries", out.len());
+        Ok(out)
+    }
+
+    fn search_library(&self, query: &str) -> Result<Vec<Playable>> {
+        let Some(query) = match_query(query) else {
+            return Ok(Vec::new());
+        };
+        // a title match weighs the most, then artist and album
+        let mut stmt = self.reader().prepare_cached(playable_query!(
+            "JOIN (
+                SELECT rowid, bm25(PlayableSearch, 10.0, 5.0, 3.0, 1.0, 1.0, 1.0) AS rank
+                FROM PlayableSearch WHERE PlayableSearch MATCH ?1
+             ) s ON s.rowid = p.id
+             ORDER BY s.rank, p.id
+             LIMIT ?2"
+        ))?;
+        trace!("search_library: query {query}");
+        let out = stmt
+            .query_map(params![query, SEARCH_LIMIT], to_playable)?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("search_library: done {} entries", out.len());
+        Ok(out)
+    }
+
+    // Auto playlists
+    fn read_auto_playlist(&self, playlist: AutoPlaylist) -> Result<Vec<Playable>> {
+        let (query, limit) = match playlist {
+            AutoPlaylist::MostPlayed => (
+                playable_query!(
+                    "JOIN (
+                        SELECT playable_id, COUNT(*) AS plays FROM PlayHistory
+                        GROUP BY playable_id
+                     ) h ON h.playable_id = p.id
+                     ORDER BY h.plays DESC, p.id
+                     LIMIT ?1"
+                ),
+                AutoPlaylist::LIMIT,
+            ),
+            AutoPlaylist::RecentlyLiked => (
+                playable_query!(
+                    "JOIN Like l ON l.playable_id = p.id
+                     ORDER BY l.liked_at DESC, p.id
+                     LIMIT ?1"
+                ),
+                AutoPlaylist::LIMIT,
+            ),
+            // a negative limit lists everything
+            AutoPlaylist::NeverPlayed => (
+                playable_query!(
+                    "WHERE p.id NOT IN (SELECT playable_id FROM PlayHistory)
+                     ORDER BY p.id
+                     LIMIT ?1"
+                ),
+                -1,
+            ),
+            AutoPlaylist::TopRated => return self.read_by_rating(AutoPlaylist::TOP_RATED),
+        };
+        let mut stmt = self.reader().prepare_cached(query)?;
+        trace!("read_auto_playlist: query {playlist:?}");
+        let out = stmt
+            .query_map(params![limit], to_playable)?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_auto_playlist: done {} entries", out.len());
+        Ok(out)
+    }
+    fn read_not_played_since(&self, played_before: i64) -> Result<Vec<Playable>> {
+        let mut stmt = self.reader().prepare_cached(playable_query!(
+            "WHERE p.id IN (
+                SELECT playable_id FROM PlayHistory
+                GROUP BY playable_id
+                HAVING MAX(played_at) < ?1
+             )"
+        ))?;
+        trace!("read_not_played_since: query {played_before}");
+        let out = stmt
+            .query_map(params![played_before], to_playable)?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_not_played_since: done {} entries", out.len());
+        Ok(out)
+    }
+    fn read_recently_played(&self, limit: usize) -> Result<Vec<(Playable, i64)>> {
+        let mut stmt = self.reader().prepare_cached(playable_query!(
+            ", h.played_at",
+            "(SELECT * FROM Playable WHERE deleted_at IS NULL)",
+            "JOIN (
+                SELECT playable_id, MAX(played_at) AS played_at FROM PlayHistory
+                GROUP BY playable_id
+             ) h ON h.playable_id = p.id
+             ORDER BY h.played_at DESC, p.id
+             LIMIT ?1"
+        ))?;
+        trace!("read_recently_played: query {limit}");
+        let out = stmt
+            .query_map(params![limit], |row| Ok((to_playable(row)?, row.get(15)?)))?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_recently_played: done {} entries", out.len());
+        Ok(out)
+    }
     fn remove_from_tag(&mut self, tag_id: i64, playable_id: i64) -> Result<()> {
         self.conn.execute(
             "DELETE FROM PlayableTag WHERE tag_id = ? AND playable_id = ?",
@@ -450,7 +966,7 @@ impl Storage for LocalStorage {
         Ok(())
     }
     fn read_tags(&self) -> Result<Vec<super::Tag>> {
-        let mut stmt = self.conn.prepare("SELECT id, name FROM Tag")?;
+        let mut stmt = self.reader().prepare_cached("SELECT id, name FROM Tag")?;
         trace!("read_tags: query");
         let mut rows = stmt.query(())?;
         let mut out = Vec::new();
@@ -464,155 +980,1436 @@ impl Storage for LocalStorage {
         trace!("read_tags: done {} entries", out.len());
         Ok(out)
     }
-    fn delete_tag(&mut self, tag_id: i64) -> Result<()> {
-        trace!("delete_tag: execute");
-        self.conn
-            .execute("DELETE FROM Tag WHERE id = ?", params![tag_id])?;
-        trace!("delete_tag: removed id {tag_id}");
+    fn read_tags_of(&self, playable_id: i64) -> Result<Vec<super::Tag>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT t.id, t.name FROM Tag t
+             JOIN PlayableTag pt ON pt.tag_id = t.id
+             WHERE pt.playable_id = ?
+             ORDER BY t.name COLLATE NOCASE",
+        )?;
+        trace!("read_tags_of: query");
+        let out = stmt
+            .query_map(params![playable_id], |row| {
+                Ok(super::Tag {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                })
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_tags_of: done {} entries", out.len());
+        Ok(out)
+    }
+    fn delete_tag(&mut self, tag_id: i64) -> Result<()> {
+        trace!("delete_tag: execute");
+        self.conn
+            .execute("DELETE FROM Tag WHERE id = ?", params![tag_id])?;
+        trace!("delete_tag: removed id {tag_id}");
+        Ok(())
+    }
+    fn rename_tag(&mut self, tag_id: i64, name: &str) -> Result<()> {
+        let old_name: String =
+            self.conn
+                .query_row("SELECT name FROM Tag WHERE id = ?", params![tag_id], |r| {
+                    r.get(0)
+                })?;
+        trace!("rename_tag: execute");
+        // nested tags keep their suffix, `mood/dark` becomes `vibe/dark` when `mood` is renamed
+        let changed = self.conn.execute(
+            "UPDATE Tag SET name = ?1 || substr(name, length(?2) + 1)
+             WHERE id = ?3
+                OR substr(name, 1, length(?2) + 1) = ?2 || '/' COLLATE NOCASE",
+            params![name, old_name, tag_id],
+        )?;
+        trace!("rename_tag: renamed id {tag_id} to {name}, {changed} tags updated");
+        Ok(())
+    }
+
+    fn bulk_append_to_library(&mut self, playables: &[AudioFileDescriptor]) -> Result<Vec<i64>> {
+        let mut row_ids = Vec::with_capacity(playables.len());
+        trace!("bulk_append_to_library: execute");
+        // a transaction per chunk, so that the other connections get to write during a large
+        // import, and a chunk that fails is rolled back when its transaction is dropped
+        for chunk in playables.chunks(WRITE_CHUNK) {
+            let tx = self
+                .conn
+                .transaction_with_behavior(TransactionBehavior::Immediate)?;
+            for playable in chunk {
+                let res = insert_playable(&tx, playable);
+                if let Err(StorageError::DuplicateEntry) = res {
+                    continue;
+                }
+                let row_id = res?;
+                row_ids.push(row_id);
+            }
+            tx.commit()?;
+        }
+        trace!("bulk_append_to_library: done");
+        Ok(row_ids)
+    }
+
+    fn bulk_remove_from_library(&mut self, playable_ids: &[i64]) -> Result<()> {
+        trace!("bulk_remove_from_library: execute");
+        self.conn.execute("BEGIN IMMEDIATE", ())?;
+        for id in playable_ids {
+            self.remove_from_library(*id)?;
+        }
+        self.conn.execute("COMMIT", ())?;
+        trace!("bulk_remove_from_library: done");
+        Ok(())
+    }
+
+    fn bulk_remove_from_playlist(&mut self, playlist_id: i64, indexes: &[i64]) -> Result<()> {
+        self.conn.execute("BEGIN IMMEDIATE", ())?;
+        for index in indexes {
+            self.remove_from_playlist(playlist_id, *index)?;
+        }
+        self.conn.execute("COMMIT", ())?;
+        trace!("bulk_remove_from_playlist: done");
+        Ok(())
+    }
+
+    fn soft_delete(&mut self, playable_ids: &[i64]) -> Result<()> {
+        trace!("soft_delete: execute");
+        // likes, playlists and tags stay, a restored playable comes back with them
+        let changed = self.conn.execute(
+            "UPDATE Playable SET deleted_at = strftime('%s', 'now')
+             WHERE id IN rarray(?1) AND deleted_at IS NULL",
+            params![id_array(playable_ids)],
+        )?;
+        trace!("soft_delete: {changed} playables deleted");
+        Ok(())
+    }
+    fn restore_deleted(&mut self, playable_ids: &[i64]) -> Result<()> {
+        trace!("restore_deleted: execute");
+        let changed = self.conn.execute(
+            "UPDATE Playable SET deleted_at = NULL WHERE id IN rarray(?1)",
+            params![id_array(playable_ids)],
+        )?;
+        trace!("restore_deleted: {changed} playables restored");
+        Ok(())
+    }
+    fn read_deleted(&self) -> Result<Vec<(Playable, i64)>> {
+        let mut stmt = self.reader().prepare_cached(playable_query!(
+            ", p.deleted_at",
+            "Playable",
+            "WHERE p.deleted_at IS NOT NULL ORDER BY p.deleted_at DESC, p.id"
+        ))?;
+        trace!("read_deleted: query");
+        let out = stmt
+            .query_map((), |row| Ok((to_playable(row)?, row.get(15)?)))?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_deleted: done {} entries", out.len());
+        Ok(out)
+    }
+    fn purge_deleted(&mut self, deleted_before: i64) -> Result<usize> {
+        trace!("purge_deleted: execute");
+        let purged = self.conn.execute(
+            "DELETE FROM Playable WHERE deleted_at < ?",
+            params![deleted_before],
+        )?;
+        trace!("purge_deleted: {purged} playables purged");
+        Ok(purged)
+    }
+
+    fn is_liked(&self, playable_id: i64) -> Result<bool> {
+        let mut stmt = self
+            .reader()
+            .prepare_cached("SELECT * FROM Like where playable_id = ?")?;
+        let mut rows = stmt.query([playable_id])?;
+        if (rows.next()?).is_some() {
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn set_rating(&mut self, playable_id: i64, rating: u8) -> Result<()> {
+        trace!("set_rating: execute");
+        self.conn.execute(
+            "UPDATE Playable SET rating = ? WHERE id = ?",
+            params![rating.min(MAX_RATING), playable_id],
+        )?;
+        trace!("set_rating: {playable_id} = {rating}");
+        Ok(())
+    }
+
+    fn set_genre(&mut self, playable_id: i64, genre: &str) -> Result<()> {
+        trace!("set_genre: execute");
+        let genre = genre.trim();
+        let genre_id = if genre.is_empty() {
+            None
+        } else {
+            Some(maybe_insert_genre(&self.conn, genre)?)
+        };
+        self.conn.execute(
+            "UPDATE Playable SET genre_id = ? WHERE id = ?",
+            params![genre_id, playable_id],
+        )?;
+        Ok(())
+    }
+
+    fn read_notes(&self, playable_id: i64) -> Result<String> {
+        let mut stmt = self
+            .reader()
+            .prepare_cached("SELECT notes FROM Playable WHERE id = ?")?;
+        trace!("read_notes: query");
+        let mut rows = stmt.query([playable_id])?;
+        let notes: Option<String> = match rows.next()? {
+            Some(row) => row.get(0)?,
+            None => None,
+        };
+        Ok(notes.unwrap_or_default())
+    }
+
+    fn set_notes(&mut self, playable_id: i64, notes: &str) -> Result<()> {
+        trace!("set_notes: execute");
+        self.conn.execute(
+            "UPDATE Playable SET notes = NULLIF(?, '') WHERE id = ?",
+            params![notes.trim(), playable_id],
+        )?;
+        Ok(())
+    }
+
+    fn read_by_rating(&self, min_rating: u8) -> Result<Vec<Playable>> {
+        let mut stmt = self.reader().prepare_cached(playable_query!(
+            "WHERE p.rating >= ?1
+             ORDER BY p.rating DESC, p.id"
+        ))?;
+        trace!("read_by_rating: query {min_rating}");
+        let out = stmt
+            .query_map(params![min_rating.max(1)], to_playable)?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_by_rating: done {} entries", out.len());
+        Ok(out)
+    }
+
+    fn read_liked_ids(&self) -> Result<HashSet<i64>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT l.playable_id FROM Like l
+             JOIN Playable p ON p.id = l.playable_id
+             WHERE p.deleted_at IS NULL",
+        )?;
+        trace!("read_liked_ids: query");
+        let out = stmt
+            .query_map([], |row| row.get(0))?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<HashSet<i64>>>()?;
+        trace!("read_liked_ids: done {} entries", out.len());
+        Ok(out)
+    }
+
+    /// Bulk append a list of playable items to a playlist.
+    /// Will check if items already exist in the library and append them if they don't.
+    fn bulk_append_to_playlist(
+        &mut self,
+        playlist_id: i64,
+        playables: &[AudioFileDescriptor],
+    ) -> Result<()> {
+        let paths = &playables
+            .iter()
+            .map(|p| p.path.clone())
+            .collect::<Vec<String>>();
+        let existing = self.filter_library_by_paths(paths)?;
+        // none of the items already exist in the library
+        // so we append them and return their ids
+        let ids = if existing.is_empty() {
+            self.bulk_append_to_library(playables)?
+        } else {
+            // some items already exist in the library
+            // so we append only the new ones
+            let existing_paths = existing
+                .iter()
+                .map(|p| p.source_url.clone())
+                .collect::<HashSet<String>>();
+            self.bulk_append_to_library(
+                &playables
+                    .iter()
+                    .filter(|p| !existing_paths.contains(&p.path))
+                    .cloned()
+                    .collect::<Vec<AudioFileDescriptor>>(),
+            )?;
+            // now we are guaranteed that all items are in the library
+            let existing = self.filter_library_by_paths(paths)?;
+            existing.iter().map(|p| p.id).collect()
+        };
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        // the ones already in the playlist are left where they are
+        for id in ids {
+            tx.execute(
+                "INSERT OR IGNORE INTO PlaylistPlayable (playlist_id, playable_id, position)
+                 SELECT ?1, ?2, COALESCE(MAX(position), 0) + 1
+                 FROM PlaylistPlayable WHERE playlist_id = ?1",
+                params![playlist_id, id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn filter_library_by_paths(&self, paths: &[String]) -> Result<Vec<Playable>> {
+        playables_by_paths(&self.conn, paths)
+    }
+
+    // an album is the same in every source, it is kept once per name and artist
+    fn read_local_copies(&self) -> Result<Vec<LocalCopy>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT r.id, r.source_url,
+                    (SELECT l.source_url FROM Playable l
+                      WHERE l.album_id = r.album_id AND l.type_id = 0
+                        AND l.deleted_at IS NULL AND l.title = r.title COLLATE NOCASE
+                      LIMIT 1)
+               FROM Playable r
+              WHERE r.type_id != 0 AND r.deleted_at IS NULL
+                AND r.album_id IN (SELECT p.album_id FROM Playable p
+                                     JOIN Album al ON al.id = p.album_id
+                                    WHERE p.type_id = 0 AND p.deleted_at IS NULL
+                                      AND al.name != '')",
+        )?;
+        trace!("read_local_copies: query");
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(LocalCopy {
+                    playable_id: row.get(0)?,
+                    source_url: row.get(1)?,
+                    path: row.get(2)?,
+                })
+            })?
+            .map(|result| result.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_local_copies: done {} entries", rows.len());
+        Ok(rows)
+    }
+
+    // Settings
+    fn read_setting(&self, key: &str) -> Result<Option<String>> {
+        let mut stmt = self
+            .reader()
+            .prepare_cached("SELECT value FROM Setting WHERE key = ?")?;
+        trace!("read_setting: query {key}");
+        let mut rows = stmt.query([key])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(row.get(0)?))
+        } else {
+            Ok(None)
+        }
+    }
+    fn write_setting(&mut self, key: &str, value: &str) -> Result<()> {
+        trace!("write_setting: execute");
+        self.conn.execute(
+            "INSERT INTO Setting(key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        trace!("write_setting: {key} = {value}");
+        Ok(())
+    }
+
+    // Podcasts
+    // Subscribing to a feed twice only refreshes its title
+    fn create_podcast(&mut self, title: &str, feed_url: &str) -> Result<i64> {
+        let mut stmt = self.conn.prepare_cached(
+            "INSERT INTO Podcast (title, feed_url) VALUES (?, ?)
+             ON CONFLICT(feed_url) DO UPDATE SET title = excluded.title RETURNING id",
+        )?;
+        trace!("create_podcast: query");
+        let mut rows = stmt.query(params![title, feed_url])?;
+        if let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            trace!("create_podcast: added {feed_url} with id {id}");
+            Ok(id)
+        } else {
+            Err(StorageError::QueryError)
+        }
+    }
+    fn delete_podcast(&mut self, podcast_id: i64) -> Result<()> {
+        trace!("delete_podcast: execute");
+        self.conn.execute(
+            "DELETE FROM Episode WHERE podcast_id = ?",
+            params![podcast_id],
+        )?;
+        self.conn
+            .execute("DELETE FROM Podcast WHERE id = ?", params![podcast_id])?;
+        trace!("delete_podcast: removed id {podcast_id}");
+        Ok(())
+    }
+    fn read_podcasts(&self) -> Result<Vec<Podcast>> {
+        let mut stmt = self
+            .reader()
+            .prepare_cached("SELECT id, title, feed_url FROM Podcast ORDER BY title")?;
+        trace!("read_podcasts: query");
+        let out = stmt
+            .query_map((), |row| {
+                Ok(Podcast {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    feed_url: row.get(2)?,
+                })
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_podcasts: done {} entries", out.len());
+        Ok(out)
+    }
+    fn read_episodes(&self, podcast_id: i64) -> Result<Vec<Episode>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT id, podcast_id, title, description, published, audio_url, duration,
+                    local_path, position
+             FROM Episode
+             WHERE podcast_id = ?
+             ORDER BY published DESC",
+        )?;
+        trace!("read_episodes: query");
+        let out = stmt
+            .query_map(params![podcast_id], |row| {
+                Ok(Episode {
+                    id: row.get(0)?,
+                    podcast_id: row.get(1)?,
+                    title: row.get(2)?,
+                    description: row.get(3)?,
+                    published: row.get(4)?,
+                    audio_url: row.get(5)?,
+                    duration: row.get(6)?,
+                    local_path: row.get(7)?,
+                    position: row.get(8)?,
+                })
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_episodes: done {} entries", out.len());
+        Ok(out)
+    }
+    /// Inserts new episodes and refreshes the metadata of known ones.
+    /// Playback positions and downloads are kept.
+    fn bulk_append_to_podcast(
+        &mut self,
+        podcast_id: i64,
+        episodes: &[EpisodeDescriptor],
+    ) -> Result<()> {
+        self.conn.execute("BEGIN IMMEDIATE", ())?;
+        trace!("bulk_append_to_podcast: execute");
+        for episode in episodes {
+            self.conn.execute(
+                "INSERT INTO Episode (podcast_id, guid, title, description, published, audio_url, duration)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)
+                 ON CONFLICT(podcast_id, guid) DO UPDATE SET
+                    title = excluded.title,
+                    description = excluded.description,
+                    published = excluded.published,
+                    audio_url = excluded.audio_url,
+                    duration = excluded.duration",
+                params![
+                    podcast_id,
+                    episode.guid,
+                    episode.title,
+                    episode.description,
+                    episode.published,
+                    episode.audio_url,
+                    episode.duration as i64
+                ],
+            )?;
+        }
+        self.conn.execute("COMMIT", ())?;
+        trace!("bulk_append_to_podcast: done {} entries", episodes.len());
+        Ok(())
+    }
+    fn set_episode_position(&mut self, episode_id: i64, position: u64) -> Result<()> {
+        trace!("set_episode_position: execute");
+        self.conn.execute(
+            "UPDATE Episode SET position = ? WHERE id = ?",
+            params![position as i64, episode_id],
+        )?;
+        Ok(())
+    }
+    fn set_episode_local_path(&mut self, episode_id: i64, path: &str) -> Result<()> {
+        trace!("set_episode_local_path: execute");
+        self.conn.execute(
+            "UPDATE Episode SET local_path = ? WHERE id = ?",
+            params![path, episode_id],
+        )?;
+        trace!("set_episode_local_path: {episode_id} saved to {path}");
+        Ok(())
+    }
+
+    // Discogs watches
+    fn create_watch(
+        &mut self,
+        kind: WatchKind,
+        discogs_id: i64,
+        name: &str,
+        interval_hours: u32,
+    ) -> Result<i64> {
+        let mut stmt = self.conn.prepare_cached(
+            "INSERT INTO Watch (kind, discogs_id, name, interval_hours) VALUES (?, ?, ?, ?)
+             ON CONFLICT(kind, discogs_id) DO UPDATE SET name = excluded.name RETURNING id",
+        )?;
+        trace!("create_watch: query");
+        let mut rows = stmt.query(params![kind.as_str(), discogs_id, name, interval_hours])?;
+        if let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            trace!("create_watch: added {name} with id {id}");
+            Ok(id)
+        } else {
+            Err(StorageError::QueryError)
+        }
+    }
+    fn delete_watch(&mut self, watch_id: i64) -> Result<()> {
+        trace!("delete_watch: execute");
+        self.conn.execute(
+            "DELETE FROM WatchRelease WHERE watch_id = ?",
+            params![watch_id],
+        )?;
+        self.conn
+            .execute("DELETE FROM Watch WHERE id = ?", params![watch_id])?;
+        trace!("delete_watch: removed id {watch_id}");
+        Ok(())
+    }
+    fn read_watches(&self) -> Result<Vec<Watch>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT id, kind, discogs_id, name, interval_hours, checked_at FROM Watch
+             ORDER BY name COLLATE NOCASE",
+        )?;
+        trace!("read_watches: query");
+        let out = stmt
+            .query_map((), |row| {
+                let kind: String = row.get(1)?;
+                Ok(Watch {
+                    id: row.get(0)?,
+                    kind: WatchKind::from(kind.as_str()),
+                    discogs_id: row.get(2)?,
+                    name: row.get(3)?,
+                    interval_hours: row.get(4)?,
+                    checked_at: row.get(5)?,
+                })
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_watches: done {} entries", out.len());
+        Ok(out)
+    }
+    fn set_watch_interval(&mut self, watch_id: i64, interval_hours: u32) -> Result<()> {
+        trace!("set_watch_interval: execute");
+        self.conn.execute(
+            "UPDATE Watch SET interval_hours = ? WHERE id = ?",
+            params![interval_hours, watch_id],
+        )?;
+        Ok(())
+    }
+    /// The releases found by the first check are what the label or artist had already,
+    /// they are stored as seen.
+    fn write_watch_releases(
+        &mut self,
+        watch_id: i64,
+        checked_at: i64,
+        releases: &[WatchReleaseDescriptor],
+    ) -> Result<usize> {
+        self.conn.execute("BEGIN IMMEDIATE", ())?;
+        trace!("write_watch_releases: execute");
+        let known: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM WatchRelease WHERE watch_id = ?",
+            params![watch_id],
+            |row| row.get(0),
+        )?;
+        let seen = known == 0;
+        let mut added = 0;
+        for release in releases {
+            added += self.conn.execute(
+                "INSERT OR IGNORE INTO WatchRelease (watch_id, release_id, title, artist, year, found_at, seen)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    watch_id,
+                    release.release_id,
+                    release.title,
+                    release.artist,
+                    release.year,
+                    checked_at,
+                    seen
+                ],
+            )?;
+        }
+        self.conn.execute(
+            "UPDATE Watch SET checked_at = ? WHERE id = ?",
+            params![checked_at, watch_id],
+        )?;
+        self.conn.execute("COMMIT", ())?;
+        let added = if seen { 0 } else { added };
+        trace!("write_watch_releases: {added} new for {watch_id}");
+        Ok(added)
+    }
+    fn read_new_releases(&self) -> Result<Vec<WatchRelease>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT watch_id, release_id, title, artist, year, found_at FROM WatchRelease
+             WHERE seen = 0
+             ORDER BY found_at DESC, year DESC, title COLLATE NOCASE",
+        )?;
+        trace!("read_new_releases: query");
+        let out = stmt
+            .query_map((), |row| {
+                Ok(WatchRelease {
+                    watch_id: row.get(0)?,
+                    release_id: row.get(1)?,
+                    title: row.get(2)?,
+                    artist: row.get(3)?,
+                    year: row.get(4)?,
+                    found_at: row.get(5)?,
+                })
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_new_releases: done {} entries", out.len());
+        Ok(out)
+    }
+    fn mark_releases_seen(&mut self, watch_id: i64) -> Result<()> {
+        trace!("mark_releases_seen: execute");
+        self.conn.execute(
+            "UPDATE WatchRelease SET seen = 1 WHERE watch_id = ?",
+            params![watch_id],
+        )?;
+        Ok(())
+    }
+
+    // Audiobooks
+    fn set_audiobook(&mut self, playable_id: i64, audiobook: bool) -> Result<()> {
+        trace!("set_audiobook: execute");
+        if audiobook {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO Audiobook (playable_id) VALUES (?)",
+                params![playable_id],
+            )?;
+        } else {
+            self.conn.execute(
+                "DELETE FROM Audiobook WHERE playable_id = ?",
+                params![playable_id],
+            )?;
+        }
+        trace!("set_audiobook: {playable_id} = {audiobook}");
+        Ok(())
+    }
+    fn read_audiobook_position(&self, playable_id: i64) -> Result<Option<u64>> {
+        let mut stmt = self
+            .reader()
+            .prepare_cached("SELECT position FROM Audiobook WHERE playable_id = ?")?;
+        trace!("read_audiobook_position: query");
+        let mut rows = stmt.query([playable_id])?;
+        if let Some(row) = rows.next()? {
+            let position: i64 = row.get(0)?;
+            Ok(Some(position as u64))
+        } else {
+            Ok(None)
+        }
+    }
+    fn set_audiobook_position(&mut self, playable_id: i64, position: u64) -> Result<()> {
+        trace!("set_audiobook_position: execute");
+        self.conn.execute(
+            "UPDATE Audiobook SET position = ? WHERE playable_id = ?",
+            params![position as i64, playable_id],
+        )?;
+        Ok(())
+    }
+    fn read_progress(&self, playable_id: i64, min_duration: i64) -> Result<Option<u64>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT COALESCE(pp.position, 0) FROM Playable p
+             LEFT JOIN PlaybackProgress pp ON pp.playable_id = p.id
+             WHERE p.id = ? AND p.duration >= ?",
+        )?;
+        trace!("read_progress: query");
+        let mut rows = stmt.query(params![playable_id, min_duration])?;
+        if let Some(row) = rows.next()? {
+            let position: i64 = row.get(0)?;
+            Ok(Some(position as u64))
+        } else {
+            Ok(None)
+        }
+    }
+    fn set_progress(&mut self, playable_id: i64, position: u64) -> Result<()> {
+        trace!("set_progress: execute");
+        if position == 0 {
+            self.conn.execute(
+                "DELETE FROM PlaybackProgress WHERE playable_id = ?",
+                params![playable_id],
+            )?;
+        } else {
+            self.conn.execute(
+                "INSERT INTO PlaybackProgress (playable_id, position) VALUES (?, ?)
+                 ON CONFLICT (playable_id) DO UPDATE SET position = excluded.position",
+                params![playable_id, position as i64],
+            )?;
+        }
+        Ok(())
+    }
+    fn read_release_chapters(&self, playable_id: i64) -> Result<Vec<(u64, String)>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT start, title FROM ReleaseChapter WHERE playable_id = ? ORDER BY start",
+        )?;
+        trace!("read_release_chapters: query");
+        let chapters = stmt
+            .query_map(params![playable_id], |row| {
+                Ok((row.get::<_, i64>(0)? as u64, row.get(1)?))
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(chapters)
+    }
+    fn set_release_chapters(&mut self, playable_id: i64, chapters: &[(u64, String)]) -> Result<()> {
+        self.conn.execute("BEGIN IMMEDIATE", ())?;
+        trace!("set_release_chapters: execute");
+        self.conn.execute(
+            "DELETE FROM ReleaseChapter WHERE playable_id = ?",
+            params![playable_id],
+        )?;
+        for (start, title) in chapters {
+            self.conn.execute(
+                "INSERT OR IGNORE INTO ReleaseChapter (playable_id, start, title) VALUES (?, ?, ?)",
+                params![playable_id, *start as i64, title],
+            )?;
+        }
+        self.conn.execute("COMMIT", ())?;
+        Ok(())
+    }
+    fn read_bookmarks(&self, playable_id: i64) -> Result<Vec<Bookmark>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT id, position, note FROM Bookmark WHERE playable_id = ? ORDER BY position, id",
+        )?;
+        trace!("read_bookmarks: query");
+        let bookmarks = stmt
+            .query_map(params![playable_id], |row| {
+                Ok(Bookmark {
+                    id: row.get(0)?,
+                    position: row.get::<_, i64>(1)? as u64,
+                    note: row.get(2)?,
+                })
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(bookmarks)
+    }
+    fn add_bookmark(&mut self, playable_id: i64, position: u64, note: &str) -> Result<i64> {
+        trace!("add_bookmark: execute");
+        self.conn.execute(
+            "INSERT INTO Bookmark (playable_id, position, note) VALUES (?, ?, ?)",
+            params![playable_id, position as i64, note],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+    fn delete_bookmark(&mut self, bookmark_id: i64) -> Result<()> {
+        trace!("delete_bookmark: execute");
+        self.conn
+            .execute("DELETE FROM Bookmark WHERE id = ?", params![bookmark_id])?;
+        Ok(())
+    }
+
+    // Custom fields
+    fn read_custom_fields(&self) -> Result<Vec<CustomField>> {
+        let mut stmt = self
+            .reader()
+            .prepare_cached("SELECT id, name FROM CustomField ORDER BY name COLLATE NOCASE")?;
+        trace!("read_custom_fields: query");
+        let fields = stmt
+            .query_map((), |row| {
+                Ok(CustomField {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                })
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(fields)
+    }
+    fn create_custom_field(&mut self, name: &str) -> Result<i64> {
+        let mut stmt = self.conn.prepare_cached(
+            "INSERT INTO CustomField (name) VALUES (?) ON CONFLICT(name) DO NOTHING RETURNING id",
+        )?;
+        trace!("create_custom_field: query");
+        let mut rows = stmt.query(params![name.trim()])?;
+        match rows.next()? {
+            Some(row) => Ok(row.get(0)?),
+            None => Err(StorageError::DuplicateEntry),
+        }
+    }
+    fn rename_custom_field(&mut self, field_id: i64, name: &str) -> Result<()> {
+        trace!("rename_custom_field: execute");
+        let renamed = self.conn.execute(
+            "UPDATE OR IGNORE CustomField SET name = ? WHERE id = ?",
+            params![name.trim(), field_id],
+        )?;
+        // another field has the name
+        if renamed == 0 {
+            return Err(StorageError::DuplicateEntry);
+        }
+        Ok(())
+    }
+    fn delete_custom_field(&mut self, field_id: i64) -> Result<()> {
+        trace!("delete_custom_field: execute");
+        self.conn
+            .execute("DELETE FROM CustomField WHERE id = ?", params![field_id])?;
+        Ok(())
+    }
+    fn read_field_values(&self) -> Result<Vec<(i64, i64, String)>> {
+        let mut stmt = self
+            .reader()
+            .prepare_cached("SELECT playable_id, field_id, value FROM FieldValue")?;
+        trace!("read_field_values: query");
+        let values = stmt
+            .query_map((), |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_field_values: done {} entries", values.len());
+        Ok(values)
+    }
+    fn set_field_value(&mut self, playable_id: i64, field_id: i64, value: &str) -> Result<()> {
+        trace!("set_field_value: execute");
+        if value.trim().is_empty() {
+            self.conn.execute(
+                "DELETE FROM FieldValue WHERE playable_id = ? AND field_id = ?",
+                params![playable_id, field_id],
+            )?;
+        } else {
+            self.conn.execute(
+                "INSERT INTO FieldValue (playable_id, field_id, value) VALUES (?, ?, ?)
+                 ON CONFLICT (playable_id, field_id) DO UPDATE SET value = excluded.value",
+                params![playable_id, field_id, value.trim()],
+            )?;
+        }
+        Ok(())
+    }
+
+    // Albums
+    fn read_album(&self, name: &str) -> Result<Album> {
+        let mut stmt = self.reader().prepare_cached(playable_query!(
+            ", p.disc_number, p.track_number",
+            "(SELECT * FROM Playable WHERE deleted_at IS NULL)",
+            "WHERE al.name = ?1
+             ORDER BY p.disc_number IS NULL, p.disc_number, p.track_number IS NULL,
+                      p.track_number, p.title COLLATE NOCASE"
+        ))?;
+        trace!("read_album: query {name}");
+        let mut album = Album {
+            name: name.to_string(),
+            ..Album::default()
+        };
+        let mut rows = stmt.query(params![name])?;
+        while let Some(row) = rows.next()? {
+            let playable = to_playable(row)?;
+            if album.label.is_empty()
+                && let Some(label) = &playable.label
+            {
+                album.label = label.clone();
+            }
+            album.tracks.push(AlbumTrack {
+                playable,
+                disc: row.get(15)?,
+                track: row.get(16)?,
+            });
+        }
+        let mut artists = album
+            .tracks
+            .iter()
+            .map(|track| track.playable.artist_name.as_deref().unwrap_or_default());
+        if let Some(first) = artists.next()
+            && artists.all(|artist| artist == first)
+        {
+            album.artist = first.to_string();
+        }
+        album.year = album
+            .tracks
+            .iter()
+            .filter_map(|track| track.playable.year)
+            .min();
+        trace!("read_album: done {} tracks", album.tracks.len());
+        Ok(album)
+    }
+
+    // Links
+    fn read_links(&self, playable_id: i64) -> Result<Vec<PlayableLink>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT original_id, kind, 0 FROM PlayableLink WHERE playable_id = ?1
+             UNION ALL
+             SELECT playable_id, kind, 1 FROM PlayableLink WHERE original_id = ?1",
+        )?;
+        trace!("read_links: query");
+        let edges = stmt
+            .query_map(params![playable_id], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    LinkKind::from(row.get::<_, String>(1)?.as_str()),
+                    row.get::<_, bool>(2)?,
+                ))
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        let ids: Vec<i64> = edges.iter().map(|(id, _, _)| *id).collect();
+        let mut links: Vec<PlayableLink> = self
+            .read_library_from_ids(&ids)?
+            .into_iter()
+            .filter_map(|playable| {
+                let (_, kind, of_other) = edges.iter().find(|(id, _, _)| *id == playable.id)?;
+                Some(PlayableLink {
+                    kind: *kind,
+                    playable,
+                    of_other: *of_other,
+                })
+            })
+            .collect();
+        links.sort_by_key(|link| link.playable.title.to_lowercase());
+        trace!("read_links: done {} entries", links.len());
+        Ok(links)
+    }
+    fn link_playables(&mut self, playable_id: i64, original_id: i64, kind: LinkKind) -> Result<()> {
+        // a playable is not a remix of itself
+        if playable_id == original_id {
+            return Err(StorageError::QueryError);
+        }
+        self.conn.execute("BEGIN IMMEDIATE", ())?;
+        trace!("link_playables: execute");
+        self.conn.execute(
+            "DELETE FROM PlayableLink WHERE (playable_id = ?1 AND original_id = ?2)
+             OR (playable_id = ?2 AND original_id = ?1)",
+            params![playable_id, original_id],
+        )?;
+        self.conn.execute(
+            "INSERT INTO PlayableLink (playable_id, original_id, kind) VALUES (?, ?, ?)",
+            params![playable_id, original_id, kind.as_str()],
+        )?;
+        self.conn.execute("COMMIT", ())?;
+        Ok(())
+    }
+    fn unlink_playables(&mut self, playable_id: i64, other_id: i64) -> Result<()> {
+        trace!("unlink_playables: execute");
+        self.conn.execute(
+            "DELETE FROM PlayableLink WHERE (playable_id = ?1 AND original_id = ?2)
+             OR (playable_id = ?2 AND original_id = ?1)",
+            params![playable_id, other_id],
+        )?;
+        Ok(())
+    }
+
+    // Import history
+    fn create_import(
+        &mut self,
+        source: &str,
+        playlist_id: Option<i64>,
+        file_count: usize,
+        playable_ids: &[i64],
+    ) -> Result<i64> {
+        trace!("create_import: execute");
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute(
+            "INSERT INTO ImportBatch (source, playlist_id, file_count) VALUES (?, ?, ?)",
+            params![source, playlist_id, file_count as i64],
+        )?;
+        let import_id = tx.last_insert_rowid();
+        for playable_id in playable_ids {
+            tx.execute(
+                "INSERT OR IGNORE INTO ImportBatchPlayable (batch_id, playable_id) VALUES (?, ?)",
+                params![import_id, playable_id],
+            )?;
+        }
+        tx.commit()?;
+        trace!(
+            "create_import: batch {import_id} from {source} with {} playables",
+            playable_ids.len()
+        );
+        Ok(import_id)
+    }
+    fn read_imports(&self) -> Result<Vec<ImportBatch>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT b.id, b.imported_at, b.source, pl.name, b.file_count,
+                    (SELECT COUNT(*) FROM ImportBatchPlayable bp
+                     JOIN Playable p ON p.id = bp.playable_id
+                     WHERE bp.batch_id = b.id AND p.deleted_at IS NULL)
+             FROM ImportBatch b
+             LEFT JOIN Playlist pl ON pl.id = b.playlist_id
+             ORDER BY b.imported_at DESC, b.id DESC",
+        )?;
+        trace!("read_imports: query");
+        let out = stmt
+            .query_map((), |row| {
+                Ok(ImportBatch {
+                    id: row.get(0)?,
+                    imported_at: row.get(1)?,
+                    source: row.get(2)?,
+                    playlist_name: row.get(3)?,
+                    file_count: row.get(4)?,
+                    playable_count: row.get(5)?,
+                })
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_imports: done {} entries", out.len());
+        Ok(out)
+    }
+    fn read_import(&self, import_id: i64) -> Result<Vec<Playable>> {
+        let mut stmt = self.reader().prepare_cached(playable_query!(
+            "WHERE p.id IN (SELECT playable_id FROM ImportBatchPlayable WHERE batch_id = ?)"
+        ))?;
+        trace!("read_import: query");
+        let out = stmt
+            .query_map(params![import_id], to_playable)?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_import: done {} entries", out.len());
+        Ok(out)
+    }
+    fn delete_import(&mut self, import_id: i64) -> Result<()> {
+        trace!("delete_import: execute");
+        self.conn.execute(
+            "DELETE FROM ImportBatchPlayable WHERE batch_id = ?",
+            params![import_id],
+        )?;
+        self.conn
+            .execute("DELETE FROM ImportBatch WHERE id = ?", params![import_id])?;
+        trace!("delete_import: removed id {import_id}");
+        Ok(())
+    }
+    fn create_snapshot(&mut self, label: &str) -> Result<i64> {
+        let entries = self.read_snapshot_entries(None)?;
+        let count = |table: &str| -> Result<i64> {
+            Ok(self
+                .conn
+                .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                    row.get(0)
+                })?)
+        };
+        let tag_count = count("Tag")?;
+        let playlist_count = count("Playlist")?;
+        trace!("create_snapshot: execute");
+        self.conn.execute("BEGIN IMMEDIATE", ())?;
+        self.conn.execute(
+            "INSERT INTO Snapshot (label, playable_count, tag_count, playlist_count)
+             VALUES (?, ?, ?, ?)",
+            params![label, entries.len() as i64, tag_count, playlist_count],
+        )?;
+        let snapshot_id = self.conn.last_insert_rowid();
+        for entry in &entries {
+            // a path which is in the library twice is recorded once
+            self.conn.execute(
+                "INSERT OR IGNORE INTO SnapshotEntry (snapshot_id, path, title, artist, album, tags)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                params![
+                    snapshot_id,
+                    entry.path,
+                    entry.title,
+                    entry.artist,
+                    entry.album,
+                    entry.tags.join("\n"),
+                ],
+            )?;
+        }
+        self.conn.execute("COMMIT", ())?;
+        trace!(
+            "create_snapshot: snapshot {snapshot_id} with {} entries",
+            entries.len()
+        );
+        Ok(snapshot_id)
+    }
+    fn read_snapshots(&self) -> Result<Vec<Snapshot>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT id, taken_at, label, playable_count, tag_count, playlist_count
+             FROM Snapshot ORDER BY taken_at DESC, id DESC",
+        )?;
+        trace!("read_snapshots: query");
+        let out = stmt
+            .query_map((), |row| {
+                Ok(Snapshot {
+                    id: row.get(0)?,
+                    taken_at: row.get(1)?,
+                    label: row.get(2)?,
+                    playable_count: row.get(3)?,
+                    tag_count: row.get(4)?,
+                    playlist_count: row.get(5)?,
+                })
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<Vec<_>>>()?;
+        trace!("read_snapshots: done {} entries", out.len());
+        Ok(out)
+    }
+    fn read_snapshot_entries(&self, snapshot_id: Option<i64>) -> Result<Vec<SnapshotEntry>> {
+        trace!("read_snapshot_entries: query {snapshot_id:?}");
+        let out = match snapshot_id {
+            Some(snapshot_id) => {
+                let mut stmt = self.reader().prepare_cached(
+                    "SELECT path, title, artist, album, tags FROM SnapshotEntry
+                     WHERE snapshot_id = ?",
+                )?;
+                stmt.query_map(params![snapshot_id], to_snapshot_entry)?
+                    .map(|r| r.map_err(StorageError::from))
+                    .collect::<Result<Vec<_>>>()?
+            }
+            None => {
+                let mut stmt = self.reader().prepare_cached(
+                    "SELECT p.source_url, p.title, a.name, al.name,
+                            COALESCE((SELECT group_concat(t.name, char(10)) FROM PlayableTag pt
+                                      JOIN Tag t ON t.id = pt.tag_id
+                                      WHERE pt.playable_id = p.id), '')
+                     FROM Playable p
+                     LEFT JOIN Artist a  ON p.artist_id = a.id
+                     LEFT JOIN Album al  ON p.album_id  = al.id
+                     WHERE p.deleted_at IS NULL AND p.source_url IS NOT NULL",
+                )?;
+                stmt.query_map((), to_snapshot_entry)?
+                    .map(|r| r.map_err(StorageError::from))
+                    .collect::<Result<Vec<_>>>()?
+            }
+        };
+        trace!("read_snapshot_entries: done {} entries", out.len());
+        Ok(out)
+    }
+    fn delete_snapshot(&mut self, snapshot_id: i64) -> Result<()> {
+        trace!("delete_snapshot: execute");
+        self.conn.execute(
+            "DELETE FROM SnapshotEntry WHERE snapshot_id = ?",
+            params![snapshot_id],
+        )?;
+        self.conn
+            .execute("DELETE FROM Snapshot WHERE id = ?", params![snapshot_id])?;
+        trace!("delete_snapshot: removed id {snapshot_id}");
+        Ok(())
+    }
+    fn read_fingerprints(&self, playable_ids: &[i64]) -> Result<HashMap<i64, String>> {
+        let mut stmt = self.reader().prepare_cached(
+            "SELECT playable_id, fingerprint FROM Fingerprint WHERE playable_id IN rarray(?1)",
+        )?;
+        trace!("read_fingerprints: query");
+        let out = stmt
+            .query_map(params![id_array(playable_ids)], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<HashMap<_, _>>>()?;
+        trace!("read_fingerprints: done {} entries", out.len());
+        Ok(out)
+    }
+    fn set_fingerprint(&mut self, playable_id: i64, fingerprint: &str) -> Result<()> {
+        trace!("set_fingerprint: execute");
+        self.conn.execute(
+            "INSERT INTO Fingerprint (playable_id, fingerprint) VALUES (?1, ?2)
+             ON CONFLICT(playable_id) DO UPDATE SET fingerprint = excluded.fingerprint",
+            params![playable_id, fingerprint],
+        )?;
+        Ok(())
+    }
+    fn relocate(&mut self, playable_id: i64, path: &str) -> Result<()> {
+        trace!("relocate: execute");
+        self.conn.execute(
+            "UPDATE Playable SET source_url = ?2 WHERE id = ?1",
+            params![playable_id, path],
+        )?;
+        // the fingerprint was of the old file
+        self.conn.execute(
+            "DELETE FROM Fingerprint WHERE playable_id = ?",
+            params![playable_id],
+        )?;
+        Ok(())
+    }
+    fn read_offline_copies(&self) -> Result<HashMap<i64, String>> {
+        let mut stmt = self
+            .reader()
+            .prepare_cached("SELECT playable_id, path FROM OfflineCopy")?;
+        trace!("read_offline_copies: query");
+        let out = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .map(|r| r.map_err(StorageError::from))
+            .collect::<Result<HashMap<_, _>>>()?;
+        trace!("read_offline_copies: done {} entries", out.len());
+        Ok(out)
+    }
+    fn set_offline_copy(&mut self, playable_id: i64, path: &str) -> Result<()> {
+        trace!("set_offline_copy: execute");
+        self.conn.execute(
+            "INSERT INTO OfflineCopy (playable_id, path) VALUES (?1, ?2)
+             ON CONFLICT(playable_id) DO UPDATE SET path = excluded.path,
+                downloaded_at = excluded.downloaded_at",
+            params![playable_id, path],
+        )?;
         Ok(())
     }
-    fn rename_tag(&mut self, tag_id: i64, name: &str) -> Result<()> {
-        trace!("rename_tag: execute");
+    fn remove_offline_copy(&mut self, playable_id: i64) -> Result<()> {
+        trace!("remove_offline_copy: execute");
         self.conn.execute(
-            "UPDATE Tag SET name = ? WHERE id = ?",
-            params![name, tag_id],
+            "DELETE FROM OfflineCopy WHERE playable_id = ?",
+            params![playable_id],
         )?;
-        trace!("rename_tag: renamed id {tag_id} to {name}");
         Ok(())
     }
+    fn read_database_stats(&self) -> Result<DatabaseStats> {
+        let page_count: u64 = self
+            .conn
+            .pragma_query_value(None, "page_count", |row| row.get(0))?;
+        let page_size: u64 = self
+            .conn
+            .pragma_query_value(None, "page_size", |row| row.get(0))?;
+        // in memory databases have an empty path
+        let wal_size = match self.conn.path().filter(|path| !path.is_empty()) {
+            Some(path) => std::fs::metadata(format!("{path}-wal"))
+                .map(|m| m.len())
+                .unwrap_or(0),
+            None => 0,
+        };
+        let artwork_size = self.conn.query_row(
+            "SELECT COALESCE(SUM(length(artwork)), 0) FROM Playable",
+            [],
+            |row| row.get(0),
+        )?;
 
-    fn bulk_append_to_library(&mut self, playables: &[AudioFileDescriptor]) -> Result<Vec<i64>> {
-        let mut row_ids = Vec::with_capacity(playables.len());
-        self.conn.execute("BEGIN IMMEDIATE", ())?;
-        trace!("bulk_append_to_library: execute");
-        for playable in playables {
-            let res = self.append_to_library(playable);
-            if let Err(StorageError::DuplicateEntry) = res {
-                continue;
-            }
-            let row_id = res?;
-            row_ids.push(row_id);
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT name FROM sqlite_master
+             WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+             ORDER BY name",
+        )?;
+        let tables = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut row_counts = Vec::with_capacity(tables.len());
+        for table in tables {
+            let count =
+                self.conn
+                    .query_row(&format!("SELECT COUNT(*) FROM \"{table}\""), [], |row| {
+                        row.get(0)
+                    })?;
+            row_counts.push((table, count));
         }
-        // flush to disk once
-        self.conn.execute("COMMIT", ())?;
-        trace!("bulk_append_to_library: done");
-        Ok(row_ids)
-    }
+        trace!("read_database_stats: {} tables", row_counts.len());
 
-    fn bulk_remove_from_library(&mut self, playable_ids: &[i64]) -> Result<()> {
-        trace!("bulk_remove_from_library: execute");
-        self.conn.execute("BEGIN IMMEDIATE", ())?;
-        for id in playable_ids {
-            self.remove_from_library(*id)?;
+        Ok(DatabaseStats {
+            file_size: page_count * page_size,
+            wal_size,
+            artwork_size,
+            row_counts,
+        })
+    }
+    fn checkpoint(&mut self) -> Result<()> {
+        trace!("checkpoint: execute");
+        // returns (busy, wal pages, checkpointed pages), in memory databases report -1 pages
+        let busy: i64 = self
+            .conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))?;
+        if busy != 0 {
+            return Err(StorageError::QueryError);
         }
-        self.conn.execute("COMMIT", ())?;
-        trace!("bulk_remove_from_library: done");
         Ok(())
     }
-
-    fn bulk_remove_from_playlist(&mut self, playlist_id: i64, indexes: &[i64]) -> Result<()> {
-        self.conn.execute("BEGIN IMMEDIATE", ())?;
-        for index in indexes {
-            self.remove_from_playlist(playlist_id, *index)?;
-        }
-        self.conn.execute("COMMIT", ())?;
-        trace!("bulk_remove_from_playlist: done");
+    fn optimize(&mut self) -> Result<()> {
+        trace!("optimize: execute");
+        self.conn
+            .execute_batch("ANALYZE; PRAGMA optimize; VACUUM;")?;
         Ok(())
     }
 
-    fn is_liked(&self, playable_id: i64) -> Result<bool> {
-        let mut stmt = self
-            .conn
-            .prepare("SELECT * FROM Like where playable_id = ?")?;
-        let mut rows = stmt.query([playable_id])?;
-        if (rows.next()?).is_some() {
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+    // Listening history
+    fn record_play(&mut self, playable_id: i64, played_at: i64) -> Result<()> {
+        trace!("record_play: execute");
+        self.conn.execute(
+            "INSERT INTO PlayHistory (playable_id, played_at) VALUES (?, ?)",
+            params![playable_id, played_at],
+        )?;
+        self.conn.execute(
+            "UPDATE Playable SET play_count = play_count + 1, last_played_at = ?2
+             WHERE id = ?1",
+            params![playable_id, played_at],
+        )?;
+        Ok(())
     }
-
-    /// Bulk append a list of playable items to a playlist.
-    /// Will check if items already exist in the library and append them if they don't.
-    fn bulk_append_to_playlist(
-        &mut self,
-        playlist_id: i64,
-        playables: &[AudioFileDescriptor],
-    ) -> Result<()> {
-        let paths = &playables
-            .iter()
-            .map(|p| p.path.clone())
-            .collect::<Vec<String>>();
-        let existing = self.filter_library_by_paths(paths)?;
-        // none of the items already exist in the library
-        // so we append them and return their ids
-        let ids = if existing.is_empty() {
-            self.bulk_append_to_library(playables)?
-        } else {
-            // some items already exist in the library
-            // so we append only the new ones
-            let existing_paths = existing
-                .iter()
-                .map(|p| p.source_url.clone())
-                .collect::<HashSet<String>>();
-            self.bulk_append_to_library(
-                &playables
-                    .iter()
-                    .filter(|p| !existing_paths.contains(&p.path))
-                    .cloned()
-                    .collect::<Vec<AudioFileDescriptor>>(),
-            )?;
-            // now we are guaranteed that all items are in the library
-            let existing = self.filter_library_by_paths(paths)?;
-            existing.iter().map(|p| p.id).collect()
-        };
-        self.conn.execute("BEGIN IMMEDIATE", ())?;
-        for id in ids {
-            let res = self.append_to_playlist(playlist_id, id);
-            if let Err(StorageError::DuplicateEntry) = res {
-                continue;
-            } else {
-                res?;
-            }
-        }
-        self.conn.execute("COMMIT", ())?;
+    fn prune_history(&mut self, played_before: i64) -> Result<usize> {
+        trace!("prune_history: execute");
+        let removed = self.conn.execute(
+            "DELETE FROM PlayHistory WHERE played_at < ?",
+            params![played_before],
+        )?;
+        trace!("prune_history: removed {removed} plays");
+        Ok(removed)
+    }
+    fn clear_history(&mut self) -> Result<()> {
+        trace!("clear_history: execute");
+        self.conn.execute("DELETE FROM PlayHistory", ())?;
         Ok(())
     }
+}
 
-    fn filter_library_by_paths(&self, paths: &[String]) -> Result<Vec<Playable>> {
-        let paths: HashSet<String> = HashSet::from_iter(paths.iter().cloned());
-        let mut stmt = self.conn.prepare(
-            "SELECT p.id, p.title,
-                        a.name  AS artist_name,
-                        al.name AS album_name,
-                        g.name  AS genre_name,
-                        p.duration,
-                        p.source_url,
-                        p.type_id,
-                        p.date_added,
-                        p.artwork
-                 FROM Playable p
-                 LEFT JOIN Artist a  ON p.artist_id = a.id
-                 LEFT JOIN Album al  ON p.album_id   = al.id
-                 LEFT JOIN Genre g   ON p.genre_id   = g.id",
-        )?;
-        trace!("read_library_stream: Query");
-        let rows = stmt
-            .query_map([], to_playable)?
-            .map(|result| result.map_err(StorageError::from))
-            .filter(|r| paths.contains(&r.as_ref().unwrap().source_url))
-            .collect::<Result<Vec<_>>>()?;
-
-        Ok(rows)
+// Brings databases created by older versions up to the current schema, which only creates
+// missing tables.
+fn migrate(conn: &Connection) -> Result<()> {
+    let has_liked_at: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('Like') WHERE name = 'liked_at'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_liked_at {
+        // columns added later can not default to the current time, earlier likes sort last
+        conn.execute(
+            "ALTER TABLE Like ADD COLUMN liked_at INTEGER NOT NULL DEFAULT 0",
+            (),
+        )?;
+    }
+    let has_deleted_at: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('Playable') WHERE name = 'deleted_at'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_deleted_at {
+        conn.execute("ALTER TABLE Playable ADD COLUMN deleted_at INTEGER", ())?;
+    }
+    let has_year: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('Playable') WHERE name = 'year'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_year {
+        // filled for files imported from now on
+        conn.execute("ALTER TABLE Playable ADD COLUMN year INTEGER", ())?;
+    }
+    let has_play_count: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('Playable') WHERE name = 'play_count'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_play_count {
+        conn.execute(
+            "ALTER TABLE Playable ADD COLUMN play_count INTEGER NOT NULL DEFAULT 0",
+            (),
+        )?;
+        conn.execute("ALTER TABLE Playable ADD COLUMN last_played_at INTEGER", ())?;
+        // counted from the history kept so far
+        conn.execute(
+            "UPDATE Playable SET
+                play_count = (SELECT COUNT(*) FROM PlayHistory h WHERE h.playable_id = Playable.id),
+                last_played_at = (
+                    SELECT MAX(played_at) FROM PlayHistory h WHERE h.playable_id = Playable.id
+                )",
+            (),
+        )?;
+    }
+    let has_rating: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('Playable') WHERE name = 'rating'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_rating {
+        conn.execute(
+            "ALTER TABLE Playable ADD COLUMN rating INTEGER NOT NULL DEFAULT 0",
+            (),
+        )?;
+    }
+    let has_bitrate: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('Playable') WHERE name = 'bitrate'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_bitrate {
+        // filled for files imported from now on
+        conn.execute_batch(
+            "ALTER TABLE Playable ADD COLUMN bitrate INTEGER;
+             ALTER TABLE Playable ADD COLUMN sample_rate INTEGER;
+             ALTER TABLE Playable ADD COLUMN lossless INTEGER;",
+        )?;
+    }
+    let has_release_date: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('Playable') WHERE name = 'release_date'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_release_date {
+        // filled for files imported from now on
+        conn.execute("ALTER TABLE Playable ADD COLUMN release_date TEXT", ())?;
+    }
+    let has_notes: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('Playable') WHERE name = 'notes'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_notes {
+        conn.execute("ALTER TABLE Playable ADD COLUMN notes TEXT", ())?;
+    }
+    let has_track_number: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('Playable') WHERE name = 'track_number'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_track_number {
+        // filled for files imported from now on
+        conn.execute_batch(
+            "ALTER TABLE Playable ADD COLUMN track_number INTEGER;
+             ALTER TABLE Playable ADD COLUMN disc_number INTEGER;
+             ALTER TABLE Playable ADD COLUMN label TEXT;",
+        )?;
+    }
+    // older databases in the tests have no playlists
+    let playlist_needs_notes: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 AND SUM(name = 'notes') = 0 FROM pragma_table_info('Playlist')",
+        [],
+        |row| row.get(0),
+    )?;
+    if playlist_needs_notes {
+        conn.execute("ALTER TABLE Playlist ADD COLUMN notes TEXT", ())?;
+    }
+    let has_old_index: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE name = 'PlayableFTS'",
+        [],
+        |row| row.get(0),
+    )?;
+    if has_old_index {
+        // the first index only held titles, artists and albums and was never read, the search
+        // index replacing it is filled with the library so far
+        conn.execute_batch(
+            "DROP TRIGGER IF EXISTS trg_fts_insert;
+             DROP TRIGGER IF EXISTS trg_fts_update;
+             DROP TRIGGER IF EXISTS trg_fts_delete;
+             DROP TABLE PlayableFTS;",
+        )?;
+        conn.execute_batch(FILL_SEARCH)?;
+    }
+    let search_needs_notes: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 AND SUM(name = 'notes') = 0 FROM pragma_table_info('PlayableSearch')",
+        [],
+        |row| row.get(0),
+    )?;
+    if search_needs_notes {
+        // the index and its triggers are made again from the schema with the notes
+        conn.execute_batch(
+            "DROP TRIGGER IF EXISTS trg_search_insert;
+             DROP TRIGGER IF EXISTS trg_search_update;
+             DROP TRIGGER IF EXISTS trg_search_delete;
+             DROP TRIGGER IF EXISTS trg_search_tag_insert;
+             DROP TRIGGER IF EXISTS trg_search_tag_delete;
+             DROP TRIGGER IF EXISTS trg_search_tag_rename;
+             DROP TABLE PlayableSearch;",
+        )?;
+        conn.execute_batch(SCHEMA)?;
+        conn.execute_batch(FILL_SEARCH)?;
     }
+    // created here, the schema runs before the column exists in older databases
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_playable_deleted_at ON Playable (deleted_at)",
+        (),
+    )?;
+    Ok(())
+}
+
+// An FTS5 query matching every word of `text` by prefix. Words are quoted, so operators and
+// punctuation typed in are taken literally. None when nothing is left to search for.
+fn match_query(text: &str) -> Option<String> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+    (!words.is_empty()).then(|| words.join(" "))
 }
 
 pub fn init_storage<T: AsRef<Path>>(path: T) -> Result<LocalStorage> {
-    let conn = if path.as_ref().to_str().unwrap().eq(":memory:") {
+    let path = path.as_ref();
+    if path.to_str().unwrap().eq(":memory:") {
         let conn = Connection::open_in_memory()?;
         conn.execute_batch(SCHEMA)?;
+        migrate(&conn)?;
         conn.pragma_update(None, "foreign_keys", "ON")?;
-        conn
+        array::load_module(&conn)?;
+        Ok(LocalStorage { conn, reader: None })
     } else {
         let conn = Connection::open_with_flags(
             path,
@@ -621,9 +2418,9 @@ pub fn init_storage<T: AsRef<Path>>(path: T) -> Result<LocalStorage> {
                 | OpenFlags::SQLITE_OPEN_NO_MUTEX,
         )?;
         conn.execute_batch(SCHEMA)?;
+        migrate(&conn)?;
         conn.pragma_update(None, "journal_mode", "WAL")?;
         conn.pragma_update(None, "synchronous", "NORMAL")?;
-        conn.pragma_update(None, "locking_mode", "EXCLUSIVE")?;
         // conn.pragma_update(None, "read_uncommitted", &1)?;
         conn.pragma_update(None, "temp_store", "MEMORY")?;
         conn.pragma_update(None, "cache_size", -20000)?; // ~80 MiB
@@ -635,11 +2432,26 @@ pub fn init_storage<T: AsRef<Path>>(path: T) -> Result<LocalStorage> {
 
         // bump statement cache & busy timeout
         conn.set_prepared_statement_cache_capacity(100);
-        conn.busy_timeout(std::time::Duration::from_secs(1))?;
-        conn
-    };
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        array::load_module(&conn)?;
+
+        // opened after the schema exists, a read-only connection can not create it
+        let reader = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        reader.pragma_update(None, "temp_store", "MEMORY")?;
+        reader.pragma_update(None, "cache_size", -20000)?; // ~80 MiB
+        reader.pragma_update(None, "mmap_size", 536870912)?; // 512 MiB
+        reader.set_prepared_statement_cache_capacity(100);
+        reader.busy_timeout(BUSY_TIMEOUT)?;
+        array::load_module(&reader)?;
 
-    Ok(LocalStorage { conn })
+        Ok(LocalStorage {
+            conn,
+            reader: Some(reader),
+        })
+    }
 }
 
 #[cfg(test)]
@@ -650,7 +2462,22 @@ mod tests {
     fn setup() -> LocalStorage {
         let db = Connection::open_in_memory().unwrap();
         db.execute_batch(SCHEMA).unwrap();
-        LocalStorage { conn: db }
+        array::load_module(&db).unwrap();
+        LocalStorage {
+            conn: db,
+            reader: None,
+        }
+    }
+
+    fn episode(guid: &str, published: i64) -> EpisodeDescriptor {
+        EpisodeDescriptor {
+            guid: guid.to_string(),
+            title: format!("title_{guid}"),
+            description: String::new(),
+            published,
+            audio_url: format!("https://example.com/{guid}.mp3"),
+            duration: 100,
+        }
     }
 
     fn local_file(title: &str) -> AudioFileDescriptor {
@@ -661,6 +2488,13 @@ mod tests {
             year: 2021,
             genre: format!("genre_{title}"),
             duration: 100,
+            bitrate: 0,
+            sample_rate: 0,
+            lossless: None,
+            release_date: String::new(),
+            track_number: 0,
+            disc_number: 0,
+            label: String::new(),
             artwork: None,
             path: format!("/tmp/test_{title}.mp3"),
             kind: AudioFileKind::LocalFile,
@@ -680,13 +2514,13 @@ mod tests {
     #[test]
     fn test_maybe_insert_artist() {
         let mut storage = setup();
-        let id = storage.maybe_insert_artist("test");
+        let id = maybe_insert_artist(&storage.conn, "test");
         if let Err(e) = &id {
             panic!("Error: {e}");
         }
         assert!(id.is_ok());
         assert_eq!(id.unwrap(), 1);
-        let id = storage.maybe_insert_artist("test");
+        let id = maybe_insert_artist(&storage.conn, "test");
         if let Err(e) = &id {
             panic!("Error: {e}");
         }
@@ -697,13 +2531,13 @@ mod tests {
     #[test]
     fn test_maybe_insert_genre() {
         let mut storage = setup();
-        let id = storage.maybe_insert_genre("test");
+        let id = maybe_insert_genre(&storage.conn, "test");
         if let Err(e) = &id {
             panic!("Error: {e}");
         }
         assert!(id.is_ok());
         assert_eq!(id.unwrap(), 1);
-        let id = storage.maybe_insert_genre("test");
+        let id = maybe_insert_genre(&storage.conn, "test");
         if let Err(e) = &id {
             panic!("Error: {e}");
         }
@@ -714,18 +2548,18 @@ mod tests {
     #[test]
     fn test_maybe_insert_album() {
         let mut storage = setup();
-        let artist_id = storage.maybe_insert_artist("test");
+        let artist_id = maybe_insert_artist(&storage.conn, "test");
         if let Err(e) = &artist_id {
             panic!("Error: {e}");
         }
         let artist_id = artist_id.unwrap();
-        let id = storage.maybe_insert_album("test", artist_id);
+        let id = maybe_insert_album(&storage.conn, "test", artist_id);
         if let Err(e) = &id {
             panic!("Error: {e}");
         }
         assert!(id.is_ok());
         assert_eq!(id.unwrap(), 1);
-        let id = storage.maybe_insert_album("test", artist_id);
+        let id = maybe_insert_album(&storage.conn, "test", artist_id);
         if let Err(e) = &id {
             panic!("Error: {e}");
         }
@@ -741,6 +2575,25 @@ mod tests {
         assert!(library.unwrap().is_empty());
     }
 
+    #[test]
+    fn test_read_library_page() {
+        let mut storage = setup();
+        let files: Vec<AudioFileDescriptor> =
+            (1..=5).map(|i| local_file(&format!("test{i}"))).collect();
+        storage.bulk_append_to_library(&files).unwrap();
+
+        let ids = |page: Vec<Playable>| page.iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids(storage.read_library_page(0, 2).unwrap()), [1, 2]);
+        assert_eq!(ids(storage.read_library_page(2, 2).unwrap()), [3, 4]);
+        assert_eq!(ids(storage.read_library_page(4, 2).unwrap()), [5]);
+        assert!(storage.read_library_page(5, 2).unwrap().is_empty());
+
+        // playables deleted meanwhile move none of the others into a page read already
+        storage.remove_from_library(1).unwrap();
+        storage.remove_from_library(2).unwrap();
+        assert_eq!(ids(storage.read_library_page(2, 2).unwrap()), [3, 4]);
+    }
+
     #[test]
     fn test_read_library_from_ids() {
         let mut storage = setup();
@@ -760,6 +2613,95 @@ mod tests {
         assert_eq!(library.as_ref().unwrap()[2].id, 3);
     }
 
+    #[test]
+    fn test_read_artwork() {
+        let mut storage = setup();
+        let mut file = local_file("one");
+        file.artwork = Some(vec![1, 2, 3]);
+        let ids = storage
+            .bulk_append_to_library(&[file, local_file("two")])
+            .unwrap();
+
+        // library queries leave the artwork out
+        assert!(
+            storage
+                .read_library()
+                .unwrap()
+                .iter()
+                .all(|p| p.artwork.is_none())
+        );
+        assert_eq!(storage.read_artwork(ids[0]).unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(storage.read_artwork(ids[1]).unwrap(), None);
+        assert_eq!(storage.read_artwork(100).unwrap(), None);
+
+        let without: Vec<i64> = storage
+            .read_without_artwork()
+            .unwrap()
+            .iter()
+            .map(|p| p.id)
+            .collect();
+        assert_eq!(without, [ids[1]]);
+        storage.write_artwork(&[ids[1]], &[4, 5]).unwrap();
+        assert_eq!(storage.read_artwork(ids[1]).unwrap(), Some(vec![4, 5]));
+        assert!(storage.read_without_artwork().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_filter_library_by_paths() {
+        let mut storage = setup();
+        storage
+            .bulk_append_to_library(&[local_file("one"), local_file("two")])
+            .unwrap();
+
+        let found = storage
+            .filter_library_by_paths(&[
+                "/tmp/test_two.mp3".to_string(),
+                "/tmp/missing.mp3".to_string(),
+            ])
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].title, "two");
+        assert!(storage.filter_library_by_paths(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_local_copies() {
+        let mut storage = setup();
+        let remote = |title: &str, album: &str| AudioFileDescriptor {
+            artist: "artist_one".to_string(),
+            album: album.to_string(),
+            path: format!("https://music.example.com/{title}"),
+            kind: AudioFileKind::Subsonic,
+            ..local_file(title)
+        };
+        let ids = storage
+            .bulk_append_to_library(&[
+                local_file("one"),
+                remote("ONE", "album_one"),
+                remote("two", "album_one"),
+                remote("three", "album_three"),
+            ])
+            .unwrap();
+
+        let mut copies = storage.read_local_copies().unwrap();
+        copies.sort_by_key(|copy| copy.playable_id);
+        assert_eq!(
+            copies,
+            [
+                LocalCopy {
+                    playable_id: ids[1],
+                    source_url: "https://music.example.com/ONE".to_string(),
+                    path: Some("/tmp/test_one.mp3".to_string()),
+                },
+                LocalCopy {
+                    playable_id: ids[2],
+                    source_url: "https://music.example.com/two".to_string(),
+                    path: None,
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_append_to_library() {
         let mut storage = setup();
@@ -935,6 +2877,39 @@ mod tests {
         assert_eq!(res.err().unwrap(), StorageError::DuplicateEntry);
     }
 
+    #[test]
+    fn test_move_in_playlist() {
+        let mut storage = setup();
+        let playlist_id = storage.create_playlist("test", None, None).unwrap();
+        for title in ["a", "b", "c", "d"] {
+            let id = storage.append_to_library(&local_file(title)).unwrap();
+            storage.append_to_playlist(playlist_id, id).unwrap();
+        }
+        let titles = |storage: &LocalStorage| -> Vec<String> {
+            storage
+                .read_playlist(playlist_id)
+                .unwrap()
+                .into_iter()
+                .map(|p| p.title)
+                .collect()
+        };
+        assert_eq!(titles(&storage), ["a", "b", "c", "d"]);
+
+        storage.move_in_playlist(playlist_id, 0, 2).unwrap();
+        assert_eq!(titles(&storage), ["b", "c", "a", "d"]);
+        storage.move_in_playlist(playlist_id, 3, 0).unwrap();
+        assert_eq!(titles(&storage), ["d", "b", "c", "a"]);
+        // past the end moves to the end, from past the end is left alone
+        storage.move_in_playlist(playlist_id, 0, 10).unwrap();
+        storage.move_in_playlist(playlist_id, 10, 0).unwrap();
+        assert_eq!(titles(&storage), ["b", "c", "a", "d"]);
+
+        // appended tracks come after the ones moved
+        let id = storage.append_to_library(&local_file("e")).unwrap();
+        storage.append_to_playlist(playlist_id, id).unwrap();
+        assert_eq!(titles(&storage), ["b", "c", "a", "d", "e"]);
+    }
+
     #[test]
     fn test_remove_from_playlist() {
         let mut storage = setup();
@@ -1024,6 +2999,143 @@ mod tests {
         assert_eq!(tag.as_ref().unwrap()[0].id, 1);
     }
 
+    #[test]
+    fn test_read_containers_of_playable() {
+        let mut storage = setup();
+        storage.append_to_library(&local_file("test")).unwrap();
+        storage.append_to_library(&local_file("other")).unwrap();
+        let playlist = storage.create_playlist("sets", None, None).unwrap();
+        storage.create_playlist("empty", None, None).unwrap();
+        let tag = storage.create_tag("dub").unwrap();
+        storage.create_tag("techno").unwrap();
+        storage.append_to_playlist(playlist, 1).unwrap();
+        storage.append_to_tag(tag, 1).unwrap();
+
+        let playlists = storage.read_playlists_containing(1).unwrap();
+        assert_eq!(playlists.len(), 1);
+        assert_eq!(playlists[0].name, "sets");
+        let tags = storage.read_tags_of(1).unwrap();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].name, "dub");
+        assert!(storage.read_playlists_containing(2).unwrap().is_empty());
+        assert!(storage.read_tags_of(2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_tag_intersection() {
+        let mut storage = setup();
+        let _ = storage.create_tag("dark");
+        let _ = storage.create_tag("fast");
+        for name in ["one", "two", "three"] {
+            let _ = storage.append_to_library(&local_file(name));
+        }
+        let _ = storage.append_to_tag(1, 1);
+        let _ = storage.append_to_tag(1, 2);
+        let _ = storage.append_to_tag(2, 2);
+        let _ = storage.append_to_tag(2, 3);
+
+        let both = storage.read_tag_intersection(&[1, 2]).unwrap();
+        assert_eq!(both.len(), 1);
+        assert_eq!(both[0].id, 2);
+
+        assert_eq!(storage.read_tag_intersection(&[1]).unwrap().len(), 2);
+        assert!(
+            storage
+                .read_tag_intersection(&[1, 2, 3])
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_read_tag_prefix() {
+        let mut storage = setup();
+        let _ = storage.create_tag("mood/dark");
+        let _ = storage.create_tag("mood/happy");
+        let _ = storage.create_tag("moody");
+        for name in ["one", "two", "three"] {
+            let _ = storage.append_to_library(&local_file(name));
+        }
+        let _ = storage.append_to_tag(1, 1);
+        let _ = storage.append_to_tag(2, 2);
+        let _ = storage.append_to_tag(3, 3);
+
+        let mood = storage.read_tag_prefix("mood").unwrap();
+        assert_eq!(mood.len(), 2);
+        assert!(mood.iter().all(|p| p.id != 3));
+        assert_eq!(storage.read_tag_prefix("Mood/Dark").unwrap().len(), 1);
+        assert_eq!(storage.read_tag_prefix("moody").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_notes() {
+        let mut storage = setup();
+        let id = storage.append_to_library(&local_file("Knights")).unwrap();
+        assert_eq!(storage.read_notes(id).unwrap(), "");
+
+        storage.set_notes(id, " Bought at Hard Wax, 2019 ").unwrap();
+        assert_eq!(storage.read_notes(id).unwrap(), "Bought at Hard Wax, 2019");
+        let found = storage.search_library("hard wax").unwrap();
+        assert_eq!(found.iter().map(|p| p.id).collect::<Vec<_>>(), [id]);
+
+        storage.set_notes(id, "").unwrap();
+        assert_eq!(storage.read_notes(id).unwrap(), "");
+        assert!(storage.search_library("wax").unwrap().is_empty());
+
+        let playlist = storage.create_playlist("Warm up", None, None).unwrap();
+        storage
+            .set_playlist_notes(playlist, "Plays well after midnight")
+            .unwrap();
+        assert_eq!(
+            storage.read_playlists().unwrap()[0].notes,
+            "Plays well after midnight"
+        );
+    }
+
+    #[test]
+    fn test_search_library() {
+        let mut storage = setup();
+        let titles = |playables: Vec<Playable>| -> Vec<String> {
+            playables.into_iter().map(|p| p.title).collect()
+        };
+        let mut hyperballad = local_file("Hyperballad");
+        hyperballad.artist = "Björk".to_string();
+        let mut army = local_file("Army of Me");
+        army.artist = "Björk".to_string();
+        army.album = "Post".to_string();
+        for file in [&hyperballad, &army, &local_file("Posters")] {
+            let _ = storage.append_to_library(file);
+        }
+        // a title match ranks above an album match, accents need not be typed
+        assert_eq!(
+            titles(storage.search_library("post").unwrap()),
+            ["Posters", "Army of Me"]
+        );
+        assert_eq!(
+            titles(storage.search_library("bjork hyper").unwrap()),
+            ["Hyperballad"]
+        );
+        assert!(storage.search_library(" \" - ").unwrap().is_empty());
+        assert!(storage.search_library("OR army").is_ok());
+
+        let tag = storage.create_tag("mood/dark").unwrap();
+        let _ = storage.append_to_tag(tag, 1);
+        assert_eq!(
+            titles(storage.search_library("dark").unwrap()),
+            ["Hyperballad"]
+        );
+        let _ = storage.rename_tag(tag, "mood/bright");
+        assert!(storage.search_library("dark").unwrap().is_empty());
+        assert_eq!(
+            titles(storage.search_library("bright").unwrap()),
+            ["Hyperballad"]
+        );
+
+        // removed playables are not found
+        let _ = storage.soft_delete(&[1]);
+        assert!(storage.search_library("bright").unwrap().is_empty());
+    }
+
     #[test]
     fn test_remove_from_tag() {
         let mut storage = setup();
@@ -1115,6 +3227,40 @@ mod tests {
         assert_eq!(tags.as_ref().unwrap()[0].name, "test2");
     }
 
+    #[test]
+    fn test_rename_tag_cascades() {
+        let mut storage = setup();
+        let _ = storage.create_tag("mood");
+        let _ = storage.create_tag("mood/dark");
+        let _ = storage.create_tag("mood/dark/heavy");
+        let _ = storage.create_tag("moody");
+
+        let res = storage.rename_tag(1, "vibe");
+        assert!(res.is_ok());
+
+        let tag_names = |storage: &LocalStorage| {
+            let mut names: Vec<String> = storage
+                .read_tags()
+                .unwrap()
+                .into_iter()
+                .map(|t| t.name)
+                .collect();
+            names.sort();
+            names
+        };
+        assert_eq!(
+            tag_names(&storage),
+            vec!["moody", "vibe", "vibe/dark", "vibe/dark/heavy"]
+        );
+
+        let res = storage.rename_tag(2, "vibe/light");
+        assert!(res.is_ok());
+        assert_eq!(
+            tag_names(&storage),
+            vec!["moody", "vibe", "vibe/light", "vibe/light/heavy"]
+        );
+    }
+
     #[test]
     fn test_bulk_append_to_library() {
         let mut storage = setup();
@@ -1132,6 +3278,38 @@ mod tests {
         assert_eq!(ids.as_ref().unwrap()[2], 3);
     }
 
+    #[test]
+    fn test_bulk_append_to_library_rolls_back() {
+        let mut storage = setup();
+        storage
+            .conn
+            .execute_batch(
+                "CREATE TEMP TRIGGER fail BEFORE INSERT ON Playable WHEN NEW.title = 'bad'
+                 BEGIN SELECT RAISE(ABORT, 'bad file'); END;",
+            )
+            .unwrap();
+        // the chunk of the failed file is rolled back, the ones before it are kept
+        let mut files: Vec<AudioFileDescriptor> = (0..WRITE_CHUNK)
+            .map(|i| local_file(&format!("test{i}")))
+            .collect();
+        files.extend([local_file("next"), local_file("bad")]);
+        assert!(storage.bulk_append_to_library(&files).is_err());
+        assert_eq!(storage.read_library().unwrap().len(), WRITE_CHUNK);
+        assert!(
+            storage
+                .filter_library_by_paths(&[files[WRITE_CHUNK].path.clone()])
+                .unwrap()
+                .is_empty()
+        );
+
+        // no transaction is left open on the connection
+        let ids = storage
+            .bulk_append_to_library(&[local_file("next")])
+            .unwrap();
+        assert_eq!(ids.len(), 1);
+        assert_eq!(storage.read_library().unwrap().len(), WRITE_CHUNK + 1);
+    }
+
     #[test]
     fn test_bulk_remove_from_library() {
         let mut storage = setup();
@@ -1168,6 +3346,82 @@ mod tests {
         assert_eq!(playlist.as_ref().unwrap().len(), 0);
     }
 
+    #[test]
+    fn test_soft_delete() {
+        let mut storage = setup();
+        let ids = storage
+            .bulk_append_to_library(&[local_file("test1"), local_file("test2")])
+            .unwrap();
+        let playlist_id = storage.create_playlist("test", None, None).unwrap();
+        storage.append_to_playlist(playlist_id, ids[0]).unwrap();
+        storage.append_like(ids[0]).unwrap();
+
+        storage.soft_delete(&ids[..1]).unwrap();
+        assert_eq!(storage.read_library().unwrap().len(), 1);
+        assert!(storage.read_likes().unwrap().is_empty());
+        assert!(storage.read_playlist(playlist_id).unwrap().is_empty());
+        let deleted = storage.read_deleted().unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(deleted[0].0.id, ids[0]);
+
+        // comes back with its likes and playlists
+        storage.restore_deleted(&ids[..1]).unwrap();
+        assert_eq!(storage.read_library().unwrap().len(), 2);
+        assert_eq!(storage.read_likes().unwrap().len(), 1);
+        assert_eq!(storage.read_playlist(playlist_id).unwrap().len(), 1);
+        assert!(storage.read_deleted().unwrap().is_empty());
+
+        storage.soft_delete(&ids).unwrap();
+        assert_eq!(storage.purge_deleted(0).unwrap(), 0);
+        assert_eq!(storage.purge_deleted(i64::MAX).unwrap(), 2);
+        assert!(storage.read_deleted().unwrap().is_empty());
+
+        // importing a deleted file again replaces the deleted copy
+        let id = storage.append_to_library(&local_file("test3")).unwrap();
+        storage.soft_delete(&[id]).unwrap();
+        assert!(storage.append_to_library(&local_file("test3")).is_ok());
+        assert!(storage.read_deleted().unwrap().is_empty());
+        assert_eq!(storage.read_library().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_ratings() {
+        let mut storage = setup();
+        let ids = storage
+            .bulk_append_to_library(&[local_file("one"), local_file("two"), local_file("three")])
+            .unwrap();
+        assert!(storage.read_by_rating(0).unwrap().is_empty());
+
+        storage.set_rating(ids[0], 3).unwrap();
+        storage.set_rating(ids[2], 9).unwrap();
+        let rated = storage.read_by_rating(0).unwrap();
+        assert_eq!(
+            rated.iter().map(|p| (p.id, p.rating)).collect::<Vec<_>>(),
+            [(ids[2], MAX_RATING), (ids[0], 3)]
+        );
+        assert_eq!(
+            storage.read_auto_playlist(AutoPlaylist::TopRated).unwrap()[0].id,
+            ids[2]
+        );
+
+        storage.set_rating(ids[2], 0).unwrap();
+        assert_eq!(storage.read_by_rating(4).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_set_genre() {
+        let mut storage = setup();
+        let id = storage.append_to_library(&local_file("one")).unwrap();
+        storage.set_genre(id, " Shoegaze ").unwrap();
+        let library = storage.read_library().unwrap();
+        assert_eq!(library[0].genre_name.as_deref(), Some("Shoegaze"));
+        // the search index follows the new genre
+        assert_eq!(storage.search_library("shoegaze").unwrap().len(), 1);
+
+        storage.set_genre(id, "").unwrap();
+        assert_eq!(storage.read_library().unwrap()[0].genre_name, None);
+    }
+
     #[test]
     fn test_is_liked() {
         let mut storage = setup();
@@ -1189,6 +3443,9 @@ mod tests {
         assert!(storage.is_liked(1).unwrap());
         assert!(!storage.is_liked(2).unwrap());
         assert!(!storage.is_liked(3).unwrap());
+
+        storage.append_like(3).unwrap();
+        assert_eq!(storage.read_liked_ids().unwrap(), HashSet::from([1, 3]));
     }
 
     #[test]
@@ -1270,6 +3527,320 @@ mod tests {
         assert_eq!(library.len(), 3);
     }
 
+    #[test]
+    fn test_watch_releases() {
+        let mut storage = setup();
+        let watch_id = storage
+            .create_watch(WatchKind::Label, 1818, "Warp", 24)
+            .unwrap();
+        let release = |id: i64| WatchReleaseDescriptor {
+            release_id: id,
+            title: format!("Release {id}"),
+            artist: "Various".to_string(),
+            year: 2001,
+        };
+
+        // what the label put out before it was watched is not new
+        assert_eq!(
+            storage
+                .write_watch_releases(watch_id, 100, &[release(1), release(2)])
+                .unwrap(),
+            0
+        );
+        assert!(storage.read_new_releases().unwrap().is_empty());
+        assert_eq!(storage.read_watches().unwrap()[0].checked_at, Some(100));
+
+        assert_eq!(
+            storage
+                .write_watch_releases(watch_id, 200, &[release(1), release(2), release(3)])
+                .unwrap(),
+            1
+        );
+        let new = storage.read_new_releases().unwrap();
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].release_id, 3);
+        assert_eq!(new[0].found_at, 200);
+
+        storage.mark_releases_seen(watch_id).unwrap();
+        assert!(storage.read_new_releases().unwrap().is_empty());
+        storage.set_watch_interval(watch_id, 168).unwrap();
+        assert_eq!(storage.read_watches().unwrap()[0].interval_hours, 168);
+        storage.delete_watch(watch_id).unwrap();
+        assert!(storage.read_watches().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_album() {
+        let mut storage = setup();
+        for (title, disc, track, label) in [
+            ("B2", 2, 2, ""),
+            ("A1", 1, 1, "Warp"),
+            ("Bonus", 0, 0, ""),
+            ("B1", 2, 1, ""),
+        ] {
+            storage
+                .append_to_library(&AudioFileDescriptor {
+                    artist: "Autechre".to_string(),
+                    album: "Amber".to_string(),
+                    year: 1994,
+                    track_number: track,
+                    disc_number: disc,
+                    label: label.to_string(),
+                    ..local_file(title)
+                })
+                .unwrap();
+        }
+        storage.append_to_library(&local_file("Other")).unwrap();
+
+        let album = storage.read_album("Amber").unwrap();
+        let titles: Vec<&str> = album
+            .tracks
+            .iter()
+            .map(|track| track.playable.title.as_str())
+            .collect();
+        // tracks without a place on the release last
+        assert_eq!(titles, ["A1", "B1", "B2", "Bonus"]);
+        assert_eq!(
+            (album.tracks[1].disc, album.tracks[1].track),
+            (Some(2), Some(1))
+        );
+        assert_eq!(album.artist, "Autechre");
+        assert_eq!(album.year, Some(1994));
+        assert_eq!(album.label, "Warp");
+        assert_eq!(album.duration(), 400);
+        assert!(storage.read_album("Unknown").unwrap().tracks.is_empty());
+    }
+
+    #[test]
+    fn test_links() {
+        let mut storage = setup();
+        let original = storage.append_to_library(&local_file("Knights")).unwrap();
+        let remix = storage
+            .append_to_library(&local_file("Knights (Dub)"))
+            .unwrap();
+        let cover = storage.append_to_library(&local_file("Cover")).unwrap();
+        assert!(storage.read_links(original).unwrap().is_empty());
+        assert!(
+            storage
+                .link_playables(original, original, LinkKind::Edit)
+                .is_err()
+        );
+
+        storage
+            .link_playables(remix, original, LinkKind::Edit)
+            .unwrap();
+        // linking again replaces the link, either way
+        storage
+            .link_playables(remix, original, LinkKind::Remix)
+            .unwrap();
+        storage
+            .link_playables(cover, original, LinkKind::Cover)
+            .unwrap();
+        let links = storage.read_links(original).unwrap();
+        let found: Vec<(i64, LinkKind, bool)> = links
+            .iter()
+            .map(|link| (link.playable.id, link.kind, link.of_other))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (cover, LinkKind::Cover, true),
+                (remix, LinkKind::Remix, true)
+            ]
+        );
+        let links = storage.read_links(remix).unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].playable.id, original);
+        assert!(!links[0].of_other);
+
+        storage.unlink_playables(original, remix).unwrap();
+        assert!(storage.read_links(remix).unwrap().is_empty());
+        assert_eq!(storage.read_links(original).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_custom_fields() {
+        let mut storage = setup();
+        let id = storage.append_to_library(&local_file("Knights")).unwrap();
+        let mood = storage.create_custom_field(" Mood ").unwrap();
+        let slot = storage.create_custom_field("Set slot").unwrap();
+        assert!(storage.create_custom_field("mood").is_err());
+        assert!(storage.rename_custom_field(slot, "MOOD").is_err());
+        storage.rename_custom_field(slot, "Slot").unwrap();
+        let names: Vec<String> = storage
+            .read_custom_fields()
+            .unwrap()
+            .into_iter()
+            .map(|field| field.name)
+            .collect();
+        assert_eq!(names, ["Mood", "Slot"]);
+
+        storage.set_field_value(id, mood, "dark").unwrap();
+        storage.set_field_value(id, mood, "driving ").unwrap();
+        storage.set_field_value(id, slot, "closing").unwrap();
+        let mut values = storage.read_field_values().unwrap();
+        values.sort();
+        assert_eq!(
+            values,
+            [
+                (id, mood, "driving".to_string()),
+                (id, slot, "closing".to_string())
+            ]
+        );
+        storage.set_field_value(id, slot, "").unwrap();
+        // the values go with their field
+        storage.delete_custom_field(mood).unwrap();
+        assert!(storage.read_field_values().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_dynamic_playlist() {
+        let mut storage = setup();
+        let mut ids = Vec::new();
+        for (title, genre, year, duration, bitrate, sample_rate, lossless) in [
+            ("One", "Deep House", 1995, 400, 1411, 44_100, true),
+            ("Two", "Techno", 2005, 300, 320, 44_100, false),
+            ("Three", "House", 0, 200, 128, 48_000, false),
+        ] {
+            let id = storage
+                .append_to_library(&AudioFileDescriptor {
+                    genre: genre.to_string(),
+                    year,
+                    duration,
+                    bitrate,
+                    sample_rate,
+                    lossless: Some(lossless),
+                    release_date: String::new(),
+                    ..local_file(title)
+                })
+                .unwrap();
+            ids.push(id);
+        }
+        storage.append_like(ids[1]).unwrap();
+        storage.set_rating(ids[2], 4).unwrap();
+        let tag_id = storage.create_tag("mood/dark").unwrap();
+        storage.append_to_tag(tag_id, ids[0]).unwrap();
+        let mood = storage.create_custom_field("Mood").unwrap();
+        storage.set_field_value(ids[1], mood, "Peak time").unwrap();
+        storage.set_field_value(ids[2], mood, "Warm up").unwrap();
+        let playlist_id = storage
+            .create_playlist("Smart", Some(PlaylistKind::Dynamic), None)
+            .unwrap();
+        let now = crate::util::unix_now();
+        let titles = |storage: &LocalStorage, now: i64| -> Vec<String> {
+            let mut titles: Vec<String> = storage
+                .read_dynamic_playlist(playlist_id, now)
+                .unwrap()
+                .into_iter()
+                .map(|p| p.title)
+                .collect();
+            titles.sort();
+            titles
+        };
+        let rule = |field, text: &str, min, max| PlaylistRule {
+            field,
+            text: text.to_string(),
+            min,
+            max,
+        };
+
+        assert!(titles(&storage, now).is_empty());
+        let cases = [
+            (
+                vec![rule(RuleField::Genre, "house", None, None)],
+                vec!["One", "Three"],
+            ),
+            (
+                vec![
+                    rule(RuleField::Genre, "house", None, None),
+                    rule(RuleField::Duration, "", Some(300), None),
+                ],
+                vec!["One"],
+            ),
+            (
+                vec![rule(RuleField::Year, "", Some(2000), None)],
+                vec!["Two"],
+            ),
+            // unknown years are left out of any year range
+            (
+                vec![rule(RuleField::Year, "", None, None)],
+                vec!["One", "Two"],
+            ),
+            (vec![rule(RuleField::Liked, "", None, None)], vec!["Two"]),
+            (
+                vec![rule(RuleField::Rating, "", Some(3), None)],
+                vec!["Three"],
+            ),
+            (vec![rule(RuleField::Tag, "mood", None, None)], vec!["One"]),
+            (
+                vec![rule(RuleField::Artist, "artist_t", None, None)],
+                vec!["Three", "Two"],
+            ),
+            (
+                vec![rule(RuleField::Added, "", Some(1), None)],
+                vec!["One", "Three", "Two"],
+            ),
+            (
+                vec![rule(RuleField::Bitrate, "", Some(320), None)],
+                vec!["One", "Two"],
+            ),
+            (
+                vec![rule(RuleField::SampleRate, "", Some(44_100), Some(44_100))],
+                vec!["One", "Two"],
+            ),
+            (vec![rule(RuleField::Lossless, "", None, None)], vec!["One"]),
+            (
+                vec![rule(RuleField::Custom, "mood = peak", None, None)],
+                vec!["Two"],
+            ),
+            (
+                vec![rule(RuleField::Custom, "Mood", None, None)],
+                vec!["Three", "Two"],
+            ),
+        ];
+        for (rules, expected) in cases {
+            storage.write_playlist_rules(playlist_id, &rules).unwrap();
+            assert_eq!(storage.read_playlist_rules(playlist_id).unwrap(), rules);
+            assert_eq!(titles(&storage, now), expected);
+        }
+        // a day later nothing was added in the last day
+        assert!(titles(&storage, now + SECONDS_PER_DAY + 1).is_empty());
+    }
+
+    #[test]
+    fn test_playlist_playback() {
+        let mut storage = setup();
+        let playlist_id = storage.create_playlist("Warmup", None, None).unwrap();
+        assert_eq!(
+            storage.read_playlist_playback(playlist_id).unwrap(),
+            PlaylistPlayback::default()
+        );
+
+        let playback = PlaylistPlayback {
+            shuffle: true,
+            fade_ms: Some(0),
+        };
+        storage
+            .write_playlist_playback(playlist_id, &playback)
+            .unwrap();
+        assert_eq!(
+            storage.read_playlist_playback(playlist_id).unwrap(),
+            playback
+        );
+
+        let playback = PlaylistPlayback {
+            shuffle: false,
+            fade_ms: None,
+        };
+        storage
+            .write_playlist_playback(playlist_id, &playback)
+            .unwrap();
+        assert_eq!(
+            storage.read_playlist_playback(playlist_id).unwrap(),
+            playback
+        );
+    }
+
     #[test]
     fn test_clear_playlist() {
         let mut storage = setup();
@@ -1294,4 +3865,448 @@ mod tests {
         let library = storage.read_library().unwrap();
         assert_eq!(library.len(), 2);
     }
+
+    #[test]
+    fn test_settings() {
+        let mut storage = setup();
+        assert_eq!(storage.read_setting("missing").unwrap(), None);
+
+        storage.write_setting("template", "{n}. {title}").unwrap();
+        assert_eq!(
+            storage.read_setting("template").unwrap(),
+            Some("{n}. {title}".to_string())
+        );
+
+        storage.write_setting("template", "{title}").unwrap();
+        assert_eq!(
+            storage.read_setting("template").unwrap(),
+            Some("{title}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_podcasts() {
+        let mut storage = setup();
+        let id = storage
+            .create_podcast("Show", "https://example.com/feed")
+            .unwrap();
+        let same = storage
+            .create_podcast("Renamed Show", "https://example.com/feed")
+            .unwrap();
+        assert_eq!(id, same);
+
+        let podcasts = storage.read_podcasts().unwrap();
+        assert_eq!(podcasts.len(), 1);
+        assert_eq!(podcasts[0].title, "Renamed Show");
+
+        storage.delete_podcast(id).unwrap();
+        assert!(storage.read_podcasts().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_episodes() {
+        let mut storage = setup();
+        let id = storage
+            .create_podcast("Show", "https://example.com/feed")
+            .unwrap();
+        storage
+            .bulk_append_to_podcast(id, &[episode("a", 10), episode("b", 20)])
+            .unwrap();
+
+        let episodes = storage.read_episodes(id).unwrap();
+        assert_eq!(episodes.len(), 2);
+        // newest first
+        assert_eq!(episodes[0].title, "title_b");
+
+        let episode_id = episodes[1].id;
+        storage.set_episode_position(episode_id, 42).unwrap();
+        storage
+            .set_episode_local_path(episode_id, "/tmp/a.mp3")
+            .unwrap();
+
+        // refreshing the feed keeps the position and the download
+        let mut updated = episode("a", 10);
+        updated.title = "new title".to_string();
+        storage
+            .bulk_append_to_podcast(id, &[updated, episode("c", 30)])
+            .unwrap();
+        let episodes = storage.read_episodes(id).unwrap();
+        assert_eq!(episodes.len(), 3);
+        let episode = episodes.iter().find(|e| e.id == episode_id).unwrap();
+        assert_eq!(episode.title, "new title");
+        assert_eq!(episode.position, 42);
+        assert_eq!(episode.local_path.as_deref(), Some("/tmp/a.mp3"));
+    }
+
+    #[test]
+    fn test_audiobooks() {
+        let mut storage = setup();
+        let id = storage.append_to_library(&local_file("Book")).unwrap();
+        assert_eq!(storage.read_audiobook_position(id).unwrap(), None);
+
+        // positions of regular playables are not stored
+        storage.set_audiobook_position(id, 10).unwrap();
+        assert_eq!(storage.read_audiobook_position(id).unwrap(), None);
+
+        storage.set_audiobook(id, true).unwrap();
+        assert_eq!(storage.read_audiobook_position(id).unwrap(), Some(0));
+        storage.set_audiobook_position(id, 1234).unwrap();
+        // marking twice keeps the position
+        storage.set_audiobook(id, true).unwrap();
+        assert_eq!(storage.read_audiobook_position(id).unwrap(), Some(1234));
+
+        storage.set_audiobook(id, false).unwrap();
+        assert_eq!(storage.read_audiobook_position(id).unwrap(), None);
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut storage = setup();
+        let id = storage.append_to_library(&local_file("Mix")).unwrap();
+        assert_eq!(storage.read_progress(id, 120).unwrap(), None);
+        assert_eq!(storage.read_progress(id, 100).unwrap(), Some(0));
+
+        storage.set_progress(id, 42).unwrap();
+        storage.set_progress(id, 64).unwrap();
+        assert_eq!(storage.read_progress(id, 100).unwrap(), Some(64));
+        assert_eq!(storage.read_progress(id, 120).unwrap(), None);
+
+        storage.set_progress(id, 0).unwrap();
+        assert_eq!(storage.read_progress(id, 100).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn test_release_chapters() {
+        let mut storage = setup();
+        let id = storage.append_to_library(&local_file("Album")).unwrap();
+        assert!(storage.read_release_chapters(id).unwrap().is_empty());
+
+        let chapters = vec![(0, "1 Intro".to_string()), (90, "2 Outro".to_string())];
+        storage.set_release_chapters(id, &chapters[1..]).unwrap();
+        storage.set_release_chapters(id, &chapters).unwrap();
+        assert_eq!(storage.read_release_chapters(id).unwrap(), chapters);
+
+        storage.set_release_chapters(id, &[]).unwrap();
+        assert!(storage.read_release_chapters(id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_bookmarks() {
+        let mut storage = setup();
+        let id = storage.append_to_library(&local_file("Mix")).unwrap();
+        assert!(storage.read_bookmarks(id).unwrap().is_empty());
+
+        let drop = storage.add_bookmark(id, 1800, "The drop").unwrap();
+        let intro = storage.add_bookmark(id, 60, "").unwrap();
+        let positions: Vec<(i64, u64)> = storage
+            .read_bookmarks(id)
+            .unwrap()
+            .into_iter()
+            .map(|b| (b.id, b.position))
+            .collect();
+        assert_eq!(positions, [(intro, 60), (drop, 1800)]);
+
+        storage.delete_bookmark(intro).unwrap();
+        let bookmarks = storage.read_bookmarks(id).unwrap();
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].note, "The drop");
+    }
+
+    #[test]
+    fn test_fingerprints() {
+        let mut storage = setup();
+        let ids = storage
+            .bulk_append_to_library(&[local_file("one"), local_file("two")])
+            .unwrap();
+        storage.set_fingerprint(ids[0], "4-abc").unwrap();
+        storage.set_fingerprint(ids[0], "4-def").unwrap();
+
+        let fingerprints = storage.read_fingerprints(&ids).unwrap();
+        assert_eq!(fingerprints.len(), 1);
+        assert_eq!(fingerprints[&ids[0]], "4-def");
+        assert!(storage.read_fingerprints(&[]).unwrap().is_empty());
+
+        storage.relocate(ids[0], "/tmp/test_one.opus").unwrap();
+        assert!(storage.read_fingerprints(&ids).unwrap().is_empty());
+        let playable = &storage.read_library_from_ids(&ids[..1]).unwrap()[0];
+        assert_eq!(playable.source_url, "/tmp/test_one.opus");
+    }
+
+    #[test]
+    fn test_offline_copies() {
+        let mut storage = setup();
+        let ids = storage
+            .bulk_append_to_library(&[local_file("one"), local_file("two")])
+            .unwrap();
+        storage
+            .set_offline_copy(ids[0], "/tmp/offline/1.mp3")
+            .unwrap();
+        storage
+            .set_offline_copy(ids[1], "/tmp/offline/2.mp3")
+            .unwrap();
+        storage
+            .set_offline_copy(ids[1], "/tmp/offline/2.flac")
+            .unwrap();
+        storage.remove_offline_copy(ids[0]).unwrap();
+
+        let copies = storage.read_offline_copies().unwrap();
+        assert_eq!(copies.len(), 1);
+        assert_eq!(copies[&ids[1]], "/tmp/offline/2.flac");
+    }
+
+    #[test]
+    fn test_snapshots() {
+        let mut storage = setup();
+        let ids = storage
+            .bulk_append_to_library(&[local_file("one"), local_file("two")])
+            .unwrap();
+        let tag_id = storage.create_tag("dub").unwrap();
+        storage.append_to_tag(tag_id, ids[0]).unwrap();
+        let first = storage.create_snapshot("before").unwrap();
+        storage.remove_from_library(ids[1]).unwrap();
+        let second = storage.create_snapshot("").unwrap();
+
+        let snapshots = storage.read_snapshots().unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].id, second);
+        assert_eq!(snapshots[1].label, "before");
+        assert_eq!(snapshots[1].playable_count, 2);
+        assert_eq!(snapshots[1].tag_count, 1);
+
+        let mut entries = storage.read_snapshot_entries(Some(first)).unwrap();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, "/tmp/test_one.mp3");
+        assert_eq!(entries[0].artist.as_deref(), Some("artist_one"));
+        assert_eq!(entries[0].tags, ["dub"]);
+        assert!(entries[1].tags.is_empty());
+        assert_eq!(
+            storage.read_snapshot_entries(None).unwrap(),
+            storage.read_snapshot_entries(Some(second)).unwrap()
+        );
+
+        storage.delete_snapshot(first).unwrap();
+        assert_eq!(storage.read_snapshots().unwrap().len(), 1);
+        assert!(
+            storage
+                .read_snapshot_entries(Some(first))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_imports() {
+        let mut storage = setup();
+        let playlist_id = storage.create_playlist("crate", None, None).unwrap();
+        let ids = storage
+            .bulk_append_to_library(&[local_file("one"), local_file("two")])
+            .unwrap();
+        let first = storage.create_import("/music/one", None, 2, &ids).unwrap();
+        let id = storage.append_to_library(&local_file("three")).unwrap();
+        let second = storage
+            .create_import("/music/three.mp3", Some(playlist_id), 1, &[id])
+            .unwrap();
+
+        let imports = storage.read_imports().unwrap();
+        assert_eq!(imports.len(), 2);
+        assert_eq!(imports[0].id, second);
+        assert_eq!(imports[0].playlist_name.as_deref(), Some("crate"));
+        assert_eq!(imports[1].source, "/music/one");
+        assert_eq!(imports[1].playlist_name, None);
+        assert_eq!(imports[1].playable_count, 2);
+
+        assert_eq!(storage.read_import(first).unwrap().len(), 2);
+        storage.remove_from_library(ids[0]).unwrap();
+        assert_eq!(storage.read_import(first).unwrap().len(), 1);
+        assert_eq!(storage.read_imports().unwrap()[1].playable_count, 1);
+
+        storage.delete_import(first).unwrap();
+        assert_eq!(storage.read_imports().unwrap().len(), 1);
+        assert!(storage.read_import(first).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reader_connection() {
+        let path = std::env::temp_dir().join(format!("phonique-reader-{}.db", std::process::id()));
+        let mut storage = init_storage(&path).unwrap();
+        assert!(storage.reader.is_some());
+
+        let id = storage.append_to_library(&local_file("one")).unwrap();
+        storage.append_like(id).unwrap();
+        // writes are visible to the reader as soon as they are committed
+        assert_eq!(storage.read_library().unwrap().len(), 1);
+        assert!(storage.is_liked(id).unwrap());
+        assert!(
+            storage
+                .reader()
+                .execute("DELETE FROM Playable", ())
+                .is_err()
+        );
+
+        drop(storage);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+
+    #[test]
+    fn test_database_stats() {
+        let mut storage = setup();
+        let mut file = local_file("one");
+        file.artwork = Some(vec![0; 100]);
+        storage
+            .bulk_append_to_library(&[file, local_file("two")])
+            .unwrap();
+
+        let stats = storage.read_database_stats().unwrap();
+        assert!(stats.file_size > 0);
+        assert_eq!(stats.wal_size, 0);
+        assert_eq!(stats.artwork_size, 100);
+        let count = |table: &str| {
+            stats
+                .row_counts
+                .iter()
+                .find(|(name, _)| name == table)
+                .map(|(_, count)| *count)
+        };
+        assert_eq!(count("Playable"), Some(2));
+        assert_eq!(count("Tag"), Some(0));
+        assert!(stats.row_counts.is_sorted_by(|a, b| a.0 <= b.0));
+
+        assert!(storage.checkpoint().is_ok());
+        assert!(storage.optimize().is_ok());
+        assert_eq!(storage.read_database_stats().unwrap().artwork_size, 100);
+    }
+
+    #[test]
+    fn test_play_history() {
+        let mut storage = setup();
+        let ids = storage
+            .bulk_append_to_library(&[local_file("one"), local_file("two")])
+            .unwrap();
+        let plays = |storage: &LocalStorage| -> i64 {
+            storage
+                .conn
+                .query_row("SELECT COUNT(*) FROM PlayHistory", [], |row| row.get(0))
+                .unwrap()
+        };
+        storage.record_play(ids[0], 100).unwrap();
+        storage.record_play(ids[0], 200).unwrap();
+        storage.record_play(ids[1], 300).unwrap();
+        assert!(storage.record_play(100, 300).is_err());
+        assert_eq!(plays(&storage), 3);
+
+        // each playable once, at its latest play
+        let recent: Vec<(i64, i64)> = storage
+            .read_recently_played(10)
+            .unwrap()
+            .into_iter()
+            .map(|(playable, played_at)| (playable.id, played_at))
+            .collect();
+        assert_eq!(recent, vec![(ids[1], 300), (ids[0], 200)]);
+        let first = &storage.read_library_from_ids(&ids[..1]).unwrap()[0];
+        assert_eq!((first.play_count, first.last_played_at), (2, Some(200)));
+        assert_eq!(storage.read_recently_played(1).unwrap().len(), 1);
+
+        assert_eq!(storage.prune_history(200).unwrap(), 1);
+        assert_eq!(plays(&storage), 2);
+
+        // history goes along with the playable
+        storage.remove_from_library(ids[1]).unwrap();
+        assert_eq!(plays(&storage), 1);
+
+        storage.clear_history().unwrap();
+        assert_eq!(plays(&storage), 0);
+        // the counts outlive the history
+        let first = &storage.read_library_from_ids(&ids[..1]).unwrap()[0];
+        assert_eq!(first.play_count, 2);
+    }
+
+    #[test]
+    fn test_auto_playlists() {
+        let mut storage = setup();
+        let ids = storage
+            .bulk_append_to_library(&[local_file("one"), local_file("two"), local_file("three")])
+            .unwrap();
+        let read = |storage: &LocalStorage, playlist| -> Vec<i64> {
+            storage
+                .read_auto_playlist(playlist)
+                .unwrap()
+                .iter()
+                .map(|p| p.id)
+                .collect()
+        };
+        assert!(read(&storage, AutoPlaylist::MostPlayed).is_empty());
+        assert_eq!(read(&storage, AutoPlaylist::NeverPlayed), ids);
+
+        storage.record_play(ids[0], 100).unwrap();
+        storage.record_play(ids[2], 100).unwrap();
+        storage.record_play(ids[2], 200).unwrap();
+        assert_eq!(
+            read(&storage, AutoPlaylist::MostPlayed),
+            vec![ids[2], ids[0]]
+        );
+        assert_eq!(read(&storage, AutoPlaylist::NeverPlayed), vec![ids[1]]);
+
+        storage.append_like(ids[1]).unwrap();
+        storage
+            .conn
+            .execute("UPDATE Like SET liked_at = 0", ())
+            .unwrap();
+        storage.append_like(ids[0]).unwrap();
+        assert_eq!(
+            read(&storage, AutoPlaylist::RecentlyLiked),
+            vec![ids[0], ids[1]]
+        );
+
+        // only the last play counts, never played ones are left out
+        let ids_of = |playables: Vec<Playable>| playables.iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(
+            ids_of(storage.read_not_played_since(150).unwrap()),
+            vec![ids[0]]
+        );
+        assert_eq!(
+            ids_of(storage.read_not_played_since(300).unwrap()),
+            vec![ids[0], ids[2]]
+        );
+    }
+
+    #[test]
+    fn test_migrate() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(
+            "CREATE TABLE Like (playable_id INTEGER PRIMARY KEY);
+             INSERT INTO Like (playable_id) VALUES (1);
+             CREATE TABLE Playable (id INTEGER PRIMARY KEY, title TEXT NOT NULL);
+             INSERT INTO Playable (id, title) VALUES (1, 'one'), (2, 'two');
+             CREATE TABLE PlayHistory (playable_id INTEGER NOT NULL, played_at INTEGER NOT NULL);
+             INSERT INTO PlayHistory VALUES (1, 100), (1, 300), (1, 200);",
+        )
+        .unwrap();
+        migrate(&db).unwrap();
+        let liked_at: i64 = db
+            .query_row("SELECT liked_at FROM Like", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(liked_at, 0);
+        // play counts are filled from the history
+        let plays = |id: i64| -> (i64, Option<i64>) {
+            db.query_row(
+                "SELECT play_count, last_played_at FROM Playable WHERE id = ?",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+        };
+        assert_eq!(plays(1), (3, Some(300)));
+        assert_eq!(plays(2), (0, None));
+        let rating: u8 = db
+            .query_row("SELECT rating FROM Playable WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(rating, 0);
+        // running it again leaves the table alone
+        assert!(migrate(&db).is_ok());
+    }
 }
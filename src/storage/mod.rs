@@ -1,7 +1,12 @@
 pub mod local;
+pub mod worker;
 
 use serde::Deserialize;
-use std::fmt;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    pin::Pin,
+};
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, StorageError>;
@@ -13,6 +18,9 @@ pub enum AudioFileKind {
     Dropbox = 2,
     Youtube = 3,
     Stream = 4,
+    WebDav = 5,
+    Subsonic = 6,
+    Jellyfin = 7,
 }
 
 impl TryFrom<i64> for AudioFileKind {
@@ -24,6 +32,9 @@ impl TryFrom<i64> for AudioFileKind {
             2 => Ok(AudioFileKind::Dropbox),
             3 => Ok(AudioFileKind::Youtube),
             4 => Ok(AudioFileKind::Stream),
+            5 => Ok(AudioFileKind::WebDav),
+            6 => Ok(AudioFileKind::Subsonic),
+            7 => Ok(AudioFileKind::Jellyfin),
             _ => Err(StorageError::InvalidPlayableKind),
         }
     }
@@ -46,9 +57,61 @@ pub struct Playable {
     pub source_url: String,
     pub type_id: AudioFileKind,
     pub date_added: i64,
+    // library queries leave it empty, see `Storage::read_artwork`
     pub artwork: Option<Vec<u8>>,
+    #[serde(default)]
+    pub play_count: i64,
+    #[serde(default)]
+    pub last_played_at: Option<i64>,
+    // 0 while unrated, up to `MAX_RATING` stars
+    #[serde(default)]
+    pub rating: u8,
+    #[serde(default)]
+    pub year: Option<u16>,
+    // YYYY-MM-DD, None when only the year is known
+    #[serde(default)]
+    pub release_date: Option<String>,
+    // the record label from the tags
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
+impl Playable {
+    /// The release date, or the year when the day is not known.
+    pub fn released(&self) -> Option<String> {
+        self.release_date
+            .clone()
+            .or_else(|| self.year.map(|year| year.to_string()))
+    }
+}
+
+// a local file with nothing known about it, tests fill in the fields they look at
+#[cfg(test)]
+impl Default for Playable {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            title: String::new(),
+            artist_name: None,
+            album_name: None,
+            genre_name: None,
+            duration: 0,
+            source_url: String::new(),
+            type_id: AudioFileKind::LocalFile,
+            date_added: 0,
+            artwork: None,
+            play_count: 0,
+            last_played_at: None,
+            rating: 0,
+            year: None,
+            release_date: None,
+            label: None,
+        }
+    }
+}
+
+pub const MAX_RATING: u8 = 5;
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct Artist {
     pub id: i64,
@@ -90,6 +153,33 @@ impl fmt::Display for PlaylistKind {
     }
 }
 
+/// Built-in playlists computed from the listening history and likes, they can not be edited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoPlaylist {
+    MostPlayed,
+    RecentlyLiked,
+    NeverPlayed,
+    TopRated,
+}
+
+impl AutoPlaylist {
+    // length of the top lists, never played lists everything
+    pub const LIMIT: i64 = 25;
+    // stars a playable needs to be listed as top rated
+    pub const TOP_RATED: u8 = 4;
+}
+
+impl fmt::Display for AutoPlaylist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutoPlaylist::MostPlayed => write!(f, "Top {} Most Played", Self::LIMIT),
+            AutoPlaylist::RecentlyLiked => write!(f, "Recently Liked"),
+            AutoPlaylist::NeverPlayed => write!(f, "Never Played"),
+            AutoPlaylist::TopRated => write!(f, "Top Rated"),
+        }
+    }
+}
+
 impl From<&str> for PlaylistKind {
     fn from(s: &str) -> Self {
         match s {
@@ -117,6 +207,120 @@ pub struct Playlist {
     pub name: String,
     pub kind: PlaylistKind,
     pub position: Option<i64>,
+    // written by the user, empty for none
+    #[serde(default)]
+    pub notes: String,
+}
+
+/// What a rule of a dynamic playlist looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RuleField {
+    #[default]
+    Genre,
+    Artist,
+    Year,
+    Added,
+    Liked,
+    Tag,
+    Duration,
+    Rating,
+    Bitrate,
+    SampleRate,
+    Lossless,
+    // a custom field, `name=value` in the text of the rule
+    Custom,
+}
+
+impl RuleField {
+    pub const ALL: [RuleField; 12] = [
+        RuleField::Genre,
+        RuleField::Artist,
+        RuleField::Year,
+        RuleField::Added,
+        RuleField::Liked,
+        RuleField::Tag,
+        RuleField::Duration,
+        RuleField::Rating,
+        RuleField::Bitrate,
+        RuleField::SampleRate,
+        RuleField::Lossless,
+        RuleField::Custom,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RuleField::Genre => "genre",
+            RuleField::Artist => "artist",
+            RuleField::Year => "year",
+            RuleField::Added => "added",
+            RuleField::Liked => "liked",
+            RuleField::Tag => "tag",
+            RuleField::Duration => "duration",
+            RuleField::Rating => "rating",
+            RuleField::Bitrate => "bitrate",
+            RuleField::SampleRate => "sample_rate",
+            RuleField::Lossless => "lossless",
+            RuleField::Custom => "custom",
+        }
+    }
+}
+
+impl From<&str> for RuleField {
+    fn from(s: &str) -> Self {
+        match s {
+            "artist" => RuleField::Artist,
+            "year" => RuleField::Year,
+            "added" => RuleField::Added,
+            "liked" => RuleField::Liked,
+            "tag" => RuleField::Tag,
+            "duration" => RuleField::Duration,
+            "rating" => RuleField::Rating,
+            "bitrate" => RuleField::Bitrate,
+            "sample_rate" => RuleField::SampleRate,
+            "lossless" => RuleField::Lossless,
+            "custom" => RuleField::Custom,
+            _ => RuleField::Genre,
+        }
+    }
+}
+
+impl fmt::Display for RuleField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleField::Genre => write!(f, "Genre"),
+            RuleField::Artist => write!(f, "Artist"),
+            RuleField::Year => write!(f, "Year"),
+            RuleField::Added => write!(f, "Added in the last days"),
+            RuleField::Liked => write!(f, "Liked"),
+            RuleField::Tag => write!(f, "Tag"),
+            RuleField::Duration => write!(f, "Duration"),
+            RuleField::Rating => write!(f, "Rated at least"),
+            RuleField::Bitrate => write!(f, "Bitrate"),
+            RuleField::SampleRate => write!(f, "Sample rate"),
+            RuleField::Lossless => write!(f, "Lossless"),
+            RuleField::Custom => write!(f, "Custom field"),
+        }
+    }
+}
+
+/// A condition of a dynamic playlist, the playlist holds the playables meeting all of its
+/// rules. Genre and artist rules match part of the name, a tag rule includes nested tags.
+/// `min` and `max` bound years, durations in seconds, bitrates in kbps and sample rates in Hz,
+/// an added rule keeps its days in `min` and a rating rule its stars.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PlaylistRule {
+    pub field: RuleField,
+    pub text: String,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+}
+
+/// Playback preferences a playlist keeps for itself, applied whenever it is started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PlaylistPlayback {
+    pub shuffle: bool,
+    // in milliseconds, None follows the playback setting and 0 plays gapless
+    pub fade_ms: Option<u64>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -146,17 +350,316 @@ pub struct AudioFileDescriptor {
     pub year: u16,
     pub genre: String,
     pub duration: u64,
+    // in kbps, 0 when unknown
+    pub bitrate: u32,
+    // in Hz, 0 when unknown
+    pub sample_rate: u32,
+    pub lossless: Option<bool>,
+    // YYYY-MM-DD, empty unless the tags have the full date
+    pub release_date: String,
+    // 0 when unknown
+    pub track_number: u32,
+    pub disc_number: u32,
+    // empty when unknown
+    pub label: String,
     pub path: String,
     pub artwork: Option<Vec<u8>>,
     pub kind: AudioFileKind,
 }
 
+#[derive(Deserialize, Debug, Clone)]
+pub struct ImportBatch {
+    pub id: i64,
+    pub imported_at: i64,
+    pub source: String,
+    pub playlist_name: Option<String>,
+    pub file_count: i64,
+    // playables added by the batch which are still in the library
+    pub playable_count: i64,
+}
+
+/// Files scanned to add to the library in one write, recorded as a batch of the import history.
+#[derive(Debug, Clone)]
+pub struct NewImport {
+    pub source: String,
+    // the playlist shown when the files came in, they join it as well
+    pub playlist_id: Option<i64>,
+    pub items: Vec<AudioFileDescriptor>,
+}
+
+impl NewImport {
+    /// Adds the files, returns the ids of the playables which were not in the library yet.
+    /// The files are committed a chunk at a time, those written before an error stay in the
+    /// library and are skipped when they are imported again.
+    pub fn write(&self, storage: &mut (impl Storage + ?Sized)) -> Result<Vec<i64>> {
+        let ids = storage.bulk_append_to_library(&self.items)?;
+        if let Some(playlist_id) = self.playlist_id {
+            storage.bulk_append_to_playlist(playlist_id, &self.items)?;
+        }
+        storage.create_import(&self.source, self.playlist_id, self.items.len(), &ids)?;
+        Ok(ids)
+    }
+}
+
+/// A moment of a track marked by the user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub id: i64,
+    // seconds from the start of the track
+    pub position: u64,
+    pub note: String,
+}
+
+/// A field defined by the user, e.g. `Mood` or `Set slot`, any playable can hold a value of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomField {
+    pub id: i64,
+    pub name: String,
+}
+
+/// A track of an album with its place on the release, None when the tags do not say.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlbumTrack {
+    pub playable: Playable,
+    pub disc: Option<u32>,
+    pub track: Option<u32>,
+}
+
+/// An album as its page shows it, its tracks in disc and track order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Album {
+    pub name: String,
+    // empty when the tracks are by more than one artist
+    pub artist: String,
+    pub year: Option<u16>,
+    // empty when no track has one
+    pub label: String,
+    pub tracks: Vec<AlbumTrack>,
+}
+
+impl Album {
+    /// In seconds.
+    pub fn duration(&self) -> u64 {
+        self.tracks
+            .iter()
+            .map(|track| track.playable.duration.max(0) as u64)
+            .sum()
+    }
+}
+
+/// How a playable relates to the one it is linked to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinkKind {
+    #[default]
+    Remix,
+    Edit,
+    Cover,
+}
+
+impl LinkKind {
+    pub const ALL: [LinkKind; 3] = [LinkKind::Remix, LinkKind::Edit, LinkKind::Cover];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkKind::Remix => "remix",
+            LinkKind::Edit => "edit",
+            LinkKind::Cover => "cover",
+        }
+    }
+}
+
+impl From<&str> for LinkKind {
+    fn from(s: &str) -> Self {
+        match s {
+            "edit" => LinkKind::Edit,
+            "cover" => LinkKind::Cover,
+            _ => LinkKind::Remix,
+        }
+    }
+}
+
+impl fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkKind::Remix => write!(f, "Remix"),
+            LinkKind::Edit => write!(f, "Edit"),
+            LinkKind::Cover => write!(f, "Cover"),
+        }
+    }
+}
+
+/// A playable of another source than the local files whose album is in the local files too.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalCopy {
+    pub playable_id: i64,
+    pub source_url: String,
+    // the local file of the same track, None when the local album lacks it
+    pub path: Option<String>,
+}
+
+/// A playable linked to another, `of_other` when the other is a `kind` of it, otherwise it is
+/// a `kind` of the other.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayableLink {
+    pub kind: LinkKind,
+    pub playable: Playable,
+    pub of_other: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub id: i64,
+    pub taken_at: i64,
+    pub label: String,
+    pub playable_count: i64,
+    pub tag_count: i64,
+    pub playlist_count: i64,
+}
+
+/// One playable as a snapshot saw it, matched to the library by path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotEntry {
+    pub path: String,
+    pub title: String,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    // sorted
+    pub tags: Vec<String>,
+}
+
+// sizes in bytes
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DatabaseStats {
+    pub file_size: u64,
+    // zero for in memory databases and right after a checkpoint
+    pub wal_size: u64,
+    pub artwork_size: u64,
+    // table name and row count, sorted by name
+    pub row_counts: Vec<(String, i64)>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Podcast {
+    pub id: i64,
+    pub title: String,
+    pub feed_url: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Episode {
+    pub id: i64,
+    pub podcast_id: i64,
+    pub title: String,
+    pub description: String,
+    pub published: i64,
+    pub audio_url: String,
+    pub duration: i64,
+    pub local_path: Option<String>,
+    pub position: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct EpisodeDescriptor {
+    pub guid: String,
+    pub title: String,
+    pub description: String,
+    pub published: i64,
+    pub audio_url: String,
+    pub duration: u64,
+}
+
+/// What a Discogs watch follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WatchKind {
+    #[default]
+    Label,
+    Artist,
+}
+
+impl WatchKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WatchKind::Label => "label",
+            WatchKind::Artist => "artist",
+        }
+    }
+}
+
+impl From<&str> for WatchKind {
+    fn from(s: &str) -> Self {
+        match s {
+            "artist" => WatchKind::Artist,
+            _ => WatchKind::Label,
+        }
+    }
+}
+
+impl fmt::Display for WatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchKind::Label => write!(f, "Label"),
+            WatchKind::Artist => write!(f, "Artist"),
+        }
+    }
+}
+
+/// A Discogs label or artist checked for new releases every `interval_hours`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watch {
+    pub id: i64,
+    pub kind: WatchKind,
+    pub discogs_id: i64,
+    pub name: String,
+    pub interval_hours: u32,
+    // None before the first check
+    pub checked_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchRelease {
+    pub watch_id: i64,
+    pub release_id: i64,
+    pub title: String,
+    pub artist: String,
+    // 0 when unknown
+    pub year: u32,
+    pub found_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchReleaseDescriptor {
+    pub release_id: i64,
+    pub title: String,
+    pub artist: String,
+    pub year: u32,
+}
+
 pub trait Storage {
     fn read_library(&self) -> Result<Vec<Playable>>;
+    /// `limit` playables of the library added after the one with the id `after`, in the order
+    /// they were added. 0 starts at the first.
+    fn read_library_page(&self, after: i64, limit: usize) -> Result<Vec<Playable>>;
     fn read_library_from_ids(&self, ids: &[i64]) -> Result<Vec<Playable>>;
+    fn read_artwork(&self, playable_id: i64) -> Result<Option<Vec<u8>>>;
+    /// Playables without artwork, to look it up online.
+    fn read_without_artwork(&self) -> Result<Vec<Playable>>;
+    /// Sets the artwork of the playables, e.g. one found online for their album.
+    fn write_artwork(&mut self, playable_ids: &[i64], artwork: &[u8]) -> Result<()>;
     fn read_likes(&self) -> Result<Vec<Playable>>;
     fn read_playlist(&self, playlist_id: i64) -> Result<Vec<Playable>>;
     fn read_tag(&self, tag_id: i64) -> Result<Vec<Playable>>;
+    /// Playables carrying every one of `tag_ids`.
+    fn read_tag_intersection(&self, tag_ids: &[i64]) -> Result<Vec<Playable>>;
+    /// Playables carrying the tag named `prefix` or any tag nested under it.
+    fn read_tag_prefix(&self, prefix: &str) -> Result<Vec<Playable>>;
+    /// Playables whose title, artist, album, genre or tags match every word of `query`, best
+    /// matches first. Words match by prefix, `beat sat` finds "Beatles - Sat in Your Lap".
+    fn search_library(&self, query: &str) -> Result<Vec<Playable>>;
+    fn read_auto_playlist(&self, playlist: AutoPlaylist) -> Result<Vec<Playable>>;
+    /// Playables which were played, but not since `played_before`.
+    fn read_not_played_since(&self, played_before: i64) -> Result<Vec<Playable>>;
+    /// The last `limit` playables played with the time of their latest play, newest first.
+    fn read_recently_played(&self, limit: usize) -> Result<Vec<(Playable, i64)>>;
 
     fn create_playlist(
         &mut self,
@@ -166,12 +669,30 @@ pub trait Storage {
     ) -> Result<i64>;
     fn delete_playlist(&mut self, playlist_id: i64) -> Result<()>;
     fn rename_playlist(&mut self, playlist_id: i64, name: &str) -> Result<()>;
+    // empty clears the notes
+    fn set_playlist_notes(&mut self, playlist_id: i64, notes: &str) -> Result<()>;
     fn read_playlists(&self) -> Result<Vec<Playlist>>;
+    /// The defaults when the playlist never had its own.
+    fn read_playlist_rules(&self, playlist_id: i64) -> Result<Vec<PlaylistRule>>;
+    fn write_playlist_rules(&mut self, playlist_id: i64, rules: &[PlaylistRule]) -> Result<()>;
+    // the playables meeting every rule of the playlist at `now`, none without rules
+    fn read_dynamic_playlist(&self, playlist_id: i64, now: i64) -> Result<Vec<Playable>>;
+    fn read_playlist_playback(&self, playlist_id: i64) -> Result<PlaylistPlayback>;
+    fn write_playlist_playback(
+        &mut self,
+        playlist_id: i64,
+        playback: &PlaylistPlayback,
+    ) -> Result<()>;
 
     fn create_tag(&mut self, name: &str) -> Result<i64>;
     fn delete_tag(&mut self, tag_id: i64) -> Result<()>;
+    /// Renames the tag and every tag nested under it.
     fn rename_tag(&mut self, tag_id: i64, name: &str) -> Result<()>;
     fn read_tags(&self) -> Result<Vec<Tag>>;
+    /// Tags attached to one playable.
+    fn read_tags_of(&self, playable_id: i64) -> Result<Vec<Tag>>;
+    /// Playlists holding the playable.
+    fn read_playlists_containing(&self, playable_id: i64) -> Result<Vec<Playlist>>;
 
     fn clear_playlist(&mut self, id: i64) -> Result<()>;
 
@@ -183,6 +704,8 @@ pub trait Storage {
     fn remove_from_library(&mut self, id: i64) -> Result<()>;
     fn remove_from_likes(&mut self, playable_id: i64) -> Result<()>;
     fn remove_from_playlist(&mut self, playlist_id: i64, playable_id: i64) -> Result<()>;
+    /// Moves the track at `from` of the playlist, as `read_playlist` lists it, to `to`.
+    fn move_in_playlist(&mut self, playlist_id: i64, from: usize, to: usize) -> Result<()>;
     fn remove_from_tag(&mut self, tag_id: i64, playable_id: i64) -> Result<()>;
 
     fn bulk_append_to_library(&mut self, playables: &[AudioFileDescriptor]) -> Result<Vec<i64>>;
@@ -194,10 +717,147 @@ pub trait Storage {
     fn bulk_remove_from_library(&mut self, playable_ids: &[i64]) -> Result<()>;
     fn bulk_remove_from_playlist(&mut self, playlist_id: i64, indexes: &[i64]) -> Result<()>;
 
+    /// Moves playables to Recently Deleted, every other read leaves them out.
+    fn soft_delete(&mut self, playable_ids: &[i64]) -> Result<()>;
+    fn restore_deleted(&mut self, playable_ids: &[i64]) -> Result<()>;
+    /// Soft deleted playables with the time they were deleted, newest first.
+    fn read_deleted(&self) -> Result<Vec<(Playable, i64)>>;
+    /// Deletes the playables soft deleted before `deleted_before` for good.
+    fn purge_deleted(&mut self, deleted_before: i64) -> Result<usize>;
+
     // fn query_library(&self, )
 
     fn is_liked(&self, playable_id: i64) -> Result<bool>;
+    // clamped to `MAX_RATING`, 0 clears the rating
+    fn set_rating(&mut self, playable_id: i64, rating: u8) -> Result<()>;
+    // the library's genre only, the file keeps its tags
+    fn set_genre(&mut self, playable_id: i64, genre: &str) -> Result<()>;
+    // empty when the playable has no notes
+    fn read_notes(&self, playable_id: i64) -> Result<String>;
+    // empty clears the notes, they are searched with the rest of the playable
+    fn set_notes(&mut self, playable_id: i64, notes: &str) -> Result<()>;
+    /// Playables rated at least `min_rating` stars, best rated first.
+    fn read_by_rating(&self, min_rating: u8) -> Result<Vec<Playable>>;
+    fn read_liked_ids(&self) -> Result<HashSet<i64>>;
     fn filter_library_by_paths(&self, paths: &[String]) -> Result<Vec<Playable>>;
+    /// The playables of other sources whose album is in the local files as well.
+    fn read_local_copies(&self) -> Result<Vec<LocalCopy>>;
+
+    fn read_setting(&self, key: &str) -> Result<Option<String>>;
+    fn write_setting(&mut self, key: &str, value: &str) -> Result<()>;
+
+    fn create_podcast(&mut self, title: &str, feed_url: &str) -> Result<i64>;
+    fn delete_podcast(&mut self, podcast_id: i64) -> Result<()>;
+    fn read_podcasts(&self) -> Result<Vec<Podcast>>;
+    fn read_episodes(&self, podcast_id: i64) -> Result<Vec<Episode>>;
+    fn bulk_append_to_podcast(
+        &mut self,
+        podcast_id: i64,
+        episodes: &[EpisodeDescriptor],
+    ) -> Result<()>;
+    fn set_episode_position(&mut self, episode_id: i64, position: u64) -> Result<()>;
+    fn set_episode_local_path(&mut self, episode_id: i64, path: &str) -> Result<()>;
+
+    fn create_watch(
+        &mut self,
+        kind: WatchKind,
+        discogs_id: i64,
+        name: &str,
+        interval_hours: u32,
+    ) -> Result<i64>;
+    fn delete_watch(&mut self, watch_id: i64) -> Result<()>;
+    fn read_watches(&self) -> Result<Vec<Watch>>;
+    fn set_watch_interval(&mut self, watch_id: i64, interval_hours: u32) -> Result<()>;
+    // stores the releases not known yet and the time of the check, returns how many are new
+    fn write_watch_releases(
+        &mut self,
+        watch_id: i64,
+        checked_at: i64,
+        releases: &[WatchReleaseDescriptor],
+    ) -> Result<usize>;
+    // releases not seen yet, newest first
+    fn read_new_releases(&self) -> Result<Vec<WatchRelease>>;
+    fn mark_releases_seen(&mut self, watch_id: i64) -> Result<()>;
+
+    fn set_audiobook(&mut self, playable_id: i64, audiobook: bool) -> Result<()>;
+    // None when the playable is not an audiobook
+    fn read_audiobook_position(&self, playable_id: i64) -> Result<Option<u64>>;
+    fn set_audiobook_position(&mut self, playable_id: i64, position: u64) -> Result<()>;
+    // None when the playable is shorter than `min_duration` seconds, 0 when it was not left
+    // unfinished
+    fn read_progress(&self, playable_id: i64, min_duration: i64) -> Result<Option<u64>>;
+    // 0 forgets the position
+    fn set_progress(&mut self, playable_id: i64, position: u64) -> Result<()>;
+    // start in seconds and title of each marker, in order of their start
+    fn read_release_chapters(&self, playable_id: i64) -> Result<Vec<(u64, String)>>;
+    // replaces the markers, none unlinks the release
+    fn set_release_chapters(&mut self, playable_id: i64, chapters: &[(u64, String)]) -> Result<()>;
+    // in order of their position
+    fn read_bookmarks(&self, playable_id: i64) -> Result<Vec<Bookmark>>;
+    // the id of the new bookmark
+    fn add_bookmark(&mut self, playable_id: i64, position: u64, note: &str) -> Result<i64>;
+    fn delete_bookmark(&mut self, bookmark_id: i64) -> Result<()>;
+    // by name, ignoring case
+    fn read_custom_fields(&self) -> Result<Vec<CustomField>>;
+    // names are unique ignoring case
+    fn create_custom_field(&mut self, name: &str) -> Result<i64>;
+    fn rename_custom_field(&mut self, field_id: i64, name: &str) -> Result<()>;
+    // the values of the field go with it
+    fn delete_custom_field(&mut self, field_id: i64) -> Result<()>;
+    // playable id, field id and value of every value set
+    fn read_field_values(&self) -> Result<Vec<(i64, i64, String)>>;
+    // empty clears the value
+    fn set_field_value(&mut self, playable_id: i64, field_id: i64, value: &str) -> Result<()>;
+    // the tracks of the album named `name`, empty when there are none
+    fn read_album(&self, name: &str) -> Result<Album>;
+    // the playables linked to one either way, by title
+    fn read_links(&self, playable_id: i64) -> Result<Vec<PlayableLink>>;
+    // `playable_id` is a `kind` of `original_id`, replaces a link between them
+    fn link_playables(&mut self, playable_id: i64, original_id: i64, kind: LinkKind) -> Result<()>;
+    // either way
+    fn unlink_playables(&mut self, playable_id: i64, other_id: i64) -> Result<()>;
+
+    fn create_import(
+        &mut self,
+        source: &str,
+        playlist_id: Option<i64>,
+        file_count: usize,
+        playable_ids: &[i64],
+    ) -> Result<i64>;
+    // newest first
+    fn read_imports(&self) -> Result<Vec<ImportBatch>>;
+    fn read_import(&self, import_id: i64) -> Result<Vec<Playable>>;
+    fn delete_import(&mut self, import_id: i64) -> Result<()>;
+
+    /// Records the paths, metadata and tags of the library as it is now.
+    fn create_snapshot(&mut self, label: &str) -> Result<i64>;
+    /// Newest first.
+    fn read_snapshots(&self) -> Result<Vec<Snapshot>>;
+    /// Entries of a snapshot, or of the library as it is now when None.
+    fn read_snapshot_entries(&self, snapshot_id: Option<i64>) -> Result<Vec<SnapshotEntry>>;
+    fn delete_snapshot(&mut self, snapshot_id: i64) -> Result<()>;
+
+    /// Cached content fingerprints of the given playables, those never fingerprinted are left out.
+    fn read_fingerprints(&self, playable_ids: &[i64]) -> Result<HashMap<i64, String>>;
+    fn set_fingerprint(&mut self, playable_id: i64, fingerprint: &str) -> Result<()>;
+    /// Points a playable to another file, e.g. a converted copy of it.
+    fn relocate(&mut self, playable_id: i64, path: &str) -> Result<()>;
+
+    /// The files remote playables were downloaded to, by playable.
+    fn read_offline_copies(&self) -> Result<HashMap<i64, String>>;
+    fn set_offline_copy(&mut self, playable_id: i64, path: &str) -> Result<()>;
+    fn remove_offline_copy(&mut self, playable_id: i64) -> Result<()>;
+
+    fn read_database_stats(&self) -> Result<DatabaseStats>;
+    // moves the WAL content into the database file and truncates the WAL
+    fn checkpoint(&mut self) -> Result<()>;
+    // refreshes the query planner statistics and reclaims free pages
+    fn optimize(&mut self) -> Result<()>;
+
+    fn record_play(&mut self, playable_id: i64, played_at: i64) -> Result<()>;
+    /// Deletes the plays recorded before `played_before`, returns how many were removed.
+    fn prune_history(&mut self, played_before: i64) -> Result<usize>;
+    fn clear_history(&mut self) -> Result<()>;
 }
 
 pub struct DummyStorage;
@@ -207,10 +867,26 @@ impl Storage for DummyStorage {
         Ok(vec![])
     }
 
+    fn read_library_page(&self, _after: i64, _limit: usize) -> Result<Vec<Playable>> {
+        Ok(vec![])
+    }
+
     fn read_library_from_ids(&self, _ids: &[i64]) -> Result<Vec<Playable>> {
         Ok(vec![])
     }
 
+    fn read_artwork(&self, _playable_id: i64) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    fn read_without_artwork(&self) -> Result<Vec<Playable>> {
+        Ok(vec![])
+    }
+
+    fn write_artwork(&mut self, _playable_ids: &[i64], _artwork: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
     fn read_likes(&self) -> Result<Vec<Playable>> {
         Ok(vec![])
     }
@@ -223,6 +899,30 @@ impl Storage for DummyStorage {
         Ok(vec![])
     }
 
+    fn read_tag_intersection(&self, _tag_ids: &[i64]) -> Result<Vec<Playable>> {
+        Ok(vec![])
+    }
+
+    fn read_tag_prefix(&self, _prefix: &str) -> Result<Vec<Playable>> {
+        Ok(vec![])
+    }
+
+    fn search_library(&self, _query: &str) -> Result<Vec<Playable>> {
+        Ok(vec![])
+    }
+
+    fn read_auto_playlist(&self, _playlist: AutoPlaylist) -> Result<Vec<Playable>> {
+        Ok(vec![])
+    }
+
+    fn read_not_played_since(&self, _played_before: i64) -> Result<Vec<Playable>> {
+        Ok(vec![])
+    }
+
+    fn read_recently_played(&self, _limit: usize) -> Result<Vec<(Playable, i64)>> {
+        Ok(vec![])
+    }
+
     fn create_playlist(
         &mut self,
         _name: &str,
@@ -240,10 +940,38 @@ impl Storage for DummyStorage {
         Ok(())
     }
 
+    fn set_playlist_notes(&mut self, _playlist_id: i64, _notes: &str) -> Result<()> {
+        Ok(())
+    }
+
     fn read_playlists(&self) -> Result<Vec<Playlist>> {
         Ok(vec![])
     }
 
+    fn read_playlist_rules(&self, _playlist_id: i64) -> Result<Vec<PlaylistRule>> {
+        Ok(vec![])
+    }
+
+    fn write_playlist_rules(&mut self, _playlist_id: i64, _rules: &[PlaylistRule]) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_dynamic_playlist(&self, _playlist_id: i64, _now: i64) -> Result<Vec<Playable>> {
+        Ok(vec![])
+    }
+
+    fn read_playlist_playback(&self, _playlist_id: i64) -> Result<PlaylistPlayback> {
+        Ok(PlaylistPlayback::default())
+    }
+
+    fn write_playlist_playback(
+        &mut self,
+        _playlist_id: i64,
+        _playback: &PlaylistPlayback,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     fn create_tag(&mut self, _name: &str) -> Result<i64> {
         Ok(0)
     }
@@ -260,6 +988,14 @@ impl Storage for DummyStorage {
         Ok(vec![])
     }
 
+    fn read_tags_of(&self, _playable_id: i64) -> Result<Vec<Tag>> {
+        Ok(vec![])
+    }
+
+    fn read_playlists_containing(&self, _playable_id: i64) -> Result<Vec<Playlist>> {
+        Ok(vec![])
+    }
+
     fn append_to_library(&mut self, _arg: &AudioFileDescriptor) -> Result<i64> {
         Ok(0)
     }
@@ -288,6 +1024,10 @@ impl Storage for DummyStorage {
         Ok(())
     }
 
+    fn move_in_playlist(&mut self, _playlist_id: i64, _from: usize, _to: usize) -> Result<()> {
+        Ok(())
+    }
+
     fn remove_from_tag(&mut self, _tag_id: i64, _playable_id: i64) -> Result<()> {
         Ok(())
     }
@@ -304,10 +1044,54 @@ impl Storage for DummyStorage {
         Ok(())
     }
 
+    fn soft_delete(&mut self, _playable_ids: &[i64]) -> Result<()> {
+        Ok(())
+    }
+
+    fn restore_deleted(&mut self, _playable_ids: &[i64]) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_deleted(&self) -> Result<Vec<(Playable, i64)>> {
+        Ok(vec![])
+    }
+
+    fn purge_deleted(&mut self, _deleted_before: i64) -> Result<usize> {
+        Ok(0)
+    }
+
     fn is_liked(&self, _playable_id: i64) -> Result<bool> {
         Ok(false)
     }
 
+    fn set_rating(&mut self, _playable_id: i64, _rating: u8) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_genre(&mut self, _playable_id: i64, _genre: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_notes(&self, _playable_id: i64) -> Result<String> {
+        Ok(String::new())
+    }
+
+    fn set_notes(&mut self, _playable_id: i64, _notes: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_by_rating(&self, _min_rating: u8) -> Result<Vec<Playable>> {
+        Ok(vec![])
+    }
+
+    fn read_liked_ids(&self) -> Result<HashSet<i64>> {
+        Ok(HashSet::new())
+    }
+
+    fn read_local_copies(&self) -> Result<Vec<LocalCopy>> {
+        Ok(vec![])
+    }
+
     fn filter_library_by_paths(&self, _paths: &[String]) -> Result<Vec<Playable>> {
         Ok(vec![])
     }
@@ -323,6 +1107,278 @@ impl Storage for DummyStorage {
     fn clear_playlist(&mut self, _id: i64) -> Result<()> {
         Ok(())
     }
+
+    fn read_setting(&self, _key: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn write_setting(&mut self, _key: &str, _value: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn create_podcast(&mut self, _title: &str, _feed_url: &str) -> Result<i64> {
+        Ok(0)
+    }
+
+    fn delete_podcast(&mut self, _podcast_id: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_podcasts(&self) -> Result<Vec<Podcast>> {
+        Ok(vec![])
+    }
+
+    fn read_episodes(&self, _podcast_id: i64) -> Result<Vec<Episode>> {
+        Ok(vec![])
+    }
+
+    fn bulk_append_to_podcast(
+        &mut self,
+        _podcast_id: i64,
+        _episodes: &[EpisodeDescriptor],
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_episode_position(&mut self, _episode_id: i64, _position: u64) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_episode_local_path(&mut self, _episode_id: i64, _path: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn create_watch(
+        &mut self,
+        _kind: WatchKind,
+        _discogs_id: i64,
+        _name: &str,
+        _interval_hours: u32,
+    ) -> Result<i64> {
+        Ok(0)
+    }
+
+    fn delete_watch(&mut self, _watch_id: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_watches(&self) -> Result<Vec<Watch>> {
+        Ok(vec![])
+    }
+
+    fn set_watch_interval(&mut self, _watch_id: i64, _interval_hours: u32) -> Result<()> {
+        Ok(())
+    }
+
+    fn write_watch_releases(
+        &mut self,
+        _watch_id: i64,
+        _checked_at: i64,
+        _releases: &[WatchReleaseDescriptor],
+    ) -> Result<usize> {
+        Ok(0)
+    }
+
+    fn read_new_releases(&self) -> Result<Vec<WatchRelease>> {
+        Ok(vec![])
+    }
+
+    fn mark_releases_seen(&mut self, _watch_id: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn set_audiobook(&mut self, _playable_id: i64, _audiobook: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_audiobook_position(&self, _playable_id: i64) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    fn set_audiobook_position(&mut self, _playable_id: i64, _position: u64) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_progress(&self, _playable_id: i64, _min_duration: i64) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    fn set_progress(&mut self, _playable_id: i64, _position: u64) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_release_chapters(&self, _playable_id: i64) -> Result<Vec<(u64, String)>> {
+        Ok(Vec::new())
+    }
+
+    fn set_release_chapters(
+        &mut self,
+        _playable_id: i64,
+        _chapters: &[(u64, String)],
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_bookmarks(&self, _playable_id: i64) -> Result<Vec<Bookmark>> {
+        Ok(Vec::new())
+    }
+
+    fn add_bookmark(&mut self, _playable_id: i64, _position: u64, _note: &str) -> Result<i64> {
+        Ok(0)
+    }
+
+    fn delete_bookmark(&mut self, _bookmark_id: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_custom_fields(&self) -> Result<Vec<CustomField>> {
+        Ok(vec![])
+    }
+
+    fn create_custom_field(&mut self, _name: &str) -> Result<i64> {
+        Ok(0)
+    }
+
+    fn rename_custom_field(&mut self, _field_id: i64, _name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn delete_custom_field(&mut self, _field_id: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_field_values(&self) -> Result<Vec<(i64, i64, String)>> {
+        Ok(vec![])
+    }
+
+    fn set_field_value(&mut self, _playable_id: i64, _field_id: i64, _value: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_album(&self, _name: &str) -> Result<Album> {
+        Ok(Album::default())
+    }
+
+    fn read_links(&self, _playable_id: i64) -> Result<Vec<PlayableLink>> {
+        Ok(vec![])
+    }
+
+    fn link_playables(
+        &mut self,
+        _playable_id: i64,
+        _original_id: i64,
+        _kind: LinkKind,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    fn unlink_playables(&mut self, _playable_id: i64, _other_id: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn create_import(
+        &mut self,
+        _source: &str,
+        _playlist_id: Option<i64>,
+        _file_count: usize,
+        _playable_ids: &[i64],
+    ) -> Result<i64> {
+        Ok(0)
+    }
+
+    fn read_imports(&self) -> Result<Vec<ImportBatch>> {
+        Ok(vec![])
+    }
+
+    fn read_import(&self, _import_id: i64) -> Result<Vec<Playable>> {
+        Ok(vec![])
+    }
+
+    fn delete_import(&mut self, _import_id: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn create_snapshot(&mut self, _label: &str) -> Result<i64> {
+        Ok(0)
+    }
+
+    fn read_snapshots(&self) -> Result<Vec<Snapshot>> {
+        Ok(vec![])
+    }
+
+    fn read_snapshot_entries(&self, _snapshot_id: Option<i64>) -> Result<Vec<SnapshotEntry>> {
+        Ok(vec![])
+    }
+
+    fn delete_snapshot(&mut self, _snapshot_id: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_fingerprints(&self, _playable_ids: &[i64]) -> Result<HashMap<i64, String>> {
+        Ok(HashMap::new())
+    }
+
+    fn set_fingerprint(&mut self, _playable_id: i64, _fingerprint: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn relocate(&mut self, _playable_id: i64, _path: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_offline_copies(&self) -> Result<HashMap<i64, String>> {
+        Ok(HashMap::new())
+    }
+
+    fn set_offline_copy(&mut self, _playable_id: i64, _path: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn remove_offline_copy(&mut self, _playable_id: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn read_database_stats(&self) -> Result<DatabaseStats> {
+        Ok(DatabaseStats::default())
+    }
+
+    fn checkpoint(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn optimize(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn record_play(&mut self, _playable_id: i64, _played_at: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn prune_history(&mut self, _played_before: i64) -> Result<usize> {
+        Ok(0)
+    }
+
+    fn clear_history(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// What an `AsyncStorage` call resolves to, it can be awaited off the UI thread.
+pub type StorageFuture<T> = Pin<Box<dyn Future<Output = Result<T>> + Send>>;
+
+/// The storage calls which may take long, made without blocking the UI thread. A backend
+/// reached over the network implements it directly, a `Storage` is run on a `Worker`.
+pub trait AsyncStorage: Send + Sync {
+    fn read_library(&self) -> StorageFuture<Vec<Playable>>;
+    fn read_library_page(&self, after: i64, limit: usize) -> StorageFuture<Vec<Playable>>;
+    fn read_library_from_ids(&self, ids: Vec<i64>) -> StorageFuture<Vec<Playable>>;
+    fn read_artwork(&self, playable_id: i64) -> StorageFuture<Option<Vec<u8>>>;
+    fn read_without_artwork(&self) -> StorageFuture<Vec<Playable>>;
+    fn write_artwork(&self, playable_ids: Vec<i64>, artwork: Vec<u8>) -> StorageFuture<()>;
+    fn read_setting(&self, key: String) -> StorageFuture<Option<String>>;
+    fn write_setting(&self, key: String, value: String) -> StorageFuture<()>;
+    /// Writes an import, see `NewImport::write`.
+    fn import(&self, import: NewImport) -> StorageFuture<Vec<i64>>;
 }
 
 #[derive(Debug, Error, PartialEq)]
@@ -337,4 +1393,6 @@ pub enum StorageError {
     PlaylistExists,
     #[error("Duplicate entry")]
     DuplicateEntry,
+    #[error("Storage worker stopped")]
+    WorkerStopped,
 }
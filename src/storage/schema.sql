@@ -48,7 +48,28 @@ CREATE TABLE IF NOT EXISTS Playable (
     source_url TEXT,
     type_id INTEGER NOT NULL,
     date_added INTEGER NOT NULL DEFAULT (strftime ('%s', 'now')),
-    artwork BLOB
+    artwork BLOB,
+    -- release year from the tags, NULL when unknown
+    year INTEGER,
+    -- set while the playable sits in Recently Deleted
+    deleted_at INTEGER,
+    -- finished plays, kept when the history is pruned or cleared
+    play_count INTEGER NOT NULL DEFAULT 0,
+    last_played_at INTEGER,
+    -- stars given by hand, 0 for none up to 5
+    rating INTEGER NOT NULL DEFAULT 0,
+    -- audio properties read on import, NULL when unknown. Bitrate in kbps, sample rate in Hz
+    bitrate INTEGER,
+    sample_rate INTEGER,
+    lossless INTEGER,
+    -- full release date from the tags as YYYY-MM-DD, NULL when they only have the year
+    release_date TEXT,
+    -- written by the user, e.g. where the record was bought, NULL for none
+    notes TEXT,
+    -- the place on the release and its label from the tags, NULL when unknown
+    track_number INTEGER,
+    disc_number INTEGER,
+    label TEXT
 );
 
 -- composite indexes for JOIN+ORDER
@@ -63,9 +84,13 @@ CREATE INDEX IF NOT EXISTS idx_playable_duration ON Playable (duration);
 
 CREATE INDEX IF NOT EXISTS idx_playable_date_added ON Playable (date_added);
 
+-- duplicate checks on import look files up by path
+CREATE INDEX IF NOT EXISTS idx_playable_source_url ON Playable (source_url);
+
 -- 3) Likes table (one LIKE per playable)
 CREATE TABLE IF NOT EXISTS Like (
-    playable_id INTEGER PRIMARY KEY REFERENCES Playable (id) ON DELETE CASCADE
+    playable_id INTEGER PRIMARY KEY REFERENCES Playable (id) ON DELETE CASCADE,
+    liked_at INTEGER NOT NULL DEFAULT (strftime ('%s', 'now'))
 );
 
 -- 4) Playlists + junction
@@ -74,7 +99,9 @@ CREATE TABLE IF NOT EXISTS Playlist (
     parent_id INTEGER,
     name TEXT NOT NULL UNIQUE COLLATE NOCASE,
     kind TEXT CHECK (kind IN ('static', 'dynamic', 'folder')),
-    position INTEGER
+    position INTEGER,
+    -- written by the user, NULL for none
+    notes TEXT
 );
 
 CREATE INDEX IF NOT EXISTS idx_playlist_name ON Playlist (name);
@@ -94,7 +121,31 @@ CREATE TABLE IF NOT EXISTS PlaylistPlayable (
 
 CREATE INDEX IF NOT EXISTS idx_pp_by_playable ON PlaylistPlayable (playable_id);
 
+-- how a playlist plays when it is started, fade_ms is NULL to follow the playback setting
+CREATE TABLE IF NOT EXISTS PlaylistPlayback (
+    playlist_id INTEGER PRIMARY KEY REFERENCES Playlist (id) ON DELETE CASCADE,
+    shuffle INTEGER NOT NULL DEFAULT 0,
+    fade_ms INTEGER
+);
+
+-- rules a dynamic playlist is filled by, a playable has to meet all of them
+CREATE TABLE IF NOT EXISTS PlaylistRule (
+    playlist_id INTEGER NOT NULL REFERENCES Playlist (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    -- 'genre', 'artist', 'year', 'added', 'liked', 'tag', 'duration', 'rating', 'bitrate',
+    -- 'sample_rate' or 'lossless'
+    field TEXT NOT NULL,
+    -- the name genre, artist and tag rules match
+    text TEXT NOT NULL DEFAULT '',
+    -- bounds of year, duration, bitrate and sample rate rules, added rules keep the number of
+    -- days in min
+    min INTEGER,
+    max INTEGER,
+    PRIMARY KEY (playlist_id, position)
+) WITHOUT ROWID;
+
 -- 5) Tags + junction
+-- names double as paths, `mood/dark` is the `dark` tag nested under `mood`
 CREATE TABLE IF NOT EXISTS Tag (
     id INTEGER PRIMARY KEY,
     name TEXT NOT NULL UNIQUE COLLATE NOCASE
@@ -110,84 +161,366 @@ CREATE TABLE IF NOT EXISTS PlayableTag (
 
 CREATE INDEX IF NOT EXISTS idx_pt_by_playable ON PlayableTag (playable_id);
 
--- 6) FTS5 table for text-search on title/artist/album
-CREATE VIRTUAL TABLE IF NOT EXISTS PlayableFTS USING fts5 (
+-- 6) FTS5 index searching the whole library, one row per playable with its id as rowid.
+-- Tags are listed by name, `mood/dark` matches both `mood` and `dark`
+CREATE VIRTUAL TABLE IF NOT EXISTS PlayableSearch USING fts5 (
     title,
-    artist_name,
-    album_name,
-    content = 'Playable', -- external content mode
-    content_rowid = 'id'
+    artist,
+    album,
+    genre,
+    tags,
+    notes,
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
 );
 
--- 7) Triggers to keep the FTS index in sync
-CREATE TRIGGER IF NOT EXISTS trg_fts_insert AFTER INSERT ON Playable BEGIN
+-- 7) Triggers to keep the search index in sync
+CREATE TRIGGER IF NOT EXISTS trg_search_insert AFTER INSERT ON Playable BEGIN
 INSERT INTO
-    PlayableFTS (rowid, title, artist_name, album_name)
-VALUES
+    PlayableSearch (rowid, title, artist, album, genre, tags, notes)
+SELECT
+    p.id,
+    p.title,
+    a.name,
+    al.name,
+    g.name,
     (
-        NEW.id,
-        NEW.title,
-        (
-            SELECT
-                name
-            FROM
-                Artist
-            WHERE
-                id = NEW.artist_id
-        ),
-        (
-            SELECT
-                name
-            FROM
-                Album
-            WHERE
-                id = NEW.album_id
-        )
-    );
+        SELECT
+            group_concat (t.name, ' ')
+        FROM
+            PlayableTag pt
+            JOIN Tag t ON t.id = pt.tag_id
+        WHERE
+            pt.playable_id = p.id
+    ),
+    p.notes
+FROM
+    Playable p
+    LEFT JOIN Artist a ON a.id = p.artist_id
+    LEFT JOIN Album al ON al.id = p.album_id
+    LEFT JOIN Genre g ON g.id = p.genre_id
+WHERE
+    p.id = NEW.id;
 
 END;
 
-CREATE TRIGGER IF NOT EXISTS trg_fts_update AFTER
+CREATE TRIGGER IF NOT EXISTS trg_search_update AFTER
 UPDATE OF title,
 artist_id,
-album_id ON Playable BEGIN
--- delete the old entry
+album_id,
+genre_id,
+notes ON Playable BEGIN
+DELETE FROM PlayableSearch
+WHERE
+    rowid = OLD.id;
+
+INSERT INTO
+    PlayableSearch (rowid, title, artist, album, genre, tags, notes)
+SELECT
+    p.id,
+    p.title,
+    a.name,
+    al.name,
+    g.name,
+    (
+        SELECT
+            group_concat (t.name, ' ')
+        FROM
+            PlayableTag pt
+            JOIN Tag t ON t.id = pt.tag_id
+        WHERE
+            pt.playable_id = p.id
+    ),
+    p.notes
+FROM
+    Playable p
+    LEFT JOIN Artist a ON a.id = p.artist_id
+    LEFT JOIN Album al ON al.id = p.album_id
+    LEFT JOIN Genre g ON g.id = p.genre_id
+WHERE
+    p.id = NEW.id;
+
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_search_delete AFTER DELETE ON Playable BEGIN
+DELETE FROM PlayableSearch
+WHERE
+    rowid = OLD.id;
+
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_search_tag_insert AFTER INSERT ON PlayableTag BEGIN
+DELETE FROM PlayableSearch
+WHERE
+    rowid = NEW.playable_id;
+
 INSERT INTO
-    PlayableFTS (PlayableFTS, rowid)
-VALUES
-    ('delete', OLD.id);
+    PlayableSearch (rowid, title, artist, album, genre, tags, notes)
+SELECT
+    p.id,
+    p.title,
+    a.name,
+    al.name,
+    g.name,
+    (
+        SELECT
+            group_concat (t.name, ' ')
+        FROM
+            PlayableTag pt
+            JOIN Tag t ON t.id = pt.tag_id
+        WHERE
+            pt.playable_id = p.id
+    ),
+    p.notes
+FROM
+    Playable p
+    LEFT JOIN Artist a ON a.id = p.artist_id
+    LEFT JOIN Album al ON al.id = p.album_id
+    LEFT JOIN Genre g ON g.id = p.genre_id
+WHERE
+    p.id = NEW.playable_id;
+
+END;
+
+CREATE TRIGGER IF NOT EXISTS trg_search_tag_delete AFTER DELETE ON PlayableTag BEGIN
+DELETE FROM PlayableSearch
+WHERE
+    rowid = OLD.playable_id;
 
--- insert the new
 INSERT INTO
-    PlayableFTS (rowid, title, artist_name, album_name)
-VALUES
+    PlayableSearch (rowid, title, artist, album, genre, tags, notes)
+SELECT
+    p.id,
+    p.title,
+    a.name,
+    al.name,
+    g.name,
     (
-        NEW.id,
-        NEW.title,
-        (
-            SELECT
-                name
-            FROM
-                Artist
-            WHERE
-                id = NEW.artist_id
-        ),
-        (
-            SELECT
-                name
-            FROM
-                Album
-            WHERE
-                id = NEW.album_id
-        )
-    );
+        SELECT
+            group_concat (t.name, ' ')
+        FROM
+            PlayableTag pt
+            JOIN Tag t ON t.id = pt.tag_id
+        WHERE
+            pt.playable_id = p.id
+    ),
+    p.notes
+FROM
+    Playable p
+    LEFT JOIN Artist a ON a.id = p.artist_id
+    LEFT JOIN Album al ON al.id = p.album_id
+    LEFT JOIN Genre g ON g.id = p.genre_id
+WHERE
+    p.id = OLD.playable_id;
 
 END;
 
-CREATE TRIGGER IF NOT EXISTS trg_fts_delete AFTER DELETE ON Playable BEGIN
+CREATE TRIGGER IF NOT EXISTS trg_search_tag_rename AFTER
+UPDATE OF name ON Tag BEGIN
+DELETE FROM PlayableSearch
+WHERE
+    rowid IN (SELECT playable_id FROM PlayableTag WHERE tag_id = NEW.id);
+
 INSERT INTO
-    PlayableFTS (PlayableFTS, rowid)
-VALUES
-    ('delete', OLD.id);
+    PlayableSearch (rowid, title, artist, album, genre, tags, notes)
+SELECT
+    p.id,
+    p.title,
+    a.name,
+    al.name,
+    g.name,
+    (
+        SELECT
+            group_concat (t.name, ' ')
+        FROM
+            PlayableTag pt
+            JOIN Tag t ON t.id = pt.tag_id
+        WHERE
+            pt.playable_id = p.id
+    ),
+    p.notes
+FROM
+    Playable p
+    LEFT JOIN Artist a ON a.id = p.artist_id
+    LEFT JOIN Album al ON al.id = p.album_id
+    LEFT JOIN Genre g ON g.id = p.genre_id
+WHERE
+    p.id IN (SELECT playable_id FROM PlayableTag WHERE tag_id = NEW.id);
 
 END;
+
+-- 8) Key/value application settings
+CREATE TABLE IF NOT EXISTS Setting (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+
+-- 9) Podcast subscriptions + their episodes
+CREATE TABLE IF NOT EXISTS Podcast (
+    id INTEGER PRIMARY KEY,
+    title TEXT NOT NULL,
+    feed_url TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS Episode (
+    id INTEGER PRIMARY KEY,
+    podcast_id INTEGER NOT NULL REFERENCES Podcast (id) ON DELETE CASCADE,
+    guid TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT NOT NULL DEFAULT '',
+    published INTEGER NOT NULL DEFAULT 0,
+    audio_url TEXT NOT NULL,
+    duration INTEGER NOT NULL DEFAULT 0,
+    local_path TEXT,
+    -- last playback position in seconds
+    position INTEGER NOT NULL DEFAULT 0,
+    UNIQUE (podcast_id, guid)
+);
+
+CREATE INDEX IF NOT EXISTS idx_episode_published ON Episode (podcast_id, published);
+
+-- 10) Playables marked as audiobooks always resume where they were left
+CREATE TABLE IF NOT EXISTS Audiobook (
+    playable_id INTEGER PRIMARY KEY REFERENCES Playable (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL DEFAULT 0
+);
+
+-- playback positions of long tracks such as mixes and live sets, kept while left unfinished
+CREATE TABLE IF NOT EXISTS PlaybackProgress (
+    playable_id INTEGER PRIMARY KEY REFERENCES Playable (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL
+);
+
+-- markers of a whole release played as one track, timed by the lengths in its Discogs tracklist
+CREATE TABLE IF NOT EXISTS ReleaseChapter (
+    playable_id INTEGER NOT NULL REFERENCES Playable (id) ON DELETE CASCADE,
+    -- seconds from the start of the track
+    start INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    PRIMARY KEY (playable_id, start)
+);
+
+-- moments of a track marked by the user, e.g. the best part of a long mix
+CREATE TABLE IF NOT EXISTS Bookmark (
+    id INTEGER PRIMARY KEY,
+    playable_id INTEGER NOT NULL REFERENCES Playable (id) ON DELETE CASCADE,
+    -- seconds from the start of the track
+    position INTEGER NOT NULL,
+    note TEXT NOT NULL DEFAULT ''
+);
+
+CREATE INDEX IF NOT EXISTS idx_bookmark_by_playable ON Bookmark (playable_id);
+
+-- 11) Import history, one batch per scanned file or folder
+CREATE TABLE IF NOT EXISTS ImportBatch (
+    id INTEGER PRIMARY KEY,
+    imported_at INTEGER NOT NULL DEFAULT (strftime ('%s', 'now')),
+    -- the dropped or opened file/folder
+    source TEXT NOT NULL,
+    playlist_id INTEGER REFERENCES Playlist (id) ON DELETE SET NULL,
+    -- files found by the scanner, including those already in the library
+    file_count INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS ImportBatchPlayable (
+    batch_id INTEGER NOT NULL REFERENCES ImportBatch (id) ON DELETE CASCADE,
+    playable_id INTEGER NOT NULL REFERENCES Playable (id) ON DELETE CASCADE,
+    PRIMARY KEY (batch_id, playable_id)
+) WITHOUT ROWID;
+
+-- 12) Listening history, one row per finished play
+CREATE TABLE IF NOT EXISTS PlayHistory (
+    id INTEGER PRIMARY KEY,
+    playable_id INTEGER NOT NULL REFERENCES Playable (id) ON DELETE CASCADE,
+    played_at INTEGER NOT NULL DEFAULT (strftime ('%s', 'now'))
+);
+
+-- retention pruning deletes by age
+CREATE INDEX IF NOT EXISTS idx_play_history_played_at ON PlayHistory (played_at);
+
+CREATE INDEX IF NOT EXISTS idx_play_history_by_playable ON PlayHistory (playable_id);
+
+-- 13) Library snapshots, what the library held at one point, to diff against later
+CREATE TABLE IF NOT EXISTS Snapshot (
+    id INTEGER PRIMARY KEY,
+    taken_at INTEGER NOT NULL DEFAULT (strftime ('%s', 'now')),
+    label TEXT NOT NULL DEFAULT '',
+    playable_count INTEGER NOT NULL DEFAULT 0,
+    tag_count INTEGER NOT NULL DEFAULT 0,
+    playlist_count INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS SnapshotEntry (
+    snapshot_id INTEGER NOT NULL REFERENCES Snapshot (id) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    title TEXT NOT NULL,
+    artist TEXT,
+    album TEXT,
+    -- tag names, sorted and one per line
+    tags TEXT NOT NULL DEFAULT '',
+    PRIMARY KEY (snapshot_id, path)
+) WITHOUT ROWID;
+
+-- 14) Content fingerprints of local files, computed once to match tracks with other machines
+CREATE TABLE IF NOT EXISTS Fingerprint (
+    playable_id INTEGER PRIMARY KEY REFERENCES Playable (id) ON DELETE CASCADE,
+    fingerprint TEXT NOT NULL
+);
+
+-- 15) Discogs labels and artists watched for new releases
+CREATE TABLE IF NOT EXISTS Watch (
+    id INTEGER PRIMARY KEY,
+    -- 'label' or 'artist'
+    kind TEXT NOT NULL,
+    discogs_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    -- how often the releases are checked
+    interval_hours INTEGER NOT NULL DEFAULT 24,
+    -- NULL before the first check
+    checked_at INTEGER,
+    UNIQUE (kind, discogs_id)
+);
+
+-- releases known of a watch, those found after the first check are new until seen
+CREATE TABLE IF NOT EXISTS WatchRelease (
+    watch_id INTEGER NOT NULL REFERENCES Watch (id) ON DELETE CASCADE,
+    release_id INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    artist TEXT NOT NULL DEFAULT '',
+    year INTEGER NOT NULL DEFAULT 0,
+    found_at INTEGER NOT NULL DEFAULT (strftime ('%s', 'now')),
+    seen INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (watch_id, release_id)
+) WITHOUT ROWID;
+
+-- 16) Fields defined by the user, e.g. `Mood` or `Set slot`, and their values per playable
+CREATE TABLE IF NOT EXISTS CustomField (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE COLLATE NOCASE
+);
+
+CREATE TABLE IF NOT EXISTS FieldValue (
+    playable_id INTEGER NOT NULL REFERENCES Playable (id) ON DELETE CASCADE,
+    field_id INTEGER NOT NULL REFERENCES CustomField (id) ON DELETE CASCADE,
+    value TEXT NOT NULL,
+    PRIMARY KEY (playable_id, field_id)
+) WITHOUT ROWID;
+
+-- 17) Related playables, `playable_id` is a remix, edit or cover of `original_id`
+CREATE TABLE IF NOT EXISTS PlayableLink (
+    playable_id INTEGER NOT NULL REFERENCES Playable (id) ON DELETE CASCADE,
+    original_id INTEGER NOT NULL REFERENCES Playable (id) ON DELETE CASCADE,
+    -- see `LinkKind`
+    kind TEXT NOT NULL,
+    PRIMARY KEY (playable_id, original_id),
+    CHECK (playable_id <> original_id)
+) WITHOUT ROWID;
+
+CREATE INDEX IF NOT EXISTS idx_link_by_original ON PlayableLink (original_id);
+
+-- 18) Files remote playables were downloaded to, played in place of streaming them
+CREATE TABLE IF NOT EXISTS OfflineCopy (
+    playable_id INTEGER PRIMARY KEY REFERENCES Playable (id) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    downloaded_at INTEGER NOT NULL DEFAULT (strftime ('%s', 'now'))
+);
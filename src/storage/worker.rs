@@ -0,0 +1,155 @@
+// A `Storage` run on a thread of its own, whose calls are sent to it and awaited, so that a
+// slow query or a large write does not hold up the UI thread.
+use std::{
+    sync::mpsc::{self, Sender},
+    thread,
+};
+
+use iced::futures::channel::oneshot;
+use log::error;
+
+use super::{AsyncStorage, NewImport, Playable, Result, Storage, StorageError, StorageFuture};
+
+type Job<S> = Box<dyn FnOnce(&mut S) + Send>;
+
+/// Runs the calls made on it one after the other, on the thread the storage was moved to.
+/// The thread stops once the worker is dropped.
+pub struct Worker<S> {
+    jobs: Sender<Job<S>>,
+}
+
+impl<S: Storage + Send + 'static> Worker<S> {
+    pub fn spawn(storage: S) -> Self {
+        let (jobs, received) = mpsc::channel::<Job<S>>();
+        let spawned = thread::Builder::new()
+            .name("storage".to_string())
+            .spawn(move || {
+                let mut storage = storage;
+                for job in received {
+                    job(&mut storage);
+                }
+            });
+        if let Err(err) = spawned {
+            // the calls made fail with `WorkerStopped`
+            error!("Error starting the storage worker: {err:?}");
+        }
+        Self { jobs }
+    }
+
+    fn run<T: Send + 'static>(
+        &self,
+        call: impl FnOnce(&mut S) -> Result<T> + Send + 'static,
+    ) -> StorageFuture<T> {
+        let (sender, receiver) = oneshot::channel();
+        let sent = self.jobs.send(Box::new(move |storage| {
+            let _ = sender.send(call(storage));
+        }));
+        Box::pin(async move {
+            sent.map_err(|_| StorageError::WorkerStopped)?;
+            receiver.await.unwrap_or(Err(StorageError::WorkerStopped))
+        })
+    }
+}
+
+impl<S: Storage + Send + 'static> AsyncStorage for Worker<S> {
+    fn read_library(&self) -> StorageFuture<Vec<Playable>> {
+        self.run(|storage| storage.read_library())
+    }
+
+    fn read_library_page(&self, after: i64, limit: usize) -> StorageFuture<Vec<Playable>> {
+        self.run(move |storage| storage.read_library_page(after, limit))
+    }
+
+    fn read_library_from_ids(&self, ids: Vec<i64>) -> StorageFuture<Vec<Playable>> {
+        self.run(move |storage| storage.read_library_from_ids(&ids))
+    }
+
+    fn read_artwork(&self, playable_id: i64) -> StorageFuture<Option<Vec<u8>>> {
+        self.run(move |storage| storage.read_artwork(playable_id))
+    }
+
+    fn read_without_artwork(&self) -> StorageFuture<Vec<Playable>> {
+        self.run(|storage| storage.read_without_artwork())
+    }
+
+    fn write_artwork(&self, playable_ids: Vec<i64>, artwork: Vec<u8>) -> StorageFuture<()> {
+        self.run(move |storage| storage.write_artwork(&playable_ids, &artwork))
+    }
+
+    fn read_setting(&self, key: String) -> StorageFuture<Option<String>> {
+        self.run(move |storage| storage.read_setting(&key))
+    }
+
+    fn write_setting(&self, key: String, value: String) -> StorageFuture<()> {
+        self.run(move |storage| storage.write_setting(&key, &value))
+    }
+
+    fn import(&self, import: NewImport) -> StorageFuture<Vec<i64>> {
+        self.run(move |storage| import.write(storage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{AudioFileDescriptor, AudioFileKind, local::init_storage};
+
+    fn descriptor(title: &str) -> AudioFileDescriptor {
+        AudioFileDescriptor {
+            title: title.to_string(),
+            artist: "Artist".to_string(),
+            album: "Album".to_string(),
+            year: 2001,
+            genre: "Genre".to_string(),
+            duration: 100,
+            bitrate: 0,
+            sample_rate: 0,
+            lossless: None,
+            release_date: String::new(),
+            track_number: 0,
+            disc_number: 0,
+            label: String::new(),
+            artwork: None,
+            path: format!("/tmp/test_worker_{title}.mp3"),
+            kind: AudioFileKind::LocalFile,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_worker() {
+        let mut storage = init_storage(":memory:").unwrap();
+        let id = storage.append_to_library(&descriptor("Title")).unwrap();
+        let worker = Worker::spawn(storage);
+
+        let library = worker.read_library().await.unwrap();
+        assert_eq!(library.len(), 1);
+        assert_eq!(library[0].id, id);
+        assert_eq!(worker.read_library_page(0, 10).await.unwrap().len(), 1);
+        assert!(worker.read_library_page(id, 10).await.unwrap().is_empty());
+        assert_eq!(worker.read_without_artwork().await.unwrap().len(), 1);
+        worker.write_artwork(vec![id], vec![1, 2, 3]).await.unwrap();
+        assert_eq!(worker.read_artwork(id).await.unwrap(), Some(vec![1, 2, 3]));
+        assert!(worker.read_without_artwork().await.unwrap().is_empty());
+
+        assert_eq!(worker.read_setting("key".to_string()).await.unwrap(), None);
+        worker
+            .write_setting("key".to_string(), "value".to_string())
+            .await
+            .unwrap();
+        assert_eq!(
+            worker.read_setting("key".to_string()).await.unwrap(),
+            Some("value".to_string())
+        );
+
+        // files in the library already are counted in the batch, not added again
+        let import = NewImport {
+            source: "/tmp".to_string(),
+            playlist_id: None,
+            items: vec![descriptor("Title"), descriptor("Other")],
+        };
+        let ids = worker.import(import).await.unwrap();
+        assert_eq!(ids.len(), 1);
+        assert_ne!(ids[0], id);
+        assert_eq!(worker.read_library().await.unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,475 @@
+// Servers speaking the Subsonic API, e.g. Navidrome or Airsonic. Their albums, playlists and
+// starred songs are mirrored into the library, songs are streamed from the server.
+use std::{collections::HashSet, io};
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::{
+    remote::{self, Mirror},
+    storage::{AudioFileDescriptor, AudioFileKind},
+};
+
+// the oldest version of the API with everything used here
+const API_VERSION: &str = "1.16.1";
+const CLIENT_NAME: &str = "phoniq";
+// albums asked for at once, the most the API hands out
+const ALBUM_PAGE: usize = 500;
+
+#[derive(Debug, Error)]
+pub enum SubsonicError {
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Invalid answer: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("The server refused: {0}")]
+    Api(String),
+    // error 41 of the API, e.g. for accounts checked by LDAP
+    #[error("The server takes no token for the account")]
+    TokenNotSupported,
+    #[error("Invalid address: {0}")]
+    InvalidUrl(String),
+}
+
+pub type Result<T> = std::result::Result<T, SubsonicError>;
+
+/// A server with the account its music is read with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Server {
+    // where the server is reached, e.g. `https://music.example.com/`
+    pub url: String,
+    pub username: String,
+    // kept in the keychain rather than with the server, older settings still have it
+    #[serde(default, skip_serializing)]
+    pub password: String,
+}
+
+// how the requests prove the account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Auth {
+    // the password salted and hashed, new for each request
+    Token,
+    // the password hex encoded, for servers checking it elsewhere (e.g. LDAP) which can not
+    // take a token
+    Password,
+}
+
+impl Server {
+    /// How the server is called in the sidebar, its host.
+    pub fn name(&self) -> &str {
+        remote::host(&self.url)
+    }
+
+    /// The key the password is kept under in the keychain.
+    pub fn secret_key(&self) -> String {
+        format!("subsonic {}@{}", self.username, self.url)
+    }
+
+    // the address of an API method, signed with the account
+    fn endpoint(&self, method: &str, params: &[(&str, &str)], auth: Auth) -> Result<Url> {
+        let salt = format!("{:016x}", rand::random::<u64>());
+        self.salted_endpoint(method, params, auth, &salt)
+    }
+
+    fn salted_endpoint(
+        &self,
+        method: &str,
+        params: &[(&str, &str)],
+        auth: Auth,
+        salt: &str,
+    ) -> Result<Url> {
+        let mut url = Url::parse(&self.url)
+            .and_then(|url| url.join(&format!("rest/{method}")))
+            .map_err(|e| SubsonicError::InvalidUrl(e.to_string()))?;
+        let mut query = url.query_pairs_mut();
+        query.append_pair("u", &self.username);
+        match auth {
+            Auth::Token => query
+                .append_pair("t", &token(&self.password, salt))
+                .append_pair("s", salt),
+            Auth::Password => query.append_pair("p", &format!("enc:{}", hex(&self.password))),
+        };
+        query
+            .append_pair("v", API_VERSION)
+            .append_pair("c", CLIENT_NAME)
+            .append_pair("f", "json")
+            .extend_pairs(params);
+        drop(query);
+        Ok(url)
+    }
+
+    /// The address a song is kept at in the library, without the account.
+    pub fn stream_url(&self, song_id: &str) -> String {
+        format!(
+            "{}rest/stream?id={}",
+            self.url,
+            urlencoding::encode(song_id)
+        )
+    }
+}
+
+/// A song as the server describes it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Song {
+    pub id: String,
+    pub title: String,
+    #[serde(default)]
+    pub artist: String,
+    #[serde(default)]
+    pub album: String,
+    #[serde(default)]
+    pub genre: String,
+    #[serde(default)]
+    pub year: u16,
+    // in seconds
+    #[serde(default)]
+    pub duration: u64,
+    #[serde(default)]
+    pub track: u32,
+    #[serde(default)]
+    pub disc_number: u32,
+    // in kbps
+    #[serde(default)]
+    pub bit_rate: u32,
+    #[serde(default)]
+    pub suffix: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Album {
+    id: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Playlist {
+    id: String,
+    name: String,
+}
+
+impl Song {
+    /// The song as added to the library, streamed from `server`.
+    pub fn descriptor(&self, server: &Server) -> AudioFileDescriptor {
+        AudioFileDescriptor {
+            title: self.title.clone(),
+            artist: self.artist.clone(),
+            album: self.album.clone(),
+            year: self.year,
+            genre: self.genre.clone(),
+            duration: self.duration,
+            bitrate: self.bit_rate,
+            sample_rate: 0,
+            lossless: match self.suffix.to_lowercase().as_str() {
+                "flac" | "alac" | "wav" | "aiff" | "ape" | "wv" => Some(true),
+                "" => None,
+                _ => Some(false),
+            },
+            release_date: String::new(),
+            track_number: self.track,
+            disc_number: self.disc_number,
+            label: String::new(),
+            path: server.stream_url(&self.id),
+            artwork: None,
+            kind: AudioFileKind::Subsonic,
+        }
+    }
+}
+
+fn hex(text: &str) -> String {
+    text.bytes().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// the md5 of the password followed by the salt, as the API takes it
+fn token(password: &str, salt: &str) -> String {
+    format!("{:x}", md5::compute(format!("{password}{salt}")))
+}
+
+/// The id of the song a library address streams, None for other addresses.
+pub fn song_id(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    if !url.path().ends_with("/rest/stream") {
+        return None;
+    }
+    url.query_pairs()
+        .find(|(key, _)| key == "id")
+        .map(|(_, id)| id.into_owned())
+}
+
+// the body of an answer, `key` taken out of it. Failures come with a status of their own
+// rather than as an HTTP error
+fn parse_response<T: DeserializeOwned>(json: &str, key: &str) -> Result<T> {
+    let mut body: Value = serde_json::from_str(json)?;
+    let response = body
+        .get_mut("subsonic-response")
+        .ok_or_else(|| SubsonicError::Api("Not a Subsonic server".to_string()))?;
+    if response.get("status").and_then(Value::as_str) != Some("ok") {
+        if response.pointer("/error/code").and_then(Value::as_u64) == Some(41) {
+            return Err(SubsonicError::TokenNotSupported);
+        }
+        let message = response
+            .pointer("/error/message")
+            .and_then(Value::as_str)
+            .unwrap_or("Unknown error");
+        return Err(SubsonicError::Api(message.to_string()));
+    }
+    let value = response
+        .get_mut(key)
+        .map(Value::take)
+        .unwrap_or(Value::Null);
+    Ok(serde_json::from_value(value)?)
+}
+
+// lists the API leaves out when they are empty
+fn list<T: DeserializeOwned>(value: &Value, key: &str) -> Result<Vec<T>> {
+    match value.get(key) {
+        Some(list) => Ok(serde_json::from_value(list.clone())?),
+        None => Ok(Vec::new()),
+    }
+}
+
+// signed with a token, or with the password once the server refused a token
+async fn call(
+    client: &reqwest::Client,
+    server: &Server,
+    method: &str,
+    params: &[(&str, &str)],
+    key: &str,
+) -> Result<Value> {
+    match call_with(client, server, method, params, key, Auth::Token).await {
+        Err(SubsonicError::TokenNotSupported) => {
+            call_with(client, server, method, params, key, Auth::Password).await
+        }
+        result => result,
+    }
+}
+
+async fn call_with(
+    client: &reqwest::Client,
+    server: &Server,
+    method: &str,
+    params: &[(&str, &str)],
+    key: &str,
+    auth: Auth,
+) -> Result<Value> {
+    let json = client
+        .get(server.endpoint(method, params, auth)?)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_response(&json, key)
+}
+
+/// Checks the address and the account.
+pub async fn ping(server: Server) -> Result<()> {
+    call(&reqwest::Client::new(), &server, "ping", &[], "status").await?;
+    Ok(())
+}
+
+/// Reads the albums, playlists and starred songs of the server.
+pub async fn mirror(server: Server) -> Result<Mirror> {
+    let client = reqwest::Client::new();
+    let mut songs: Vec<Song> = Vec::new();
+    let mut offset = 0;
+    loop {
+        let (size, from) = (ALBUM_PAGE.to_string(), offset.to_string());
+        let params = [
+            ("type", "alphabeticalByName"),
+            ("size", size.as_str()),
+            ("offset", from.as_str()),
+        ];
+        let page = call(&client, &server, "getAlbumList2", &params, "albumList2").await?;
+        let albums: Vec<Album> = list(&page, "album")?;
+        for album in &albums {
+            let album = call(
+                &client,
+                &server,
+                "getAlbum",
+                &[("id", album.id.as_str())],
+                "album",
+            )
+            .await?;
+            songs.extend(list::<Song>(&album, "song")?);
+        }
+        if albums.len() < ALBUM_PAGE {
+            break;
+        }
+        offset += ALBUM_PAGE;
+    }
+
+    let mut known: HashSet<String> = songs.iter().map(|song| song.id.clone()).collect();
+    let mut mirror = Mirror::default();
+    let playlists = call(&client, &server, "getPlaylists", &[], "playlists").await?;
+    for playlist in list::<Playlist>(&playlists, "playlist")? {
+        let entries = call(
+            &client,
+            &server,
+            "getPlaylist",
+            &[("id", playlist.id.as_str())],
+            "playlist",
+        )
+        .await?;
+        let entries: Vec<Song> = list(&entries, "entry")?;
+        let paths = entries
+            .iter()
+            .map(|song| server.stream_url(&song.id))
+            .collect();
+        // a playlist may hold songs outside of every album, e.g. of a folder
+        for song in entries {
+            if known.insert(song.id.clone()) {
+                songs.push(song);
+            }
+        }
+        mirror.playlists.push((playlist.name, paths));
+    }
+
+    let starred = call(&client, &server, "getStarred2", &[], "starred2").await?;
+    mirror.liked = list::<Song>(&starred, "song")?
+        .iter()
+        .map(|song| server.stream_url(&song.id))
+        .collect();
+    mirror.tracks = songs.iter().map(|song| song.descriptor(&server)).collect();
+    Ok(mirror)
+}
+
+/// Stars a song on the server, or takes the star away, as it is liked in the library.
+pub async fn star(server: Server, song_id: String, starred: bool) -> Result<()> {
+    let method = if starred { "star" } else { "unstar" };
+    call(
+        &reqwest::Client::new(),
+        &server,
+        method,
+        &[("id", song_id.as_str())],
+        "status",
+    )
+    .await?;
+    Ok(())
+}
+
+/// The server a library address streams from.
+pub fn find_server(url: &str) -> Option<Server> {
+    match remote::find_account(url)? {
+        remote::Account::Subsonic(server) => Some(server),
+        _ => None,
+    }
+}
+
+impl From<Server> for remote::Server {
+    fn from(server: Server) -> Self {
+        remote::Server::Subsonic(server)
+    }
+}
+
+/// Blocking read used by the audio worker to stream a song of a server.
+pub fn fetch_audio(url: &str) -> io::Result<Vec<u8>> {
+    let (Some(server), Some(id)) = (find_server(url), song_id(url)) else {
+        return Err(io::Error::other(format!("No Subsonic server for {url}")));
+    };
+    match fetch_stream(&server, &id, Auth::Token) {
+        Err(SubsonicError::TokenNotSupported) => fetch_stream(&server, &id, Auth::Password),
+        result => result,
+    }
+    .map_err(io::Error::other)
+}
+
+// the audio of a song, failures come as an answer in JSON instead
+fn fetch_stream(server: &Server, id: &str, auth: Auth) -> Result<Vec<u8>> {
+    let response = reqwest::blocking::get(server.endpoint("stream", &[("id", id)], auth)?)?
+        .error_for_status()?;
+    let is_json = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.contains("json"));
+    if is_json {
+        parse_response::<Value>(&response.text()?, "status")?;
+        return Err(SubsonicError::Api("No audio in the answer".to_string()));
+    }
+    Ok(response.bytes()?.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> Server {
+        Server {
+            url: "https://music.example.com/".to_string(),
+            username: "alice".to_string(),
+            password: "pw".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_endpoint() {
+        // the example of the API documentation
+        assert_eq!(
+            token("sesame", "c19b2d"),
+            "26719a1196d2a940705a59634eb18eab"
+        );
+        let url = server()
+            .salted_endpoint("getAlbum", &[("id", "a 1")], Auth::Token, "c19b2d")
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            format!(
+                "https://music.example.com/rest/getAlbum?u=alice&t={}&s=c19b2d&v=1.16.1\
+                 &c=phoniq&f=json&id=a+1",
+                token("pw", "c19b2d")
+            )
+        );
+        let url = server()
+            .endpoint("getAlbum", &[("id", "a 1")], Auth::Password)
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://music.example.com/rest/getAlbum?u=alice&p=enc%3A7077&v=1.16.1&c=phoniq\
+             &f=json&id=a+1"
+        );
+        assert_eq!(server().name(), "music.example.com");
+    }
+
+    #[test]
+    fn test_song_id() {
+        let url = server().stream_url("tr/1");
+        assert_eq!(url, "https://music.example.com/rest/stream?id=tr%2F1");
+        assert_eq!(song_id(&url).as_deref(), Some("tr/1"));
+        assert_eq!(song_id("https://music.example.com/a.mp3"), None);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let json = r#"{"subsonic-response": {"status": "ok", "version": "1.16.1",
+            "album": {"id": "al", "song": [
+                {"id": "1", "title": "One", "artist": "A", "duration": 200, "track": 1,
+                 "suffix": "flac"},
+                {"id": "2", "title": "Two"}
+            ]}}}"#;
+        let album: Value = parse_response(json, "album").unwrap();
+        let songs: Vec<Song> = list(&album, "song").unwrap();
+        assert_eq!(songs.len(), 2);
+        assert_eq!(songs[0].duration, 200);
+        assert_eq!(songs[0].descriptor(&server()).lossless, Some(true));
+        assert_eq!(songs[1].descriptor(&server()).lossless, None);
+        assert!(list::<Song>(&Value::Null, "song").unwrap().is_empty());
+
+        let json = r#"{"subsonic-response": {"status": "failed",
+            "error": {"code": 40, "message": "Wrong username or password"}}}"#;
+        let error = parse_response::<Value>(json, "album").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The server refused: Wrong username or password"
+        );
+        assert!(parse_response::<Value>("{}", "album").is_err());
+
+        let json = r#"{"subsonic-response": {"status": "failed",
+            "error": {"code": 41, "message": "Token authentication not supported for LDAP users."}}}"#;
+        assert!(matches!(
+            parse_response::<Value>(json, "album"),
+            Err(SubsonicError::TokenNotSupported)
+        ));
+    }
+}
@@ -0,0 +1,505 @@
+// Syncing with other Phoniq instances on the local network. An instance sharing its library
+// announces itself with mDNS and serves a plain text document of its likes, ratings, tags and
+// playlists over a small HTTP server. Pulling a peer's document merges it into the local library, tracks
+// are matched by path first and by a fingerprint of their content when the paths differ.
+// The document is only served to peers giving the pairing code shown by the sharing instance.
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    net::TcpStream,
+    path::Path,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use iced::futures::channel::oneshot;
+use log::{debug, error, warn};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::listener::Listener;
+
+const SERVICE_TYPE: &str = "_phoniq-sync._tcp.local.";
+const HEADER: &str = "phoniq-sync 1";
+// the request header carrying the pairing code
+const PAIRING_HEADER: &str = "X-Phoniq-Pairing";
+// wrong codes a server takes, it refuses every request after them until it is started again
+const MAX_WRONG_CODES: u32 = 10;
+// bytes read from the start and from the end of a file for its fingerprint
+const FINGERPRINT_SPAN: u64 = 64 * 1024;
+
+#[derive(Debug, Error)]
+pub enum SyncError {
+    #[error("Network discovery failed: {0}")]
+    Mdns(#[from] mdns_sd::Error),
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Invalid sync document: {0}")]
+    Invalid(String),
+    #[error("Wrong pairing code")]
+    WrongCode,
+    #[error("Too many wrong pairing codes, the peer has to share its library again")]
+    Locked,
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, SyncError>;
+
+/// Another instance sharing its library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Peer {
+    pub name: String,
+    pub url: String,
+    // full mDNS name, tells our own announcement apart
+    fullname: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncTrack {
+    pub path: String,
+    // None when the file could not be read, such tracks only match by path
+    pub fingerprint: Option<String>,
+}
+
+/// What one instance shares, the likes, ratings, tags and playlists point into `tracks`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncDocument {
+    pub tracks: Vec<SyncTrack>,
+    pub likes: Vec<usize>,
+    // the track and its stars, unrated tracks are left out
+    pub ratings: Vec<(usize, u8)>,
+    pub tags: Vec<(String, Vec<usize>)>,
+    pub playlists: Vec<(String, Vec<usize>)>,
+}
+
+impl SyncDocument {
+    /// One record per line, fields are separated by tabs and track lists by spaces.
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![HEADER.to_string()];
+        for track in &self.tracks {
+            let fingerprint = track.fingerprint.as_deref().unwrap_or("-");
+            lines.push(format!("track\t{fingerprint}\t{}", escape(&track.path)));
+        }
+        for index in &self.likes {
+            lines.push(format!("like\t{index}"));
+        }
+        for (index, rating) in &self.ratings {
+            lines.push(format!("rating\t{index}\t{rating}"));
+        }
+        for (kind, groups) in [("tag", &self.tags), ("playlist", &self.playlists)] {
+            for (name, indexes) in groups {
+                let indexes: Vec<String> = indexes.iter().map(|i| i.to_string()).collect();
+                lines.push(format!("{kind}\t{}\t{}", escape(name), indexes.join(" ")));
+            }
+        }
+        lines.join("\n")
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(SyncError::Invalid("unknown format".into()));
+        }
+        let mut document = SyncDocument::default();
+        for line in lines.filter(|line| !line.is_empty()) {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                ["track", fingerprint, path] => document.tracks.push(SyncTrack {
+                    path: unescape(path),
+                    fingerprint: (*fingerprint != "-").then(|| fingerprint.to_string()),
+                }),
+                ["like", index] => document.likes.push(parse_index(index)?),
+                ["rating", index, rating] => {
+                    let rating = rating
+                        .parse()
+                        .map_err(|_| SyncError::Invalid(format!("bad rating `{rating}`")))?;
+                    document.ratings.push((parse_index(index)?, rating));
+                }
+                ["tag", name, indexes] => document
+                    .tags
+                    .push((unescape(name), parse_indexes(indexes)?)),
+                ["playlist", name, indexes] => document
+                    .playlists
+                    .push((unescape(name), parse_indexes(indexes)?)),
+                _ => return Err(SyncError::Invalid(format!("unexpected line `{line}`"))),
+            }
+        }
+        let tracks = document.tracks.len();
+        let groups = document.tags.iter().chain(&document.playlists);
+        let mut indexes = document
+            .likes
+            .iter()
+            .chain(document.ratings.iter().map(|(index, _)| index))
+            .chain(groups.flat_map(|(_, indexes)| indexes));
+        if let Some(index) = indexes.find(|index| **index >= tracks) {
+            return Err(SyncError::Invalid(format!("no track {index}")));
+        }
+        Ok(document)
+    }
+}
+
+fn parse_index(value: &str) -> Result<usize> {
+    value
+        .parse()
+        .map_err(|_| SyncError::Invalid(format!("bad track index `{value}`")))
+}
+
+fn parse_indexes(value: &str) -> Result<Vec<usize>> {
+    value.split_whitespace().map(parse_index).collect()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Size of the file and a hash of its first and last bytes, e.g. `5301921-9c1a…`.
+/// Reading the whole file would make fingerprinting a large library take minutes.
+pub fn fingerprint(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    let mut buffer = Vec::new();
+    (&mut file)
+        .take(FINGERPRINT_SPAN)
+        .read_to_end(&mut buffer)?;
+    if size > FINGERPRINT_SPAN * 2 {
+        file.seek(SeekFrom::End(-(FINGERPRINT_SPAN as i64)))?;
+    }
+    file.read_to_end(&mut buffer)?;
+    Ok(format!("{size}-{:016x}", fnv1a(&buffer)))
+}
+
+/// The file size a fingerprint starts with, cheap to compare before fingerprinting.
+pub fn fingerprint_size(fingerprint: &str) -> Option<u64> {
+    fingerprint.split_once('-')?.0.parse().ok()
+}
+
+// stable across builds and platforms, unlike the hasher of the standard library
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Searches the local network for instances sharing their library, waits `timeout` for answers.
+pub async fn discover(timeout: Duration) -> Result<Vec<Peer>> {
+    let (sender, receiver) = oneshot::channel();
+    // the mDNS daemon blocks, it gets a thread of its own instead of stalling the executor
+    std::thread::spawn(move || {
+        let _ = sender.send(browse(timeout));
+    });
+    receiver.await.unwrap_or_else(|_| Ok(Vec::new()))
+}
+
+fn browse(timeout: Duration) -> Result<Vec<Peer>> {
+    let daemon = ServiceDaemon::new()?;
+    let receiver = daemon.browse(SERVICE_TYPE)?;
+    let deadline = Instant::now() + timeout;
+    let mut peers = Vec::new();
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        let Ok(event) = receiver.recv_timeout(remaining) else {
+            break;
+        };
+        if let ServiceEvent::ServiceResolved(info) = event {
+            let Some(address) = info.get_addresses_v4().into_iter().next() else {
+                warn!("discover: {} has no IPv4 address", info.get_fullname());
+                continue;
+            };
+            let peer = Peer {
+                name: info
+                    .get_property_val_str("name")
+                    .unwrap_or(info.get_hostname())
+                    .to_string(),
+                url: format!("http://{address}:{}", info.get_port()),
+                fullname: info.get_fullname().to_string(),
+            };
+            if !peers.contains(&peer) {
+                peers.push(peer);
+            }
+        }
+    }
+    let _ = daemon.shutdown();
+    Ok(peers)
+}
+
+/// Reads the document a peer shares, `code` is the pairing code the peer shows.
+pub async fn pull(peer_url: String, code: String) -> Result<SyncDocument> {
+    let response = reqwest::Client::new()
+        .get(format!("{peer_url}/library"))
+        .header(PAIRING_HEADER, code.trim())
+        .send()
+        .await?;
+    match response.status() {
+        StatusCode::UNAUTHORIZED => return Err(SyncError::WrongCode),
+        StatusCode::FORBIDDEN => return Err(SyncError::Locked),
+        _ => {}
+    }
+    let body = response.error_for_status()?.text().await?;
+    SyncDocument::parse(&body)
+}
+
+/// A new pairing code, e.g. `4821-0937`.
+pub fn pairing_code() -> String {
+    let number = rand::random_range(0..100_000_000u32);
+    format!("{:04}-{:04}", number / 10_000, number % 10_000)
+}
+
+// The code a server asks of its peers and the wrong ones it was given.
+struct Pairing {
+    // digits only, peers may type the code with or without its dash
+    code: String,
+    wrong_codes: AtomicU32,
+}
+
+impl Pairing {
+    fn new(code: &str) -> Self {
+        Self {
+            code: digits(code),
+            wrong_codes: AtomicU32::new(0),
+        }
+    }
+
+    fn is_locked(&self) -> bool {
+        self.wrong_codes.load(Ordering::Acquire) >= MAX_WRONG_CODES
+    }
+
+    fn accepts(&self, given: Option<&str>) -> bool {
+        let given = given.map(digits).unwrap_or_default();
+        // compares every digit so that the time taken does not tell how many were right
+        let matches = given.len() == self.code.len()
+            && given
+                .bytes()
+                .zip(self.code.bytes())
+                .fold(0, |differ, (a, b)| differ | (a ^ b))
+                == 0;
+        if !matches {
+            self.wrong_codes.fetch_add(1, Ordering::AcqRel);
+        }
+        matches
+    }
+}
+
+fn digits(code: &str) -> String {
+    code.chars().filter(char::is_ascii_digit).collect()
+}
+
+/// Announces this instance on the network and serves its sync document to paired peers until
+/// dropped.
+pub struct SyncServer {
+    daemon: ServiceDaemon,
+    fullname: String,
+    code: String,
+    document: Arc<Mutex<String>>,
+    // stops listening when dropped
+    _listener: Listener,
+}
+
+impl SyncServer {
+    pub fn start(document: String, code: String) -> Result<Self> {
+        let document = Arc::new(Mutex::new(document));
+        let served = document.clone();
+        let pairing = Pairing::new(&code);
+        let listener = Listener::start("sync-server", move |stream| {
+            if let Err(e) = serve(stream, &served, &pairing) {
+                debug!("SyncServer: request failed: {e}");
+            }
+        })?;
+        let port = listener.port();
+
+        let name = machine_name();
+        let host: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        let info = ServiceInfo::new(
+            SERVICE_TYPE,
+            &host,
+            &format!("{host}.local."),
+            "",
+            port,
+            &[("name", name.as_str())][..],
+        )?
+        .enable_addr_auto();
+        let fullname = info.get_fullname().to_string();
+        let daemon = ServiceDaemon::new()?;
+        daemon.register(info)?;
+        Ok(Self {
+            daemon,
+            fullname,
+            code,
+            document,
+            _listener: listener,
+        })
+    }
+
+    pub fn set_document(&self, document: String) {
+        *self.document.lock().unwrap() = document;
+    }
+
+    /// The code peers give to read the document.
+    pub fn pairing_code(&self) -> &str {
+        &self.code
+    }
+
+    pub fn is_own(&self, peer: &Peer) -> bool {
+        peer.fullname == self.fullname
+    }
+}
+
+impl Drop for SyncServer {
+    fn drop(&mut self) {
+        if let Err(e) = self.daemon.unregister(&self.fullname) {
+            error!("SyncServer: failed to withdraw the announcement: {e}");
+        }
+        let _ = self.daemon.shutdown();
+    }
+}
+
+fn machine_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Phoniq".to_string())
+}
+
+fn serve(mut stream: TcpStream, document: &Mutex<String>, pairing: &Pairing) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut code = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.trim().eq_ignore_ascii_case(PAIRING_HEADER)
+        {
+            code = Some(value.trim().to_string());
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    if parts.next() != Some("GET") || parts.next() != Some("/library") {
+        return stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+    }
+    if pairing.is_locked() {
+        return stream.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n");
+    }
+    if !pairing.accepts(code.as_deref()) {
+        return stream.write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\n\r\n");
+    }
+    let document = document.lock().unwrap().clone();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        document.len()
+    )?;
+    stream.write_all(document.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(path: &str, fingerprint: Option<&str>) -> SyncTrack {
+        SyncTrack {
+            path: path.to_string(),
+            fingerprint: fingerprint.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_document_round_trip() {
+        let document = SyncDocument {
+            tracks: vec![
+                track("/music/a\tb.mp3", Some("10-00ff")),
+                track("C:\\Music\\c.flac", None),
+            ],
+            likes: vec![1],
+            ratings: vec![(0, 4)],
+            tags: vec![("mood/dark".to_string(), vec![0, 1])],
+            playlists: vec![
+                ("Warm up".to_string(), vec![]),
+                ("Peak".to_string(), vec![1]),
+            ],
+        };
+        let text = document.to_text();
+        assert_eq!(text.lines().count(), 8);
+        assert_eq!(SyncDocument::parse(&text).unwrap(), document);
+    }
+
+    #[test]
+    fn test_parse_rejects() {
+        assert!(SyncDocument::parse("").is_err());
+        assert!(SyncDocument::parse("phoniq-sync 1\nlike\t0").is_err());
+        assert!(SyncDocument::parse("phoniq-sync 1\nrating\t0\t5").is_err());
+        let track = "phoniq-sync 1\ntrack\t-\t/music/a.mp3";
+        assert!(SyncDocument::parse(&format!("{track}\nrating\t0\tfive")).is_err());
+        assert!(SyncDocument::parse(&format!("{track}\nbpm\t0\t120")).is_err());
+    }
+
+    #[test]
+    fn test_pairing() {
+        let code = pairing_code();
+        assert_eq!(code.len(), 9);
+        assert_eq!(digits(&code).len(), 8);
+
+        let pairing = Pairing::new("4821-0937");
+        assert!(pairing.accepts(Some("4821-0937")));
+        assert!(pairing.accepts(Some(" 48210937 ")));
+        assert!(!pairing.accepts(Some("4821-0938")));
+        assert!(!pairing.accepts(Some("4821")));
+        assert!(!pairing.accepts(None));
+        assert!(!pairing.is_locked());
+        for _ in 3..MAX_WRONG_CODES {
+            assert!(!pairing.accepts(Some("0000-0000")));
+        }
+        assert!(pairing.is_locked());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let path = std::env::temp_dir().join("phoniq_test_fingerprint.bin");
+        let mut bytes = vec![7u8; 3 * FINGERPRINT_SPAN as usize];
+        std::fs::write(&path, &bytes).unwrap();
+        let first = fingerprint(&path).unwrap();
+        assert_eq!(fingerprint_size(&first), Some(bytes.len() as u64));
+
+        // the middle is not read, the end is
+        bytes[FINGERPRINT_SPAN as usize + 1] = 0;
+        std::fs::write(&path, &bytes).unwrap();
+        assert_eq!(fingerprint(&path).unwrap(), first);
+        *bytes.last_mut().unwrap() = 0;
+        std::fs::write(&path, &bytes).unwrap();
+        assert_ne!(fingerprint(&path).unwrap(), first);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
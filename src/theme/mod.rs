@@ -9,6 +9,8 @@ use iced::{
     theme::palette::Pair,
 };
 
+use crate::widgets::context_menu::MenuStyle;
+
 #[derive(Debug, Clone, Default, Eq, PartialEq)]
 pub enum Theme {
     #[default]
@@ -22,47 +24,75 @@ impl std::fmt::Display for Theme {
     }
 }
 
+impl From<&str> for Theme {
+    fn from(s: &str) -> Self {
+        match s {
+            "dark" => Theme::Dark,
+            _ => Theme::Light,
+        }
+    }
+}
+
 impl From<Theme> for IcedTheme {
     fn from(theme: Theme) -> Self {
-        match theme {
-            Theme::Light => IcedTheme::Custom(Arc::new(theme.to_theme())),
-            Theme::Dark => IcedTheme::Custom(Arc::new(iced::theme::Custom::new(
-                "Dark".to_string(),
-                theme.to_palette(),
-            ))),
-        }
+        IcedTheme::Custom(Arc::new(theme.to_theme()))
     }
 }
 
 impl Theme {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+        }
+    }
+
+    pub fn toggled(&self) -> Theme {
+        match self {
+            Theme::Light => Theme::Dark,
+            Theme::Dark => Theme::Light,
+        }
+    }
+
     pub fn to_palette(&self) -> iced::theme::Palette {
         let colors = self.colors();
         iced::theme::Palette {
             background: colors.background_base,
             text: colors.foreground_base,
-            primary: color!(0x3026F1),
-            success: color!(0x3026F1),
+            primary: colors.primary,
+            success: colors.primary,
             warning: color!(0xFF9292),
             danger: color!(0xE71E7D),
         }
     }
 
     pub fn to_theme(&self) -> iced::theme::Custom {
-        let colors = self.colors();
         let name = match self {
             Theme::Light => "PhoniqueLight",
             Theme::Dark => "PhoniqueDark",
         };
-        let is_dark = matches!(self, Theme::Dark);
-        iced::theme::Custom::new("PhoniqueLight".into(), self.to_palette())
+        iced::theme::Custom::new(name.into(), self.to_palette())
     }
 
     pub fn colors(&self) -> &Palette {
-        &Palette::LIGHT
+        match self {
+            Theme::Light => &Palette::LIGHT,
+            Theme::Dark => &Palette::DARK,
+        }
+    }
+
+    /// The context menus, which are drawn outside of the widget styles.
+    pub fn menu_style(&self) -> MenuStyle {
+        match self {
+            Theme::Light => MenuStyle::default(),
+            Theme::Dark => MenuStyle::dark(),
+        }
     }
 }
 
 pub struct Palette {
+    // buttons, links and the selection
+    pub primary: Color,
     pub primary_base_gradient: iced::Background,
     pub primary_base_text: Color,
     pub primary_strong_gradient: iced::Background,
@@ -99,6 +129,7 @@ mod light {
 
 impl Palette {
     pub const LIGHT: Palette = Palette {
+        primary: color!(0x3026F1),
         primary_base_gradient: Background::Gradient(Gradient::Linear(Linear {
             angle: iced::Radians(2.355),
             stops: [
@@ -159,4 +190,67 @@ impl Palette {
         border_dark: color!(0xE6E3E3),
         scroller: color!(0xD3D0D0),
     };
+
+    pub const DARK: Palette = Palette {
+        primary: color!(0x8C85FF),
+        primary_base_gradient: Background::Gradient(Gradient::Linear(Linear {
+            angle: iced::Radians(2.355),
+            stops: [
+                Some(ColorStop {
+                    offset: 0.0,
+                    color: Color {
+                        r: 0.549,
+                        g: 0.522,
+                        b: 1.0,
+                        a: 0.4,
+                    },
+                }),
+                Some(ColorStop {
+                    offset: 1.0,
+                    color: color!(0xC2577F),
+                }),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+        })),
+        primary_base_text: color!(0xFFFFFF),
+        primary_strong_gradient: Background::Gradient(Gradient::Linear(Linear {
+            angle: iced::Radians(2.355),
+            stops: [
+                Some(ColorStop {
+                    offset: 0.0,
+                    color: color!(0x5A52F5),
+                }),
+                Some(ColorStop {
+                    offset: 1.0,
+                    color: color!(0xC2577F),
+                }),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+            ],
+        })),
+        primary_strong_text: color!(0xFFFFFF),
+        secondary_background: color!(0xE71E7D),
+        secondary_text: color!(0xFFFFFF),
+        background_base: color!(0x18181B),
+        foreground_base: color!(0xF4F4F5),
+        background_weak: color!(0x232326),
+        foreground_weak: color!(0x9F9FA9),
+        background_strong: color!(0x27272A),
+        foreground_strong: color!(0xFFFFFF),
+        background_alt: color!(0x3F3F46),
+        foreground_alt: color!(0xD4D4D8),
+        background_highlight: color!(0x71717B),
+        border_light: color!(0x2E2E33),
+        border_dark: color!(0x3F3F46),
+        scroller: color!(0x52525C),
+    };
 }
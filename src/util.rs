@@ -1,3 +1,5 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use crate::app_state::AudioPlayable;
 
 pub static CONTAINER_SVG: &[u8] = include_bytes!("../images/placeholder.svg");
@@ -5,6 +7,109 @@ pub fn duration_to_str(duration: u64) -> String {
     format!("{}:{:02}", duration / 60, duration % 60)
 }
 
+/// Formats a remaining play time in whole minutes, e.g. `42 min` or `1 h 05 min`.
+pub fn remaining_to_str(seconds: u64) -> String {
+    let minutes = seconds.div_ceil(60);
+    if minutes < 60 {
+        format!("{minutes} min")
+    } else {
+        format!("{} h {:02} min", minutes / 60, minutes % 60)
+    }
+}
+
+/// Formats a byte count with binary units, e.g. `1.5 MiB`.
+pub fn bytes_to_str(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Formats a unix timestamp as `YYYY-MM-DD HH:MM:SS` (UTC).
+pub fn timestamp_to_str(timestamp: i64) -> String {
+    let days = timestamp.div_euclid(86_400);
+    let seconds = timestamp.rem_euclid(86_400);
+    // days to civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Converts a UTC calendar date to the unix timestamp of its midnight.
+pub fn date_to_timestamp(year: i64, month: i64, day: i64) -> i64 {
+    // civil date to days, see http://howardhinnant.github.io/date_algorithms.html
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    (era * 146_097 + doe - 719_468) * 86_400
+}
+
+/// Seconds the local time zone is ahead of UTC at `timestamp`, 0 when it is not known.
+#[cfg(unix)]
+pub fn local_offset(timestamp: i64) -> i64 {
+    let time = timestamp as libc::time_t;
+    // SAFETY: localtime_r only writes to the tm it is given
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&time, &mut tm).is_null() {
+            return 0;
+        }
+        tm.tm_gmtoff as i64
+    }
+}
+
+/// Seconds the local time zone is ahead of UTC now, 0 when it is not known.
+#[cfg(windows)]
+pub fn local_offset(_timestamp: i64) -> i64 {
+    use windows_sys::Win32::System::Time::{GetTimeZoneInformation, TIME_ZONE_INFORMATION};
+    const TIME_ZONE_ID_DAYLIGHT: u32 = 2;
+    // SAFETY: the function only fills in the struct it is given
+    unsafe {
+        let mut info: TIME_ZONE_INFORMATION = std::mem::zeroed();
+        let bias = match GetTimeZoneInformation(&mut info) {
+            u32::MAX => return 0,
+            TIME_ZONE_ID_DAYLIGHT => info.Bias + info.DaylightBias,
+            _ => info.Bias + info.StandardBias,
+        };
+        // the bias is in minutes and counts from local time to UTC
+        -bias as i64 * 60
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn local_offset(_timestamp: i64) -> i64 {
+    0
+}
+
 pub fn playable_artwork<'a, Message, T: AudioPlayable + ?Sized>(
     playable: &'a T,
     height: u32,
@@ -13,7 +118,19 @@ pub fn playable_artwork<'a, Message, T: AudioPlayable + ?Sized>(
 where
     Message: 'a + Clone,
 {
-    if let Some(bytes) = playable.get_album_art() {
+    artwork_image(playable.get_album_art().as_ref(), height, width)
+}
+
+/// The artwork when there is one, the placeholder otherwise.
+pub fn artwork_image<'a, Message>(
+    artwork: Option<&Vec<u8>>,
+    height: u32,
+    width: u32,
+) -> iced::Element<'a, Message>
+where
+    Message: 'a + Clone,
+{
+    if let Some(bytes) = artwork {
         let handle = iced::widget::image::Handle::from_bytes(bytes.clone());
         iced::widget::Image::new(handle)
             .width(width)
@@ -27,3 +144,36 @@ where
             .into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp_to_str() {
+        assert_eq!(timestamp_to_str(0), "1970-01-01 00:00:00");
+        assert_eq!(timestamp_to_str(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(timestamp_to_str(1_700_000_000), "2023-11-14 22:13:20");
+    }
+
+    #[test]
+    fn test_remaining_to_str() {
+        assert_eq!(remaining_to_str(0), "0 min");
+        assert_eq!(remaining_to_str(2_501), "42 min");
+        assert_eq!(remaining_to_str(3_900), "1 h 05 min");
+    }
+
+    #[test]
+    fn test_bytes_to_str() {
+        assert_eq!(bytes_to_str(512), "512 B");
+        assert_eq!(bytes_to_str(1536), "1.5 KiB");
+        assert_eq!(bytes_to_str(10 * 1024 * 1024), "10.0 MiB");
+    }
+
+    #[test]
+    fn test_date_to_timestamp() {
+        assert_eq!(date_to_timestamp(1970, 1, 1), 0);
+        assert_eq!(date_to_timestamp(2000, 2, 29), 951_782_400);
+        assert_eq!(date_to_timestamp(2023, 11, 14), 1_699_920_000);
+    }
+}
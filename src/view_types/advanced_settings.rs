@@ -0,0 +1,69 @@
+use crate::{
+    app_state::state_impl::State, fonts::SANS_BOLD, storage::DatabaseStats, util::bytes_to_str,
+};
+use iced::{
+    Element, Length,
+    widget::{Button, Column, column, horizontal_rule, row, text},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Refresh,
+    Checkpoint,
+    Optimize,
+}
+
+pub fn view(state: &State) -> Element<'_, Message> {
+    let stats = state.database_stats();
+    column![
+        text("Database").font(SANS_BOLD).size(20),
+        size_rows(stats),
+        horizontal_rule(1),
+        text("Rows per table").font(SANS_BOLD).size(14),
+        Column::from_vec(
+            stats
+                .row_counts
+                .iter()
+                .map(|(table, count)| stat_row(table, count.to_string()))
+                .collect()
+        )
+        .spacing(4),
+        horizontal_rule(1),
+        row![
+            Button::new(text("Refresh"))
+                .style(iced::widget::button::secondary)
+                .on_press(Message::Refresh),
+            Button::new(text("Checkpoint WAL")).on_press(Message::Checkpoint),
+            Button::new(text("Optimize")).on_press(Message::Optimize),
+        ]
+        .spacing(10),
+        text(
+            "Checkpointing writes the WAL back into the database file. \
+             Optimizing refreshes the query statistics and compacts the file, \
+             it can take a while on large libraries."
+        )
+        .size(12),
+    ]
+    .spacing(12)
+    .max_width(480)
+    .into()
+}
+
+fn size_rows(stats: &DatabaseStats) -> Element<'_, Message> {
+    column![
+        stat_row("Database file", bytes_to_str(stats.file_size)),
+        stat_row("Write-ahead log", bytes_to_str(stats.wal_size)),
+        stat_row("Artwork", bytes_to_str(stats.artwork_size)),
+    ]
+    .spacing(4)
+    .into()
+}
+
+fn stat_row<'a>(label: &'a str, value: String) -> Element<'a, Message> {
+    row![
+        text(label).size(14).width(Length::Fill),
+        text(value).size(14)
+    ]
+    .spacing(10)
+    .into()
+}
@@ -0,0 +1,190 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    app_state::{
+        PlayableId,
+        state_impl::{AlbumGroup, State},
+    },
+    fonts::SANS_BOLD,
+    util::artwork_image,
+};
+use iced::{
+    Element, Length, Task,
+    alignment::Vertical,
+    widget::{
+        Button, Column, Row, Scrollable, column, container, image::Handle, mouse_area, row,
+        scrollable::Viewport, text,
+    },
+};
+
+const TILE_SIZE: f32 = 150.0;
+// the artwork and two lines of text under it
+const TILE_HEIGHT: f32 = 196.0;
+const SPACING: f32 = 16.0;
+// thumbnails are made at twice the tile size to stay sharp on high density screens
+const THUMBNAIL_SIZE: u32 = 300;
+// tiles read before the grid is first scrolled, enough for a large window
+const FIRST_TILES: usize = 48;
+// past this many thumbnails those of tiles scrolled away are dropped
+const MAX_THUMBNAILS: usize = 200;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    // the name of the album to show the tracks of
+    Open(String),
+    ShowTracks,
+    Scrolled(Viewport),
+    // None for a cover without artwork or artwork which could not be read
+    Thumbnail(PlayableId, Option<Handle>),
+}
+
+/// The albums of a track list as tiles with their artwork. The artwork is only read for the
+/// tiles scrolled into view and shrunk to a thumbnail off the UI thread.
+pub struct AlbumGrid {
+    // the tiles on screen, first and past the last
+    visible: (usize, usize),
+    thumbnails: HashMap<PlayableId, Option<Handle>>,
+    // covers whose thumbnail is being made
+    loading: HashSet<PlayableId>,
+}
+
+impl Default for AlbumGrid {
+    fn default() -> Self {
+        Self {
+            visible: (0, FIRST_TILES),
+            thumbnails: HashMap::new(),
+            loading: HashSet::new(),
+        }
+    }
+}
+
+impl AlbumGrid {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Scrolled(viewport) => {
+                let bounds = viewport.bounds();
+                let columns = ((bounds.width + SPACING) / (TILE_SIZE + SPACING)).max(1.0) as usize;
+                let row_height = TILE_HEIGHT + SPACING;
+                let first_row = (viewport.absolute_offset().y / row_height) as usize;
+                let rows = (bounds.height / row_height).ceil() as usize + 1;
+                self.visible = (first_row * columns, (first_row + rows) * columns);
+            }
+            Message::Thumbnail(id, thumbnail) => {
+                self.loading.remove(&id);
+                self.thumbnails.insert(id, thumbnail);
+            }
+            Message::Open(_) | Message::ShowTracks => {}
+        }
+        Task::none()
+    }
+
+    /// The covers of the tiles on screen which have no thumbnail yet, taken as being loaded.
+    pub fn missing(&mut self, albums: &[AlbumGroup]) -> Vec<PlayableId> {
+        let (first, last) = self.visible;
+        let shown: Vec<PlayableId> = albums
+            .iter()
+            .skip(first)
+            .take(last.saturating_sub(first))
+            .map(|album| album.cover)
+            .collect();
+        if self.thumbnails.len() > MAX_THUMBNAILS {
+            self.thumbnails.retain(|id, _| shown.contains(id));
+        }
+        shown
+            .into_iter()
+            .filter(|id| !self.thumbnails.contains_key(id) && self.loading.insert(*id))
+            .collect()
+    }
+
+    /// Drops the thumbnails of covers whose artwork changed, they are made again when shown.
+    pub fn forget(&mut self, ids: &[PlayableId]) {
+        self.thumbnails.retain(|id, _| !ids.contains(id));
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let albums = state.albums();
+        let header = row![
+            text(format!("{} albums", albums.len()))
+                .size(15)
+                .width(Length::Fill),
+            Button::new(text("Tracks").size(13))
+                .style(iced::widget::button::secondary)
+                .on_press(Message::ShowTracks),
+        ]
+        .align_y(Vertical::Center)
+        .padding([0, 20]);
+        let tiles = Row::with_children(albums.into_iter().map(|album| self.tile(album)))
+            .spacing(SPACING)
+            .wrap()
+            .vertical_spacing(SPACING);
+        column![
+            header,
+            Scrollable::new(container(tiles).padding([0, 20]).width(Length::Fill))
+                .on_scroll(Message::Scrolled)
+                .height(Length::Fill),
+        ]
+        .spacing(16)
+        .into()
+    }
+
+    fn tile<'a>(&'a self, album: AlbumGroup) -> Element<'a, Message> {
+        let artwork: Element<'a, Message> = match self.thumbnails.get(&album.cover) {
+            Some(Some(handle)) => iced::widget::image(handle.clone())
+                .width(TILE_SIZE)
+                .height(TILE_SIZE)
+                .into(),
+            _ => artwork_image(None, TILE_SIZE as u32, TILE_SIZE as u32),
+        };
+        let name = if album.name.is_empty() {
+            "Unknown Album".to_string()
+        } else {
+            album.name.clone()
+        };
+        let artist = if album.artist.is_empty() {
+            "Various Artists".to_string()
+        } else {
+            album.artist
+        };
+        let tracks = if album.tracks == 1 {
+            "1 track".to_string()
+        } else {
+            format!("{} tracks", album.tracks)
+        };
+        let year = album
+            .released
+            .as_deref()
+            .and_then(|released| released.get(..4))
+            .map(|year| format!("{year} · "))
+            .unwrap_or_default();
+        let tile = Column::new()
+            .push(artwork)
+            .push(
+                text(name)
+                    .font(SANS_BOLD)
+                    .size(13)
+                    .wrapping(text::Wrapping::None),
+            )
+            .push(
+                text(format!("{artist} · {year}{tracks}"))
+                    .size(12)
+                    .style(text::secondary)
+                    .wrapping(text::Wrapping::None),
+            )
+            .spacing(4)
+            .width(TILE_SIZE)
+            .height(TILE_HEIGHT)
+            .clip(true);
+        mouse_area(tile).on_press(Message::Open(album.name)).into()
+    }
+}
+
+/// Shrinks the artwork of a cover to a thumbnail for its tile.
+pub async fn thumbnail(artwork: Option<Vec<u8>>) -> Option<Handle> {
+    let artwork = image::load_from_memory(&artwork?).ok()?;
+    let thumbnail = artwork.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgba8();
+    Some(Handle::from_rgba(
+        thumbnail.width(),
+        thumbnail.height(),
+        thumbnail.into_raw(),
+    ))
+}
@@ -0,0 +1,144 @@
+use crate::{
+    app_state::{AudioPlayable, PlayableId, state_impl::State},
+    fonts::SANS_BOLD,
+    storage::AlbumTrack,
+    util::{artwork_image, duration_to_str, remaining_to_str},
+};
+use iced::{
+    Element, Length, Padding,
+    alignment::{Horizontal, Vertical},
+    widget::{Button, Column, Scrollable, column, container, horizontal_rule, row, text},
+};
+
+const ARTWORK_SIZE: u32 = 200;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    // the whole album, shuffled when true
+    Play(bool),
+    // row index and playable id of a track
+    PlayTrack(usize, PlayableId),
+    ShowAlbums,
+}
+
+/// An album with its tracks in the order of the release, opened from its name anywhere.
+pub fn view(state: &State) -> Element<'_, Message> {
+    let album = state.album();
+    if album.tracks.is_empty() {
+        return text("No tracks of this album in the library").into();
+    }
+    let name = if album.name.is_empty() {
+        "Unknown Album"
+    } else {
+        album.name.as_str()
+    };
+    let artist = if album.artist.is_empty() {
+        "Various Artists"
+    } else {
+        album.artist.as_str()
+    };
+    let mut details = Vec::new();
+    if let Some(year) = album.year {
+        details.push(year.to_string());
+    }
+    if !album.label.is_empty() {
+        details.push(album.label.clone());
+    }
+    details.push(if album.tracks.len() == 1 {
+        "1 track".to_string()
+    } else {
+        format!("{} tracks", album.tracks.len())
+    });
+    details.push(remaining_to_str(album.duration()));
+
+    let header = row![
+        artwork_image(state.album_artwork(), ARTWORK_SIZE, ARTWORK_SIZE),
+        column![
+            text(name).font(SANS_BOLD).size(24),
+            text(artist).size(16),
+            text(details.join(" · ")).size(13).style(text::secondary),
+            row![
+                Button::new(text("Play").size(14)).on_press(Message::Play(false)),
+                Button::new(text("Shuffle").size(14))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::Play(true)),
+                Button::new(text("Albums").size(14))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::ShowAlbums),
+            ]
+            .spacing(10),
+        ]
+        .spacing(8),
+    ]
+    .spacing(20)
+    .align_y(Vertical::Bottom);
+
+    // discs only get a heading when there is more than one
+    let discs = album
+        .tracks
+        .iter()
+        .filter_map(|track| track.disc)
+        .max()
+        .unwrap_or(0);
+    let mut tracklist = Column::new().spacing(2);
+    let mut disc = None;
+    for (index, track) in album.tracks.iter().enumerate() {
+        if discs > 1 && track.disc != disc {
+            disc = track.disc;
+            let heading = match disc {
+                Some(disc) => format!("Disc {disc}"),
+                None => "Other tracks".to_string(),
+            };
+            tracklist = tracklist.push(container(text(heading).font(SANS_BOLD).size(14)).padding(
+                Padding {
+                    top: 12.0,
+                    bottom: 4.0,
+                    ..Padding::default()
+                },
+            ));
+        }
+        let playing = state.player.current_playable == Some(track.playable.id);
+        tracklist = tracklist.push(track_row(index, track, &album.artist, playing));
+    }
+
+    column![
+        header,
+        horizontal_rule(1),
+        Scrollable::new(tracklist.padding(Padding::default().right(16))).height(Length::Fill),
+    ]
+    .spacing(16)
+    .into()
+}
+
+fn track_row<'a>(
+    index: usize,
+    track: &'a AlbumTrack,
+    album_artist: &str,
+    playing: bool,
+) -> Element<'a, Message> {
+    let playable = &track.playable;
+    let number = track.track.map(|n| n.to_string()).unwrap_or_default();
+    let mut title = text(playable.get_title()).size(14);
+    if playing {
+        title = title.font(SANS_BOLD);
+    }
+    let mut title = column![title];
+    // tracks by someone else than the album artist, e.g. on a compilation
+    if playable.get_artist() != album_artist {
+        title = title.push(text(playable.get_artist()).size(12).style(text::secondary));
+    }
+    Button::new(
+        row![
+            text(number).size(14).width(32),
+            title.width(Length::Fill),
+            container(text(duration_to_str(playable.get_duration())).size(13))
+                .align_x(Horizontal::Right),
+        ]
+        .spacing(12)
+        .align_y(Vertical::Center),
+    )
+    .width(Length::Fill)
+    .style(iced::widget::button::text)
+    .on_press(Message::PlayTrack(index, playable.id))
+    .into()
+}
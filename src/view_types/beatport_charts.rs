@@ -0,0 +1,174 @@
+use crate::{
+    beatport::{BeatportClient, ChartTrack, Genre},
+    fonts::SANS_BOLD,
+    storage::Playable,
+};
+use iced::{
+    Element, Length, Task,
+    alignment::Vertical,
+    widget::{Button, Column, column, pick_list, row, text},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    GenresLoaded(Result<Vec<Genre>, String>),
+    GenreSelected(Genre),
+    Loaded(Genre, Result<Vec<ChartTrack>, String>),
+    // plays the samples of the chart from the track at the index on
+    Play(usize),
+    // saves the samples as a playlist, done by the app
+    SaveAsPlaylist,
+    CopyLink(String),
+}
+
+/// The Beatport Top 100 of a genre, with the samples of the store to preview the tracks.
+#[derive(Default)]
+pub struct BeatportCharts {
+    client: BeatportClient,
+    // read from the store the first time the tab is shown
+    genres: Vec<Genre>,
+    genre: Option<Genre>,
+    loading: bool,
+    tracks: Vec<ChartTrack>,
+    status: Option<String>,
+}
+
+impl BeatportCharts {
+    pub fn genre(&self) -> Option<&Genre> {
+        self.genre.as_ref()
+    }
+
+    /// Reads the genres from the store unless they are known or on their way.
+    pub fn load_genres(&mut self) -> Task<Message> {
+        if !self.genres.is_empty() || self.loading {
+            return Task::none();
+        }
+        self.loading = true;
+        self.status = None;
+        Task::perform(self.client.clone().genres(), |result| {
+            Message::GenresLoaded(result.map_err(|e| e.to_string()))
+        })
+    }
+
+    /// The samples of the chart from the track at `index` on, tracks without one are left out.
+    pub fn samples_from(&self, index: usize) -> Vec<Playable> {
+        self.tracks
+            .iter()
+            .skip(index)
+            .filter_map(ChartTrack::to_playable)
+            .collect()
+    }
+
+    pub fn set_status(&mut self, status: String) {
+        self.status = Some(status);
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::GenresLoaded(result) => {
+                self.loading = false;
+                match result {
+                    Ok(genres) => self.genres = genres,
+                    Err(error) => self.status = Some(error),
+                }
+            }
+            Message::GenreSelected(genre) => {
+                self.genre = Some(genre.clone());
+                self.loading = true;
+                self.tracks.clear();
+                self.status = None;
+                return Task::perform(self.client.clone().top_100(genre.clone()), move |result| {
+                    Message::Loaded(genre, result.map_err(|e| e.to_string()))
+                });
+            }
+            // a chart picked before this one came late
+            Message::Loaded(genre, _) if self.genre.as_ref() != Some(&genre) => {}
+            Message::Loaded(_, result) => {
+                self.loading = false;
+                match result {
+                    Ok(tracks) => self.tracks = tracks,
+                    Err(error) => self.status = Some(error),
+                }
+            }
+            Message::CopyLink(link) => return iced::clipboard::write(link),
+            Message::Play(_) | Message::SaveAsPlaylist => {}
+        }
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let samples = self
+            .tracks
+            .iter()
+            .filter(|track| track.sample_url.is_some())
+            .count();
+        let genres = pick_list(
+            self.genres.as_slice(),
+            self.genre.clone(),
+            Message::GenreSelected,
+        )
+        .placeholder("Pick a genre")
+        .width(Length::Fixed(310.0));
+        let mut content = column![
+            row![
+                genres,
+                Button::new(text("Save as playlist"))
+                    .style(iced::widget::button::secondary)
+                    .on_press_maybe((samples > 0).then_some(Message::SaveAsPlaylist)),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            text(
+                "Beatport plays a short sample of each track, the playlist saved keeps the \
+                 samples."
+            )
+            .size(12),
+        ]
+        .spacing(12);
+        if self.loading {
+            let loading = if self.genres.is_empty() {
+                "Loading the genres…"
+            } else {
+                "Loading the chart…"
+            };
+            content = content.push(text(loading).size(12));
+        }
+        if let Some(status) = &self.status {
+            content = content.push(text(status).size(12));
+        }
+        let tracks = Column::with_children(self.tracks.iter().enumerate().map(|(index, track)| {
+            let details: Vec<String> = [
+                track.label.clone(),
+                (track.bpm > 0)
+                    .then(|| format!("{} BPM", track.bpm))
+                    .unwrap_or_default(),
+                track.key.clone(),
+            ]
+            .into_iter()
+            .filter(|detail| !detail.is_empty())
+            .collect();
+            row![
+                text(format!("{:>3}", track.position))
+                    .font(SANS_BOLD)
+                    .size(12)
+                    .width(Length::Fixed(30.0)),
+                column![
+                    text(format!("{} - {}", track.artists, track.name())).size(12),
+                    text(details.join(" · ")).size(11),
+                ]
+                .width(Length::Fill),
+                Button::new(text("Play").size(12))
+                    .style(iced::widget::button::primary)
+                    .on_press_maybe(track.sample_url.is_some().then_some(Message::Play(index))),
+                Button::new(text("Copy link").size(12))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::CopyLink(track.url())),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center)
+            .into()
+        }))
+        .spacing(6);
+        content.push(tracks).into()
+    }
+}
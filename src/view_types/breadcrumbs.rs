@@ -0,0 +1,83 @@
+use crate::{
+    app_state::state_impl::State,
+    fonts::{ICON, SANS_BOLD},
+    icons::{ICON_CHEVRON_LEFT, ICON_CHEVRON_RIGHT},
+    navigation::History,
+};
+use iced::{
+    Element, Subscription,
+    alignment::Vertical,
+    event,
+    keyboard::{self, key::Named},
+    widget::{Button, Row, text},
+};
+
+// sections visited before the current one shown in the trail
+const TRAIL_LENGTH: usize = 4;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Back,
+    Forward,
+    // a section of the trail, by its index in the history
+    BackTo(usize),
+}
+
+/// Back and forward buttons with the last sections visited, the current one last.
+pub fn view<'a>(history: &History, state: &State) -> Element<'a, Message> {
+    let arrow = |icon: char, message: Option<Message>| {
+        Button::new(text(icon).font(ICON).size(14))
+            .style(iced::widget::button::text)
+            .padding([2, 4])
+            .on_press_maybe(message)
+    };
+    let mut crumbs = Row::new()
+        .spacing(6)
+        .align_y(Vertical::Center)
+        .push(arrow(
+            ICON_CHEVRON_LEFT,
+            history.can_go_back().then_some(Message::Back),
+        ))
+        .push(arrow(
+            ICON_CHEVRON_RIGHT,
+            history.can_go_forward().then_some(Message::Forward),
+        ));
+    let trail = history.trail();
+    let first = trail.len().saturating_sub(TRAIL_LENGTH);
+    if first > 0 {
+        crumbs = crumbs.push(text("…").size(13));
+    }
+    for (index, section) in trail.iter().enumerate().skip(first) {
+        crumbs = crumbs
+            .push(
+                Button::new(text(state.section_title(section)).size(13))
+                    .style(iced::widget::button::text)
+                    .padding([2, 4])
+                    .on_press(Message::BackTo(index)),
+            )
+            .push(text("›").size(13));
+    }
+    crumbs
+        .push(
+            text(state.section_title(state.section()))
+                .font(SANS_BOLD)
+                .size(13),
+        )
+        .into()
+}
+
+/// Alt+Left and Alt+Right go back and forward.
+pub fn subscription() -> Subscription<Message> {
+    event::listen_with(|event, status, _| match event {
+        iced::Event::Keyboard(keyboard::Event::KeyPressed {
+            key: keyboard::Key::Named(key),
+            modifiers,
+            ..
+        }) if modifiers.alt() && status == event::Status::Ignored => match key {
+            Named::ArrowLeft => Some(Message::Back),
+            Named::ArrowRight => Some(Message::Forward),
+            _ => None,
+        },
+        _ => None,
+    })
+}
@@ -0,0 +1,434 @@
+use crate::{
+    app_state::state_impl::State,
+    discogs::{self, Credentials, DigFilters, Release},
+    fonts::SANS_BOLD,
+    storage::Playable,
+    view_types::{
+        beatport_charts::{self, BeatportCharts},
+        discogs_connect::{self, DiscogsConnect},
+    },
+};
+use iced::{
+    Element, Length, Subscription, Task,
+    alignment::Vertical,
+    widget::{
+        Button, Column, Scrollable, column, horizontal_rule, mouse_area, row, text, text_input,
+    },
+};
+use std::time::{Duration, Instant};
+
+// releases dug in one session, the oldest are dropped past this
+const MAX_DUG: usize = 50;
+// how long the pointer rests on a track before its preview starts
+const PREVIEW_DELAY: Duration = Duration::from_secs(1);
+// how often a resting pointer is checked
+const HOVER_TICK: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tab {
+    #[default]
+    Digger,
+    Charts,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ShowTab(Tab),
+    Charts(beatport_charts::Message),
+    GenreChanged(String),
+    StyleChanged(String),
+    YearFromChanged(String),
+    YearToChanged(String),
+    CountryChanged(String),
+    TokenChanged(String),
+    // an empty token forgets the saved one
+    SaveToken(String),
+    Connect(discogs_connect::Message),
+    Disconnect,
+    Dig,
+    // fetches a release by its Discogs id, e.g. a new one from a watched label
+    Open(i64),
+    Dug(Result<Release, String>),
+    // plays the tracks of the shown release found in the library
+    Preview,
+    // shows a release dug earlier, by its index
+    Show(usize),
+    CopyLink(String),
+    // the pointer entered or left a track of the shown release, by its index
+    Hover(usize),
+    Unhover(usize),
+    HoverTick,
+}
+
+/// Digs a random release out of Discogs, picked from those matching the filters, or lists
+/// the Beatport charts on the other tab.
+#[derive(Default)]
+pub struct BrowseView {
+    tab: Tab,
+    charts: BeatportCharts,
+    genre: String,
+    style: String,
+    year_from: String,
+    year_to: String,
+    country: String,
+    token: String,
+    connect: DiscogsConnect,
+    digging: bool,
+    // newest first
+    dug: Vec<Release>,
+    shown: Option<usize>,
+    // the library's copy of each track of the shown release
+    found: Vec<Option<Playable>>,
+    // the track under the pointer and since when
+    hovered: Option<(usize, Instant)>,
+    // the track whose preview plays
+    previewing: Option<usize>,
+    status: Option<String>,
+}
+
+impl BrowseView {
+    fn filters(&self) -> DigFilters {
+        DigFilters {
+            genre: self.genre.clone(),
+            style: self.style.clone(),
+            year_from: self.year_from.trim().parse().ok(),
+            year_to: self.year_to.trim().parse().ok(),
+            country: self.country.clone(),
+        }
+    }
+
+    pub fn charts(&self) -> &BeatportCharts {
+        &self.charts
+    }
+
+    pub fn charts_mut(&mut self) -> &mut BeatportCharts {
+        &mut self.charts
+    }
+
+    pub fn shown(&self) -> Option<&Release> {
+        self.dug.get(self.shown?)
+    }
+
+    pub fn set_found(&mut self, found: Vec<Option<Playable>>) {
+        self.found = found;
+    }
+
+    pub fn found(&self) -> Vec<Playable> {
+        self.found.iter().flatten().cloned().collect()
+    }
+
+    pub fn previewing(&self) -> Option<usize> {
+        self.previewing
+    }
+
+    /// The library's copy of the hovered track once the pointer rested on it long enough,
+    /// returned once per hover.
+    pub fn preview_due(&mut self) -> Option<Playable> {
+        let (index, since) = self.hovered?;
+        if self.previewing == Some(index) || since.elapsed() < PREVIEW_DELAY {
+            return None;
+        }
+        let playable = self.found.get(index)?.clone()?;
+        self.previewing = Some(index);
+        Some(playable)
+    }
+
+    pub fn update(&mut self, message: Message, credentials: Option<Credentials>) -> Task<Message> {
+        match message {
+            Message::ShowTab(tab) => {
+                self.tab = tab;
+                self.hovered = None;
+                self.previewing = None;
+                if tab == Tab::Charts {
+                    return self.charts.load_genres().map(Message::Charts);
+                }
+            }
+            Message::Charts(message) => return self.charts.update(message).map(Message::Charts),
+            Message::GenreChanged(value) => self.genre = value,
+            Message::StyleChanged(value) => self.style = value,
+            Message::YearFromChanged(value) => self.year_from = value,
+            Message::YearToChanged(value) => self.year_to = value,
+            Message::CountryChanged(value) => self.country = value,
+            Message::TokenChanged(value) => self.token = value,
+            Message::SaveToken(_) => self.token.clear(),
+            Message::Connect(message) => return self.connect.update(message).map(Message::Connect),
+            Message::Disconnect => {}
+            Message::Dig => {
+                let Some(credentials) = credentials else {
+                    self.status = Some("Connect Discogs or save a token to dig".to_string());
+                    return Task::none();
+                };
+                self.digging = true;
+                self.status = None;
+                return Task::perform(
+                    discogs::random_release(self.filters(), credentials),
+                    |result| Message::Dug(result.map_err(|e| e.to_string())),
+                );
+            }
+            Message::Open(id) => {
+                self.digging = true;
+                self.status = None;
+                return Task::perform(discogs::fetch_release(id.to_string()), |result| {
+                    Message::Dug(result.map_err(|e| e.to_string()))
+                });
+            }
+            Message::Dug(result) => {
+                self.digging = false;
+                match result {
+                    Ok(release) => {
+                        self.dug.insert(0, release);
+                        self.dug.truncate(MAX_DUG);
+                        self.shown = Some(0);
+                        self.hovered = None;
+                        self.previewing = None;
+                    }
+                    Err(error) => self.status = Some(error),
+                }
+            }
+            Message::Show(index) => {
+                self.shown = Some(index);
+                self.hovered = None;
+                self.previewing = None;
+            }
+            Message::Hover(index) => self.hovered = Some((index, Instant::now())),
+            Message::Unhover(index) => {
+                if self.hovered.is_some_and(|(hovered, _)| hovered == index) {
+                    self.hovered = None;
+                }
+                if self.previewing == Some(index) {
+                    self.previewing = None;
+                }
+            }
+            Message::HoverTick => {}
+            Message::CopyLink(link) => return iced::clipboard::write(link),
+            Message::Preview => {}
+        }
+        Task::none()
+    }
+
+    /// Ticks while the pointer rests on a track of the library which is not previewed yet.
+    pub fn subscription(&self) -> Subscription<Message> {
+        match self.hovered {
+            Some((index, _))
+                if self.previewing != Some(index)
+                    && self.found.get(index).is_some_and(Option::is_some) =>
+            {
+                iced::time::every(HOVER_TICK).map(|_| Message::HoverTick)
+            }
+            _ => Subscription::none(),
+        }
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let tabs = row([
+            ("Random Digger", Tab::Digger),
+            ("Beatport Top 100", Tab::Charts),
+        ]
+        .into_iter()
+        .map(|(label, tab)| {
+            Button::new(text(label))
+                .style(if self.tab == tab {
+                    iced::widget::button::primary
+                } else {
+                    iced::widget::button::text
+                })
+                .on_press(Message::ShowTab(tab))
+                .into()
+        }))
+        .spacing(4);
+        let content = match self.tab {
+            Tab::Digger => self.digger(state),
+            Tab::Charts => self.charts.view().map(Message::Charts),
+        };
+        Scrollable::new(column![tabs, content].spacing(12).max_width(640)).into()
+    }
+
+    fn digger<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let token_row = if state.discogs_connected() {
+            row![
+                text("Connected to Discogs").size(12),
+                Button::new(text("Disconnect").size(12))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::Disconnect),
+            ]
+        } else if self.connect.is_open() {
+            row![self.connect.view().map(Message::Connect)]
+        } else {
+            match state.discogs_token() {
+                Some(_) => row![
+                    text("Searching Discogs with your token").size(12),
+                    Button::new(text("Forget").size(12))
+                        .style(iced::widget::button::secondary)
+                        .on_press(Message::SaveToken(String::new())),
+                ],
+                None => row![
+                    text_input("Discogs personal access token", &self.token)
+                        .on_input(Message::TokenChanged)
+                        .width(Length::Fixed(310.0)),
+                    Button::new(text("Save"))
+                        .style(iced::widget::button::secondary)
+                        .on_press_maybe(
+                            (!self.token.trim().is_empty())
+                                .then(|| Message::SaveToken(self.token.clone()))
+                        ),
+                    text("or").size(12),
+                    Button::new(text("Connect Discogs"))
+                        .style(iced::widget::button::primary)
+                        .on_press(Message::Connect(discogs_connect::Message::Open)),
+                ],
+            }
+        }
+        .spacing(10)
+        .align_y(Vertical::Center);
+        let dig = Button::new(text(if self.digging {
+            "Digging…"
+        } else {
+            "Dig a random release"
+        }))
+        .style(iced::widget::button::primary)
+        .on_press_maybe(
+            (!self.digging && state.discogs_credentials().is_some()).then_some(Message::Dig),
+        );
+
+        let mut content = column![
+            text("Random Digger").font(SANS_BOLD).size(20),
+            filter("Genre", "Electronic", &self.genre, Message::GenreChanged),
+            filter("Style", "Deep House", &self.style, Message::StyleChanged),
+            row![
+                text("Years").size(14).width(Length::Fixed(100.0)),
+                text_input("1990", &self.year_from)
+                    .on_input(Message::YearFromChanged)
+                    .width(Length::Fixed(90.0)),
+                text("to").size(14),
+                text_input("1999", &self.year_to)
+                    .on_input(Message::YearToChanged)
+                    .width(Length::Fixed(90.0)),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            filter("Country", "Germany", &self.country, Message::CountryChanged),
+            token_row,
+            text(
+                "Searching needs Discogs to be connected, or a personal access token made under \
+                 Developers in the Discogs settings. Empty filters match everything."
+            )
+            .size(12),
+            dig,
+        ]
+        .spacing(12);
+        if let Some(status) = &self.status {
+            content = content.push(text(status).size(12));
+        }
+        if let Some(release) = self.shown() {
+            content = content.push(horizontal_rule(1)).push(self.release(release));
+        }
+        if self.dug.len() > 1 {
+            content = content
+                .push(horizontal_rule(1))
+                .push(text("Dug this session").font(SANS_BOLD).size(14))
+                .push(Column::with_children(self.dug.iter().enumerate().map(
+                    |(index, release)| {
+                        Button::new(text(format!("{} - {}", release.artist(), release.title)))
+                            .style(if self.shown == Some(index) {
+                                iced::widget::button::primary
+                            } else {
+                                iced::widget::button::text
+                            })
+                            .on_press(Message::Show(index))
+                            .into()
+                    },
+                )));
+        }
+        content.into()
+    }
+
+    fn release<'a>(&'a self, release: &'a Release) -> Element<'a, Message> {
+        let year = (release.year > 0).then(|| release.year.to_string());
+        let details: Vec<String> = year
+            .into_iter()
+            .chain(release.country.clone())
+            .chain(release.genres.iter().chain(&release.styles).cloned())
+            .filter(|detail| !detail.is_empty())
+            .collect();
+
+        let tracks = Column::with_children(release.side("").into_iter().enumerate().map(
+            |(index, track)| {
+                let owned = self.found.get(index).is_some_and(Option::is_some);
+                let previewing = self.previewing == Some(index);
+                let line = text(format!(
+                    "{}  {}{}",
+                    track.position,
+                    track.title,
+                    if previewing {
+                        "  (previewing)"
+                    } else if owned {
+                        "  (in your library)"
+                    } else {
+                        ""
+                    }
+                ))
+                .size(12)
+                .style(if previewing {
+                    text::primary
+                } else {
+                    text::default
+                });
+                mouse_area(line)
+                    .on_enter(Message::Hover(index))
+                    .on_exit(Message::Unhover(index))
+                    .into()
+            },
+        ))
+        .spacing(2);
+        let videos = Column::with_children(release.videos.iter().map(|video| {
+            row![
+                text(&video.title).size(12).width(Length::Fill),
+                Button::new(text("Copy link").size(12))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::CopyLink(video.uri.clone())),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center)
+            .into()
+        }))
+        .spacing(4);
+        let owned = self.found.iter().flatten().count();
+
+        column![
+            text(format!("{} - {}", release.artist(), release.title))
+                .font(SANS_BOLD)
+                .size(16),
+            text(details.join(" · ")).size(12),
+            row![
+                Button::new(text(format!("Preview {owned} from library")))
+                    .style(iced::widget::button::primary)
+                    .on_press_maybe((owned > 0).then_some(Message::Preview)),
+                Button::new(text("Copy Discogs link"))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::CopyLink(release.url())),
+            ]
+            .spacing(10),
+            tracks,
+            videos,
+        ]
+        .spacing(10)
+        .into()
+    }
+}
+
+fn filter<'a>(
+    label: &'a str,
+    placeholder: &'a str,
+    value: &'a str,
+    on_input: fn(String) -> Message,
+) -> Element<'a, Message> {
+    row![
+        text(label).size(14).width(Length::Fixed(100.0)),
+        text_input(placeholder, value)
+            .on_input(on_input)
+            .width(Length::Fixed(200.0)),
+    ]
+    .spacing(10)
+    .align_y(Vertical::Center)
+    .into()
+}
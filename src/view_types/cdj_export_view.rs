@@ -0,0 +1,270 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    cdj_export::{self, ExportItem},
+    fonts::SANS_BOLD,
+    storage::Playable,
+    view_types::conversions_view::JobStatus,
+};
+use iced::{
+    Element, Length, Padding, Task,
+    alignment::Vertical,
+    widget::{Button, Column, Scrollable, column, horizontal_rule, row, text, text_input, toggler},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    DriveChanged(String),
+    PickDrive,
+    DrivePicked(Option<PathBuf>),
+    // the players read FLAC
+    Flac(bool),
+    // the FLAC setting the files were checked with
+    Checked(bool, Vec<ExportItem>),
+    Export,
+    // index of the item and how it went
+    Exported(usize, Result<(), String>),
+}
+
+/// Checks a playlist against what CDJs play, then copies it onto a USB drive, converting and
+/// renaming the files which need it. One file is written at a time.
+#[derive(Default)]
+pub struct CdjExportView {
+    playlist: String,
+    playables: Vec<Playable>,
+    drive: String,
+    flac: bool,
+    checking: bool,
+    items: Vec<ExportItem>,
+    // one per item once the export started
+    statuses: Vec<JobStatus>,
+    // the playlist file written when the export is done
+    result: Option<Result<PathBuf, String>>,
+}
+
+impl CdjExportView {
+    /// Starts over with the tracks of a playlist, the drive and the FLAC setting are kept.
+    pub fn open(&mut self, playlist: String, playables: Vec<Playable>) -> Task<Message> {
+        self.playlist = playlist;
+        self.playables = playables;
+        self.check()
+    }
+
+    fn check(&mut self) -> Task<Message> {
+        self.checking = true;
+        self.items.clear();
+        self.statuses.clear();
+        self.result = None;
+        let flac = self.flac;
+        Task::perform(
+            cdj_export::check(self.playables.clone(), flac),
+            move |items| Message::Checked(flac, items),
+        )
+    }
+
+    fn exporting(&self) -> bool {
+        self.statuses
+            .iter()
+            .any(|status| matches!(status, JobStatus::Queued | JobStatus::Running))
+    }
+
+    fn start_next(&mut self) -> Task<Message> {
+        let drive = PathBuf::from(self.drive.trim());
+        let Some(index) = self
+            .statuses
+            .iter()
+            .position(|status| *status == JobStatus::Queued)
+        else {
+            self.result = Some(self.write_playlist(&drive));
+            return Task::none();
+        };
+        self.statuses[index] = JobStatus::Running;
+        Task::perform(
+            cdj_export::export(self.items[index].clone(), drive),
+            move |result| Message::Exported(index, result.map_err(|e| e.to_string())),
+        )
+    }
+
+    // the playlist only lists the files which made it onto the drive
+    fn write_playlist(&self, drive: &Path) -> Result<PathBuf, String> {
+        let exported: Vec<ExportItem> = self
+            .items
+            .iter()
+            .zip(&self.statuses)
+            .filter(|(_, status)| **status == JobStatus::Done)
+            .map(|(item, _)| item.clone())
+            .collect();
+        let path = cdj_export::playlist_path(drive, &self.playlist);
+        std::fs::write(&path, cdj_export::playlist(&self.playlist, &exported))
+            .map(|_| path)
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::DriveChanged(value) => self.drive = value,
+            Message::PickDrive => {
+                return Task::perform(
+                    async {
+                        rfd::AsyncFileDialog::new()
+                            .pick_folder()
+                            .await
+                            .map(|folder| folder.path().to_path_buf())
+                    },
+                    Message::DrivePicked,
+                );
+            }
+            Message::DrivePicked(Some(folder)) => self.drive = folder.to_string_lossy().to_string(),
+            Message::DrivePicked(None) => {}
+            Message::Flac(value) => {
+                if !self.exporting() {
+                    self.flac = value;
+                    return self.check();
+                }
+            }
+            // a check made before the setting changed again
+            Message::Checked(flac, _) if flac != self.flac => {}
+            Message::Checked(_, items) => {
+                self.checking = false;
+                self.items = items;
+            }
+            Message::Export => {
+                if self.exporting() || self.drive.trim().is_empty() {
+                    return Task::none();
+                }
+                self.result = None;
+                self.statuses = self
+                    .items
+                    .iter()
+                    .map(|item| {
+                        if item.is_missing() {
+                            JobStatus::Failed(cdj_export::Issue::Missing.to_string())
+                        } else {
+                            JobStatus::Queued
+                        }
+                    })
+                    .collect();
+                return self.start_next();
+            }
+            Message::Exported(index, result) => {
+                if let Some(status) = self.statuses.get_mut(index) {
+                    *status = match result {
+                        Ok(()) => JobStatus::Done,
+                        Err(error) => JobStatus::Failed(error),
+                    };
+                }
+                return self.start_next();
+            }
+        }
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let count =
+            |filter: fn(&ExportItem) -> bool| self.items.iter().filter(|i| filter(i)).count();
+        let summary = if self.checking {
+            "Checking the files…".to_string()
+        } else {
+            format!(
+                "{} tracks: {} to convert, {} to rename, {} missing",
+                self.items.len(),
+                count(|item| item.conversion.is_some() && !item.is_missing()),
+                count(ExportItem::is_renamed),
+                count(ExportItem::is_missing),
+            )
+        };
+        let done = self
+            .statuses
+            .iter()
+            .filter(|status| **status == JobStatus::Done)
+            .count();
+        let progress = match &self.result {
+            Some(Ok(path)) => format!(
+                "Exported {done} of {} tracks, the playlist is saved as {}",
+                self.items.len(),
+                path.to_string_lossy()
+            ),
+            Some(Err(error)) => format!("The playlist could not be saved: {error}"),
+            None if self.exporting() => format!("Exported {done} of {} tracks…", self.items.len()),
+            None => String::new(),
+        };
+        let export = (!self.checking && !self.exporting() && !self.drive.trim().is_empty())
+            .then_some(Message::Export);
+
+        let settings = column![
+            text(format!("Export “{}” for CDJ", self.playlist))
+                .font(SANS_BOLD)
+                .size(20),
+            row![
+                text_input("USB drive", &self.drive).on_input(Message::DriveChanged),
+                Button::new(text("Choose…"))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::PickDrive),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            toggler(self.flac)
+                .label("The players read FLAC (CDJ-2000NXS2, CDJ-3000 and newer)")
+                .on_toggle(Message::Flac),
+            text(
+                "Files the players can not read are converted, which needs ffmpeg to be \
+                 installed. The tracks are copied into the Contents folder of the drive and \
+                 the playlist next to it, import it in rekordbox to analyze the tracks."
+            )
+            .size(12),
+            row![
+                text(summary).size(14).width(Length::Fill),
+                Button::new(text("Export"))
+                    .style(iced::widget::button::primary)
+                    .on_press_maybe(export),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            text(progress).size(12),
+        ]
+        .spacing(12)
+        .max_width(560);
+
+        let mut checklist = Column::new()
+            .spacing(8)
+            .padding(Padding::default().right(16));
+        for (index, item) in self.items.iter().enumerate() {
+            let mut notes: Vec<String> = item.issues.iter().map(|i| i.to_string()).collect();
+            if !item.is_missing()
+                && let Some(conversion) = item.conversion
+            {
+                notes.push(conversion.to_string());
+            }
+            let status = match self.statuses.get(index) {
+                Some(JobStatus::Queued) => "Queued".to_string(),
+                Some(JobStatus::Running) => "Exporting…".to_string(),
+                Some(JobStatus::Done) => "Done".to_string(),
+                Some(JobStatus::Failed(error)) => format!("Failed: {error}"),
+                None if item.issues.is_empty() => "Ready".to_string(),
+                None => String::new(),
+            };
+            checklist = checklist.push(
+                row![
+                    column![
+                        text(&item.title).font(SANS_BOLD).size(14),
+                        text(item.target.to_string_lossy()).size(12),
+                        text(notes.join(" · ")).size(12).style(text::secondary),
+                    ]
+                    .spacing(2)
+                    .width(Length::Fill),
+                    text(status).size(12),
+                ]
+                .spacing(12)
+                .align_y(Vertical::Center),
+            );
+        }
+
+        column![
+            settings,
+            horizontal_rule(1),
+            Scrollable::new(checklist).height(Length::Fill),
+        ]
+        .spacing(12)
+        .into()
+    }
+}
@@ -0,0 +1,281 @@
+use crate::{
+    app_state::{Section, state_impl::State},
+    storage::AutoPlaylist,
+};
+use iced::{
+    Element, Length, Padding, Subscription, Task,
+    alignment::Vertical,
+    event,
+    keyboard::{self, key::Named},
+    widget::{Button, Column, container, row, text, text_input},
+};
+
+static INPUT_ID: &str = "command_palette";
+// entries listed under the input
+const MAX_RESULTS: usize = 8;
+// characters skipped between two matching ones cost this much at most
+const MAX_GAP_PENALTY: usize = 3;
+
+const SECTIONS: [Section; 15] = [
+    Section::ListenNow,
+    Section::Browse,
+    Section::Library,
+    Section::Favorites,
+    Section::RecentlyPlayed,
+    Section::Queue,
+    Section::ImportHistory,
+    Section::RecentlyDeleted,
+    Section::Snapshots,
+    Section::Conversions,
+    Section::Insights,
+    Section::Auto(AutoPlaylist::MostPlayed),
+    Section::Auto(AutoPlaylist::RecentlyLiked),
+    Section::Auto(AutoPlaylist::NeverPlayed),
+    Section::Auto(AutoPlaylist::TopRated),
+];
+
+const COMMANDS: [(&str, Action); 10] = [
+    ("Create playlist", Action::CreatePlaylist),
+    ("Play / pause", Action::TogglePlay),
+    ("Next track", Action::Next),
+    ("Previous track", Action::Previous),
+    ("Toggle shuffle", Action::ToggleShuffle),
+    ("Toggle theme", Action::ToggleTheme),
+    ("Toggle split pane", Action::ToggleSplit),
+    ("Add folder…", Action::AddFolder),
+    ("Import playlist…", Action::ImportPlaylist),
+    ("Settings", Action::Settings),
+];
+
+/// What an entry of the palette does, carried out by the app.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    Open(Section),
+    CreatePlaylist,
+    TogglePlay,
+    Next,
+    Previous,
+    ToggleShuffle,
+    ToggleTheme,
+    ToggleSplit,
+    AddFolder,
+    ImportPlaylist,
+    Settings,
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub title: String,
+    // what the entry is, shown after its title
+    pub kind: &'static str,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    // Ctrl+K, opens the palette or closes it
+    Toggle,
+    Close,
+    Input(String),
+    // moves the highlight to the entry above or below
+    Up,
+    Down,
+    // runs the highlighted entry
+    Submit,
+    Run(Action),
+}
+
+/// How well `query` matches `candidate`, None unless all its characters appear in it in order.
+/// Characters following one another and those starting a word score higher, those far apart
+/// lower.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = position + candidate[position..].iter().position(|other| *other == c)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 8;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 8;
+        }
+        score -= (found - position).min(MAX_GAP_PENALTY) as i32;
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+// the sections, playlists, tags and commands the palette finds
+fn entries(state: &State) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = SECTIONS
+        .into_iter()
+        .map(|section| Entry {
+            title: section.to_string(),
+            kind: "Section",
+            action: Action::Open(section),
+        })
+        .collect();
+    // nested playlists right after the folder they are in
+    let mut playlists: Vec<_> = state.playlists().iter().rev().collect();
+    while let Some(node) = playlists.pop() {
+        entries.push(Entry {
+            title: node.value.name.clone(),
+            kind: "Playlist",
+            action: Action::Open(Section::Playlist(node.value.id)),
+        });
+        playlists.extend(node.children.iter().rev());
+    }
+    entries.extend(state.tags().iter().map(|tag| Entry {
+        title: tag.name.clone(),
+        kind: "Tag",
+        action: Action::Open(Section::Tag(tag.id)),
+    }));
+    entries.extend(COMMANDS.into_iter().map(|(title, action)| Entry {
+        title: title.to_string(),
+        kind: "Command",
+        action,
+    }));
+    entries
+}
+
+/// A bar under the menu to jump to any section, playlist or tag, or run a command, by typing
+/// part of its name.
+#[derive(Default)]
+pub struct CommandPalette {
+    open: bool,
+    query: String,
+    highlighted: usize,
+}
+
+impl CommandPalette {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// The entries matching what was typed in, best first.
+    pub fn results(&self, state: &State) -> Vec<Entry> {
+        let entries = entries(state);
+        if self.query.trim().is_empty() {
+            return entries.into_iter().take(MAX_RESULTS).collect();
+        }
+        let mut scored: Vec<(i32, Entry)> = entries
+            .into_iter()
+            .filter_map(|entry| Some((fuzzy_score(&self.query, &entry.title)?, entry)))
+            .collect();
+        // the sort is stable, entries scoring the same keep their order
+        scored.sort_by_key(|(score, _)| -score);
+        scored
+            .into_iter()
+            .take(MAX_RESULTS)
+            .map(|(_, entry)| entry)
+            .collect()
+    }
+
+    pub fn update(&mut self, message: Message, state: &State) -> Task<Message> {
+        match message {
+            Message::Toggle if !self.open => {
+                self.open = true;
+                self.query.clear();
+                self.highlighted = 0;
+                return text_input::focus(INPUT_ID);
+            }
+            Message::Toggle | Message::Close | Message::Run(_) => self.open = false,
+            Message::Input(query) => {
+                self.query = query;
+                self.highlighted = 0;
+            }
+            Message::Up => self.highlighted = self.highlighted.saturating_sub(1),
+            Message::Down => {
+                let last = self.results(state).len().saturating_sub(1);
+                self.highlighted = (self.highlighted + 1).min(last);
+            }
+            Message::Submit => {
+                if let Some(entry) = self.results(state).into_iter().nth(self.highlighted) {
+                    return Task::done(Message::Run(entry.action));
+                }
+            }
+        }
+        Task::none()
+    }
+
+    pub fn view<'a>(&'a self, state: &State) -> Element<'a, Message> {
+        let mut results = Column::new().spacing(2);
+        for (index, entry) in self.results(state).into_iter().enumerate() {
+            let style = if index == self.highlighted {
+                iced::widget::button::secondary
+            } else {
+                iced::widget::button::text
+            };
+            results = results.push(
+                Button::new(
+                    row![
+                        text(entry.title).size(14).width(Length::Fill),
+                        text(entry.kind).size(12).style(text::secondary),
+                    ]
+                    .align_y(Vertical::Center),
+                )
+                .width(Length::Fill)
+                .style(style)
+                .on_press(Message::Run(entry.action)),
+            );
+        }
+        container(
+            Column::new()
+                .push(
+                    text_input(
+                        "Go to a section, playlist or tag, or run a command",
+                        &self.query,
+                    )
+                    .id(INPUT_ID)
+                    .size(14)
+                    .on_input(Message::Input)
+                    .on_submit(Message::Submit),
+                )
+                .push(results)
+                .spacing(6)
+                .max_width(560),
+        )
+        .padding(Padding::from([8, 16]))
+        .width(Length::Fill)
+        .center_x(Length::Fill)
+        .into()
+    }
+
+    /// Ctrl+K opens and closes the palette, Escape closes it and the arrows move the highlight.
+    pub fn subscription(&self) -> Subscription<Message> {
+        let open = self.open;
+        event::listen_with(move |event, _, _| match event {
+            iced::Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+                match key.as_ref() {
+                    keyboard::Key::Character("k") if modifiers.command() => Some(Message::Toggle),
+                    keyboard::Key::Named(Named::Escape) if open => Some(Message::Close),
+                    keyboard::Key::Named(Named::ArrowUp) if open => Some(Message::Up),
+                    keyboard::Key::Named(Named::ArrowDown) if open => Some(Message::Down),
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("lib", "Recently Liked"), None);
+        assert_eq!(fuzzy_score("yrl", "Library"), None);
+        assert!(fuzzy_score("", "Library").is_some());
+        // following characters beat spread out ones
+        assert!(fuzzy_score("lib", "Library") > fuzzy_score("lib", "Lost in Babylon"));
+        // so do the starts of words
+        assert!(fuzzy_score("tmp", "Top 25 Most Played") > fuzzy_score("tmp", "Tempo"));
+        assert!(fuzzy_score("TOGGLE sh", "Toggle shuffle").is_some());
+    }
+}
@@ -1,13 +1,25 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, fmt};
 
 use crate::{
-    app_state::{AudioPlayable, PlayableId, state_impl::State},
+    app_state::{
+        AudioPlayable, PlayableId, Section,
+        state_impl::{ListColumn, MIN_COLUMN_WIDTH, SortOrder, State},
+    },
+    chapters::{Chapter, current_chapter},
+    clipboard::ClipboardFormat,
+    discogs::{self, Release},
     fonts::{ICON, SANS_BOLD},
-    icons::ICON_SQUARE_SPLIT_HORIZONTAL,
-    util::playable_artwork,
+    icons::{
+        ICON_CIRCLE_CHECK, ICON_CLOCK, ICON_CLOUD, ICON_LOADER, ICON_SQUARE_SPLIT_HORIZONTAL,
+        ICON_X,
+    },
+    offline,
+    storage::{AudioFileKind, Bookmark, LinkKind, PlayableLink, PlaylistPlayback},
+    util::{artwork_image, duration_to_str, timestamp_to_str},
     widgets::{
-        column::{Column, find_position},
-        compact_row,
+        column::{Column, find_position, window_range},
+        compact_row::{self, ROW_HEIGHT},
+        stars::stars,
     },
 };
 use iced::{
@@ -17,36 +29,277 @@ use iced::{
     event,
     keyboard::{Key, key},
     widget::{
-        Row, Scrollable, container, horizontal_rule, mouse_area,
+        Button, Row, Scrollable, container, horizontal_rule, mouse_area, pick_list,
         scrollable::{self, scroll_to},
-        text, text_input,
+        text, text_input, toggler, vertical_rule,
     },
 };
 
 static COL_ID: &str = "compact_col";
 static SCROLLABLE_ID: &str = "compact_scrollable";
+// the height of the list assumed until it is scrolled, about that of a large window
+const DEFAULT_LIST_HEIGHT: f32 = 1600.0;
+
+static TRACK_CONTEXT_MENU: &[&str] = &[
+    "Play Selection",
+    "Play Next",
+    "Play Last",
+    "Copy Artist - Title",
+    "Copy as JSON",
+    "Copy File Paths",
+    "Convert…",
+    "Fetch Artwork",
+    "Fetch Album Artwork",
+    "Make Available Offline",
+    "Remove Offline Copy",
+];
+
+// the duration is not resized, it sits at the right edge of the list
+const DURATION_WIDTH: f32 = 80.0;
+// the part of a header edge which can be grabbed to resize its column
+const RESIZE_HANDLE_WIDTH: f32 = 9.0;
+
+// fades a playlist can keep for itself, in milliseconds
+const PLAYLIST_FADES: [FadeChoice; 5] = [
+    FadeChoice(None),
+    FadeChoice(Some(0)),
+    FadeChoice(Some(150)),
+    FadeChoice(Some(300)),
+    FadeChoice(Some(500)),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FadeChoice(Option<u64>);
+
+impl fmt::Display for FadeChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            None => write!(f, "Usual fade"),
+            Some(0) => write!(f, "Gapless"),
+            Some(ms) => write!(f, "{ms} ms fade"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SourceChoice(Option<AudioFileKind>);
+
+impl fmt::Display for SourceChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            None => write!(f, "All sources"),
+            Some(kind) => write!(f, "{}", kind_label(kind)),
+        }
+    }
+}
+
+/// What notes typed in the details belong to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteTarget {
+    Playable(PlayableId),
+    Playlist(i64),
+}
 
 #[derive(Debug, Clone)]
 pub enum Message {
     Selected(usize),
     DblClick(usize, PlayableId),
     RightClick(usize),
+    // a row is dragged, with the rest of the selection when it is part of it
+    Drag(usize),
+    // the rows dragged are over another row of a playlist, to be moved there
+    DragOver(usize),
+    // the rows dragged left the list
+    DragOut,
     SelectionModifierKey(Option<Key>),
     DeleteSelection,
     RemovePlayables(Vec<usize>, bool),
     ScrollTo(usize),
     ScrollEnd(usize),
+    // vertical offset of the track list and its height on screen
+    Scrolled(f32, f32),
     ToggleDetails,
+    ContextAction(usize, usize),
+    CopyToClipboard(ClipboardFormat, Vec<usize>),
+    ConvertSelection(Vec<usize>),
+    // looks up the artwork missing from the rows, or from their whole albums when true
+    FetchArtwork(Vec<usize>, bool),
+    // downloads the remote rows, or deletes their downloads when false
+    MakeOffline(Vec<usize>, bool),
+    // queues the rows, right after the playing one when true, otherwise after those queued
+    Enqueue(Vec<usize>, bool),
+    // plays the rows in the order they were selected, without saving them as a playlist
+    PlaySelection(Vec<usize>),
+    SetAudiobook(PlayableId, bool),
+    // stars, 0 clears the rating
+    Rate(PlayableId, u8),
+    // row index, playable id, chapter start in seconds
+    PlayChapter(usize, PlayableId, u64),
+    BookmarkNote(String),
+    // marks where the playing track is, with the note typed in
+    AddBookmark(PlayableId, String),
+    DeleteBookmark(i64),
+    // row index, playable id, bookmarked position in seconds
+    PlayBookmark(usize, PlayableId, u64),
+    // playable id, field id and the value typed in
+    FieldInput(PlayableId, i64, String),
+    // empty clears the value
+    SaveField(PlayableId, i64, String),
+    LinkKindPicked(LinkKind),
+    // turns around which of the two selected tracks is linked to the other
+    SwapLink,
+    // the first is a remix, edit or cover of the second
+    Link(PlayableId, PlayableId, LinkKind),
+    Unlink(PlayableId, PlayableId),
+    // a linked track, shown in the list
+    ShowPlayable(PlayableId),
+    // selects the row of a track shown and scrolls to it
+    Reveal(usize),
+    NotesInput(NoteTarget, String),
+    // empty clears the notes
+    SaveNotes(NoteTarget, String),
+    // a playlist found by the search
+    OpenPlaylist(i64),
+    // the page of an album, by name
+    OpenAlbum(String),
+    ReleaseInput(String),
+    // fetches the Discogs release typed in to mark its tracks on a playable
+    LinkRelease(PlayableId),
+    ReleaseFetched(PlayableId, Result<Release, String>),
+    UnlinkRelease(PlayableId),
+    // playlist id and how it plays from now on
+    PlaylistPlayback(i64, PlaylistPlayback),
+    // orders the list by a column, None goes back to the order of the section
+    SortBy(Option<ListColumn>),
+    // shows or hides an optional column
+    ToggleColumn(ListColumn),
+    // the edge of a header pressed, with the width of its column
+    ResizeStart(ListColumn, f32),
+    // where the cursor is across the window while the edge is dragged
+    ResizeMove(f32),
+    ResizeEnd,
+    // a column dragged to a new width, kept by the app for the view
+    ColumnResized(ListColumn, f32),
+    ResetColumnWidths,
+    // switches to the album grid
+    ShowAlbums,
+    // mirrors the Subsonic or Jellyfin server at this address again
+    SyncServer(String),
+    // shows the library of one source, None of all of them
+    FilterSource(Option<AudioFileKind>),
 }
 
 #[derive(Default)]
 pub struct CompactView {
     currently_selected_index: VecDeque<usize>,
+    // rows ctrl-clicked onto the selection, they are at the front of it, newest first
+    ctrl_picks: usize,
     selection_modifier_key: Option<Key>,
     details: bool,
+    scroll_offset: f32,
+    // None until the list is scrolled
+    list_height: Option<f32>,
+    // the Discogs release to link, as typed in the details
+    release_input: String,
+    // the note of the next bookmark, as typed in the details
+    bookmark_note: String,
+    // notes being edited, the saved ones are shown otherwise
+    notes_edit: Option<(NoteTarget, String)>,
+    // the value of a custom field being edited, by playable and field
+    field_edit: Option<(PlayableId, i64, String)>,
+    // how two selected tracks are linked, the first selected is linked to the other unless
+    // swapped
+    link_kind: LinkKind,
+    link_swapped: bool,
+    // the rows dragged, in the order of the list, and the row they are over
+    dragged: Vec<usize>,
+    drop_row: Option<usize>,
+    resizing: Option<Resizing>,
+}
+
+// a column whose header edge is being dragged
+#[derive(Debug, Clone, Copy)]
+struct Resizing {
+    column: ListColumn,
+    start_width: f32,
+    // where the cursor was first seen, the width follows how far it moved since
+    origin: Option<f32>,
+    width: f32,
 }
 
 impl CompactView {
+    pub fn details_visible(&self) -> bool {
+        self.details
+    }
+
+    /// The selected row, when exactly one is selected.
+    pub fn single_selection(&self) -> Option<usize> {
+        if self.currently_selected_index.len() == 1 {
+            self.currently_selected_index.front().copied()
+        } else {
+            None
+        }
+    }
+
+    pub fn selection(&self) -> Vec<usize> {
+        self.currently_selected_index.iter().copied().collect()
+    }
+
+    /// The rows dragged from `index`, in the order of the list.
+    pub fn dragged_rows(&self, index: usize) -> Vec<usize> {
+        if !self.currently_selected_index.contains(&index) {
+            return vec![index];
+        }
+        let mut rows = self.selection();
+        rows.sort_unstable();
+        rows
+    }
+
+    /// The rows dragged and where they go in the list once dropped on one of its rows, the
+    /// selection follows them.
+    pub fn end_drag(&mut self) -> Option<(Vec<usize>, usize)> {
+        let rows = std::mem::take(&mut self.dragged);
+        let row = self.drop_row.take()?;
+        if rows.contains(&row) {
+            return None;
+        }
+        let to = match Self::drop_marker(&rows, row) {
+            compact_row::DropMarker::Above => row,
+            compact_row::DropMarker::Below => row + 1,
+        };
+        let at = to - rows.iter().filter(|moved| **moved < to).count();
+        self.ctrl_picks = 0;
+        self.currently_selected_index = (at..at + rows.len()).collect();
+        Some((rows, to))
+    }
+
+    // rows go below one dragged down onto and above one dragged up onto
+    fn drop_marker(rows: &[usize], row: usize) -> compact_row::DropMarker {
+        if rows.first().is_some_and(|first| *first < row) {
+            compact_row::DropMarker::Below
+        } else {
+            compact_row::DropMarker::Above
+        }
+    }
+
+    pub fn scroll_offset(&self) -> f32 {
+        self.scroll_offset
+    }
+
+    /// Selects the rows and scrolls the list back to where the last session left it.
+    pub fn restore(&mut self, selection: Vec<usize>, scroll_offset: f32) -> Task<Message> {
+        self.currently_selected_index = selection.into();
+        self.ctrl_picks = 0;
+        self.scroll_offset = scroll_offset;
+        scroll_to(
+            scrollable::Id::new(SCROLLABLE_ID),
+            scrollable::AbsoluteOffset {
+                x: 0.0,
+                y: scroll_offset,
+            },
+        )
+    }
+
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::ScrollTo(index) => {
@@ -60,6 +313,10 @@ impl CompactView {
                     .map(Message::ScrollEnd);
             }
             Message::Selected(index) => {
+                match self.selection_modifier_key {
+                    Some(Key::Named(key::Named::Control)) => self.ctrl_picks += 1,
+                    _ => self.ctrl_picks = 0,
+                }
                 compute_selection(
                     index,
                     &mut self.currently_selected_index,
@@ -67,9 +324,37 @@ impl CompactView {
                 );
             }
             Message::DblClick(index, _) => {
+                self.ctrl_picks = 0;
                 self.currently_selected_index.clear();
                 self.currently_selected_index.push_front(index);
             }
+            Message::Drag(index) => {
+                self.dragged = self.dragged_rows(index);
+                self.drop_row = None;
+            }
+            Message::DragOver(index) if !self.dragged.is_empty() => self.drop_row = Some(index),
+            Message::DragOut => self.drop_row = None,
+            Message::ResizeStart(column, width) => {
+                self.resizing = Some(Resizing {
+                    column,
+                    start_width: width,
+                    origin: None,
+                    width,
+                });
+            }
+            Message::ResizeMove(x) => {
+                if let Some(resizing) = &mut self.resizing {
+                    let origin = *resizing.origin.get_or_insert(x);
+                    resizing.width = (resizing.start_width + x - origin).max(MIN_COLUMN_WIDTH);
+                }
+            }
+            Message::ResizeEnd => {
+                if let Some(resizing) = self.resizing.take()
+                    && resizing.width != resizing.start_width
+                {
+                    return Task::done(Message::ColumnResized(resizing.column, resizing.width));
+                }
+            }
             Message::SelectionModifierKey(modifier) => {
                 self.selection_modifier_key = modifier;
             }
@@ -83,9 +368,67 @@ impl CompactView {
                     to_trash,
                 ));
             }
+            Message::Scrolled(offset, height) => {
+                self.scroll_offset = offset;
+                self.list_height = Some(height);
+            }
             Message::ToggleDetails => {
                 self.details = !self.details;
             }
+            // the rows shown change, those selected are others
+            Message::FilterSource(_) => {
+                self.currently_selected_index.clear();
+                self.ctrl_picks = 0;
+            }
+            Message::ReleaseInput(input) => self.release_input = input,
+            Message::LinkRelease(id) => {
+                return Task::perform(
+                    discogs::fetch_release(self.release_input.clone()),
+                    move |result| Message::ReleaseFetched(id, result.map_err(|e| e.to_string())),
+                );
+            }
+            Message::ReleaseFetched(_, Ok(_)) => self.release_input.clear(),
+            Message::BookmarkNote(note) => self.bookmark_note = note,
+            Message::AddBookmark(..) => self.bookmark_note.clear(),
+            Message::NotesInput(target, notes) => self.notes_edit = Some((target, notes)),
+            Message::SaveNotes(..) => self.notes_edit = None,
+            Message::FieldInput(id, field_id, value) => {
+                self.field_edit = Some((id, field_id, value))
+            }
+            Message::SaveField(..) => self.field_edit = None,
+            Message::LinkKindPicked(kind) => self.link_kind = kind,
+            Message::SwapLink => self.link_swapped = !self.link_swapped,
+            Message::Link(..) => self.link_swapped = false,
+            Message::Reveal(index) => {
+                self.ctrl_picks = 0;
+                self.currently_selected_index.clear();
+                self.currently_selected_index.push_front(index);
+                return self.update(Message::ScrollTo(index));
+            }
+            Message::ContextAction(option, index) => {
+                // right clicking outside of the selection acts on that row only
+                if !self.currently_selected_index.contains(&index) {
+                    self.ctrl_picks = 0;
+                    self.currently_selected_index.clear();
+                    self.currently_selected_index.push_front(index);
+                }
+                let selection: Vec<usize> = self.currently_selected_index.clone().into();
+                let format = match option {
+                    0 => {
+                        let order =
+                            selection_order(&self.currently_selected_index, self.ctrl_picks);
+                        return Task::done(Message::PlaySelection(order));
+                    }
+                    1 | 2 => return Task::done(Message::Enqueue(selection, option == 1)),
+                    3 => ClipboardFormat::ArtistTitle,
+                    4 => ClipboardFormat::Json,
+                    5 => ClipboardFormat::Paths,
+                    6 => return Task::done(Message::ConvertSelection(selection)),
+                    9 | 10 => return Task::done(Message::MakeOffline(selection, option == 9)),
+                    _ => return Task::done(Message::FetchArtwork(selection, option == 8)),
+                };
+                return Task::done(Message::CopyToClipboard(format, selection));
+            }
             _ => {}
         }
 
@@ -93,50 +436,269 @@ impl CompactView {
     }
 
     pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let recently_played = *state.section() == Section::RecentlyPlayed;
+        // the column after the album, lists of what was played show when in place of the genre
+        let second_column = if recently_played {
+            ListColumn::LastPlayed
+        } else {
+            ListColumn::Genre
+        };
+        // the width being dragged wins over the one kept for the view
+        let width = |column: ListColumn| match self.resizing {
+            Some(resizing) if resizing.column == column => resizing.width,
+            _ => state.column_width(column),
+        };
         let row_sizes = (
             Length::Fixed(50.),
-            Length::FillPortion(5),
-            Length::FillPortion(4),
-            Length::FillPortion(2),
-            Length::FillPortion(2),
+            Length::Fill,
+            Length::Fixed(width(ListColumn::Album)),
+            Length::Fixed(width(second_column)),
+            Length::Fixed(DURATION_WIDTH),
         );
 
+        let sort = state.sort();
+        let extra_columns = state.list_columns();
+        let mut header_row = iced::widget::row![
+            mouse_area(text("#").font(SANS_BOLD).size(16).width(row_sizes.0))
+                .on_press(Message::SortBy(None)),
+            sort_header("Title", ListColumn::Title, sort, row_sizes.1),
+            resizable_header("Album", ListColumn::Album, sort, width(ListColumn::Album)),
+            resizable_header(
+                if recently_played { "Played" } else { "Genre" },
+                second_column,
+                sort,
+                width(second_column),
+            ),
+        ];
+        for column in extra_columns {
+            let label = match column {
+                ListColumn::PlayCount => "Plays",
+                ListColumn::Rating => "Rating",
+                ListColumn::Released => "Released",
+                ListColumn::Year => "Year",
+                ListColumn::DateAdded => "Added",
+                ListColumn::Kind => "Kind",
+                ListColumn::Field(id) => field_name(state, *id),
+                _ => "Last Played",
+            };
+            header_row = header_row.push(resizable_header(label, *column, sort, width(*column)));
+        }
+        header_row = header_row.push(
+            container(sort_header(
+                "Duration",
+                ListColumn::Duration,
+                sort,
+                Length::Shrink,
+            ))
+            .align_x(Horizontal::Right)
+            .width(row_sizes.4),
+        );
         let header: Element<Message> = std::convert::Into::<Element<Message>>::into(
-            iced::widget::row![
-                text("#").font(SANS_BOLD).size(16).width(row_sizes.0),
-                text("Title").font(SANS_BOLD).size(16).width(row_sizes.1),
-                text("Album").font(SANS_BOLD).size(16).width(row_sizes.2),
-                text("Genre").font(SANS_BOLD).size(16).width(row_sizes.3),
-                container(text("Duration").font(SANS_BOLD).size(16),)
-                    .align_x(Horizontal::Right)
-                    .width(row_sizes.4)
-            ]
-            .width(Length::Fill)
-            .padding(Padding {
-                left: 20.0,
-                right: 20.0,
-                bottom: 20.0,
-                top: 0.0,
-            })
-            .spacing(40),
+            header_row
+                .width(Length::Fill)
+                .padding(Padding {
+                    left: 20.0,
+                    right: 20.0,
+                    bottom: 20.0,
+                    top: 0.0,
+                })
+                .spacing(40),
         );
 
         let compact_column = Column::new().push(header);
 
         let (_, count) = state.playables().size_hint();
-        let mut rows = Column::new().id(iced::advanced::widget::Id::new(COL_ID));
-        for (i, playable) in state.playables().enumerate() {
+        let count = count.unwrap_or(0);
+        let reordering = !self.dragged.is_empty() && state.can_reorder();
+        // only the rows around the ones on screen are built
+        let window = window_range(
+            self.scroll_offset,
+            self.list_height.unwrap_or(DEFAULT_LIST_HEIGHT),
+            ROW_HEIGHT,
+            count,
+        );
+        let mut rows = Column::new()
+            .id(iced::advanced::widget::Id::new(COL_ID))
+            .window(window.start, count, ROW_HEIGHT);
+        for (i, playable) in state
+            .playables()
+            .enumerate()
+            .skip(window.start)
+            .take(window.len())
+        {
             let is_selected = self.currently_selected_index.contains(&i);
-            let row = compact_row::compact_row(playable, i, is_selected, &row_sizes)
-                .on_select(Message::Selected(i))
-                .on_dbl_click(Message::DblClick(i, playable.get_id()));
+            let played_at = recently_played
+                .then(|| state.last_played(playable.get_id()))
+                .flatten();
+            let id = playable.get_id();
+            let extra = extra_columns
+                .iter()
+                .map(|column| {
+                    let width = width(*column);
+                    match column {
+                        ListColumn::PlayCount => cell(playable.play_count.to_string(), width),
+                        ListColumn::Rating => stars(playable.rating, 13.0, move |rating| {
+                            Message::Rate(id, rating)
+                        })
+                        .width(width)
+                        .into(),
+                        ListColumn::Released => {
+                            cell(playable.released().unwrap_or_default(), width)
+                        }
+                        ListColumn::Year => cell(
+                            playable
+                                .year
+                                .map(|year| year.to_string())
+                                .unwrap_or_default(),
+                            width,
+                        ),
+                        ListColumn::DateAdded => cell(timestamp_to_str(playable.date_added), width),
+                        ListColumn::Kind => cell(kind_label(playable.type_id).to_string(), width),
+                        ListColumn::Field(field_id) => {
+                            cell(state.field_value(id, *field_id).to_string(), width)
+                        }
+                        _ => cell(
+                            playable
+                                .last_played_at
+                                .map(timestamp_to_str)
+                                .unwrap_or_default(),
+                            width,
+                        ),
+                    }
+                })
+                .collect();
+            let status = state.offline_status(playable).map(status_icon);
+            let mut row = compact_row::compact_row(
+                playable,
+                i,
+                is_selected,
+                &row_sizes,
+                played_at,
+                status,
+                extra,
+            )
+            .on_select(Message::Selected(i))
+            .on_dbl_click(Message::DblClick(i, playable.get_id()))
+            .on_drag(Message::Drag(i))
+            .context_menu(TRACK_CONTEXT_MENU, move |option| {
+                Message::ContextAction(option, i)
+            })
+            .menu_style(state.theme().menu_style());
+            if reordering {
+                let marker = (self.drop_row == Some(i) && !self.dragged.contains(&i))
+                    .then(|| Self::drop_marker(&self.dragged, i));
+                row = row.on_drag_over(Message::DragOver(i)).drop_marker(marker);
+            }
             rows = rows.push(row);
         }
 
         let mut details_bar: Row<Message> = iced::widget::Row::new().align_y(Vertical::Center);
-        let count = count.unwrap_or(0);
+        if let Section::Playlist(id) = *state.section() {
+            let playback = state.section_playback();
+            details_bar = details_bar
+                .push(
+                    toggler(playback.shuffle)
+                        .label("Shuffle")
+                        .size(16)
+                        .on_toggle(move |shuffle| {
+                            Message::PlaylistPlayback(
+                                id,
+                                PlaylistPlayback {
+                                    shuffle,
+                                    ..playback
+                                },
+                            )
+                        }),
+                )
+                .push(
+                    pick_list(
+                        PLAYLIST_FADES,
+                        Some(FadeChoice(playback.fade_ms)),
+                        move |fade| {
+                            Message::PlaylistPlayback(
+                                id,
+                                PlaylistPlayback {
+                                    fade_ms: fade.0,
+                                    ..playback
+                                },
+                            )
+                        },
+                    )
+                    .text_size(13)
+                    .padding([0, 6]),
+                );
+        }
+        let albums_button = Button::new(text("Albums").size(13))
+            .style(iced::widget::button::secondary)
+            .padding([0, 6])
+            .on_press(Message::ShowAlbums);
+        details_bar = details_bar.push(albums_button);
+        let sources = state.sources();
+        if *state.section() == Section::Library && sources.len() > 1 {
+            let choices: Vec<SourceChoice> = std::iter::once(None)
+                .chain(sources.into_iter().map(Some))
+                .map(SourceChoice)
+                .collect();
+            details_bar = details_bar.push(
+                pick_list(
+                    choices,
+                    Some(SourceChoice(state.source_filter())),
+                    |choice| Message::FilterSource(choice.0),
+                )
+                .text_size(13)
+                .padding([0, 6]),
+            );
+        }
+        if let Section::Server(url) = state.section() {
+            details_bar = details_bar.push(
+                Button::new(text("Sync").size(13))
+                    .style(iced::widget::button::secondary)
+                    .padding([0, 6])
+                    .on_press(Message::SyncServer(url.clone())),
+            );
+        }
+        let column_toggle = |label: &'a str, column: ListColumn| {
+            toggler(extra_columns.contains(&column))
+                .label(label)
+                .size(14)
+                .on_toggle(move |_| Message::ToggleColumn(column))
+        };
+        details_bar = details_bar
+            .push(column_toggle("Plays", ListColumn::PlayCount))
+            .push(column_toggle("Last played", ListColumn::LastPlayed))
+            .push(column_toggle("Rating", ListColumn::Rating))
+            .push(column_toggle("Released", ListColumn::Released))
+            .push(column_toggle("Year", ListColumn::Year))
+            .push(column_toggle("Added", ListColumn::DateAdded))
+            .push(column_toggle("Kind", ListColumn::Kind));
+        for field in state.custom_fields() {
+            details_bar = details_bar.push(column_toggle(&field.name, ListColumn::Field(field.id)));
+        }
+        details_bar = details_bar.push(
+            Button::new(text("Reset widths").size(13))
+                .style(iced::widget::button::secondary)
+                .padding([0, 6])
+                .on_press(Message::ResetColumnWidths),
+        );
+        // the search also finds playlists by their name or notes
+        if let Section::Search(query) = state.section() {
+            for playlist in state.playlists_matching(query).into_iter().take(3) {
+                details_bar = details_bar.push(
+                    Button::new(text(format!("Playlist: {}", playlist.name)).size(13))
+                        .style(iced::widget::button::secondary)
+                        .padding([0, 6])
+                        .on_press(Message::OpenPlaylist(playlist.id)),
+                );
+            }
+        }
         details_bar = details_bar
-            .push(text(format!("{count} entries")).size(15))
+            .push(
+                text(match state.section() {
+                    Section::Search(query) => format!("{count} results for “{query}”"),
+                    _ => format!("{count} entries"),
+                })
+                .size(15),
+            )
             .push(
                 mouse_area(text(ICON_SQUARE_SPLIT_HORIZONTAL).font(ICON).size(18))
                     .on_press(Message::ToggleDetails),
@@ -145,9 +707,18 @@ impl CompactView {
 
         let compact_column = compact_column
             .push(
-                Scrollable::new(rows)
-                    .id(scrollable::Id::new(SCROLLABLE_ID))
-                    .height(Length::Fill),
+                mouse_area(
+                    Scrollable::new(rows)
+                        .id(scrollable::Id::new(SCROLLABLE_ID))
+                        .on_scroll(|viewport| {
+                            Message::Scrolled(
+                                viewport.absolute_offset().y,
+                                viewport.bounds().height,
+                            )
+                        })
+                        .height(Length::Fill),
+                )
+                .on_exit(Message::DragOut),
             )
             .push(
                 container(details_bar)
@@ -160,23 +731,240 @@ impl CompactView {
         }
         split_view.into()
     }
-    fn details_panel<'a>(&self, state: &'a State) -> container::Container<'a, Message> {
+    // the notes of `target` with a button saving them once edited
+    fn notes_editor<'a>(&'a self, target: NoteTarget, saved: &'a str) -> Column<'a, Message> {
+        let edited = match &self.notes_edit {
+            Some((editing, notes)) if *editing == target => Some(notes.as_str()),
+            _ => None,
+        };
+        let save = edited
+            .filter(|notes| notes.trim() != saved)
+            .map(|notes| Message::SaveNotes(target, notes.to_string()));
+        Column::new()
+            .spacing(10)
+            .push(text("Notes").font(SANS_BOLD))
+            .push(
+                iced::widget::row![
+                    text_input("e.g. plays well after …", edited.unwrap_or(saved))
+                        .size(14)
+                        .on_input(move |notes| Message::NotesInput(target, notes))
+                        .on_submit_maybe(save.clone()),
+                    Button::new(text("Save").size(14))
+                        .style(iced::widget::button::secondary)
+                        .on_press_maybe(save),
+                ]
+                .spacing(10)
+                .align_y(Vertical::Center),
+            )
+    }
+
+    // links the two selected tracks, the first selected to the second unless swapped
+    fn link_form<'a>(
+        &'a self,
+        state: &'a State,
+        first: usize,
+        second: usize,
+    ) -> Column<'a, Message> {
+        let (Some(first), Some(second)) =
+            (state.playables().nth(first), state.playables().nth(second))
+        else {
+            return Column::new();
+        };
+        let (playable, original) = if self.link_swapped {
+            (second, first)
+        } else {
+            (first, second)
+        };
+        let link = (playable.id != original.id).then_some(Message::Link(
+            playable.id,
+            original.id,
+            self.link_kind,
+        ));
+        Column::new()
+            .spacing(10)
+            .push(text("Link").font(SANS_BOLD))
+            .push(text(playable.get_title()))
+            .push(
+                iced::widget::row![
+                    text("is a"),
+                    pick_list(LinkKind::ALL, Some(self.link_kind), Message::LinkKindPicked)
+                        .text_size(14),
+                    text("of"),
+                ]
+                .spacing(10)
+                .align_y(Vertical::Center),
+            )
+            .push(text(original.get_title()))
+            .push(
+                iced::widget::row![
+                    Button::new(text("Swap").size(14))
+                        .style(iced::widget::button::secondary)
+                        .on_press(Message::SwapLink),
+                    Button::new(text("Link").size(14)).on_press_maybe(link),
+                ]
+                .spacing(10),
+            )
+    }
+
+    // a row per custom field with the value of the playable, saved once edited
+    fn field_editor<'a>(&'a self, state: &'a State, id: PlayableId) -> Column<'a, Message> {
+        let rows = state.custom_fields().iter().map(|field| {
+            let field_id = field.id;
+            let saved = state.field_value(id, field_id);
+            let edited = match &self.field_edit {
+                Some((playable, editing, value)) if *playable == id && *editing == field_id => {
+                    Some(value.as_str())
+                }
+                _ => None,
+            };
+            let save = edited
+                .filter(|value| value.trim() != saved)
+                .map(|value| Message::SaveField(id, field_id, value.to_string()));
+            iced::widget::row![
+                text(&field.name).width(Length::FillPortion(1)),
+                text_input("", edited.unwrap_or(saved))
+                    .size(14)
+                    .width(Length::FillPortion(2))
+                    .on_input(move |value| Message::FieldInput(id, field_id, value))
+                    .on_submit_maybe(save.clone()),
+                Button::new(text("Save").size(14))
+                    .style(iced::widget::button::secondary)
+                    .on_press_maybe(save),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center)
+            .into()
+        });
+        Column::with_children(rows).spacing(10)
+    }
+
+    fn details_panel<'a>(&'a self, state: &'a State) -> container::Container<'a, Message> {
         let content: Element<Message> = if self.currently_selected_index.is_empty() {
-            text("No song selected").into()
-        } else if self.currently_selected_index.len() == 1 {
-            let playable = state
-                .playables()
-                .nth(*self.currently_selected_index.front().unwrap())
-                .unwrap();
+            match *state.section() {
+                Section::Playlist(id) => self
+                    .notes_editor(NoteTarget::Playlist(id), state.playlist_notes(id))
+                    .into(),
+                _ => text("No song selected").into(),
+            }
+        } else if self.currently_selected_index.len() == 1
+            && let Some(index) = self.currently_selected_index.front().copied()
+            && let Some(playable) = state.playables().nth(index)
+        {
+            let id = playable.get_id();
+
+            let audiobook = toggler(state.is_audiobook(id))
+                .label("Audiobook (remember position)")
+                .on_toggle(move |value| Message::SetAudiobook(id, value));
+            let rating = iced::widget::row![
+                text("Rating").width(Length::FillPortion(1)),
+                container(stars(playable.rating, 16.0, move |rating| {
+                    Message::Rate(id, rating)
+                }))
+                .width(Length::FillPortion(3)),
+            ]
+            .spacing(10);
+            // a whole album in one track gets the tracks of its release as chapters
+            let release = if state.release_linked(id) {
+                iced::widget::row![
+                    text("Chapters from a Discogs tracklist")
+                        .size(14)
+                        .width(Length::Fill),
+                    Button::new(text("Unlink").size(14))
+                        .style(iced::widget::button::secondary)
+                        .on_press(Message::UnlinkRelease(id)),
+                ]
+            } else {
+                let link =
+                    (!self.release_input.trim().is_empty()).then_some(Message::LinkRelease(id));
+                iced::widget::row![
+                    text_input("Discogs release of a whole album", &self.release_input)
+                        .size(14)
+                        .on_input(Message::ReleaseInput)
+                        .on_submit_maybe(link.clone()),
+                    Button::new(text("Mark tracks").size(14))
+                        .style(iced::widget::button::secondary)
+                        .on_press_maybe(link),
+                ]
+            }
+            .spacing(10)
+            .align_y(Vertical::Center);
+            let mut details = playable_details(playable, state.artwork(id))
+                .push(rating)
+                .push(horizontal_rule(1))
+                .push(audiobook)
+                .push(release);
+            if !state.custom_fields().is_empty() {
+                details = details
+                    .push(horizontal_rule(1))
+                    .push(self.field_editor(state, id));
+            }
+            details = details
+                .push(horizontal_rule(1))
+                .push(self.notes_editor(NoteTarget::Playable(id), state.notes(id)));
+
+            let chapters = state.chapters(id);
+            if !chapters.is_empty() {
+                let current = if state.player.current_playable == Some(id) {
+                    current_chapter(chapters, state.player.position)
+                } else {
+                    None
+                };
+                details = details
+                    .push(horizontal_rule(1))
+                    .push(text("Chapters").font(SANS_BOLD))
+                    .push(chapter_list(index, id, chapters, current));
+            }
 
-            playable_details(playable).into()
+            // only the playing track has a position to mark
+            let add = (state.player.current_playable == Some(id))
+                .then(|| Message::AddBookmark(id, self.bookmark_note.clone()));
+            let add_label = match add {
+                Some(_) => format!("Bookmark {}", duration_to_str(state.player.position)),
+                None => "Bookmark".to_string(),
+            };
+            details = details
+                .push(horizontal_rule(1))
+                .push(text("Bookmarks").font(SANS_BOLD))
+                .push(
+                    iced::widget::row![
+                        text_input("Note, e.g. the best part of the mix", &self.bookmark_note)
+                            .size(14)
+                            .on_input(Message::BookmarkNote)
+                            .on_submit_maybe(add.clone()),
+                        Button::new(text(add_label).size(14))
+                            .style(iced::widget::button::secondary)
+                            .on_press_maybe(add),
+                    ]
+                    .spacing(10)
+                    .align_y(Vertical::Center),
+                )
+                .push(bookmark_list(index, id, state.bookmarks(id)));
+
+            let links = state.links(id);
+            if !links.is_empty() {
+                details = details
+                    .push(horizontal_rule(1))
+                    .push(text("Links").font(SANS_BOLD))
+                    .push(link_list(id, links));
+            }
+            details.into()
+        } else if self.currently_selected_index.len() == 1 {
+            // restored from the last session, the row is not loaded yet
+            text("Loading…").into()
+        } else if self.currently_selected_index.len() == 2 {
+            // the newest selection is at the front
+            let (first, second) = (
+                self.currently_selected_index[1],
+                self.currently_selected_index[0],
+            );
+            self.link_form(state, first, second).into()
         } else {
             text("Many elements").into()
         };
         container(content).padding(Padding::default().left(16).right(16))
     }
     pub fn subscription(&self) -> Subscription<Message> {
-        event::listen_with(|event, status, _| {
+        let keys = event::listen_with(|event, status, _| {
             if status.eq(&event::Status::Captured) {
                 return None;
             }
@@ -220,16 +1008,147 @@ impl CompactView {
                 },
                 _ => None,
             }
-        })
+        });
+        if self.resizing.is_none() {
+            return keys;
+        }
+        // the edge follows the cursor anywhere in the window until the button is let go
+        let resize = event::listen_with(|event, _, _| match event {
+            iced::Event::Mouse(iced::mouse::Event::CursorMoved { position }) => {
+                Some(Message::ResizeMove(position.x))
+            }
+            iced::Event::Mouse(iced::mouse::Event::ButtonReleased(iced::mouse::Button::Left)) => {
+                Some(Message::ResizeEnd)
+            }
+            _ => None,
+        });
+        Subscription::batch([keys, resize])
+    }
+}
+
+// a cell of an optional column
+fn link_list<'a>(id: PlayableId, links: &'a [PlayableLink]) -> Element<'a, Message> {
+    let rows = links.iter().map(|link| {
+        let other = &link.playable;
+        let relation = if link.of_other {
+            link.kind.to_string()
+        } else {
+            format!("{} of", link.kind)
+        };
+        iced::widget::row![
+            text(relation).size(14).width(70),
+            Button::new(
+                iced::widget::column![
+                    text(other.get_title()).size(14),
+                    text(other.get_artist()).size(12).style(text::secondary),
+                ]
+                .spacing(2)
+            )
+            .style(iced::widget::button::text)
+            .width(Length::Fill)
+            .on_press(Message::ShowPlayable(other.id)),
+            mouse_area(text(ICON_X).font(ICON).size(14))
+                .interaction(iced::mouse::Interaction::Pointer)
+                .on_press(Message::Unlink(id, other.id)),
+        ]
+        .spacing(10)
+        .align_y(Vertical::Center)
+        .into()
+    });
+    Column::with_children(rows).spacing(4).into()
+}
+
+// the name of a custom field, empty once it is deleted
+fn field_name(state: &State, field_id: i64) -> &str {
+    state
+        .custom_fields()
+        .iter()
+        .find(|field| field.id == field_id)
+        .map_or("", |field| field.name.as_str())
+}
+
+fn cell<'a>(value: String, width: f32) -> Element<'a, Message> {
+    text(value)
+        .size(13)
+        .width(width)
+        .wrapping(text::Wrapping::None)
+        .into()
+}
+
+fn status_icon(status: offline::Status) -> char {
+    match status {
+        offline::Status::Streamed => ICON_CLOUD,
+        offline::Status::Queued => ICON_CLOCK,
+        offline::Status::Downloading => ICON_LOADER,
+        offline::Status::Offline => ICON_CIRCLE_CHECK,
     }
 }
 
-fn playable_details<'a>(playable: &'a impl AudioPlayable) -> Column<'a, Message> {
+fn kind_label(kind: AudioFileKind) -> &'static str {
+    match kind {
+        AudioFileKind::LocalFile => "File",
+        AudioFileKind::GoogleDrive => "Google Drive",
+        AudioFileKind::Dropbox => "Dropbox",
+        AudioFileKind::Youtube => "YouTube",
+        AudioFileKind::Stream => "Stream",
+        AudioFileKind::WebDav => "WebDAV",
+        AudioFileKind::Subsonic => "Subsonic",
+        AudioFileKind::Jellyfin => "Jellyfin",
+    }
+}
+
+// a header whose right edge is dragged to resize its column, header and rows alike
+fn resizable_header<'a>(
+    label: &'a str,
+    column: ListColumn,
+    sort: Option<SortOrder>,
+    width: f32,
+) -> Element<'a, Message> {
+    let handle = mouse_area(
+        container(vertical_rule(1))
+            .width(RESIZE_HANDLE_WIDTH)
+            .height(18)
+            .align_x(Horizontal::Center),
+    )
+    .interaction(iced::mouse::Interaction::ResizingHorizontally)
+    .on_press(Message::ResizeStart(column, width));
+    iced::widget::row![sort_header(label, column, sort, Length::Fill), handle]
+        .width(width)
+        .align_y(Vertical::Center)
+        .into()
+}
+
+// a header which sorts the list by its column, marked with the direction when it does
+fn sort_header<'a>(
+    label: &'a str,
+    column: ListColumn,
+    sort: Option<SortOrder>,
+    width: Length,
+) -> Element<'a, Message> {
+    let arrow = match sort {
+        Some(order) if order.column == column && order.descending => " ▼",
+        Some(order) if order.column == column => " ▲",
+        _ => "",
+    };
+    mouse_area(
+        text(format!("{label}{arrow}"))
+            .font(SANS_BOLD)
+            .size(16)
+            .width(width),
+    )
+    .on_press(Message::SortBy(Some(column)))
+    .into()
+}
+
+fn playable_details<'a>(
+    playable: &'a impl AudioPlayable,
+    artwork: Option<&Vec<u8>>,
+) -> Column<'a, Message> {
     let (label_width, input_width) = (Length::FillPortion(1), Length::FillPortion(3));
     let header = Row::new()
         .width(Length::Fill)
         .spacing(10)
-        .push(playable_artwork(playable, 120, 120))
+        .push(artwork_image(artwork, 120, 120))
         .push(text(playable.get_title()).font(SANS_BOLD).size(20));
 
     let track_title = Row::new()
@@ -253,12 +1172,20 @@ fn playable_details<'a>(playable: &'a impl AudioPlayable) -> Column<'a, Message>
         .push(text("Genre").width(label_width))
         .push(text_input("Edit genre", playable.get_genre()).width(input_width));
 
-    let album = Row::new()
+    let mut album = Row::new()
         .width(Length::Fill)
         .spacing(10)
         .align_y(Vertical::Center)
         .push(text("Album").width(label_width))
         .push(text_input("Edit album", playable.get_album()).width(input_width));
+    if !playable.get_album().is_empty() {
+        album = album.push(
+            Button::new(text("Open").size(13))
+                .style(iced::widget::button::secondary)
+                .padding([0, 6])
+                .on_press(Message::OpenAlbum(playable.get_album().to_string())),
+        );
+    }
     let path = Row::new()
         .width(Length::Fill)
         .spacing(10)
@@ -282,6 +1209,69 @@ fn playable_details<'a>(playable: &'a impl AudioPlayable) -> Column<'a, Message>
         .push(path)
 }
 
+fn chapter_list<'a>(
+    index: usize,
+    id: PlayableId,
+    chapters: &'a [Chapter],
+    current: Option<usize>,
+) -> Scrollable<'a, Message> {
+    let rows = chapters.iter().enumerate().map(|(i, chapter)| {
+        let mut title = text(&chapter.title).size(14);
+        if current == Some(i) {
+            title = title.font(SANS_BOLD);
+        }
+        mouse_area(
+            iced::widget::row![
+                text(duration_to_str(chapter.start)).size(14).width(60),
+                title,
+            ]
+            .spacing(10),
+        )
+        .interaction(iced::mouse::Interaction::Pointer)
+        .on_press(Message::PlayChapter(index, id, chapter.start))
+        .into()
+    });
+    Scrollable::new(
+        iced::widget::Column::with_children(rows)
+            .spacing(6)
+            .width(Length::Fill),
+    )
+    .height(Length::Fill)
+}
+
+fn bookmark_list<'a>(
+    index: usize,
+    id: PlayableId,
+    bookmarks: &'a [Bookmark],
+) -> Scrollable<'a, Message> {
+    let rows = bookmarks.iter().map(|bookmark| {
+        iced::widget::row![
+            mouse_area(
+                iced::widget::row![
+                    text(duration_to_str(bookmark.position)).size(14).width(60),
+                    text(&bookmark.note).size(14),
+                ]
+                .spacing(10)
+                .width(Length::Fill),
+            )
+            .interaction(iced::mouse::Interaction::Pointer)
+            .on_press(Message::PlayBookmark(index, id, bookmark.position)),
+            Button::new(text("Remove").size(12))
+                .style(iced::widget::button::text)
+                .on_press(Message::DeleteBookmark(bookmark.id)),
+        ]
+        .spacing(10)
+        .align_y(Vertical::Center)
+        .into()
+    });
+    Scrollable::new(
+        iced::widget::Column::with_children(rows)
+            .spacing(6)
+            .width(Length::Fill),
+    )
+    .height(Length::Fill)
+}
+
 fn compute_selection(index: usize, indexes: &mut VecDeque<usize>, modifier_key: &Option<Key>) {
     if modifier_key.is_none() {
         indexes.clear();
@@ -303,13 +1293,31 @@ fn compute_selection(index: usize, indexes: &mut VecDeque<usize>, modifier_key:
         indexes.push_front(index);
     }
 }
+
+// The selected rows in the order they were picked: the first click or shift range, from its
+// anchor, then the `ctrl_picks` rows ctrl-clicked onto it. A row picked twice keeps its first
+// place.
+fn selection_order(indexes: &VecDeque<usize>, ctrl_picks: usize) -> Vec<usize> {
+    let ctrl_picks = ctrl_picks.min(indexes.len());
+    let mut order: Vec<usize> = Vec::with_capacity(indexes.len());
+    for index in indexes
+        .iter()
+        .skip(ctrl_picks)
+        .chain(indexes.iter().take(ctrl_picks).rev())
+    {
+        if !order.contains(index) {
+            order.push(*index);
+        }
+    }
+    order
+}
 #[cfg(test)]
 mod tests {
     use std::collections::VecDeque;
 
     use iced::keyboard::{Key, key};
 
-    use crate::view_types::compact_view::compute_selection;
+    use crate::view_types::compact_view::{compute_selection, selection_order};
 
     #[test]
     fn test_compute_single_selection() {
@@ -355,4 +1363,18 @@ mod tests {
         compute_selection(10, &mut indexes, &Some(Key::Named(key::Named::Control)));
         assert_eq!(indexes, VecDeque::from(vec![10, 5, 6, 7]));
     }
+
+    #[test]
+    fn test_selection_order() {
+        let mut indexes: VecDeque<usize> = VecDeque::new();
+        compute_selection(5, &mut indexes, &None);
+        compute_selection(2, &mut indexes, &Some(Key::Named(key::Named::Shift)));
+        compute_selection(9, &mut indexes, &Some(Key::Named(key::Named::Control)));
+        compute_selection(1, &mut indexes, &Some(Key::Named(key::Named::Control)));
+        assert_eq!(selection_order(&indexes, 2), vec![5, 4, 3, 2, 9, 1]);
+
+        // ctrl-clicking a selected row again does not play it twice
+        compute_selection(9, &mut indexes, &Some(Key::Named(key::Named::Control)));
+        assert_eq!(selection_order(&indexes, 3), vec![5, 4, 3, 2, 9, 1]);
+    }
 }
@@ -0,0 +1,269 @@
+use std::path::PathBuf;
+
+use crate::{
+    app_state::AudioPlayable,
+    convert::{self, ConvertOptions, Format},
+    fonts::SANS_BOLD,
+    storage::{AudioFileKind, Playable},
+};
+use iced::{
+    Element, Length, Padding, Task,
+    alignment::Vertical,
+    widget::{
+        Button, Column, Scrollable, column, horizontal_rule, pick_list, row, text, text_input,
+        toggler,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    FormatChanged(Format),
+    BitrateChanged(String),
+    FolderChanged(String),
+    PickFolder,
+    FolderPicked(Option<PathBuf>),
+    TemplateChanged(String),
+    Replace(bool),
+    Save,
+    // index of the job and the converted file
+    JobFinished(usize, Result<PathBuf, String>),
+    ClearFinished,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed(String),
+}
+
+pub struct ConversionJob {
+    pub playable: Playable,
+    pub output: PathBuf,
+    pub format: Format,
+    pub bitrate: u32,
+    pub replace: bool,
+    pub status: JobStatus,
+}
+
+/// The conversion settings and the queue of conversions, one runs at a time.
+#[derive(Default)]
+pub struct ConversionsView {
+    format: Format,
+    bitrate: String,
+    folder: String,
+    template: String,
+    replace: bool,
+    jobs: Vec<ConversionJob>,
+}
+
+impl ConversionsView {
+    /// Shows the saved settings, the queue is kept.
+    pub fn reset(&mut self, options: &ConvertOptions) {
+        self.format = options.format;
+        self.bitrate = options.bitrate.to_string();
+        self.folder = options.folder.to_string_lossy().to_string();
+        self.template = options.template.clone();
+        self.replace = options.replace;
+    }
+
+    /// The settings as currently typed in, an invalid bitrate counts as the default.
+    pub fn options(&self) -> ConvertOptions {
+        ConvertOptions {
+            format: self.format,
+            bitrate: self
+                .bitrate
+                .trim()
+                .parse()
+                .ok()
+                .filter(|bitrate| *bitrate > 0)
+                .unwrap_or(convert::DEFAULT_BITRATE),
+            folder: PathBuf::from(self.folder.trim()),
+            template: self.template.trim().to_string(),
+            replace: self.replace,
+        }
+    }
+
+    pub fn job(&self, index: usize) -> Option<&ConversionJob> {
+        self.jobs.get(index)
+    }
+
+    /// Queues the local files among `playables` and starts converting unless already busy.
+    pub fn enqueue(&mut self, playables: Vec<Playable>, options: &ConvertOptions) -> Task<Message> {
+        for playable in playables {
+            if playable.type_id != AudioFileKind::LocalFile {
+                continue;
+            }
+            self.jobs.push(ConversionJob {
+                output: convert::output_path(options, &playable),
+                playable,
+                format: options.format,
+                bitrate: options.bitrate,
+                replace: options.replace,
+                status: JobStatus::Queued,
+            });
+        }
+        self.start_next()
+    }
+
+    fn start_next(&mut self) -> Task<Message> {
+        if self.jobs.iter().any(|job| job.status == JobStatus::Running) {
+            return Task::none();
+        }
+        let Some((index, job)) = self
+            .jobs
+            .iter_mut()
+            .enumerate()
+            .find(|(_, job)| job.status == JobStatus::Queued)
+        else {
+            return Task::none();
+        };
+        job.status = JobStatus::Running;
+        Task::perform(
+            convert::convert(
+                job.playable.source_url.clone(),
+                job.output.clone(),
+                job.format,
+                job.bitrate,
+            ),
+            move |result| Message::JobFinished(index, result.map_err(|e| e.to_string())),
+        )
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::FormatChanged(format) => self.format = format,
+            Message::BitrateChanged(value) => self.bitrate = value,
+            Message::FolderChanged(value) => self.folder = value,
+            Message::PickFolder => {
+                return Task::perform(
+                    async {
+                        rfd::AsyncFileDialog::new()
+                            .pick_folder()
+                            .await
+                            .map(|folder| folder.path().to_path_buf())
+                    },
+                    Message::FolderPicked,
+                );
+            }
+            Message::FolderPicked(Some(folder)) => {
+                self.folder = folder.to_string_lossy().to_string()
+            }
+            Message::TemplateChanged(value) => self.template = value,
+            Message::Replace(value) => self.replace = value,
+            Message::JobFinished(index, result) => {
+                if let Some(job) = self.jobs.get_mut(index) {
+                    job.status = match result {
+                        Ok(_) => JobStatus::Done,
+                        Err(error) => JobStatus::Failed(error),
+                    };
+                }
+                return self.start_next();
+            }
+            Message::ClearFinished => {
+                // indexes of running jobs would shift, finished ones are kept until it is done
+                if !self.jobs.iter().any(|job| job.status == JobStatus::Running) {
+                    self.jobs.retain(|job| job.status == JobStatus::Queued);
+                }
+            }
+            Message::FolderPicked(None) | Message::Save => {}
+        }
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let bitrate: Element<'_, Message> = if self.format.is_lossless() {
+            text("Lossless").size(14).into()
+        } else {
+            text_input("256", &self.bitrate)
+                .on_input(Message::BitrateChanged)
+                .width(Length::Fixed(80.0))
+                .into()
+        };
+        let settings = column![
+            text("Convert").font(SANS_BOLD).size(20),
+            row![
+                text("Format").size(14).width(Length::Fill),
+                pick_list(Format::ALL, Some(self.format), Message::FormatChanged),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Bitrate (kbit/s)").size(14).width(Length::Fill),
+                bitrate
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text_input("Output folder", &self.folder).on_input(Message::FolderChanged),
+                Button::new(text("Choose…"))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::PickFolder),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            text_input(convert::DEFAULT_TEMPLATE, &self.template)
+                .on_input(Message::TemplateChanged),
+            text(
+                "Files are named after the template inside the output folder, \
+                 it may use {artist}, {album}, {genre} and {title}."
+            )
+            .size(12),
+            toggler(self.replace)
+                .label("Replace the library entry with the converted file")
+                .on_toggle(Message::Replace),
+            text(
+                "Otherwise the converted file is added next to the original. \
+                 Converting needs ffmpeg to be installed."
+            )
+            .size(12),
+            Button::new(text("Save")).on_press(Message::Save),
+        ]
+        .spacing(12)
+        .max_width(480);
+
+        let mut queue = Column::new()
+            .spacing(8)
+            .padding(Padding::default().right(16));
+        if self.jobs.is_empty() {
+            queue = queue.push(text("Right click tracks and pick Convert… to queue them").size(12));
+        }
+        for job in &self.jobs {
+            let status = match &job.status {
+                JobStatus::Queued => "Queued".to_string(),
+                JobStatus::Running => "Converting…".to_string(),
+                JobStatus::Done => "Done".to_string(),
+                JobStatus::Failed(error) => format!("Failed: {error}"),
+            };
+            queue = queue.push(
+                row![
+                    column![
+                        text(job.playable.get_title()).font(SANS_BOLD).size(14),
+                        text(job.output.to_string_lossy()).size(12),
+                    ]
+                    .spacing(2)
+                    .width(Length::Fill),
+                    text(status).size(12),
+                ]
+                .spacing(12)
+                .align_y(Vertical::Center),
+            );
+        }
+
+        column![
+            settings,
+            horizontal_rule(1),
+            row![
+                text("Queue").font(SANS_BOLD).size(16).width(Length::Fill),
+                Button::new(text("Clear finished").size(12))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::ClearFinished),
+            ]
+            .align_y(Vertical::Center),
+            Scrollable::new(queue).height(Length::Fill),
+        ]
+        .spacing(12)
+        .into()
+    }
+}
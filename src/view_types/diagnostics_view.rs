@@ -0,0 +1,124 @@
+use crate::{
+    app_state::{Section, state_impl::State},
+    diagnostics::{self, Finding, Status},
+    fonts::{ICON, SANS_BOLD},
+    icons::{ICON_CIRCLE_CHECK, ICON_CIRCLE_X, ICON_TRIANGLE_ALERT},
+};
+use iced::{
+    Element, Length, Task,
+    alignment::Vertical,
+    widget::{Button, Column, column, horizontal_rule, row, text},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    // runs every check again
+    Run,
+    NetworkChecked(Vec<Finding>),
+    // the settings a problem is fixed in
+    OpenSettings(Section),
+}
+
+#[derive(Default)]
+pub struct DiagnosticsView {
+    findings: Vec<Finding>,
+    // None while the services are asked
+    network: Option<Vec<Finding>>,
+}
+
+impl DiagnosticsView {
+    /// Checks the database, the output device and the watched folders, the services the
+    /// integrations talk to are checked in the background.
+    pub fn run(&mut self, state: &State) -> Task<Message> {
+        self.findings = vec![
+            diagnostics::check_storage(state.storage_problem()),
+            diagnostics::check_audio(),
+        ];
+        self.findings
+            .extend(diagnostics::check_watch_folders(state.watch_folders()));
+        self.network = None;
+        Task::perform(
+            diagnostics::check_network(diagnostics::services(state.podcasts())),
+            Message::NetworkChecked,
+        )
+    }
+
+    /// Whether a check done so far found a problem.
+    pub fn has_problems(&self) -> bool {
+        self.findings
+            .iter()
+            .chain(self.network.iter().flatten())
+            .any(|finding| finding.status != Status::Ok)
+    }
+
+    pub fn update(&mut self, message: Message, state: &State) -> Task<Message> {
+        match message {
+            Message::Run => return self.run(state),
+            Message::NetworkChecked(findings) => self.network = Some(findings),
+            Message::OpenSettings(_) => {}
+        }
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let checking = self.network.is_none();
+        let mut findings = Column::new().spacing(12);
+        for finding in self.findings.iter().chain(self.network.iter().flatten()) {
+            findings = findings.push(finding_row(finding));
+        }
+        if checking {
+            findings =
+                findings.push(text("Asking the services used by the integrations…").size(12));
+        }
+        column![
+            row![
+                text("Diagnostics")
+                    .font(SANS_BOLD)
+                    .size(20)
+                    .width(Length::Fill),
+                Button::new(text(if checking {
+                    "Checking…"
+                } else {
+                    "Check again"
+                }))
+                .style(iced::widget::button::secondary)
+                .on_press_maybe((!checking).then_some(Message::Run)),
+            ]
+            .align_y(Vertical::Center),
+            horizontal_rule(1),
+            findings,
+        ]
+        .spacing(12)
+        .max_width(560)
+        .into()
+    }
+}
+
+// the outcome of a check, with what may fix a problem
+fn finding_row(finding: &Finding) -> Element<'_, Message> {
+    let (icon, style): (char, fn(&iced::Theme) -> text::Style) = match finding.status {
+        Status::Ok => (ICON_CIRCLE_CHECK, text::success),
+        Status::Warning => (ICON_TRIANGLE_ALERT, text::warning),
+        Status::Error => (ICON_CIRCLE_X, text::danger),
+    };
+    let mut details = column![
+        text(&finding.check).font(SANS_BOLD).size(14),
+        text(&finding.detail).size(13),
+    ]
+    .spacing(2)
+    .width(Length::Fill);
+    if let Some(fix) = &finding.fix {
+        details = details.push(text(fix).size(12).style(text::secondary));
+    }
+    let mut finding_row = row![text(icon).font(ICON).size(16).style(style), details]
+        .spacing(10)
+        .align_y(Vertical::Center);
+    if let Some(section) = &finding.settings {
+        finding_row = finding_row.push(
+            Button::new(text("Open settings").size(12))
+                .style(iced::widget::button::secondary)
+                .on_press(Message::OpenSettings(section.clone())),
+        );
+    }
+    finding_row.into()
+}
@@ -0,0 +1,204 @@
+use crate::{
+    discogs::{self, Consumer, OAuthToken, OAuthTokens},
+    fonts::SANS_BOLD,
+};
+use iced::{
+    Element, Length, Task,
+    alignment::Vertical,
+    widget::{Button, column, container, row, text, text_input},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Open,
+    Cancel,
+    KeyChanged(String),
+    SecretChanged(String),
+    // asks Discogs for a temporary token to authorize
+    Authorize,
+    Authorizing(Result<OAuthToken, String>),
+    CopyLink(String),
+    VerifierChanged(String),
+    Connect,
+    // the tokens to keep, saved by the app
+    Connected(Result<OAuthTokens, String>),
+}
+
+/// Connects to Discogs with OAuth: the user authorizes Phoniq on the Discogs site and pastes
+/// back the verifier shown there.
+#[derive(Default)]
+pub struct DiscogsConnect {
+    open: bool,
+    // the application's key and secret, typed in when the build comes without them
+    key: String,
+    secret: String,
+    // the temporary token waiting to be authorized
+    request: Option<OAuthToken>,
+    verifier: String,
+    busy: bool,
+    status: Option<String>,
+}
+
+impl DiscogsConnect {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    fn consumer(&self) -> Option<Consumer> {
+        Consumer::bundled().or_else(|| {
+            let (key, secret) = (self.key.trim(), self.secret.trim());
+            (!key.is_empty() && !secret.is_empty()).then(|| Consumer {
+                key: key.to_string(),
+                secret: secret.to_string(),
+            })
+        })
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Open => self.open = true,
+            Message::Cancel => *self = Self::default(),
+            Message::KeyChanged(value) => self.key = value,
+            Message::SecretChanged(value) => self.secret = value,
+            Message::Authorize => {
+                let Some(consumer) = self.consumer() else {
+                    return Task::none();
+                };
+                self.busy = true;
+                self.status = None;
+                return Task::perform(discogs::request_token(consumer), |result| {
+                    Message::Authorizing(result.map_err(|e| e.to_string()))
+                });
+            }
+            Message::Authorizing(result) => {
+                self.busy = false;
+                match result {
+                    Ok(request) => self.request = Some(request),
+                    Err(error) => self.status = Some(error),
+                }
+            }
+            Message::CopyLink(link) => return iced::clipboard::write(link),
+            Message::VerifierChanged(value) => self.verifier = value,
+            Message::Connect => {
+                let (Some(consumer), Some(request)) = (self.consumer(), self.request.clone())
+                else {
+                    return Task::none();
+                };
+                self.busy = true;
+                self.status = None;
+                return Task::perform(
+                    discogs::access_token(consumer, request, self.verifier.clone()),
+                    |result| Message::Connected(result.map_err(|e| e.to_string())),
+                );
+            }
+            Message::Connected(Ok(_)) => *self = Self::default(),
+            Message::Connected(Err(error)) => {
+                // a verifier is good for one try, the user starts over
+                self.busy = false;
+                self.request = None;
+                self.verifier.clear();
+                self.status = Some(error);
+            }
+        }
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let mut content = column![text("Connect Discogs").font(SANS_BOLD).size(14)].spacing(10);
+        match &self.request {
+            None => {
+                if Consumer::bundled().is_none() {
+                    content = content
+                        .push(
+                            text(
+                                "This build has no Discogs application of its own. Register \
+                                 one under Developers in the Discogs settings and enter its \
+                                 consumer key and secret.",
+                            )
+                            .size(12),
+                        )
+                        .push(
+                            text_input("Consumer key", &self.key)
+                                .on_input(Message::KeyChanged)
+                                .width(Length::Fixed(310.0)),
+                        )
+                        .push(
+                            text_input("Consumer secret", &self.secret)
+                                .secure(true)
+                                .on_input(Message::SecretChanged)
+                                .width(Length::Fixed(310.0)),
+                        );
+                }
+                let authorize =
+                    (!self.busy && self.consumer().is_some()).then_some(Message::Authorize);
+                content = content.push(
+                    row![
+                        Button::new(text(if self.busy {
+                            "Asking Discogs…"
+                        } else {
+                            "Authorize on Discogs"
+                        }))
+                        .style(iced::widget::button::primary)
+                        .on_press_maybe(authorize),
+                        cancel(),
+                    ]
+                    .spacing(10),
+                );
+            }
+            Some(request) => {
+                let url = request.authorize_url();
+                let connect =
+                    (!self.busy && !self.verifier.trim().is_empty()).then_some(Message::Connect);
+                content = content
+                    .push(
+                        text(
+                            "Open this page in your browser, allow Phoniq and paste the code \
+                             Discogs shows.",
+                        )
+                        .size(12),
+                    )
+                    .push(
+                        row![
+                            text(url.clone()).size(12).width(Length::Fill),
+                            Button::new(text("Copy link").size(12))
+                                .style(iced::widget::button::secondary)
+                                .on_press(Message::CopyLink(url)),
+                        ]
+                        .spacing(10)
+                        .align_y(Vertical::Center),
+                    )
+                    .push(
+                        row![
+                            text_input("Verification code", &self.verifier)
+                                .on_input(Message::VerifierChanged)
+                                .on_submit_maybe(connect.clone())
+                                .width(Length::Fixed(200.0)),
+                            Button::new(text(if self.busy {
+                                "Connecting…"
+                            } else {
+                                "Connect"
+                            }))
+                            .style(iced::widget::button::primary)
+                            .on_press_maybe(connect),
+                            cancel(),
+                        ]
+                        .spacing(10)
+                        .align_y(Vertical::Center),
+                    );
+            }
+        }
+        if let Some(status) = &self.status {
+            content = content.push(text(status).size(12));
+        }
+        container(content)
+            .padding(12)
+            .style(container::rounded_box)
+            .into()
+    }
+}
+
+fn cancel<'a>() -> Button<'a, Message> {
+    Button::new(text("Cancel"))
+        .style(iced::widget::button::secondary)
+        .on_press(Message::Cancel)
+}
@@ -0,0 +1,98 @@
+use crate::{app_state::state_impl::State, fonts::SANS_BOLD};
+use iced::{
+    Element, Length,
+    alignment::Vertical,
+    widget::{Button, Column, column, horizontal_rule, row, text, text_input},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    NameInput(String),
+    // the name typed in is saved by the app
+    Create(String),
+    RenameInput(i64, String),
+    Rename(i64, String),
+    Delete(i64),
+}
+
+/// The fields defined by the user, which tracks hold a value of in the details and the list
+/// shows as columns.
+#[derive(Default)]
+pub struct FieldSettings {
+    name: String,
+    // the field being renamed and its name as typed in
+    renaming: Option<(i64, String)>,
+}
+
+// `=` separates a field from its value in the rules of dynamic playlists
+fn valid(name: &str) -> bool {
+    !name.trim().is_empty() && !name.contains('=')
+}
+
+impl FieldSettings {
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::NameInput(name) => self.name = name,
+            Message::Create(_) => self.name.clear(),
+            Message::RenameInput(id, name) => self.renaming = Some((id, name)),
+            Message::Rename(..) | Message::Delete(_) => self.renaming = None,
+        }
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let create = valid(&self.name).then(|| Message::Create(self.name.trim().to_string()));
+        let mut fields = Column::new().spacing(8);
+        for field in state.custom_fields() {
+            let id = field.id;
+            let name = match &self.renaming {
+                Some((renaming, name)) if *renaming == id => name.as_str(),
+                _ => field.name.as_str(),
+            };
+            let rename = (valid(name) && name.trim() != field.name)
+                .then(|| Message::Rename(id, name.trim().to_string()));
+            fields = fields.push(
+                row![
+                    text_input("Name", name)
+                        .size(14)
+                        .width(Length::Fill)
+                        .on_input(move |name| Message::RenameInput(id, name))
+                        .on_submit_maybe(rename.clone()),
+                    Button::new(text("Rename").size(14))
+                        .style(iced::widget::button::secondary)
+                        .on_press_maybe(rename),
+                    Button::new(text("Delete").size(14))
+                        .style(iced::widget::button::danger)
+                        .on_press(Message::Delete(id)),
+                ]
+                .spacing(10)
+                .align_y(Vertical::Center),
+            );
+        }
+
+        column![
+            text("Custom Fields").font(SANS_BOLD).size(20),
+            text(
+                "Fields of your own, e.g. Mood or Set slot. Tracks get a value in the details \
+                 panel, the list can show them as columns and dynamic playlists can filter by \
+                 them with a rule like Mood=dark."
+            )
+            .size(12),
+            row![
+                text_input("New field", &self.name)
+                    .size(14)
+                    .width(Length::Fill)
+                    .on_input(Message::NameInput)
+                    .on_submit_maybe(create.clone()),
+                Button::new(text("Add").size(14)).on_press_maybe(create),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            horizontal_rule(1),
+            fields,
+            text("Deleting a field deletes its values.").size(12),
+        ]
+        .spacing(12)
+        .max_width(480)
+        .into()
+    }
+}
@@ -0,0 +1,212 @@
+use crate::{
+    app_state::{AudioPlayable, PlayableId},
+    discogs::Credentials,
+    fonts::SANS_BOLD,
+    genre_inference::{self, Suggestion},
+    storage::Playable,
+};
+use iced::{
+    Element, Length, Padding, Task,
+    alignment::Vertical,
+    widget::{Button, Column, Scrollable, column, horizontal_rule, pick_list, row, text},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    // another of the genres suggested for a track
+    Picked(PlayableId, String),
+    Accept(PlayableId),
+    Reject(PlayableId),
+    AcceptAll,
+    RejectAll,
+    // the lookup the suggestions came from and what Discogs knew
+    LookedUp(usize, Vec<Suggestion>),
+    // the genres accepted for tracks, saved by the app
+    Save(Vec<(PlayableId, String)>),
+}
+
+/// The genres suggested for the tracks without one, each accepted or rejected by the user.
+/// Tracks the library has no suggestion for are looked up on Discogs when it is connected.
+#[derive(Default)]
+pub struct GenreSuggestionsView {
+    // each suggestion with the genre picked among its genres
+    suggestions: Vec<(Suggestion, String)>,
+    // tracks nothing is suggested for yet
+    unknown: usize,
+    discogs: bool,
+    looking_up: bool,
+    // counts the lookups, the results of one started before the view was opened again are
+    // dropped
+    lookup: usize,
+}
+
+impl GenreSuggestionsView {
+    /// Starts over with the suggestions of the library, the rest are looked up on Discogs.
+    pub fn open(
+        &mut self,
+        suggestions: Vec<Suggestion>,
+        unknown: Vec<Playable>,
+        credentials: Option<Credentials>,
+    ) -> Task<Message> {
+        self.suggestions.clear();
+        self.add(suggestions);
+        self.unknown = unknown.len();
+        self.discogs = credentials.is_some();
+        self.lookup += 1;
+        self.looking_up = false;
+        let Some(credentials) = credentials.filter(|_| !unknown.is_empty()) else {
+            return Task::none();
+        };
+        self.looking_up = true;
+        let lookup = self.lookup;
+        Task::perform(
+            genre_inference::from_discogs(unknown, credentials),
+            move |suggestions| Message::LookedUp(lookup, suggestions),
+        )
+    }
+
+    fn add(&mut self, suggestions: Vec<Suggestion>) {
+        self.suggestions
+            .extend(suggestions.into_iter().map(|suggestion| {
+                let picked = suggestion.genres[0].clone();
+                (suggestion, picked)
+            }));
+    }
+
+    // removes the suggestions of `ids` and returns the genres picked for them
+    fn take(&mut self, ids: &[PlayableId]) -> Vec<(PlayableId, String)> {
+        let (taken, kept) = std::mem::take(&mut self.suggestions)
+            .into_iter()
+            .partition(|(suggestion, _)| ids.contains(&suggestion.playable.id));
+        self.suggestions = kept;
+        taken
+            .into_iter()
+            .map(|(suggestion, picked)| (suggestion.playable.id, picked))
+            .collect()
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Picked(id, genre) => {
+                if let Some((_, picked)) = self
+                    .suggestions
+                    .iter_mut()
+                    .find(|(s, _)| s.playable.id == id)
+                {
+                    *picked = genre;
+                }
+            }
+            Message::Accept(id) => return Task::done(Message::Save(self.take(&[id]))),
+            Message::Reject(id) => {
+                self.take(&[id]);
+            }
+            Message::AcceptAll => {
+                let ids: Vec<PlayableId> = self
+                    .suggestions
+                    .iter()
+                    .map(|(s, _)| s.playable.id)
+                    .collect();
+                return Task::done(Message::Save(self.take(&ids)));
+            }
+            Message::RejectAll => self.suggestions.clear(),
+            Message::LookedUp(lookup, _) if lookup != self.lookup => {}
+            Message::LookedUp(_, suggestions) => {
+                self.looking_up = false;
+                self.unknown = self.unknown.saturating_sub(suggestions.len());
+                self.add(suggestions);
+            }
+            Message::Save(_) => {}
+        }
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let status = if self.looking_up {
+            format!("Looking up {} tracks on Discogs…", self.unknown)
+        } else if self.unknown > 0 && !self.discogs {
+            format!(
+                "Nothing is known about {} tracks, connect Discogs in Browse to look them up.",
+                self.unknown
+            )
+        } else if self.unknown > 0 {
+            format!("Nothing is known about {} tracks.", self.unknown)
+        } else {
+            String::new()
+        };
+        let has_suggestions = !self.suggestions.is_empty();
+
+        let header = column![
+            text("Genre Suggestions").font(SANS_BOLD).size(20),
+            text(
+                "Tracks without a genre get the genre of the tracks of their album or artist, \
+                 or of their release on Discogs. Accepted genres are saved in the library, the \
+                 files keep their tags."
+            )
+            .size(12),
+            row![
+                text(format!("{} suggestions", self.suggestions.len()))
+                    .size(14)
+                    .width(Length::Fill),
+                Button::new(text("Accept All").size(12))
+                    .style(iced::widget::button::primary)
+                    .on_press_maybe(has_suggestions.then_some(Message::AcceptAll)),
+                Button::new(text("Reject All").size(12))
+                    .style(iced::widget::button::secondary)
+                    .on_press_maybe(has_suggestions.then_some(Message::RejectAll)),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            text(status).size(12),
+        ]
+        .spacing(12)
+        .max_width(560);
+
+        let mut list = Column::new()
+            .spacing(8)
+            .padding(Padding::default().right(16));
+        for (suggestion, picked) in &self.suggestions {
+            let playable = &suggestion.playable;
+            let id = playable.id;
+            let details = [playable.get_artist(), playable.get_album()]
+                .into_iter()
+                .filter(|value| !value.trim().is_empty())
+                .collect::<Vec<_>>()
+                .join(" · ");
+            list = list.push(
+                row![
+                    column![
+                        text(playable.get_title()).font(SANS_BOLD).size(14),
+                        text(details).size(12).style(text::secondary),
+                    ]
+                    .spacing(2)
+                    .width(Length::Fill),
+                    text(suggestion.source.to_string())
+                        .size(12)
+                        .style(text::secondary),
+                    pick_list(
+                        suggestion.genres.as_slice(),
+                        Some(picked.clone()),
+                        move |genre| Message::Picked(id, genre)
+                    )
+                    .text_size(12),
+                    Button::new(text("Accept").size(12))
+                        .style(iced::widget::button::primary)
+                        .on_press(Message::Accept(id)),
+                    Button::new(text("Reject").size(12))
+                        .style(iced::widget::button::secondary)
+                        .on_press(Message::Reject(id)),
+                ]
+                .spacing(12)
+                .align_y(Vertical::Center),
+            );
+        }
+
+        column![
+            header,
+            horizontal_rule(1),
+            Scrollable::new(list).height(Length::Fill),
+        ]
+        .spacing(12)
+        .into()
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{
+    app_state::state_impl::State, fonts::SANS_BOLD, storage::ImportBatch, util::timestamp_to_str,
+};
+use iced::{
+    Element, Length, Padding,
+    alignment::Vertical,
+    widget::{Button, Column, Scrollable, column, horizontal_rule, row, text},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    // lists the playables of the batch in the central view
+    Show(i64),
+    Remove(i64),
+}
+
+pub fn view(state: &State) -> Element<'_, Message> {
+    if state.imports().is_empty() {
+        return text("Nothing imported yet").into();
+    }
+    let rows: Vec<Element<'_, Message>> = state
+        .imports()
+        .iter()
+        .flat_map(|batch| [import_row(batch), horizontal_rule(1).into()])
+        .collect();
+    Scrollable::new(
+        Column::from_vec(rows)
+            .spacing(12)
+            .padding(Padding::default().right(16)),
+    )
+    .height(Length::Fill)
+    .into()
+}
+
+fn import_row(batch: &ImportBatch) -> Element<'_, Message> {
+    let mut details = vec![
+        timestamp_to_str(batch.imported_at),
+        format!(
+            "{} files, {} new in library",
+            batch.file_count, batch.playable_count
+        ),
+    ];
+    if let Some(playlist) = &batch.playlist_name {
+        details.push(format!("into {playlist}"));
+    }
+
+    let info = column![
+        text(&batch.source).font(SANS_BOLD).size(14),
+        text(details.join(" · ")).size(12),
+    ]
+    .spacing(4)
+    .width(Length::Fill);
+
+    row![
+        info,
+        Button::new(text("Show").size(12))
+            .style(iced::widget::button::secondary)
+            .on_press(Message::Show(batch.id)),
+        Button::new(text("Remove").size(12))
+            .style(iced::widget::button::danger)
+            .on_press(Message::Remove(batch.id)),
+    ]
+    .spacing(12)
+    .align_y(Vertical::Center)
+    .into()
+}
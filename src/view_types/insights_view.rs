@@ -0,0 +1,152 @@
+use crate::{
+    app_state::{PlayableId, Section, state_impl::State},
+    fonts::SANS_BOLD,
+    storage::{AutoPlaylist, Playable},
+    util::duration_to_str,
+};
+use iced::{
+    Element, Length, Task,
+    alignment::Vertical,
+    widget::{Button, column, horizontal_rule, row, text, text_input},
+};
+
+const MONTH_OPTIONS: [u32; 4] = [3, 6, 12, 24];
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    MonthsChanged(u32),
+    // lists the group in the central view
+    Show(Section),
+    // plays the group without changing the central view
+    Queue(Section),
+    TagNameChanged(String),
+    // tags the playables with the typed in tag name
+    Tag(Vec<PlayableId>),
+    Remove(Vec<PlayableId>),
+    // opens the review of the genres suggested for the tracks without one
+    SuggestGenres,
+}
+
+#[derive(Default)]
+pub struct InsightsView {
+    tag_name: String,
+}
+
+impl InsightsView {
+    pub fn tag_name(&self) -> &str {
+        self.tag_name.trim()
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        if let Message::TagNameChanged(value) = message {
+            self.tag_name = value;
+        }
+        Task::none()
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let insights = state.insights();
+        let months = row(MONTH_OPTIONS.into_iter().map(|months| {
+            let style = if insights.months == months {
+                iced::widget::button::primary
+            } else {
+                iced::widget::button::secondary
+            };
+            Button::new(text(format!("{months} months")))
+                .style(style)
+                .on_press(Message::MonthsChanged(months))
+                .into()
+        }))
+        .spacing(4);
+
+        column![
+            text("Library Insights").font(SANS_BOLD).size(20),
+            row![
+                text("Tag for bulk tagging").size(14).width(Length::Fill),
+                text_input("relisten", &self.tag_name)
+                    .on_input(Message::TagNameChanged)
+                    .width(Length::Fixed(160.0)),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            horizontal_rule(1),
+            self.group(
+                "Never played".to_string(),
+                &insights.never_played,
+                Section::Auto(AutoPlaylist::NeverPlayed),
+            ),
+            horizontal_rule(1),
+            self.group(
+                format!("Not played in {} months", insights.months),
+                &insights.not_played,
+                Section::NotPlayedFor(insights.months),
+            ),
+            months,
+            horizontal_rule(1),
+            row![
+                column![
+                    text("Without a genre").font(SANS_BOLD).size(14),
+                    text(format!("{} tracks", insights.without_genre)).size(12),
+                ]
+                .spacing(4)
+                .width(Length::Fill),
+                Button::new(text("Suggest Genres").size(12))
+                    .style(iced::widget::button::secondary)
+                    .on_press_maybe((insights.without_genre > 0).then_some(Message::SuggestGenres)),
+            ]
+            .spacing(12)
+            .align_y(Vertical::Center),
+            text(
+                "Plays older than the history retention are forgotten, \
+                 keep the history forever in the privacy settings for accurate numbers."
+            )
+            .size(12),
+        ]
+        .spacing(12)
+        .max_width(560)
+        .into()
+    }
+
+    fn group<'a>(
+        &'a self,
+        title: String,
+        playables: &'a [Playable],
+        section: Section,
+    ) -> Element<'a, Message> {
+        let duration: i64 = playables.iter().map(|p| p.duration).sum();
+        let ids: Vec<PlayableId> = playables.iter().map(|p| p.id).collect();
+        let has_playables = !ids.is_empty();
+        let tag = (has_playables && !self.tag_name().is_empty()).then(|| Message::Tag(ids.clone()));
+
+        let info = column![
+            text(title).font(SANS_BOLD).size(14),
+            text(format!(
+                "{} tracks · {}",
+                playables.len(),
+                duration_to_str(duration.max(0) as u64)
+            ))
+            .size(12),
+        ]
+        .spacing(4)
+        .width(Length::Fill);
+
+        row![
+            info,
+            Button::new(text("Show").size(12))
+                .style(iced::widget::button::secondary)
+                .on_press(Message::Show(section.clone())),
+            Button::new(text("Queue").size(12))
+                .style(iced::widget::button::secondary)
+                .on_press_maybe(has_playables.then(|| Message::Queue(section))),
+            Button::new(text("Tag").size(12))
+                .style(iced::widget::button::secondary)
+                .on_press_maybe(tag),
+            Button::new(text("Remove").size(12))
+                .style(iced::widget::button::danger)
+                .on_press_maybe(has_playables.then_some(Message::Remove(ids))),
+        ]
+        .spacing(12)
+        .align_y(Vertical::Center)
+        .into()
+    }
+}
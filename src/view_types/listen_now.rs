@@ -0,0 +1,218 @@
+use crate::{
+    app_state::state_impl::State,
+    discogs,
+    fonts::SANS_BOLD,
+    storage::{Watch, WatchKind},
+    util::timestamp_to_str,
+};
+use iced::{
+    Element, Length, Task,
+    alignment::Vertical,
+    widget::{Button, Column, Scrollable, column, horizontal_rule, row, text, text_input},
+};
+
+// in hours
+const INTERVAL_OPTIONS: [(&str, u32); 4] =
+    [("6 h", 6), ("12 h", 12), ("Daily", 24), ("Weekly", 168)];
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    InputChanged(String),
+    Watch,
+    // the kind, Discogs id and name of the label or artist to watch
+    Watched(Result<(WatchKind, u64, String), String>),
+    IntervalChanged(i64, u32),
+    Remove(i64),
+    // marks the new releases of a watch as seen
+    Dismiss(i64),
+    // opens a release in Browse, by its Discogs id
+    Open(i64),
+    // lists the tracks of an album of the library
+    OpenAlbum(String),
+    CheckNow,
+}
+
+/// New releases of the Discogs labels and artists being watched, and the watches themselves.
+#[derive(Default)]
+pub struct ListenNowView {
+    input: String,
+    adding: bool,
+    status: Option<String>,
+}
+
+impl ListenNowView {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::InputChanged(value) => self.input = value,
+            Message::Watch => {
+                self.adding = true;
+                self.status = None;
+                return Task::perform(discogs::fetch_watch(self.input.clone()), |result| {
+                    Message::Watched(result.map_err(|e| e.to_string()))
+                });
+            }
+            Message::Watched(result) => {
+                self.adding = false;
+                match result {
+                    Ok(_) => self.input.clear(),
+                    Err(error) => self.status = Some(error),
+                }
+            }
+            Message::IntervalChanged(..)
+            | Message::Remove(_)
+            | Message::Dismiss(_)
+            | Message::Open(_)
+            | Message::OpenAlbum(_)
+            | Message::CheckNow => {}
+        }
+        Task::none()
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let mut content = column![text("Listen Now").font(SANS_BOLD).size(20)].spacing(12);
+        let this_week = state.this_week();
+        if !this_week.is_empty() {
+            let list = Column::with_children(this_week.iter().map(|album| {
+                let released = album.released.as_deref().unwrap_or_default();
+                let by = if album.artist.is_empty() {
+                    String::new()
+                } else {
+                    format!(" by {}", album.artist)
+                };
+                row![
+                    text(format!(
+                        "On this week in {}: {}{by}",
+                        released.get(..4).unwrap_or(released),
+                        album.name
+                    ))
+                    .size(12)
+                    .width(Length::Fill),
+                    Button::new(text("Show").size(12))
+                        .style(iced::widget::button::secondary)
+                        .on_press(Message::OpenAlbum(album.name.clone())),
+                ]
+                .spacing(10)
+                .align_y(Vertical::Center)
+                .into()
+            }))
+            .spacing(4);
+            content = content
+                .push(text("This week in your library").font(SANS_BOLD).size(14))
+                .push(list)
+                .push(horizontal_rule(1));
+        }
+        content = content.push(text("New releases").font(SANS_BOLD).size(14));
+        let new_releases = state.new_releases();
+        if new_releases.is_empty() {
+            content =
+                content.push(text("Nothing new from the labels and artists you watch").size(12));
+        }
+        for watch in state.watches() {
+            let releases: Vec<_> = new_releases
+                .iter()
+                .filter(|release| release.watch_id == watch.id)
+                .collect();
+            if releases.is_empty() {
+                continue;
+            }
+            let header = row![
+                text(&watch.name).font(SANS_BOLD).size(14),
+                text(format!("{} new", releases.len()))
+                    .size(12)
+                    .style(text::primary),
+                Button::new(text("Mark as seen").size(12))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::Dismiss(watch.id)),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center);
+            let list = Column::with_children(releases.into_iter().map(|release| {
+                let year = if release.year > 0 {
+                    format!(" ({})", release.year)
+                } else {
+                    String::new()
+                };
+                row![
+                    text(format!("{} - {}{year}", release.artist, release.title))
+                        .size(12)
+                        .width(Length::Fill),
+                    Button::new(text("Open").size(12))
+                        .style(iced::widget::button::primary)
+                        .on_press(Message::Open(release.release_id)),
+                ]
+                .spacing(10)
+                .align_y(Vertical::Center)
+                .into()
+            }))
+            .spacing(4);
+            content = content.push(column![header, list].spacing(6));
+        }
+
+        content = content
+            .push(horizontal_rule(1))
+            .push(text("Watching").font(SANS_BOLD).size(14))
+            .push(
+                row![
+                    text_input("Discogs label or artist link", &self.input)
+                        .on_input(Message::InputChanged)
+                        .on_submit(Message::Watch),
+                    Button::new(text(if self.adding { "Adding…" } else { "Watch" }))
+                        .style(iced::widget::button::primary)
+                        .on_press_maybe(
+                            (!self.adding && !self.input.trim().is_empty())
+                                .then_some(Message::Watch)
+                        ),
+                    Button::new(text("Check now"))
+                        .style(iced::widget::button::secondary)
+                        .on_press_maybe((!state.watches().is_empty()).then_some(Message::CheckNow)),
+                ]
+                .spacing(10)
+                .align_y(Vertical::Center),
+            )
+            .push(
+                text(
+                    "Releases out before a label or artist is watched are not new, those found \
+                     by later checks show up here.",
+                )
+                .size(12),
+            );
+        if let Some(status) = &self.status {
+            content = content.push(text(status).size(12));
+        }
+        content = content.push(Column::with_children(state.watches().iter().map(watch)).spacing(8));
+        Scrollable::new(content.max_width(640)).into()
+    }
+}
+
+fn watch(watch: &Watch) -> Element<'_, Message> {
+    let intervals = row(INTERVAL_OPTIONS.into_iter().map(|(label, hours)| {
+        let style = if watch.interval_hours == hours {
+            iced::widget::button::primary
+        } else {
+            iced::widget::button::secondary
+        };
+        Button::new(text(label).size(12))
+            .style(style)
+            .on_press(Message::IntervalChanged(watch.id, hours))
+            .into()
+    }))
+    .spacing(4);
+    let checked = watch
+        .checked_at
+        .map(|checked| format!("checked {}", timestamp_to_str(checked)))
+        .unwrap_or_else(|| "not checked yet".to_string());
+    row![
+        column![
+            text(format!("{}: {}", watch.kind, watch.name)).size(14),
+            text(checked).size(12),
+        ]
+        .width(Length::Fill),
+        intervals,
+        Button::new(text("Remove").size(12))
+            .style(iced::widget::button::danger)
+            .on_press(Message::Remove(watch.id)),
+    ]
+    .spacing(10)
+    .align_y(Vertical::Center)
+    .into()
+}
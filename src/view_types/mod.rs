@@ -1 +1,33 @@
+pub mod advanced_settings;
+pub mod album_grid;
+pub mod album_view;
+pub mod breadcrumbs;
+pub mod beatport_charts;
+pub mod browse_view;
+pub mod cdj_export_view;
+pub mod command_palette;
 pub mod compact_view;
+pub mod conversions_view;
+pub mod diagnostics_view;
+pub mod discogs_connect;
+pub mod field_settings;
+pub mod genre_suggestions_view;
+pub mod import_history;
+pub mod insights_view;
+pub mod listen_now;
+pub mod playback_settings;
+pub mod playlist_settings;
+pub mod podcast_view;
+pub mod privacy_settings;
+pub mod queue_view;
+pub mod quit_prompt;
+pub mod recently_deleted;
+pub mod recorder_view;
+pub mod scanner_settings;
+pub mod server_settings;
+pub mod settings_tabs;
+pub mod snapshots_view;
+pub mod song_info;
+pub mod split_view;
+pub mod sync_settings;
+pub mod vinyl_view;
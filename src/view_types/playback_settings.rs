@@ -0,0 +1,468 @@
+use std::fmt;
+
+use crate::{
+    app_state::{
+        AudioPlayable, PlayableKind,
+        state_impl::{CloseBehavior, ContextEnd, PlaylistNode, State},
+    },
+    fonts::SANS_BOLD,
+    resampler::ResampleQuality,
+    schedule::Alarm,
+    storage::PlaylistKind,
+    util::{remaining_to_str, unix_now},
+    volume_trim::{self, MAX_TRIM_DB, TrimTarget},
+};
+use iced::{
+    Element, Length,
+    alignment::Vertical,
+    widget::{Button, Column, column, horizontal_rule, pick_list, row, text, toggler},
+};
+
+const CONTEXT_END_OPTIONS: [(&str, ContextEnd); 3] = [
+    ("Stop", ContextEnd::Stop),
+    ("Repeat", ContextEnd::Repeat),
+    ("Continue into the library", ContextEnd::Library),
+];
+
+// in milliseconds
+const FADE_OPTIONS: [(&str, u64); 4] = [
+    ("Off", 0),
+    ("150 ms", 150),
+    ("300 ms", 300),
+    ("500 ms", 500),
+];
+
+// in seconds, up to `MAX_CROSSFADE_SECS`
+const CROSSFADE_OPTIONS: [(&str, u64); 6] = [
+    ("Gapless", 0),
+    ("2 s", 2),
+    ("4 s", 4),
+    ("6 s", 6),
+    ("8 s", 8),
+    ("12 s", 12),
+];
+
+// in milliseconds
+const LATENCY_OPTIONS: [(&str, u64); 5] = [
+    ("None", 0),
+    ("100 ms", 100),
+    ("200 ms", 200),
+    ("300 ms", 300),
+    ("2 s", 2000),
+];
+
+const RESAMPLE_OPTIONS: [(&str, ResampleQuality); 3] = [
+    ("Fast", ResampleQuality::Fast),
+    ("Medium", ResampleQuality::Medium),
+    ("High", ResampleQuality::High),
+];
+
+const CLOSE_OPTIONS: [(&str, CloseBehavior); 3] = [
+    ("Quit", CloseBehavior::Quit),
+    ("Keep playing minimized", CloseBehavior::KeepPlaying),
+    ("Ask", CloseBehavior::Ask),
+];
+
+// in dB below full scale, quieter sound counts as silence
+const SKIP_SILENCE_OPTIONS: [(&str, Option<i32>); 4] = [
+    ("Off", None),
+    ("−60 dB", Some(-60)),
+    ("−50 dB", Some(-50)),
+    ("−40 dB", Some(-40)),
+];
+
+// in minutes
+const LONG_TRACK_OPTIONS: [(&str, u32); 5] = [
+    ("Off", 0),
+    ("10 min", 10),
+    ("20 min", 20),
+    ("30 min", 30),
+    ("60 min", 60),
+];
+
+// in minutes
+const RAMP_OPTIONS: [(&str, u32); 4] = [("Off", 0), ("1 min", 1), ("5 min", 5), ("15 min", 15)];
+
+/// An hour or minute of the alarm time, shown with two digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Clock(u32);
+
+impl fmt::Display for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PlaylistChoice {
+    id: i64,
+    name: String,
+}
+
+impl fmt::Display for PlaylistChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+fn playlist_choices(nodes: &[PlaylistNode], choices: &mut Vec<PlaylistChoice>) {
+    for node in nodes {
+        if node.value.kind != PlaylistKind::Folder {
+            choices.push(PlaylistChoice {
+                id: node.value.id,
+                name: node.value.name.clone(),
+            });
+        }
+        playlist_choices(&node.children, choices);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ContextEndChanged(ContextEnd),
+    FadeChanged(u64),
+    CrossfadeChanged(u64),
+    LatencyChanged(u64),
+    MatchSourceRate(bool),
+    HoverPreview(bool),
+    // in minutes, 0 turns resuming long tracks off
+    LongTrackChanged(u32),
+    // in dB, None plays pauses whole
+    SkipSilenceChanged(Option<i32>),
+    ResampleQualityChanged(ResampleQuality),
+    // in dB, a station without a trim is added at 0
+    TrimChanged(TrimTarget, i32),
+    RemoveTrim(TrimTarget),
+    CloseBehaviorChanged(CloseBehavior),
+    AlarmChanged(Alarm),
+}
+
+pub fn view(state: &State) -> Element<'_, Message> {
+    let context_end = row(CONTEXT_END_OPTIONS.into_iter().map(|(label, option)| {
+        let style = if state.context_end() == option {
+            iced::widget::button::primary
+        } else {
+            iced::widget::button::secondary
+        };
+        Button::new(text(label))
+            .style(style)
+            .on_press(Message::ContextEndChanged(option))
+            .into()
+    }))
+    .spacing(4);
+    let fade = row(FADE_OPTIONS.into_iter().map(|(label, fade_ms)| {
+        let style = if state.fade_ms() == fade_ms {
+            iced::widget::button::primary
+        } else {
+            iced::widget::button::secondary
+        };
+        Button::new(text(label))
+            .style(style)
+            .on_press(Message::FadeChanged(fade_ms))
+            .into()
+    }))
+    .spacing(4);
+    let crossfade = row(CROSSFADE_OPTIONS.into_iter().map(|(label, secs)| {
+        let style = if state.crossfade_secs() == secs {
+            iced::widget::button::primary
+        } else {
+            iced::widget::button::secondary
+        };
+        Button::new(text(label))
+            .style(style)
+            .on_press(Message::CrossfadeChanged(secs))
+            .into()
+    }))
+    .spacing(4);
+    let latency = row(LATENCY_OPTIONS.into_iter().map(|(label, latency_ms)| {
+        let style = if state.output_latency_ms() == latency_ms {
+            iced::widget::button::primary
+        } else {
+            iced::widget::button::secondary
+        };
+        Button::new(text(label))
+            .style(style)
+            .on_press(Message::LatencyChanged(latency_ms))
+            .into()
+    }))
+    .spacing(4);
+
+    let long_track = row(LONG_TRACK_OPTIONS.into_iter().map(|(label, minutes)| {
+        let style = if state.long_track_minutes() == minutes {
+            iced::widget::button::primary
+        } else {
+            iced::widget::button::secondary
+        };
+        Button::new(text(label))
+            .style(style)
+            .on_press(Message::LongTrackChanged(minutes))
+            .into()
+    }))
+    .spacing(4);
+
+    let skip_silence = row(SKIP_SILENCE_OPTIONS
+        .into_iter()
+        .map(|(label, threshold_db)| {
+            let style = if state.skip_silence() == threshold_db {
+                iced::widget::button::primary
+            } else {
+                iced::widget::button::secondary
+            };
+            Button::new(text(label))
+                .style(style)
+                .on_press(Message::SkipSilenceChanged(threshold_db))
+                .into()
+        }))
+    .spacing(4);
+
+    let close = row(CLOSE_OPTIONS.into_iter().map(|(label, behavior)| {
+        let style = if state.close_behavior() == behavior {
+            iced::widget::button::primary
+        } else {
+            iced::widget::button::secondary
+        };
+        Button::new(text(label))
+            .style(style)
+            .on_press(Message::CloseBehaviorChanged(behavior))
+            .into()
+    }))
+    .spacing(4);
+
+    let resample = row(RESAMPLE_OPTIONS.into_iter().map(|(label, quality)| {
+        let style = if state.resample_quality() == quality {
+            iced::widget::button::primary
+        } else {
+            iced::widget::button::secondary
+        };
+        Button::new(text(label))
+            .style(style)
+            .on_press(Message::ResampleQualityChanged(quality))
+            .into()
+    }))
+    .spacing(4);
+
+    column![
+        text("Playback").font(SANS_BOLD).size(20),
+        text("At the end of a playlist, tag or the library").size(14),
+        context_end,
+        text("Shuffle keeps picking from the list and never reaches its end.").size(12),
+        text("Fade on pause, resume and skip").size(14),
+        fade,
+        text("From one track to the next").size(14),
+        crossfade,
+        text(
+            "The next track is prepared while one plays and starts the moment it ends, or \
+             overlaps its end. Not while shuffling, streaming or changing the device format."
+        )
+        .size(12),
+        text("Output latency").size(14),
+        latency,
+        text(
+            "Bluetooth headphones usually lag 150 to 300 ms and AirPlay up to 2 seconds, \
+             the progress shown is held back by the latency to match what you hear."
+        )
+        .size(12),
+        text("Skip silence").size(14),
+        skip_silence,
+        text(
+            "Pauses longer than half a second are cut short as they play, for lectures and \
+             field recordings. Sound quieter than the level counts as silence."
+        )
+        .size(12),
+        text("Resume tracks longer than").size(14),
+        long_track,
+        text(
+            "Mixes and live sets pick up where they were left the next time they play, and \
+             start over once played to the end."
+        )
+        .size(12),
+        horizontal_rule(1),
+        toggler(state.match_source_rate())
+            .label("Match source sample rate")
+            .on_toggle(Message::MatchSourceRate),
+        text(
+            "Opens the output device at the sample rate of each track so it is not resampled, \
+             the volume and the volume trims are left out meanwhile. The device stays shared \
+             with other apps and their mixer, this is not an exclusive output. Formats the \
+             device can not play go through the system mixer as before."
+        )
+        .size(12),
+        text("Sample rate conversion").size(14),
+        resample,
+        text(
+            "Used when a track and the output device run at different sample rates, \
+             higher quality takes more CPU."
+        )
+        .size(12),
+        horizontal_rule(1),
+        toggler(state.hover_preview())
+            .label("Preview tracks on hover in Browse")
+            .on_toggle(Message::HoverPreview),
+        text(
+            "Resting the pointer on a track of a release found in your library plays a short, \
+             quieter snippet of it. The playing track is turned down until the snippet ends or \
+             the pointer moves away."
+        )
+        .size(12),
+        horizontal_rule(1),
+        text("Closing the window while playing").size(14),
+        close,
+        text("Minimized, Phoniq keeps playing until it is quit from the window again.").size(12),
+        horizontal_rule(1),
+        trims_view(state),
+        horizontal_rule(1),
+        alarm_view(state),
+    ]
+    .spacing(12)
+    .max_width(480)
+    .into()
+}
+
+fn trims_view(state: &State) -> Element<'_, Message> {
+    let trims = state.volume_trims();
+    let kinds = volume_trim::KINDS.into_iter().map(|(label, kind)| {
+        let target = TrimTarget::Kind(kind);
+        let db = trims.get(&target).unwrap_or(0);
+        trim_row(label.to_string(), target, db)
+    });
+    let stations = trims.stations().map(|(host, db)| {
+        let target = TrimTarget::Station(host.to_string());
+        trim_row(host.to_string(), target.clone(), db)
+            .push(
+                Button::new(text("Remove").size(12))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::RemoveTrim(target)),
+            )
+            .into()
+    });
+    let mut content = column![
+        text("Volume trims").size(14),
+        Column::with_children(kinds.map(Into::into)).spacing(4),
+        Column::with_children(stations).spacing(4),
+    ]
+    .spacing(8);
+    // the station of the playing stream, once it has no trim of its own
+    let playing = state
+        .current_queued()
+        .filter(|playable| playable.get_kind() == PlayableKind::Stream)
+        .and_then(|playable| volume_trim::station(playable.get_path()))
+        .filter(|host| trims.get(&TrimTarget::Station(host.clone())).is_none());
+    if let Some(host) = playing {
+        content = content.push(
+            Button::new(text(format!("Trim {host}")).size(12))
+                .style(iced::widget::button::secondary)
+                .on_press(Message::TrimChanged(TrimTarget::Station(host), 0)),
+        );
+    }
+    content
+        .push(
+            text(
+                "Evens out sources playing louder or quieter than your files, from the next \
+                 track on. A station's trim wins over the one for streams.",
+            )
+            .size(12),
+        )
+        .into()
+}
+
+fn trim_row<'a>(label: String, target: TrimTarget, db: i32) -> iced::widget::Row<'a, Message> {
+    row![
+        text(label).size(14).width(Length::Fixed(160.0)),
+        Button::new(text("−"))
+            .style(iced::widget::button::secondary)
+            .on_press_maybe(
+                (db > -MAX_TRIM_DB).then(|| Message::TrimChanged(target.clone(), db - 1))
+            ),
+        text(format!("{db:+} dB"))
+            .size(14)
+            .width(Length::Fixed(60.0)),
+        Button::new(text("+"))
+            .style(iced::widget::button::secondary)
+            .on_press_maybe((db < MAX_TRIM_DB).then(|| Message::TrimChanged(target, db + 1))),
+    ]
+    .spacing(8)
+    .align_y(Vertical::Center)
+}
+
+fn alarm_view(state: &State) -> Element<'_, Message> {
+    let alarm = state.alarm();
+    let mut playlists = Vec::new();
+    playlist_choices(state.playlists(), &mut playlists);
+    let selected = playlists
+        .iter()
+        .find(|choice| Some(choice.id) == alarm.playlist)
+        .cloned();
+    let hours: Vec<Clock> = (0..24).map(Clock).collect();
+    let minutes: Vec<Clock> = (0..60).step_by(5).map(Clock).collect();
+
+    let time = row![
+        pick_list(hours, Some(Clock(alarm.time / 60)), move |hour| {
+            Message::AlarmChanged(Alarm {
+                time: hour.0 * 60 + alarm.time % 60,
+                ..alarm.clone()
+            })
+        }),
+        text(":").size(14),
+        pick_list(minutes, Some(Clock(alarm.time % 60)), move |minute| {
+            Message::AlarmChanged(Alarm {
+                time: alarm.time / 60 * 60 + minute.0,
+                ..alarm.clone()
+            })
+        }),
+        pick_list(playlists, selected, move |choice| {
+            Message::AlarmChanged(Alarm {
+                playlist: Some(choice.id),
+                ..alarm.clone()
+            })
+        })
+        .placeholder("Pick a playlist"),
+    ]
+    .spacing(6)
+    .align_y(Vertical::Center);
+    let ramp = row(RAMP_OPTIONS.into_iter().map(|(label, ramp_minutes)| {
+        let style = if alarm.ramp_minutes == ramp_minutes {
+            iced::widget::button::primary
+        } else {
+            iced::widget::button::secondary
+        };
+        Button::new(text(label))
+            .style(style)
+            .on_press(Message::AlarmChanged(Alarm {
+                ramp_minutes,
+                ..alarm.clone()
+            }))
+            .into()
+    }))
+    .spacing(4);
+    let next = match state.next_alarm() {
+        Some(ring) => format!(
+            "Plays in {}",
+            remaining_to_str((ring - unix_now()).max(0) as u64)
+        ),
+        None if alarm.enabled => "Pick a playlist for the alarm to play".to_string(),
+        None => "The alarm is off".to_string(),
+    };
+
+    column![
+        toggler(alarm.enabled)
+            .label("Alarm")
+            .on_toggle(move |enabled| Message::AlarmChanged(Alarm {
+                enabled,
+                ..alarm.clone()
+            })),
+        text("Starts a playlist at").size(14),
+        time,
+        text("Rises from silence over").size(14),
+        ramp,
+        toggler(alarm.daily)
+            .label("Every day")
+            .on_toggle(move |daily| Message::AlarmChanged(Alarm {
+                daily,
+                ..alarm.clone()
+            })),
+        text(next).size(12),
+        text("Phoniq has to be running for the alarm to play.").size(12),
+    ]
+    .spacing(12)
+    .into()
+}
@@ -0,0 +1,62 @@
+use crate::{
+    app_state::state_impl::State, clipboard::DEFAULT_TRACKLIST_TEMPLATE, fonts::SANS_BOLD,
+};
+use iced::{
+    Element,
+    alignment::Vertical,
+    widget::{Button, column, row, text, text_input},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    TemplateInput(String),
+    // the template typed in is saved by the app
+    SaveTemplate(String),
+}
+
+/// How playlists are written out, the template is saved once submitted rather than per key.
+#[derive(Default)]
+pub struct PlaylistSettings {
+    // the template as typed in, None while it is the saved one
+    template: Option<String>,
+}
+
+impl PlaylistSettings {
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::TemplateInput(template) => self.template = Some(template),
+            Message::SaveTemplate(_) => self.template = None,
+        }
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let template = self
+            .template
+            .as_deref()
+            .unwrap_or(state.tracklist_template());
+        let save = (template != state.tracklist_template())
+            .then(|| Message::SaveTemplate(template.to_string()));
+        column![
+            text("Tracklist format").font(SANS_BOLD).size(20),
+            row![
+                text_input(DEFAULT_TRACKLIST_TEMPLATE, template)
+                    .on_input(Message::TemplateInput)
+                    .on_submit_maybe(save.clone()),
+                Button::new(text("Save"))
+                    .style(iced::widget::button::secondary)
+                    .on_press_maybe(save),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            text(
+                "One line per track when copying a playlist's tracklist. \
+                 {n}, {artist}, {title}, {album}, {genre}, {label} and {duration} are filled in, \
+                 brackets around an empty one are left out. An empty format uses the default."
+            )
+            .size(12),
+        ]
+        .spacing(12)
+        .max_width(480)
+        .into()
+    }
+}
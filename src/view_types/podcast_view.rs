@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+
+use crate::{
+    app_state::state_impl::State,
+    fonts::{ICON, SANS_BOLD},
+    icons::{ICON_CIRCLE_CHECK, ICON_DOWNLOAD, ICON_LOADER, ICON_PLAY},
+    storage::Episode,
+    util::{duration_to_str, timestamp_to_str},
+};
+use iced::{
+    Element, Length, Padding, Task,
+    alignment::Vertical,
+    widget::{Button, Column, Scrollable, column, horizontal_rule, row, text},
+};
+
+const DESCRIPTION_MAX_CHARS: usize = 280;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Play(i64),
+    Download(i64),
+    DownloadFinished(i64),
+}
+
+#[derive(Default)]
+pub struct PodcastView {
+    downloading: HashSet<i64>,
+}
+
+impl PodcastView {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Download(id) => {
+                self.downloading.insert(id);
+            }
+            Message::DownloadFinished(id) => {
+                self.downloading.remove(&id);
+            }
+            _ => {}
+        };
+        Task::none()
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        if state.episodes().is_empty() {
+            return text("No episodes yet").into();
+        }
+        let rows: Vec<Element<'a, Message>> = state
+            .episodes()
+            .iter()
+            .flat_map(|episode| [self.episode_row(episode), horizontal_rule(1).into()])
+            .collect();
+        Scrollable::new(
+            Column::from_vec(rows)
+                .spacing(12)
+                .padding(Padding::default().right(16)),
+        )
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn episode_row<'a>(&'a self, episode: &'a Episode) -> Element<'a, Message> {
+        let mut details = Vec::new();
+        if episode.published > 0 {
+            details.push(timestamp_to_str(episode.published)[..10].to_string());
+        }
+        if episode.duration > 0 {
+            details.push(duration_to_str(episode.duration as u64));
+        }
+        if episode.position > 0 {
+            details.push(format!(
+                "resume at {}",
+                duration_to_str(episode.position as u64)
+            ));
+        }
+
+        let description: String = episode
+            .description
+            .chars()
+            .take(DESCRIPTION_MAX_CHARS)
+            .collect();
+        let description = if description.len() < episode.description.len() {
+            format!("{description}…")
+        } else {
+            description
+        };
+
+        let info = column![
+            text(&episode.title).font(SANS_BOLD).size(14),
+            text(details.join(" · ")).size(12),
+            text(description).size(12),
+        ]
+        .spacing(4)
+        .width(Length::Fill);
+
+        let download: Element<'a, Message> = if episode.local_path.is_some() {
+            icon(ICON_CIRCLE_CHECK).into()
+        } else if self.downloading.contains(&episode.id) {
+            icon(ICON_LOADER).into()
+        } else {
+            Button::new(icon(ICON_DOWNLOAD))
+                .style(iced::widget::button::text)
+                .on_press(Message::Download(episode.id))
+                .into()
+        };
+
+        row![
+            Button::new(icon(ICON_PLAY))
+                .style(iced::widget::button::text)
+                .on_press(Message::Play(episode.id)),
+            info,
+            download,
+        ]
+        .spacing(12)
+        .align_y(Vertical::Center)
+        .into()
+    }
+}
+
+fn icon<'a>(icon: char) -> iced::widget::Text<'a> {
+    text(icon).font(ICON).size(18)
+}
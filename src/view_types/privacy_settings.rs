@@ -0,0 +1,55 @@
+use crate::{app_state::state_impl::State, fonts::SANS_BOLD};
+use iced::{
+    Element,
+    widget::{Button, column, horizontal_rule, row, text, toggler},
+};
+
+const RETENTION_OPTIONS: [(&str, Option<u32>); 4] = [
+    ("30 days", Some(30)),
+    ("90 days", Some(90)),
+    ("1 year", Some(365)),
+    ("Forever", None),
+];
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    // days to keep plays for, None for forever
+    RetentionChanged(Option<u32>),
+    Incognito(bool),
+    ClearHistory,
+}
+
+pub fn view(state: &State) -> Element<'_, Message> {
+    let retention = row(RETENTION_OPTIONS.into_iter().map(|(label, days)| {
+        let style = if state.history_retention() == days {
+            iced::widget::button::primary
+        } else {
+            iced::widget::button::secondary
+        };
+        Button::new(text(label))
+            .style(style)
+            .on_press(Message::RetentionChanged(days))
+            .into()
+    }))
+    .spacing(4);
+
+    column![
+        text("Listening History").font(SANS_BOLD).size(20),
+        text("Keep plays for").size(14),
+        retention,
+        text("Older plays are deleted on startup and whenever this changes.").size(12),
+        horizontal_rule(1),
+        toggler(state.incognito())
+            .label("Listen incognito")
+            .on_toggle(Message::Incognito),
+        text("Nothing is added to the history until incognito is turned off or the app restarts.")
+            .size(12),
+        horizontal_rule(1),
+        Button::new(text("Clear history"))
+            .style(iced::widget::button::danger)
+            .on_press(Message::ClearHistory),
+    ]
+    .spacing(12)
+    .max_width(480)
+    .into()
+}
@@ -0,0 +1,93 @@
+use crate::{
+    app_state::{AudioPlayable, state_impl::State},
+    fonts::{ICON, SANS_BOLD},
+    icons::{ICON_ARROW_DOWN, ICON_ARROW_UP},
+    storage::Playable,
+    util::duration_to_str,
+};
+use iced::{
+    Element, Length,
+    alignment::Vertical,
+    widget::{Button, Column, Scrollable, column, row, text},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    // from and to positions in the queue
+    Move(usize, usize),
+    Remove(usize),
+    Clear,
+}
+
+pub fn view(state: &State) -> Element<'_, Message> {
+    let up_next = state.up_next();
+    let mut content = column![
+        row![
+            text("Queue").font(SANS_BOLD).size(20).width(Length::Fill),
+            Button::new(text("Clear").size(12))
+                .style(iced::widget::button::secondary)
+                .on_press_maybe((!up_next.is_empty()).then_some(Message::Clear)),
+        ]
+        .align_y(Vertical::Center),
+    ]
+    .spacing(12);
+    if let Some(playable) = state.current_queued().filter(|_| state.playing_up_next()) {
+        content = content.push(
+            text(format!(
+                "Now playing {} - {}",
+                playable.get_artist(),
+                playable.get_title()
+            ))
+            .size(12),
+        );
+    }
+    if up_next.is_empty() {
+        content = content.push(
+            text(
+                "Nothing queued. Right click tracks to play them next or last, after them \
+                 playback goes on with the list it was started from.",
+            )
+            .size(12),
+        );
+    }
+    let last = up_next.len().saturating_sub(1);
+    content = content.push(
+        Column::with_children(
+            up_next
+                .iter()
+                .enumerate()
+                .map(|(index, playable)| queued(index, last, playable)),
+        )
+        .spacing(4),
+    );
+    Scrollable::new(content.max_width(640)).into()
+}
+
+fn queued(index: usize, last: usize, playable: &Playable) -> Element<'_, Message> {
+    let arrow = |icon: char, to: Option<usize>| {
+        Button::new(text(icon).font(ICON).size(12))
+            .style(iced::widget::button::text)
+            .on_press_maybe(to.map(|to| Message::Move(index, to)))
+    };
+    row![
+        text(format!("{}.", index + 1))
+            .size(12)
+            .width(Length::Fixed(30.0)),
+        text(format!(
+            "{} - {}",
+            playable.get_artist(),
+            playable.get_title()
+        ))
+        .size(14)
+        .width(Length::Fill),
+        text(duration_to_str(playable.get_duration())).size(12),
+        arrow(ICON_ARROW_UP, index.checked_sub(1)),
+        arrow(ICON_ARROW_DOWN, (index < last).then_some(index + 1)),
+        Button::new(text("Remove").size(12))
+            .style(iced::widget::button::secondary)
+            .on_press(Message::Remove(index)),
+    ]
+    .spacing(10)
+    .align_y(Vertical::Center)
+    .into()
+}
@@ -0,0 +1,40 @@
+use crate::fonts::SANS_BOLD;
+use iced::{
+    Element, Length, Padding,
+    alignment::Vertical,
+    widget::{Button, container, row, text},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Quit,
+    // minimizes the window and keeps playing
+    KeepPlaying,
+    Cancel,
+}
+
+/// Asks what to do when the window is closed while something plays.
+pub fn view<'a>() -> Element<'a, Message> {
+    container(
+        row![
+            text("Music is playing. Quit Phoniq?")
+                .font(SANS_BOLD)
+                .size(14)
+                .width(Length::Fill),
+            Button::new(text("Keep playing minimized"))
+                .style(iced::widget::button::secondary)
+                .on_press(Message::KeepPlaying),
+            Button::new(text("Cancel"))
+                .style(iced::widget::button::secondary)
+                .on_press(Message::Cancel),
+            Button::new(text("Quit"))
+                .style(iced::widget::button::danger)
+                .on_press(Message::Quit),
+        ]
+        .spacing(8)
+        .align_y(Vertical::Center),
+    )
+    .padding(Padding::from([8, 16]))
+    .width(Length::Fill)
+    .into()
+}
@@ -0,0 +1,123 @@
+use crate::{
+    app_state::{AudioPlayable, PlayableId, state_impl::State},
+    fonts::SANS_BOLD,
+    storage::Playable,
+    util::{timestamp_to_str, unix_now},
+};
+use iced::{
+    Element, Length, Padding,
+    alignment::Vertical,
+    widget::{Button, Column, Scrollable, column, horizontal_rule, row, text},
+};
+
+const RETENTION_OPTIONS: [(&str, Option<u32>); 4] = [
+    ("7 days", Some(7)),
+    ("30 days", Some(30)),
+    ("90 days", Some(90)),
+    ("Until emptied", None),
+];
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    // days to keep deleted playables for, None until emptied by hand
+    RetentionChanged(Option<u32>),
+    Restore(PlayableId),
+    // deletes the playable for good
+    Purge(PlayableId),
+    RestoreAll,
+    Empty,
+}
+
+pub fn view(state: &State) -> Element<'_, Message> {
+    let retention = row(RETENTION_OPTIONS.into_iter().map(|(label, days)| {
+        let style = if state.deleted_retention() == days {
+            iced::widget::button::primary
+        } else {
+            iced::widget::button::secondary
+        };
+        Button::new(text(label))
+            .style(style)
+            .on_press(Message::RetentionChanged(days))
+            .into()
+    }))
+    .spacing(4);
+
+    let mut content = column![
+        text("Recently Deleted").font(SANS_BOLD).size(20),
+        text("Keep deleted tracks for").size(14),
+        retention,
+        text("Older tracks are deleted for good on startup and whenever this changes.").size(12),
+        horizontal_rule(1),
+    ]
+    .spacing(12);
+
+    if state.deleted().is_empty() {
+        return content.push(text("Nothing deleted")).into();
+    }
+
+    let actions = row![
+        Button::new(text("Restore all"))
+            .style(iced::widget::button::secondary)
+            .on_press(Message::RestoreAll),
+        Button::new(text("Empty"))
+            .style(iced::widget::button::danger)
+            .on_press(Message::Empty),
+    ]
+    .spacing(8);
+    let rows: Vec<Element<'_, Message>> = state
+        .deleted()
+        .iter()
+        .flat_map(|(playable, deleted_at)| {
+            [
+                deleted_row(playable, *deleted_at, state.deleted_retention()),
+                horizontal_rule(1).into(),
+            ]
+        })
+        .collect();
+    content = content.push(actions).push(
+        Scrollable::new(
+            Column::from_vec(rows)
+                .spacing(12)
+                .padding(Padding::default().right(16)),
+        )
+        .height(Length::Fill),
+    );
+    content.into()
+}
+
+fn deleted_row(
+    playable: &Playable,
+    deleted_at: i64,
+    retention: Option<u32>,
+) -> Element<'_, Message> {
+    let mut details = vec![format!("deleted {}", timestamp_to_str(deleted_at))];
+    if let Some(days) = retention {
+        let days_left = (deleted_at + days as i64 * 86_400 - unix_now()).max(0) / 86_400;
+        details.push(format!("gone in {days_left} days"));
+    }
+    let title = if playable.get_artist().is_empty() {
+        playable.get_title().to_string()
+    } else {
+        format!("{} - {}", playable.get_artist(), playable.get_title())
+    };
+
+    let info = column![
+        text(title).font(SANS_BOLD).size(14),
+        text(details.join(" · ")).size(12),
+    ]
+    .spacing(4)
+    .width(Length::Fill);
+
+    row![
+        info,
+        Button::new(text("Restore").size(12))
+            .style(iced::widget::button::secondary)
+            .on_press(Message::Restore(playable.id)),
+        Button::new(text("Delete now").size(12))
+            .style(iced::widget::button::danger)
+            .on_press(Message::Purge(playable.id)),
+    ]
+    .spacing(12)
+    .align_y(Vertical::Center)
+    .into()
+}
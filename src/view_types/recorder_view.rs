@@ -0,0 +1,220 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    app_state::state_impl::State,
+    convert::{self, Format},
+    fonts::SANS_BOLD,
+    recorder::{self, InputSource, RecordFormat, Recorder, Take},
+    util::{duration_to_str, timestamp_to_str, unix_now},
+};
+use iced::{
+    Element, Length, Subscription, Task,
+    alignment::Vertical,
+    widget::{Button, column, pick_list, row, text},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Refresh,
+    SourceSelected(InputSource),
+    FormatSelected(RecordFormat),
+    Start,
+    Stop,
+    // redraws the elapsed time while recording
+    Tick,
+    Encoded(Take, String, Result<PathBuf, String>),
+    // the file to add to the library, its title and duration
+    Finished(PathBuf, String, u64),
+}
+
+#[derive(Default)]
+pub struct RecorderView {
+    sources: Vec<InputSource>,
+    source: Option<InputSource>,
+    format: RecordFormat,
+    recorder: Option<Recorder>,
+    // title of the recording in progress
+    title: String,
+    encoding: bool,
+    status: Option<String>,
+}
+
+impl RecorderView {
+    /// Lists the devices again and picks the last one recorded from, unless recording.
+    pub fn reset(&mut self, state: &State) {
+        if self.recorder.is_some() {
+            return;
+        }
+        self.sources = recorder::input_sources();
+        self.source = self
+            .sources
+            .iter()
+            .find(|source| Some(source.name.as_str()) == state.record_source())
+            .or(self.sources.first())
+            .cloned();
+        self.format = state.record_format();
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Refresh => {
+                self.sources = recorder::input_sources();
+                if !self.sources.iter().any(|s| Some(s) == self.source.as_ref()) {
+                    self.source = self.sources.first().cloned();
+                }
+            }
+            Message::SourceSelected(source) => self.source = Some(source),
+            Message::FormatSelected(format) => self.format = format,
+            Message::Start => {
+                let Some(source) = self.source.clone() else {
+                    return Task::none();
+                };
+                let Some(folder) = recorder::recordings_dir() else {
+                    self.status = Some("There is no music folder to record to".to_string());
+                    return Task::none();
+                };
+                let timestamp = timestamp_to_str(unix_now());
+                let path = folder.join(format!("Recording {}.wav", timestamp.replace(':', "-")));
+                match Recorder::start(source, path) {
+                    Ok(recorder) => {
+                        self.recorder = Some(recorder);
+                        self.title = format!("Recording {timestamp}");
+                        self.status = None;
+                    }
+                    Err(error) => self.status = Some(error.to_string()),
+                }
+            }
+            Message::Stop => {
+                let Some(recorder) = self.recorder.take() else {
+                    return Task::none();
+                };
+                let take = match recorder.stop() {
+                    Ok(take) => take,
+                    Err(error) => {
+                        self.status = Some(error.to_string());
+                        return Task::none();
+                    }
+                };
+                let title = self.title.clone();
+                if self.format == RecordFormat::Wav {
+                    return Task::done(Message::Finished(take.path, title, take.duration));
+                }
+                self.encoding = true;
+                return Task::perform(
+                    convert::convert(
+                        take.path.to_string_lossy().to_string(),
+                        take.path.with_extension("flac"),
+                        Format::Flac,
+                        0,
+                    ),
+                    move |result| {
+                        Message::Encoded(
+                            take.clone(),
+                            title.clone(),
+                            result.map_err(|e| e.to_string()),
+                        )
+                    },
+                );
+            }
+            Message::Encoded(take, title, result) => {
+                self.encoding = false;
+                return match result {
+                    Ok(path) => {
+                        let _ = std::fs::remove_file(&take.path);
+                        Task::done(Message::Finished(path, title, take.duration))
+                    }
+                    Err(error) => {
+                        // the WAV is added instead, nothing recorded is lost
+                        self.status = Some(format!("Kept the WAV, encoding failed: {error}"));
+                        Task::done(Message::Finished(take.path, title, take.duration))
+                    }
+                };
+            }
+            Message::Finished(_, title, _) => {
+                if self.status.is_none() {
+                    self.status = Some(format!("Added {title} to the library"));
+                }
+            }
+            Message::Tick => {}
+        }
+        Task::none()
+    }
+
+    pub fn subscription(&self) -> Subscription<Message> {
+        if self.recorder.is_some() {
+            iced::time::every(Duration::from_secs(1)).map(|_| Message::Tick)
+        } else {
+            Subscription::none()
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let recording = self.recorder.is_some();
+        let record = if recording {
+            Button::new(text("Stop"))
+                .style(iced::widget::button::danger)
+                .on_press(Message::Stop)
+        } else {
+            Button::new(text(if self.encoding {
+                "Encoding…"
+            } else {
+                "Record"
+            }))
+            .style(iced::widget::button::primary)
+            .on_press_maybe((self.source.is_some() && !self.encoding).then_some(Message::Start))
+        };
+        let elapsed = self
+            .recorder
+            .as_ref()
+            .map(|recorder| {
+                format!(
+                    "{}  {}",
+                    self.title,
+                    duration_to_str(recorder.elapsed().as_secs())
+                )
+            })
+            .unwrap_or_default();
+
+        let mut content = column![
+            text("Recorder").font(SANS_BOLD).size(20),
+            row![
+                text("Record from").size(14).width(Length::Fill),
+                pick_list(
+                    self.sources.as_slice(),
+                    self.source.clone(),
+                    Message::SourceSelected
+                )
+                .placeholder("No input found"),
+                Button::new(text("Refresh").size(12))
+                    .style(iced::widget::button::secondary)
+                    .on_press_maybe((!recording).then_some(Message::Refresh)),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            row![
+                text("Save as").size(14).width(Length::Fill),
+                pick_list(
+                    RecordFormat::ALL,
+                    Some(self.format),
+                    Message::FormatSelected
+                ),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            text(
+                "To capture what the computer plays, pick a device marked (what plays), or on \
+                 Linux a monitor of the output in the sound settings. FLAC needs ffmpeg."
+            )
+            .size(12),
+            row![record, text(elapsed).size(14)]
+                .spacing(16)
+                .align_y(Vertical::Center),
+        ]
+        .spacing(12)
+        .max_width(480);
+        if let Some(status) = &self.status {
+            content = content.push(text(status).size(12));
+        }
+        content.into()
+    }
+}
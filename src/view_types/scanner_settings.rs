@@ -0,0 +1,231 @@
+use std::path::PathBuf;
+
+use crate::{
+    audio_scanner::{ScanOptions, default_scan_threads},
+    fonts::SANS_BOLD,
+};
+use iced::{
+    Element, Length, Task,
+    alignment::Vertical,
+    widget::{Button, Column, column, row, text, text_editor, text_input, toggler},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ExcludeEdited(text_editor::Action),
+    MinDurationChanged(String),
+    FollowSymlinks(bool),
+    SkipHidden(bool),
+    MaxDepthChanged(String),
+    ThreadsChanged(String),
+    ArtworkMaxSizeChanged(String),
+    Save,
+    // watched folders are saved right away
+    AddWatchFolder,
+    WatchFolderPicked(Option<PathBuf>),
+    RemoveWatchFolder(PathBuf),
+}
+
+#[derive(Default)]
+pub struct ScannerSettings {
+    // one glob per line
+    exclude: text_editor::Content,
+    min_duration: String,
+    follow_symlinks: bool,
+    skip_hidden: bool,
+    // empty for no limit
+    max_depth: String,
+    // empty for the default
+    threads: String,
+    // empty to keep the original artwork
+    artwork_max_size: String,
+}
+
+impl ScannerSettings {
+    pub fn new(options: &ScanOptions) -> Self {
+        Self {
+            exclude: text_editor::Content::with_text(&options.exclude.join("\n")),
+            min_duration: options.min_duration.to_string(),
+            follow_symlinks: options.follow_symlinks,
+            skip_hidden: options.skip_hidden,
+            max_depth: options
+                .max_depth
+                .map(|depth| depth.to_string())
+                .unwrap_or_default(),
+            threads: options
+                .threads
+                .map(|threads| threads.to_string())
+                .unwrap_or_default(),
+            artwork_max_size: options
+                .artwork_max_size
+                .map(|size| size.to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// The options as currently typed in, invalid numbers count as no limit.
+    pub fn options(&self) -> ScanOptions {
+        ScanOptions {
+            exclude: self
+                .exclude
+                .text()
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(str::to_string)
+                .collect(),
+            min_duration: self.min_duration.trim().parse().unwrap_or(0),
+            follow_symlinks: self.follow_symlinks,
+            skip_hidden: self.skip_hidden,
+            max_depth: self.max_depth.trim().parse().ok(),
+            threads: self
+                .threads
+                .trim()
+                .parse()
+                .ok()
+                .filter(|threads| *threads > 0),
+            artwork_max_size: self
+                .artwork_max_size
+                .trim()
+                .parse()
+                .ok()
+                .filter(|size| *size > 0),
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::ExcludeEdited(action) => {
+                self.exclude.perform(action);
+            }
+            Message::MinDurationChanged(value) => {
+                self.min_duration = value;
+            }
+            Message::FollowSymlinks(value) => {
+                self.follow_symlinks = value;
+            }
+            Message::SkipHidden(value) => {
+                self.skip_hidden = value;
+            }
+            Message::MaxDepthChanged(value) => {
+                self.max_depth = value;
+            }
+            Message::ThreadsChanged(value) => {
+                self.threads = value;
+            }
+            Message::ArtworkMaxSizeChanged(value) => {
+                self.artwork_max_size = value;
+            }
+            Message::AddWatchFolder => {
+                return Task::perform(
+                    async {
+                        rfd::AsyncFileDialog::new()
+                            .pick_folder()
+                            .await
+                            .map(|folder| folder.path().to_path_buf())
+                    },
+                    Message::WatchFolderPicked,
+                );
+            }
+            Message::Save | Message::WatchFolderPicked(_) | Message::RemoveWatchFolder(_) => {}
+        };
+        Task::none()
+    }
+
+    pub fn view<'a>(&'a self, watch_folders: &'a [PathBuf]) -> Element<'a, Message> {
+        let default_threads = default_scan_threads().to_string();
+        let watched = Column::with_children(watch_folders.iter().map(|folder| {
+            row![
+                text(folder.to_string_lossy()).size(14).width(Length::Fill),
+                Button::new(text("Remove").size(12))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::RemoveWatchFolder(folder.clone())),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center)
+            .into()
+        }))
+        .spacing(6);
+        column![
+            text("Scanner Settings").font(SANS_BOLD).size(20),
+            text("Exclude patterns, one per line").size(14),
+            text_editor(&self.exclude)
+                .placeholder("**/samples/**")
+                .height(Length::Fixed(120.0))
+                .on_action(Message::ExcludeEdited),
+            text(
+                "Patterns with a `/` match the path inside the scanned folder, \
+                 the others match file names only, e.g. `*.stem.mp4`."
+            )
+            .size(12),
+            number_row(
+                "Minimum duration (seconds)",
+                "0",
+                &self.min_duration,
+                Message::MinDurationChanged
+            ),
+            number_row(
+                "Maximum folder depth",
+                "No limit",
+                &self.max_depth,
+                Message::MaxDepthChanged
+            ),
+            number_row(
+                "Files read in parallel",
+                &default_threads,
+                &self.threads,
+                Message::ThreadsChanged
+            ),
+            text(
+                "Scanning runs at background priority, \
+                 fewer parallel reads keep the disk free for playback."
+            )
+            .size(12),
+            number_row(
+                "Maximum artwork size (pixels)",
+                "Original",
+                &self.artwork_max_size,
+                Message::ArtworkMaxSizeChanged
+            ),
+            text("Larger artwork is downscaled and stored as JPEG, leave empty to keep it as is.")
+                .size(12),
+            toggler(self.skip_hidden)
+                .label("Skip hidden files and folders")
+                .on_toggle(Message::SkipHidden),
+            toggler(self.follow_symlinks)
+                .label("Follow symbolic links")
+                .on_toggle(Message::FollowSymlinks),
+            Button::new(text("Save")).on_press(Message::Save),
+            text("Watched folders").font(SANS_BOLD).size(14),
+            watched,
+            Button::new(text("Add folder…"))
+                .style(iced::widget::button::secondary)
+                .on_press(Message::AddWatchFolder),
+            text(
+                "New files in these folders are imported once they finish copying, files \
+                 deleted from them move to Recently Deleted."
+            )
+            .size(12),
+        ]
+        .spacing(12)
+        .max_width(480)
+        .into()
+    }
+}
+
+fn number_row<'a>(
+    label: &'a str,
+    placeholder: &str,
+    value: &str,
+    on_input: fn(String) -> Message,
+) -> Element<'a, Message> {
+    row![
+        text(label).size(14).width(Length::Fill),
+        text_input(placeholder, value)
+            .on_input(on_input)
+            .width(Length::Fixed(80.0)),
+    ]
+    .spacing(10)
+    .align_y(Vertical::Center)
+    .into()
+}
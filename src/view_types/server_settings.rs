@@ -0,0 +1,416 @@
+use std::fmt;
+
+use crate::{
+    app_state::state_impl::State,
+    audio_scanner::ScannedFile,
+    fonts::SANS_BOLD,
+    jellyfin,
+    remote::{self, Mirror},
+    subsonic,
+    webdav::{self, Entry, Server},
+};
+use iced::{
+    Element, Length, Task,
+    alignment::Vertical,
+    widget::{
+        Button, Column, Scrollable, column, horizontal_rule, pick_list, row, text, text_input,
+    },
+};
+
+/// What the server typed in speaks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ServerKind {
+    #[default]
+    WebDav,
+    Subsonic,
+    Jellyfin,
+}
+
+impl fmt::Display for ServerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServerKind::WebDav => f.write_str("WebDAV share"),
+            ServerKind::Subsonic => f.write_str("Subsonic / Navidrome"),
+            ServerKind::Jellyfin => f.write_str("Jellyfin"),
+        }
+    }
+}
+
+const SERVER_KINDS: [ServerKind; 3] = [
+    ServerKind::WebDav,
+    ServerKind::Subsonic,
+    ServerKind::Jellyfin,
+];
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    KindSelected(ServerKind),
+    UrlInput(String),
+    UsernameInput(String),
+    PasswordInput(String),
+    // saves the share typed in, by the app
+    Add(Server),
+    Remove(String),
+    // lists a folder of a share
+    Browse(Server, String),
+    Listed(String, Result<Vec<Entry>, String>),
+    CloseBrowser,
+    // indexes the audio files under a folder, the app scans and imports them
+    Index(Server, String),
+    Scanned(String, Result<Vec<ScannedFile>, String>),
+    // the folder and how many tracks were added from it
+    Indexed(String, Result<usize, String>),
+    // a Subsonic server typed in is checked and a Jellyfin one signed in to, with the address,
+    // user name and password, before the app saves it
+    CheckSubsonic(subsonic::Server),
+    SignInJellyfin(String, String, String),
+    Connected(String, Result<remote::Server, String>),
+    RemoveServer(String),
+    // mirrors the server into the library, the app applies what was read
+    Sync(remote::Server),
+    Mirrored(remote::Server, Result<Mirror, String>),
+    // the server and how many songs were added from it
+    Synced(String, Result<usize, String>),
+}
+
+#[derive(Default)]
+pub struct ServerSettings {
+    kind: ServerKind,
+    url: String,
+    username: String,
+    password: String,
+    // the share and the folder browsed
+    browsing: Option<(Server, String)>,
+    // None while the folder is listed
+    entries: Option<Vec<Entry>>,
+    // the folder being indexed
+    indexing: Option<String>,
+    // the address of the Subsonic or Jellyfin server being connected to or mirrored
+    syncing: Option<String>,
+    status: Option<String>,
+}
+
+impl ServerSettings {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::KindSelected(kind) => self.kind = kind,
+            Message::UrlInput(url) => self.url = url,
+            Message::UsernameInput(username) => self.username = username,
+            Message::PasswordInput(password) => self.password = password,
+            Message::Add(_) => {
+                self.url.clear();
+                self.username.clear();
+                self.password.clear();
+            }
+            Message::CheckSubsonic(server) => {
+                self.syncing = Some(server.url.clone());
+                self.status = None;
+                let url = server.url.clone();
+                return Task::perform(subsonic::ping(server.clone()), move |result| {
+                    let result = result.map(|()| server.clone().into());
+                    Message::Connected(url.clone(), result.map_err(|e| e.to_string()))
+                });
+            }
+            Message::SignInJellyfin(url, username, password) => {
+                self.syncing = Some(url.clone());
+                self.status = None;
+                let signing_in = jellyfin::authenticate(url.clone(), username, password);
+                return Task::perform(signing_in, move |result| {
+                    let result = result.map(remote::Server::from);
+                    Message::Connected(url.clone(), result.map_err(|e| e.to_string()))
+                });
+            }
+            Message::Connected(url, result) => match result {
+                Ok(server) => {
+                    self.url.clear();
+                    self.username.clear();
+                    self.password.clear();
+                    return Task::done(Message::Sync(server));
+                }
+                Err(error) => {
+                    self.syncing = None;
+                    self.status = Some(format!("Connecting to {url} failed: {error}"));
+                }
+            },
+            Message::Sync(server) => {
+                self.syncing = Some(server.url().to_string());
+                self.status = None;
+                return Task::perform(server.clone().mirror(), move |result| {
+                    Message::Mirrored(server.clone(), result)
+                });
+            }
+            Message::Synced(name, result) => {
+                self.syncing = None;
+                self.status = Some(match result {
+                    Ok(count) => format!("Synced {name}, {count} new songs"),
+                    Err(error) => format!("Syncing {name} failed: {error}"),
+                });
+            }
+            Message::Remove(url) => {
+                if self
+                    .browsing
+                    .as_ref()
+                    .is_some_and(|(server, _)| server.url == url)
+                {
+                    self.browsing = None;
+                }
+            }
+            Message::Browse(server, folder) => {
+                self.browsing = Some((server.clone(), folder.clone()));
+                self.entries = None;
+                self.status = None;
+                return Task::perform(webdav::list(server, folder.clone()), move |result| {
+                    Message::Listed(folder.clone(), result.map_err(|e| e.to_string()))
+                });
+            }
+            Message::Listed(folder, result) => {
+                // an answer for a folder left meanwhile is dropped
+                if self
+                    .browsing
+                    .as_ref()
+                    .is_some_and(|(_, browsed)| *browsed == folder)
+                {
+                    match result {
+                        Ok(entries) => self.entries = Some(entries),
+                        Err(error) => {
+                            self.entries = Some(Vec::new());
+                            self.status = Some(format!("Listing {folder} failed: {error}"));
+                        }
+                    }
+                }
+            }
+            Message::CloseBrowser => self.browsing = None,
+            Message::Index(_, folder) => {
+                self.indexing = Some(folder);
+                self.status = None;
+            }
+            Message::Indexed(folder, result) => {
+                self.indexing = None;
+                self.status = Some(match result {
+                    Ok(count) => format!("Added {count} tracks from {folder}"),
+                    Err(error) => format!("Indexing {folder} failed: {error}"),
+                });
+            }
+            Message::Scanned(..) | Message::Mirrored(..) | Message::RemoveServer(_) => {}
+        }
+        Task::none()
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let mut servers: Vec<Element<'_, Message>> = state
+            .webdav_servers()
+            .iter()
+            .map(|server| {
+                row![
+                    server_label(&server.url, "WebDAV", &server.username),
+                    Button::new(text("Browse").size(12))
+                        .style(iced::widget::button::secondary)
+                        .on_press(Message::Browse(server.clone(), server.url.clone())),
+                    Button::new(text("Remove").size(12))
+                        .style(iced::widget::button::secondary)
+                        .on_press(Message::Remove(server.url.clone())),
+                ]
+                .spacing(8)
+                .align_y(Vertical::Center)
+                .into()
+            })
+            .collect();
+        servers.extend(state.remote_servers().into_iter().map(|server| {
+            let syncing = self.syncing.is_some();
+            let kind = match server {
+                remote::Server::Subsonic(_) => "Subsonic",
+                remote::Server::Jellyfin(_) => "Jellyfin",
+            };
+            row![
+                server_label(server.url(), kind, server.username()),
+                Button::new(text(if syncing { "Syncing…" } else { "Sync" }).size(12))
+                    .style(iced::widget::button::secondary)
+                    .on_press_maybe((!syncing).then(|| Message::Sync(server.clone()))),
+                Button::new(text("Remove").size(12))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::RemoveServer(server.url().to_string())),
+            ]
+            .spacing(8)
+            .align_y(Vertical::Center)
+            .into()
+        }));
+        let servers: Element<'_, Message> = if servers.is_empty() {
+            text("No server added yet").size(12).into()
+        } else {
+            Column::from_vec(servers).spacing(8).into()
+        };
+
+        let valid = reqwest::Url::parse(self.url.trim())
+            .is_ok_and(|url| matches!(url.scheme(), "http" | "https"));
+        let mut url = self.url.trim().to_string();
+        if !url.ends_with('/') {
+            url.push('/');
+        }
+        let username = self.username.trim().to_string();
+        let add = match self.kind {
+            ServerKind::WebDav => valid.then(|| {
+                Message::Add(Server {
+                    url,
+                    username,
+                    password: self.password.clone(),
+                })
+            }),
+            // a Subsonic or Jellyfin server always wants an account
+            ServerKind::Subsonic => {
+                (valid && !username.is_empty() && self.syncing.is_none()).then(|| {
+                    Message::CheckSubsonic(subsonic::Server {
+                        url,
+                        username,
+                        password: self.password.clone(),
+                    })
+                })
+            }
+            ServerKind::Jellyfin => (valid && !username.is_empty() && self.syncing.is_none())
+                .then(|| Message::SignInJellyfin(url, username, self.password.clone())),
+        };
+        let add = Button::new(text("Add server")).on_press_maybe(add);
+        let placeholder = match self.kind {
+            ServerKind::WebDav => "https://nas.local/music/",
+            ServerKind::Subsonic => "https://music.example.com/",
+            ServerKind::Jellyfin => "https://jellyfin.example.com/",
+        };
+
+        let mut content = column![
+            text("Servers").font(SANS_BOLD).size(20),
+            text(
+                "Music on a WebDAV share, e.g. of a NAS, is indexed into the library and \
+                 streamed from it. Tracks played are kept in a cache to play again offline."
+            )
+            .size(12),
+            text(
+                "The library, playlists and starred songs of a Subsonic server, e.g. Navidrome \
+                 or Airsonic, are mirrored and listed under it in the sidebar. Songs liked here \
+                 are starred on the server."
+            )
+            .size(12),
+            text(
+                "Jellyfin is signed in to once, its music is mirrored the same way. Songs in \
+                 formats which can not be played here are transcoded to MP3 by the server."
+            )
+            .size(12),
+            servers,
+            horizontal_rule(1),
+            row![
+                text("Add a server")
+                    .font(SANS_BOLD)
+                    .size(14)
+                    .width(Length::Fill),
+                pick_list(SERVER_KINDS, Some(self.kind), Message::KindSelected).text_size(13),
+            ]
+            .align_y(Vertical::Center),
+            text_input(placeholder, &self.url).on_input(Message::UrlInput),
+            row![
+                text_input("User name", &self.username).on_input(Message::UsernameInput),
+                text_input("Password", &self.password)
+                    .secure(true)
+                    .on_input(Message::PasswordInput),
+            ]
+            .spacing(8),
+            add,
+        ]
+        .spacing(12)
+        .max_width(560);
+        if let Some((server, folder)) = &self.browsing {
+            content = content.push(horizontal_rule(1));
+            content = content.push(self.browser(server, folder));
+        }
+        if let Some(status) = &self.status {
+            content = content.push(text(status).size(12));
+        }
+        content.into()
+    }
+
+    // the folder browsed, its subfolders open on click and it can be indexed as a whole
+    fn browser<'a>(&'a self, server: &'a Server, folder: &'a str) -> Element<'a, Message> {
+        let up = parent(folder).filter(|parent| parent.starts_with(server.url.as_str()));
+        let indexing = self.indexing.is_some();
+        let header = row![
+            text(folder).size(13).width(Length::Fill),
+            Button::new(text("Up").size(12))
+                .style(iced::widget::button::secondary)
+                .on_press_maybe(up.map(|up| Message::Browse(server.clone(), up))),
+            Button::new(
+                text(if indexing {
+                    "Indexing…"
+                } else {
+                    "Index folder"
+                })
+                .size(12)
+            )
+            .on_press_maybe(
+                (!indexing).then(|| Message::Index(server.clone(), folder.to_string()))
+            ),
+            Button::new(text("Close").size(12))
+                .style(iced::widget::button::secondary)
+                .on_press(Message::CloseBrowser),
+        ]
+        .spacing(8)
+        .align_y(Vertical::Center);
+        let entries: Element<'_, Message> = match &self.entries {
+            None => text("Listing…").size(12).into(),
+            Some(entries) if entries.is_empty() => text("Nothing in this folder").size(12).into(),
+            Some(entries) => {
+                let rows = entries.iter().map(|entry| {
+                    if entry.is_dir {
+                        Button::new(text(format!("{}/", entry.name)).size(13))
+                            .style(iced::widget::button::text)
+                            .on_press(Message::Browse(server.clone(), entry.url.clone()))
+                            .into()
+                    } else {
+                        text(&entry.name).size(13).into()
+                    }
+                });
+                Scrollable::new(Column::with_children(rows).spacing(2))
+                    .height(Length::Fixed(240.0))
+                    .into()
+            }
+        };
+        column![header, entries].spacing(8).into()
+    }
+}
+
+fn server_label<'a>(url: &str, kind: &'a str, username: &str) -> Element<'a, Message> {
+    let account = if username.is_empty() {
+        "No account".to_string()
+    } else {
+        username.to_string()
+    };
+    column![
+        text(url.to_string()).font(SANS_BOLD).size(14),
+        text(format!("{kind} · {account}")).size(12)
+    ]
+    .spacing(2)
+    .width(Length::Fill)
+    .into()
+}
+
+// the folder `folder` is in, None at the root of the server
+fn parent(folder: &str) -> Option<String> {
+    let trimmed = folder.trim_end_matches('/');
+    let (parent, _) = trimmed.rsplit_once('/')?;
+    // `https://host` has no folder above it
+    (!parent.ends_with('/')).then(|| format!("{parent}/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parent() {
+        assert_eq!(
+            parent("https://nas.local/music/Albums/").as_deref(),
+            Some("https://nas.local/music/")
+        );
+        assert_eq!(
+            parent("https://nas.local/music/").as_deref(),
+            Some("https://nas.local/")
+        );
+        assert_eq!(parent("https://nas.local/"), None);
+    }
+}
@@ -0,0 +1,37 @@
+use crate::app_state::Section;
+use iced::{
+    Element,
+    widget::{Button, row, text},
+};
+
+const TABS: [(&str, Section); 9] = [
+    ("Scanner", Section::ScannerSettings),
+    ("Playback", Section::PlaybackSettings),
+    ("Fields", Section::FieldSettings),
+    ("Playlists", Section::PlaylistSettings),
+    ("Privacy", Section::PrivacySettings),
+    ("Sync", Section::SyncSettings),
+    ("Servers", Section::ServerSettings),
+    ("Advanced", Section::AdvancedSettings),
+    ("Diagnostics", Section::Diagnostics),
+];
+
+/// The tab bar shown above every settings page, `on_select` receives the section of the tab.
+pub fn view<'a, Message: Clone + 'a>(
+    section: &Section,
+    on_select: fn(Section) -> Message,
+) -> Element<'a, Message> {
+    row(TABS.into_iter().map(|(label, tab)| {
+        let style = if *section == tab {
+            iced::widget::button::primary
+        } else {
+            iced::widget::button::text
+        };
+        Button::new(text(label))
+            .style(style)
+            .on_press(on_select(tab))
+            .into()
+    }))
+    .spacing(4)
+    .into()
+}
@@ -0,0 +1,187 @@
+use crate::{
+    app_state::state_impl::{SnapshotComparison, State},
+    fonts::SANS_BOLD,
+    snapshots::describe_changes,
+    storage::{Snapshot, SnapshotEntry},
+    util::timestamp_to_str,
+};
+use iced::{
+    Element, Length, Padding, Task,
+    alignment::Vertical,
+    widget::{Button, Column, Scrollable, column, horizontal_rule, row, text, text_input},
+};
+
+// entries listed per group of a comparison, a full rescan can change thousands
+const MAX_LISTED: usize = 200;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    LabelChanged(String),
+    // snapshots the library with the typed in label
+    Take,
+    Compare(i64),
+    Delete(i64),
+    CloseComparison,
+}
+
+#[derive(Default)]
+pub struct SnapshotsView {
+    label: String,
+}
+
+impl SnapshotsView {
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::LabelChanged(value) => self.label = value,
+            Message::Take => self.label.clear(),
+            _ => {}
+        }
+        Task::none()
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let mut content = column![
+            text("Library Snapshots").font(SANS_BOLD).size(20),
+            row![
+                text_input("before syncing", &self.label)
+                    .on_input(Message::LabelChanged)
+                    .on_submit(Message::Take),
+                Button::new(text("Take snapshot"))
+                    .style(iced::widget::button::primary)
+                    .on_press(Message::Take),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+            text("A snapshot records the path, metadata and tags of every track.").size(12),
+            horizontal_rule(1),
+        ]
+        .spacing(12)
+        .padding(Padding::default().right(16));
+
+        if state.snapshots().is_empty() {
+            content = content.push(text("No snapshots yet"));
+        }
+        for snapshot in state.snapshots() {
+            content = content.push(snapshot_row(snapshot));
+        }
+        if let Some(comparison) = state.comparison() {
+            content = content
+                .push(horizontal_rule(1))
+                .push(comparison_view(comparison));
+        }
+
+        Scrollable::new(content.max_width(720))
+            .height(Length::Fill)
+            .into()
+    }
+}
+
+fn snapshot_name(snapshot: &Snapshot) -> String {
+    if snapshot.label.is_empty() {
+        timestamp_to_str(snapshot.taken_at)
+    } else {
+        snapshot.label.clone()
+    }
+}
+
+fn snapshot_row(snapshot: &Snapshot) -> Element<'_, Message> {
+    let info = column![
+        text(snapshot_name(snapshot)).font(SANS_BOLD).size(14),
+        text(format!(
+            "{} · {} tracks, {} tags, {} playlists",
+            timestamp_to_str(snapshot.taken_at),
+            snapshot.playable_count,
+            snapshot.tag_count,
+            snapshot.playlist_count
+        ))
+        .size(12),
+    ]
+    .spacing(4)
+    .width(Length::Fill);
+
+    row![
+        info,
+        Button::new(text("Compare").size(12))
+            .style(iced::widget::button::secondary)
+            .on_press(Message::Compare(snapshot.id)),
+        Button::new(text("Delete").size(12))
+            .style(iced::widget::button::danger)
+            .on_press(Message::Delete(snapshot.id)),
+    ]
+    .spacing(12)
+    .align_y(Vertical::Center)
+    .into()
+}
+
+fn comparison_view(comparison: &SnapshotComparison) -> Element<'_, Message> {
+    let snapshot = &comparison.snapshot;
+    let (playables, tags, playlists) = comparison.counts;
+    let diff = &comparison.diff;
+
+    let mut content = column![
+        row![
+            text(format!("Changes since {}", snapshot_name(snapshot)))
+                .font(SANS_BOLD)
+                .size(16)
+                .width(Length::Fill),
+            Button::new(text("Close").size(12))
+                .style(iced::widget::button::secondary)
+                .on_press(Message::CloseComparison),
+        ]
+        .align_y(Vertical::Center),
+        text(format!(
+            "Tracks {} → {playables} · Tags {} → {tags} · Playlists {} → {playlists}",
+            snapshot.playable_count, snapshot.tag_count, snapshot.playlist_count
+        ))
+        .size(12),
+    ]
+    .spacing(12);
+
+    if diff.is_empty() {
+        return content.push(text("Nothing changed").size(14)).into();
+    }
+    let added = diff.added.iter().map(|entry| entry_row(entry, None));
+    let removed = diff.removed.iter().map(|entry| entry_row(entry, None));
+    let changed = diff
+        .changed
+        .iter()
+        .map(|(before, after)| entry_row(after, Some(describe_changes(before, after).join("; "))));
+    content = content
+        .push(group("Added", diff.added.len(), added))
+        .push(group("Removed", diff.removed.len(), removed))
+        .push(group("Changed", diff.changed.len(), changed));
+    content.into()
+}
+
+fn group<'a>(
+    title: &str,
+    count: usize,
+    rows: impl Iterator<Item = Element<'a, Message>>,
+) -> Element<'a, Message> {
+    let mut list = Column::new()
+        .push(text(format!("{title} ({count})")).font(SANS_BOLD).size(14))
+        .spacing(6);
+    for row in rows.take(MAX_LISTED) {
+        list = list.push(row);
+    }
+    if count > MAX_LISTED {
+        list = list.push(text(format!("and {} more", count - MAX_LISTED)).size(12));
+    }
+    list.into()
+}
+
+fn entry_row(entry: &SnapshotEntry, changes: Option<String>) -> Element<'_, Message> {
+    let title = match &entry.artist {
+        Some(artist) => format!("{artist} - {}", entry.title),
+        None => entry.title.clone(),
+    };
+    let mut details = column![text(title).size(13), text(&entry.path).size(11)].spacing(2);
+    if let Some(changes) = changes {
+        details = details.push(text(changes).size(11));
+    }
+    details.into()
+}
@@ -0,0 +1,120 @@
+use crate::{
+    app_state::{AudioPlayable, state_impl::SongInfo},
+    fonts::SANS_BOLD,
+    util::{duration_to_str, playable_artwork, timestamp_to_str},
+};
+use iced::{
+    Element, Length, Padding,
+    alignment::Vertical,
+    widget::{Button, Column, Row, column, container, horizontal_rule, row, text},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Close,
+    // filters the library by the album or artist name
+    ShowAlbum(String),
+    ShowArtist(String),
+    ShowPlaylist(i64),
+    ShowTag(i64),
+}
+
+/// Full details of a playable, opened from the title in the player bar.
+pub fn view(info: &SongInfo) -> Element<'_, Message> {
+    let playable = &info.playable;
+    let mut details = Column::new()
+        .push(text(playable.get_title()).font(SANS_BOLD).size(18))
+        .spacing(6)
+        .width(Length::Fill);
+    if !playable.get_artist().is_empty() {
+        details = details.push(link_row(
+            "Artist",
+            playable.get_artist(),
+            Message::ShowArtist(playable.get_artist().to_string()),
+        ));
+    }
+    if !playable.get_album().is_empty() {
+        details = details.push(link_row(
+            "Album",
+            playable.get_album(),
+            Message::ShowAlbum(playable.get_album().to_string()),
+        ));
+    }
+    if !playable.get_genre().is_empty() {
+        details = details.push(field("Genre", playable.get_genre().to_string()));
+    }
+    details = details
+        .push(field("Duration", duration_to_str(playable.get_duration())))
+        .push(field("Added", timestamp_to_str(*playable.get_date_added())))
+        .push(field("Location", playable.get_path().to_string()));
+
+    let tags = chips(
+        info.tags
+            .iter()
+            .map(|tag| (tag.name.as_str(), Message::ShowTag(tag.id))),
+        "No tags",
+    );
+    let playlists = chips(
+        info.playlists
+            .iter()
+            .map(|playlist| (playlist.name.as_str(), Message::ShowPlaylist(playlist.id))),
+        "In no playlist",
+    );
+
+    let content = column![
+        row![
+            playable_artwork(playable, 120, 120),
+            details,
+            Button::new(text("Close").size(12))
+                .style(iced::widget::button::secondary)
+                .on_press(Message::Close),
+        ]
+        .spacing(16),
+        horizontal_rule(1),
+        row![text("Tags").size(12).width(80), tags].align_y(Vertical::Center),
+        row![text("Playlists").size(12).width(80), playlists].align_y(Vertical::Center),
+    ]
+    .spacing(10);
+
+    container(content)
+        .padding(Padding::from([12, 16]))
+        .width(Length::Fill)
+        .style(container::rounded_box)
+        .into()
+}
+
+fn field<'a>(label: &'a str, value: String) -> Element<'a, Message> {
+    row![text(label).size(12).width(80), text(value).size(12)]
+        .align_y(Vertical::Center)
+        .into()
+}
+
+fn link_row<'a>(label: &'a str, value: &'a str, message: Message) -> Element<'a, Message> {
+    row![
+        text(label).size(12).width(80),
+        Button::new(text(value).size(12))
+            .padding(0)
+            .style(iced::widget::button::text)
+            .on_press(message),
+    ]
+    .align_y(Vertical::Center)
+    .into()
+}
+
+fn chips<'a>(
+    items: impl Iterator<Item = (&'a str, Message)>,
+    empty: &'a str,
+) -> Element<'a, Message> {
+    let buttons: Vec<Element<'a, Message>> = items
+        .map(|(name, message)| {
+            Button::new(text(name).size(12))
+                .style(iced::widget::button::secondary)
+                .on_press(message)
+                .into()
+        })
+        .collect();
+    if buttons.is_empty() {
+        return text(empty).size(12).into();
+    }
+    Row::from_vec(buttons).spacing(4).wrap().into()
+}
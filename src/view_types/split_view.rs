@@ -0,0 +1,156 @@
+use std::fmt;
+
+use crate::{
+    app_state::{
+        AudioPlayable, PlayableId, Section,
+        state_impl::{Result, State},
+    },
+    fonts::ICON,
+    icons::ICON_X,
+    storage::{Playable, PlaylistKind},
+    widgets::compact_row,
+};
+use iced::{
+    Element, Length, Padding,
+    alignment::Vertical,
+    widget::{Column, Scrollable, column, mouse_area, pick_list, row, text},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Pick(SectionChoice),
+    Selected(usize),
+    // a row is dragged, to a playlist or tag in the other pane or the sidebar
+    Drag(usize),
+    Close,
+}
+
+/// A section the split pane can show, named as in the sidebar.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionChoice {
+    section: Section,
+    title: String,
+}
+
+impl fmt::Display for SectionChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.title)
+    }
+}
+
+/// A second section beside the central one, to drag tracks from one to the other.
+pub struct SplitView {
+    section: Section,
+    playables: Vec<Playable>,
+    selected: Option<usize>,
+}
+
+impl SplitView {
+    pub fn new(section: Section) -> Self {
+        Self {
+            section,
+            playables: Vec::new(),
+            selected: None,
+        }
+    }
+
+    pub fn section(&self) -> &Section {
+        &self.section
+    }
+
+    /// Reads the tracks of the section again, after tracks were dropped or removed.
+    pub fn reload(&mut self, state: &State) -> Result<()> {
+        self.playables = state.section_playables(&self.section)?;
+        if self
+            .selected
+            .is_some_and(|index| index >= self.playables.len())
+        {
+            self.selected = None;
+        }
+        Ok(())
+    }
+
+    /// The playable of a row dragged away.
+    pub fn dragged(&self, index: usize) -> Option<PlayableId> {
+        self.playables.get(index).map(|p| p.get_id())
+    }
+
+    pub fn update(&mut self, message: Message) {
+        match message {
+            Message::Pick(choice) => {
+                self.section = choice.section;
+                self.selected = None;
+            }
+            Message::Selected(index) | Message::Drag(index) => self.selected = Some(index),
+            Message::Close => {}
+        }
+    }
+
+    // the library, the favorites and every playlist and tag, as listed in the sidebar
+    fn choices(state: &State) -> Vec<SectionChoice> {
+        let mut sections = vec![Section::Library, Section::Favorites];
+        sections.extend(
+            state
+                .playlists()
+                .iter()
+                .filter(|node| node.value.kind != PlaylistKind::Folder)
+                .map(|node| Section::Playlist(node.value.id)),
+        );
+        sections.extend(state.tags().iter().map(|tag| Section::Tag(tag.id)));
+        sections
+            .into_iter()
+            .map(|section| SectionChoice {
+                title: state.section_title(&section),
+                section,
+            })
+            .collect()
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let choices = Self::choices(state);
+        let picked = choices
+            .iter()
+            .find(|choice| choice.section == self.section)
+            .cloned();
+        let header = row![
+            pick_list(choices, picked, Message::Pick)
+                .text_size(14)
+                .width(Length::Fill),
+            text(format!("{} entries", self.playables.len())).size(13),
+            mouse_area(text(ICON_X).font(ICON).size(16))
+                .interaction(iced::mouse::Interaction::Pointer)
+                .on_press(Message::Close),
+        ]
+        .spacing(12)
+        .align_y(Vertical::Center);
+
+        let row_sizes = (
+            Length::Fixed(40.),
+            Length::FillPortion(5),
+            Length::FillPortion(4),
+            Length::Shrink,
+            Length::FillPortion(2),
+        );
+        let mut rows = Column::new();
+        for (i, playable) in self.playables.iter().enumerate() {
+            rows = rows.push(
+                compact_row::compact_row(
+                    playable,
+                    i,
+                    self.selected == Some(i),
+                    &row_sizes,
+                    None,
+                    None,
+                    Vec::new(),
+                )
+                .on_select(Message::Selected(i))
+                .on_drag(Message::Drag(i)),
+            );
+        }
+
+        column![header, Scrollable::new(rows).height(Length::Fill)]
+            .spacing(10)
+            .padding(Padding::default().left(10))
+            .into()
+    }
+}
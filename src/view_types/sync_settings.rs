@@ -0,0 +1,263 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::{
+    app_state::state_impl::{State, SyncSummary},
+    fonts::SANS_BOLD,
+    shared_playlist,
+    sync::{Peer, SyncDocument},
+};
+use iced::{
+    Element, Length, Task,
+    alignment::Vertical,
+    widget::{Button, Column, column, horizontal_rule, row, text, text_input, toggler},
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Sharing(bool),
+    Search,
+    PeersFound(Result<Vec<Peer>, String>),
+    NewPairingCode,
+    // the name of a peer and the pairing code it shows
+    CodeChanged(String, String),
+    Pull(Peer, String),
+    // the document of the named peer and the code it took, merged by the app before the view
+    // sees `Merged`
+    Pulled(String, String, Result<SyncDocument, String>),
+    Merged(String, Result<SyncSummary, String>),
+    ExportSidecar,
+    ImportSidecar,
+    // picked in the file dialogs, None when cancelled
+    ExportTo(Option<PathBuf>),
+    ImportFrom(Option<PathBuf>),
+    SidecarDone(Result<String, String>),
+    ImportPlaylist,
+    ImportPlaylistFrom(Option<PathBuf>),
+    PlaylistImported(Result<String, String>),
+}
+
+#[derive(Default)]
+pub struct SyncSettings {
+    searching: bool,
+    peers: Vec<Peer>,
+    // the codes typed for the peers by name, those kept from earlier syncs are shown otherwise
+    codes: HashMap<String, String>,
+    // name of the peer being pulled from
+    pulling: Option<String>,
+    // outcome of the last pull
+    status: Option<String>,
+}
+
+impl SyncSettings {
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Search => {
+                self.searching = true;
+                self.status = None;
+            }
+            Message::PeersFound(result) => {
+                self.searching = false;
+                match result {
+                    Ok(peers) => self.peers = peers,
+                    Err(error) => self.status = Some(format!("Search failed: {error}")),
+                }
+            }
+            Message::CodeChanged(name, code) => {
+                self.codes.insert(name, code);
+            }
+            Message::Pull(peer, _) => {
+                self.pulling = Some(peer.name);
+                self.status = None;
+            }
+            Message::Merged(name, result) => {
+                self.pulling = None;
+                self.status = Some(match result {
+                    Ok(summary) => describe(&name, &summary),
+                    Err(error) => format!("Syncing with {name} failed: {error}"),
+                });
+            }
+            Message::ExportSidecar => {
+                return Task::perform(
+                    async {
+                        rfd::AsyncFileDialog::new()
+                            .set_file_name("phoniq-user-data.json")
+                            .save_file()
+                            .await
+                            .map(|file| file.path().to_path_buf())
+                    },
+                    Message::ExportTo,
+                );
+            }
+            Message::ImportSidecar => {
+                return Task::perform(
+                    async {
+                        rfd::AsyncFileDialog::new()
+                            .add_filter("Phoniq user data", &["json"])
+                            .pick_file()
+                            .await
+                            .map(|file| file.path().to_path_buf())
+                    },
+                    Message::ImportFrom,
+                );
+            }
+            Message::SidecarDone(result) => {
+                self.status = Some(result.unwrap_or_else(|error| error));
+            }
+            Message::ImportPlaylist => {
+                return Task::perform(
+                    async {
+                        rfd::AsyncFileDialog::new()
+                            .add_filter("Phoniq playlist", &[shared_playlist::EXTENSION])
+                            .pick_file()
+                            .await
+                            .map(|file| file.path().to_path_buf())
+                    },
+                    Message::ImportPlaylistFrom,
+                );
+            }
+            Message::PlaylistImported(result) => {
+                self.status = Some(result.unwrap_or_else(|error| error));
+            }
+            Message::Sharing(_)
+            | Message::NewPairingCode
+            | Message::Pulled(..)
+            | Message::ExportTo(_)
+            | Message::ImportFrom(_)
+            | Message::ImportPlaylistFrom(_) => {}
+        }
+        Task::none()
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let search = Button::new(text(if self.searching {
+            "Searching…"
+        } else {
+            "Search the network"
+        }))
+        .style(iced::widget::button::secondary)
+        .on_press_maybe((!self.searching).then_some(Message::Search));
+
+        let peers: Vec<Element<'_, Message>> = self
+            .peers
+            .iter()
+            .filter(|peer| !state.is_own_peer(peer))
+            .map(|peer| {
+                let pulling = self.pulling.as_ref() == Some(&peer.name);
+                let code = self
+                    .codes
+                    .get(&peer.name)
+                    .map_or(state.peer_code(&peer.name), String::as_str);
+                let name = peer.name.clone();
+                row![
+                    column![
+                        text(&peer.name).font(SANS_BOLD).size(14),
+                        text(&peer.url).size(12)
+                    ]
+                    .spacing(2)
+                    .width(Length::Fill),
+                    text_input("Pairing code", code)
+                        .on_input(move |code| Message::CodeChanged(name.clone(), code))
+                        .size(12)
+                        .width(Length::Fixed(110.0)),
+                    Button::new(text(if pulling { "Syncing…" } else { "Sync from" }).size(12))
+                        .on_press_maybe(
+                            (self.pulling.is_none() && !code.trim().is_empty())
+                                .then(|| Message::Pull(peer.clone(), code.trim().to_string()))
+                        ),
+                ]
+                .spacing(12)
+                .align_y(Vertical::Center)
+                .into()
+            })
+            .collect();
+        let peers: Element<'_, Message> = if peers.is_empty() && !self.searching {
+            text("No other Phoniq found yet").size(12).into()
+        } else {
+            Column::from_vec(peers).spacing(8).into()
+        };
+
+        let pairing: Element<'_, Message> = match state.pairing_code() {
+            Some(code) => column![
+                row![
+                    text(format!("Pairing code: {code}"))
+                        .font(SANS_BOLD)
+                        .size(14),
+                    Button::new(text("New code").size(12))
+                        .style(iced::widget::button::secondary)
+                        .on_press(Message::NewPairingCode),
+                ]
+                .spacing(12)
+                .align_y(Vertical::Center),
+                text(
+                    "Machines syncing from this one give the code once, \
+                     a new code shuts out the ones which have it."
+                )
+                .size(12),
+            ]
+            .spacing(12)
+            .into(),
+            None => text("Machines syncing from this one have to give its pairing code.")
+                .size(12)
+                .into(),
+        };
+
+        let mut content = column![
+            text("Sync").font(SANS_BOLD).size(20),
+            toggler(state.sharing())
+                .label("Share my library on this network")
+                .on_toggle(Message::Sharing),
+            text(
+                "Other machines can then copy your likes, ratings, tags and playlists. \
+                 Nothing is ever removed, tracks are matched by path or by their content."
+            )
+            .size(12),
+            pairing,
+            horizontal_rule(1),
+            search,
+            peers,
+            horizontal_rule(1),
+            text("Portable user data").font(SANS_BOLD).size(14),
+            row![
+                Button::new(text("Export…"))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::ExportSidecar),
+                Button::new(text("Import…"))
+                    .style(iced::widget::button::secondary)
+                    .on_press(Message::ImportSidecar),
+            ]
+            .spacing(10),
+            text(
+                "Saves likes, ratings and tags to a file keyed by the content of each track, \
+                 importing it attaches them again after moving or rebuilding the library."
+            )
+            .size(12),
+            horizontal_rule(1),
+            text("Shared playlists").font(SANS_BOLD).size(14),
+            Button::new(text("Import playlist…"))
+                .style(iced::widget::button::secondary)
+                .on_press(Message::ImportPlaylist),
+            text(
+                "Opens a playlist a friend shared from the playlist menu in the sidebar, \
+                 its tracks are looked up in your library by their content, artist and title."
+            )
+            .size(12),
+        ]
+        .spacing(12)
+        .max_width(480);
+        if let Some(status) = &self.status {
+            content = content.push(text(status).size(12));
+        }
+        content.into()
+    }
+}
+
+fn describe(name: &str, summary: &SyncSummary) -> String {
+    let mut text = format!(
+        "Synced from {name}: {} liked, {} rated, {} tagged, {} added to playlists",
+        summary.liked, summary.rated, summary.tagged, summary.added_to_playlists
+    );
+    if summary.unmatched > 0 {
+        text.push_str(&format!(", {} tracks not found here", summary.unmatched));
+    }
+    text
+}
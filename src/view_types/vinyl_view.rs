@@ -0,0 +1,427 @@
+use std::path::PathBuf;
+
+use crate::{
+    discogs::{self, Release},
+    fonts::SANS_BOLD,
+    vinyl::{self, Recording, SplitTrack},
+    widgets::waveform::Waveform,
+};
+use iced::{
+    Element, Length, Padding, Task,
+    alignment::Vertical,
+    widget::{Button, Column, Scrollable, column, horizontal_rule, row, slider, text, text_input},
+};
+
+// how far the nudge buttons move a split point
+const NUDGE_MS: i64 = 250;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    Open,
+    Opened(Option<PathBuf>),
+    Analyzed(Result<Recording, String>),
+    ThresholdChanged(f32),
+    // in seconds
+    MinSilenceChanged(f32),
+    Detect,
+    // position in milliseconds
+    WaveformClicked(u64),
+    // picks a split point to move with the next click on the waveform
+    Select(usize),
+    Nudge(usize, i64),
+    RemoveSplit(usize),
+    TitleChanged(usize, String),
+    ArtistChanged(String),
+    AlbumChanged(String),
+    ReleaseChanged(String),
+    SideChanged(String),
+    FetchRelease,
+    ReleaseFetched(Result<Release, String>),
+    Split,
+    // the recording and the files written
+    Done(PathBuf, Result<Vec<PathBuf>, String>),
+}
+
+/// Splits a digitized record side into tracks at the quiet gaps between them.
+pub struct VinylView {
+    recording: Option<Recording>,
+    analyzing: bool,
+    threshold_db: f32,
+    min_silence: f32,
+    // in milliseconds, sorted
+    splits: Vec<u64>,
+    selected: Option<usize>,
+    // one per track, a track more than there are splits
+    titles: Vec<String>,
+    artist: String,
+    album: String,
+    release: String,
+    side: String,
+    fetching: bool,
+    splitting: bool,
+    status: Option<String>,
+}
+
+impl Default for VinylView {
+    fn default() -> Self {
+        Self {
+            recording: None,
+            analyzing: false,
+            threshold_db: vinyl::DEFAULT_THRESHOLD_DB,
+            min_silence: vinyl::DEFAULT_MIN_SILENCE_MS as f32 / 1000.0,
+            splits: Vec::new(),
+            selected: None,
+            titles: Vec::new(),
+            artist: String::new(),
+            album: String::new(),
+            release: String::new(),
+            side: String::new(),
+            fetching: false,
+            splitting: false,
+            status: None,
+        }
+    }
+}
+
+impl VinylView {
+    fn detect(&mut self) {
+        let Some(recording) = &self.recording else {
+            return;
+        };
+        self.splits = vinyl::detect_splits(
+            &recording.levels,
+            self.threshold_db,
+            (self.min_silence * 1000.0) as u64,
+        );
+        self.splits_changed();
+    }
+
+    fn splits_changed(&mut self) {
+        self.splits.sort_unstable();
+        self.splits.dedup();
+        self.selected = None;
+        let count = self.splits.len() + 1;
+        while self.titles.len() < count {
+            self.titles.push(format!("Track {}", self.titles.len() + 1));
+        }
+        self.titles.truncate(count);
+    }
+
+    fn tracks(&self) -> Vec<SplitTrack> {
+        let Some(recording) = &self.recording else {
+            return Vec::new();
+        };
+        vinyl::segments(&self.splits, recording.duration_ms)
+            .into_iter()
+            .zip(&self.titles)
+            .enumerate()
+            .map(|(index, ((start_ms, end_ms), title))| SplitTrack {
+                start_ms,
+                end_ms,
+                title: title.clone(),
+                output: vinyl::output_path(
+                    &recording.path,
+                    &self.artist,
+                    &self.album,
+                    index + 1,
+                    title,
+                ),
+            })
+            .collect()
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Open => {
+                return Task::perform(
+                    async {
+                        rfd::AsyncFileDialog::new()
+                            .add_filter("Recordings", &["wav", "flac", "aiff", "aif"])
+                            .pick_file()
+                            .await
+                            .map(|file| file.path().to_path_buf())
+                    },
+                    Message::Opened,
+                );
+            }
+            Message::Opened(Some(path)) => {
+                self.analyzing = true;
+                self.status = None;
+                return Task::perform(vinyl::analyze(path), |result| {
+                    Message::Analyzed(result.map_err(|e| e.to_string()))
+                });
+            }
+            Message::Analyzed(result) => {
+                self.analyzing = false;
+                match result {
+                    Ok(recording) => {
+                        self.recording = Some(recording);
+                        self.titles.clear();
+                        self.detect();
+                    }
+                    Err(error) => self.status = Some(error),
+                }
+            }
+            Message::ThresholdChanged(value) => self.threshold_db = value,
+            Message::MinSilenceChanged(value) => self.min_silence = value,
+            Message::Detect => self.detect(),
+            Message::WaveformClicked(position) => {
+                match self.selected {
+                    Some(index) => self.splits[index] = position,
+                    None => self.splits.push(position),
+                }
+                self.splits_changed();
+            }
+            Message::Select(index) => {
+                self.selected = (self.selected != Some(index)).then_some(index);
+            }
+            Message::Nudge(index, delta) => {
+                if let Some(split) = self.splits.get_mut(index) {
+                    *split = split.saturating_add_signed(delta);
+                    self.splits_changed();
+                }
+            }
+            Message::RemoveSplit(index) => {
+                if index < self.splits.len() {
+                    self.splits.remove(index);
+                    // the track after the split joins the one before it
+                    self.titles.remove(index + 1);
+                    self.splits_changed();
+                }
+            }
+            Message::TitleChanged(index, value) => {
+                if let Some(title) = self.titles.get_mut(index) {
+                    *title = value;
+                }
+            }
+            Message::ArtistChanged(value) => self.artist = value,
+            Message::AlbumChanged(value) => self.album = value,
+            Message::ReleaseChanged(value) => self.release = value,
+            Message::SideChanged(value) => self.side = value,
+            Message::FetchRelease => {
+                self.fetching = true;
+                self.status = None;
+                return Task::perform(discogs::fetch_release(self.release.clone()), |result| {
+                    Message::ReleaseFetched(result.map_err(|e| e.to_string()))
+                });
+            }
+            Message::ReleaseFetched(result) => {
+                self.fetching = false;
+                match result {
+                    Ok(release) => {
+                        self.artist = release.artist();
+                        self.album = release.title.clone();
+                        let tracks = release.side(&self.side);
+                        for (title, track) in self.titles.iter_mut().zip(&tracks) {
+                            *title = track.title.clone();
+                        }
+                        if tracks.len() != self.titles.len() {
+                            self.status = Some(format!(
+                                "The release lists {} tracks, the recording has {}",
+                                tracks.len(),
+                                self.titles.len()
+                            ));
+                        }
+                    }
+                    Err(error) => self.status = Some(error),
+                }
+            }
+            Message::Split => {
+                let Some(recording) = &self.recording else {
+                    return Task::none();
+                };
+                self.splitting = true;
+                self.status = None;
+                let path = recording.path.clone();
+                return Task::perform(
+                    vinyl::split(
+                        path.clone(),
+                        self.artist.clone(),
+                        self.album.clone(),
+                        self.tracks(),
+                    ),
+                    move |result| Message::Done(path.clone(), result.map_err(|e| e.to_string())),
+                );
+            }
+            Message::Done(_, result) => {
+                self.splitting = false;
+                self.status = Some(match result {
+                    Ok(files) => format!("Wrote and imported {} tracks", files.len()),
+                    Err(error) => error,
+                });
+            }
+            Message::Opened(None) => {}
+        }
+        Task::none()
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let open = Button::new(text(if self.analyzing {
+            "Reading…"
+        } else {
+            "Open recording…"
+        }))
+        .style(iced::widget::button::secondary)
+        .on_press_maybe((!self.analyzing).then_some(Message::Open));
+        let mut content = column![
+            text("Split Recording").font(SANS_BOLD).size(20),
+            row![
+                open,
+                text(
+                    self.recording
+                        .as_ref()
+                        .map(|recording| recording.path.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                )
+                .size(12),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center),
+        ]
+        .spacing(12)
+        .padding(Padding::default().right(16));
+
+        if let Some(recording) = &self.recording {
+            content = content
+                .push(
+                    Waveform::new(&recording.levels, recording.duration_ms, &self.splits)
+                        .selected(self.selected)
+                        .on_click(Message::WaveformClicked),
+                )
+                .push(
+                    text(if self.selected.is_some() {
+                        "Click the waveform to move the highlighted split"
+                    } else {
+                        "Click the waveform to add a split"
+                    })
+                    .size(12),
+                )
+                .push(
+                    row![
+                        text(format!("Silence below {:.0} dB", self.threshold_db)).size(14),
+                        slider(-70.0..=-20.0, self.threshold_db, Message::ThresholdChanged)
+                            .width(Length::Fixed(160.0)),
+                        text(format!("for {:.1} s", self.min_silence)).size(14),
+                        slider(0.3..=5.0, self.min_silence, Message::MinSilenceChanged)
+                            .step(0.1)
+                            .width(Length::Fixed(160.0)),
+                        Button::new(text("Detect"))
+                            .style(iced::widget::button::secondary)
+                            .on_press(Message::Detect),
+                    ]
+                    .spacing(10)
+                    .align_y(Vertical::Center),
+                )
+                .push(horizontal_rule(1))
+                .push(
+                    row![
+                        text_input("Discogs release URL or id", &self.release)
+                            .on_input(Message::ReleaseChanged)
+                            .on_submit(Message::FetchRelease),
+                        text_input("Side", &self.side)
+                            .on_input(Message::SideChanged)
+                            .width(Length::Fixed(60.0)),
+                        Button::new(text(if self.fetching {
+                            "Fetching…"
+                        } else {
+                            "Name from Discogs"
+                        }))
+                        .style(iced::widget::button::secondary)
+                        .on_press_maybe((!self.fetching).then_some(Message::FetchRelease)),
+                    ]
+                    .spacing(10)
+                    .align_y(Vertical::Center),
+                )
+                .push(
+                    row![
+                        text_input("Artist", &self.artist).on_input(Message::ArtistChanged),
+                        text_input("Album", &self.album).on_input(Message::AlbumChanged),
+                    ]
+                    .spacing(10),
+                )
+                .push(self.tracks_view())
+                .push(
+                    Button::new(text(if self.splitting {
+                        "Splitting…"
+                    } else {
+                        "Split and import"
+                    }))
+                    .style(iced::widget::button::primary)
+                    .on_press_maybe((!self.splitting).then_some(Message::Split)),
+                );
+        } else {
+            content = content.push(
+                text(
+                    "Open a WAV or FLAC of a whole record side, it is split where the music \
+                     stops. Splitting needs ffmpeg to be installed.",
+                )
+                .size(12),
+            );
+        }
+        if let Some(status) = &self.status {
+            content = content.push(text(status).size(12));
+        }
+
+        Scrollable::new(content.max_width(840))
+            .height(Length::Fill)
+            .into()
+    }
+
+    fn tracks_view(&self) -> Element<'_, Message> {
+        let mut tracks = Column::new().spacing(8);
+        for (index, track) in self.tracks().into_iter().enumerate() {
+            let mut line = row![
+                text(format!("{:02}", index + 1))
+                    .font(SANS_BOLD)
+                    .size(14)
+                    .width(Length::Fixed(28.0)),
+                text(format!(
+                    "{} – {}",
+                    position_to_str(track.start_ms),
+                    position_to_str(track.end_ms)
+                ))
+                .size(12)
+                .width(Length::Fixed(140.0)),
+                text_input("Title", &self.titles[index])
+                    .on_input(move |value| Message::TitleChanged(index, value)),
+            ]
+            .spacing(10)
+            .align_y(Vertical::Center);
+            // the split ending this track
+            if index < self.splits.len() {
+                let small = |label| Button::new(text(label).size(12));
+                line = line
+                    .push(
+                        small("◀")
+                            .style(iced::widget::button::secondary)
+                            .on_press(Message::Nudge(index, -NUDGE_MS)),
+                    )
+                    .push(
+                        small("▶")
+                            .style(iced::widget::button::secondary)
+                            .on_press(Message::Nudge(index, NUDGE_MS)),
+                    )
+                    .push(
+                        small(if self.selected == Some(index) {
+                            "Moving"
+                        } else {
+                            "Move"
+                        })
+                        .style(iced::widget::button::secondary)
+                        .on_press(Message::Select(index)),
+                    )
+                    .push(
+                        small("Join")
+                            .style(iced::widget::button::secondary)
+                            .on_press(Message::RemoveSplit(index)),
+                    );
+            }
+            tracks = tracks.push(line);
+        }
+        tracks.into()
+    }
+}
+
+fn position_to_str(ms: u64) -> String {
+    format!("{}:{:02}.{}", ms / 60_000, ms / 1000 % 60, ms / 100 % 10)
+}
@@ -0,0 +1,218 @@
+// Splitting a digitized record side into its tracks. The recording is decoded once to find the
+// quiet gaps between tracks, the tracks are then cut out of it with ffmpeg.
+use std::{
+    fs::File,
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+
+use iced::futures::channel::oneshot;
+use rodio::{Decoder, Source};
+use thiserror::Error;
+
+use crate::convert::{self, ConvertError};
+
+// level readings per second of recording
+pub const LEVELS_PER_SECOND: u64 = 20;
+// quieter than this counts as the gap between two tracks
+pub const DEFAULT_THRESHOLD_DB: f32 = -45.0;
+// shorter quiet passages belong to the music
+pub const DEFAULT_MIN_SILENCE_MS: u64 = 1200;
+
+#[derive(Debug, Error)]
+pub enum VinylError {
+    #[error("Could not decode the recording: {0}")]
+    Decode(#[from] rodio::decoder::DecoderError),
+    #[error(transparent)]
+    Convert(#[from] ConvertError),
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, VinylError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    pub path: PathBuf,
+    pub duration_ms: u64,
+    // RMS amplitude from 0 to 1, `LEVELS_PER_SECOND` of them per second
+    pub levels: Vec<f32>,
+}
+
+/// One track to cut out of a recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SplitTrack {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub title: String,
+    pub output: PathBuf,
+}
+
+/// Decodes the recording on a thread of its own and measures how loud it is over time.
+pub async fn analyze(path: PathBuf) -> Result<Recording> {
+    let (sender, receiver) = oneshot::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(measure(path));
+    });
+    receiver
+        .await
+        .unwrap_or_else(|_| Err(io::Error::other("analysis stopped").into()))
+}
+
+fn measure(path: PathBuf) -> Result<Recording> {
+    let source = Decoder::new(BufReader::new(File::open(&path)?))?.convert_samples::<f32>();
+    let channels = source.channels().max(1) as u64;
+    let sample_rate = (source.sample_rate() as u64).max(1);
+    let window = (sample_rate / LEVELS_PER_SECOND).max(1) * channels;
+    let mut levels = Vec::new();
+    let mut squares = 0.0;
+    let mut samples = 0;
+    let mut total = 0;
+    for sample in source {
+        squares += sample * sample;
+        samples += 1;
+        total += 1;
+        if samples == window {
+            levels.push((squares / samples as f32).sqrt());
+            squares = 0.0;
+            samples = 0;
+        }
+    }
+    if samples > 0 {
+        levels.push((squares / samples as f32).sqrt());
+    }
+    Ok(Recording {
+        path,
+        duration_ms: total * 1000 / (channels * sample_rate),
+        levels,
+    })
+}
+
+/// Split points in the middle of each gap quieter than `threshold_db` which lasts at least
+/// `min_silence_ms`. The run-in and run-out grooves are not gaps between tracks.
+pub fn detect_splits(levels: &[f32], threshold_db: f32, min_silence_ms: u64) -> Vec<u64> {
+    let threshold = 10f32.powf(threshold_db / 20.0);
+    let min_levels = (min_silence_ms * LEVELS_PER_SECOND / 1000).max(1) as usize;
+    let mut splits = Vec::new();
+    let mut quiet_since = None;
+    for (index, level) in levels.iter().enumerate() {
+        match (*level < threshold, quiet_since) {
+            (true, None) => quiet_since = Some(index),
+            (false, Some(start)) => {
+                if start > 0 && index - start >= min_levels {
+                    let middle = (start + index) / 2;
+                    splits.push(middle as u64 * 1000 / LEVELS_PER_SECOND);
+                }
+                quiet_since = None;
+            }
+            _ => {}
+        }
+    }
+    splits
+}
+
+/// Start and end of each track between the split points, the first starts with the recording.
+pub fn segments(splits: &[u64], duration_ms: u64) -> Vec<(u64, u64)> {
+    let mut bounds = vec![0];
+    bounds.extend(
+        splits
+            .iter()
+            .copied()
+            .filter(|split| *split > 0 && *split < duration_ms),
+    );
+    bounds.push(duration_ms);
+    bounds.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+/// Where a track of the recording is written, a folder for the release next to the recording.
+pub fn output_path(
+    recording: &Path,
+    artist: &str,
+    album: &str,
+    number: usize,
+    title: &str,
+) -> PathBuf {
+    let folder = format!(
+        "{} - {}",
+        convert::file_name_part(artist),
+        convert::file_name_part(album)
+    );
+    let file_name = format!("{number:02} {}.flac", convert::file_name_part(title));
+    recording
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(folder)
+        .join(file_name)
+}
+
+/// Cuts the tracks out of the recording and tags them, returns the files written.
+pub async fn split(
+    recording: PathBuf,
+    artist: String,
+    album: String,
+    tracks: Vec<SplitTrack>,
+) -> Result<Vec<PathBuf>> {
+    let (sender, receiver) = oneshot::channel();
+    std::thread::spawn(move || {
+        let count = tracks.len().to_string();
+        let mut written = Vec::new();
+        for (index, track) in tracks.iter().enumerate() {
+            let number = (index + 1).to_string();
+            let tags = [
+                ("title", track.title.as_str()),
+                ("artist", artist.as_str()),
+                ("album", album.as_str()),
+                ("track", number.as_str()),
+                ("tracktotal", count.as_str()),
+            ];
+            let result = convert::extract(
+                &recording,
+                &track.output,
+                track.start_ms,
+                track.end_ms,
+                &tags,
+            );
+            if let Err(error) = result {
+                let _ = sender.send(Err(error.into()));
+                return;
+            }
+            written.push(track.output.clone());
+        }
+        let _ = sender.send(Ok(written));
+    });
+    receiver
+        .await
+        .unwrap_or_else(|_| Err(io::Error::other("splitting stopped").into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_splits() {
+        let loud = 0.3;
+        let quiet = 0.001;
+        let mut levels = vec![quiet; 10];
+        levels.extend([loud; 100]);
+        // too short for a gap
+        levels.extend([quiet; 10]);
+        levels.extend([loud; 100]);
+        levels.extend([quiet; 40]);
+        levels.extend([loud; 100]);
+        levels.extend([quiet; 60]);
+        let splits = detect_splits(&levels, DEFAULT_THRESHOLD_DB, DEFAULT_MIN_SILENCE_MS);
+        assert_eq!(splits, [240 * 1000 / LEVELS_PER_SECOND]);
+
+        assert_eq!(segments(&splits, 21_000), [(0, 12_000), (12_000, 21_000)]);
+        assert_eq!(segments(&[], 21_000), [(0, 21_000)]);
+    }
+
+    #[test]
+    fn test_output_path() {
+        assert_eq!(
+            output_path(Path::new("/rips/side-a.wav"), "Alpha", "", 3, "Go: Now"),
+            PathBuf::from("/rips/Alpha - Unknown/03 Go_ Now.flac")
+        );
+    }
+}
@@ -0,0 +1,187 @@
+// Gain trims evening out sources which play louder or quieter than the local files, set per kind
+// of source and per radio station. A station is the host a stream is served from.
+use crate::app_state::{AudioPlayable, PlayableKind};
+
+pub const MAX_TRIM_DB: i32 = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrimTarget {
+    Kind(PlayableKind),
+    Station(String),
+}
+
+impl TrimTarget {
+    fn to_setting(&self) -> String {
+        match self {
+            TrimTarget::Kind(kind) => format!("kind:{}", kind_name(*kind)),
+            TrimTarget::Station(host) => format!("station:{host}"),
+        }
+    }
+
+    fn from_setting(value: &str) -> Option<Self> {
+        match value.split_once(':')? {
+            ("kind", name) => KINDS
+                .into_iter()
+                .find(|(_, kind)| kind_name(*kind) == name)
+                .map(|(_, kind)| TrimTarget::Kind(kind)),
+            ("station", host) if !host.is_empty() => Some(TrimTarget::Station(host.to_string())),
+            _ => None,
+        }
+    }
+}
+
+// the kinds a trim can be set for, with their labels
+pub const KINDS: [(&str, PlayableKind); 8] = [
+    ("Local files", PlayableKind::LocalFile),
+    ("Google Drive", PlayableKind::GoogleDrive),
+    ("Dropbox", PlayableKind::Dropbox),
+    ("YouTube", PlayableKind::Youtube),
+    ("Streams", PlayableKind::Stream),
+    ("WebDAV", PlayableKind::WebDav),
+    ("Subsonic", PlayableKind::Subsonic),
+    ("Jellyfin", PlayableKind::Jellyfin),
+];
+
+fn kind_name(kind: PlayableKind) -> &'static str {
+    match kind {
+        PlayableKind::LocalFile => "local",
+        PlayableKind::GoogleDrive => "google_drive",
+        PlayableKind::Dropbox => "dropbox",
+        PlayableKind::Youtube => "youtube",
+        PlayableKind::Stream => "stream",
+        PlayableKind::WebDav => "webdav",
+        PlayableKind::Subsonic => "subsonic",
+        PlayableKind::Jellyfin => "jellyfin",
+    }
+}
+
+/// The station of a stream address, its host without a leading `www.`.
+pub fn station(url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(url.trim()).ok()?;
+    let host = url.host_str()?.to_lowercase();
+    Some(host.strip_prefix("www.").unwrap_or(&host).to_string())
+}
+
+/// Trims in dB, kept in one setting as `kind:stream=-3,station:radio.example.com=2`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VolumeTrims {
+    trims: Vec<(TrimTarget, i32)>,
+}
+
+impl VolumeTrims {
+    pub fn parse(value: &str) -> Self {
+        let trims = value
+            .split(',')
+            .filter_map(|entry| {
+                let (target, db) = entry.rsplit_once('=')?;
+                let db: i32 = db.trim().parse().ok()?;
+                Some((
+                    TrimTarget::from_setting(target.trim())?,
+                    db.clamp(-MAX_TRIM_DB, MAX_TRIM_DB),
+                ))
+            })
+            .collect();
+        Self { trims }
+    }
+
+    pub fn to_setting(&self) -> String {
+        self.trims
+            .iter()
+            .map(|(target, db)| format!("{}={db}", target.to_setting()))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    pub fn get(&self, target: &TrimTarget) -> Option<i32> {
+        self.trims
+            .iter()
+            .find(|(trimmed, _)| trimmed == target)
+            .map(|(_, db)| *db)
+    }
+
+    /// Sets the trim of `target`, clamped to ±`MAX_TRIM_DB`. Kinds without a trim are left
+    /// out, stations stay listed until removed.
+    pub fn set(&mut self, target: TrimTarget, db: i32) {
+        let db = db.clamp(-MAX_TRIM_DB, MAX_TRIM_DB);
+        self.remove(&target);
+        if db != 0 || matches!(target, TrimTarget::Station(_)) {
+            self.trims.push((target, db));
+        }
+    }
+
+    pub fn remove(&mut self, target: &TrimTarget) {
+        self.trims.retain(|(trimmed, _)| trimmed != target);
+    }
+
+    pub fn stations(&self) -> impl Iterator<Item = (&str, i32)> {
+        self.trims.iter().filter_map(|(target, db)| match target {
+            TrimTarget::Station(host) => Some((host.as_str(), *db)),
+            TrimTarget::Kind(_) => None,
+        })
+    }
+
+    /// The factor the samples of `playable` are scaled by, the trim of its station wins over
+    /// the one of its kind.
+    pub fn gain(&self, playable: &dyn AudioPlayable) -> f32 {
+        let kind = playable.get_kind();
+        let station = (kind == PlayableKind::Stream)
+            .then(|| station(playable.get_path()))
+            .flatten()
+            .and_then(|host| self.get(&TrimTarget::Station(host)));
+        let db = station
+            .or_else(|| self.get(&TrimTarget::Kind(kind)))
+            .unwrap_or(0);
+        10f32.powf(db as f32 / 20.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{AudioFileKind, Playable};
+
+    #[test]
+    fn test_station() {
+        assert_eq!(
+            station("https://www.Radio.example.com:8000/live.mp3"),
+            Some("radio.example.com".to_string())
+        );
+        assert_eq!(station("/music/a.flac"), None);
+    }
+
+    #[test]
+    fn test_setting() {
+        let mut trims = VolumeTrims::default();
+        trims.set(TrimTarget::Kind(PlayableKind::Stream), -30);
+        trims.set(TrimTarget::Station("radio.example.com".to_string()), 0);
+        trims.set(TrimTarget::Kind(PlayableKind::Youtube), 0);
+        assert_eq!(
+            trims.to_setting(),
+            "kind:stream=-12,station:radio.example.com=0"
+        );
+        assert_eq!(VolumeTrims::parse(&trims.to_setting()), trims);
+        assert_eq!(
+            VolumeTrims::parse("kind:tape=3,station:=1,kind:youtube=x,kind:dropbox=2"),
+            VolumeTrims {
+                trims: vec![(TrimTarget::Kind(PlayableKind::Dropbox), 2)],
+            }
+        );
+        assert_eq!(VolumeTrims::parse(""), VolumeTrims::default());
+    }
+
+    #[test]
+    fn test_gain() {
+        let stream = |url: &str| Playable {
+            id: 1,
+            source_url: url.to_string(),
+            type_id: AudioFileKind::Stream,
+            ..Default::default()
+        };
+        let mut trims = VolumeTrims::default();
+        assert_eq!(trims.gain(&stream("https://a.example.com/live")), 1.0);
+        trims.set(TrimTarget::Kind(PlayableKind::Stream), 6);
+        trims.set(TrimTarget::Station("b.example.com".to_string()), -6);
+        assert!((trims.gain(&stream("https://a.example.com/live")) - 1.995).abs() < 0.01);
+        assert!((trims.gain(&stream("http://b.example.com/live")) - 0.501).abs() < 0.01);
+    }
+}
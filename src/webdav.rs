@@ -0,0 +1,392 @@
+// Music kept on a WebDAV server, as NAS boxes share it. Its folders are browsed and their
+// files indexed from the tags at the start of each file, tracks are streamed through a cache
+// on disk.
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io::{self, Cursor},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use directories::BaseDirs;
+use lofty::{
+    error::LoftyError,
+    file::{FileType, TaggedFile},
+    probe::Probe,
+};
+use log::error;
+use reqwest::{
+    Method, Url,
+    header::{CONTENT_TYPE, RANGE},
+};
+use roxmltree::Document;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    audio_scanner::{self, AUDIO_EXTENSIONS, ScanOptions, ScannedFile, ScannedKind},
+    remote,
+};
+
+// the start of a file read for its tags, enough for most headers with their artwork
+const HEADER_BYTES: u64 = 512 * 1024;
+// the tracks played least recently are removed from the cache past this size
+const CACHE_LIMIT: u64 = 2 * 1024 * 1024 * 1024;
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop><d:resourcetype/><d:getcontentlength/></d:prop>
+</d:propfind>"#;
+
+#[derive(Debug, Error)]
+pub enum WebDavError {
+    #[error("Request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Invalid listing: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("Unreadable tags: {0}")]
+    Tags(#[from] LoftyError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("Invalid address: {0}")]
+    InvalidUrl(String),
+}
+
+pub type Result<T> = std::result::Result<T, WebDavError>;
+
+/// A share on a WebDAV server, the files under its address are read with its account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Server {
+    // the folder shared, e.g. `https://nas.local/music/`
+    pub url: String,
+    pub username: String,
+    pub password: String,
+}
+
+impl Server {
+    fn request(
+        &self,
+        client: &reqwest::Client,
+        method: Method,
+        url: &str,
+    ) -> reqwest::RequestBuilder {
+        let request = client.request(method, url);
+        if self.username.is_empty() {
+            request
+        } else {
+            request.basic_auth(&self.username, Some(&self.password))
+        }
+    }
+}
+
+/// A folder or a file of a listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub url: String,
+    pub name: String,
+    pub is_dir: bool,
+    // in bytes, 0 for folders
+    pub size: u64,
+}
+
+impl Entry {
+    fn is_audio(&self) -> bool {
+        !self.is_dir
+            && Path::new(&self.name)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+    }
+}
+
+// the folders first, then by name
+fn parse_listing(folder: &str, xml: &str) -> Result<Vec<Entry>> {
+    let base = Url::parse(folder).map_err(|e| WebDavError::InvalidUrl(e.to_string()))?;
+    let doc = Document::parse(xml)?;
+    let mut entries = Vec::new();
+    for response in doc
+        .descendants()
+        .filter(|n| n.has_tag_name(("DAV:", "response")))
+    {
+        let Some(href) = response
+            .descendants()
+            .find(|n| n.has_tag_name(("DAV:", "href")))
+            .and_then(|n| n.text())
+        else {
+            continue;
+        };
+        let Ok(url) = base.join(href.trim()) else {
+            continue;
+        };
+        // the folder listed is part of its own listing
+        if url.path().trim_end_matches('/') == base.path().trim_end_matches('/') {
+            continue;
+        }
+        let name = url
+            .path_segments()
+            .and_then(|segments| segments.filter(|s| !s.is_empty()).last())
+            .map(|name| urlencoding::decode(name).map(|n| n.into_owned()))
+            .and_then(|name| name.ok())
+            .unwrap_or_default();
+        let is_dir = response
+            .descendants()
+            .any(|n| n.has_tag_name(("DAV:", "collection")));
+        let size = response
+            .descendants()
+            .find(|n| n.has_tag_name(("DAV:", "getcontentlength")))
+            .and_then(|n| n.text())
+            .and_then(|size| size.trim().parse().ok())
+            .unwrap_or(0);
+        entries.push(Entry {
+            url: url.to_string(),
+            name,
+            is_dir,
+            size,
+        });
+    }
+    entries.sort_by_cached_key(|entry| (!entry.is_dir, entry.name.to_lowercase()));
+    Ok(entries)
+}
+
+async fn propfind(client: &reqwest::Client, server: &Server, folder: &str) -> Result<Vec<Entry>> {
+    let xml = server
+        .request(client, Method::from_bytes(b"PROPFIND").unwrap(), folder)
+        .header("Depth", "1")
+        .header(CONTENT_TYPE, "application/xml")
+        .body(PROPFIND_BODY)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_listing(folder, &xml)
+}
+
+/// The folders and files right inside `folder`.
+pub async fn list(server: Server, folder: String) -> Result<Vec<Entry>> {
+    propfind(&reqwest::Client::new(), &server, &folder).await
+}
+
+/// The audio files under `folder` with their tags, as the scanner options allow.
+pub async fn scan(
+    server: Server,
+    folder: String,
+    options: ScanOptions,
+) -> Result<Vec<ScannedFile>> {
+    let client = reqwest::Client::new();
+    let mut folders = vec![(folder.clone(), 0)];
+    let mut files = Vec::new();
+    while let Some((current, depth)) = folders.pop() {
+        for entry in propfind(&client, &server, &current).await? {
+            let relative = entry.url.strip_prefix(&folder).unwrap_or(&entry.url);
+            let relative = urlencoding::decode(relative)
+                .map(|r| r.into_owned())
+                .unwrap_or_else(|_| relative.to_string());
+            if (options.skip_hidden && entry.name.starts_with('.'))
+                || options.is_excluded(relative.trim_end_matches('/'))
+            {
+                continue;
+            }
+            if entry.is_dir {
+                // files right in the scanned folder are at depth 1
+                if options.max_depth.is_none_or(|max| depth + 1 < max) {
+                    folders.push((entry.url, depth + 1));
+                }
+            } else if entry.is_audio() {
+                match read_tags(&client, &server, &entry, &options).await {
+                    Ok(file) if file.duration >= options.min_duration => files.push(file),
+                    Ok(_) => {}
+                    Err(err) => error!("Error reading the tags of {}: {err:?}", entry.url),
+                }
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn probe(bytes: &[u8]) -> Result<TaggedFile> {
+    Ok(Probe::new(Cursor::new(bytes)).guess_file_type()?.read()?)
+}
+
+// the tags are read from the start of the file, the whole of it is fetched when they go on
+// past it, e.g. for large artwork
+async fn read_tags(
+    client: &reqwest::Client,
+    server: &Server,
+    entry: &Entry,
+    options: &ScanOptions,
+) -> Result<ScannedFile> {
+    let mut request = server.request(client, Method::GET, &entry.url);
+    if entry.size > HEADER_BYTES {
+        request = request.header(RANGE, format!("bytes=0-{}", HEADER_BYTES - 1));
+    }
+    let bytes = request.send().await?.error_for_status()?.bytes().await?;
+    // a server which ignores the range sends the whole file
+    let mut partial = (bytes.len() as u64) < entry.size;
+    let tagged_file = match probe(&bytes) {
+        Ok(tagged_file) => tagged_file,
+        Err(_) if partial => {
+            partial = false;
+            let request = server.request(client, Method::GET, &entry.url);
+            probe(&request.send().await?.error_for_status()?.bytes().await?)?
+        }
+        Err(err) => return Err(err),
+    };
+    let mut file =
+        audio_scanner::scanned_file(&tagged_file, entry.url.clone(), &entry.name, options);
+    // the length of an MP3 is reckoned from its size, the start of it is taken for the whole
+    if partial && tagged_file.file_type() == FileType::Mpeg && file.bitrate > 0 {
+        file.duration = entry.size * 8 / (u64::from(file.bitrate) * 1000);
+    }
+    file.kind = ScannedKind::WebDav;
+    Ok(file)
+}
+
+fn cache_dir() -> Option<PathBuf> {
+    Some(BaseDirs::new()?.cache_dir().join("phoniq").join("webdav"))
+}
+
+// named after the address, with its extension for the decoder to go by
+fn cache_name(url: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let extension = Path::new(url)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("audio");
+    format!("{:016x}.{extension}", hasher.finish())
+}
+
+// removes the files used least recently until the cache fits in `limit` bytes
+fn trim_cache(dir: &Path, limit: u64) -> io::Result<()> {
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+        })
+        .collect();
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    files.sort();
+    for (_, size, path) in files {
+        if total <= limit {
+            break;
+        }
+        fs::remove_file(path)?;
+        total -= size;
+    }
+    Ok(())
+}
+
+/// Blocking read used by the audio worker to stream a file of a server, from the cache when
+/// it was played before.
+pub fn fetch_audio(url: &str) -> io::Result<Vec<u8>> {
+    let cached = cache_dir().map(|dir| (cache_name(url), dir));
+    if let Some((name, dir)) = &cached
+        && let Ok(bytes) = fs::read(dir.join(name))
+    {
+        // kept as the most recently used
+        let _ = fs::File::options()
+            .append(true)
+            .open(dir.join(name))
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        return Ok(bytes);
+    }
+    let client = reqwest::blocking::Client::new();
+    let mut request = client.get(url);
+    if let Some(remote::Account::WebDav(server)) = remote::find_account(url)
+        && !server.username.is_empty()
+    {
+        request = request.basic_auth(&server.username, Some(&server.password));
+    }
+    let bytes = request
+        .send()
+        .and_then(|r| r.error_for_status())
+        .and_then(|r| r.bytes())
+        .map_err(io::Error::other)?
+        .to_vec();
+    if let Some((name, dir)) = cached {
+        let stored = fs::create_dir_all(&dir)
+            .and_then(|_| fs::write(dir.join(name), &bytes))
+            .and_then(|_| trim_cache(&dir, CACHE_LIMIT));
+        if let Err(err) = stored {
+            error!("Error caching {url}: {err:?}");
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listing() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response>
+    <d:href>/music/</d:href>
+    <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/music/Track%20One.mp3</d:href>
+    <d:propstat><d:prop>
+      <d:resourcetype/><d:getcontentlength>4096</d:getcontentlength>
+    </d:prop></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>https://nas.local/music/Albums/</d:href>
+    <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop></d:propstat>
+  </d:response>
+</d:multistatus>"#;
+        let entries = parse_listing("https://nas.local/music/", xml).unwrap();
+        assert_eq!(
+            entries,
+            [
+                Entry {
+                    url: "https://nas.local/music/Albums/".to_string(),
+                    name: "Albums".to_string(),
+                    is_dir: true,
+                    size: 0,
+                },
+                Entry {
+                    url: "https://nas.local/music/Track%20One.mp3".to_string(),
+                    name: "Track One.mp3".to_string(),
+                    is_dir: false,
+                    size: 4096,
+                },
+            ]
+        );
+        assert!(!entries[0].is_audio());
+        assert!(entries[1].is_audio());
+        assert!(parse_listing("not a url", xml).is_err());
+    }
+
+    #[test]
+    fn test_trim_cache() {
+        let dir = std::env::temp_dir().join(format!("phonique-webdav-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let now = SystemTime::now();
+        for (age, name) in [(30, "old"), (20, "older_use"), (10, "new")] {
+            let path = dir.join(name);
+            fs::write(&path, [0u8; 100]).unwrap();
+            fs::File::options()
+                .append(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(now - std::time::Duration::from_secs(age))
+                .unwrap();
+        }
+        trim_cache(&dir, 200).unwrap();
+        let mut left: Vec<String> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["new", "older_use"]);
+        assert!(cache_name("https://nas.local/a.flac").ends_with(".flac"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -77,7 +77,7 @@ where
     Renderer: iced::advanced::text::Renderer,
 {
     content: iced::Element<'a, Message, Theme, Renderer>,
-    menu_options: &'a [&'a str],
+    menu_options: Vec<&'a str>,
     width: Length,
     height: Length,
     padding: f32,
@@ -93,10 +93,10 @@ where
     Theme: Catalog + iced::widget::text::Catalog + 'a,
     Renderer: iced::advanced::text::Renderer + 'a,
 {
-    pub fn new(content: &'a str, menu_options: &'a [&'a str]) -> Self {
+    pub fn new(content: &'a str, menu_options: impl Into<Vec<&'a str>>) -> Self {
         Self {
             content: Text::new(content).into(),
-            menu_options,
+            menu_options: menu_options.into(),
             width: Length::Shrink,
             height: Length::Shrink,
             padding: 8.0,
@@ -252,7 +252,7 @@ where
 
                 if let Some(on_select) = &self.on_option_select {
                     Some(iced::advanced::overlay::Element::new(Box::new(
-                        ContextMenu::new(self.menu_options, overlay_position)
+                        ContextMenu::new(&self.menu_options, overlay_position)
                             .min_width(bounds.width)
                             .font_size(self.font_size)
                             .menu_style(self.menu_style.clone())
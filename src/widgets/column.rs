@@ -1,5 +1,6 @@
 //! Distribute content vertically.
 use std::any::Any;
+use std::ops::Range;
 
 use iced::advanced::Clipboard;
 use iced::advanced::Shell;
@@ -13,7 +14,7 @@ use iced::advanced::widget::tree;
 use iced::advanced::widget::{Operation, Tree, Widget};
 use iced::alignment::{self, Alignment};
 use iced::mouse;
-use iced::{Element, Event, Length, Padding, Pixels, Rectangle, Size, Vector};
+use iced::{Element, Event, Length, Padding, Pixels, Point, Rectangle, Size, Vector};
 use layout::Layout;
 
 /// A container that distributes its contents vertically.
@@ -48,9 +49,22 @@ pub struct Column<'a, Message, Theme = iced::Theme, Renderer = iced::Renderer> {
     max_width: f32,
     align: Alignment,
     clip: bool,
+    window: Option<Window>,
     children: Vec<Element<'a, Message, Theme, Renderer>>,
 }
 
+// rows laid out beyond each edge of the viewport, so scrolling a little does not show a gap
+const WINDOW_BUFFER: usize = 20;
+
+/// The rows of a long list which are built, the others only take up their space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Window {
+    // the index of the first child in the whole list
+    first: usize,
+    count: usize,
+    row_height: f32,
+}
+
 impl<'a, Message, Theme, Renderer> Column<'a, Message, Theme, Renderer>
 where
     Renderer: renderer::Renderer,
@@ -91,6 +105,7 @@ where
             max_width: f32::INFINITY,
             align: Alignment::Start,
             clip: false,
+            window: None,
             children,
         }
     }
@@ -147,6 +162,20 @@ where
         self
     }
 
+    /// Makes the [`Column`] stand for a list of `count` rows, each `row_height` high, of which
+    /// only the children pushed are built, starting at the row `first`.
+    ///
+    /// The rows before and after them only take up their space, so a scrollable around the
+    /// column scrolls as if all of them were there. See [`window_range`] for the rows to build.
+    pub fn window(mut self, first: usize, count: usize, row_height: f32) -> Self {
+        self.window = Some(Window {
+            first,
+            count,
+            row_height,
+        });
+        self
+    }
+
     /// Adds an element to the [`Column`].
     pub fn push(mut self, child: impl Into<Element<'a, Message, Theme, Renderer>>) -> Self {
         let child = child.into();
@@ -200,6 +229,64 @@ impl<'a, Message, Theme, Renderer: renderer::Renderer>
 #[derive(Default)]
 struct State {
     pub positions: Vec<f32>,
+    // the top of the first row, the distance between rows and their count, for a windowed
+    // column whose children are not all laid out
+    pub rows: Option<(f32, f32, usize)>,
+}
+
+impl State {
+    fn position(&self, index: usize) -> Option<f32> {
+        match self.rows {
+            Some((top, stride, count)) => (index < count).then(|| top + index as f32 * stride),
+            None => self.positions.get(index).copied(),
+        }
+    }
+}
+
+impl<Message, Theme, Renderer> Column<'_, Message, Theme, Renderer>
+where
+    Renderer: renderer::Renderer,
+{
+    // the children are placed at the rows they stand for, as high as a row and as wide as the
+    // column allows
+    fn layout_window(
+        &mut self,
+        window: Window,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        let stride = window.row_height + self.spacing;
+        let content_height = (window.count as f32 * stride - self.spacing).max(0.0);
+        let row_width = limits.shrink(self.padding).max().width;
+        let row_limits = layout::Limits::new(
+            Size::new(0.0, window.row_height),
+            Size::new(row_width, window.row_height),
+        );
+
+        let mut width: f32 = 0.0;
+        let mut nodes = Vec::with_capacity(self.children.len());
+        for (i, (child, tree)) in self.children.iter_mut().zip(&mut tree.children).enumerate() {
+            let node = child.as_widget_mut().layout(tree, renderer, &row_limits);
+            width = width.max(node.size().width);
+            let y = self.padding.top + (window.first + i) as f32 * stride;
+            nodes.push(node.move_to(Point::new(self.padding.left, y)));
+        }
+
+        let state = tree.state.downcast_mut::<State>();
+        state.positions.clear();
+        state.rows = Some((self.padding.top, stride, window.count));
+
+        let size = limits.resolve(
+            self.width,
+            self.height,
+            Size::new(
+                width + self.padding.horizontal(),
+                content_height + self.padding.vertical(),
+            ),
+        );
+        layout::Node::with_children(size, nodes)
+    }
 }
 
 impl<Message, Theme, Renderer> Widget<Message, Theme, Renderer>
@@ -237,9 +324,13 @@ where
         limits: &layout::Limits,
     ) -> layout::Node {
         let limits = limits.max_width(self.max_width);
+        if let Some(window) = self.window {
+            return self.layout_window(window, tree, renderer, &limits);
+        }
 
         let state = tree.state.downcast_mut::<State>();
         state.positions.clear();
+        state.rows = None;
 
         let node = layout::flex::resolve(
             layout::flex::Axis::Vertical,
@@ -389,8 +480,24 @@ where
     }
 }
 
+/// The rows of a list of `count` to build for a windowed [`Column`], scrolled down by
+/// `offset` in a viewport `height` high, with rows `stride` apart. A buffer of rows is built on
+/// each side and the first row is a multiple of it, so the rows built seldom change while
+/// scrolling.
+pub fn window_range(offset: f32, height: f32, stride: f32, count: usize) -> Range<usize> {
+    let offset = offset.max(0.0);
+    let first = (offset / stride) as usize;
+    let last = ((offset + height) / stride).ceil() as usize;
+    let end = (last + WINDOW_BUFFER).min(count);
+    let start = first.saturating_sub(WINDOW_BUFFER) / WINDOW_BUFFER * WINDOW_BUFFER;
+    start.min(end)..end
+}
+
 /// Create a [`Task`](iced::Task) to find the position of a specific child by index
 /// within the widget identified by the given [`Id`].
+///
+/// The index of a windowed [`Column`] is that of the row in the whole list, whether it is
+/// built or not.
 pub fn find_position(target: Id, index: usize) -> iced::Task<Option<f32>> {
     struct FindPosition {
         target: Id,
@@ -415,7 +522,7 @@ pub fn find_position(target: Id, index: usize) -> iced::Task<Option<f32>> {
         fn custom(&mut self, id: Option<&Id>, _bounds: Rectangle, state: &mut dyn Any) {
             if Some(&self.target) == id {
                 if let Some(position_state) = state.downcast_mut::<State>() {
-                    self.found_bounds = position_state.positions.get(self.index).copied();
+                    self.found_bounds = position_state.position(self.index);
                 }
             }
         }
@@ -431,3 +538,20 @@ pub fn find_position(target: Id, index: usize) -> iced::Task<Option<f32>> {
         found_bounds: None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_range() {
+        assert_eq!(window_range(0.0, 800.0, 80.0, 10_000), 0..30);
+        // a little scrolling keeps the rows built
+        assert_eq!(window_range(400.0, 800.0, 80.0, 10_000), 0..35);
+        assert_eq!(window_range(80_000.0, 800.0, 80.0, 10_000), 980..1030);
+        assert_eq!(window_range(799_000.0, 800.0, 80.0, 10_000), 9960..10_000);
+        assert_eq!(window_range(0.0, 800.0, 80.0, 5), 0..5);
+        assert_eq!(window_range(8_000.0, 800.0, 80.0, 5), 5..5);
+        assert_eq!(window_range(0.0, 800.0, 80.0, 0), 0..0);
+    }
+}
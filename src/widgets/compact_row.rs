@@ -1,24 +1,52 @@
-use crate::{app_state::AudioPlayable, fonts::SANS_BOLD, util::duration_to_str};
+use crate::{
+    app_state::{AudioPlayable, PlayableKind},
+    fonts::{ICON, SANS_BOLD},
+    util::{duration_to_str, timestamp_to_str},
+};
 use iced::{
-    Alignment, Border, Color, Element, Event, Length, Rectangle, Renderer, Shadow, Size, Theme,
+    Alignment, Border, Color, Element, Event, Length, Point, Rectangle, Renderer, Shadow, Size,
+    Theme, Vector,
     advanced::{
         Clipboard, Layout, Shell, Widget,
         layout::{Node, flex},
-        mouse, renderer,
+        mouse, overlay, renderer,
         widget::Tree,
     },
     theme::palette,
     touch,
-    widget::{Column, text},
+    widget::{Column, Row, text},
     window,
 };
+
+use super::context_menu::{ContextMenu, MenuStyle};
 use std::time::{Duration, Instant};
 
+// how far the cursor moves with the button held before a row is dragged
+const DRAG_THRESHOLD: f32 = 6.0;
+// height of the line marking where dragged rows are dropped
+const DROP_MARKER_HEIGHT: f32 = 2.0;
+/// The height of a track row, a title and an artist line with the padding around them. Rows
+/// are all as high so a long list only builds the rows on screen, text running longer is cut.
+pub const ROW_HEIGHT: f32 = 78.0;
+
+/// The edge of a row dragged rows are dropped at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropMarker {
+    Above,
+    Below,
+}
+
+// `played_at` is shown in place of the genre, for lists of what was played, `status` is an icon
+// after the source, e.g. whether a remote track is available offline. `extra` cells of the
+// optional columns come before the duration, sized to their column by the caller
 pub fn compact_row<'a, Message, Theme>(
     playable: &'a impl AudioPlayable,
     index: usize,
     is_selected: bool,
     row_sizes: &(Length, Length, Length, Length, Length),
+    played_at: Option<i64>,
+    status: Option<char>,
+    extra: Vec<Element<'a, Message, Theme>>,
 ) -> CompactRow<'a, Message, Theme>
 where
     Theme: Catalog + iced::widget::text::Catalog + 'a,
@@ -27,7 +55,10 @@ where
     let artist = playable.get_artist();
     let title = playable.get_title();
     let album = playable.get_album();
-    let genre = playable.get_genre();
+    let genre = match played_at {
+        Some(played_at) => timestamp_to_str(played_at),
+        None => playable.get_genre().to_string(),
+    };
     let duration = playable.get_duration();
     let duration_str = duration_to_str(duration);
 
@@ -37,11 +68,20 @@ where
             .size(15)
             .wrapping(text::Wrapping::WordOrGlyph),
     );
-    if !artist.is_empty() {
-        artist_title_cell =
-            artist_title_cell.push(text(artist).size(14).wrapping(text::Wrapping::WordOrGlyph))
+    let badge = source_badge(playable.get_kind());
+    if !artist.is_empty() || badge.is_some() || status.is_some() {
+        let mut artist_line: Row<'_, Message, Theme, Renderer> =
+            Row::new().spacing(6).align_y(Alignment::Center);
+        if let Some(badge) = badge {
+            artist_line = artist_line.push(text(badge).font(SANS_BOLD).size(11));
+        }
+        if let Some(status) = status {
+            artist_line = artist_line.push(text(status).font(ICON).size(12));
+        }
+        artist_title_cell = artist_title_cell
+            .push(artist_line.push(text(artist).size(14).wrapping(text::Wrapping::WordOrGlyph)))
     }
-    CompactRow::new(is_selected)
+    let mut row = CompactRow::new(is_selected)
         .push(
             text((index).to_string())
                 .size(13)
@@ -58,22 +98,45 @@ where
             text(genre)
                 .width(row_sizes.3)
                 .wrapping(text::Wrapping::WordOrGlyph),
-        )
-        .push(
-            text(duration_str)
-                .size(13)
-                .width(row_sizes.4)
-                .align_x(Alignment::End),
-        )
-        .width(Length::Fill)
-        .padding(20)
-        .spacing(40)
+        );
+    for cell in extra {
+        row = row.push(cell);
+    }
+    row.push(
+        text(duration_str)
+            .size(13)
+            .width(row_sizes.4)
+            .align_x(Alignment::End),
+    )
+    .width(Length::Fill)
+    .height(Length::Fixed(ROW_HEIGHT))
+    .padding(20)
+    .spacing(40)
+}
+
+// the source marked before the artist, the local files are not marked
+fn source_badge(kind: PlayableKind) -> Option<&'static str> {
+    match kind {
+        PlayableKind::LocalFile => None,
+        PlayableKind::GoogleDrive => Some("DRIVE"),
+        PlayableKind::Dropbox => Some("DROPBOX"),
+        PlayableKind::Youtube => Some("YOUTUBE"),
+        PlayableKind::Stream => Some("STREAM"),
+        PlayableKind::WebDav => Some("WEBDAV"),
+        PlayableKind::Subsonic => Some("SUBSONIC"),
+        PlayableKind::Jellyfin => Some("JELLYFIN"),
+    }
 }
 
 #[derive(Default)]
 struct CompactRowState {
     last_click: Option<Instant>,
     is_hovered: bool,
+    // where the context menu was opened, None while it is hidden
+    context_menu_position: Option<Point>,
+    // where the left button went down on the row, until it is released
+    press_position: Option<Point>,
+    is_dragging: bool,
 }
 
 pub struct CompactRow<'a, Message, Theme, Renderer = iced::Renderer>
@@ -90,6 +153,12 @@ where
     on_select: Option<Message>,
     on_dbl_click: Option<Message>,
     on_right_click: Option<Message>,
+    on_drag: Option<Message>,
+    on_drag_over: Option<Message>,
+    drop_marker: Option<DropMarker>,
+    context_menu_options: &'a [&'a str],
+    on_context_menu_select: Option<Box<dyn Fn(usize) -> Message + 'a>>,
+    menu_style: MenuStyle,
     is_selected: bool,
     class: Theme::Class<'a>,
 }
@@ -110,6 +179,12 @@ where
             on_select: None,
             on_dbl_click: None,
             on_right_click: None,
+            on_drag: None,
+            on_drag_over: None,
+            drop_marker: None,
+            context_menu_options: &[],
+            on_context_menu_select: None,
+            menu_style: MenuStyle::default(),
             is_selected,
             class: Theme::default(),
         }
@@ -130,6 +205,11 @@ where
         self
     }
 
+    pub fn height(mut self, length: Length) -> Self {
+        self.height = length;
+        self
+    }
+
     pub fn spacing(mut self, units: u16) -> Self {
         self.spacing = units;
         self
@@ -148,14 +228,45 @@ where
         self.on_right_click = Some(on_right_click);
         self
     }
+
+    /// Published once the row is pressed and the cursor moved away with the button held.
+    pub fn on_drag(mut self, on_drag: Message) -> Self {
+        self.on_drag = Some(on_drag);
+        self
+    }
+
+    /// Published when the cursor enters the row, set while rows are dragged over the list.
+    pub fn on_drag_over(mut self, on_drag_over: Message) -> Self {
+        self.on_drag_over = Some(on_drag_over);
+        self
+    }
+
+    pub fn drop_marker(mut self, marker: Option<DropMarker>) -> Self {
+        self.drop_marker = marker;
+        self
+    }
+
+    pub fn context_menu<F>(mut self, options: &'a [&'a str], on_select: F) -> Self
+    where
+        F: Fn(usize) -> Message + 'a,
+    {
+        self.context_menu_options = options;
+        self.on_context_menu_select = Some(Box::new(on_select));
+        self
+    }
+
+    pub fn menu_style(mut self, style: MenuStyle) -> Self {
+        self.menu_style = style;
+        self
+    }
 }
 
 impl<'a, Message, Theme, Renderer> Widget<Message, Theme, Renderer>
     for CompactRow<'a, Message, Theme, Renderer>
 where
     Message: 'a + Clone,
-    Renderer: renderer::Renderer,
-    Theme: Catalog,
+    Renderer: iced::advanced::text::Renderer,
+    Theme: Catalog + iced::widget::container::Catalog + iced::widget::text::Catalog,
 {
     fn size(&self) -> Size<Length> {
         Size {
@@ -215,7 +326,7 @@ where
             Status::Default
         };
 
-        let appearance = theme.style(&self.class, &status);
+        let appearance = <Theme as Catalog>::style(theme, &self.class, &status);
         renderer.fill_quad(
             renderer::Quad {
                 bounds: layout.bounds(),
@@ -226,15 +337,39 @@ where
             appearance.background,
         );
 
-        for ((content, tree), layout) in self
-            .content
-            .iter()
-            .zip(&tree.children)
-            .zip(layout.children())
-        {
-            content
-                .as_widget()
-                .draw(tree, renderer, theme, style, layout, cursor, viewport);
+        // cells are cut at the edges of a row of a fixed height
+        renderer.with_layer(layout.bounds(), |renderer| {
+            for ((content, tree), layout) in self
+                .content
+                .iter()
+                .zip(&tree.children)
+                .zip(layout.children())
+            {
+                content
+                    .as_widget()
+                    .draw(tree, renderer, theme, style, layout, cursor, viewport);
+            }
+        });
+
+        if let Some(marker) = self.drop_marker {
+            let bounds = layout.bounds();
+            let y = match marker {
+                DropMarker::Above => bounds.y,
+                DropMarker::Below => bounds.y + bounds.height - DROP_MARKER_HEIGHT,
+            };
+            renderer.fill_quad(
+                renderer::Quad {
+                    bounds: Rectangle {
+                        y,
+                        height: DROP_MARKER_HEIGHT,
+                        ..bounds
+                    },
+                    border: Border::default(),
+                    shadow: Shadow::default(),
+                    snap: true,
+                },
+                appearance.marker,
+            );
         }
     }
 
@@ -259,12 +394,20 @@ where
                 tree, event, layout, cursor, renderer, clipboard, shell, viewport,
             );
         }
+
+        let state = tree.state.downcast_mut::<CompactRowState>();
+        // any click closes an open context menu, the menu itself has already seen the event
+        if state.context_menu_position.is_some()
+            && let Event::Mouse(mouse::Event::ButtonPressed(_)) = event
+        {
+            state.context_menu_position = None;
+            shell.request_redraw();
+        }
+
         if shell.is_event_captured() {
             return;
         }
 
-        let state = tree.state.downcast_mut::<CompactRowState>();
-
         match event {
             Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
             | Event::Touch(touch::Event::FingerPressed { .. }) => {
@@ -281,6 +424,7 @@ where
                         }
                     }
                     state.last_click = Some(Instant::now());
+                    state.press_position = cursor.position();
 
                     if self.on_select.is_some() {
                         shell.capture_event();
@@ -289,31 +433,54 @@ where
             }
             Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
             | Event::Touch(touch::Event::FingerLifted { .. }) => {
+                let pressed = state.press_position.take().is_some();
+                // a drag ends wherever the rows were dropped, it does not select
+                if std::mem::take(&mut state.is_dragging) {
+                    return;
+                }
                 if let Some(on_select) = &self.on_select {
                     let bounds = layout.bounds();
 
-                    if cursor.is_over(bounds) {
+                    if pressed && cursor.is_over(bounds) {
                         shell.publish(on_select.clone());
                     }
                 }
             }
             Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) => {
-                if self.on_right_click.is_some() && cursor.is_over(layout.bounds()) {
+                if (self.on_right_click.is_some() || self.on_context_menu_select.is_some())
+                    && cursor.is_over(layout.bounds())
+                {
                     shell.capture_event();
                 }
             }
             Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Right)) => {
-                if let Some(on_right_click) = &self.on_right_click {
-                    if cursor.is_over(layout.bounds()) {
+                if cursor.is_over(layout.bounds()) {
+                    if let Some(on_right_click) = &self.on_right_click {
                         shell.capture_event();
                         shell.publish(on_right_click.clone());
                     }
+                    if self.on_context_menu_select.is_some() {
+                        state.context_menu_position = cursor.position();
+                        shell.capture_event();
+                        shell.request_redraw();
+                    }
                 }
             }
-            Event::Mouse(mouse::Event::CursorMoved { .. }) => {
+            Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                if let Some(on_drag) = &self.on_drag
+                    && let Some(pressed_at) = state.press_position
+                    && !state.is_dragging
+                    && pressed_at.distance(*position) > DRAG_THRESHOLD
+                {
+                    state.is_dragging = true;
+                    shell.publish(on_drag.clone());
+                }
                 let was_hovered = state.is_hovered;
                 state.is_hovered = cursor.is_over(layout.bounds());
                 if !was_hovered && state.is_hovered {
+                    if let Some(on_drag_over) = &self.on_drag_over {
+                        shell.publish(on_drag_over.clone());
+                    }
                     shell.request_redraw();
                 }
             }
@@ -325,6 +492,44 @@ where
         }
     }
 
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'b>,
+        renderer: &Renderer,
+        viewport: &Rectangle,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        let position = tree
+            .state
+            .downcast_ref::<CompactRowState>()
+            .context_menu_position;
+
+        if let Some(position) = position
+            && !self.context_menu_options.is_empty()
+            && self.on_context_menu_select.is_some()
+        {
+            let on_select = self.on_context_menu_select.as_ref().unwrap();
+            return Some(overlay::Element::new(Box::new(
+                ContextMenu::new(
+                    self.context_menu_options,
+                    Point::new(position.x + translation.x, position.y + translation.y),
+                )
+                .menu_style(self.menu_style.clone())
+                .on_select(|index| (on_select)(index)),
+            )));
+        }
+
+        overlay::from_children(
+            &mut self.content,
+            tree,
+            layout,
+            renderer,
+            viewport,
+            translation,
+        )
+    }
+
     fn mouse_interaction(
         &self,
         _tree: &Tree,
@@ -347,8 +552,8 @@ impl<'a, Message, Theme, Renderer> From<CompactRow<'a, Message, Theme, Renderer>
     for Element<'a, Message, Theme, Renderer>
 where
     Message: 'a + Clone,
-    Renderer: 'a + renderer::Renderer,
-    Theme: 'a + Catalog,
+    Renderer: 'a + iced::advanced::text::Renderer,
+    Theme: 'a + Catalog + iced::widget::container::Catalog + iced::widget::text::Catalog,
 {
     fn from(value: CompactRow<'a, Message, Theme, Renderer>) -> Self {
         Self::new(value)
@@ -365,12 +570,15 @@ pub enum Status {
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Style {
     pub background: Color,
+    // the line marking where dragged rows are dropped
+    pub marker: Color,
 }
 
 impl Style {
     pub fn with_background(self, background: impl Into<Color>) -> Self {
         Self {
             background: background.into(),
+            ..self
         }
     }
 }
@@ -379,6 +587,7 @@ impl Default for Style {
     fn default() -> Self {
         Self {
             background: Color::TRANSPARENT,
+            marker: Color::TRANSPARENT,
         }
     }
 }
@@ -405,15 +614,17 @@ impl Catalog for Theme {
 
 pub fn primary(theme: &Theme, status: &Status) -> Style {
     let palette = theme.extended_palette();
+    let style = Style {
+        marker: palette.primary.base.color,
+        ..Style::default()
+    };
     match status {
-        Status::Default => Style::default(),
-        Status::Selected => styled(palette.background.strong),
-        Status::Hovered => styled(palette.background.weakest),
+        Status::Default => style,
+        Status::Selected => styled(style, palette.background.strong),
+        Status::Hovered => styled(style, palette.background.weakest),
     }
 }
 
-fn styled(pair: palette::Pair) -> Style {
-    Style {
-        background: pair.color,
-    }
+fn styled(style: Style, pair: palette::Pair) -> Style {
+    style.with_background(pair.color)
 }
@@ -36,11 +36,11 @@ impl Default for MenuStyle {
 impl MenuStyle {
     pub fn dark() -> Self {
         Self {
-            background_color: Color::from_rgb(0.2, 0.2, 0.3),
-            hover_color: Color::from_rgb(0.3, 0.3, 0.4),
-            text_color: Color::WHITE,
+            background_color: Color::from_rgb8(0x27, 0x27, 0x2A),
+            hover_color: Color::from_rgb8(0x3F, 0x3F, 0x46),
+            text_color: Color::from_rgb8(0xF4, 0xF4, 0xF5),
             border: Border {
-                color: Color::from_rgb(0.5, 0.5, 0.6),
+                color: Color::from_rgb8(0x52, 0x52, 0x5C),
                 width: 1.0,
                 radius: 4.0.into(),
             },
@@ -3,3 +3,5 @@ pub mod column;
 pub mod compact_row;
 pub mod container;
 pub mod context_menu;
+pub mod stars;
+pub mod waveform;
@@ -0,0 +1,24 @@
+use crate::{fonts::ICON, icons::ICON_STAR, storage::MAX_RATING};
+use iced::widget::{Row, mouse_area, text};
+
+// a rating as clickable stars, clicking the highest lit star clears the rating
+pub fn stars<'a, Message: Clone + 'a>(
+    rating: u8,
+    size: f32,
+    on_rate: impl Fn(u8) -> Message,
+) -> Row<'a, Message> {
+    Row::with_children((1..=MAX_RATING).map(|stars| {
+        let star = text(ICON_STAR)
+            .font(ICON)
+            .size(size)
+            .style(if stars <= rating {
+                text::primary
+            } else {
+                text::secondary
+            });
+        mouse_area(star)
+            .on_press(on_rate(if stars == rating { 0 } else { stars }))
+            .into()
+    }))
+    .spacing(2)
+}
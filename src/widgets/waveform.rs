@@ -0,0 +1,187 @@
+use iced::{
+    Background, Border, Element, Event, Length, Rectangle, Shadow, Size, Theme,
+    advanced::{
+        Clipboard, Layout, Shell, Widget,
+        layout::{Limits, Node},
+        mouse, renderer,
+        widget::Tree,
+    },
+};
+
+use crate::meter::meter_fraction;
+
+/// Levels of a recording drawn as bars, with a marker at each split point. Clicking reports the
+/// position under the cursor in milliseconds.
+pub struct Waveform<'a, Message> {
+    levels: &'a [f32],
+    duration_ms: u64,
+    splits: &'a [u64],
+    // index into `splits` drawn highlighted
+    selected: Option<usize>,
+    height: f32,
+    on_click: Option<Box<dyn Fn(u64) -> Message + 'a>>,
+}
+
+impl<'a, Message> Waveform<'a, Message> {
+    pub fn new(levels: &'a [f32], duration_ms: u64, splits: &'a [u64]) -> Self {
+        Self {
+            levels,
+            duration_ms,
+            splits,
+            selected: None,
+            height: 120.0,
+            on_click: None,
+        }
+    }
+
+    pub fn selected(mut self, selected: Option<usize>) -> Self {
+        self.selected = selected;
+        self
+    }
+
+    pub fn height(mut self, height: f32) -> Self {
+        self.height = height;
+        self
+    }
+
+    pub fn on_click<F>(mut self, on_click: F) -> Self
+    where
+        F: Fn(u64) -> Message + 'a,
+    {
+        self.on_click = Some(Box::new(on_click));
+        self
+    }
+}
+
+impl<Message, Renderer> Widget<Message, Theme, Renderer> for Waveform<'_, Message>
+where
+    Renderer: renderer::Renderer,
+{
+    fn size(&self) -> Size<Length> {
+        Size {
+            width: Length::Fill,
+            height: Length::Fixed(self.height),
+        }
+    }
+
+    fn layout(&mut self, _tree: &mut Tree, _renderer: &Renderer, limits: &Limits) -> Node {
+        Node::new(limits.resolve(Length::Fill, Length::Fixed(self.height), Size::ZERO))
+    }
+
+    fn draw(
+        &self,
+        _tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        _style: &renderer::Style,
+        layout: Layout<'_>,
+        _cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+    ) {
+        let bounds = layout.bounds();
+        let palette = theme.extended_palette();
+        let quad = |bounds| renderer::Quad {
+            bounds,
+            border: Border::default(),
+            shadow: Shadow::default(),
+            snap: true,
+        };
+        renderer.fill_quad(
+            quad(bounds),
+            Background::Color(palette.background.weak.color),
+        );
+
+        // one bar per pixel column, as loud as the loudest level it covers
+        let columns = bounds.width.floor().max(1.0) as usize;
+        if !self.levels.is_empty() {
+            let per_column = self.levels.len() as f32 / columns as f32;
+            for column in 0..columns {
+                let start = (column as f32 * per_column) as usize;
+                let end = (((column + 1) as f32 * per_column) as usize)
+                    .max(start + 1)
+                    .min(self.levels.len());
+                let level = self.levels[start..end].iter().copied().fold(0.0, f32::max);
+                let height = meter_fraction(level) * bounds.height;
+                renderer.fill_quad(
+                    quad(Rectangle {
+                        x: bounds.x + column as f32,
+                        y: bounds.y + (bounds.height - height) / 2.0,
+                        width: 1.0,
+                        height,
+                    }),
+                    Background::Color(palette.primary.base.color),
+                );
+            }
+        }
+
+        if self.duration_ms == 0 {
+            return;
+        }
+        for (index, split) in self.splits.iter().enumerate() {
+            let x = bounds.x + bounds.width * (*split as f32 / self.duration_ms as f32);
+            let color = if self.selected == Some(index) {
+                palette.danger.base.color
+            } else {
+                palette.background.strong.text
+            };
+            renderer.fill_quad(
+                quad(Rectangle {
+                    x: x - 1.0,
+                    y: bounds.y,
+                    width: 2.0,
+                    height: bounds.height,
+                }),
+                Background::Color(color),
+            );
+        }
+    }
+
+    fn update(
+        &mut self,
+        _tree: &mut Tree,
+        event: &Event,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _renderer: &Renderer,
+        _clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        _viewport: &Rectangle,
+    ) {
+        let Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) = event else {
+            return;
+        };
+        let bounds = layout.bounds();
+        if let Some(on_click) = &self.on_click
+            && let Some(position) = cursor.position_in(bounds)
+        {
+            let fraction = (position.x / bounds.width).clamp(0.0, 1.0);
+            shell.publish(on_click((fraction * self.duration_ms as f32) as u64));
+            shell.capture_event();
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        _tree: &Tree,
+        layout: Layout<'_>,
+        cursor: mouse::Cursor,
+        _viewport: &Rectangle,
+        _renderer: &Renderer,
+    ) -> mouse::Interaction {
+        if self.on_click.is_some() && cursor.is_over(layout.bounds()) {
+            mouse::Interaction::Crosshair
+        } else {
+            mouse::Interaction::default()
+        }
+    }
+}
+
+impl<'a, Message, Renderer> From<Waveform<'a, Message>> for Element<'a, Message, Theme, Renderer>
+where
+    Message: 'a,
+    Renderer: 'a + renderer::Renderer,
+{
+    fn from(value: Waveform<'a, Message>) -> Self {
+        Self::new(value)
+    }
+}